@@ -0,0 +1,71 @@
+//! Talk features the server supports, so the UI can leave out the ones it does not.
+use std::collections::HashSet;
+
+use super::{chat_types::ChatFeature, nc_request::NCReqDataCapabilities};
+
+/// Name the server announces the feature as.
+fn talk_feature_name(feature: ChatFeature) -> &'static str {
+    match feature {
+        ChatFeature::Reactions => "reactions",
+        ChatFeature::EditMessages => "edit-messages",
+        ChatFeature::Reminders => "remind-me-later",
+        ChatFeature::PinnedMessages => "pinned-messages",
+    }
+}
+
+/// Features announced by the server.
+///
+/// While they are unknown, e.g. if fetching them failed, every feature is assumed to be supported.
+#[derive(Debug, Default, Clone)]
+pub struct Capabilities {
+    features: Option<HashSet<String>>,
+    version: Option<String>,
+}
+
+impl From<NCReqDataCapabilities> for Capabilities {
+    fn from(data: NCReqDataCapabilities) -> Self {
+        let talk = data.capabilities.spreed.unwrap_or_default();
+        Capabilities {
+            features: Some(talk.features.into_iter().collect()),
+            version: talk.version,
+        }
+    }
+}
+
+impl Capabilities {
+    pub fn supports(&self, feature: ChatFeature) -> bool {
+        self.features
+            .as_ref()
+            .is_none_or(|features| features.contains(talk_feature_name(feature)))
+    }
+
+    /// Talk version of the server, if it announced one.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supports() {
+        assert!(Capabilities::default().supports(ChatFeature::EditMessages));
+
+        let data: NCReqDataCapabilities = serde_json::from_str(
+            r#"{"capabilities": {"spreed": {"features": ["reactions", "remind-me-later"], "version": "17.1.0"}, "core": {}}}"#,
+        )
+        .unwrap();
+        let capabilities = Capabilities::from(data);
+        assert!(capabilities.supports(ChatFeature::Reactions));
+        assert!(capabilities.supports(ChatFeature::Reminders));
+        assert!(!capabilities.supports(ChatFeature::EditMessages));
+        assert!(!capabilities.supports(ChatFeature::PinnedMessages));
+        assert_eq!(capabilities.version(), Some("17.1.0"));
+
+        let data: NCReqDataCapabilities =
+            serde_json::from_str(r#"{"capabilities": {"core": {}}}"#).unwrap();
+        assert!(!Capabilities::from(data).supports(ChatFeature::Reactions));
+    }
+}
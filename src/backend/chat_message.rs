@@ -0,0 +1,212 @@
+use super::nc_request::{
+    NCReqDataMessage, NCReqDataMessageParameter, NCReqDataMessageSystemMessage,
+};
+use chrono::prelude::*;
+use itertools::Itertools;
+
+/// Message of a room, the UI only reads it through the getters.
+/// Providers build it from the NC Talk message data, which stays hidden in the backend.
+#[derive(Debug, Default, Clone)]
+pub struct ChatMessage(NCReqDataMessage);
+
+impl From<NCReqDataMessage> for ChatMessage {
+    fn from(data: NCReqDataMessage) -> Self {
+        ChatMessage(data)
+    }
+}
+
+impl ChatMessage {
+    /// return message time stamp as string
+    pub fn get_time_str(&self) -> String {
+        let time: DateTime<Local> = DateTime::from(
+            DateTime::<Utc>::from_timestamp(self.0.timestamp, 0)
+                .expect("cannot convert UTC time stamp"),
+        );
+        time.format("%H:%M").to_string()
+    }
+
+    /// return message date as string with given format
+    pub fn get_date_str(&self, date_format: &str) -> String {
+        let date: DateTime<Local> = DateTime::from(
+            DateTime::<Utc>::from_timestamp(self.0.timestamp, 0)
+                .expect("cannot convert UTC time stamp"),
+        );
+        date.format(date_format).to_string()
+    }
+
+    /// return opponent display name
+    pub fn get_name(&self) -> &str {
+        if !self.is_comment() || self.is_system() || self.is_comment_deleted() || self.is_command()
+        {
+            "System"
+        } else {
+            &self.0.actorDisplayName
+        }
+    }
+
+    /// return the id of the author, stable unlike the display name
+    pub fn get_actor_id(&self) -> &str {
+        &self.0.actorId
+    }
+
+    /// return the message itself
+    pub fn get_message(&self) -> &str {
+        &self.0.message
+    }
+
+    /// get list of reactions as comma separated string
+    pub fn get_reactions_str(&self) -> String {
+        self.0
+            .reactions
+            .iter()
+            .map(|(icon, number)| format!("('{icon}' times {}), ", &number.to_string()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// get message identifier
+    pub fn get_id(&self) -> i32 {
+        self.0.id
+    }
+
+    /// return message time stamp in seconds since the epoch
+    pub fn get_timestamp(&self) -> i64 {
+        self.0.timestamp
+    }
+
+    /// return `true` if the message was written by the user with the given id
+    pub fn is_from_user(&self, user_id: &str) -> bool {
+        self.0.actorType == "users" && self.0.actorId == user_id
+    }
+
+    /// return `true` if the server allows replying to this message
+    pub fn is_replyable(&self) -> bool {
+        self.0.isReplyable
+    }
+
+    /// get id of the message this one replies to
+    pub fn get_parent_id(&self) -> Option<i32> {
+        Some(self.0.parent.id).filter(|id| *id > 0)
+    }
+
+    /// get reactions with their count, sorted by the reaction
+    pub fn get_reactions(&self) -> Vec<(&str, i32)> {
+        self.0
+            .reactions
+            .iter()
+            .map(|(reaction, count)| (reaction.as_str(), *count))
+            .sorted()
+            .collect()
+    }
+
+    /// get reactions added by the current user
+    pub fn get_own_reactions(&self) -> &[String] {
+        &self.0.reactionsSelf
+    }
+
+    /// replace the message text, e.g. after it was edited
+    pub fn set_message(&mut self, message: &str) {
+        self.0.message = message.to_string();
+    }
+
+    /// count a reaction of the current user
+    pub fn add_own_reaction(&mut self, reaction: &str) {
+        if !self.0.reactionsSelf.iter().any(|own| own == reaction) {
+            *self.0.reactions.entry(reaction.to_string()).or_insert(0) += 1;
+            self.0.reactionsSelf.push(reaction.to_string());
+        }
+    }
+
+    /// take back a reaction of the current user
+    pub fn remove_own_reaction(&mut self, reaction: &str) {
+        if let Some(position) = self.0.reactionsSelf.iter().position(|own| own == reaction) {
+            self.0.reactionsSelf.remove(position);
+            if let Some(count) = self.0.reactions.get_mut(reaction) {
+                *count -= 1;
+                if *count <= 0 {
+                    self.0.reactions.remove(reaction);
+                }
+            }
+        }
+    }
+
+    /// turn this message into a deleted comment
+    pub fn mark_deleted(&mut self) {
+        self.0.messageType = "comment_deleted".to_string();
+    }
+
+    /// return inner data message
+    pub(super) fn data(&self) -> &NCReqDataMessage {
+        &self.0
+    }
+
+    /// return `true` if message is a comment
+    pub fn is_comment(&self) -> bool {
+        self.0.messageType == "comment"
+    }
+
+    /// return `true` if message is a deleted comment
+    pub fn is_comment_deleted(&self) -> bool {
+        self.0.messageType == "comment_deleted"
+    }
+
+    /// return `true` if message is a system message
+    pub fn is_system(&self) -> bool {
+        self.0.messageType == "system"
+    }
+
+    /// return `true` if message is an edited message
+    pub fn is_edit_note(&self) -> bool {
+        self.is_system() && self.0.systemMessage == NCReqDataMessageSystemMessage::MessageEdited
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.is_system()
+            && (self.0.systemMessage == NCReqDataMessageSystemMessage::MessageDeleted
+                || self.0.systemMessage == NCReqDataMessageSystemMessage::ReactionRevoked
+                || self.0.systemMessage == NCReqDataMessageSystemMessage::ReactionDeleted)
+    }
+
+    /// return `true` if message is a reaction
+    pub fn is_reaction(&self) -> bool {
+        self.is_system() && self.0.systemMessage == NCReqDataMessageSystemMessage::Reaction
+    }
+
+    /// return the id of the user added to the room, if this is the note about it
+    pub fn get_added_user(&self) -> Option<&str> {
+        if self.is_system() && self.0.systemMessage == NCReqDataMessageSystemMessage::UserAdded {
+            self.0
+                .messageParameters
+                .get("user")
+                .map(NCReqDataMessageParameter::id)
+        } else {
+            None
+        }
+    }
+
+    /// return `true` if the message mentions the user or everyone in the room
+    pub fn mentions(&self, user_id: &str) -> bool {
+        self.is_comment()
+            && self
+                .0
+                .messageParameters
+                .iter()
+                .any(|(key, parameter)| key.starts_with("mention-") && parameter.mentions(user_id))
+    }
+
+    /// return `true` if message is the note about the creation of the room
+    pub fn is_room_creation(&self) -> bool {
+        self.is_system()
+            && self.0.systemMessage == NCReqDataMessageSystemMessage::ConversationCreated
+    }
+
+    /// return `true` if message is a command
+    pub fn is_command(&self) -> bool {
+        self.0.messageType == "command"
+    }
+
+    /// return `true` if message has any reactions
+    pub fn has_reactions(&self) -> bool {
+        !self.0.reactions.is_empty()
+    }
+}
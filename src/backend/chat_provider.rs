@@ -0,0 +1,313 @@
+//! Provider agnostic interface of a chat backend.
+//!
+//! [`ChatProvider`] is what the UI talks to, [`NCTalk`] is the Nextcloud Talk implementation.
+//!
+//! [`NCTalk`]: super::nc_talk::NCTalk
+
+use super::{
+    chat_message::ChatMessage,
+    chat_room::ChatRoom,
+    chat_types::{ChatFeature, FoundUser, ListedRoom, OutOfOffice, Token, UserStatus},
+    history_fetch::HistoryProgress,
+    request_metrics::MetricsSnapshot,
+};
+use async_trait::async_trait;
+use itertools::Itertools;
+use std::{error::Error, fmt::Debug, path::PathBuf, time::Duration};
+
+/// Update the backend received without being asked, see [`ChatProvider::next_update`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendUpdate {
+    /// Rooms with messages pushed by the long poll, with display name and count if they are unread.
+    Pushed(Vec<(Token, Option<(String, usize)>)>),
+    /// A chunk of the fetched history was added, `None` once the fetch is finished.
+    History(Option<HistoryProgress>),
+}
+
+/// Rooms which could not be updated, while all other rooms were.
+#[derive(Debug)]
+pub struct RoomUpdateError {
+    /// Display name of every failed room with the reason.
+    pub failed: Vec<(String, String)>,
+    /// Display names of the rooms added by the same update, to announce them anyway.
+    pub new_rooms: Vec<String>,
+}
+
+impl std::fmt::Display for RoomUpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rooms = self
+            .failed
+            .iter()
+            .map(|(name, why)| format!("{name} ({why})"))
+            .join(", ");
+        write!(f, "Could not update {rooms}")
+    }
+}
+
+impl Error for RoomUpdateError {}
+
+/// Public Trait for a chat server instance, used by the UI for all interaction with the server.
+///
+/// The UI only depends on this trait, so other providers than [`NCTalk`] can be plugged in.
+/// This trait is also needed due to the use of the [mockall](https://crates.io/crates/mockall) crate in testing.
+/// See [`NCTalk`] for more details on the functionality.
+///
+/// [`NCTalk`]: super::nc_talk::NCTalk
+#[async_trait]
+pub trait ChatProvider: Debug + Send {
+    /// Type for Rooms used in Backend.
+    /// Since this ether can be a mock or a real room, it need to be named here.
+    type Room: ChatRoom;
+    /// Write all log files for this NC Instance to disk.
+    /// # Errors
+    /// Folder might not exists/be writable or not have space.
+    fn write_to_log(&mut self) -> Result<(), std::io::Error>;
+    /// Get a Room ref for a given Token.
+    fn get_room(&self, token: &Token) -> &Self::Room;
+    /// Get a list of tokens of rooms with unread messages, leaving out archived rooms.
+    fn get_unread_rooms(&self) -> Vec<Token>;
+    /// Get the rooms muted only in this client, see [`LocalMutes`](crate::backend::local_mutes::LocalMutes).
+    fn get_locally_muted_rooms(&self) -> Vec<Token>;
+    /// Check if the room identified by the Token is muted only in this client.
+    fn is_locally_muted(&self, token: &Token) -> bool;
+    /// Mute or unmute the room identified by the Token only in this client, persisting the change.
+    fn set_locally_muted(&mut self, token: &Token, muted: bool) -> Result<(), std::io::Error>;
+    /// Get a list of tokens of favorite rooms, leaving out archived rooms.
+    fn get_favorite_rooms(&self) -> Vec<Token>;
+    /// Get a list of tokens of archived rooms.
+    fn get_archived_rooms(&self) -> Vec<Token>;
+    /// Get a room token by its Displayname.
+    fn get_room_by_displayname(&self, name: &str) -> Token;
+    /// Get the token of the one to one room with the user, if there is one.
+    fn get_dm_room(&self, user_id: &str) -> Option<Token>;
+    /// Get a list of direct messages rooms as token, displayname pairs, leaving out archived rooms.
+    fn get_dm_keys_display_name_mapping(&self) -> Vec<(Token, String)>;
+    /// Get a list of group messages rooms as token, displayname pairs, leaving out archived rooms.
+    /// Breakout rooms are left out as well, if their parent room is known.
+    fn get_group_keys_display_name_mapping(&self) -> Vec<(Token, String)>;
+    /// Get the breakout rooms of the room identified by the Token as token, displayname pairs.
+    fn get_breakout_rooms(&self, token: &Token) -> Vec<(Token, String)>;
+    /// Get a list of all Room Token.
+    fn get_room_keys(&self) -> Vec<&'_ Token>;
+    /// Get the link under which others can join the room identified by the Token.
+    fn get_room_link(&self, token: &Token) -> String;
+    /// Resolve a permalink to a message of a known room on this server into the room Token and message id.
+    fn resolve_message_link(&self, link: &str) -> Option<(Token, i32)>;
+    /// Check if the server supports an optional feature, assumed if unknown.
+    fn supports(&self, feature: ChatFeature) -> bool;
+    /// What the user can do about an error of this provider, if anything.
+    fn error_hint(&self, _why: &(dyn Error + 'static)) -> Option<&'static str> {
+        None
+    }
+    /// Send a Message to the current selected room.
+    async fn send_message(
+        &mut self,
+        message: String,
+        token: &Token,
+    ) -> Result<Option<(String, usize)>, Box<dyn Error>>;
+    /// Select a Room by a given Token as the current Room.
+    async fn select_room(
+        &mut self,
+        token: &Token,
+    ) -> Result<Option<(String, usize)>, Box<dyn Error>>;
+    /// Long poll the Room identified by the Token for new messages, instead of the one watched before.
+    /// Only this Room is long polled, as every poll holds a connection to the server open,
+    /// the other Rooms get their new messages by [`ChatProvider::update_rooms`].
+    async fn watch_room(&mut self, token: &Token);
+    /// Wait for messages pushed by the long poll of the watched Room or fetched as history and add them.
+    /// Never returns for providers without long polling, while no history is fetched.
+    async fn next_update(&mut self) -> BackendUpdate;
+    /// Check with the Server for all Rooms if updates happened.
+    /// ```force_update``` will force the currently stored Room data to be overwritten.
+    /// Returns the names of the added rooms, or a [`RoomUpdateError`] if only some rooms failed.
+    async fn update_rooms(&mut self, force_update: bool) -> Result<Vec<String>, Box<dyn Error>>;
+    /// Mark the room identified by the Token as read.
+    /// Does not need to be the current Room, but usually is.
+    async fn mark_current_room_as_read(
+        &self,
+        token: &Token,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Mark all rooms as read, goes over list of unread rooms.
+    async fn mark_all_rooms_as_read(&self) -> Result<(), Box<dyn std::error::Error>>;
+    /// Start fetching a rooms full history in the background, replacing a running fetch.
+    /// The fetched chunks arrive as [`BackendUpdate::History`].
+    async fn fetch_room_history(&mut self, token: &Token) -> Result<(), Box<dyn Error>>;
+    /// Progress of the running history fetch, if any.
+    fn get_history_progress(&self) -> Option<HistoryProgress>;
+    /// Time left until the server accepts requests again, if it limits the rate.
+    fn get_rate_limit(&self) -> Option<Duration>;
+    /// True while the server cannot be reached and only cached rooms are shown read-only.
+    fn is_offline(&self) -> bool;
+    /// Files the responses were dumped to which could not be parsed since the last call.
+    fn take_request_dumps(&mut self) -> Vec<PathBuf>;
+    /// Counters of the requests to the server, `None` if the provider keeps none.
+    fn get_metrics(&self) -> Option<MetricsSnapshot>;
+    /// Get all messages of the room kept in the local store, the oldest first.
+    fn get_stored_messages(&self, token: &Token) -> Result<Vec<ChatMessage>, Box<dyn Error>>;
+    /// Fetch a page of messages older than the ones loaded, returns how many were added.
+    async fn fetch_older_messages(&mut self, token: &Token) -> Result<usize, Box<dyn Error>>;
+    /// Add or remove the room identified by the Token from the favorites.
+    async fn set_room_favorite(&self, token: &Token, favorite: bool) -> Result<(), Box<dyn Error>>;
+    /// Turn server side notifications of the room identified by the Token on or off.
+    async fn set_room_muted(&self, token: &Token, muted: bool) -> Result<(), Box<dyn Error>>;
+    /// Archive or unarchive the room identified by the Token.
+    async fn set_room_archived(&self, token: &Token, archived: bool) -> Result<(), Box<dyn Error>>;
+    /// Search users on the server which could be added to a room.
+    async fn search_users(&self, search: &str) -> Result<Vec<FoundUser>, Box<dyn Error>>;
+    /// Search listable rooms on the server the user is not a member of yet.
+    async fn search_listed_rooms(&self, search: &str) -> Result<Vec<ListedRoom>, Box<dyn Error>>;
+    /// Join a session in the room identified by the Token, adding it to the known rooms if needed.
+    async fn join_room(&mut self, token: &Token) -> Result<(), Box<dyn Error>>;
+    /// Leave the session joined in the room, if any, staying a participant of it.
+    async fn leave_room(&mut self, token: &Token) -> Result<(), Box<dyn Error>>;
+    /// Get the one to one room with the user, creating it if there is none yet.
+    async fn open_dm(&mut self, user_id: &str) -> Result<Token, Box<dyn Error>>;
+    /// Keep the read status of the user private, which also hides the read status of others.
+    async fn set_read_status_private(&self, private: bool) -> Result<(), Box<dyn Error>>;
+    /// Get the status of the user like `online` or `dnd`, with its message and icon.
+    async fn get_own_status(&self) -> Result<UserStatus, Box<dyn Error>>;
+    /// Get the absence of the user with the given id if they are out of office right now.
+    async fn get_out_of_office(&self, user_id: &str)
+        -> Result<Option<OutOfOffice>, Box<dyn Error>>;
+    /// Set the status of the user, one of `online`, `away`, `dnd` or `invisible`.
+    async fn set_own_status(&self, status: &str) -> Result<(), Box<dyn Error>>;
+    /// Set the custom status message with an optional emoji, an empty message without one clears it.
+    async fn set_own_status_message(
+        &self,
+        message: &str,
+        icon: Option<String>,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Upload the data as a file called `name` and share it into the room identified by the Token.
+    async fn share_file(
+        &self,
+        token: &Token,
+        name: &str,
+        data: Vec<u8>,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Add the user with the given id to the room identified by the Token.
+    async fn add_participant(&mut self, token: &Token, user_id: &str)
+        -> Result<(), Box<dyn Error>>;
+    /// Fetch the participants of the room identified by the Token again, for their current status.
+    async fn refresh_participants(&mut self, token: &Token) -> Result<(), Box<dyn Error>>;
+    /// Remove the attendee with the given id from the room identified by the Token.
+    async fn remove_participant(
+        &mut self,
+        token: &Token,
+        attendee_id: i32,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Promote the attendee with the given id to moderator, or demote them.
+    async fn set_participant_moderator(
+        &mut self,
+        token: &Token,
+        attendee_id: i32,
+        moderator: bool,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Rename the room identified by the Token.
+    async fn set_room_name(&mut self, token: &Token, name: &str) -> Result<(), Box<dyn Error>>;
+    /// Change the description of the room identified by the Token.
+    async fn set_room_description(
+        &mut self,
+        token: &Token,
+        description: &str,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Ban the attendee with the given id from the room identified by the Token.
+    async fn ban_participant(
+        &mut self,
+        token: &Token,
+        attendee_id: i32,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Send a Message to the room identified by the Token as reply to the message with the given id.
+    async fn send_reply(
+        &mut self,
+        message: String,
+        token: &Token,
+        reply_to: i32,
+    ) -> Result<Option<(String, usize)>, Box<dyn Error>>;
+    /// Replace the text of a message in the room identified by the Token.
+    async fn edit_message(
+        &mut self,
+        token: &Token,
+        message_id: i32,
+        message: &str,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Delete a message in the room identified by the Token.
+    async fn delete_message(
+        &mut self,
+        token: &Token,
+        message_id: i32,
+    ) -> Result<(), Box<dyn Error>>;
+    /// React to a message in the room identified by the Token.
+    async fn react_to_message(
+        &mut self,
+        token: &Token,
+        message_id: i32,
+        reaction: &str,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Take back an own reaction to a message in the room identified by the Token.
+    async fn remove_reaction(
+        &mut self,
+        token: &Token,
+        message_id: i32,
+        reaction: &str,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Get reminded of a message in the room identified by the Token at the given unix timestamp.
+    async fn set_message_reminder(
+        &self,
+        token: &Token,
+        message_id: i32,
+        timestamp: i64,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Pin a message in the room identified by the Token.
+    async fn pin_message(&self, token: &Token, message_id: i32) -> Result<(), Box<dyn Error>>;
+    /// Set the password of the public room identified by the Token, an empty password removes it.
+    async fn set_room_password(
+        &mut self,
+        token: &Token,
+        password: &str,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Restrict mentioning @all in the room identified by the Token to moderators, or allow it for everyone.
+    async fn set_mention_all_restricted(
+        &mut self,
+        token: &Token,
+        restricted: bool,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Let messages in the room identified by the Token expire after the given seconds, 0 keeps them.
+    async fn set_message_expiration(
+        &mut self,
+        token: &Token,
+        seconds: i32,
+    ) -> Result<(), Box<dyn Error>>;
+    /// trigger for all threads to be killed.
+    async fn shutdown(&self) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/// Find the room by its display name or token, unlike [`ChatProvider::get_room_by_displayname`]
+/// without falling back to another room.
+pub fn find_room(provider: &impl ChatProvider, room: &str) -> Option<Token> {
+    provider
+        .get_room_keys()
+        .into_iter()
+        .find(|token| *token == room || provider.get_room(token).get_display_name() == room)
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{chat_room::MockChatRoom, nc_talk::MockNCTalk};
+
+    #[test]
+    fn find_by_name_or_token() {
+        let mut backend = MockNCTalk::new();
+        let token: &'static Token = Box::leak(Box::new("abc".to_string()));
+        backend.expect_get_room_keys().return_const(vec![token]);
+        let mut room = MockChatRoom::new();
+        room.expect_get_display_name()
+            .return_const("General".to_string());
+        backend.expect_get_room().return_const(room);
+
+        assert_eq!(find_room(&backend, "General"), Some(token.clone()));
+        assert_eq!(find_room(&backend, "abc"), Some(token.clone()));
+        assert_eq!(find_room(&backend, "Other"), None);
+    }
+}
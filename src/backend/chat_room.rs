@@ -0,0 +1,134 @@
+//! Provider agnostic interface of a chat room.
+//!
+//! [`ChatRoom`] is what the UI reads rooms through, [`NCRoom`] is the Nextcloud Talk implementation.
+//!
+//! [`NCRoom`]: super::nc_room::NCRoom
+
+use super::{
+    chat_message::ChatMessage,
+    chat_types::{Participant, ParticipantChange, RoomType, Token},
+};
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Display},
+};
+
+#[cfg(test)]
+use mockall::{automock, predicate::*};
+
+/// Public Trait for a room of a chat server, used by the UI to show rooms of any [`ChatProvider`].
+///
+/// Operations changing a room go through the [`ChatProvider`], as they depend on the provider.
+///
+/// [`ChatProvider`]: super::ChatProvider
+#[cfg_attr(test, automock)]
+pub trait ChatRoom: Debug + Send + Display + Ord + Default {
+    /// Get the ID of the last message of this Room.
+    /// This is filtered to not include reactions and deleted messages.
+    fn get_last_room_level_message_id(&self) -> Option<i32>;
+    /// Check if this Room has unread messages.
+    fn has_unread(&self) -> bool;
+    /// Check if this Room is a DM Room.
+    #[allow(dead_code)]
+    fn is_dm(&self) -> bool;
+    /// Check if this Room is a Group Chat.
+    fn is_group(&self) -> bool;
+    /// Check if this Room is public, so it can be joined by link.
+    fn is_public(&self) -> bool;
+    /// Check if this Room is protected by a password.
+    fn has_password(&self) -> bool;
+    /// Get a Vector of all the messages in the room.
+    fn get_messages(&self) -> &BTreeMap<i32, ChatMessage>;
+    /// Get how many messages are unread.
+    fn get_unread(&self) -> usize;
+    /// Check if this Room has unread messages mentioning the user.
+    fn has_unread_mention(&self) -> bool;
+    /// Check if this Room is a favorite.
+    fn is_favorite(&self) -> bool;
+    /// Check if notifications for this Room are turned off on the server.
+    fn is_muted(&self) -> bool;
+    /// Check if this Room is archived.
+    fn is_archived(&self) -> bool;
+    /// Check if the user is owner or moderator of this Room.
+    fn is_moderator(&self) -> bool;
+    /// Get the token of the parent Room, if this is a breakout room.
+    fn get_parent_room(&self) -> Option<Token>;
+    /// Check if a call is running in this Room.
+    fn has_call(&self) -> bool;
+    /// Get how many participants joined the call of this Room.
+    fn get_users_in_call(&self) -> usize;
+    /// Check if the lobby of this Room keeps the user out, as it only lets moderators in.
+    fn is_lobby_closed(&self) -> bool;
+    /// Get the unix timestamp the lobby opens at, 0 if it is opened by hand.
+    fn get_lobby_timer(&self) -> i32;
+    /// Get the unix timestamp of the last activity in this Room.
+    fn get_last_activity(&self) -> i32;
+    /// Name of whoever added `user_id` to this room, see [`added_by`](super::nc_room::added_by).
+    fn get_added_by(&self, user_id: &str) -> Option<String>;
+    /// Check if mentioning @all in this Room is restricted to moderators.
+    fn is_mention_all_restricted(&self) -> bool;
+    /// Get after how many seconds messages in this Room expire, 0 if they are kept.
+    fn get_message_expiration(&self) -> i32;
+    /// Check if the user only previews this Room, as it needs joining a session before writing.
+    /// This is the case for self joined public rooms and the sample conversation.
+    fn is_previewing(&self) -> bool;
+    /// Get the human readable display name of the room.
+    fn get_display_name(&self) -> &str;
+    /// Get the internal name of the room, the user id of the other user in one to one rooms.
+    fn get_name(&self) -> &str;
+    /// Get the description of the room.
+    fn get_description(&self) -> &str;
+    /// Get the if of the last read messages.
+    fn get_last_read(&self) -> i32;
+    /// Get the id of the last message read by all participants which share their read status.
+    fn get_last_common_read(&self) -> i32;
+    /// Get a Vector of the users in the Room.
+    fn get_users(&self) -> &Vec<Participant>;
+    /// Get the participants which joined or left since the room was loaded, oldest first.
+    fn get_participant_changes(&self) -> &Vec<ParticipantChange>;
+    /// Get the unix timestamp this Room was last synced with the server, 0 if never.
+    fn get_last_synced_at(&self) -> i64;
+    /// Get the room type.
+    fn get_room_type(&self) -> &RoomType;
+    /// Search the stored messages of this room for `text`, newest first.
+    fn search_messages(&self, text: &str) -> Result<Vec<ChatMessage>, Box<dyn std::error::Error>>;
+    /// Get the rooms token.
+    fn to_token(&self) -> Token;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    static BUTZ: &str = "Butz";
+    impl std::ops::Deref for MockChatRoom {
+        type Target = str;
+        fn deref(&self) -> &Self::Target {
+            BUTZ
+        }
+    }
+    impl Ord for MockChatRoom {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.to_string().cmp(&other.to_string())
+        }
+    }
+
+    impl PartialOrd for MockChatRoom {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl PartialEq for MockChatRoom {
+        fn eq(&self, other: &Self) -> bool {
+            self.to_string() == other.to_string()
+        }
+    }
+
+    impl Eq for MockChatRoom {}
+    impl std::fmt::Display for MockChatRoom {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let self_name = BUTZ.to_string();
+            write!(f, "{self_name}")
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Provider agnostic data types the UI gets from the [`ChatProvider`] and [`ChatRoom`] traits.
+//!
+//! Providers translate what their server sends into these, the NC Talk ones are in [`nc_room`].
+//!
+//! [`ChatProvider`]: super::ChatProvider
+//! [`ChatRoom`]: super::ChatRoom
+//! [`nc_room`]: super::nc_room
+
+use strum_macros::Display;
+
+/// Identifier of a room, unique for the server of the provider.
+pub type Token = String;
+
+/// Optional features of a chat server, which the UI leaves out if it does not support them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatFeature {
+    Reactions,
+    EditMessages,
+    Reminders,
+    PinnedMessages,
+}
+
+/// Different Types of rooms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Display)]
+pub enum RoomType {
+    /// DM
+    #[default]
+    #[strum(to_string = "Direct message")]
+    OneToOne,
+    /// Group Chat
+    Group,
+    /// Public Channel
+    Public,
+    /// Change Log of the server
+    #[strum(to_string = "Changelog")]
+    ChangeLog,
+    /// Old One to One
+    #[strum(to_string = "Direct message")]
+    Deprecated,
+    /// Talk to yourself
+    #[strum(to_string = "Note to self")]
+    NoteToSelf,
+}
+
+/// Role of a participant in a room.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Display)]
+pub enum ParticipantRole {
+    Owner,
+    Moderator,
+    #[default]
+    User,
+    Guest,
+    #[strum(to_string = "User by public link")]
+    SelfJoinedUser,
+    #[strum(to_string = "Guest moderator")]
+    GuestModerator,
+    Unknown,
+}
+
+impl ParticipantRole {
+    /// Check if the role owns the room, which no moderator can change.
+    pub fn is_owner(self) -> bool {
+        self == ParticipantRole::Owner
+    }
+
+    /// Check if the role has moderation rights.
+    pub fn is_moderator(self) -> bool {
+        matches!(
+            self,
+            ParticipantRole::Owner | ParticipantRole::Moderator | ParticipantRole::GuestModerator
+        )
+    }
+}
+
+/// Participant of a room.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Participant {
+    /// Id of the participation in the room, which moderation refers to.
+    pub attendee_id: i32,
+    /// User id of the participant, or the guest id for guests.
+    pub actor_id: String,
+    /// True for users of the server, which can be messaged directly.
+    pub is_user: bool,
+    pub display_name: String,
+    pub role: ParticipantRole,
+    pub in_call: bool,
+    /// Online status like `online` or `dnd`, `None` if the participant does not share it.
+    pub status: Option<String>,
+    pub status_icon: Option<String>,
+    pub status_message: Option<String>,
+    /// Unix timestamp the status message is cleared at.
+    pub status_clear_at: Option<i64>,
+}
+
+/// A participant joining or leaving a room, noticed when refreshing the participants.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParticipantChange {
+    pub timestamp: i64,
+    pub display_name: String,
+    pub joined: bool,
+}
+
+/// Status of the user like `online` or `dnd`, with its message and icon.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserStatus {
+    pub status: String,
+    pub message: Option<String>,
+    pub icon: Option<String>,
+}
+
+/// Absence of a user right now, as configured in their out of office settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutOfOffice {
+    /// Unix timestamp of the last day of the absence.
+    pub end_date: i64,
+    pub short_message: String,
+}
+
+/// User found by a search, which could be added to a room.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FoundUser {
+    pub id: String,
+    pub display_name: String,
+}
+
+/// Listable room found by a search, which the user could join.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListedRoom {
+    pub token: Token,
+    pub display_name: String,
+    pub description: String,
+}
@@ -0,0 +1,430 @@
+//! Export of the messages of a room into markdown, HTML or JSON files, or as JSON lines.
+use std::{
+    error::Error,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::Serialize;
+use strum_macros::Display;
+
+use super::{chat_message::ChatMessage, message_store::MessageStore};
+
+/// Format the messages are exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, clap::ValueEnum)]
+pub enum ExportFormat {
+    #[strum(to_string = "markdown")]
+    #[value(name = "md", alias = "markdown")]
+    Markdown,
+    #[strum(to_string = "HTML")]
+    Html,
+    #[strum(to_string = "JSON")]
+    Json,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// Reactions of the message like "👍 2, 🎉 1", empty without any.
+fn reactions_text(message: &ChatMessage) -> String {
+    message
+        .get_reactions()
+        .iter()
+        .map(|(reaction, count)| format!("{reaction} {count}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Escape the text to show it as is in HTML.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn export_markdown(room_name: &str, messages: &[&ChatMessage]) -> String {
+    let mut lines = vec![format!("# {room_name}")];
+    let mut last_date = String::new();
+    for message in messages {
+        let date = message.get_date_str("%Y-%m-%d");
+        if date != last_date {
+            lines.push(String::new());
+            lines.push(format!("## {date}"));
+            last_date = date;
+        }
+        lines.push(String::new());
+        lines.push(format!(
+            "**{}** ({})",
+            message.get_name(),
+            message.get_time_str()
+        ));
+        lines.push(message.get_message().to_string());
+        if message.has_reactions() {
+            lines.push(format!("_{}_", reactions_text(message)));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+fn export_html(room_name: &str, messages: &[&ChatMessage]) -> String {
+    let room_name = escape_html(room_name);
+    let mut lines = vec![
+        "<!DOCTYPE html>".to_string(),
+        "<html>".to_string(),
+        "<head>".to_string(),
+        "<meta charset=\"utf-8\">".to_string(),
+        format!("<title>{room_name}</title>"),
+        "</head>".to_string(),
+        "<body>".to_string(),
+        format!("<h1>{room_name}</h1>"),
+    ];
+    let mut last_date = String::new();
+    for message in messages {
+        let date = message.get_date_str("%Y-%m-%d");
+        if date != last_date {
+            lines.push(format!("<h2>{date}</h2>"));
+            last_date = date;
+        }
+        lines.push(format!(
+            "<p><strong>{}</strong> <time>{}</time><br>\n{}",
+            escape_html(message.get_name()),
+            message.get_time_str(),
+            escape_html(message.get_message()).replace('\n', "<br>\n")
+        ));
+        if message.has_reactions() {
+            lines.push(format!(
+                "<br><small>{}</small>",
+                escape_html(&reactions_text(message))
+            ));
+        }
+        lines.push("</p>".to_string());
+    }
+    lines.push("</body>".to_string());
+    lines.push("</html>".to_string());
+    lines.join("\n") + "\n"
+}
+
+/// Message as written into JSON exports.
+#[derive(Serialize)]
+struct ExportedMessage<'a> {
+    id: i32,
+    timestamp: i64,
+    author: &'a str,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to: Option<i32>,
+    reactions: Vec<(&'a str, i32)>,
+}
+
+impl<'a> From<&'a ChatMessage> for ExportedMessage<'a> {
+    fn from(message: &'a ChatMessage) -> Self {
+        ExportedMessage {
+            id: message.get_id(),
+            timestamp: message.get_timestamp(),
+            author: message.get_name(),
+            message: message.get_message(),
+            reply_to: message.get_parent_id(),
+            reactions: message.get_reactions(),
+        }
+    }
+}
+
+/// Render the messages of the room `room_name` in the given format.
+/// # Errors
+/// Fails only if the JSON serialization fails.
+pub fn export_messages(
+    room_name: &str,
+    messages: &[&ChatMessage],
+    format: ExportFormat,
+) -> Result<String, serde_json::Error> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(
+            &messages
+                .iter()
+                .map(|message| ExportedMessage::from(*message))
+                .collect::<Vec<_>>(),
+        ),
+        ExportFormat::Markdown => Ok(export_markdown(room_name, messages)),
+        ExportFormat::Html => Ok(export_html(room_name, messages)),
+    }
+}
+
+/// Name of the export file of `room_name`, made unique by the time of the export.
+pub fn export_file_name(room_name: &str, format: ExportFormat, now: DateTime<Local>) -> String {
+    let room: String = room_name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!(
+        "{room}-{}.{}",
+        now.format("%Y%m%d-%H%M%S"),
+        format.extension()
+    )
+}
+
+/// Write the export of the messages into a new file in `dir`, returning its path.
+/// # Errors
+/// The directory may not be writable.
+pub fn write_export(
+    dir: &Path,
+    room_name: &str,
+    messages: &[&ChatMessage],
+    format: ExportFormat,
+) -> Result<PathBuf, Box<dyn Error>> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(export_file_name(room_name, format, Local::now()));
+    std::fs::write(&path, export_messages(room_name, messages, format)?)?;
+    Ok(path)
+}
+
+/// Write the export of all messages stored of the room, found by its display name or token,
+/// into a new file in `dir`, returning its path.
+/// # Errors
+/// The room may be unknown, the store not readable or the directory not writable.
+pub fn export_stored_room(
+    store: &MessageStore,
+    room: &str,
+    dir: &Path,
+    format: ExportFormat,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let (room_name, messages) = load_stored_room(store, room)?;
+    write_export(
+        dir,
+        &room_name,
+        &messages.iter().collect::<Vec<_>>(),
+        format,
+    )
+}
+
+/// Write the stored messages of the room, found by its display name or token, to `out` as one
+/// JSON object per line. With `since` only the messages from the start of that local day on.
+/// Returns how many messages were written.
+/// # Errors
+/// The room may be unknown, the store not readable or `out` not writable.
+pub fn dump_stored_room(
+    store: &MessageStore,
+    room: &str,
+    since: Option<NaiveDate>,
+    out: &mut impl Write,
+) -> Result<usize, Box<dyn Error>> {
+    let start = since
+        .and_then(|date| {
+            date.and_hms_opt(0, 0, 0)?
+                .and_local_timezone(Local)
+                .earliest()
+        })
+        .map_or(i64::MIN, |start| start.timestamp());
+    let (_, messages) = load_stored_room(store, room)?;
+    let mut count = 0;
+    for message in messages
+        .iter()
+        .filter(|message| message.get_timestamp() >= start)
+    {
+        serde_json::to_writer(&mut *out, &ExportedMessage::from(message))?;
+        writeln!(out)?;
+        count += 1;
+    }
+    out.flush()?;
+    Ok(count)
+}
+
+/// Display name and all stored messages of the room, found by its display name or token.
+fn load_stored_room(
+    store: &MessageStore,
+    room: &str,
+) -> Result<(String, Vec<ChatMessage>), Box<dyn Error>> {
+    let room_data = store
+        .load_rooms()?
+        .into_values()
+        .find(|data| data.displayName == room || data.token == room)
+        .ok_or_else(|| format!("There is no stored room called {room}."))?;
+    let messages = store
+        .load_all(&room_data.token)?
+        .into_iter()
+        .map(ChatMessage::from)
+        .collect();
+    Ok((room_data.displayName, messages))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::nc_request::{NCReqDataMessage, NCReqDataRoom};
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+
+    fn messages() -> Vec<ChatMessage> {
+        vec![
+            ChatMessage::from(NCReqDataMessage {
+                id: 1,
+                messageType: "comment".to_string(),
+                actorDisplayName: "Butz".to_string(),
+                message: "Hello".to_string(),
+                ..Default::default()
+            }),
+            ChatMessage::from(NCReqDataMessage {
+                id: 2,
+                timestamp: 60,
+                messageType: "comment".to_string(),
+                actorDisplayName: "Bert".to_string(),
+                message: "Hi".to_string(),
+                reactions: HashMap::from([("👍".to_string(), 1)]),
+                ..Default::default()
+            }),
+        ]
+    }
+
+    #[test]
+    fn markdown() {
+        let messages = messages();
+        let text = export_messages(
+            "General",
+            &messages.iter().collect::<Vec<_>>(),
+            ExportFormat::Markdown,
+        )
+        .unwrap();
+        assert_eq!(
+            text,
+            format!(
+                "# General\n\n## {}\n\n**Butz** ({})\nHello\n\n**Bert** ({})\nHi\n_👍 1_\n",
+                messages[0].get_date_str("%Y-%m-%d"),
+                messages[0].get_time_str(),
+                messages[1].get_time_str()
+            )
+        );
+    }
+
+    #[test]
+    fn html() {
+        let mut messages = messages();
+        messages.push(ChatMessage::from(NCReqDataMessage {
+            id: 3,
+            timestamp: 120,
+            messageType: "comment".to_string(),
+            actorDisplayName: "Butz".to_string(),
+            message: "<b>bold</b> & \"quoted\"\nsecond".to_string(),
+            ..Default::default()
+        }));
+        let text = export_messages(
+            "A & B",
+            &messages.iter().collect::<Vec<_>>(),
+            ExportFormat::Html,
+        )
+        .unwrap();
+        assert!(text.starts_with("<!DOCTYPE html>\n"));
+        assert!(text.contains("<h1>A &amp; B</h1>"));
+        assert_eq!(text.matches("<h2>").count(), 1);
+        assert!(text.contains(&format!(
+            "<p><strong>Bert</strong> <time>{}</time><br>\nHi\n<br><small>👍 1</small>\n</p>",
+            messages[1].get_time_str()
+        )));
+        assert!(text.contains("&lt;b&gt;bold&lt;/b&gt; &amp; &quot;quoted&quot;<br>\nsecond\n</p>"));
+        assert!(text.ends_with("</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn json() {
+        let messages = messages();
+        let text = export_messages(
+            "General",
+            &messages.iter().collect::<Vec<_>>(),
+            ExportFormat::Json,
+        )
+        .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(value[0]["author"], "Butz");
+        assert_eq!(value[0]["message"], "Hello");
+        assert_eq!(value[1]["timestamp"], 60);
+        assert_eq!(value[1]["reactions"][0], serde_json::json!(["👍", 1]));
+        assert!(value[1].get("reply_to").is_none());
+    }
+
+    #[test]
+    fn file_name() {
+        let now = Local.with_ymd_and_hms(2024, 5, 6, 7, 8, 9).unwrap();
+        assert_eq!(
+            export_file_name("Off Topic/1", ExportFormat::Markdown, now),
+            "Off_Topic_1-20240506-070809.md"
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let messages = messages();
+        let path = write_export(
+            dir.path(),
+            "General",
+            &messages.iter().collect::<Vec<_>>(),
+            ExportFormat::Json,
+        )
+        .unwrap();
+        assert!(path.starts_with(dir.path()));
+        assert!(std::fs::read_to_string(path).unwrap().contains("\"Hi\""));
+    }
+
+    #[test]
+    fn stored_room() {
+        let store = MessageStore::in_memory().unwrap();
+        let room = NCReqDataRoom {
+            token: "abc".to_string(),
+            displayName: "General".to_string(),
+            ..Default::default()
+        };
+        store.save_rooms(std::iter::once(&room)).unwrap();
+        let data: Vec<NCReqDataMessage> = [2, 1]
+            .iter()
+            .map(|id| NCReqDataMessage {
+                id: *id,
+                messageType: "comment".to_string(),
+                actorDisplayName: "Butz".to_string(),
+                message: format!("message {id}"),
+                ..Default::default()
+            })
+            .collect();
+        store.insert_messages(&room.token, data.iter()).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        for name in &["General", "abc"] {
+            let path =
+                export_stored_room(&store, name, dir.path(), ExportFormat::Markdown).unwrap();
+            assert!(path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("General-"));
+            let text = std::fs::read_to_string(path).unwrap();
+            assert!(text.find("message 1").unwrap() < text.find("message 2").unwrap());
+        }
+        assert!(export_stored_room(&store, "Other", dir.path(), ExportFormat::Json).is_err());
+
+        let mut out = vec![];
+        assert_eq!(dump_stored_room(&store, "abc", None, &mut out).unwrap(), 2);
+        let lines: Vec<serde_json::Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines[0]["message"], "message 1");
+        assert_eq!(lines[1]["id"], 2);
+        let mut out = vec![];
+        let tomorrow = Local::now().date_naive().succ_opt();
+        assert_eq!(
+            dump_stored_room(&store, "General", tomorrow, &mut out).unwrap(),
+            0
+        );
+        assert!(out.is_empty());
+    }
+}
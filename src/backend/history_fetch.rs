@@ -0,0 +1,231 @@
+//! Fetching the full history of a room in background tasks, so the UI stays interactive.
+use std::{fmt, sync::Arc};
+
+use itertools::Itertools;
+use tokio::sync::{
+    mpsc::{self, Receiver, Sender},
+    Mutex,
+};
+
+use super::nc_request::{nc_requester::NCRequestInterface, NCReqDataMessage, Token};
+
+/// Messages fetched per request.
+const PAGE_SIZE: i32 = 200;
+/// Message ids fetched by a single task, the ranges are fetched in parallel.
+const RANGE_SIZE: i32 = 10_000;
+/// Events held in the channel until the backend takes them.
+const CHANNEL_SIZE: usize = 20;
+
+/// How far fetching the history of a room got.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HistoryProgress {
+    pub messages: usize,
+    pub ranges_done: usize,
+    pub ranges: usize,
+}
+
+impl fmt::Display for HistoryProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} messages, {}/{} ranges",
+            self.messages, self.ranges_done, self.ranges
+        )
+    }
+}
+
+#[derive(Debug)]
+enum HistoryEvent {
+    Ranges(usize),
+    Messages(Vec<NCReqDataMessage>),
+    RangeDone,
+}
+
+/// History of a room, fetched by background tasks which stream the messages through a channel.
+///
+/// The tasks stop after their current request, once this is dropped.
+#[derive(Debug)]
+pub struct HistoryFetch {
+    token: Token,
+    receiver: Receiver<HistoryEvent>,
+    progress: HistoryProgress,
+}
+
+impl HistoryFetch {
+    /// Spawn the tasks fetching all messages of the room up to `last_message`.
+    pub fn start<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        requester: Arc<Mutex<Requester>>,
+        token: &Token,
+        last_message: i32,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(CHANNEL_SIZE);
+        let cloned_token = token.clone();
+        tokio::spawn(async move {
+            let first_message = match fetch_page(&requester, &cloned_token, 1).await {
+                Ok(page) => page.first().map(|message| message.id),
+                Err(why) => {
+                    log::warn!("Fetching the history of {cloned_token} failed: {why}");
+                    return;
+                }
+            };
+            // Room is empty.
+            let Some(first_message) = first_message else {
+                return;
+            };
+            // Messages after the given id are fetched, so start before the first one.
+            let ranges = (first_message - 1..=last_message)
+                .step_by(RANGE_SIZE.unsigned_abs() as usize)
+                .collect_vec();
+            log::debug!(
+                "Fetching full history of {cloned_token} from {first_message} to {last_message} in {} ranges",
+                ranges.len()
+            );
+            if sender
+                .send(HistoryEvent::Ranges(ranges.len()))
+                .await
+                .is_err()
+            {
+                return;
+            }
+            for first in ranges {
+                tokio::spawn(fetch_range(
+                    Arc::clone(&requester),
+                    cloned_token.clone(),
+                    first,
+                    first + RANGE_SIZE,
+                    sender.clone(),
+                ));
+            }
+        });
+        HistoryFetch {
+            token: token.clone(),
+            receiver,
+            progress: HistoryProgress::default(),
+        }
+    }
+
+    /// Token of the room whose history is fetched.
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn progress(&self) -> HistoryProgress {
+        self.progress
+    }
+
+    /// Wait for the next fetched messages, `None` once all ranges are fetched.
+    pub async fn next(&mut self) -> Option<Vec<NCReqDataMessage>> {
+        loop {
+            match self.receiver.recv().await? {
+                HistoryEvent::Ranges(ranges) => self.progress.ranges = ranges,
+                HistoryEvent::Messages(messages) => {
+                    self.progress.messages += messages.len();
+                    return Some(messages);
+                }
+                HistoryEvent::RangeDone => self.progress.ranges_done += 1,
+            }
+        }
+    }
+}
+
+/// Fetch up to [`PAGE_SIZE`] messages after `after`.
+/// The errors are turned into text, as they are kept across awaits of spawned tasks.
+async fn fetch_page<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+    requester: &Arc<Mutex<Requester>>,
+    token: &Token,
+    after: i32,
+) -> Result<Vec<NCReqDataMessage>, String> {
+    let response = requester
+        .lock()
+        .await
+        .request_chat_update(token, PAGE_SIZE, after)
+        .await
+        .map_err(|why| why.to_string())?;
+    response
+        .await
+        .map_err(|why| why.to_string())?
+        .map_err(|why| why.to_string())
+}
+
+/// Fetch the messages with ids from `first` to `last` page by page, sending every page.
+async fn fetch_range<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+    requester: Arc<Mutex<Requester>>,
+    token: Token,
+    first: i32,
+    last: i32,
+    sender: Sender<HistoryEvent>,
+) {
+    let mut fetch_key = first;
+    while fetch_key <= last && fetch_key >= 0 {
+        let page = match fetch_page(&requester, &token, fetch_key).await {
+            Ok(page) => page,
+            Err(why) => {
+                log::warn!("Fetching the history of {token} after {fetch_key} failed: {why}");
+                break;
+            }
+        };
+        let Some(last_fetched) = page.last().map(|message| message.id) else {
+            log::debug!("No Messages found aborting {fetch_key}");
+            break;
+        };
+        fetch_key = last_fetched;
+        if sender.send(HistoryEvent::Messages(page)).await.is_err() {
+            return;
+        }
+    }
+    // Fails only if the fetch was dropped, which no longer waits for this range then.
+    let _ = sender.send(HistoryEvent::RangeDone).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::nc_request::nc_requester::MockNCRequest;
+    use mockall::predicate::eq;
+
+    fn page(ids: &[i32]) -> Vec<NCReqDataMessage> {
+        ids.iter()
+            .map(|id| NCReqDataMessage {
+                id: *id,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn stream_history() {
+        let mut mock_requester = MockNCRequest::new();
+        for (after, ids) in [(1, vec![5, 6]), (4, vec![5, 6]), (6, vec![7]), (7, vec![])] {
+            mock_requester
+                .expect_request_chat_update()
+                .with(eq(Token::from("123")), eq(PAGE_SIZE), eq(after))
+                .returning(move |_, _, _| {
+                    let (tx, rx) = tokio::sync::oneshot::channel();
+                    tx.send(Ok(page(&ids))).unwrap();
+                    Ok(rx)
+                });
+        }
+
+        let mut fetch =
+            HistoryFetch::start(Arc::new(Mutex::new(mock_requester)), &Token::from("123"), 7);
+        assert_eq!(fetch.token(), "123");
+
+        let messages = fetch.next().await.unwrap();
+        assert_eq!(
+            messages.iter().map(|message| message.id).collect_vec(),
+            [5, 6]
+        );
+        let messages = fetch.next().await.unwrap();
+        assert_eq!(messages.iter().map(|message| message.id).collect_vec(), [7]);
+        assert!(fetch.next().await.is_none());
+        assert_eq!(
+            fetch.progress(),
+            HistoryProgress {
+                messages: 3,
+                ranges_done: 1,
+                ranges: 1
+            }
+        );
+        assert_eq!(fetch.progress().to_string(), "3 messages, 1/1 ranges");
+    }
+}
@@ -0,0 +1,78 @@
+use crate::backend::Token;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+/// Rooms muted only in this client, without changing the notification level on the server.
+///
+/// Persisted as `Muted.json` in the data dir, next to the `Talk.json` of the rooms.
+#[derive(Debug, Default)]
+pub struct LocalMutes {
+    path: PathBuf,
+    tokens: BTreeSet<Token>,
+}
+
+impl LocalMutes {
+    /// Read the muted rooms from the data dir, starting with none if there is no readable list.
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("Muted.json");
+        let tokens = if path.exists() {
+            std::fs::read_to_string(&path)
+                .map_err(|why| why.to_string())
+                .and_then(|data| serde_json::from_str(&data).map_err(|why| why.to_string()))
+                .unwrap_or_else(|why| {
+                    log::warn!("Failed to read muted rooms from {}: {why}", path.display());
+                    BTreeSet::new()
+                })
+        } else {
+            BTreeSet::new()
+        };
+        LocalMutes { path, tokens }
+    }
+
+    pub fn contains(&self, token: &Token) -> bool {
+        self.tokens.contains(token)
+    }
+
+    pub fn tokens(&self) -> Vec<Token> {
+        self.tokens.iter().cloned().collect()
+    }
+
+    /// Mute or unmute the room and write the list to disk.
+    pub fn set(&mut self, token: &Token, muted: bool) -> Result<(), std::io::Error> {
+        let changed = if muted {
+            self.tokens.insert(token.clone())
+        } else {
+            self.tokens.remove(token)
+        };
+        if changed {
+            std::fs::write(&self.path, serde_json::to_string(&self.tokens)?)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persist() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut mutes = LocalMutes::load(dir.path());
+        assert!(!mutes.contains(&Token::from("123")));
+
+        mutes.set(&Token::from("123"), true).unwrap();
+        mutes.set(&Token::from("456"), true).unwrap();
+        mutes.set(&Token::from("456"), false).unwrap();
+        assert!(mutes.contains(&Token::from("123")));
+
+        let mutes = LocalMutes::load(dir.path());
+        assert_eq!(mutes.tokens(), vec![Token::from("123")]);
+
+        std::fs::write(dir.path().join("Muted.json"), "butz").unwrap();
+        assert!(LocalMutes::load(dir.path()).tokens().is_empty());
+    }
+}
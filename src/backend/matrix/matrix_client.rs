@@ -0,0 +1,217 @@
+use crate::config::Config;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, AUTHORIZATION},
+    Client, Method, Url,
+};
+use serde_json::{json, Value};
+use std::{
+    error::Error,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// Minimal wrapper around the [Matrix Client-Server API](https://spec.matrix.org/latest/client-server-api/).
+///
+/// Authenticates with an access token and only covers the endpoints needed by [`super::MatrixTalk`].
+#[derive(Debug)]
+pub struct MatrixClient {
+    client: Client,
+    homeserver: Url,
+    user_id: String,
+    transaction_id: AtomicU64,
+}
+
+impl MatrixClient {
+    /// Create a client from the general config section.
+    /// `url` is the homeserver, `user` the full matrix id and `app_pw` an access token.
+    /// # Errors
+    /// The homeserver url or the access token may be invalid.
+    pub fn new(config: &Config) -> Result<MatrixClient, Box<dyn Error>> {
+        let general = &config.data.general;
+
+        let mut headers = HeaderMap::new();
+        let mut auth_value =
+            HeaderValue::from_str(&format!("Bearer {}", config.get_app_password()?))?;
+        auth_value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, auth_value);
+
+        let client = config.http_client()?.default_headers(headers).build()?;
+
+        Ok(MatrixClient {
+            client,
+            homeserver: Url::parse(&general.url)?,
+            user_id: general.user.clone(),
+            transaction_id: AtomicU64::new(chrono::Utc::now().timestamp_millis().unsigned_abs()),
+        })
+    }
+
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    fn url(&self, segments: &[&str]) -> Result<Url, Box<dyn Error>> {
+        let mut url = self.homeserver.clone();
+        url.path_segments_mut()
+            .map_err(|()| format!("The homeserver url {} cannot be a base.", self.homeserver))?
+            .pop_if_empty()
+            .extend(["_matrix", "client", "v3"])
+            .extend(segments);
+        Ok(url)
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        url: Url,
+        body: Option<Value>,
+    ) -> Result<Value, Box<dyn Error>> {
+        log::trace!("Matrix {method} {url}");
+        let mut builder = self.client.request(method, url);
+        if let Some(body) = body {
+            builder = builder.json(&body);
+        }
+        let response = builder.send().await?.error_for_status()?;
+        Ok(response.json::<Value>().await?)
+    }
+
+    /// Fetch all changes since the given sync token, or a full initial sync.
+    pub async fn sync(&self, since: Option<&str>) -> Result<Value, Box<dyn Error>> {
+        let mut url = self.url(&["sync"])?;
+        url.query_pairs_mut()
+            .append_pair("timeout", "0")
+            .append_pair("filter", r#"{"room":{"timeline":{"limit":50}}}"#);
+        if let Some(since) = since {
+            url.query_pairs_mut().append_pair("since", since);
+        }
+        self.request(Method::GET, url, None).await
+    }
+
+    /// Send a plain text message, returns the event id.
+    pub async fn send_message(
+        &self,
+        room_id: &str,
+        message: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        let transaction_id = self
+            .transaction_id
+            .fetch_add(1, Ordering::Relaxed)
+            .to_string();
+        let url = self.url(&[
+            "rooms",
+            room_id,
+            "send",
+            "m.room.message",
+            transaction_id.as_str(),
+        ])?;
+        let response = self
+            .request(
+                Method::PUT,
+                url,
+                Some(json!({"msgtype": "m.text", "body": message})),
+            )
+            .await?;
+        Ok(response["event_id"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string())
+    }
+
+    pub async fn set_read_marker(
+        &self,
+        room_id: &str,
+        event_id: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let url = self.url(&["rooms", room_id, "read_markers"])?;
+        self.request(
+            Method::POST,
+            url,
+            Some(json!({"m.fully_read": event_id, "m.read": event_id})),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn set_favorite(&self, room_id: &str, favorite: bool) -> Result<(), Box<dyn Error>> {
+        let url = self.url(&[
+            "user",
+            &self.user_id,
+            "rooms",
+            room_id,
+            "tags",
+            "m.favourite",
+        ])?;
+        if favorite {
+            self.request(Method::PUT, url, Some(json!({}))).await?;
+        } else {
+            self.request(Method::DELETE, url, None).await?;
+        }
+        Ok(())
+    }
+
+    /// Search the user directory, returns pairs of user id and display name.
+    pub async fn search_users(
+        &self,
+        search: &str,
+    ) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+        let url = self.url(&["user_directory", "search"])?;
+        let response = self
+            .request(Method::POST, url, Some(json!({"search_term": search})))
+            .await?;
+        Ok(response["results"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|user| {
+                let id = user["user_id"].as_str()?.to_string();
+                let name = user["display_name"].as_str().unwrap_or(&id).to_string();
+                Some((id, name))
+            })
+            .collect())
+    }
+
+    pub async fn invite(&self, room_id: &str, user_id: &str) -> Result<(), Box<dyn Error>> {
+        let url = self.url(&["rooms", room_id, "invite"])?;
+        self.request(Method::POST, url, Some(json!({"user_id": user_id})))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn ban(&self, room_id: &str, user_id: &str) -> Result<(), Box<dyn Error>> {
+        let url = self.url(&["rooms", room_id, "ban"])?;
+        self.request(Method::POST, url, Some(json!({"user_id": user_id})))
+            .await?;
+        Ok(())
+    }
+
+    /// Replace the state event of the given type with an empty state key.
+    pub async fn set_state(
+        &self,
+        room_id: &str,
+        event_type: &str,
+        content: Value,
+    ) -> Result<(), Box<dyn Error>> {
+        let url = self.url(&["rooms", room_id, "state", event_type, ""])?;
+        self.request(Method::PUT, url, Some(content)).await?;
+        Ok(())
+    }
+
+    /// Change the power level of a user by rewriting the rooms power level state.
+    pub async fn set_power_level(
+        &self,
+        room_id: &str,
+        user_id: &str,
+        level: i64,
+    ) -> Result<(), Box<dyn Error>> {
+        let url = self.url(&["rooms", room_id, "state", "m.room.power_levels", ""])?;
+        let mut power_levels = self.request(Method::GET, url.clone(), None).await?;
+        power_levels["users"][user_id] = json!(level);
+        self.request(Method::PUT, url, Some(power_levels)).await?;
+        Ok(())
+    }
+
+    pub async fn kick(&self, room_id: &str, user_id: &str) -> Result<(), Box<dyn Error>> {
+        let url = self.url(&["rooms", room_id, "kick"])?;
+        self.request(Method::POST, url, Some(json!({"user_id": user_id})))
+            .await?;
+        Ok(())
+    }
+}
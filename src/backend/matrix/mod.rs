@@ -0,0 +1,1015 @@
+//! Experimental Matrix provider, only built with the `matrix` feature.
+//!
+//! [`MatrixTalk`] translates Matrix rooms and events into the NC Talk data types,
+//! so the UI renders them through the same [`NCRoom`] as Talk rooms.
+//! It has no [`ChatRoom`] implementation of its own yet.
+
+mod matrix_client;
+
+use crate::{
+    backend::{
+        chat_message::ChatMessage,
+        chat_provider::{BackendUpdate, ChatProvider},
+        chat_room::ChatRoom,
+        chat_types::RoomType,
+        chat_types::{ChatFeature, FoundUser, ListedRoom, OutOfOffice, Token, UserStatus},
+        history_fetch::HistoryProgress,
+        local_mutes::LocalMutes,
+        message_store::MessageStore,
+        nc_request::{NCReqDataMessage, NCReqDataParticipants, NCReqDataRoom},
+        nc_room::{conversation_type, NCRoom, NCRoomInterface},
+        request_metrics::MetricsSnapshot,
+    },
+    config::Config,
+};
+use async_trait::async_trait;
+use itertools::Itertools;
+use matrix_client::MatrixClient;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::TryFrom,
+    error::Error,
+    path::PathBuf,
+    time::Duration,
+};
+
+/// Power level from which on a Matrix user is shown as moderator.
+const MODERATOR_POWER_LEVEL: i64 = 50;
+
+/// NC Talk participant types used for translated Matrix members.
+const PARTICIPANT_TYPE_MODERATOR: i32 = 2;
+const PARTICIPANT_TYPE_USER: i32 = 3;
+
+/// Matrix room state which has no direct counterpart in [`NCReqDataRoom`].
+#[derive(Debug, Default)]
+struct MatrixRoomState {
+    name: Option<String>,
+    topic: String,
+    /// Joined members, user id to display name.
+    members: BTreeMap<String, String>,
+    power_levels: HashMap<String, i64>,
+    users_default: i64,
+    favorite: bool,
+    unread: i32,
+    highlight: bool,
+    last_event_id: Option<String>,
+    /// Ids of the events already added as messages, to skip them when a sync repeats them.
+    known_events: HashSet<String>,
+}
+
+impl MatrixRoomState {
+    /// Start with the events stored by earlier runs as known, as the sync token is not kept
+    /// and the first sync of a run repeats them.
+    fn with_stored_events(store: &MessageStore, room_id: &Token) -> Self {
+        let known_events = store.reference_ids(room_id).unwrap_or_else(|why| {
+            log::warn!("Could not read the stored events of {room_id}: {why}");
+            HashSet::new()
+        });
+        MatrixRoomState {
+            known_events,
+            ..Default::default()
+        }
+    }
+
+    fn apply_state_event(&mut self, event: &Value) {
+        let content = &event["content"];
+        match event["type"].as_str() {
+            Some("m.room.name") => {
+                self.name = content["name"]
+                    .as_str()
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string);
+            }
+            Some("m.room.member") => {
+                let Some(user_id) = event["state_key"].as_str() else {
+                    return;
+                };
+                if content["membership"] == "join" {
+                    let name = content["displayname"].as_str().unwrap_or(user_id);
+                    self.members.insert(user_id.to_string(), name.to_string());
+                } else {
+                    self.members.remove(user_id);
+                }
+            }
+            Some("m.room.topic") => {
+                self.topic = content["topic"].as_str().unwrap_or_default().to_string();
+            }
+            Some("m.room.power_levels") => {
+                self.users_default = content["users_default"].as_i64().unwrap_or(0);
+                self.power_levels = content["users"]
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(user, level)| Some((user.clone(), level.as_i64()?)))
+                    .collect();
+            }
+            _ => (),
+        }
+    }
+
+    /// Apply the state, tags and unread counts of a room in the `rooms.join` section of a sync.
+    fn apply_joined_room(&mut self, room: &Value) {
+        for event in events(&room["state"]).chain(events(&room["timeline"])) {
+            self.apply_state_event(event);
+        }
+        for event in events(&room["account_data"]) {
+            if event["type"] == "m.tag" {
+                self.favorite = event["content"]["tags"].get("m.favourite").is_some();
+            }
+        }
+        if let Some(unread) = room["unread_notifications"]["notification_count"].as_i64() {
+            self.unread = i32::try_from(unread).unwrap_or(i32::MAX);
+        }
+        if let Some(highlight) = room["unread_notifications"]["highlight_count"].as_i64() {
+            self.highlight = highlight > 0;
+        }
+    }
+
+    fn display_name_of<'a>(&'a self, user_id: &'a str) -> &'a str {
+        self.members.get(user_id).map_or(user_id, String::as_str)
+    }
+
+    fn participant_type_of(&self, user_id: &str) -> i32 {
+        if self
+            .power_levels
+            .get(user_id)
+            .copied()
+            .unwrap_or(self.users_default)
+            >= MODERATOR_POWER_LEVEL
+        {
+            PARTICIPANT_TYPE_MODERATOR
+        } else {
+            PARTICIPANT_TYPE_USER
+        }
+    }
+
+    /// Room name, falls back to the names of the other members like Matrix clients do.
+    fn room_name(&self, room_id: &str, own_user_id: &str) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let others = self
+            .members
+            .iter()
+            .filter(|(user_id, _)| user_id.as_str() != own_user_id)
+            .map(|(_, name)| name.as_str())
+            .take(3)
+            .join(", ");
+        if others.is_empty() {
+            room_id.to_string()
+        } else {
+            others
+        }
+    }
+
+    /// Translate a `m.room.message` event into a NC Talk comment with the given id.
+    /// The event id is kept as reference id.
+    fn message(&self, room_id: &str, id: i32, event: &Value) -> NCReqDataMessage {
+        let sender = event["sender"].as_str().unwrap_or_default();
+        NCReqDataMessage {
+            id,
+            token: room_id.to_string(),
+            referenceId: event["event_id"].as_str().unwrap_or_default().to_string(),
+            actorType: "users".to_string(),
+            actorId: sender.to_string(),
+            actorDisplayName: self.display_name_of(sender).to_string(),
+            timestamp: event["origin_server_ts"].as_i64().unwrap_or_default() / 1000,
+            messageType: "comment".to_string(),
+            message: event["content"]["body"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn room_data(
+        &self,
+        room_id: &str,
+        own_user_id: &str,
+        room_type: RoomType,
+        last_message: NCReqDataMessage,
+    ) -> NCReqDataRoom {
+        NCReqDataRoom {
+            token: room_id.to_string(),
+            roomtype: conversation_type(room_type),
+            name: self.room_name(room_id, own_user_id),
+            displayName: self.room_name(room_id, own_user_id),
+            description: self.topic.clone(),
+            participantType: self.participant_type_of(own_user_id),
+            isFavorite: self.favorite,
+            unreadMessages: self.unread,
+            unreadMention: self.highlight,
+            lastReadMessage: (last_message.id - self.unread).max(0),
+            lastActivity: i32::try_from(last_message.timestamp).unwrap_or_default(),
+            lastMessage: last_message,
+            ..Default::default()
+        }
+    }
+
+    fn participants(&self) -> Vec<NCReqDataParticipants> {
+        self.members
+            .iter()
+            .enumerate()
+            .map(|(index, (user_id, name))| {
+                let mut participant = NCReqDataParticipants::default();
+                participant.attendeeId = i32::try_from(index + 1).unwrap_or(i32::MAX);
+                participant.actorType = "users".to_string();
+                participant.actorId.clone_from(user_id);
+                participant.displayName.clone_from(name);
+                participant.participantType = self.participant_type_of(user_id);
+                participant
+            })
+            .collect()
+    }
+}
+
+/// Changes found while applying a sync response.
+#[derive(Debug, Default)]
+struct SyncChanges {
+    /// Display names of rooms which were not known before.
+    new_rooms: Vec<String>,
+    /// Number of new messages by others per room.
+    new_messages: HashMap<Token, usize>,
+}
+
+/// Matrix homeserver instance, implementing the [`ChatProvider`] trait.
+///
+/// Holds all joined rooms as [`NCRoom`]s and the sync token of the last sync.
+#[derive(Debug)]
+pub struct MatrixTalk {
+    client: MatrixClient,
+    rooms: HashMap<Token, NCRoom>,
+    states: HashMap<Token, MatrixRoomState>,
+    direct_rooms: HashSet<Token>,
+    since: Option<String>,
+    store: MessageStore,
+    local_mutes: LocalMutes,
+}
+
+impl MatrixTalk {
+    /// Create a new Matrix Backend instance and run the initial sync.
+    ///
+    /// The general config section is reused, `url` is the homeserver, `user` the full
+    /// matrix id (`@user:example.org`) and `app_pw` an access token.
+    /// # Errors
+    /// The initial sync may fail or the account may not have joined any room.
+    pub async fn new(config: &Config) -> Result<MatrixTalk, Box<dyn Error>> {
+        let mut talk = MatrixTalk {
+            client: MatrixClient::new(config)?,
+            rooms: HashMap::new(),
+            states: HashMap::new(),
+            direct_rooms: HashSet::new(),
+            since: None,
+            store: MessageStore::open(&config.get_server_data_dir())?,
+            local_mutes: LocalMutes::load(&config.get_server_data_dir()),
+        };
+        log::debug!("Running initial Matrix sync");
+        talk.sync().await?;
+        if talk.rooms.is_empty() {
+            return Err("No joined Matrix rooms found.".into());
+        }
+        log::debug!("Found {} Rooms", talk.rooms.len());
+        Ok(talk)
+    }
+
+    async fn sync(&mut self) -> Result<SyncChanges, Box<dyn Error>> {
+        let response = self.client.sync(self.since.as_deref()).await?;
+        Ok(self.apply_sync(&response))
+    }
+
+    fn apply_sync(&mut self, sync: &Value) -> SyncChanges {
+        let mut changes = SyncChanges::default();
+        if let Some(next_batch) = sync["next_batch"].as_str() {
+            self.since = Some(next_batch.to_string());
+        }
+        for event in events(&sync["account_data"]) {
+            if event["type"] == "m.direct" {
+                self.direct_rooms = event["content"]
+                    .as_object()
+                    .into_iter()
+                    .flat_map(|users| users.values())
+                    .filter_map(Value::as_array)
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .map(Token::from)
+                    .collect();
+            }
+        }
+        let Some(joined) = sync["rooms"]["join"].as_object() else {
+            return changes;
+        };
+        let own_user_id = self.client.user_id();
+        let store = &self.store;
+        for (room_id, room) in joined {
+            let state = self
+                .states
+                .entry(room_id.clone())
+                .or_insert_with(|| MatrixRoomState::with_stored_events(store, room_id));
+            state.apply_joined_room(room);
+
+            if !self.rooms.contains_key(room_id) {
+                changes
+                    .new_rooms
+                    .push(state.room_name(room_id, own_user_id));
+            }
+            let nc_room = self.rooms.entry(room_id.clone()).or_insert_with(|| {
+                NCRoom::from_data(
+                    NCReqDataRoom {
+                        token: room_id.clone(),
+                        ..Default::default()
+                    },
+                    store.clone(),
+                )
+            });
+
+            // Matrix events have no numeric ids, continue after the stored ones, which rooms
+            // created this run have not loaded.
+            let stored_id = store.last_id(room_id).unwrap_or_else(|why| {
+                log::warn!("Could not read the last stored message of {room_id}: {why}");
+                None
+            });
+            let mut next_id = nc_room
+                .get_messages()
+                .keys()
+                .last()
+                .copied()
+                .max(stored_id)
+                .map_or(1, |id| id + 1);
+            let mut new_messages = 0;
+            for event in events(&room["timeline"]) {
+                let Some(event_id) = event["event_id"].as_str() else {
+                    continue;
+                };
+                if event["type"] != "m.room.message" {
+                    continue;
+                }
+                state.last_event_id = Some(event_id.to_string());
+                if !state.known_events.insert(event_id.to_string()) {
+                    continue;
+                }
+                let sender = event["sender"].as_str().unwrap_or_default();
+                if sender != own_user_id {
+                    new_messages += 1;
+                }
+                nc_room.insert_message(state.message(room_id, next_id, event));
+                next_id += 1;
+            }
+            if new_messages > 0 {
+                changes.new_messages.insert(room_id.clone(), new_messages);
+            }
+
+            let last_message = nc_room
+                .get_messages()
+                .values()
+                .last()
+                .map(|message| message.data().clone())
+                .unwrap_or_default();
+            let room_type = if self.direct_rooms.contains(room_id) {
+                RoomType::OneToOne
+            } else {
+                RoomType::Group
+            };
+            nc_room.set_data(state.room_data(room_id, own_user_id, room_type, last_message));
+            nc_room.set_participants(state.participants());
+        }
+        changes
+    }
+
+    /// Look up the Matrix user id of a translated participant.
+    fn participant_user_id(
+        &self,
+        token: &Token,
+        attendee_id: i32,
+    ) -> Result<String, Box<dyn Error>> {
+        Ok(self
+            .rooms
+            .get(token)
+            .ok_or("Room not found when it should be there")?
+            .get_users()
+            .iter()
+            .find(|participant| participant.attendee_id == attendee_id)
+            .map(|participant| participant.actor_id.clone())
+            .ok_or("Participant not found in room")?)
+    }
+
+    fn notification_for(&self, changes: &SyncChanges, token: &Token) -> Option<(String, usize)> {
+        changes
+            .new_messages
+            .get(token)
+            .map(|count| (self.rooms[token].to_string(), *count))
+    }
+}
+
+/// Iterate over the `events` list of a sync response section.
+fn events(section: &Value) -> impl Iterator<Item = &Value> {
+    section["events"].as_array().into_iter().flatten()
+}
+
+#[async_trait]
+impl ChatProvider for MatrixTalk {
+    type Room = NCRoom;
+
+    fn write_to_log(&mut self) -> Result<(), std::io::Error> {
+        for room in self.rooms.values_mut() {
+            room.write_to_log()?;
+        }
+        Ok(())
+    }
+
+    fn get_room(&self, token: &Token) -> &Self::Room {
+        &self.rooms[token]
+    }
+
+    fn get_locally_muted_rooms(&self) -> Vec<Token> {
+        self.local_mutes.tokens()
+    }
+
+    fn is_locally_muted(&self, token: &Token) -> bool {
+        self.local_mutes.contains(token)
+    }
+
+    fn set_locally_muted(&mut self, token: &Token, muted: bool) -> Result<(), std::io::Error> {
+        self.local_mutes.set(token, muted)
+    }
+
+    fn get_unread_rooms(&self) -> Vec<Token> {
+        self.rooms
+            .values()
+            .filter(|room| room.has_unread() && !room.is_archived())
+            .sorted()
+            .map(ChatRoom::to_token)
+            .collect()
+    }
+
+    fn get_favorite_rooms(&self) -> Vec<Token> {
+        self.rooms
+            .values()
+            .filter(|room| room.is_favorite() && !room.is_archived())
+            .sorted()
+            .map(ChatRoom::to_token)
+            .collect()
+    }
+
+    fn get_archived_rooms(&self) -> Vec<Token> {
+        self.rooms
+            .values()
+            .filter(|room| room.is_archived())
+            .sorted()
+            .map(ChatRoom::to_token)
+            .collect()
+    }
+
+    fn get_room_by_displayname(&self, name: &str) -> Token {
+        if let Some(room) = self.rooms.values().find(|room| room.as_str() == name) {
+            return room.to_token();
+        }
+        let first = self
+            .rooms
+            .values()
+            .sorted()
+            .next()
+            .map(ChatRoom::to_token)
+            .unwrap_or_default();
+        log::warn!("No Matrix room named {name}, falling back to {first}.");
+        first
+    }
+
+    fn get_dm_room(&self, user_id: &str) -> Option<Token> {
+        self.rooms
+            .values()
+            .find(|room| {
+                room.is_dm() && room.get_users().iter().any(|user| user.actor_id == user_id)
+            })
+            .map(ChatRoom::to_token)
+    }
+
+    fn get_dm_keys_display_name_mapping(&self) -> Vec<(Token, String)> {
+        self.rooms
+            .values()
+            .filter(|room| room.is_dm() && !room.is_archived())
+            .sorted()
+            .map(|room| (room.to_token(), room.to_string()))
+            .collect()
+    }
+
+    fn get_group_keys_display_name_mapping(&self) -> Vec<(Token, String)> {
+        self.rooms
+            .values()
+            .filter(|room| room.is_group() && !room.is_archived())
+            .sorted()
+            .map(|room| (room.to_token(), room.to_string()))
+            .collect()
+    }
+
+    fn get_breakout_rooms(&self, _token: &Token) -> Vec<(Token, String)> {
+        vec![]
+    }
+
+    fn get_room_keys(&self) -> Vec<&'_ Token> {
+        self.rooms.keys().collect()
+    }
+
+    fn get_room_link(&self, token: &Token) -> String {
+        format!("https://matrix.to/#/{token}")
+    }
+
+    fn resolve_message_link(&self, _link: &str) -> Option<(Token, i32)> {
+        None
+    }
+
+    fn supports(&self, _feature: ChatFeature) -> bool {
+        true
+    }
+
+    async fn send_message(
+        &mut self,
+        message: String,
+        token: &Token,
+    ) -> Result<Option<(String, usize)>, Box<dyn Error>> {
+        log::info!("Send Message {}", &message);
+        self.client.send_message(token, &message).await?;
+        let changes = self.sync().await?;
+        Ok(self.notification_for(&changes, token))
+    }
+
+    async fn select_room(
+        &mut self,
+        token: &Token,
+    ) -> Result<Option<(String, usize)>, Box<dyn Error>> {
+        log::debug!("selected room {token}");
+        let changes = self.sync().await?;
+        Ok(self.notification_for(&changes, token))
+    }
+
+    async fn watch_room(&mut self, token: &Token) {
+        log::debug!("Not long polling {token}, the Matrix provider only syncs.");
+    }
+
+    async fn next_update(&mut self) -> BackendUpdate {
+        std::future::pending().await
+    }
+
+    async fn update_rooms(&mut self, _force_update: bool) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(self.sync().await?.new_rooms)
+    }
+
+    async fn mark_current_room_as_read(&self, token: &Token) -> Result<(), Box<dyn Error>> {
+        if let Some(event_id) = self
+            .states
+            .get(token)
+            .and_then(|state| state.last_event_id.as_deref())
+        {
+            self.client.set_read_marker(token, event_id).await?;
+        }
+        Ok(())
+    }
+
+    async fn mark_all_rooms_as_read(&self) -> Result<(), Box<dyn Error>> {
+        for token in self.get_unread_rooms() {
+            self.mark_current_room_as_read(&token).await?;
+        }
+        Ok(())
+    }
+
+    async fn fetch_room_history(&mut self, token: &Token) -> Result<(), Box<dyn Error>> {
+        log::info!("Fetching the history of {token} is not supported for Matrix rooms yet.");
+        Ok(())
+    }
+
+    fn get_history_progress(&self) -> Option<HistoryProgress> {
+        None
+    }
+
+    fn get_rate_limit(&self) -> Option<Duration> {
+        None
+    }
+
+    fn is_offline(&self) -> bool {
+        false
+    }
+
+    fn take_request_dumps(&mut self) -> Vec<PathBuf> {
+        vec![]
+    }
+
+    fn get_metrics(&self) -> Option<MetricsSnapshot> {
+        None
+    }
+
+    fn get_stored_messages(&self, token: &Token) -> Result<Vec<ChatMessage>, Box<dyn Error>> {
+        Ok(self
+            .store
+            .load_all(token)?
+            .into_iter()
+            .map(ChatMessage::from)
+            .collect())
+    }
+
+    async fn fetch_older_messages(&mut self, token: &Token) -> Result<usize, Box<dyn Error>> {
+        log::info!("Fetching older messages of {token} is not supported for Matrix rooms yet.");
+        Ok(0)
+    }
+
+    async fn set_room_favorite(&self, token: &Token, favorite: bool) -> Result<(), Box<dyn Error>> {
+        self.client.set_favorite(token, favorite).await
+    }
+
+    async fn set_room_muted(&self, _token: &Token, _muted: bool) -> Result<(), Box<dyn Error>> {
+        Err("Muting rooms is not supported by the Matrix provider.".into())
+    }
+
+    async fn set_room_archived(
+        &self,
+        _token: &Token,
+        _archived: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        Err("Archiving rooms is not supported by the Matrix provider.".into())
+    }
+
+    async fn search_users(&self, search: &str) -> Result<Vec<FoundUser>, Box<dyn Error>> {
+        Ok(self
+            .client
+            .search_users(search)
+            .await?
+            .into_iter()
+            .map(|(id, display_name)| FoundUser { id, display_name })
+            .collect())
+    }
+
+    async fn search_listed_rooms(&self, _search: &str) -> Result<Vec<ListedRoom>, Box<dyn Error>> {
+        Err("Browsing public rooms is not supported by the Matrix provider.".into())
+    }
+
+    async fn join_room(&mut self, _token: &Token) -> Result<(), Box<dyn Error>> {
+        Err("Joining public rooms is not supported by the Matrix provider.".into())
+    }
+
+    async fn leave_room(&mut self, _token: &Token) -> Result<(), Box<dyn Error>> {
+        // Matrix has no sessions in rooms, there is nothing to leave.
+        Ok(())
+    }
+
+    async fn open_dm(&mut self, user_id: &str) -> Result<Token, Box<dyn Error>> {
+        self.get_dm_room(user_id).ok_or_else(|| {
+            "Creating direct messages is not supported by the Matrix provider.".into()
+        })
+    }
+
+    async fn set_read_status_private(&self, _private: bool) -> Result<(), Box<dyn Error>> {
+        Err("Read status privacy is not supported by the Matrix provider.".into())
+    }
+
+    async fn get_own_status(&self) -> Result<UserStatus, Box<dyn Error>> {
+        Err("Setting a status is not supported by the Matrix provider.".into())
+    }
+
+    async fn get_out_of_office(
+        &self,
+        _user_id: &str,
+    ) -> Result<Option<OutOfOffice>, Box<dyn Error>> {
+        Err("Out of office is not supported by the Matrix provider.".into())
+    }
+
+    async fn set_own_status_message(
+        &self,
+        _message: &str,
+        _icon: Option<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        Err("Setting a status message is not supported by the Matrix provider.".into())
+    }
+
+    async fn set_own_status(&self, _status: &str) -> Result<(), Box<dyn Error>> {
+        Err("Setting a status is not supported by the Matrix provider.".into())
+    }
+
+    async fn share_file(
+        &self,
+        _token: &Token,
+        _name: &str,
+        _data: Vec<u8>,
+    ) -> Result<(), Box<dyn Error>> {
+        Err("Sharing files is not supported by the Matrix provider.".into())
+    }
+
+    async fn add_participant(
+        &mut self,
+        token: &Token,
+        user_id: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        self.client.invite(token, user_id).await?;
+        self.sync().await?;
+        Ok(())
+    }
+
+    async fn refresh_participants(&mut self, _token: &Token) -> Result<(), Box<dyn Error>> {
+        self.sync().await?;
+        Ok(())
+    }
+
+    async fn remove_participant(
+        &mut self,
+        token: &Token,
+        attendee_id: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        let user_id = self.participant_user_id(token, attendee_id)?;
+        self.client.kick(token, &user_id).await?;
+        self.sync().await?;
+        Ok(())
+    }
+
+    async fn set_participant_moderator(
+        &mut self,
+        token: &Token,
+        attendee_id: i32,
+        moderator: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let user_id = self.participant_user_id(token, attendee_id)?;
+        let level = if moderator { MODERATOR_POWER_LEVEL } else { 0 };
+        self.client.set_power_level(token, &user_id, level).await?;
+        self.sync().await?;
+        Ok(())
+    }
+
+    async fn set_room_name(&mut self, token: &Token, name: &str) -> Result<(), Box<dyn Error>> {
+        self.client
+            .set_state(token, "m.room.name", serde_json::json!({"name": name}))
+            .await?;
+        self.sync().await?;
+        Ok(())
+    }
+
+    async fn set_room_description(
+        &mut self,
+        token: &Token,
+        description: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        self.client
+            .set_state(
+                token,
+                "m.room.topic",
+                serde_json::json!({"topic": description}),
+            )
+            .await?;
+        self.sync().await?;
+        Ok(())
+    }
+
+    async fn ban_participant(
+        &mut self,
+        token: &Token,
+        attendee_id: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        let user_id = self.participant_user_id(token, attendee_id)?;
+        self.client.ban(token, &user_id).await?;
+        self.sync().await?;
+        Ok(())
+    }
+
+    async fn send_reply(
+        &mut self,
+        _message: String,
+        _token: &Token,
+        _reply_to: i32,
+    ) -> Result<Option<(String, usize)>, Box<dyn Error>> {
+        Err("Replies are not supported by the Matrix provider.".into())
+    }
+
+    async fn edit_message(
+        &mut self,
+        _token: &Token,
+        _message_id: i32,
+        _message: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        Err("Editing messages is not supported by the Matrix provider.".into())
+    }
+
+    async fn delete_message(
+        &mut self,
+        _token: &Token,
+        _message_id: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        Err("Deleting messages is not supported by the Matrix provider.".into())
+    }
+
+    async fn react_to_message(
+        &mut self,
+        _token: &Token,
+        _message_id: i32,
+        _reaction: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        Err("Reactions are not supported by the Matrix provider.".into())
+    }
+
+    async fn remove_reaction(
+        &mut self,
+        _token: &Token,
+        _message_id: i32,
+        _reaction: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        Err("Reactions are not supported by the Matrix provider.".into())
+    }
+
+    async fn set_message_reminder(
+        &self,
+        _token: &Token,
+        _message_id: i32,
+        _timestamp: i64,
+    ) -> Result<(), Box<dyn Error>> {
+        Err("Reminders are not supported by the Matrix provider.".into())
+    }
+
+    async fn pin_message(&self, _token: &Token, _message_id: i32) -> Result<(), Box<dyn Error>> {
+        Err("Pinning messages is not supported by the Matrix provider.".into())
+    }
+
+    async fn set_room_password(
+        &mut self,
+        _token: &Token,
+        _password: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        Err("Room passwords are not supported by the Matrix provider.".into())
+    }
+
+    async fn set_mention_all_restricted(
+        &mut self,
+        _token: &Token,
+        _restricted: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        Err("Mention permissions are not supported by the Matrix provider.".into())
+    }
+
+    async fn set_message_expiration(
+        &mut self,
+        _token: &Token,
+        _seconds: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        Err("Message expiration is not supported by the Matrix provider.".into())
+    }
+
+    async fn shutdown(&self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::init;
+    use serde_json::json;
+
+    fn sync_response() -> Value {
+        json!({
+            "next_batch": "s1",
+            "account_data": {"events": [
+                {"type": "m.direct", "content": {"@bert:butz.com": ["!dm:butz.com"]}}
+            ]},
+            "rooms": {"join": {
+                "!group:butz.com": {
+                    "state": {"events": [
+                        {"type": "m.room.name", "state_key": "", "content": {"name": "General"}},
+                        {"type": "m.room.power_levels", "state_key": "",
+                         "content": {"users": {"@dummy_user:butz.com": 100}}},
+                        {"type": "m.room.member", "state_key": "@dummy_user:butz.com",
+                         "content": {"membership": "join", "displayname": "Dummy"}},
+                        {"type": "m.room.member", "state_key": "@bert:butz.com",
+                         "content": {"membership": "join", "displayname": "Bert"}}
+                    ]},
+                    "timeline": {"events": [
+                        {"type": "m.room.message", "event_id": "$1", "sender": "@bert:butz.com",
+                         "origin_server_ts": 1_700_000_000_000_i64,
+                         "content": {"msgtype": "m.text", "body": "Hello"}},
+                        {"type": "m.room.message", "event_id": "$2", "sender": "@dummy_user:butz.com",
+                         "origin_server_ts": 1_700_000_001_000_i64,
+                         "content": {"msgtype": "m.text", "body": "Hi"}}
+                    ]},
+                    "account_data": {"events": [
+                        {"type": "m.tag", "content": {"tags": {"m.favourite": {}}}}
+                    ]},
+                    "unread_notifications": {"notification_count": 1, "highlight_count": 1}
+                },
+                "!dm:butz.com": {
+                    "state": {"events": [
+                        {"type": "m.room.member", "state_key": "@bert:butz.com",
+                         "content": {"membership": "join", "displayname": "Bert"}}
+                    ]},
+                    "timeline": {"events": []},
+                    "unread_notifications": {"notification_count": 0, "highlight_count": 0}
+                }
+            }}
+        })
+    }
+
+    fn new_talk(config: &Config) -> MatrixTalk {
+        MatrixTalk {
+            client: MatrixClient::new(config).unwrap(),
+            rooms: HashMap::new(),
+            states: HashMap::new(),
+            direct_rooms: HashSet::new(),
+            since: None,
+            store: MessageStore::open(&config.get_server_data_dir()).unwrap(),
+            local_mutes: LocalMutes::load(&config.get_server_data_dir()),
+        }
+    }
+
+    #[test]
+    fn apply_sync() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let mut config = init("./test/").unwrap();
+        config.data.general.user = "@dummy_user:butz.com".to_string();
+
+        let mut talk = new_talk(&config);
+        let changes = talk.apply_sync(&sync_response());
+
+        assert_eq!(talk.since.as_deref(), Some("s1"));
+        assert_eq!(changes.new_rooms.len(), 2);
+        assert_eq!(changes.new_messages.get("!group:butz.com"), Some(&1));
+
+        let group = talk.get_room(&"!group:butz.com".to_string());
+        assert_eq!(group.get_display_name(), "General");
+        assert!(group.is_group());
+        assert!(group.is_favorite());
+        assert!(group.is_moderator());
+        assert!(group.has_unread_mention());
+        assert_eq!(group.get_messages().len(), 2);
+        assert_eq!(group.get_users().len(), 2);
+
+        let dm = talk.get_room(&"!dm:butz.com".to_string());
+        assert_eq!(dm.get_display_name(), "Bert");
+        assert!(dm.is_dm());
+        assert!(!dm.has_unread());
+
+        assert_eq!(talk.get_unread_rooms(), vec!["!group:butz.com".to_string()]);
+        assert_eq!(talk.get_room_by_displayname("Bert"), "!dm:butz.com");
+        assert_eq!(talk.get_room_by_displayname("Unknown"), "!dm:butz.com");
+
+        // A new run continues after the stored messages, instead of replacing them.
+        talk.write_to_log().unwrap();
+        let mut talk = new_talk(&config);
+        talk.since = Some("s1".to_string());
+        let changes = talk.apply_sync(&json!({
+            "next_batch": "s2",
+            "rooms": {"join": {"!group:butz.com": {
+                "timeline": {"events": [
+                    {"type": "m.room.message", "event_id": "$3", "sender": "@bert:butz.com",
+                     "origin_server_ts": 1_700_000_002_000_i64,
+                     "content": {"msgtype": "m.text", "body": "Still there?"}}
+                ]},
+                "unread_notifications": {"notification_count": 0, "highlight_count": 0}
+            }}}
+        }));
+        assert_eq!(changes.new_rooms.len(), 1);
+        let group = talk.get_room(&"!group:butz.com".to_string());
+        assert_eq!(
+            group.get_messages().keys().copied().collect::<Vec<_>>(),
+            [3]
+        );
+        assert!(!group.has_unread());
+        assert_eq!(
+            talk.states["!group:butz.com"].last_event_id.as_deref(),
+            Some("$3")
+        );
+        talk.write_to_log().unwrap();
+        let stored = talk.store.load_all(&"!group:butz.com".to_string()).unwrap();
+        assert_eq!(stored.len(), 3);
+    }
+
+    #[test]
+    fn repeated_initial_sync() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let mut config = init("./test/").unwrap();
+        config.data.general.user = "@dummy_user:butz.com".to_string();
+
+        let mut talk = new_talk(&config);
+        talk.apply_sync(&sync_response());
+        talk.write_to_log().unwrap();
+
+        // The sync token is not kept, so the next run starts with the same initial sync.
+        let mut talk = new_talk(&config);
+        let changes = talk.apply_sync(&sync_response());
+        assert!(changes.new_messages.is_empty());
+        assert!(talk
+            .get_room(&"!group:butz.com".to_string())
+            .get_messages()
+            .is_empty());
+        assert_eq!(
+            talk.states["!group:butz.com"].last_event_id.as_deref(),
+            Some("$2")
+        );
+        talk.write_to_log().unwrap();
+
+        let stored = talk.store.load_all(&"!group:butz.com".to_string()).unwrap();
+        assert_eq!(
+            stored
+                .iter()
+                .map(|message| message.referenceId.as_str())
+                .collect::<Vec<_>>(),
+            ["$1", "$2"]
+        );
+    }
+}
@@ -0,0 +1,460 @@
+//! Rooms and messages cached on the disk in a SQLite database, one per server.
+//!
+//! Messages are inserted as they arrive, so nothing has to be rewritten on exit,
+//! and only the recent ones of a room are read into memory.
+use std::{
+    collections::HashMap,
+    error::Error,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::nc_request::{NCReqDataMessage, NCReqDataRoom, Token};
+
+/// File name of the database in the data dir of the server.
+const DB_FILE: &str = "Talk.sqlite";
+/// Version of the schema, kept in the `user_version` of the database.
+const SCHEMA_VERSION: i32 = 1;
+
+// Created if missing, as a failed import leaves the tables without a version.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS rooms (
+    token TEXT PRIMARY KEY,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS messages (
+    token TEXT NOT NULL,
+    id INTEGER NOT NULL,
+    timestamp INTEGER NOT NULL,
+    message TEXT NOT NULL,
+    data TEXT NOT NULL,
+    PRIMARY KEY (token, id)
+) WITHOUT ROWID;
+CREATE INDEX IF NOT EXISTS messages_by_time ON messages (token, timestamp);
+";
+
+fn schema_version(connection: &Connection) -> rusqlite::Result<i32> {
+    connection.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+/// Handle of the database, shared by the provider and all of its rooms.
+/// The default handle has no database, all access to it fails.
+#[derive(Debug, Clone, Default)]
+pub struct MessageStore(Option<Arc<Mutex<Connection>>>);
+
+impl MessageStore {
+    /// Open the database in `dir`, creating it if needed.
+    /// A new database imports `Talk.json` and the room logs of older versions, if there are any.
+    /// The version is only set once the import succeeded, so a failed one is tried again.
+    pub fn open(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        std::fs::create_dir_all(dir)?;
+        let connection = Connection::open(dir.join(DB_FILE))?;
+        let fresh = schema_version(&connection)? == 0;
+        let store = MessageStore::with_connection(connection)?;
+        if fresh {
+            match store.import_json_logs(dir) {
+                Ok(()) => store.set_schema_version()?,
+                Err(why) => {
+                    log::warn!("Could not import the JSON logs of {}: {why}", dir.display());
+                }
+            }
+        }
+        Ok(store)
+    }
+
+    /// A database only in memory.
+    #[cfg(test)]
+    pub fn in_memory() -> Result<Self, Box<dyn Error>> {
+        let store = MessageStore::with_connection(Connection::open_in_memory()?)?;
+        store.set_schema_version()?;
+        Ok(store)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, Box<dyn Error>> {
+        let version = schema_version(&connection)?;
+        if version == 0 {
+            connection.execute_batch(SCHEMA)?;
+        } else if version > SCHEMA_VERSION {
+            return Err(format!(
+                "The message store was written by a newer version (schema {version})."
+            )
+            .into());
+        }
+        Ok(MessageStore(Some(Arc::new(Mutex::new(connection)))))
+    }
+
+    fn set_schema_version(&self) -> Result<(), Box<dyn Error>> {
+        self.connection()?
+            .pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(())
+    }
+
+    fn connection(&self) -> Result<MutexGuard<'_, Connection>, Box<dyn Error>> {
+        self.0
+            .as_ref()
+            .ok_or("No message store is open.")?
+            .lock()
+            .map_err(|_| "The message store is poisoned.".into())
+    }
+
+    /// Import the rooms of `Talk.json` and their logs, either a JSON array or a message per line.
+    /// The files are left in place, they are not read again.
+    /// A log which cannot be parsed fails the import, so it is tried again on the next start.
+    /// Lines of a message per line log which cannot be parsed are skipped with a warning.
+    fn import_json_logs(&self, dir: &Path) -> Result<(), Box<dyn Error>> {
+        let Ok(text) = std::fs::read_to_string(dir.join("Talk.json")) else {
+            return Ok(());
+        };
+        let rooms = serde_json::from_str::<HashMap<Token, NCReqDataRoom>>(&text)?;
+        self.save_rooms(rooms.values())?;
+        for token in rooms.keys() {
+            let Ok(text) = std::fs::read_to_string(dir.join(token)) else {
+                continue;
+            };
+            let messages = if text.trim_start().starts_with('[') {
+                serde_json::from_str::<Vec<NCReqDataMessage>>(&text)
+                    .map_err(|why| format!("Could not parse the log of {token}: {why}"))?
+            } else {
+                text.lines()
+                    .enumerate()
+                    .filter_map(|(index, line)| {
+                        serde_json::from_str::<NCReqDataMessage>(line)
+                            .inspect_err(|why| {
+                                log::warn!(
+                                    "Skipping line {} of the log of {token}: {why}",
+                                    index + 1
+                                );
+                            })
+                            .ok()
+                    })
+                    .collect()
+            };
+            self.insert_messages(token, messages.iter())?;
+        }
+        log::info!(
+            "Imported {} rooms from {}, the JSON logs there are not used anymore",
+            rooms.len(),
+            dir.display()
+        );
+        Ok(())
+    }
+
+    pub fn load_rooms(&self) -> Result<HashMap<Token, NCReqDataRoom>, Box<dyn Error>> {
+        let connection = self.connection()?;
+        let mut statement = connection.prepare("SELECT data FROM rooms")?;
+        let rooms = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .map(|data| -> Result<_, Box<dyn Error>> {
+                let room = serde_json::from_str::<NCReqDataRoom>(&data?)?;
+                Ok((room.token.clone(), room))
+            })
+            .collect();
+        rooms
+    }
+
+    pub fn save_rooms<'a>(
+        &self,
+        rooms: impl Iterator<Item = &'a NCReqDataRoom>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut connection = self.connection()?;
+        let transaction = connection.transaction()?;
+        {
+            let mut statement = transaction
+                .prepare("INSERT OR REPLACE INTO rooms (token, data) VALUES (?1, ?2)")?;
+            for room in rooms {
+                statement.execute(params![room.token, serde_json::to_string(room)?])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    pub fn has_messages(&self, token: &Token) -> Result<bool, Box<dyn Error>> {
+        Ok(self
+            .connection()?
+            .query_row(
+                "SELECT 1 FROM messages WHERE token = ?1 LIMIT 1",
+                [token],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some())
+    }
+
+    /// Id of the newest stored message of a room, `None` without any.
+    #[cfg(any(test, feature = "matrix"))]
+    pub fn last_id(&self, token: &Token) -> Result<Option<i32>, Box<dyn Error>> {
+        Ok(self.connection()?.query_row(
+            "SELECT MAX(id) FROM messages WHERE token = ?1",
+            [token],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Reference ids of all stored messages of a room which have one.
+    #[cfg(any(test, feature = "matrix"))]
+    pub fn reference_ids(
+        &self,
+        token: &Token,
+    ) -> Result<std::collections::HashSet<String>, Box<dyn Error>> {
+        let connection = self.connection()?;
+        let mut statement = connection.prepare_cached(
+            "SELECT json_extract(data, '$.referenceId') FROM messages
+             WHERE token = ?1 AND json_extract(data, '$.referenceId') != ''",
+        )?;
+        let ids = statement
+            .query_map([token], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(ids)
+    }
+
+    /// Insert the messages of a room, replacing older versions of them.
+    pub fn insert_messages<'a>(
+        &self,
+        token: &Token,
+        messages: impl Iterator<Item = &'a NCReqDataMessage>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut connection = self.connection()?;
+        let transaction = connection.transaction()?;
+        {
+            let mut statement = transaction.prepare(
+                "INSERT OR REPLACE INTO messages (token, id, timestamp, message, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for message in messages {
+                statement.execute(params![
+                    token,
+                    message.id,
+                    message.timestamp,
+                    message.message,
+                    serde_json::to_string(message)?
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Read the `count` newest messages of a room, oldest first.
+    pub fn load_recent(
+        &self,
+        token: &Token,
+        count: usize,
+    ) -> Result<Vec<NCReqDataMessage>, Box<dyn Error>> {
+        self.load_before(token, i32::MAX, count)
+    }
+
+    /// Read up to `count` messages of a room older than the message `before`, oldest first.
+    pub fn load_before(
+        &self,
+        token: &Token,
+        before: i32,
+        count: usize,
+    ) -> Result<Vec<NCReqDataMessage>, Box<dyn Error>> {
+        let mut messages = self.query_messages(
+            "SELECT data FROM messages WHERE token = ?1 AND id < ?2 ORDER BY id DESC LIMIT ?3",
+            params![token, before, count],
+        )?;
+        messages.reverse();
+        Ok(messages)
+    }
+
+    /// Read all stored messages of a room, oldest first.
+    pub fn load_all(&self, token: &Token) -> Result<Vec<NCReqDataMessage>, Box<dyn Error>> {
+        self.query_messages(
+            "SELECT data FROM messages WHERE token = ?1 ORDER BY id",
+            params![token],
+        )
+    }
+
+    /// Find up to `count` messages of a room containing `text`, ignoring case, newest first.
+    pub fn search(
+        &self,
+        token: &Token,
+        text: &str,
+        count: usize,
+    ) -> Result<Vec<NCReqDataMessage>, Box<dyn Error>> {
+        let pattern = format!(
+            "%{}%",
+            text.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        self.query_messages(
+            "SELECT data FROM messages WHERE token = ?1 AND message LIKE ?2 ESCAPE '\\'
+             ORDER BY id DESC LIMIT ?3",
+            params![token, pattern, count],
+        )
+    }
+
+    fn query_messages(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<NCReqDataMessage>, Box<dyn Error>> {
+        let connection = self.connection()?;
+        let mut statement = connection.prepare_cached(sql)?;
+        let messages = statement
+            .query_map(params, |row| row.get::<_, String>(0))?
+            .map(|data| -> Result<_, Box<dyn Error>> { Ok(serde_json::from_str(&data?)?) })
+            .collect();
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: i32, text: &str) -> NCReqDataMessage {
+        NCReqDataMessage {
+            id,
+            timestamp: i64::from(id) * 60,
+            message: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn ids(messages: &[NCReqDataMessage]) -> Vec<i32> {
+        messages.iter().map(|message| message.id).collect()
+    }
+
+    #[test]
+    fn insert_load_and_search() {
+        let store = MessageStore::in_memory().unwrap();
+        let token = Token::from("room");
+        assert!(!store.has_messages(&token).unwrap());
+        assert_eq!(store.last_id(&token).unwrap(), None);
+
+        let messages: Vec<_> = (1..=5)
+            .map(|id| message(id, if id % 2 == 0 { "Hello 100%" } else { "bye" }))
+            .collect();
+        store.insert_messages(&token, messages.iter()).unwrap();
+        store
+            .insert_messages(&token, std::iter::once(&message(3, "hello again")))
+            .unwrap();
+        store
+            .insert_messages(&Token::from("other"), std::iter::once(&message(9, "hello")))
+            .unwrap();
+
+        assert!(store.has_messages(&token).unwrap());
+        assert_eq!(store.last_id(&token).unwrap(), Some(5));
+        assert_eq!(ids(&store.load_recent(&token, 3).unwrap()), [3, 4, 5]);
+        assert_eq!(ids(&store.load_before(&token, 3, 10).unwrap()), [1, 2]);
+        assert_eq!(ids(&store.load_all(&token).unwrap()), [1, 2, 3, 4, 5]);
+        assert_eq!(ids(&store.search(&token, "HELLO", 10).unwrap()), [4, 3, 2]);
+        assert_eq!(ids(&store.search(&token, "0%", 1).unwrap()), [4]);
+        assert!(store.search(&token, "_", 10).unwrap().is_empty());
+
+        assert!(store.reference_ids(&token).unwrap().is_empty());
+        let referenced = NCReqDataMessage {
+            referenceId: "$event".to_string(),
+            ..message(6, "from matrix")
+        };
+        store
+            .insert_messages(&token, std::iter::once(&referenced))
+            .unwrap();
+        assert_eq!(
+            store.reference_ids(&token).unwrap(),
+            std::collections::HashSet::from(["$event".to_string()])
+        );
+    }
+
+    #[test]
+    fn import_json_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        let rooms = HashMap::from([
+            (
+                Token::from("array"),
+                NCReqDataRoom {
+                    token: Token::from("array"),
+                    ..Default::default()
+                },
+            ),
+            (
+                Token::from("lines"),
+                NCReqDataRoom {
+                    token: Token::from("lines"),
+                    ..Default::default()
+                },
+            ),
+        ]);
+        std::fs::write(
+            dir.path().join("Talk.json"),
+            serde_json::to_string(&rooms).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("array"),
+            serde_json::to_string(&[message(1, "a"), message(2, "b")]).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("lines"),
+            format!(
+                "{}\n{}\n{{\"id\": 3",
+                serde_json::to_string(&message(1, "old")).unwrap(),
+                serde_json::to_string(&message(1, "new")).unwrap()
+            ),
+        )
+        .unwrap();
+
+        let store = MessageStore::open(dir.path()).unwrap();
+        assert_eq!(store.load_rooms().unwrap().len(), 2);
+        assert_eq!(
+            ids(&store.load_recent(&Token::from("array"), 10).unwrap()),
+            [1, 2]
+        );
+        let lines = store.load_recent(&Token::from("lines"), 10).unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].message, "new");
+        drop(store);
+
+        std::fs::remove_file(dir.path().join("Talk.json")).unwrap();
+        let store = MessageStore::open(dir.path()).unwrap();
+        assert_eq!(store.load_rooms().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn retry_failed_import() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Talk.json"), "{\"broken\"").unwrap();
+
+        let store = MessageStore::open(dir.path()).unwrap();
+        assert!(store.load_rooms().unwrap().is_empty());
+        assert_eq!(schema_version(&store.connection().unwrap()).unwrap(), 0);
+        drop(store);
+
+        let rooms = HashMap::from([(
+            Token::from("room"),
+            NCReqDataRoom {
+                token: Token::from("room"),
+                ..Default::default()
+            },
+        )]);
+        std::fs::write(
+            dir.path().join("Talk.json"),
+            serde_json::to_string(&rooms).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("room"), "[{\"broken\"").unwrap();
+        let store = MessageStore::open(dir.path()).unwrap();
+        assert_eq!(schema_version(&store.connection().unwrap()).unwrap(), 0);
+        drop(store);
+
+        std::fs::write(
+            dir.path().join("room"),
+            serde_json::to_string(&[message(1, "a")]).unwrap(),
+        )
+        .unwrap();
+        let store = MessageStore::open(dir.path()).unwrap();
+        assert_eq!(store.load_rooms().unwrap().len(), 1);
+        assert_eq!(store.last_id(&Token::from("room")).unwrap(), Some(1));
+        assert_eq!(
+            schema_version(&store.connection().unwrap()).unwrap(),
+            SCHEMA_VERSION
+        );
+    }
+}
@@ -1,9 +1,43 @@
 //! Backend for API Communication to NC Talk Server
+//!
+//! The UI only uses the re-exported [`ChatProvider`] and [`ChatRoom`] traits,
+//! with the [`ChatMessage`](chat_message::ChatMessage) and the data types of [`chat_types`].
 
-/// NC Talk Message Object
-pub mod nc_message;
+/// Talk Features supported by the Server
+pub mod capabilities;
+/// Provider agnostic Message Object
+pub mod chat_message;
+/// Provider agnostic Backend Interface
+pub mod chat_provider;
+/// Provider agnostic Room Interface
+pub mod chat_room;
+/// Provider agnostic Data Types of Participants, Users and Rooms
+pub mod chat_types;
+
+/// Experimental Matrix Provider, reusing the NC Talk Room Object
+#[cfg(feature = "matrix")]
+pub mod matrix;
+
+/// Export of Room Histories into Files
+pub mod export;
+/// Background fetch of the full history of a room
+pub mod history_fetch;
+/// Rooms muted only in this client
+pub mod local_mutes;
+/// Rooms and Messages cached in a Database
+pub mod message_store;
 /// NC Talk API Wrapper
 pub mod nc_request;
 /// NC Talk Room Object
 pub mod nc_room;
 pub mod nc_talk;
+/// Tiered Polling of Rooms
+pub mod poll_schedule;
+/// Dumps of Responses which could not be parsed
+pub mod request_dump;
+/// Counters of the Requests to the Server
+pub mod request_metrics;
+
+pub use chat_provider::ChatProvider;
+pub use chat_room::ChatRoom;
+pub use chat_types::Token;
@@ -2,16 +2,24 @@
 #![allow(unused_variables)]
 #![allow(dead_code)]
 
+pub mod nc_api_error;
+mod nc_guest;
+pub mod nc_login_flow;
+pub mod nc_long_poll;
+mod nc_req_data_capabilities;
 mod nc_req_data_message;
 mod nc_req_data_room;
 mod nc_req_data_user;
 mod nc_req_worker;
 mod nc_request_ocs_wrapper;
 pub mod nc_requester;
+mod nc_retry;
 
+pub use nc_api_error::NCApiError;
+pub use nc_req_data_capabilities::*;
 pub use nc_req_data_message::*;
 pub use nc_req_data_room::*;
 pub use nc_req_data_user::*;
 pub use nc_request_ocs_wrapper::*;
 
-pub type Token = String;
+pub use super::chat_types::Token;
@@ -0,0 +1,173 @@
+//! Errors of requests to the NC API, sorted into classes the UI can react to.
+use std::{fmt, path::PathBuf};
+
+use reqwest::StatusCode;
+use tokio::sync::mpsc::error::SendError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NCApiError {
+    /// The server did not accept the login or the password of a room.
+    AuthFailed(String),
+    /// The server limits the rate and did not answer the request.
+    RateLimited,
+    /// The room or the resource asked for does not exist (anymore).
+    NotFound(String),
+    /// The server could not be reached or the connection broke.
+    Network(String),
+    /// The response could not be parsed, `dump` is where it was written to, if dumping is on.
+    Parse {
+        reason: String,
+        dump: Option<PathBuf>,
+    },
+    /// The server answered with a status not covered by the other classes.
+    Status(StatusCode),
+    /// The request could not be built or its answer not be passed on.
+    Other(String),
+}
+
+impl NCApiError {
+    /// Sort an unexpected status of the server into its class.
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED => {
+                NCApiError::AuthFailed("the user or app password is wrong".to_string())
+            }
+            StatusCode::TOO_MANY_REQUESTS => NCApiError::RateLimited,
+            // The chat answers with "precondition failed" when the room is gone.
+            StatusCode::NOT_FOUND | StatusCode::PRECONDITION_FAILED => {
+                NCApiError::NotFound(status.to_string())
+            }
+            _ => NCApiError::Status(status),
+        }
+    }
+
+    /// What the user can do about the error, if anything.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            NCApiError::AuthFailed(_) => Some("Check the user and app password in the config."),
+            NCApiError::RateLimited => Some("Requests are paused until the server accepts them."),
+            NCApiError::NotFound(_) => Some("The room may have been deleted or left."),
+            NCApiError::Network(_) => Some("Check the connection, updates are tried again."),
+            NCApiError::Parse { dump: Some(_), .. } => Some("'D' shows what the server sent."),
+            NCApiError::Parse { dump: None, .. } | NCApiError::Status(_) | NCApiError::Other(_) => {
+                None
+            }
+        }
+    }
+}
+
+impl fmt::Display for NCApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NCApiError::AuthFailed(why) => write!(f, "Authentication failed, {why}"),
+            NCApiError::RateLimited => write!(f, "Rate limited by the server"),
+            NCApiError::NotFound(why) => write!(f, "Not found, {why}"),
+            NCApiError::Network(why) => write!(f, "Network error, {why}"),
+            NCApiError::Parse {
+                reason,
+                dump: Some(dump),
+            } => write!(
+                f,
+                "Could not parse the response, {reason}, dumped to {}",
+                dump.display()
+            ),
+            NCApiError::Parse { reason, dump: None } => {
+                write!(f, "Could not parse the response, {reason}")
+            }
+            NCApiError::Status(status) => write!(f, "The server answered {status}"),
+            NCApiError::Other(why) => write!(f, "{why}"),
+        }
+    }
+}
+
+impl std::error::Error for NCApiError {}
+
+impl From<reqwest::Error> for NCApiError {
+    fn from(why: reqwest::Error) -> Self {
+        if let Some(status) = why.status() {
+            NCApiError::from_status(status)
+        } else if why.is_decode() {
+            NCApiError::Parse {
+                reason: why.to_string(),
+                dump: None,
+            }
+        } else if why.is_builder() {
+            NCApiError::Other(why.to_string())
+        } else {
+            NCApiError::Network(why.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for NCApiError {
+    fn from(why: serde_json::Error) -> Self {
+        NCApiError::Parse {
+            reason: why.to_string(),
+            dump: None,
+        }
+    }
+}
+
+impl<T> From<SendError<T>> for NCApiError {
+    fn from(_: SendError<T>) -> Self {
+        NCApiError::Other("Queuing the request failed, the workers are shut down.".to_string())
+    }
+}
+
+impl From<&str> for NCApiError {
+    fn from(why: &str) -> Self {
+        NCApiError::Other(why.to_string())
+    }
+}
+
+impl From<String> for NCApiError {
+    fn from(why: String) -> Self {
+        NCApiError::Other(why)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classes() {
+        assert!(matches!(
+            NCApiError::from_status(StatusCode::UNAUTHORIZED),
+            NCApiError::AuthFailed(_)
+        ));
+        assert_eq!(
+            NCApiError::from_status(StatusCode::TOO_MANY_REQUESTS),
+            NCApiError::RateLimited
+        );
+        assert!(matches!(
+            NCApiError::from_status(StatusCode::PRECONDITION_FAILED),
+            NCApiError::NotFound(_)
+        ));
+        assert_eq!(
+            NCApiError::from_status(StatusCode::BAD_GATEWAY),
+            NCApiError::Status(StatusCode::BAD_GATEWAY)
+        );
+        assert!(matches!(
+            NCApiError::from(serde_json::from_str::<i32>("x").unwrap_err()),
+            NCApiError::Parse { dump: None, .. }
+        ));
+    }
+
+    #[test]
+    fn display() {
+        let error = NCApiError::Parse {
+            reason: "expected value".to_string(),
+            dump: Some(PathBuf::from("/tmp/dump.json")),
+        };
+        assert_eq!(
+            error.to_string(),
+            "Could not parse the response, expected value, dumped to /tmp/dump.json"
+        );
+        assert_eq!(error.hint(), Some("'D' shows what the server sent."));
+        assert_eq!(
+            NCApiError::Status(StatusCode::BAD_GATEWAY).to_string(),
+            "The server answered 502 Bad Gateway"
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Session of a guest in a public conversation, which the server keeps track of by cookies.
+use std::sync::{Arc, Mutex};
+
+use reqwest::{
+    header::{HeaderValue, COOKIE, SET_COOKIE},
+    RequestBuilder, Response,
+};
+
+/// Cookies the server set for the guest, shared by all workers so they act as the same guest.
+#[derive(Debug, Clone, Default)]
+pub struct GuestSession(Arc<Mutex<Vec<(String, String)>>>);
+
+impl GuestSession {
+    /// Keep the cookies set by the response, replacing older ones of the same name.
+    pub fn remember(&self, response: &Response) {
+        let Ok(mut cookies) = self.0.lock() else {
+            return;
+        };
+        for set_cookie in response.headers().get_all(SET_COOKIE) {
+            let Some((name, value)) = set_cookie
+                .to_str()
+                .ok()
+                .and_then(|set_cookie| set_cookie.split(';').next())
+                .and_then(|pair| pair.split_once('='))
+            else {
+                continue;
+            };
+            let (name, value) = (name.trim().to_string(), value.trim().to_string());
+            cookies.retain(|(known, _)| *known != name);
+            if value != "deleted" {
+                cookies.push((name, value));
+            }
+        }
+    }
+
+    /// Value of the `Cookie` header, `None` before the server set any cookie.
+    fn header(&self) -> Option<HeaderValue> {
+        let cookies = self.0.lock().ok()?;
+        if cookies.is_empty() {
+            return None;
+        }
+        let text = cookies
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join("; ");
+        let mut value = HeaderValue::from_str(&text).ok()?;
+        value.set_sensitive(true);
+        Some(value)
+    }
+
+    /// Send the cookies of the session along with the request.
+    pub fn apply(&self, request: RequestBuilder) -> RequestBuilder {
+        match self.header() {
+            Some(value) => request.header(COOKIE, value),
+            None => request,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[tokio::test]
+    async fn keeps_cookies() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut requests = vec![];
+            for set_cookies in [
+                "Set-Cookie: nc_session_id=abc; path=/; HttpOnly\r\nSet-Cookie: oc_token=1\r\n",
+                "Set-Cookie: nc_session_id=def; path=/\r\nSet-Cookie: oc_token=deleted\r\n",
+                "",
+            ] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = [0; 1024];
+                let read = stream.read(&mut buffer).await.unwrap();
+                requests.push(String::from_utf8_lossy(&buffer[..read]).to_lowercase());
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\n{set_cookies}Content-Length: 0\r\nConnection: close\r\n\r\n"
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+
+        let session = GuestSession::default();
+        let client = reqwest::Client::new();
+        for _ in 0..3 {
+            let response = session.apply(client.get(&url)).send().await.unwrap();
+            session.remember(&response);
+        }
+
+        let requests = server.await.unwrap();
+        assert!(!requests[0].contains("cookie:"));
+        assert!(requests[1].contains("cookie: nc_session_id=abc; oc_token=1\r\n"));
+        assert!(requests[2].contains("cookie: nc_session_id=def\r\n"));
+    }
+}
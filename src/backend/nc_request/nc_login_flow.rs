@@ -0,0 +1,118 @@
+//! [Login Flow v2](https://docs.nextcloud.com/server/latest/developer_manual/client_apis/LoginFlow/index.html#login-flow-v2),
+//! to get an app password by logging in through the browser.
+use std::{
+    error::Error,
+    time::{Duration, Instant},
+};
+
+use reqwest::{Client, ClientBuilder, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+
+/// Wait between asking the server if the login is done.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// The server drops a login flow after 20 minutes.
+const FLOW_TIMEOUT: Duration = Duration::from_mins(20);
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct NCReqDataLoginFlowPoll {
+    token: String,
+    endpoint: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct NCReqDataLoginFlow {
+    poll: NCReqDataLoginFlowPoll,
+    login: String,
+}
+
+/// Credentials the server hands out once the user logged in.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct NCReqDataLoginCredentials {
+    pub server: String,
+    pub loginName: String,
+    pub appPassword: String,
+}
+
+/// A started login, waiting for the user to log in at [`LoginFlow::login_url`].
+#[derive(Debug)]
+pub struct LoginFlow {
+    client: Client,
+    flow: NCReqDataLoginFlow,
+}
+
+impl LoginFlow {
+    /// Ask the server at `base_url` for a new login flow, `client` being set up by the config.
+    pub async fn start(base_url: &str, client: ClientBuilder) -> Result<Self, Box<dyn Error>> {
+        let client = client
+            .user_agent(concat!("sechat-rs/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        let url =
+            Url::parse(&(base_url.trim_end_matches('/').to_string() + "/index.php/login/v2"))?;
+        let flow = client
+            .post(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<NCReqDataLoginFlow>()
+            .await?;
+        Ok(LoginFlow { client, flow })
+    }
+
+    /// Page the user has to open to log in and grant access.
+    pub fn login_url(&self) -> &str {
+        &self.flow.login
+    }
+
+    /// Poll the server until the user logged in, or the flow timed out.
+    pub async fn wait_for_credentials(&self) -> Result<NCReqDataLoginCredentials, Box<dyn Error>> {
+        let url = Url::parse_with_params(
+            &self.flow.poll.endpoint,
+            [("token", self.flow.poll.token.as_str())],
+        )?;
+        let started = Instant::now();
+        while started.elapsed() < FLOW_TIMEOUT {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let response = self.client.post(url.clone()).send().await?;
+            match response.status() {
+                StatusCode::OK => return Ok(response.json::<NCReqDataLoginCredentials>().await?),
+                // Not logged in yet.
+                StatusCode::NOT_FOUND => log::trace!("Waiting for the login to finish."),
+                _ => {
+                    return Err(Box::new(
+                        response
+                            .error_for_status()
+                            .err()
+                            .ok_or("Failed to convert Err in reqwest")?,
+                    ))
+                }
+            }
+        }
+        Err("The login was not finished within 20 minutes.".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_flow() {
+        let flow: NCReqDataLoginFlow = serde_json::from_str(
+            r#"{"poll": {"token": "mQUYQdffOSAMJYtm8pVpkOsVqXt5hglnuSpO5EMbgJMNEPFGaiDe8OUjvrJ2WcYcBSLgqynu9jaPFvZHMl83ybMvp6aDIDARjTFIBpRWod6p32fL9LIpIStvc6k8Wrs1", "endpoint": "https://cloud.example.com/login/v2/poll"}, "login": "https://cloud.example.com/login/v2/flow/guyjGtcKPTKCi4epIRIupIexgJ8wNInMFSfHabACRPZUkmEaWZSM54bFkFuzWksbps7jmTFQjnSG2bO0rXpwrPzl7JrIWWYZ1CRFVrDFjENpoHOVpoFFxSfSPmSPGn5P"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            flow.poll.endpoint,
+            "https://cloud.example.com/login/v2/poll"
+        );
+        assert!(flow
+            .login
+            .starts_with("https://cloud.example.com/login/v2/flow/"));
+
+        let credentials: NCReqDataLoginCredentials = serde_json::from_str(
+            r#"{"server": "https://cloud.example.com", "loginName": "username", "appPassword": "yKTVA4zgxjfivy52WqD8kW3M2pKGQr6srmUXMipRdunxjPFripJn0GMfmtNOqOolYSuJ6sCN"}"#,
+        )
+        .unwrap();
+        assert_eq!(credentials.loginName, "username");
+    }
+}
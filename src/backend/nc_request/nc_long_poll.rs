@@ -0,0 +1,187 @@
+//! Long polling of the chat of a single room, so new messages arrive as soon as they are sent.
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio_util::sync::CancellationToken;
+
+use super::{nc_req_worker::NCRequestWorkerInterface, NCApiError, NCReqDataMessage, Token};
+
+/// Most messages fetched by a single long poll.
+const LONG_POLL_LIMIT: i32 = 100;
+/// Wait before polling again after a failed long poll, so a server outage is not hammered.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+/// Batches of messages held in the [`PushChannel`] until the backend takes them.
+const PUSH_CHANNEL_SIZE: usize = 10;
+
+/// New messages of a room, pushed by its [`LongPoll`].
+pub type PushedMessages = (Token, Vec<NCReqDataMessage>);
+
+/// Channel the long polls push new messages into, drained by the backend.
+#[derive(Debug)]
+pub struct PushChannel {
+    sender: Sender<PushedMessages>,
+    receiver: Receiver<PushedMessages>,
+}
+
+impl Default for PushChannel {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel(PUSH_CHANNEL_SIZE);
+        PushChannel { sender, receiver }
+    }
+}
+
+impl PushChannel {
+    pub fn sender(&self) -> Sender<PushedMessages> {
+        self.sender.clone()
+    }
+
+    /// Wait for the next pushed messages.
+    pub async fn recv(&mut self) -> Option<PushedMessages> {
+        self.receiver.recv().await
+    }
+
+    /// Take the next pushed messages without waiting, if there are any.
+    pub fn try_recv(&mut self) -> Option<PushedMessages> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// Background task long polling the chat of a room, stopped when dropped.
+#[derive(Debug)]
+pub struct LongPoll {
+    token: Token,
+    cancel_token: CancellationToken,
+}
+
+impl LongPoll {
+    /// Spawn the task, which pushes all messages after `last_message` into `sender`.
+    pub fn start<Worker: NCRequestWorkerInterface + 'static>(
+        worker: Arc<Worker>,
+        token: &Token,
+        last_message: i32,
+        sender: Sender<PushedMessages>,
+    ) -> Self {
+        let cancel_token = CancellationToken::new();
+        let cloned_cancel_token = cancel_token.clone();
+        let cloned_token = token.clone();
+        log::debug!("Starting to long poll {token} after {last_message}");
+
+        tokio::spawn(async move {
+            let mut last_message = last_message;
+            loop {
+                let response = tokio::select! {
+                    () = cloned_cancel_token.cancelled() => break,
+                    response = worker.long_poll_chat(&cloned_token, LONG_POLL_LIMIT, last_message) => response,
+                };
+                match response {
+                    Ok(messages) => {
+                        let Some(newest) = messages.iter().map(|message| message.id).max() else {
+                            continue;
+                        };
+                        last_message = newest;
+                        if sender.send((cloned_token.clone(), messages)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(NCApiError::NotFound(why)) => {
+                        log::warn!("Stopped long polling {cloned_token}, the room is gone: {why}");
+                        break;
+                    }
+                    Err(why) => {
+                        log::warn!("Long polling {cloned_token} failed: {why}");
+                        tokio::select! {
+                            () = cloned_cancel_token.cancelled() => break,
+                            () = tokio::time::sleep(RETRY_DELAY) => (),
+                        }
+                    }
+                }
+            }
+            log::debug!("Stopped long polling {cloned_token}");
+        });
+
+        LongPoll {
+            token: token.clone(),
+            cancel_token,
+        }
+    }
+
+    /// Token of the room which is polled.
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+}
+
+#[cfg(test)]
+impl LongPoll {
+    /// Long poll without a task, standing in for a started one in tests.
+    pub fn idle(token: &Token) -> Self {
+        LongPoll {
+            token: token.clone(),
+            cancel_token: CancellationToken::new(),
+        }
+    }
+}
+
+impl Drop for LongPoll {
+    fn drop(&mut self) {
+        self.cancel_token.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::nc_req_worker::MockNCRequestWorker;
+    use super::*;
+    use mockall::{predicate::eq, Sequence};
+
+    #[tokio::test]
+    async fn push_messages() {
+        let mut worker = MockNCRequestWorker::default();
+        let mut seq = Sequence::new();
+        let message = NCReqDataMessage {
+            id: 2,
+            ..Default::default()
+        };
+        worker
+            .expect_long_poll_chat()
+            .with(eq(Token::from("123")), eq(LONG_POLL_LIMIT), eq(1))
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|_, _, _| Ok(vec![]));
+        worker
+            .expect_long_poll_chat()
+            .with(eq(Token::from("123")), eq(LONG_POLL_LIMIT), eq(1))
+            .once()
+            .in_sequence(&mut seq)
+            .return_once(move |_, _, _| Ok(vec![message]));
+        worker
+            .expect_long_poll_chat()
+            .with(eq(Token::from("123")), eq(LONG_POLL_LIMIT), eq(2))
+            .returning(|_, _, _| Err("offline".into()));
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+        let long_poll = LongPoll::start(Arc::new(worker), &Token::from("123"), 1, tx);
+        assert_eq!(long_poll.token(), "123");
+
+        let (token, messages) = rx.recv().await.unwrap();
+        assert_eq!(token, "123");
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id, 2);
+
+        drop(long_poll);
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn stop_when_room_is_gone() {
+        let mut worker = MockNCRequestWorker::default();
+        worker
+            .expect_long_poll_chat()
+            .once()
+            .returning(|_, _, _| Err(NCApiError::NotFound("412 Precondition Failed".into())));
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+        let _long_poll = LongPoll::start(Arc::new(worker), &Token::from("123"), 1, tx);
+        assert!(rx.recv().await.is_none());
+    }
+}
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// Capabilities the server announces, only the ones of Talk are parsed.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct NCReqDataCapabilities {
+    pub capabilities: NCReqDataCapabilityApps,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct NCReqDataCapabilityApps {
+    /// Missing if Talk is not installed.
+    #[serde(default)]
+    pub spreed: Option<NCReqDataTalkCapabilities>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct NCReqDataTalkCapabilities {
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+}
@@ -10,6 +10,22 @@ pub struct NCReqDataMessageParameter {
     name: String,
 }
 
+impl NCReqDataMessageParameter {
+    /// Id of the referenced object, e.g. the user id of a mentioned user.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Check if this mentions the user, or everyone in the room.
+    pub fn mentions(&self, user_id: &str) -> bool {
+        match self.param_type.as_str() {
+            "user" => self.id == user_id,
+            "call" => true,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct NCReqDataMessage {
     pub id: i32,
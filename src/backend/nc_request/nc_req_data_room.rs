@@ -1,4 +1,5 @@
 use super::{NCReqDataMessage, Token};
+use crate::backend::chat_types::ListedRoom;
 use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
@@ -55,6 +56,10 @@ pub struct NCReqDataRoom {
     pub callStartTime: i32,
     pub callRecording: i32,
     pub recordingConsent: i32,
+    #[serde(default)]
+    pub isArchived: bool,
+    #[serde(default)]
+    pub mentionPermissions: i32,
 }
 
 fn arr_or_message<'de, D>(deserializer: D) -> Result<NCReqDataMessage, D::Error>
@@ -73,3 +78,13 @@ where
         NCReqDataMessageVec::Vec(_) => NCReqDataMessage::default(),
     })
 }
+
+impl From<NCReqDataRoom> for ListedRoom {
+    fn from(data: NCReqDataRoom) -> Self {
+        ListedRoom {
+            token: data.token,
+            display_name: data.displayName,
+            description: data.description,
+        }
+    }
+}
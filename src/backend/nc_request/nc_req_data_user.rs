@@ -1,37 +1,51 @@
+use crate::backend::chat_types::{FoundUser, OutOfOffice, UserStatus};
 use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct NCReqDataParticipants {
-    attendeeId: i32,
-    actorType: String,
-    actorId: String,
+    pub attendeeId: i32,
+    pub actorType: String,
+    pub actorId: String,
     pub displayName: String,
-    participantType: i32,
+    pub participantType: i32,
     lastPing: i32,
-    inCall: i32,
+    pub inCall: i32,
     permissions: i32,
     attendeePermissions: i32,
     sessionIds: Vec<String>,
     pub status: Option<String>,
     pub statusIcon: Option<String>,
     pub statusMessage: Option<String>,
-    statusClearAt: Option<i32>,
+    pub statusClearAt: Option<i32>,
     roomToken: Option<String>,
     phoneNumber: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct NCReqDataUserStatus {
-    status: String,
-    message: Option<String>,
-    icon: Option<String>,
+    pub status: String,
+    pub message: Option<String>,
+    pub icon: Option<String>,
     clearAt: Option<i32>,
 }
 
+/// Absence of a user right now, as configured in the out of office settings.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct NCReqDataOutOfOffice {
+    pub userId: String,
+    /// Unix timestamps of the first and last day of the absence.
+    pub startDate: i64,
+    pub endDate: i64,
+    #[serde(default)]
+    pub shortMessage: String,
+    #[serde(default)]
+    pub message: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct NCReqDataUser {
-    id: String,
-    label: String,
+    pub id: String,
+    pub label: String,
     icon: String,
     source: String,
     #[serde(deserialize_with = "str_or_status")]
@@ -40,6 +54,34 @@ pub struct NCReqDataUser {
     shareWithDisplayNameUnique: String,
 }
 
+impl From<NCReqDataUserStatus> for UserStatus {
+    fn from(data: NCReqDataUserStatus) -> Self {
+        UserStatus {
+            status: data.status,
+            message: data.message,
+            icon: data.icon,
+        }
+    }
+}
+
+impl From<NCReqDataOutOfOffice> for OutOfOffice {
+    fn from(data: NCReqDataOutOfOffice) -> Self {
+        OutOfOffice {
+            end_date: data.endDate,
+            short_message: data.shortMessage,
+        }
+    }
+}
+
+impl From<NCReqDataUser> for FoundUser {
+    fn from(data: NCReqDataUser) -> Self {
+        FoundUser {
+            id: data.id,
+            display_name: data.label,
+        }
+    }
+}
+
 fn str_or_status<'de, D>(deserializer: D) -> Result<NCReqDataUserStatus, D::Error>
 where
     D: Deserializer<'de>,
@@ -5,115 +5,267 @@
 use crate::config::Config;
 use async_trait::async_trait;
 use base64::{prelude::BASE64_STANDARD, write::EncoderWriter};
-use jzon;
 use reqwest::{
     header::{HeaderMap, HeaderValue, AUTHORIZATION},
-    Client, Response, Url,
+    Client, Method, RequestBuilder, Response, Url,
 };
 use std::fmt::Debug;
-use std::{collections::HashMap, error::Error};
+use std::{borrow::Borrow, collections::HashMap, error::Error, path::PathBuf, time::Instant};
+use tracing::{field, Instrument};
+
+use crate::backend::{
+    request_dump::{write_dump, RequestDumps},
+    request_metrics::RequestMetrics,
+};
 
 use super::{
-    NCReqDataMessage, NCReqDataParticipants, NCReqDataRoom, NCReqDataUser, NCReqOCSWrapper, Token,
+    nc_guest::GuestSession,
+    nc_retry::{RateLimit, RetryPolicy},
+    NCApiError, NCReqDataCapabilities, NCReqDataMessage, NCReqDataOutOfOffice,
+    NCReqDataParticipants, NCReqDataRoom, NCReqDataUser, NCReqDataUserStatus, NCReqOCSWrapper,
+    Token,
 };
 
+fn parse_url(url: &str) -> Result<Url, NCApiError> {
+    Url::parse(url).map_err(|why| NCApiError::Other(format!("Invalid url {url}: {why}")))
+}
+
+fn parse_url_with_params<I, K, V>(url: &str, params: I) -> Result<Url, NCApiError>
+where
+    I: IntoIterator,
+    I::Item: Borrow<(K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    Url::parse_with_params(url, params)
+        .map_err(|why| NCApiError::Other(format!("Invalid url {url}: {why}")))
+}
+
+/// Token of the room a Talk endpoint like `/ocs/v2.php/apps/spreed/api/v4/room/<token>/...` is
+/// about, empty for other endpoints and the ones about all rooms.
+fn endpoint_token(path: &str) -> &str {
+    path.split_once("/apps/spreed/api/")
+        .and_then(|(_, endpoint)| endpoint.split('/').nth(2))
+        .unwrap_or_default()
+}
+
+/// Seconds the server holds back the answer to a long poll, when there are no new messages.
+const LONG_POLL_TIMEOUT: u32 = 30;
+
 #[derive(Debug)]
 pub struct NCRequestWorker {
     base_url: String,
+    /// Login name of the user, naming the folder of the files uploaded.
+    user: String,
     client: Client,
     base_headers: HeaderMap,
+    retry: RetryPolicy,
     json_dump_path: Option<std::path::PathBuf>,
+    dumps: RequestDumps,
+    metrics: RequestMetrics,
+    /// Cookies of the guest session, `None` when logged in as a user.
+    guest_session: Option<GuestSession>,
 }
 
 #[async_trait]
 pub trait NCRequestWorkerInterface: Debug + Send + Send + Sync + Sized {
     fn new(config: &Config) -> Result<Self, Box<dyn Error>>;
-    async fn mark_chat_read(&self, token: &str, last_message: i32) -> Result<(), Box<dyn Error>>;
+    async fn mark_chat_read(&self, token: &str, last_message: i32) -> Result<(), NCApiError>;
     async fn send_message(
         &self,
         message: String,
         token: &Token,
-    ) -> Result<NCReqDataMessage, Box<dyn Error>>;
-    async fn fetch_autocomplete_users(
-        &self,
-        name: &str,
-    ) -> Result<Vec<NCReqDataUser>, Box<dyn Error>>;
+    ) -> Result<NCReqDataMessage, NCApiError>;
+    async fn fetch_autocomplete_users(&self, name: &str) -> Result<Vec<NCReqDataUser>, NCApiError>;
     async fn fetch_participants(
         &self,
         token: &Token,
-    ) -> Result<Vec<NCReqDataParticipants>, Box<dyn Error>>;
-    async fn fetch_rooms_initial(&self) -> Result<(Vec<NCReqDataRoom>, i64), Box<dyn Error>>;
+    ) -> Result<Vec<NCReqDataParticipants>, NCApiError>;
+    async fn fetch_rooms_initial(&self) -> Result<(Vec<NCReqDataRoom>, i64), NCApiError>;
 
     async fn fetch_rooms_update(
         &self,
         last_timestamp: i64,
-    ) -> Result<(Vec<NCReqDataRoom>, i64), Box<dyn Error>>;
+    ) -> Result<(Vec<NCReqDataRoom>, i64), NCApiError>;
     async fn fetch_chat_initial(
         &self,
         token: &Token,
         maxMessage: i32,
-    ) -> Result<Vec<NCReqDataMessage>, Box<dyn Error>>;
+    ) -> Result<Vec<NCReqDataMessage>, NCApiError>;
     async fn fetch_chat_update(
         &self,
         token: &Token,
         maxMessage: i32,
         last_message: i32,
-    ) -> Result<Vec<NCReqDataMessage>, Box<dyn Error>>;
+    ) -> Result<Vec<NCReqDataMessage>, NCApiError>;
+    async fn fetch_chat_history(
+        &self,
+        token: &Token,
+        maxMessage: i32,
+        first_message: i32,
+    ) -> Result<Vec<NCReqDataMessage>, NCApiError>;
+    async fn long_poll_chat(
+        &self,
+        token: &Token,
+        maxMessage: i32,
+        last_message: i32,
+    ) -> Result<Vec<NCReqDataMessage>, NCApiError>;
+    async fn set_favorite(&self, token: &str, favorite: bool) -> Result<(), NCApiError>;
+    async fn set_notification_level(&self, token: &str, level: i32) -> Result<(), NCApiError>;
+    async fn set_archived(&self, token: &str, archived: bool) -> Result<(), NCApiError>;
+    async fn add_participant(&self, token: &str, user_id: &str) -> Result<(), NCApiError>;
+    async fn remove_attendee(&self, token: &str, attendee_id: i32) -> Result<(), NCApiError>;
+    async fn set_moderator(
+        &self,
+        token: &str,
+        attendee_id: i32,
+        moderator: bool,
+    ) -> Result<(), NCApiError>;
+    async fn ban_attendee(
+        &self,
+        token: &str,
+        actor_type: &str,
+        actor_id: &str,
+    ) -> Result<(), NCApiError>;
+    async fn set_room_name(&self, token: &str, name: &str) -> Result<(), NCApiError>;
+    async fn set_room_description(&self, token: &str, description: &str) -> Result<(), NCApiError>;
+    async fn send_reply(
+        &self,
+        message: String,
+        token: &Token,
+        reply_to: i32,
+    ) -> Result<NCReqDataMessage, NCApiError>;
+    async fn edit_message(
+        &self,
+        token: &str,
+        message_id: i32,
+        message: &str,
+    ) -> Result<(), NCApiError>;
+    async fn delete_message(&self, token: &str, message_id: i32) -> Result<(), NCApiError>;
+    async fn react_to_message(
+        &self,
+        token: &str,
+        message_id: i32,
+        reaction: &str,
+    ) -> Result<(), NCApiError>;
+    async fn delete_reaction(
+        &self,
+        token: &str,
+        message_id: i32,
+        reaction: &str,
+    ) -> Result<(), NCApiError>;
+    async fn set_reminder(
+        &self,
+        token: &str,
+        message_id: i32,
+        timestamp: i64,
+    ) -> Result<(), NCApiError>;
+    async fn pin_message(&self, token: &str, message_id: i32) -> Result<(), NCApiError>;
+    async fn set_room_password(&self, token: &str, password: &str) -> Result<(), NCApiError>;
+    async fn set_mention_permissions(
+        &self,
+        token: &str,
+        permissions: i32,
+    ) -> Result<(), NCApiError>;
+    async fn set_message_expiration(&self, token: &str, seconds: i32) -> Result<(), NCApiError>;
+    async fn fetch_listed_rooms(&self, search: &str) -> Result<Vec<NCReqDataRoom>, NCApiError>;
+    async fn join_room(&self, token: &str) -> Result<NCReqDataRoom, NCApiError>;
+    async fn leave_room(&self, token: &str) -> Result<(), NCApiError>;
+    async fn create_dm(&self, user_id: &str) -> Result<NCReqDataRoom, NCApiError>;
+    async fn set_read_status_privacy(&self, private: bool) -> Result<(), NCApiError>;
+    async fn fetch_capabilities(&self) -> Result<NCReqDataCapabilities, NCApiError>;
+    async fn join_as_guest(
+        &self,
+        token: &str,
+        password: Option<String>,
+        name: Option<String>,
+    ) -> Result<NCReqDataRoom, NCApiError>;
+    async fn share_file(&self, token: &str, name: &str, data: Vec<u8>) -> Result<(), NCApiError>;
+    async fn fetch_user_status(&self) -> Result<NCReqDataUserStatus, NCApiError>;
+    async fn fetch_out_of_office(
+        &self,
+        user_id: &str,
+    ) -> Result<Option<NCReqDataOutOfOffice>, NCApiError>;
+    async fn set_user_status(&self, status: &str) -> Result<(), NCApiError>;
+    async fn set_user_status_message(
+        &self,
+        message: &str,
+        icon: Option<String>,
+    ) -> Result<(), NCApiError>;
 }
 
 impl NCRequestWorker {
+    /// Share the rate limit with the other workers talking to the same server.
+    pub fn set_rate_limit(&mut self, rate_limit: RateLimit) {
+        self.retry.set_rate_limit(rate_limit);
+    }
+
+    /// Act as the same guest as the other workers.
+    pub fn set_guest_session(&mut self, session: GuestSession) {
+        self.guest_session = Some(session);
+    }
+
+    /// Collect the dumps of unparsable responses together with the other workers.
+    pub fn set_dumps(&mut self, dumps: RequestDumps) {
+        self.dumps = dumps;
+    }
+
+    /// Count retries and received messages together with the other workers.
+    pub fn set_metrics(&mut self, metrics: RequestMetrics) {
+        self.retry.set_metrics(metrics.clone());
+        self.metrics = metrics;
+    }
+
     async fn request_rooms(
         &self,
         last_timestamp: Option<i64>,
-    ) -> Result<(Vec<NCReqDataRoom>, i64), Box<dyn Error>> {
+    ) -> Result<(Vec<NCReqDataRoom>, i64), NCApiError> {
         let url_string = self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v4/room";
         let params = if let Some(timestamp) = last_timestamp {
             HashMap::from([("modifiedSince", timestamp.to_string())])
         } else {
             HashMap::new()
         };
-        let url = Url::parse_with_params(&url_string, &params)?;
+        let url = parse_url_with_params(&url_string, &params)?;
         let response = self.request(url).await?;
         match response.status() {
             reqwest::StatusCode::OK => {
                 let timestamp = response
                     .headers()
                     .get("X-Nextcloud-Talk-Modified-Before")
-                    .ok_or("Failed to get header")?
-                    .to_str()?
-                    .parse::<i64>()?;
+                    .and_then(|value| value.to_str().ok()?.parse::<i64>().ok())
+                    .ok_or("Failed to get header")?;
                 let text = response.text().await?;
                 match serde_json::from_str::<NCReqOCSWrapper<Vec<NCReqDataRoom>>>(&text) {
                     Ok(parser_response) => Ok((parser_response.ocs.data, timestamp)),
-                    Err(why) => {
-                        self.dump_json_to_log(&url_string, &text)?;
-                        Err(Box::new(why))
-                    }
+                    Err(why) => Err(self.parse_error(&url_string, &text, &why)),
                 }
             }
-            _ => Err(Box::new(
-                response
-                    .error_for_status()
-                    .err()
-                    .ok_or("Failed to convert Err in reqwest")?,
-            )),
+            _ => Err(NCApiError::from_status(response.status())),
         }
     }
 
+    /// Fetch messages after `last_message`, or before it if `look_into_future` is false.
+    /// With a `timeout` the server waits up to that many seconds for new messages.
     async fn request_chat(
         &self,
         token: &str,
         maxMessage: i32,
         last_message: Option<i32>,
-    ) -> Result<Option<Vec<NCReqDataMessage>>, Box<dyn Error>> {
+        look_into_future: bool,
+        timeout: u32,
+    ) -> Result<Option<Vec<NCReqDataMessage>>, NCApiError> {
         let url_string = self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v1/chat/" + token;
         let params = if let Some(lastId) = last_message {
             HashMap::from([
                 ("limit", maxMessage.to_string()),
                 ("setReadMarker", "0".into()),
-                ("lookIntoFuture", "1".into()),
+                (
+                    "lookIntoFuture",
+                    if look_into_future { "1" } else { "0" }.into(),
+                ),
                 ("lastKnownMessageId", lastId.to_string()),
-                ("timeout", "0".into()),
+                ("timeout", timeout.to_string()),
                 ("includeLastKnown", "0".into()),
             ])
         } else {
@@ -123,56 +275,158 @@ impl NCRequestWorker {
                 ("lookIntoFuture", "0".into()),
             ])
         };
-        let url = Url::parse_with_params(&url_string, &params)?;
+        let url = parse_url_with_params(&url_string, &params)?;
         let response = self.request(url).await?;
         match response.status() {
             reqwest::StatusCode::OK => {
                 let text = response.text().await?;
                 match serde_json::from_str::<NCReqOCSWrapper<Vec<NCReqDataMessage>>>(&text) {
-                    Ok(parser_response) => Ok(Some(parser_response.ocs.data)),
-                    Err(why) => {
-                        self.dump_json_to_log(&url_string, &text)?;
-                        Err(Box::new(why))
+                    Ok(parser_response) => {
+                        self.metrics.record_received(parser_response.ocs.data.len());
+                        Ok(Some(parser_response.ocs.data))
                     }
+                    Err(why) => Err(self.parse_error(&url_string, &text, &why)),
                 }
             }
             reqwest::StatusCode::NOT_MODIFIED => Ok(Some(Vec::new())),
             reqwest::StatusCode::PRECONDITION_FAILED => Ok(None),
             _ => {
-                log::debug!("{} got Err {:?}", token, response);
-                Err(Box::new(
-                    response
-                        .error_for_status()
-                        .err()
-                        .ok_or("Failed to convert Error")?,
-                ))
+                tracing::debug!("{} got Err {:?}", token, response);
+                Err(NCApiError::from_status(response.status()))
             }
         }
     }
 
+    /// Send the request with the cookies of the guest session, if there is one.
+    async fn send(&self, method: Method, url: Url) -> Result<Response, reqwest::Error> {
+        let request = self.client.request(method.clone(), url);
+        self.send_request(method, request).await
+    }
+
+    async fn send_request(
+        &self,
+        method: Method,
+        mut request: RequestBuilder,
+    ) -> Result<Response, reqwest::Error> {
+        // Bodies which are streamed, like uploads, can not be cloned to look at the url.
+        let url = request
+            .try_clone()
+            .and_then(|request| request.build().ok())
+            .map(|request| request.url().clone());
+        let endpoint = url.as_ref().map_or("", Url::path);
+        let span = tracing::debug_span!(
+            "request",
+            %method,
+            endpoint,
+            token = endpoint_token(endpoint),
+            status = field::Empty,
+            duration_ms = field::Empty,
+        );
+        if let Some(session) = &self.guest_session {
+            request = session.apply(request);
+        }
+        let start = Instant::now();
+        let result = self
+            .retry
+            .send(&method, request)
+            .instrument(span.clone())
+            .await;
+        span.record("duration_ms", start.elapsed().as_millis());
+        let response = result.inspect_err(|why| {
+            tracing::debug!(parent: &span, "{method} {endpoint} failed: {why}");
+        })?;
+        span.record("status", response.status().as_u16());
+        if let Some(session) = &self.guest_session {
+            session.remember(&response);
+        }
+        Ok(response)
+    }
+
     async fn request_post(&self, url: Url) -> Result<Response, reqwest::Error> {
-        let builder = self.client.post(url);
-        builder.send().await
+        self.send(Method::POST, url).await
+    }
+
+    async fn request_put(&self, url: Url) -> Result<Response, reqwest::Error> {
+        self.send(Method::PUT, url).await
+    }
+
+    async fn request_delete(&self, url: Url) -> Result<Response, reqwest::Error> {
+        self.send(Method::DELETE, url).await
+    }
+
+    /// Toggle a room level flag, which the API sets via POST and clears via DELETE.
+    async fn toggle_room_flag(
+        &self,
+        token: &str,
+        flag: &str,
+        enable: bool,
+    ) -> Result<(), NCApiError> {
+        let url_string =
+            self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v4/room/" + token + "/" + flag;
+        let url = parse_url(&url_string)?;
+        tracing::trace!("Setting {flag} of {token} to {enable}");
+        let response = if enable {
+            self.request_post(url).await?
+        } else {
+            self.request_delete(url).await?
+        };
+        match response.status() {
+            reqwest::StatusCode::OK => Ok(()),
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    /// Url of a single chat message, optionally followed by a sub resource like "reminder".
+    fn chat_message_url(&self, api: &str, token: &str, message_id: i32, suffix: &str) -> String {
+        let mut url_string = format!(
+            "{}/ocs/v2.php/apps/spreed/api/v1/{api}/{token}/{message_id}",
+            self.base_url
+        );
+        if !suffix.is_empty() {
+            url_string = url_string + "/" + suffix;
+        }
+        url_string
+    }
+
+    /// Build the request setting the password of a room, sent in the body like the guest password.
+    fn room_password_request(
+        &self,
+        token: &str,
+        password: &str,
+    ) -> Result<RequestBuilder, NCApiError> {
+        let url_string =
+            self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v4/room/" + token + "/password";
+        let url = parse_url(&url_string)?;
+        Ok(self.client.put(url).form(&[("password", password)]))
+    }
+
+    /// Accept every success status, the message endpoints answer with 200, 201 or 202.
+    fn success_or_error(response: &Response) -> Result<(), NCApiError> {
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(NCApiError::from_status(response.status()))
+        }
     }
 
     async fn request(&self, url: Url) -> Result<Response, reqwest::Error> {
-        let builder = self.client.get(url);
-        builder.send().await
+        self.send(Method::GET, url).await
     }
 
-    fn dump_json_to_log(&self, url: &str, text: &str) -> Result<(), Box<dyn Error>> {
-        use std::io::Write;
+    /// Dump the response which could not be parsed, returning where to find it.
+    fn dump_json_to_log(&self, url: &str, text: &str) -> Option<PathBuf> {
+        let path = self.json_dump_path.as_ref()?;
+        write_dump(path, url, text, &self.dumps)
+            .inspect_err(|why| tracing::warn!("Could not dump the response of {url}: {why}"))
+            .ok()
+    }
 
-        if let Some(path) = &self.json_dump_path {
-            let name: String = url
-                .chars()
-                .map(|ch| if ch == '/' { '_' } else { ch })
-                .collect();
-            let mut file = std::fs::File::create(name)?;
-            let pretty_text = jzon::stringify_pretty(jzon::parse(text)?, 2);
-            file.write_all(pretty_text.as_bytes())?;
+    fn parse_error(&self, url: &str, text: &str, why: &serde_json::Error) -> NCApiError {
+        tracing::debug!("{url} with {why:?}");
+        NCApiError::Parse {
+            reason: why.to_string(),
+            dump: self.dump_json_to_log(url, text),
         }
-        Ok(())
     }
 }
 
@@ -184,7 +438,6 @@ impl NCRequestWorkerInterface for NCRequestWorker {
         let general = &config.data.general;
 
         let username = general.user.clone();
-        let password = Some(general.app_pw.clone());
         let base_url = general.url.clone();
 
         let json_dump_path = config.get_http_dump_dir();
@@ -192,31 +445,41 @@ impl NCRequestWorkerInterface for NCRequestWorker {
         headers.insert("OCS-APIRequest", HeaderValue::from_static("true"));
         headers.insert("Accept", HeaderValue::from_static("application/json"));
 
-        let mut buf = b"Basic ".to_vec();
-        {
-            let mut encoder = EncoderWriter::new(&mut buf, &BASE64_STANDARD);
-            write!(encoder, "{username}:").expect("i/o error");
-            if let Some(password) = password {
-                write!(encoder, "{password}").expect("i/o error");
+        // Guests have no login, the server knows them by the cookies of their session.
+        if config.guest.is_none() {
+            let password = Some(config.get_app_password()?);
+            let mut buf = b"Basic ".to_vec();
+            {
+                let mut encoder = EncoderWriter::new(&mut buf, &BASE64_STANDARD);
+                write!(encoder, "{username}:").expect("i/o error");
+                if let Some(password) = password {
+                    write!(encoder, "{password}").expect("i/o error");
+                }
             }
+            let mut auth_value =
+                HeaderValue::from_bytes(&buf).expect("base64 is always valid HeaderValue");
+            auth_value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, auth_value);
         }
-        let mut auth_value =
-            HeaderValue::from_bytes(&buf).expect("base64 is always valid HeaderValue");
-        auth_value.set_sensitive(true);
-        headers.insert(AUTHORIZATION, auth_value);
 
         // get a client builder
-        let client = reqwest::Client::builder()
+        let client = config
+            .http_client()?
             .default_headers(headers.clone())
             .build()?;
 
-        log::trace!("Worker Ready {}", base_url.to_string());
+        tracing::trace!("Worker Ready {base_url}");
 
         Ok(NCRequestWorker {
-            base_url: base_url.to_string(),
+            base_url,
+            user: username,
             client,
             base_headers: headers,
+            retry: RetryPolicy::new(config),
             json_dump_path,
+            dumps: RequestDumps::default(),
+            metrics: RequestMetrics::default(),
+            guest_session: None,
         })
     }
 
@@ -224,10 +487,10 @@ impl NCRequestWorkerInterface for NCRequestWorker {
         &self,
         message: String,
         token: &Token,
-    ) -> Result<NCReqDataMessage, Box<dyn Error>> {
+    ) -> Result<NCReqDataMessage, NCApiError> {
         let url_string = self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v1/chat/" + token;
         let params = HashMap::from([("message", message)]);
-        let url = Url::parse_with_params(&url_string, params)?;
+        let url = parse_url_with_params(&url_string, params)?;
         let response = self.request_post(url).await?;
 
         match response.status() {
@@ -236,22 +499,14 @@ impl NCRequestWorkerInterface for NCRequestWorker {
                 .await?
                 .ocs
                 .data),
-            _ => Err(Box::new(
-                response
-                    .error_for_status()
-                    .err()
-                    .ok_or("Failed to convert Err in reqwest")?,
-            )),
+            _ => Err(NCApiError::from_status(response.status())),
         }
     }
 
-    async fn fetch_autocomplete_users(
-        &self,
-        name: &str,
-    ) -> Result<Vec<NCReqDataUser>, Box<dyn Error>> {
+    async fn fetch_autocomplete_users(&self, name: &str) -> Result<Vec<NCReqDataUser>, NCApiError> {
         let url_string = self.base_url.clone() + "/ocs/v2.php/core/autocomplete/get";
         let params = HashMap::from([("limit", "200"), ("search", name)]);
-        let url = Url::parse_with_params(&url_string, params)?;
+        let url = parse_url_with_params(&url_string, params)?;
         let response = self.request(url).await?;
 
         match response.status() {
@@ -259,32 +514,23 @@ impl NCRequestWorkerInterface for NCRequestWorker {
                 let text = response.text().await?;
                 match serde_json::from_str::<NCReqOCSWrapper<Vec<NCReqDataUser>>>(&text) {
                     Ok(parser_response) => Ok(parser_response.ocs.data),
-                    Err(why) => {
-                        self.dump_json_to_log(&url_string, &text)?;
-                        log::debug!("{} with {:?}", url_string, why);
-                        Err(Box::new(why))
-                    }
+                    Err(why) => Err(self.parse_error(&url_string, &text, &why)),
                 }
             }
-            _ => Err(Box::new(
-                response
-                    .error_for_status()
-                    .err()
-                    .ok_or("Failed to convert Err in reqwest")?,
-            )),
+            _ => Err(NCApiError::from_status(response.status())),
         }
     }
 
     async fn fetch_participants(
         &self,
         token: &Token,
-    ) -> Result<Vec<NCReqDataParticipants>, Box<dyn Error>> {
+    ) -> Result<Vec<NCReqDataParticipants>, NCApiError> {
         let url_string = self.base_url.clone()
             + "/ocs/v2.php/apps/spreed/api/v4/room/"
             + token
             + "/participants";
         let params = HashMap::from([("includeStatus", "true")]);
-        let url = Url::parse_with_params(&url_string, params)?;
+        let url = parse_url_with_params(&url_string, params)?;
 
         let response = self.request(url).await?;
         match response.status() {
@@ -292,30 +538,21 @@ impl NCRequestWorkerInterface for NCRequestWorker {
                 let text = response.text().await?;
                 match serde_json::from_str::<NCReqOCSWrapper<Vec<NCReqDataParticipants>>>(&text) {
                     Ok(parser_response) => Ok(parser_response.ocs.data),
-                    Err(why) => {
-                        self.dump_json_to_log(&url_string, &text)?;
-                        log::debug!("{} with {:?}", url_string, why);
-                        Err(Box::new(why))
-                    }
+                    Err(why) => Err(self.parse_error(&url_string, &text, &why)),
                 }
             }
-            _ => Err(Box::new(
-                response
-                    .error_for_status()
-                    .err()
-                    .ok_or("Failed to convert Err in reqwest")?,
-            )),
+            _ => Err(NCApiError::from_status(response.status())),
         }
     }
 
-    async fn fetch_rooms_initial(&self) -> Result<(Vec<NCReqDataRoom>, i64), Box<dyn Error>> {
+    async fn fetch_rooms_initial(&self) -> Result<(Vec<NCReqDataRoom>, i64), NCApiError> {
         self.request_rooms(None).await
     }
 
     async fn fetch_rooms_update(
         &self,
         last_timestamp: i64,
-    ) -> Result<(Vec<NCReqDataRoom>, i64), Box<dyn Error>> {
+    ) -> Result<(Vec<NCReqDataRoom>, i64), NCApiError> {
         self.request_rooms(Some(last_timestamp)).await
     }
 
@@ -323,15 +560,17 @@ impl NCRequestWorkerInterface for NCRequestWorker {
         &self,
         token: &Token,
         maxMessage: i32,
-    ) -> Result<Vec<NCReqDataMessage>, Box<dyn Error>> {
-        let response_result = self.request_chat(token, maxMessage, None).await;
+    ) -> Result<Vec<NCReqDataMessage>, NCApiError> {
+        let response_result = self.request_chat(token, maxMessage, None, false, 0).await;
         // Initial results come last to first. And we want the latest message always to be at the end.
         match response_result {
             Ok(Some(mut response)) => {
                 response.reverse();
                 Ok(response)
             }
-            Ok(None) => Err(String::from("Room disappeared, precondition not met error.").into()),
+            Ok(None) => Err(NCApiError::NotFound(
+                "room disappeared, precondition not met".to_string(),
+            )),
             Err(why) => Err(why),
         }
     }
@@ -341,33 +580,590 @@ impl NCRequestWorkerInterface for NCRequestWorker {
         token: &Token,
         maxMessage: i32,
         last_message: i32,
-    ) -> Result<Vec<NCReqDataMessage>, Box<dyn Error>> {
+    ) -> Result<Vec<NCReqDataMessage>, NCApiError> {
         let response_result = self
-            .request_chat(token, maxMessage, Some(last_message))
+            .request_chat(token, maxMessage, Some(last_message), true, 0)
             .await;
         match response_result {
             Ok(Some(response)) => Ok(response),
-            Ok(None) => Err(String::from("Room disappeared, precondition not met error.").into()),
+            Ok(None) => Err(NCApiError::NotFound(
+                "room disappeared, precondition not met".to_string(),
+            )),
+            Err(why) => Err(why),
+        }
+    }
+
+    async fn fetch_chat_history(
+        &self,
+        token: &Token,
+        maxMessage: i32,
+        first_message: i32,
+    ) -> Result<Vec<NCReqDataMessage>, NCApiError> {
+        let response_result = self
+            .request_chat(token, maxMessage, Some(first_message), false, 0)
+            .await;
+        // Older messages come last to first as well.
+        match response_result {
+            Ok(Some(mut response)) => {
+                response.reverse();
+                Ok(response)
+            }
+            Ok(None) => Err(NCApiError::NotFound(
+                "room disappeared, precondition not met".to_string(),
+            )),
+            Err(why) => Err(why),
+        }
+    }
+
+    async fn long_poll_chat(
+        &self,
+        token: &Token,
+        maxMessage: i32,
+        last_message: i32,
+    ) -> Result<Vec<NCReqDataMessage>, NCApiError> {
+        let response_result = self
+            .request_chat(
+                token,
+                maxMessage,
+                Some(last_message),
+                true,
+                LONG_POLL_TIMEOUT,
+            )
+            .await;
+        match response_result {
+            Ok(Some(response)) => Ok(response),
+            Ok(None) => Err(NCApiError::NotFound(
+                "room disappeared, precondition not met".to_string(),
+            )),
             Err(why) => Err(why),
         }
     }
 
-    async fn mark_chat_read(&self, token: &str, last_message: i32) -> Result<(), Box<dyn Error>> {
+    async fn mark_chat_read(&self, token: &str, last_message: i32) -> Result<(), NCApiError> {
         let url_string =
             self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v1/chat/" + token + "/read";
-        let url = Url::parse(&url_string)?;
-        log::trace!("Marking {} as read", token);
+        let url = parse_url(&url_string)?;
+        tracing::trace!("Marking {} as read", token);
         let response = self.request_post(url).await?;
         match response.status() {
             reqwest::StatusCode::OK => Ok(()),
-            _ => Err(Box::new(
-                response
-                    .error_for_status()
-                    .err()
-                    .ok_or("Failed to convert Error")?,
-            )),
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    async fn set_favorite(&self, token: &str, favorite: bool) -> Result<(), NCApiError> {
+        self.toggle_room_flag(token, "favorite", favorite).await
+    }
+
+    async fn set_notification_level(&self, token: &str, level: i32) -> Result<(), NCApiError> {
+        let url_string =
+            self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v4/room/" + token + "/notify";
+        let params = HashMap::from([("level", level.to_string())]);
+        let url = parse_url_with_params(&url_string, params)?;
+        tracing::trace!("Setting notification level of {token} to {level}");
+        let response = self.request_post(url).await?;
+        match response.status() {
+            reqwest::StatusCode::OK => Ok(()),
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    async fn set_archived(&self, token: &str, archived: bool) -> Result<(), NCApiError> {
+        self.toggle_room_flag(token, "archive", archived).await
+    }
+
+    async fn add_participant(&self, token: &str, user_id: &str) -> Result<(), NCApiError> {
+        let url_string = self.base_url.clone()
+            + "/ocs/v2.php/apps/spreed/api/v4/room/"
+            + token
+            + "/participants";
+        let params = HashMap::from([("newParticipant", user_id), ("source", "users")]);
+        let url = parse_url_with_params(&url_string, params)?;
+        tracing::trace!("Adding {user_id} to {token}");
+        let response = self.request_post(url).await?;
+        match response.status() {
+            reqwest::StatusCode::OK => Ok(()),
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    async fn remove_attendee(&self, token: &str, attendee_id: i32) -> Result<(), NCApiError> {
+        let url_string =
+            self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v4/room/" + token + "/attendees";
+        let params = HashMap::from([("attendeeId", attendee_id.to_string())]);
+        let url = parse_url_with_params(&url_string, params)?;
+        tracing::trace!("Removing attendee {attendee_id} from {token}");
+        let response = self.request_delete(url).await?;
+        match response.status() {
+            reqwest::StatusCode::OK => Ok(()),
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    async fn set_moderator(
+        &self,
+        token: &str,
+        attendee_id: i32,
+        moderator: bool,
+    ) -> Result<(), NCApiError> {
+        let url_string =
+            self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v4/room/" + token + "/moderators";
+        let params = HashMap::from([("attendeeId", attendee_id.to_string())]);
+        let url = parse_url_with_params(&url_string, params)?;
+        tracing::trace!("Setting moderator of attendee {attendee_id} in {token} to {moderator}");
+        let response = if moderator {
+            self.request_post(url).await?
+        } else {
+            self.request_delete(url).await?
+        };
+        match response.status() {
+            reqwest::StatusCode::OK => Ok(()),
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    async fn ban_attendee(
+        &self,
+        token: &str,
+        actor_type: &str,
+        actor_id: &str,
+    ) -> Result<(), NCApiError> {
+        let url_string = self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v1/ban/" + token;
+        let params = HashMap::from([
+            ("actorType", actor_type),
+            ("actorId", actor_id),
+            ("internalNote", ""),
+        ]);
+        let url = parse_url_with_params(&url_string, params)?;
+        tracing::trace!("Banning {actor_id} from {token}");
+        let response = self.request_post(url).await?;
+        match response.status() {
+            reqwest::StatusCode::OK => Ok(()),
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    async fn set_room_name(&self, token: &str, name: &str) -> Result<(), NCApiError> {
+        let url_string = self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v4/room/" + token;
+        let params = HashMap::from([("roomName", name)]);
+        let url = parse_url_with_params(&url_string, params)?;
+        tracing::trace!("Renaming {token} to {name}");
+        let response = self.request_put(url).await?;
+        match response.status() {
+            reqwest::StatusCode::OK => Ok(()),
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    async fn set_room_description(&self, token: &str, description: &str) -> Result<(), NCApiError> {
+        let url_string =
+            self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v4/room/" + token + "/description";
+        let params = HashMap::from([("description", description)]);
+        let url = parse_url_with_params(&url_string, params)?;
+        tracing::trace!("Setting description of {token}");
+        let response = self.request_put(url).await?;
+        match response.status() {
+            reqwest::StatusCode::OK => Ok(()),
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    async fn send_reply(
+        &self,
+        message: String,
+        token: &Token,
+        reply_to: i32,
+    ) -> Result<NCReqDataMessage, NCApiError> {
+        let url_string = self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v1/chat/" + token;
+        let params = HashMap::from([("message", message), ("replyTo", reply_to.to_string())]);
+        let url = parse_url_with_params(&url_string, params)?;
+        let response = self.request_post(url).await?;
+
+        match response.status() {
+            reqwest::StatusCode::CREATED => Ok(response
+                .json::<NCReqOCSWrapper<NCReqDataMessage>>()
+                .await?
+                .ocs
+                .data),
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    async fn edit_message(
+        &self,
+        token: &str,
+        message_id: i32,
+        message: &str,
+    ) -> Result<(), NCApiError> {
+        let url_string = self.chat_message_url("chat", token, message_id, "");
+        let url = parse_url_with_params(&url_string, [("message", message)])?;
+        tracing::trace!("Editing message {message_id} in {token}");
+        NCRequestWorker::success_or_error(&self.request_put(url).await?)
+    }
+
+    async fn delete_message(&self, token: &str, message_id: i32) -> Result<(), NCApiError> {
+        let url = parse_url(&self.chat_message_url("chat", token, message_id, ""))?;
+        tracing::trace!("Deleting message {message_id} in {token}");
+        NCRequestWorker::success_or_error(&self.request_delete(url).await?)
+    }
+
+    async fn react_to_message(
+        &self,
+        token: &str,
+        message_id: i32,
+        reaction: &str,
+    ) -> Result<(), NCApiError> {
+        let url_string = self.chat_message_url("reaction", token, message_id, "");
+        let url = parse_url_with_params(&url_string, [("reaction", reaction)])?;
+        tracing::trace!("Reacting with {reaction} to message {message_id} in {token}");
+        NCRequestWorker::success_or_error(&self.request_post(url).await?)
+    }
+
+    async fn delete_reaction(
+        &self,
+        token: &str,
+        message_id: i32,
+        reaction: &str,
+    ) -> Result<(), NCApiError> {
+        let url_string = self.chat_message_url("reaction", token, message_id, "");
+        let url = parse_url_with_params(&url_string, [("reaction", reaction)])?;
+        tracing::trace!("Removing reaction {reaction} from message {message_id} in {token}");
+        NCRequestWorker::success_or_error(&self.request_delete(url).await?)
+    }
+
+    async fn set_reminder(
+        &self,
+        token: &str,
+        message_id: i32,
+        timestamp: i64,
+    ) -> Result<(), NCApiError> {
+        let url_string = self.chat_message_url("chat", token, message_id, "reminder");
+        let url = parse_url_with_params(&url_string, [("timestamp", timestamp.to_string())])?;
+        tracing::trace!("Setting reminder for message {message_id} in {token} at {timestamp}");
+        NCRequestWorker::success_or_error(&self.request_post(url).await?)
+    }
+
+    async fn pin_message(&self, token: &str, message_id: i32) -> Result<(), NCApiError> {
+        let url = parse_url(&self.chat_message_url("chat", token, message_id, "pin"))?;
+        tracing::trace!("Pinning message {message_id} in {token}");
+        NCRequestWorker::success_or_error(&self.request_post(url).await?)
+    }
+
+    async fn set_room_password(&self, token: &str, password: &str) -> Result<(), NCApiError> {
+        let request = self.room_password_request(token, password)?;
+        tracing::trace!("Setting password of {token}");
+        let response = self.send_request(Method::PUT, request).await?;
+        match response.status() {
+            reqwest::StatusCode::OK => Ok(()),
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    async fn set_mention_permissions(
+        &self,
+        token: &str,
+        permissions: i32,
+    ) -> Result<(), NCApiError> {
+        let url_string = self.base_url.clone()
+            + "/ocs/v2.php/apps/spreed/api/v4/room/"
+            + token
+            + "/mention-permissions";
+        let params = HashMap::from([("mentionPermissions", permissions.to_string())]);
+        let url = parse_url_with_params(&url_string, params)?;
+        tracing::trace!("Setting mention permissions of {token} to {permissions}");
+        NCRequestWorker::success_or_error(&self.request_put(url).await?)
+    }
+
+    async fn set_message_expiration(&self, token: &str, seconds: i32) -> Result<(), NCApiError> {
+        let url_string = self.base_url.clone()
+            + "/ocs/v2.php/apps/spreed/api/v4/room/"
+            + token
+            + "/message-expiration";
+        let params = HashMap::from([("seconds", seconds.to_string())]);
+        let url = parse_url_with_params(&url_string, params)?;
+        tracing::trace!("Setting message expiration of {token} to {seconds}s");
+        NCRequestWorker::success_or_error(&self.request_post(url).await?)
+    }
+
+    async fn fetch_listed_rooms(&self, search: &str) -> Result<Vec<NCReqDataRoom>, NCApiError> {
+        let url_string = self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v4/listed-room";
+        let params = HashMap::from([("searchTerm", search)]);
+        let url = parse_url_with_params(&url_string, params)?;
+        let response = self.request(url).await?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let text = response.text().await?;
+                match serde_json::from_str::<NCReqOCSWrapper<Vec<NCReqDataRoom>>>(&text) {
+                    Ok(parser_response) => Ok(parser_response.ocs.data),
+                    Err(why) => Err(self.parse_error(&url_string, &text, &why)),
+                }
+            }
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    async fn fetch_capabilities(&self) -> Result<NCReqDataCapabilities, NCApiError> {
+        let url_string = self.base_url.clone() + "/ocs/v1.php/cloud/capabilities";
+        let url = parse_url(&url_string)?;
+        let response = self.request(url).await?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let text = response.text().await?;
+                match serde_json::from_str::<NCReqOCSWrapper<NCReqDataCapabilities>>(&text) {
+                    Ok(parser_response) => Ok(parser_response.ocs.data),
+                    Err(why) => Err(self.parse_error(&url_string, &text, &why)),
+                }
+            }
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    async fn fetch_user_status(&self) -> Result<NCReqDataUserStatus, NCApiError> {
+        let url_string = self.base_url.clone() + "/ocs/v2.php/apps/user_status/api/v1/user_status";
+        let url = parse_url(&url_string)?;
+        let response = self.request(url).await?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let text = response.text().await?;
+                match serde_json::from_str::<NCReqOCSWrapper<NCReqDataUserStatus>>(&text) {
+                    Ok(parser_response) => Ok(parser_response.ocs.data),
+                    Err(why) => Err(self.parse_error(&url_string, &text, &why)),
+                }
+            }
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    /// Fetch the absence of the user if they are out of office right now, `None` if not.
+    async fn fetch_out_of_office(
+        &self,
+        user_id: &str,
+    ) -> Result<Option<NCReqDataOutOfOffice>, NCApiError> {
+        let url_string =
+            self.base_url.clone() + "/ocs/v2.php/apps/dav/api/v1/outOfOffice/" + user_id + "/now";
+        let url = parse_url(&url_string)?;
+        let response = self.request(url).await?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let text = response.text().await?;
+                match serde_json::from_str::<NCReqOCSWrapper<NCReqDataOutOfOffice>>(&text) {
+                    Ok(parser_response) => Ok(Some(parser_response.ocs.data)),
+                    Err(why) => Err(self.parse_error(&url_string, &text, &why)),
+                }
+            }
+            reqwest::StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(NCApiError::from_status(response.status())),
         }
     }
+
+    /// Set the status of the user, one of `online`, `away`, `dnd` or `invisible`.
+    async fn set_user_status(&self, status: &str) -> Result<(), NCApiError> {
+        let url_string =
+            self.base_url.clone() + "/ocs/v2.php/apps/user_status/api/v1/user_status/status";
+        let url = parse_url_with_params(&url_string, [("statusType", status)])?;
+        tracing::trace!("Setting the user status to {status}");
+        NCRequestWorker::success_or_error(&self.request_put(url).await?)
+    }
+
+    /// Set the custom status message of the user with an optional emoji, clearing it without both.
+    async fn set_user_status_message(
+        &self,
+        message: &str,
+        icon: Option<String>,
+    ) -> Result<(), NCApiError> {
+        let base =
+            self.base_url.clone() + "/ocs/v2.php/apps/user_status/api/v1/user_status/message";
+        if message.is_empty() && icon.is_none() {
+            tracing::trace!("Clearing the user status message");
+            return NCRequestWorker::success_or_error(
+                &self.request_delete(parse_url(&base)?).await?,
+            );
+        }
+        let mut params = vec![("message", message.to_string())];
+        if let Some(icon) = icon {
+            params.push(("statusIcon", icon));
+        }
+        let url = parse_url_with_params(&(base + "/custom"), params)?;
+        tracing::trace!("Setting the user status message to {message}");
+        NCRequestWorker::success_or_error(&self.request_put(url).await?)
+    }
+
+    /// Upload the file into the Talk folder of the user and share it into the room.
+    /// Existing files are kept, the upload is named like "name (2).png" then.
+    async fn share_file(&self, token: &str, name: &str, data: Vec<u8>) -> Result<(), NCApiError> {
+        const MAX_COPIES: u32 = 20;
+
+        if self.guest_session.is_some() {
+            return Err(NCApiError::Other("Guests cannot share files.".to_string()));
+        }
+        let (stem, extension) = match name.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{extension}")),
+            _ => (name, String::new()),
+        };
+        let mut uploaded = None;
+        for copy in 1..=MAX_COPIES {
+            let file_name = if copy == 1 {
+                name.to_string()
+            } else {
+                format!("{stem} ({copy}){extension}")
+            };
+            let mut url = parse_url(&(self.base_url.clone() + "/remote.php/dav/files/"))?;
+            url.path_segments_mut()
+                .map_err(|()| NCApiError::Other(format!("Invalid url {}", self.base_url)))?
+                .pop_if_empty()
+                .extend([self.user.as_str(), "Talk", file_name.as_str()]);
+            tracing::trace!("Uploading {file_name} to share it in {token}");
+            // Only create the file, instead of replacing one of the same name.
+            let request = self
+                .client
+                .put(url)
+                .header("If-None-Match", "*")
+                .body(data.clone());
+            let response = self.send_request(Method::PUT, request).await?;
+            match response.status() {
+                reqwest::StatusCode::CREATED | reqwest::StatusCode::NO_CONTENT => {
+                    uploaded = Some(file_name);
+                    break;
+                }
+                // A file of that name exists, try the next one.
+                reqwest::StatusCode::PRECONDITION_FAILED => (),
+                status => return Err(NCApiError::from_status(status)),
+            }
+        }
+        let file_name = uploaded.ok_or_else(|| {
+            NCApiError::Other(format!(
+                "{MAX_COPIES} files named like {name} exist already."
+            ))
+        })?;
+
+        let url_string = self.base_url.clone() + "/ocs/v2.php/apps/files_sharing/api/v1/shares";
+        let path = format!("/Talk/{file_name}");
+        let params = HashMap::from([
+            ("shareType", "10"),
+            ("shareWith", token),
+            ("path", path.as_str()),
+        ]);
+        let url = parse_url_with_params(&url_string, params)?;
+        tracing::trace!("Sharing {path} in {token}");
+        let response = self.request_post(url).await?;
+        match response.status() {
+            reqwest::StatusCode::OK => Ok(()),
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    async fn set_read_status_privacy(&self, private: bool) -> Result<(), NCApiError> {
+        let url_string = self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v1/settings/user";
+        let value = if private { "1" } else { "0" };
+        let params = HashMap::from([("key", "read_status_privacy"), ("value", value)]);
+        let url = parse_url_with_params(&url_string, params)?;
+        tracing::trace!("Setting read status privacy to {value}");
+        let response = self.request_post(url).await?;
+        match response.status() {
+            reqwest::StatusCode::OK => Ok(()),
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    async fn join_room(&self, token: &str) -> Result<NCReqDataRoom, NCApiError> {
+        let url_string = self.base_url.clone()
+            + "/ocs/v2.php/apps/spreed/api/v4/room/"
+            + token
+            + "/participants/active";
+        let url = parse_url(&url_string)?;
+        tracing::trace!("Joining room {token}");
+        let response = self.request_post(url).await?;
+
+        match response.status() {
+            reqwest::StatusCode::OK => {
+                let text = response.text().await?;
+                match serde_json::from_str::<NCReqOCSWrapper<NCReqDataRoom>>(&text) {
+                    Ok(parser_response) => Ok(parser_response.ocs.data),
+                    Err(why) => Err(self.parse_error(&url_string, &text, &why)),
+                }
+            }
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    async fn leave_room(&self, token: &str) -> Result<(), NCApiError> {
+        let url_string = self.base_url.clone()
+            + "/ocs/v2.php/apps/spreed/api/v4/room/"
+            + token
+            + "/participants/active";
+        let url = parse_url(&url_string)?;
+        tracing::trace!("Leaving the session in room {token}");
+        let response = self.request_delete(url).await?;
+        Self::success_or_error(&response)
+    }
+
+    /// Create the one to one room with the user, the server answers with the existing one if
+    /// there is one already.
+    async fn create_dm(&self, user_id: &str) -> Result<NCReqDataRoom, NCApiError> {
+        let url_string = self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v4/room";
+        let url = parse_url_with_params(&url_string, [("roomType", "1"), ("invite", user_id)])?;
+        tracing::trace!("Creating the direct message room with {user_id}");
+        let response = self.request_post(url).await?;
+
+        match response.status() {
+            reqwest::StatusCode::OK | reqwest::StatusCode::CREATED => {
+                let text = response.text().await?;
+                match serde_json::from_str::<NCReqOCSWrapper<NCReqDataRoom>>(&text) {
+                    Ok(parser_response) => Ok(parser_response.ocs.data),
+                    Err(why) => Err(self.parse_error(&url_string, &text, &why)),
+                }
+            }
+            _ => Err(NCApiError::from_status(response.status())),
+        }
+    }
+
+    async fn join_as_guest(
+        &self,
+        token: &str,
+        password: Option<String>,
+        name: Option<String>,
+    ) -> Result<NCReqDataRoom, NCApiError> {
+        let url_string = self.base_url.clone()
+            + "/ocs/v2.php/apps/spreed/api/v4/room/"
+            + token
+            + "/participants/active";
+        let url = parse_url(&url_string)?;
+        tracing::trace!("Joining room {token} as guest");
+        // In the body, so the password does not end up in the logs of proxies.
+        let params = password
+            .map(|password| HashMap::from([("password", password)]))
+            .unwrap_or_default();
+        let request = self.client.post(url).form(&params);
+        let response = self.send_request(Method::POST, request).await?;
+        let room = match response.status() {
+            reqwest::StatusCode::OK => {
+                let text = response.text().await?;
+                match serde_json::from_str::<NCReqOCSWrapper<NCReqDataRoom>>(&text) {
+                    Ok(parser_response) => parser_response.ocs.data,
+                    Err(why) => return Err(self.parse_error(&url_string, &text, &why)),
+                }
+            }
+            reqwest::StatusCode::FORBIDDEN => {
+                return Err(NCApiError::AuthFailed(format!(
+                    "joining {token} as guest was refused, is the password right?"
+                )))
+            }
+            _ => {
+                return Err(NCApiError::from_status(response.status()));
+            }
+        };
+        if let Some(name) = name {
+            let url_string =
+                self.base_url.clone() + "/ocs/v2.php/apps/spreed/api/v1/guest/" + token + "/name";
+            let url = parse_url_with_params(&url_string, [("displayName", name)])?;
+            self.request_post(url).await?.error_for_status()?;
+        }
+        Ok(room)
+    }
 }
 
 #[cfg(test)]
@@ -381,37 +1177,93 @@ mock! {
     #[async_trait]
     impl NCRequestWorkerInterface for NCRequestWorker{
         fn new(config: &Config) -> Result<Self, Box<dyn Error>>;
-        async fn mark_chat_read(&self, token: &str, last_message: i32) -> Result<(), Box<dyn Error>>;
+        async fn mark_chat_read(&self, token: &str, last_message: i32) -> Result<(), NCApiError>;
         async fn send_message(
             &self,
             message: String,
             token: &Token,
-        ) -> Result<NCReqDataMessage, Box<dyn Error>>;
+        ) -> Result<NCReqDataMessage, NCApiError>;
         async fn fetch_autocomplete_users(
             &self,
             name: &str,
-        ) -> Result<Vec<NCReqDataUser>, Box<dyn Error>>;
+        ) -> Result<Vec<NCReqDataUser>, NCApiError>;
         async fn fetch_participants(
             &self,
             token: &Token,
-        ) -> Result<Vec<NCReqDataParticipants>, Box<dyn Error>>;
-        async fn fetch_rooms_initial(&self) -> Result<(Vec<NCReqDataRoom>, i64), Box<dyn Error>>;
+        ) -> Result<Vec<NCReqDataParticipants>, NCApiError>;
+        async fn fetch_rooms_initial(&self) -> Result<(Vec<NCReqDataRoom>, i64), NCApiError>;
 
         async fn fetch_rooms_update(
             &self,
             last_timestamp: i64,
-        ) -> Result<(Vec<NCReqDataRoom>, i64), Box<dyn Error>>;
+        ) -> Result<(Vec<NCReqDataRoom>, i64), NCApiError>;
         async fn fetch_chat_initial(
             &self,
             token: &Token,
             maxMessage: i32,
-        ) -> Result<Vec<NCReqDataMessage>, Box<dyn Error>>;
+        ) -> Result<Vec<NCReqDataMessage>, NCApiError>;
         async fn fetch_chat_update(
             &self,
             token: &Token,
             maxMessage: i32,
             last_message: i32,
-        ) -> Result<Vec<NCReqDataMessage>, Box<dyn Error>>;
+        ) -> Result<Vec<NCReqDataMessage>, NCApiError>;
+        async fn fetch_chat_history(
+            &self,
+            token: &Token,
+            maxMessage: i32,
+            first_message: i32,
+        ) -> Result<Vec<NCReqDataMessage>, NCApiError>;
+        async fn long_poll_chat(
+            &self,
+            token: &Token,
+            maxMessage: i32,
+            last_message: i32,
+        ) -> Result<Vec<NCReqDataMessage>, NCApiError>;
+        async fn set_favorite(&self, token: &str, favorite: bool) -> Result<(), NCApiError>;
+        async fn set_notification_level(&self, token: &str, level: i32)
+            -> Result<(), NCApiError>;
+        async fn set_archived(&self, token: &str, archived: bool) -> Result<(), NCApiError>;
+        async fn add_participant(&self, token: &str, user_id: &str) -> Result<(), NCApiError>;
+        async fn remove_attendee(&self, token: &str, attendee_id: i32) -> Result<(), NCApiError>;
+        async fn set_moderator(&self, token: &str, attendee_id: i32, moderator: bool) -> Result<(), NCApiError>;
+        async fn ban_attendee(&self, token: &str, actor_type: &str, actor_id: &str) -> Result<(), NCApiError>;
+        async fn set_room_name(&self, token: &str, name: &str) -> Result<(), NCApiError>;
+        async fn set_room_description(&self, token: &str, description: &str) -> Result<(), NCApiError>;
+        async fn send_reply(&self, message: String, token: &Token, reply_to: i32) -> Result<NCReqDataMessage, NCApiError>;
+        async fn edit_message(&self, token: &str, message_id: i32, message: &str) -> Result<(), NCApiError>;
+        async fn delete_message(&self, token: &str, message_id: i32) -> Result<(), NCApiError>;
+        async fn react_to_message(&self, token: &str, message_id: i32, reaction: &str) -> Result<(), NCApiError>;
+        async fn delete_reaction(&self, token: &str, message_id: i32, reaction: &str) -> Result<(), NCApiError>;
+        async fn set_reminder(&self, token: &str, message_id: i32, timestamp: i64) -> Result<(), NCApiError>;
+        async fn pin_message(&self, token: &str, message_id: i32) -> Result<(), NCApiError>;
+        async fn set_room_password(&self, token: &str, password: &str) -> Result<(), NCApiError>;
+        async fn set_mention_permissions(&self, token: &str, permissions: i32) -> Result<(), NCApiError>;
+        async fn set_message_expiration(&self, token: &str, seconds: i32) -> Result<(), NCApiError>;
+        async fn fetch_listed_rooms(&self, search: &str) -> Result<Vec<NCReqDataRoom>, NCApiError>;
+        async fn join_room(&self, token: &str) -> Result<NCReqDataRoom, NCApiError>;
+        async fn leave_room(&self, token: &str) -> Result<(), NCApiError>;
+        async fn create_dm(&self, user_id: &str) -> Result<NCReqDataRoom, NCApiError>;
+        async fn set_read_status_privacy(&self, private: bool) -> Result<(), NCApiError>;
+        async fn share_file(&self, token: &str, name: &str, data: Vec<u8>) -> Result<(), NCApiError>;
+        async fn fetch_user_status(&self) -> Result<NCReqDataUserStatus, NCApiError>;
+        async fn fetch_out_of_office(
+            &self,
+            user_id: &str,
+        ) -> Result<Option<NCReqDataOutOfOffice>, NCApiError>;
+        async fn set_user_status(&self, status: &str) -> Result<(), NCApiError>;
+        async fn set_user_status_message(
+            &self,
+            message: &str,
+            icon: Option<String>,
+        ) -> Result<(), NCApiError>;
+        async fn fetch_capabilities(&self) -> Result<NCReqDataCapabilities, NCApiError>;
+        async fn join_as_guest(
+            &self,
+            token: &str,
+            password: Option<String>,
+            name: Option<String>,
+        ) -> Result<NCReqDataRoom, NCApiError>;
     }
 }
 
@@ -421,6 +1273,23 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn token_of_endpoint() {
+        assert_eq!(
+            endpoint_token("/ocs/v2.php/apps/spreed/api/v4/room/abc123/participants"),
+            "abc123"
+        );
+        assert_eq!(
+            endpoint_token("/ocs/v2.php/apps/spreed/api/v1/chat/abc123"),
+            "abc123"
+        );
+        assert_eq!(endpoint_token("/ocs/v2.php/apps/spreed/api/v4/room"), "");
+        assert_eq!(
+            endpoint_token("/ocs/v2.php/apps/user_status/api/v1/user_status"),
+            ""
+        );
+    }
+
     #[tokio::test]
     async fn new_requester() {
         let dir = tempfile::tempdir().unwrap();
@@ -431,4 +1300,25 @@ mod tests {
         assert!(result.is_ok());
         let requester = result.unwrap();
     }
+
+    #[tokio::test]
+    async fn room_password_in_body() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+        let requester = NCRequestWorker::new(&config).unwrap();
+        let request = requester
+            .room_password_request("123", "s3cr3t")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(request.method(), Method::PUT);
+        assert!(request.url().path().ends_with("/room/123/password"));
+        assert!(!request.url().as_str().contains("s3cr3t"));
+        assert_eq!(
+            request.body().and_then(reqwest::Body::as_bytes),
+            Some("password=s3cr3t".as_bytes())
+        );
+    }
 }
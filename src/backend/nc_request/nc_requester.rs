@@ -1,4 +1,5 @@
 //! The Requester is the Sechat facing Abstraction of the NC API.
+use strum_macros::IntoStaticStr;
 use tokio::sync::{
     mpsc::{self, Sender},
     oneshot,
@@ -8,22 +9,35 @@ use tokio_util::sync::CancellationToken;
 use crate::config::Config;
 use async_trait::async_trait;
 
-use std::{error::Error, fmt};
-use std::{fmt::Debug, sync::Arc};
+use std::fmt;
+use std::{
+    fmt::Debug,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 #[cfg(test)]
 use mockall::{mock, predicate::*};
 
+use crate::backend::{
+    request_dump::RequestDumps,
+    request_metrics::{MetricsSnapshot, RequestMetrics},
+};
+
 use super::{
+    nc_guest::GuestSession,
+    nc_long_poll::{LongPoll, PushedMessages},
     nc_req_worker::{NCRequestWorker, NCRequestWorkerInterface},
-    NCReqDataMessage, NCReqDataParticipants, NCReqDataRoom, NCReqDataUser, Token,
+    nc_retry::RateLimit,
+    NCApiError, NCReqDataCapabilities, NCReqDataMessage, NCReqDataOutOfOffice,
+    NCReqDataParticipants, NCReqDataRoom, NCReqDataUser, NCReqDataUserStatus, Token,
 };
 
-type ApiResult<T> =
-    Result<oneshot::Receiver<Result<T, Arc<dyn Error + Send + Sync>>>, Box<dyn Error>>;
-type ApiResponseChannel<T> = oneshot::Sender<Result<T, Arc<dyn Error + Send + Sync>>>;
+type ApiResult<T> = Result<oneshot::Receiver<Result<T, NCApiError>>, NCApiError>;
+type ApiResponseChannel<T> = oneshot::Sender<Result<T, NCApiError>>;
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, IntoStaticStr)]
 pub enum ApiRequests {
     #[default]
     None,
@@ -33,8 +47,45 @@ pub enum ApiRequests {
     FetchParticipants(Token, ApiResponseChannel<Vec<NCReqDataParticipants>>),
     FetchChatInitial(Token, i32, ApiResponseChannel<Vec<NCReqDataMessage>>),
     FetchChatUpdate(Token, i32, i32, ApiResponseChannel<Vec<NCReqDataMessage>>),
+    FetchChatHistory(Token, i32, i32, ApiResponseChannel<Vec<NCReqDataMessage>>),
     FetchAutocompleteUsers(String, ApiResponseChannel<Vec<NCReqDataUser>>),
     MarkChatRead(Token, i32, ApiResponseChannel<()>),
+    SetFavorite(Token, bool, ApiResponseChannel<()>),
+    SetNotificationLevel(Token, i32, ApiResponseChannel<()>),
+    SetArchived(Token, bool, ApiResponseChannel<()>),
+    AddParticipant(Token, String, ApiResponseChannel<()>),
+    RemoveAttendee(Token, i32, ApiResponseChannel<()>),
+    SetModerator(Token, i32, bool, ApiResponseChannel<()>),
+    BanAttendee(Token, String, String, ApiResponseChannel<()>),
+    SetRoomName(Token, String, ApiResponseChannel<()>),
+    SetRoomDescription(Token, String, ApiResponseChannel<()>),
+    SendReply(Token, String, i32, ApiResponseChannel<NCReqDataMessage>),
+    EditMessage(Token, i32, String, ApiResponseChannel<()>),
+    DeleteMessage(Token, i32, ApiResponseChannel<()>),
+    ReactToMessage(Token, i32, String, ApiResponseChannel<()>),
+    DeleteReaction(Token, i32, String, ApiResponseChannel<()>),
+    SetReminder(Token, i32, i64, ApiResponseChannel<()>),
+    PinMessage(Token, i32, ApiResponseChannel<()>),
+    SetRoomPassword(Token, String, ApiResponseChannel<()>),
+    SetMentionPermissions(Token, i32, ApiResponseChannel<()>),
+    SetMessageExpiration(Token, i32, ApiResponseChannel<()>),
+    FetchListedRooms(String, ApiResponseChannel<Vec<NCReqDataRoom>>),
+    JoinRoom(Token, ApiResponseChannel<NCReqDataRoom>),
+    LeaveRoom(Token, ApiResponseChannel<()>),
+    CreateDm(String, ApiResponseChannel<NCReqDataRoom>),
+    SetReadStatusPrivacy(bool, ApiResponseChannel<()>),
+    ShareFile(Token, String, Vec<u8>, ApiResponseChannel<()>),
+    FetchUserStatus(ApiResponseChannel<NCReqDataUserStatus>),
+    FetchOutOfOffice(String, ApiResponseChannel<Option<NCReqDataOutOfOffice>>),
+    SetUserStatus(String, ApiResponseChannel<()>),
+    SetUserStatusMessage(String, Option<String>, ApiResponseChannel<()>),
+    FetchCapabilities(ApiResponseChannel<NCReqDataCapabilities>),
+    JoinAsGuest(
+        Token,
+        Option<String>,
+        Option<String>,
+        ApiResponseChannel<NCReqDataRoom>,
+    ),
 }
 
 impl fmt::Display for ApiRequests {
@@ -53,10 +104,82 @@ impl fmt::Display for ApiRequests {
             ApiRequests::FetchChatUpdate(token, maxMessage, last_message, _) => {
                 write!(f, "FetchChatUpdate {token} {maxMessage} {last_message}")
             }
+            ApiRequests::FetchChatHistory(token, maxMessage, first_message, _) => {
+                write!(f, "FetchChatHistory {token} {maxMessage} {first_message}")
+            }
             ApiRequests::FetchAutocompleteUsers(name, _) => {
                 write!(f, "FetchAutocompleteUsers {name}")
             }
             ApiRequests::MarkChatRead(token, i32, _) => write!(f, "MarkChatRead {token}"),
+            ApiRequests::SetFavorite(token, favorite, _) => {
+                write!(f, "SetFavorite {token} {favorite}")
+            }
+            ApiRequests::SetNotificationLevel(token, level, _) => {
+                write!(f, "SetNotificationLevel {token} {level}")
+            }
+            ApiRequests::SetArchived(token, archived, _) => {
+                write!(f, "SetArchived {token} {archived}")
+            }
+            ApiRequests::AddParticipant(token, user_id, _) => {
+                write!(f, "AddParticipant {token} {user_id}")
+            }
+            ApiRequests::RemoveAttendee(token, attendee_id, _) => {
+                write!(f, "RemoveAttendee {token} {attendee_id}")
+            }
+            ApiRequests::SetModerator(token, attendee_id, moderator, _) => {
+                write!(f, "SetModerator {token} {attendee_id} {moderator}")
+            }
+            ApiRequests::BanAttendee(token, _, actor_id, _) => {
+                write!(f, "BanAttendee {token} {actor_id}")
+            }
+            ApiRequests::SetRoomName(token, name, _) => write!(f, "SetRoomName {token} {name}"),
+            ApiRequests::SetRoomDescription(token, _, _) => {
+                write!(f, "SetRoomDescription {token}")
+            }
+            ApiRequests::SendReply(token, _, reply_to, _) => {
+                write!(f, "SendReply {token} {reply_to}")
+            }
+            ApiRequests::EditMessage(token, message_id, _, _) => {
+                write!(f, "EditMessage {token} {message_id}")
+            }
+            ApiRequests::DeleteMessage(token, message_id, _) => {
+                write!(f, "DeleteMessage {token} {message_id}")
+            }
+            ApiRequests::ReactToMessage(token, message_id, reaction, _) => {
+                write!(f, "ReactToMessage {token} {message_id} {reaction}")
+            }
+            ApiRequests::DeleteReaction(token, message_id, reaction, _) => {
+                write!(f, "DeleteReaction {token} {message_id} {reaction}")
+            }
+            ApiRequests::SetReminder(token, message_id, timestamp, _) => {
+                write!(f, "SetReminder {token} {message_id} {timestamp}")
+            }
+            ApiRequests::PinMessage(token, message_id, _) => {
+                write!(f, "PinMessage {token} {message_id}")
+            }
+            ApiRequests::SetRoomPassword(token, _, _) => write!(f, "SetRoomPassword {token}"),
+            ApiRequests::SetMentionPermissions(token, permissions, _) => {
+                write!(f, "SetMentionPermissions {token} {permissions}")
+            }
+            ApiRequests::SetMessageExpiration(token, seconds, _) => {
+                write!(f, "SetMessageExpiration {token} {seconds}")
+            }
+            ApiRequests::FetchListedRooms(search, _) => write!(f, "FetchListedRooms {search}"),
+            ApiRequests::JoinRoom(token, _) => write!(f, "JoinRoom {token}"),
+            ApiRequests::LeaveRoom(token, _) => write!(f, "LeaveRoom {token}"),
+            ApiRequests::CreateDm(user_id, _) => write!(f, "CreateDm {user_id}"),
+            ApiRequests::SetReadStatusPrivacy(private, _) => {
+                write!(f, "SetReadStatusPrivacy {private}")
+            }
+            ApiRequests::ShareFile(token, name, _, _) => write!(f, "ShareFile {token} {name}"),
+            ApiRequests::FetchUserStatus(_) => write!(f, "FetchUserStatus"),
+            ApiRequests::FetchOutOfOffice(user_id, _) => write!(f, "FetchOutOfOffice {user_id}"),
+            ApiRequests::SetUserStatus(status, _) => write!(f, "SetUserStatus {status}"),
+            ApiRequests::SetUserStatusMessage(message, _, _) => {
+                write!(f, "SetUserStatusMessage {message}")
+            }
+            ApiRequests::FetchCapabilities(_) => write!(f, "FetchCapabilities"),
+            ApiRequests::JoinAsGuest(token, _, _, _) => write!(f, "JoinAsGuest {token}"),
         }
     }
 }
@@ -86,7 +209,106 @@ pub trait NCRequestInterface: Debug + Send + Send + Sync {
         maxMessage: i32,
         last_message: i32,
     ) -> ApiResult<Vec<NCReqDataMessage>>;
+    async fn request_chat_history(
+        &self,
+        token: &Token,
+        maxMessage: i32,
+        first_message: i32,
+    ) -> ApiResult<Vec<NCReqDataMessage>>;
+    fn start_long_poll(
+        &self,
+        token: &Token,
+        last_message: i32,
+        sender: Sender<PushedMessages>,
+    ) -> LongPoll;
     async fn request_mark_chat_read(&self, token: &str, last_message: i32) -> ApiResult<()>;
+    async fn request_set_favorite(&self, token: &str, favorite: bool) -> ApiResult<()>;
+    async fn request_set_notification_level(&self, token: &str, level: i32) -> ApiResult<()>;
+    async fn request_set_archived(&self, token: &str, archived: bool) -> ApiResult<()>;
+    async fn request_add_participant(&self, token: &str, user_id: &str) -> ApiResult<()>;
+    async fn request_remove_attendee(&self, token: &str, attendee_id: i32) -> ApiResult<()>;
+    async fn request_set_moderator(
+        &self,
+        token: &str,
+        attendee_id: i32,
+        moderator: bool,
+    ) -> ApiResult<()>;
+    async fn request_ban_attendee(
+        &self,
+        token: &str,
+        actor_type: &str,
+        actor_id: &str,
+    ) -> ApiResult<()>;
+    async fn request_set_room_name(&self, token: &str, name: &str) -> ApiResult<()>;
+    async fn request_set_room_description(&self, token: &str, description: &str) -> ApiResult<()>;
+    async fn request_send_reply(
+        &self,
+        message: String,
+        token: &Token,
+        reply_to: i32,
+    ) -> ApiResult<NCReqDataMessage>;
+    async fn request_edit_message(
+        &self,
+        token: &str,
+        message_id: i32,
+        message: &str,
+    ) -> ApiResult<()>;
+    async fn request_delete_message(&self, token: &str, message_id: i32) -> ApiResult<()>;
+    async fn request_react_to_message(
+        &self,
+        token: &str,
+        message_id: i32,
+        reaction: &str,
+    ) -> ApiResult<()>;
+    async fn request_delete_reaction(
+        &self,
+        token: &str,
+        message_id: i32,
+        reaction: &str,
+    ) -> ApiResult<()>;
+    async fn request_set_reminder(
+        &self,
+        token: &str,
+        message_id: i32,
+        timestamp: i64,
+    ) -> ApiResult<()>;
+    async fn request_pin_message(&self, token: &str, message_id: i32) -> ApiResult<()>;
+    async fn request_set_room_password(&self, token: &str, password: &str) -> ApiResult<()>;
+    async fn request_set_mention_permissions(&self, token: &str, permissions: i32)
+        -> ApiResult<()>;
+    async fn request_set_message_expiration(&self, token: &str, seconds: i32) -> ApiResult<()>;
+    async fn request_listed_rooms(&self, search: &str) -> ApiResult<Vec<NCReqDataRoom>>;
+    async fn request_join_room(&self, token: &str) -> ApiResult<NCReqDataRoom>;
+    async fn request_leave_room(&self, token: &str) -> ApiResult<()>;
+    /// Get the one to one room with the user, creating it if there is none.
+    async fn request_create_dm(&self, user_id: &str) -> ApiResult<NCReqDataRoom>;
+    async fn request_set_read_status_privacy(&self, private: bool) -> ApiResult<()>;
+    /// Upload the data as a file called `name` and share it into the room.
+    async fn request_share_file(&self, token: &str, name: &str, data: Vec<u8>) -> ApiResult<()>;
+    async fn request_user_status(&self) -> ApiResult<NCReqDataUserStatus>;
+    /// Get the absence of the user if they are out of office right now.
+    async fn request_out_of_office(&self, user_id: &str)
+        -> ApiResult<Option<NCReqDataOutOfOffice>>;
+    async fn request_set_user_status(&self, status: &str) -> ApiResult<()>;
+    async fn request_set_user_status_message(
+        &self,
+        message: &str,
+        icon: Option<String>,
+    ) -> ApiResult<()>;
+    async fn request_capabilities(&self) -> ApiResult<NCReqDataCapabilities>;
+    /// Join the public room as a guest, optionally with its password and a name to show.
+    async fn request_join_as_guest(
+        &self,
+        token: &str,
+        password: Option<String>,
+        name: Option<String>,
+    ) -> ApiResult<NCReqDataRoom>;
+    /// Time left until the server accepts requests again, `None` if it does not limit the rate.
+    fn rate_limited_for(&self) -> Option<Duration>;
+    /// Dumps of unparsable responses written since the last call.
+    fn take_request_dumps(&self) -> Vec<PathBuf>;
+    /// Counters of the requests so far and the current depths of the request queues.
+    fn metrics(&self) -> MetricsSnapshot;
     async fn shutdown(&self) -> Result<(), Box<dyn std::error::Error>>;
 }
 
@@ -99,56 +321,331 @@ pub trait NCRequestInterface: Debug + Send + Send + Sync {
 pub struct NCRequest {
     request_tx: Sender<ApiRequests>,
     cancel_token: CancellationToken,
+    /// Worker of its own for long polls, which would block a worker of the pool for long.
+    long_poll_worker: Arc<NCRequestWorker>,
+    /// Rate limit the server asked for, shared by all workers.
+    rate_limit: RateLimit,
+    /// Dumps of unparsable responses, shared by all workers.
+    dumps: RequestDumps,
+    /// Counters of the requests, shared by all workers.
+    metrics: RequestMetrics,
+    /// Channels to the workers of the pool, only to look at their queue depths.
+    worker_tx: Vec<Sender<ApiRequests>>,
 }
 
 impl NCRequest {
-    async fn handle_req(worker: &NCRequestWorker, req: ApiRequests) {
+    /// Send the result of the worker to the caller, errors included, so a failed request
+    /// never takes down the worker. The request is counted by its `kind`.
+    fn respond<T>(
+        metrics: &RequestMetrics,
+        kind: &str,
+        response: ApiResponseChannel<T>,
+        result: Result<T, NCApiError>,
+    ) {
+        metrics.record_request(kind, result.is_ok());
+        if response.send(result).is_err() {
+            log::debug!("Caller stopped waiting for the response.");
+        }
+    }
+
+    #[allow(clippy::too_many_lines)]
+    async fn handle_req(worker: &NCRequestWorker, req: ApiRequests, metrics: &RequestMetrics) {
         log::trace!("got a new API Request {}", req);
+        let kind: &'static str = (&req).into();
         match req {
             ApiRequests::FetchChatInitial(token, maxMessage, response) => {
-                response
-                    .send(Ok(worker
-                        .fetch_chat_initial(&token, maxMessage)
-                        .await
-                        .unwrap()))
-                    .expect("could not Send.");
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.fetch_chat_initial(&token, maxMessage).await,
+                );
             }
             ApiRequests::FetchChatUpdate(token, maxMessage, last_message, response) => {
-                response
-                    .send(Ok(worker
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker
                         .fetch_chat_update(&token, maxMessage, last_message)
-                        .await
-                        .unwrap()))
-                    .expect("could not Send.");
+                        .await,
+                );
+            }
+            ApiRequests::FetchChatHistory(token, maxMessage, first_message, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker
+                        .fetch_chat_history(&token, maxMessage, first_message)
+                        .await,
+                );
             }
             ApiRequests::FetchRoomsInitial(response) => {
-                response
-                    .send(Ok(worker.fetch_rooms_initial().await.unwrap()))
-                    .expect("could not Send.");
+                Self::respond(metrics, kind, response, worker.fetch_rooms_initial().await);
             }
             ApiRequests::FetchRoomsUpdate(last_timestamp, response) => {
-                response
-                    .send(Ok(worker.fetch_rooms_update(last_timestamp).await.unwrap()))
-                    .expect("could not Send.");
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.fetch_rooms_update(last_timestamp).await,
+                );
             }
             ApiRequests::SendMessage(token, message, response) => {
-                response
-                    .send(Ok(worker.send_message(message, &token).await.unwrap()))
-                    .expect("could not Send.");
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.send_message(message, &token).await,
+                );
             }
             ApiRequests::FetchAutocompleteUsers(name, response) => {
-                response
-                    .send(Ok(worker.fetch_autocomplete_users(&name).await.unwrap()))
-                    .expect("could not Send.");
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.fetch_autocomplete_users(&name).await,
+                );
             }
             ApiRequests::FetchParticipants(token, response) => {
-                response
-                    .send(Ok(worker.fetch_participants(&token).await.unwrap()))
-                    .expect("could not Send.");
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.fetch_participants(&token).await,
+                );
             }
             ApiRequests::MarkChatRead(token, last_message, response) => {
-                worker.mark_chat_read(&token, last_message).await.unwrap();
-                response.send(Ok(())).expect("could not Send.");
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.mark_chat_read(&token, last_message).await,
+                );
+            }
+            ApiRequests::SetFavorite(token, favorite, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.set_favorite(&token, favorite).await,
+                );
+            }
+            ApiRequests::SetNotificationLevel(token, level, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.set_notification_level(&token, level).await,
+                );
+            }
+            ApiRequests::SetArchived(token, archived, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.set_archived(&token, archived).await,
+                );
+            }
+            ApiRequests::AddParticipant(token, user_id, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.add_participant(&token, &user_id).await,
+                );
+            }
+            ApiRequests::RemoveAttendee(token, attendee_id, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.remove_attendee(&token, attendee_id).await,
+                );
+            }
+            ApiRequests::SetModerator(token, attendee_id, moderator, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.set_moderator(&token, attendee_id, moderator).await,
+                );
+            }
+            ApiRequests::BanAttendee(token, actor_type, actor_id, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.ban_attendee(&token, &actor_type, &actor_id).await,
+                );
+            }
+            ApiRequests::SetRoomName(token, name, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.set_room_name(&token, &name).await,
+                );
+            }
+            ApiRequests::SetRoomDescription(token, description, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.set_room_description(&token, &description).await,
+                );
+            }
+            ApiRequests::SendReply(token, message, reply_to, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.send_reply(message, &token, reply_to).await,
+                );
+            }
+            ApiRequests::EditMessage(token, message_id, message, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.edit_message(&token, message_id, &message).await,
+                );
+            }
+            ApiRequests::DeleteMessage(token, message_id, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.delete_message(&token, message_id).await,
+                );
+            }
+            ApiRequests::ReactToMessage(token, message_id, reaction, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.react_to_message(&token, message_id, &reaction).await,
+                );
+            }
+            ApiRequests::DeleteReaction(token, message_id, reaction, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.delete_reaction(&token, message_id, &reaction).await,
+                );
+            }
+            ApiRequests::SetReminder(token, message_id, timestamp, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.set_reminder(&token, message_id, timestamp).await,
+                );
+            }
+            ApiRequests::PinMessage(token, message_id, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.pin_message(&token, message_id).await,
+                );
+            }
+            ApiRequests::SetRoomPassword(token, password, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.set_room_password(&token, &password).await,
+                );
+            }
+            ApiRequests::SetMentionPermissions(token, permissions, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.set_mention_permissions(&token, permissions).await,
+                );
+            }
+            ApiRequests::SetMessageExpiration(token, seconds, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.set_message_expiration(&token, seconds).await,
+                );
+            }
+            ApiRequests::FetchListedRooms(search, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.fetch_listed_rooms(&search).await,
+                );
+            }
+            ApiRequests::JoinRoom(token, response) => {
+                Self::respond(metrics, kind, response, worker.join_room(&token).await);
+            }
+            ApiRequests::LeaveRoom(token, response) => {
+                Self::respond(metrics, kind, response, worker.leave_room(&token).await);
+            }
+            ApiRequests::CreateDm(user_id, response) => {
+                Self::respond(metrics, kind, response, worker.create_dm(&user_id).await);
+            }
+            ApiRequests::SetReadStatusPrivacy(private, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.set_read_status_privacy(private).await,
+                );
+            }
+            ApiRequests::ShareFile(token, name, data, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.share_file(&token, &name, data).await,
+                );
+            }
+            ApiRequests::FetchUserStatus(response) => {
+                Self::respond(metrics, kind, response, worker.fetch_user_status().await);
+            }
+            ApiRequests::FetchOutOfOffice(user_id, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.fetch_out_of_office(&user_id).await,
+                );
+            }
+            ApiRequests::SetUserStatus(status, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.set_user_status(&status).await,
+                );
+            }
+            ApiRequests::SetUserStatusMessage(message, icon, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.set_user_status_message(&message, icon).await,
+                );
+            }
+            ApiRequests::FetchCapabilities(response) => {
+                Self::respond(metrics, kind, response, worker.fetch_capabilities().await);
+            }
+            ApiRequests::JoinAsGuest(token, password, name, response) => {
+                Self::respond(
+                    metrics,
+                    kind,
+                    response,
+                    worker.join_as_guest(&token, password, name).await,
+                );
             }
             ApiRequests::None => {
                 log::warn!("Unknown Request");
@@ -160,6 +657,10 @@ impl NCRequest {
 
         let mut worker_queue = vec![];
         let cancel_token = CancellationToken::new();
+        let rate_limit = RateLimit::default();
+        let dumps = RequestDumps::default();
+        let metrics = RequestMetrics::default();
+        let guest_session = config.guest.as_ref().map(|_| GuestSession::default());
 
         for i in 1..6 {
             let cloned_cancel_token = cancel_token.clone();
@@ -167,17 +668,25 @@ impl NCRequest {
             let (tx_worker, mut rx_worker) = mpsc::channel::<ApiRequests>(10);
 
             worker_queue.push(tx_worker);
-            let worker = NCRequestWorker::new(config).expect("Failed to create worker.");
+            let worker_metrics = metrics.clone();
+            let mut worker = NCRequestWorker::new(config).expect("Failed to create worker.");
+            worker.set_rate_limit(rate_limit.clone());
+            worker.set_dumps(dumps.clone());
+            worker.set_metrics(metrics.clone());
+            if let Some(session) = &guest_session {
+                worker.set_guest_session(session.clone());
+            }
 
             tokio::spawn(async move {
                 while !cloned_cancel_token.is_cancelled() {
                     if let Some(req) = rx_worker.recv().await {
-                        NCRequest::handle_req(&worker, req).await;
+                        NCRequest::handle_req(&worker, req, &worker_metrics).await;
                     };
                 }
             });
         }
         let cloned_cancel_token = cancel_token.clone();
+        let worker_tx = worker_queue.clone();
 
         tokio::spawn(async move {
             while !cloned_cancel_token.is_cancelled() {
@@ -220,9 +729,23 @@ impl NCRequest {
         });
         log::info!("Spawned API Thread");
 
+        let mut long_poll_worker =
+            NCRequestWorker::new(config).expect("Failed to create long poll worker.");
+        long_poll_worker.set_rate_limit(rate_limit.clone());
+        long_poll_worker.set_dumps(dumps.clone());
+        long_poll_worker.set_metrics(metrics.clone());
+        if let Some(session) = guest_session {
+            long_poll_worker.set_guest_session(session);
+        }
+
         NCRequest {
             request_tx: tx,
             cancel_token,
+            long_poll_worker: Arc::new(long_poll_worker),
+            rate_limit,
+            dumps,
+            metrics,
+            worker_tx,
         }
     }
 }
@@ -237,16 +760,14 @@ impl NCRequestInterface for NCRequest {
         let (tx, rx) = tokio::sync::oneshot::channel();
         self.request_tx
             .send(ApiRequests::SendMessage(token.clone(), message, tx))
-            .await
-            .expect("Queuing request for sending of message failed.");
+            .await?;
         Ok(rx)
     }
     async fn request_rooms_initial(&self) -> ApiResult<(Vec<NCReqDataRoom>, i64)> {
         let (tx, rx) = tokio::sync::oneshot::channel();
         self.request_tx
             .send(ApiRequests::FetchRoomsInitial(tx))
-            .await
-            .expect("Queuing request for sending of message failed.");
+            .await?;
         Ok(rx)
     }
     async fn request_autocomplete_users(&self, name: &str) -> ApiResult<Vec<NCReqDataUser>> {
@@ -254,8 +775,7 @@ impl NCRequestInterface for NCRequest {
 
         self.request_tx
             .send(ApiRequests::FetchAutocompleteUsers(name.to_string(), tx))
-            .await
-            .expect("Queuing request for sending of message failed.");
+            .await?;
         Ok(rx)
     }
     async fn request_participants(&self, token: &Token) -> ApiResult<Vec<NCReqDataParticipants>> {
@@ -263,8 +783,7 @@ impl NCRequestInterface for NCRequest {
 
         self.request_tx
             .send(ApiRequests::FetchParticipants(token.clone(), tx))
-            .await
-            .expect("Queuing request for sending of message failed.");
+            .await?;
         Ok(rx)
     }
 
@@ -276,8 +795,7 @@ impl NCRequestInterface for NCRequest {
 
         self.request_tx
             .send(ApiRequests::FetchRoomsUpdate(last_timestamp, tx))
-            .await
-            .expect("Queuing request for sending of message failed.");
+            .await?;
         Ok(rx)
     }
     async fn request_chat_initial(
@@ -289,8 +807,7 @@ impl NCRequestInterface for NCRequest {
 
         self.request_tx
             .send(ApiRequests::FetchChatInitial(token.clone(), maxMessage, tx))
-            .await
-            .expect("Queuing request for sending of message failed.");
+            .await?;
         Ok(rx)
     }
     async fn request_chat_update(
@@ -308,10 +825,40 @@ impl NCRequestInterface for NCRequest {
                 last_message,
                 tx,
             ))
-            .await
-            .expect("Queuing request for sending of message failed.");
+            .await?;
+        Ok(rx)
+    }
+    async fn request_chat_history(
+        &self,
+        token: &Token,
+        maxMessage: i32,
+        first_message: i32,
+    ) -> ApiResult<Vec<NCReqDataMessage>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        self.request_tx
+            .send(ApiRequests::FetchChatHistory(
+                token.clone(),
+                maxMessage,
+                first_message,
+                tx,
+            ))
+            .await?;
         Ok(rx)
     }
+    fn start_long_poll(
+        &self,
+        token: &Token,
+        last_message: i32,
+        sender: Sender<PushedMessages>,
+    ) -> LongPoll {
+        LongPoll::start(
+            Arc::clone(&self.long_poll_worker),
+            token,
+            last_message,
+            sender,
+        )
+    }
     async fn request_mark_chat_read(&self, token: &str, last_message: i32) -> ApiResult<()> {
         let (tx, rx) = tokio::sync::oneshot::channel();
 
@@ -321,10 +868,381 @@ impl NCRequestInterface for NCRequest {
                 last_message,
                 tx,
             ))
-            .await
-            .expect("Queuing request for sending of message failed.");
+            .await?;
+        Ok(rx)
+    }
+    async fn request_set_favorite(&self, token: &str, favorite: bool) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        self.request_tx
+            .send(ApiRequests::SetFavorite(token.to_string(), favorite, tx))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_set_notification_level(&self, token: &str, level: i32) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        self.request_tx
+            .send(ApiRequests::SetNotificationLevel(
+                token.to_string(),
+                level,
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_set_archived(&self, token: &str, archived: bool) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        self.request_tx
+            .send(ApiRequests::SetArchived(token.to_string(), archived, tx))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_add_participant(&self, token: &str, user_id: &str) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        self.request_tx
+            .send(ApiRequests::AddParticipant(
+                token.to_string(),
+                user_id.to_string(),
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_remove_attendee(&self, token: &str, attendee_id: i32) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        self.request_tx
+            .send(ApiRequests::RemoveAttendee(
+                token.to_string(),
+                attendee_id,
+                tx,
+            ))
+            .await?;
         Ok(rx)
     }
+    async fn request_set_moderator(
+        &self,
+        token: &str,
+        attendee_id: i32,
+        moderator: bool,
+    ) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        self.request_tx
+            .send(ApiRequests::SetModerator(
+                token.to_string(),
+                attendee_id,
+                moderator,
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_ban_attendee(
+        &self,
+        token: &str,
+        actor_type: &str,
+        actor_id: &str,
+    ) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        self.request_tx
+            .send(ApiRequests::BanAttendee(
+                token.to_string(),
+                actor_type.to_string(),
+                actor_id.to_string(),
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_set_room_name(&self, token: &str, name: &str) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        self.request_tx
+            .send(ApiRequests::SetRoomName(
+                token.to_string(),
+                name.to_string(),
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_set_room_description(&self, token: &str, description: &str) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        self.request_tx
+            .send(ApiRequests::SetRoomDescription(
+                token.to_string(),
+                description.to_string(),
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_send_reply(
+        &self,
+        message: String,
+        token: &Token,
+        reply_to: i32,
+    ) -> ApiResult<NCReqDataMessage> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::SendReply(token.clone(), message, reply_to, tx))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_edit_message(
+        &self,
+        token: &str,
+        message_id: i32,
+        message: &str,
+    ) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::EditMessage(
+                token.to_string(),
+                message_id,
+                message.to_string(),
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_delete_message(&self, token: &str, message_id: i32) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::DeleteMessage(
+                token.to_string(),
+                message_id,
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_react_to_message(
+        &self,
+        token: &str,
+        message_id: i32,
+        reaction: &str,
+    ) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::ReactToMessage(
+                token.to_string(),
+                message_id,
+                reaction.to_string(),
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_delete_reaction(
+        &self,
+        token: &str,
+        message_id: i32,
+        reaction: &str,
+    ) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::DeleteReaction(
+                token.to_string(),
+                message_id,
+                reaction.to_string(),
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_set_reminder(
+        &self,
+        token: &str,
+        message_id: i32,
+        timestamp: i64,
+    ) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::SetReminder(
+                token.to_string(),
+                message_id,
+                timestamp,
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_pin_message(&self, token: &str, message_id: i32) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::PinMessage(token.to_string(), message_id, tx))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_set_room_password(&self, token: &str, password: &str) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::SetRoomPassword(
+                token.to_string(),
+                password.to_string(),
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_set_mention_permissions(
+        &self,
+        token: &str,
+        permissions: i32,
+    ) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::SetMentionPermissions(
+                token.to_string(),
+                permissions,
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_set_message_expiration(&self, token: &str, seconds: i32) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::SetMessageExpiration(
+                token.to_string(),
+                seconds,
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_listed_rooms(&self, search: &str) -> ApiResult<Vec<NCReqDataRoom>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::FetchListedRooms(search.to_string(), tx))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_join_room(&self, token: &str) -> ApiResult<NCReqDataRoom> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::JoinRoom(token.to_string(), tx))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_leave_room(&self, token: &str) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::LeaveRoom(token.to_string(), tx))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_create_dm(&self, user_id: &str) -> ApiResult<NCReqDataRoom> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::CreateDm(user_id.to_string(), tx))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_set_read_status_privacy(&self, private: bool) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::SetReadStatusPrivacy(private, tx))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_share_file(&self, token: &str, name: &str, data: Vec<u8>) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::ShareFile(
+                token.to_string(),
+                name.to_string(),
+                data,
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_user_status(&self) -> ApiResult<NCReqDataUserStatus> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::FetchUserStatus(tx))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_out_of_office(
+        &self,
+        user_id: &str,
+    ) -> ApiResult<Option<NCReqDataOutOfOffice>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::FetchOutOfOffice(user_id.to_string(), tx))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_set_user_status(&self, status: &str) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::SetUserStatus(status.to_string(), tx))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_set_user_status_message(
+        &self,
+        message: &str,
+        icon: Option<String>,
+    ) -> ApiResult<()> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::SetUserStatusMessage(
+                message.to_string(),
+                icon,
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_capabilities(&self) -> ApiResult<NCReqDataCapabilities> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::FetchCapabilities(tx))
+            .await?;
+        Ok(rx)
+    }
+    async fn request_join_as_guest(
+        &self,
+        token: &str,
+        password: Option<String>,
+        name: Option<String>,
+    ) -> ApiResult<NCReqDataRoom> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.request_tx
+            .send(ApiRequests::JoinAsGuest(
+                token.to_string(),
+                password,
+                name,
+                tx,
+            ))
+            .await?;
+        Ok(rx)
+    }
+    fn rate_limited_for(&self) -> Option<Duration> {
+        self.rate_limit.remaining(Instant::now())
+    }
+    fn take_request_dumps(&self) -> Vec<PathBuf> {
+        self.dumps.take()
+    }
+
+    fn metrics(&self) -> MetricsSnapshot {
+        let queued = std::iter::once(&self.request_tx)
+            .chain(&self.worker_tx)
+            .map(|sender| sender.max_capacity() - sender.capacity())
+            .collect();
+        self.metrics.snapshot(queued)
+    }
+
     async fn shutdown(&self) -> Result<(), Box<dyn std::error::Error>> {
         self.cancel_token.cancel();
         Ok(())
@@ -361,7 +1279,62 @@ mock! {
         maxMessage: i32,
         last_message: i32,
     ) -> ApiResult<Vec<NCReqDataMessage>>;
+    async fn request_chat_history(
+        &self,
+        token: &Token,
+        maxMessage: i32,
+        first_message: i32,
+    ) -> ApiResult<Vec<NCReqDataMessage>>;
+    fn start_long_poll(
+        &self,
+        token: &Token,
+        last_message: i32,
+        sender: Sender<PushedMessages>,
+    ) -> LongPoll;
     async fn request_mark_chat_read(&self, token: &str, last_message: i32) -> ApiResult<()>;
+    async fn request_set_favorite(&self, token: &str, favorite: bool) -> ApiResult<()>;
+    async fn request_set_notification_level(&self, token: &str, level: i32) -> ApiResult<()>;
+    async fn request_set_archived(&self, token: &str, archived: bool) -> ApiResult<()>;
+    async fn request_add_participant(&self, token: &str, user_id: &str) -> ApiResult<()>;
+    async fn request_remove_attendee(&self, token: &str, attendee_id: i32) -> ApiResult<()>;
+    async fn request_set_moderator(&self, token: &str, attendee_id: i32, moderator: bool) -> ApiResult<()>;
+    async fn request_ban_attendee(&self, token: &str, actor_type: &str, actor_id: &str) -> ApiResult<()>;
+    async fn request_set_room_name(&self, token: &str, name: &str) -> ApiResult<()>;
+    async fn request_set_room_description(&self, token: &str, description: &str) -> ApiResult<()>;
+    async fn request_send_reply(&self, message: String, token: &Token, reply_to: i32) -> ApiResult<NCReqDataMessage>;
+    async fn request_edit_message(&self, token: &str, message_id: i32, message: &str) -> ApiResult<()>;
+    async fn request_delete_message(&self, token: &str, message_id: i32) -> ApiResult<()>;
+    async fn request_react_to_message(&self, token: &str, message_id: i32, reaction: &str) -> ApiResult<()>;
+    async fn request_delete_reaction(&self, token: &str, message_id: i32, reaction: &str) -> ApiResult<()>;
+    async fn request_set_reminder(&self, token: &str, message_id: i32, timestamp: i64) -> ApiResult<()>;
+    async fn request_pin_message(&self, token: &str, message_id: i32) -> ApiResult<()>;
+    async fn request_set_room_password(&self, token: &str, password: &str) -> ApiResult<()>;
+    async fn request_set_mention_permissions(&self, token: &str, permissions: i32) -> ApiResult<()>;
+    async fn request_set_message_expiration(&self, token: &str, seconds: i32) -> ApiResult<()>;
+    async fn request_listed_rooms(&self, search: &str) -> ApiResult<Vec<NCReqDataRoom>>;
+    async fn request_join_room(&self, token: &str) -> ApiResult<NCReqDataRoom>;
+        async fn request_leave_room(&self, token: &str) -> ApiResult<()>;
+    async fn request_create_dm(&self, user_id: &str) -> ApiResult<NCReqDataRoom>;
+    async fn request_set_read_status_privacy(&self, private: bool) -> ApiResult<()>;
+    async fn request_share_file(&self, token: &str, name: &str, data: Vec<u8>) -> ApiResult<()>;
+    async fn request_user_status(&self) -> ApiResult<NCReqDataUserStatus>;
+    async fn request_out_of_office(&self, user_id: &str) -> ApiResult<Option<NCReqDataOutOfOffice>>;
+    async fn request_set_user_status(&self, status: &str) -> ApiResult<()>;
+    async fn request_set_user_status_message(
+        &self,
+        message: &str,
+        icon: Option<String>,
+    ) -> ApiResult<()>;
+    async fn request_capabilities(&self) -> ApiResult<NCReqDataCapabilities>;
+    async fn request_join_as_guest(
+        &self,
+        token: &str,
+        password: Option<String>,
+        name: Option<String>,
+    ) -> ApiResult<NCReqDataRoom>;
+    fn rate_limited_for(&self) -> Option<Duration>;
+    fn take_request_dumps(&self) -> Vec<PathBuf>;
+    fn metrics(&self) -> MetricsSnapshot;
     async fn shutdown(&self) -> Result<(), Box<dyn std::error::Error>>;
     }
     impl Clone for NCRequest {   // specification of the trait to mock
@@ -0,0 +1,269 @@
+//! Retrying failed requests with exponential backoff, so a flaky connection does not surface as errors.
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use reqwest::{header::RETRY_AFTER, Method, RequestBuilder, Response, StatusCode};
+
+use crate::{backend::request_metrics::RequestMetrics, config::Config};
+
+/// Longest wait between two attempts, however many attempts failed before.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Longest wait the server can ask for with `Retry-After`, longer waits are cut short.
+const MAX_RETRY_AFTER: Duration = Duration::from_mins(5);
+
+/// Time until which the server asked to not send further requests, shared by all workers.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimit(Arc<Mutex<Option<Instant>>>);
+
+impl RateLimit {
+    /// Remember that the server asked to wait until `until`, keeping a later time already known.
+    fn limit_until(&self, until: Instant) {
+        if let Ok(mut limit) = self.0.lock() {
+            *limit = Some(limit.map_or(until, |known| known.max(until)));
+        }
+    }
+
+    /// Time left until the rate limit ends, `None` if requests are not limited.
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        let limit = (*self.0.lock().ok()?)?;
+        (limit > now).then(|| limit - now)
+    }
+}
+
+/// Wait asked for by the `Retry-After` header, either in seconds or as an HTTP date.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    let wait = match value.parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => chrono::DateTime::parse_from_rfc2822(value)
+            .ok()?
+            .signed_duration_since(chrono::Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    };
+    Some(wait.min(MAX_RETRY_AFTER))
+}
+
+/// How often and how long apart failed requests are retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts per request including the first one.
+    max_attempts: u32,
+    /// Wait before the first retry, doubled for every further one.
+    backoff: Duration,
+    rate_limit: RateLimit,
+    metrics: RequestMetrics,
+}
+
+impl RetryPolicy {
+    pub fn new(config: &Config) -> Self {
+        let network = &config.data.network;
+        RetryPolicy {
+            max_attempts: network.retry_max_attempts.max(1),
+            backoff: Duration::from_millis(network.retry_backoff_ms),
+            rate_limit: RateLimit::default(),
+            metrics: RequestMetrics::default(),
+        }
+    }
+
+    /// Share the rate limit with the policies of other workers talking to the same server.
+    pub fn set_rate_limit(&mut self, rate_limit: RateLimit) {
+        self.rate_limit = rate_limit;
+    }
+
+    /// Count the retries together with the other workers.
+    pub fn set_metrics(&mut self, metrics: RequestMetrics) {
+        self.metrics = metrics;
+    }
+
+    /// Wait after the failed attempt `attempt`, counting from 1.
+    fn delay(&self, attempt: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(MAX_BACKOFF)
+    }
+
+    /// Posting may create something, like a message, so it is only retried when the server
+    /// did certainly not handle the request, otherwise it could happen twice.
+    fn retry_status(method: &Method, status: StatusCode) -> bool {
+        status == StatusCode::TOO_MANY_REQUESTS
+            || (status.is_server_error() && *method != Method::POST)
+    }
+
+    fn retry_error(method: &Method, error: &reqwest::Error) -> bool {
+        error.is_connect() || (error.is_timeout() && *method != Method::POST)
+    }
+
+    /// Send the request, retrying it while it fails in a way worth retrying and attempts are left.
+    /// When the server limits the rate, all workers sharing the limit pause as asked by `Retry-After`.
+    /// The last response or error is returned as is.
+    pub async fn send(
+        &self,
+        method: &Method,
+        mut request: RequestBuilder,
+    ) -> Result<Response, reqwest::Error> {
+        let mut attempt = 1;
+        loop {
+            if let Some(wait) = self.rate_limit.remaining(Instant::now()) {
+                log::debug!("Holding back {method} for {wait:?} while rate limited");
+                tokio::time::sleep(wait).await;
+            }
+            let retry = if attempt < self.max_attempts {
+                request.try_clone()
+            } else {
+                None
+            };
+            let result = request.send().await;
+            let mut delay = self.delay(attempt);
+            let failure = match &result {
+                Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                    delay = retry_after(response).map_or(delay, |wait| wait.max(delay));
+                    self.rate_limit.limit_until(Instant::now() + delay);
+                    log::warn!("Rate limited by the server, pausing for {delay:?}");
+                    Some(response.status().to_string())
+                }
+                Ok(response) if RetryPolicy::retry_status(method, response.status()) => {
+                    Some(response.status().to_string())
+                }
+                Err(why) if RetryPolicy::retry_error(method, why) => Some(why.to_string()),
+                _ => None,
+            };
+            match (retry, failure) {
+                (Some(next), Some(failure)) => {
+                    log::debug!(
+                        "{method} attempt {attempt}/{} failed with {failure}, retrying in {delay:?}",
+                        self.max_attempts
+                    );
+                    self.metrics.record_retry();
+                    tokio::time::sleep(delay).await;
+                    request = next;
+                    attempt += 1;
+                }
+                _ => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            backoff: Duration::from_millis(1),
+            rate_limit: RateLimit::default(),
+            metrics: RequestMetrics::default(),
+        }
+    }
+
+    /// Answer one request after the other with the given status codes.
+    async fn serve(statuses: Vec<u16>) -> String {
+        serve_with_headers(statuses, "").await
+    }
+
+    /// Like [`serve`], adding the header lines to every response.
+    async fn serve_with_headers(statuses: Vec<u16>, headers: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = [0; 1024];
+                let _ = stream.read(&mut buffer).await.unwrap();
+                let response = format!(
+                    "HTTP/1.1 {status} X\r\n{headers}Content-Length: 0\r\nConnection: close\r\n\r\n"
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn backoff() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            backoff: Duration::from_millis(500),
+            rate_limit: RateLimit::default(),
+            metrics: RequestMetrics::default(),
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(500));
+        assert_eq!(policy.delay(3), Duration::from_secs(2));
+        assert_eq!(policy.delay(10), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn retried_statuses() {
+        assert!(RetryPolicy::retry_status(
+            &Method::GET,
+            StatusCode::SERVICE_UNAVAILABLE
+        ));
+        assert!(RetryPolicy::retry_status(
+            &Method::POST,
+            StatusCode::TOO_MANY_REQUESTS
+        ));
+        assert!(!RetryPolicy::retry_status(
+            &Method::POST,
+            StatusCode::BAD_GATEWAY
+        ));
+        assert!(!RetryPolicy::retry_status(
+            &Method::GET,
+            StatusCode::NOT_FOUND
+        ));
+    }
+
+    #[tokio::test]
+    async fn retry_until_success() {
+        let url = serve(vec![503, 429, 200]).await;
+        let client = reqwest::Client::new();
+        let metrics = RequestMetrics::default();
+        let mut counted = policy(3);
+        counted.set_metrics(metrics.clone());
+        let response = counted.send(&Method::GET, client.get(&url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(metrics.snapshot(vec![]).retries, 2);
+
+        let url = serve(vec![503, 503]).await;
+        let response = policy(2)
+            .send(&Method::GET, client.get(&url))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let url = serve(vec![502, 200]).await;
+        let response = policy(3)
+            .send(&Method::POST, client.post(&url))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test]
+    async fn rate_limit() {
+        let url = serve_with_headers(vec![429], "Retry-After: 120\r\n").await;
+        let policy = policy(1);
+        let response = policy
+            .send(&Method::GET, reqwest::Client::new().get(&url))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(retry_after(&response), Some(Duration::from_mins(2)));
+        let remaining = policy.rate_limit.remaining(Instant::now()).unwrap();
+        assert!(remaining > Duration::from_secs(110) && remaining <= Duration::from_mins(2));
+        assert_eq!(
+            policy
+                .rate_limit
+                .remaining(Instant::now() + Duration::from_secs(121)),
+            None
+        );
+        assert_eq!(RateLimit::default().remaining(Instant::now()), None);
+    }
+}
@@ -1,82 +1,223 @@
 use super::{
-    nc_message::NCMessage,
+    chat_message::ChatMessage,
+    chat_room::ChatRoom,
+    chat_types::{Participant, ParticipantChange, ParticipantRole, RoomType},
+    message_store::MessageStore,
     nc_request::{
         nc_requester::NCRequestInterface, NCReqDataMessage, NCReqDataParticipants, NCReqDataRoom,
         Token,
     },
 };
+use crate::config::Config;
 use async_trait::async_trait;
-use itertools::Itertools;
+use chrono::Utc;
 use log;
-use num_derive::FromPrimitive;
-use num_traits::{AsPrimitive, FromPrimitive};
-use std::collections::BTreeMap;
-use std::fmt::{Debug, Display};
+use num_traits::AsPrimitive;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-/// Different Types of rooms defined by the [NC API](https://nextcloud-talk.readthedocs.io/en/latest/constants/#conversation-types)
-#[derive(Debug, FromPrimitive, PartialEq, Default)]
-pub enum NCRoomTypes {
-    /// DM
-    #[default]
-    OneToOne = 1,
-    /// Group Chat
-    Group,
-    /// Public Channel
-    Public,
-    /// NC API Change Log
-    ChangeLog,
-    /// Old One to One
-    Deprecated,
-    /// Talk to yourself
-    NoteToSelf,
+/// Notification level which silences a room, see [NC API](https://nextcloud-talk.readthedocs.io/en/latest/constants/#participant-notification-levels)
+const NOTIFICATION_LEVEL_NEVER: i32 = 3;
+/// Notification level which falls back to the user default.
+const NOTIFICATION_LEVEL_DEFAULT: i32 = 0;
+
+/// Participant types, see [NC API](https://nextcloud-talk.readthedocs.io/en/latest/constants/#participant-types)
+const PARTICIPANT_TYPE_OWNER: i32 = 1;
+const PARTICIPANT_TYPE_MODERATOR: i32 = 2;
+const PARTICIPANT_TYPE_USER: i32 = 3;
+const PARTICIPANT_TYPE_GUEST: i32 = 4;
+/// Participant type of users which joined a public room by link, without being added.
+const PARTICIPANT_TYPE_USER_SELF_JOINED: i32 = 5;
+const PARTICIPANT_TYPE_GUEST_MODERATOR: i32 = 6;
+
+/// Actor type of users of the server, see [NC API](https://nextcloud-talk.readthedocs.io/en/latest/constants/#actor-types)
+const ACTOR_TYPE_USERS: &str = "users";
+
+/// Call flag of participants not in the call, see [NC API](https://nextcloud-talk.readthedocs.io/en/latest/constants/#participant-in-call-flag)
+const IN_CALL_DISCONNECTED: i32 = 0;
+
+/// Object type of breakout rooms, their object id is the token of the parent room.
+const OBJECT_TYPE_BREAKOUT_ROOM: &str = "room";
+/// Object type of the sample conversation Talk creates for new users.
+const OBJECT_TYPE_SAMPLE: &str = "sample";
+
+/// Mention permission which only lets moderators mention @all, see [NC API](https://nextcloud-talk.readthedocs.io/en/latest/constants/#mention-permissions)
+const MENTION_PERMISSIONS_MODERATORS: i32 = 1;
+
+/// Lobby state which only lets moderators in, see [NC API](https://nextcloud-talk.readthedocs.io/en/latest/constants/#webinar-lobby-states)
+const LOBBY_NON_MODERATORS: i32 = 1;
+
+/// How many participant changes a room keeps to show in the chat.
+const MAX_PARTICIPANT_CHANGES: usize = 100;
+
+/// Messages read from the store when a room is loaded, older ones are read while scrolling up.
+const STORED_WINDOW: usize = 500;
+/// How many matches a search of the stored messages returns.
+const SEARCH_RESULTS: usize = 100;
+
+/// How many messages are fetched at once, for rooms missing in the disk cache and on demand.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FetchSizes {
+    pub default: i32,
+    pub favorite: i32,
+    pub unopened: i32,
 }
 
-#[cfg(test)]
-use mockall::{automock, predicate::*};
+impl Default for FetchSizes {
+    fn default() -> Self {
+        FetchSizes {
+            default: 100,
+            favorite: 200,
+            unopened: 20,
+        }
+    }
+}
+
+impl FetchSizes {
+    pub fn new(config: &Config) -> Self {
+        FetchSizes {
+            default: config.data.general.initial_fetch_size,
+            favorite: config.data.general.initial_fetch_size_favorite,
+            unopened: config.data.general.initial_fetch_size_unopened,
+        }
+    }
+
+    /// Pick the initial message window, larger for favorites and smaller for rooms never read.
+    pub fn initial_for(&self, room_data: &NCReqDataRoom) -> i32 {
+        if room_data.isFavorite {
+            self.favorite
+        } else if room_data.lastReadMessage == 0 {
+            self.unopened
+        } else {
+            self.default
+        }
+    }
+}
+
+/// Compare two participant lists by attendee id, listing who joined and who left.
+pub fn diff_participants(
+    old: &[NCReqDataParticipants],
+    new: &[NCReqDataParticipants],
+    timestamp: i64,
+) -> Vec<ParticipantChange> {
+    let change = |participant: &NCReqDataParticipants, joined| ParticipantChange {
+        timestamp,
+        display_name: participant.displayName.clone(),
+        joined,
+    };
+    let ids = |participants: &[NCReqDataParticipants]| -> HashSet<i32> {
+        participants
+            .iter()
+            .map(|participant| participant.attendeeId)
+            .collect()
+    };
+    let (old_ids, new_ids) = (ids(old), ids(new));
+    let joined = new
+        .iter()
+        .filter(|participant| !old_ids.contains(&participant.attendeeId))
+        .map(|participant| change(participant, true));
+    let left = old
+        .iter()
+        .filter(|participant| !new_ids.contains(&participant.attendeeId))
+        .map(|participant| change(participant, false));
+    joined.chain(left).collect()
+}
+
+/// Role of a participant type of the NC API.
+fn role(participant_type: i32) -> ParticipantRole {
+    match participant_type {
+        PARTICIPANT_TYPE_OWNER => ParticipantRole::Owner,
+        PARTICIPANT_TYPE_MODERATOR => ParticipantRole::Moderator,
+        PARTICIPANT_TYPE_USER => ParticipantRole::User,
+        PARTICIPANT_TYPE_GUEST => ParticipantRole::Guest,
+        PARTICIPANT_TYPE_USER_SELF_JOINED => ParticipantRole::SelfJoinedUser,
+        PARTICIPANT_TYPE_GUEST_MODERATOR => ParticipantRole::GuestModerator,
+        _ => ParticipantRole::Unknown,
+    }
+}
+
+impl From<&NCReqDataParticipants> for Participant {
+    fn from(data: &NCReqDataParticipants) -> Self {
+        Participant {
+            attendee_id: data.attendeeId,
+            actor_id: data.actorId.clone(),
+            is_user: data.actorType == ACTOR_TYPE_USERS,
+            display_name: data.displayName.clone(),
+            role: role(data.participantType),
+            in_call: data.inCall != IN_CALL_DISCONNECTED,
+            status: data.status.clone(),
+            status_icon: data.statusIcon.clone(),
+            status_message: data.statusMessage.clone(),
+            status_clear_at: data.statusClearAt.map(i64::from),
+        }
+    }
+}
+
+/// Room type of a conversation type of the [NC API](https://nextcloud-talk.readthedocs.io/en/latest/constants/#conversation-types),
+/// `None` for unknown ones.
+fn room_type(conversation_type: i32) -> Option<RoomType> {
+    match conversation_type {
+        1 => Some(RoomType::OneToOne),
+        2 => Some(RoomType::Group),
+        3 => Some(RoomType::Public),
+        4 => Some(RoomType::ChangeLog),
+        5 => Some(RoomType::Deprecated),
+        6 => Some(RoomType::NoteToSelf),
+        _ => None,
+    }
+}
+
+/// Conversation type of the NC API for a room type, the reverse of [`room_type`].
+#[cfg(feature = "matrix")]
+pub fn conversation_type(room_type: RoomType) -> i32 {
+    match room_type {
+        RoomType::OneToOne => 1,
+        RoomType::Group => 2,
+        RoomType::Public => 3,
+        RoomType::ChangeLog => 4,
+        RoomType::Deprecated => 5,
+        RoomType::NoteToSelf => 6,
+    }
+}
+
+/// Name of whoever added `user_id` to a room, found in its system messages.
+/// Falls back to the creator of the room, if the note about adding the user isn't loaded.
+pub fn added_by(messages: &BTreeMap<i32, ChatMessage>, user_id: &str) -> Option<String> {
+    let actor = |message: &ChatMessage| message.data().actorDisplayName.clone();
+    messages
+        .values()
+        .rev()
+        .find(|message| message.get_added_user() == Some(user_id))
+        .or_else(|| {
+            messages
+                .values()
+                .find(|message| message.is_room_creation() && message.data().actorId != user_id)
+        })
+        .map(actor)
+        .filter(|name| !name.is_empty())
+}
 
 /// Room Interface Trait
-/// Holds all public functions for operations on NC Talk Rooms. For details see [NCRoom].
-#[cfg_attr(test, automock)]
+/// Holds the Nextcloud specific functions for operations on NC Talk Rooms, on top of the
+/// provider agnostic [`ChatRoom`] ones the UI uses. For details see [`NCRoom`].
 #[async_trait]
-pub trait NCRoomInterface: Debug + Send + Display + Ord + Default {
-    /// Get the ID of the last message of this Room.
-    /// This is filtered to not include reactions and deleted messages.
-    fn get_last_room_level_message_id(&self) -> Option<i32>;
-    /// Check if this Room has unread messages.
-    fn has_unread(&self) -> bool;
-    /// Check if this Room is a DM Room.
-    #[allow(dead_code)]
-    fn is_dm(&self) -> bool;
-    /// Check if this Room is a Group Chat.
-    fn is_group(&self) -> bool;
-    /// Get a Vector of all the messages in the room.
-    fn get_messages(&self) -> &BTreeMap<i32, NCMessage>;
-    /// Get how many messages are unread.
-    fn get_unread(&self) -> usize;
-    /// Check if this Room is a favorite.
-    fn is_favorite(&self) -> bool;
-    /// Get the human readable display name of the room.
-    fn get_display_name(&self) -> &str;
-    /// Get the if of the last read messages.
-    fn get_last_read(&self) -> i32;
-    /// Get a Vector of the users in the Room.
-    fn get_users(&self) -> &Vec<NCReqDataParticipants>;
-    /// Get the room type.
-    fn get_room_type(&self) -> &NCRoomTypes;
-
+pub trait NCRoomInterface: ChatRoom {
     /// Make this room a json object which can be serialised.
     #[allow(dead_code)]
     fn to_json(&self) -> String;
     /// Get the Underlying Data Object of this Room.
     fn to_data(&self) -> NCReqDataRoom;
-    /// Write this room into a log file.
+    /// Write the messages of this room which changed since they were last stored.
     fn write_to_log(&mut self) -> Result<(), std::io::Error>;
-    /// Get the rooms token.
-    fn to_token(&self) -> Token;
+    /// Add messages pushed by the long poll of this Room, skipping known ones.
+    /// Messages newer than the last one of the room data count as unread until the next sync.
+    /// Returns the display name and number of new messages, if the Room has unread messages.
+    fn add_messages(&mut self, messages: Vec<NCReqDataMessage>) -> Option<(String, usize)>;
     /// Check if the message ID is newer than the stored one and update the content.
-    /// This is needed since the NCTalk will fetch all rooms and only get the overview data.
+    /// This is needed since the `NCTalk` will fetch all rooms and only get the overview data.
     async fn update_if_id_is_newer<Requester: NCRequestInterface + 'static + std::marker::Sync>(
         &mut self,
         message_id: i32,
@@ -100,138 +241,420 @@ pub trait NCRoomInterface: Debug + Send + Display + Ord + Default {
         &self,
         requester: Arc<tokio::sync::Mutex<Requester>>,
     ) -> Result<(), Box<dyn std::error::Error>>;
-    async fn fill_history<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+    /// Fetch up to `count` messages older than the oldest one loaded, returns how many were added.
+    async fn fetch_older<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        count: i32,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<usize, Box<dyn std::error::Error>>;
+    /// Add or remove this Room from the favorites.
+    async fn set_favorite<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &self,
+        favorite: bool,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Turn server side notifications for this Room off or back to the default.
+    async fn set_muted<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &self,
+        muted: bool,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Archive or unarchive this Room.
+    async fn set_archived<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &self,
+        archived: bool,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Add a user to this Room and refresh the participants.
+    async fn add_participant<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        user_id: &str,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Fetch the participants of this Room again, for their current status.
+    async fn refresh_participants<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Remove a participant from this Room and refresh the participants.
+    async fn remove_participant<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        attendee_id: i32,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Promote a participant to moderator or demote them and refresh the participants.
+    async fn set_moderator<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        attendee_id: i32,
+        moderator: bool,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Rename this Room.
+    async fn set_name<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        name: &str,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Change the description of this Room.
+    async fn set_description<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        description: &str,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Set the password of this public Room, an empty password removes it.
+    async fn set_password<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        password: &str,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Restrict mentioning @all in this Room to moderators, or allow it for everyone.
+    async fn set_mention_all_restricted<
+        Requester: NCRequestInterface + 'static + std::marker::Sync,
+    >(
+        &mut self,
+        restricted: bool,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Let messages in this Room expire after the given seconds, 0 keeps them.
+    async fn set_message_expiration<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        seconds: i32,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Ban a participant from this Room and refresh the participants.
+    async fn ban_participant<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        attendee_id: i32,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Send a Message to this Room as reply to the message with the given id.
+    async fn send_reply<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &self,
+        message: String,
+        reply_to: i32,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<String, Box<dyn std::error::Error>>;
+    /// Replace the text of a message in this Room.
+    async fn edit_message<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        message_id: i32,
+        message: &str,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Delete a message in this Room.
+    async fn delete_message<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        message_id: i32,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// React to a message in this Room.
+    async fn react_to_message<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        message_id: i32,
+        reaction: &str,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Take back an own reaction to a message in this Room.
+    async fn remove_reaction<Requester: NCRequestInterface + 'static + std::marker::Sync>(
         &mut self,
+        message_id: i32,
+        reaction: &str,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Let the server remind the user of a message at the given unix timestamp.
+    async fn set_reminder<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &self,
+        message_id: i32,
+        timestamp: i64,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+    /// Pin a message to the top of this Room.
+    async fn pin_message<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &self,
+        message_id: i32,
         requester: Arc<tokio::sync::Mutex<Requester>>,
     ) -> Result<(), Box<dyn std::error::Error>>;
 }
 
 /// Real implementation of the `NCRoom`.
-/// Holds its Messages, Participants, Raw Data and Path to write its log to.
+/// Holds its Messages, Participants, Raw Data and the Store its Messages are cached in.
 #[derive(Debug, Default)]
 pub struct NCRoom {
     /// ``BTreeMap`` of all its messages.
-    pub messages: BTreeMap<i32, NCMessage>,
+    pub messages: BTreeMap<i32, ChatMessage>,
     /// Raw Data of this Room.
     room_data: NCReqDataRoom,
-    /// Path to write json output to.
-    path_to_log: std::path::PathBuf,
+    /// Store of all messages, of which only the recent ones are held in memory.
+    store: MessageStore,
+    /// Hash of every message as it was last stored, to only store what changed.
+    stored: HashMap<i32, u64>,
     /// Type of this Room.
-    pub room_type: NCRoomTypes,
+    pub room_type: RoomType,
     /// Vec of all Participants in this Room.
     participants: Vec<NCReqDataParticipants>,
+    /// The participants as shown by the UI.
+    users: Vec<Participant>,
+    /// Participants which joined or left while the app was running.
+    participant_changes: Vec<ParticipantChange>,
+    /// Unix timestamp of the last sync with the server.
+    last_synced_at: i64,
+    /// Whether a session in this Room was joined while the app was running.
+    session_joined: bool,
 }
 
 impl NCRoom {
+    /// Update the last message, activity and unread count of the room data with messages
+    /// newer than its last one, so the room list is up to date before the next sync.
+    fn note_newer_messages(&mut self, messages: &[NCReqDataMessage]) {
+        let newer = messages
+            .iter()
+            .filter(|message| message.id > self.room_data.lastMessage.id)
+            .collect::<Vec<_>>();
+        let Some(newest) = newer.iter().max_by_key(|message| message.id) else {
+            return;
+        };
+        // Messages up to the last read one are not unread, like the own ones once synced.
+        let unread: i32 = newer
+            .iter()
+            .filter(|message| {
+                message.messageType == "comment" && message.id > self.room_data.lastReadMessage
+            })
+            .count()
+            .as_();
+        self.room_data.lastActivity = newest.timestamp.as_();
+        self.room_data.lastMessage = (*newest).clone();
+        self.room_data.unreadMessages += unread;
+    }
+
     /// Create a new `NCRoom`.
     /// Tries to read chat data from the disk, else fetches it.
     /// Requester is in a Thread safe Arc/Mutex.
+    /// `fetch_size` limits how many messages are fetched, when none are on the disk.
     pub async fn new<Requester: NCRequestInterface + 'static + std::marker::Sync>(
         room_data: NCReqDataRoom,
         requester: Arc<Mutex<Requester>>,
-        path_to_log: std::path::PathBuf,
+        store: MessageStore,
+        fetch_size: i32,
     ) -> Option<NCRoom> {
-        let mut tmp_path_buf = path_to_log.clone();
-        tmp_path_buf.push(room_data.token.as_str());
-        let path = tmp_path_buf.as_path();
-
-        let mut messages = BTreeMap::<i32, NCMessage>::new();
+        let mut messages = BTreeMap::<i32, ChatMessage>::new();
+        let mut stored = HashMap::new();
 
-        if path.exists() && path.is_file() {
-            if let Ok(data) = serde_json::from_str::<Vec<NCReqDataMessage>>(
-                std::fs::read_to_string(path).unwrap().as_str(),
-            ) {
+        match store.load_recent(&room_data.token, STORED_WINDOW) {
+            Ok(data) => {
                 for message in data {
+                    stored.insert(message.id, hash_message(&message));
                     messages.insert(message.id, message.into());
                 }
-            } else {
-                log::debug!(
-                    "Failed to parse json for {}, falling back to fetching",
+            }
+            Err(why) => log::warn!(
+                "Failed to read the stored messages of {}, falling back to fetching: {why}",
+                room_data.displayName
+            ),
+        }
+        if messages.is_empty() {
+            log::debug!("No stored messages for room {}", room_data.displayName);
+            if let Err(why) = NCRoom::fetch_messages::<Requester>(
+                requester.clone(),
+                &room_data.token,
+                fetch_size,
+                &mut messages,
+            )
+            .await
+            {
+                log::warn!(
+                    "Could not fetch messages of {}: {why}",
                     room_data.displayName
                 );
-                NCRoom::fetch_messages::<Requester>(
-                    requester.clone(),
-                    &room_data.token,
-                    &mut messages,
-                )
-                .await
-                .ok();
             }
-        } else {
-            log::debug!("No Log File found for room {}", room_data.displayName);
-            NCRoom::fetch_messages::<Requester>(requester.clone(), &room_data.token, &mut messages)
-                .await
-                .ok();
         }
 
-        Some(NCRoom {
+        let Some(room_type) = room_type(room_data.roomtype) else {
+            log::warn!(
+                "Unknown type {} of room {}",
+                room_data.roomtype,
+                room_data.displayName
+            );
+            return None;
+        };
+        let mut room = NCRoom {
             messages,
-            path_to_log: tmp_path_buf,
-            room_type: FromPrimitive::from_i32(room_data.roomtype).unwrap(),
+            store,
+            stored,
+            room_type,
             participants: vec![],
+            users: vec![],
+            participant_changes: vec![],
+            last_synced_at: Utc::now().timestamp(),
+            session_joined: false,
             room_data,
-        })
+        };
+        room.store_or_warn();
+        Some(room)
+    }
+
+    /// Store the messages which are new or changed since they were last stored.
+    fn store_changed(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let changed: Vec<_> = self
+            .messages
+            .values()
+            .map(ChatMessage::data)
+            .map(|message| (message, hash_message(message)))
+            .filter(|(message, hash)| self.stored.get(&message.id) != Some(hash))
+            .collect();
+        if changed.is_empty() {
+            return Ok(());
+        }
+        self.store.insert_messages(
+            &self.room_data.token,
+            changed.iter().map(|(message, _)| *message),
+        )?;
+        log::trace!("Stored {} messages of {self}", changed.len());
+        self.stored.extend(
+            changed
+                .into_iter()
+                .map(|(message, hash)| (message.id, hash)),
+        );
+        Ok(())
+    }
+
+    /// Like [`NCRoom::store_changed`], a failure only loses the cache, so it is logged only.
+    fn store_or_warn(&mut self) {
+        if let Err(why) = self.store_changed() {
+            log::warn!(
+                "Could not store the messages of {}: {why}",
+                self.room_data.displayName
+            );
+        }
     }
     async fn fetch_messages<Requester: NCRequestInterface + 'static + std::marker::Sync>(
         requester: Arc<Mutex<Requester>>,
         token: &Token,
-        messages: &mut BTreeMap<i32, NCMessage>,
+        fetch_size: i32,
+        messages: &mut BTreeMap<i32, ChatMessage>,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let response_onceshot = {
             requester
                 .lock()
                 .await
-                .request_chat_initial(token, 200)
-                .await
-                .unwrap()
+                .request_chat_initial(token, fetch_size)
+                .await?
         };
-        let response = response_onceshot
-            .await
-            .expect("Failed for fetch chat update")
-            .expect("Failed request");
+        let response = response_onceshot.await??;
         for message in response {
             messages.insert(message.id, message.into());
         }
         Ok(())
     }
 
-    async fn fetch_message_subset<Requester: NCRequestInterface + 'static + std::marker::Sync>(
-        first: i32,
-        last: i32,
+    async fn update_participants<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
         requester: Arc<Mutex<Requester>>,
-        token: &Token,
-    ) -> BTreeMap<i32, NCMessage> {
-        let mut fetch_key = first;
-        let mut messages = BTreeMap::new();
-        while fetch_key <= last && fetch_key >= 0 {
-            let response_onceshot = {
-                requester
-                    .lock()
-                    .await
-                    .request_chat_update(token, 200, fetch_key)
-                    .await
-                    .unwrap()
-            };
-            let response = response_onceshot
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let response_onceshot = {
+            requester
+                .lock()
                 .await
-                .expect("Failed for fetch chat update")
-                .expect("Failed request");
-            if response.is_empty() {
-                log::debug!("No Messages found aborting {}", fetch_key);
-                break;
-            }
-            fetch_key = response.last().expect("No Messages fetched").id;
+                .request_participants(&self.room_data.token)
+                .await?
+        };
+
+        let participants = response_onceshot.await??;
+        self.replace_participants(participants);
+        Ok(())
+    }
 
-            for message in response {
-                messages.insert(message.id, message.into());
+    /// Replace the participants, remembering who joined or left.
+    /// The first fetch of a room is not counted as changes.
+    fn replace_participants(&mut self, participants: Vec<NCReqDataParticipants>) {
+        if !self.participants.is_empty() {
+            let changes = diff_participants(
+                &self.participants,
+                &participants,
+                chrono::Utc::now().timestamp(),
+            );
+            for change in &changes {
+                log::debug!(
+                    "{} {} {}",
+                    change.display_name,
+                    if change.joined { "joined" } else { "left" },
+                    self.room_data.displayName
+                );
             }
+            self.participant_changes.extend(changes);
+            let overflow = self
+                .participant_changes
+                .len()
+                .saturating_sub(MAX_PARTICIPANT_CHANGES);
+            self.participant_changes.drain(..overflow);
         }
-        messages
+        self.users = participants.iter().map(Participant::from).collect();
+        self.participants = participants;
+    }
+
+    /// Create a `NCRoom` from already translated data, without fetching anything.
+    /// Used by providers which map their rooms onto the NC Talk data model.
+    #[cfg(any(test, feature = "matrix"))]
+    pub fn from_data(room_data: NCReqDataRoom, store: MessageStore) -> NCRoom {
+        NCRoom {
+            messages: BTreeMap::new(),
+            store,
+            stored: HashMap::new(),
+            room_type: room_type(room_data.roomtype).unwrap_or_default(),
+            participants: vec![],
+            users: vec![],
+            participant_changes: vec![],
+            last_synced_at: 0,
+            session_joined: false,
+            room_data,
+        }
+    }
+
+    /// Take the room data returned when joining a session in this Room, ending the preview.
+    pub fn set_session_joined(&mut self, room_data: NCReqDataRoom) {
+        self.room_type = room_type(room_data.roomtype).unwrap_or_default();
+        self.room_data = room_data;
+        self.last_synced_at = Utc::now().timestamp();
+        self.session_joined = true;
+    }
+
+    pub fn is_session_joined(&self) -> bool {
+        self.session_joined
+    }
+
+    /// Forget the joined session once it is left, rooms only previewed are previewed again.
+    pub fn set_session_left(&mut self) {
+        self.session_joined = false;
+    }
+
+    /// Replace the raw room data, updating the room type with it.
+    #[cfg(feature = "matrix")]
+    pub fn set_data(&mut self, room_data: NCReqDataRoom) {
+        self.room_type = room_type(room_data.roomtype).unwrap_or_default();
+        self.room_data = room_data;
+        self.last_synced_at = Utc::now().timestamp();
+    }
+
+    #[cfg(feature = "matrix")]
+    pub fn insert_message(&mut self, message: NCReqDataMessage) {
+        self.messages.insert(message.id, message.into());
+    }
+
+    #[cfg(feature = "matrix")]
+    pub fn set_participants(&mut self, participants: Vec<NCReqDataParticipants>) {
+        self.replace_participants(participants);
     }
 }
 
-#[async_trait]
-impl NCRoomInterface for NCRoom {
+/// Hash of the message as it is stored, to notice changes.
+fn hash_message(message: &NCReqDataMessage) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(message)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+impl ChatRoom for NCRoom {
     fn get_last_room_level_message_id(&self) -> Option<i32> {
         // the room endpoint doesnt tell you about reactions...
         // The NCTalk level update does however include system messages...
@@ -240,7 +663,7 @@ impl NCRoomInterface for NCRoom {
             .filter(|&message| {
                 !message.is_reaction() && !message.is_edit_note() && !message.is_revoked()
             })
-            .collect::<Vec<&NCMessage>>()
+            .collect::<Vec<&ChatMessage>>()
             .last()
             .map(|message| message.get_id())
     }
@@ -251,26 +674,34 @@ impl NCRoomInterface for NCRoom {
 
     fn is_dm(&self) -> bool {
         match self.room_type {
-            NCRoomTypes::OneToOne | NCRoomTypes::NoteToSelf | NCRoomTypes::ChangeLog => true,
-            NCRoomTypes::Deprecated | NCRoomTypes::Group | NCRoomTypes::Public => false,
+            RoomType::OneToOne | RoomType::NoteToSelf | RoomType::ChangeLog => true,
+            RoomType::Deprecated | RoomType::Group | RoomType::Public => false,
         }
     }
 
     fn is_group(&self) -> bool {
         match self.room_type {
-            NCRoomTypes::Deprecated
-            | NCRoomTypes::OneToOne
-            | NCRoomTypes::NoteToSelf
-            | NCRoomTypes::ChangeLog => false,
-            NCRoomTypes::Group | NCRoomTypes::Public => true,
+            RoomType::Deprecated
+            | RoomType::OneToOne
+            | RoomType::NoteToSelf
+            | RoomType::ChangeLog => false,
+            RoomType::Group | RoomType::Public => true,
         }
     }
 
-    fn get_room_type(&self) -> &NCRoomTypes {
+    fn is_public(&self) -> bool {
+        self.room_type == RoomType::Public
+    }
+
+    fn has_password(&self) -> bool {
+        self.room_data.hasPassword
+    }
+
+    fn get_room_type(&self) -> &RoomType {
         &self.room_type
     }
 
-    fn get_messages(&self) -> &BTreeMap<i32, NCMessage> {
+    fn get_messages(&self) -> &BTreeMap<i32, ChatMessage> {
         &self.messages
     }
 
@@ -278,21 +709,118 @@ impl NCRoomInterface for NCRoom {
         self.room_data.unreadMessages.as_()
     }
 
+    fn has_unread_mention(&self) -> bool {
+        self.has_unread() && (self.room_data.unreadMention || self.room_data.unreadMentionDirect)
+    }
+
     fn is_favorite(&self) -> bool {
         self.room_data.isFavorite
     }
 
+    fn is_muted(&self) -> bool {
+        self.room_data.notificationLevel == NOTIFICATION_LEVEL_NEVER
+    }
+
+    fn is_archived(&self) -> bool {
+        self.room_data.isArchived
+    }
+
+    fn is_moderator(&self) -> bool {
+        role(self.room_data.participantType).is_moderator()
+    }
+
+    fn get_parent_room(&self) -> Option<Token> {
+        (self.room_data.objectType == OBJECT_TYPE_BREAKOUT_ROOM)
+            .then(|| self.room_data.objectId.clone())
+    }
+
+    fn has_call(&self) -> bool {
+        self.room_data.hasCall
+    }
+
+    fn get_users_in_call(&self) -> usize {
+        self.users
+            .iter()
+            .filter(|participant| participant.in_call)
+            .count()
+    }
+
+    fn is_lobby_closed(&self) -> bool {
+        self.room_data.lobbyState == LOBBY_NON_MODERATORS && !self.is_moderator()
+    }
+
+    fn get_lobby_timer(&self) -> i32 {
+        self.room_data.lobbyTimer
+    }
+
+    fn get_last_activity(&self) -> i32 {
+        self.room_data.lastActivity
+    }
+
+    fn get_added_by(&self, user_id: &str) -> Option<String> {
+        added_by(&self.messages, user_id)
+    }
+
+    fn is_mention_all_restricted(&self) -> bool {
+        self.room_data.mentionPermissions == MENTION_PERMISSIONS_MODERATORS
+    }
+
+    fn get_message_expiration(&self) -> i32 {
+        self.room_data.messageExpiration
+    }
+
+    fn is_previewing(&self) -> bool {
+        !self.session_joined
+            && (self.room_data.participantType == PARTICIPANT_TYPE_USER_SELF_JOINED
+                || self.room_data.objectType == OBJECT_TYPE_SAMPLE)
+    }
+
     fn get_display_name(&self) -> &str {
         &self.room_data.displayName
     }
 
+    fn get_name(&self) -> &str {
+        &self.room_data.name
+    }
+
+    fn get_description(&self) -> &str {
+        &self.room_data.description
+    }
+
     fn get_last_read(&self) -> i32 {
         self.room_data.lastReadMessage
     }
-    fn get_users(&self) -> &Vec<NCReqDataParticipants> {
-        &self.participants
+    fn get_last_common_read(&self) -> i32 {
+        self.room_data.lastCommonReadMessage
+    }
+    fn get_users(&self) -> &Vec<Participant> {
+        &self.users
+    }
+
+    fn get_participant_changes(&self) -> &Vec<ParticipantChange> {
+        &self.participant_changes
+    }
+
+    fn get_last_synced_at(&self) -> i64 {
+        self.last_synced_at
+    }
+
+    fn search_messages(&self, text: &str) -> Result<Vec<ChatMessage>, Box<dyn std::error::Error>> {
+        Ok(self
+            .store
+            .search(&self.room_data.token, text, SEARCH_RESULTS)?
+            .into_iter()
+            .map(ChatMessage::from)
+            .collect())
+    }
+
+    fn to_token(&self) -> Token {
+        self.room_data.token.clone()
     }
+}
 
+#[async_trait]
+impl NCRoomInterface for NCRoom {
     fn to_json(&self) -> String {
         serde_json::to_string(&self.room_data).unwrap()
     }
@@ -302,42 +830,31 @@ impl NCRoomInterface for NCRoom {
     }
 
     fn write_to_log(&mut self) -> Result<(), std::io::Error> {
-        use std::io::Write;
-
-        let data: Vec<_> = self.messages.values().map(NCMessage::data).collect();
-        let path = self.path_to_log.as_path();
-        // Open a file in write-only mode, returns `io::Result<File>`
-        let mut file = match std::fs::File::create(path) {
-            Err(why) => {
-                log::warn!(
-                    "Couldn't create log file {} for {}: {}",
-                    path.to_str().unwrap(),
-                    self.room_data.displayName,
-                    why
-                );
-                return Err(why);
-            }
-            Ok(file) => file,
-        };
+        self.store_changed().map_err(|why| {
+            log::warn!(
+                "Couldn't store the messages of {}: {}",
+                self.room_data.displayName,
+                why
+            );
+            std::io::Error::other(why.to_string())
+        })
+    }
 
-        match file.write_all(serde_json::to_string(&data).unwrap().as_bytes()) {
-            Err(why) => {
-                log::warn!(
-                    "couldn't write log file to {} for {}: {}",
-                    path.as_os_str()
-                        .to_str()
-                        .expect("Could not convert log path to string"),
-                    self.room_data.displayName,
-                    why
-                );
-                Err(why)
+    fn add_messages(&mut self, messages: Vec<NCReqDataMessage>) -> Option<(String, usize)> {
+        self.note_newer_messages(&messages);
+        let mut added = 0;
+        for message in messages {
+            if self.messages.insert(message.id, message.into()).is_none() {
+                added += 1;
             }
-            Ok(()) => Ok(()),
         }
-    }
-
-    fn to_token(&self) -> Token {
-        self.room_data.token.clone()
+        self.last_synced_at = Utc::now().timestamp();
+        if added > 0 {
+            log::info!("Pushed {added} new Messages to {self}");
+            self.store_or_warn();
+        }
+        (self.has_unread() && added > 0 && !self.is_archived())
+            .then(|| (self.room_data.displayName.clone(), added))
     }
 
     async fn send<Requester: NCRequestInterface + 'static + std::marker::Sync>(
@@ -351,12 +868,9 @@ impl NCRoomInterface for NCRoom {
                 .lock()
                 .await
                 .request_send_message(message, &self.room_data.token)
-                .await
-                .unwrap()
+                .await?
         };
-        let response = response_onceshot
-            .await
-            .expect("Failed for fetch chat participants");
+        let response = response_onceshot.await?;
         match response {
             Ok(v) => Ok(v.message),
             Err(why) => Err(why.into()),
@@ -372,31 +886,27 @@ impl NCRoomInterface for NCRoom {
         if let Some(data) = data_option {
             self.room_data = data.clone();
         }
+        if self.is_lobby_closed() {
+            log::debug!(
+                "Not fetching messages of {}, waiting in lobby.",
+                self.room_data.displayName
+            );
+            return Ok(None);
+        }
+        let last_id = self
+            .messages
+            .values()
+            .next_back()
+            .ok_or("No last message")?
+            .get_id();
         let response_onceshot = {
             requester
                 .lock()
                 .await
-                .request_chat_update(
-                    &self.room_data.token,
-                    200,
-                    self.messages
-                        .get(
-                            self.messages
-                                .keys()
-                                .sorted()
-                                .last()
-                                .expect("Failed to sort messages by its keys."),
-                        )
-                        .ok_or("No last message")?
-                        .get_id(),
-                )
-                .await
-                .unwrap()
+                .request_chat_update(&self.room_data.token, 200, last_id)
+                .await?
         };
-        let response = response_onceshot
-            .await
-            .expect("Failed for fetch chat update")
-            .expect("Failed request");
+        let response = response_onceshot.await??;
 
         let is_empty = response.is_empty();
         let update_info = Some((self.room_data.displayName.clone(), response.len()));
@@ -411,20 +921,10 @@ impl NCRoomInterface for NCRoom {
         for message in response {
             self.messages.insert(message.id, message.into());
         }
-        let response_onceshot = {
-            requester
-                .lock()
-                .await
-                .request_participants(&self.room_data.token)
-                .await
-                .unwrap()
-        };
-
-        self.participants = response_onceshot
-            .await
-            .expect("Failed for fetch chat participants")
-            .expect("Failed request");
-        if self.has_unread() && !is_empty {
+        self.store_or_warn();
+        self.last_synced_at = Utc::now().timestamp();
+        self.update_participants(requester).await?;
+        if self.has_unread() && !is_empty && !self.is_archived() {
             Ok(update_info)
         } else {
             Ok(None)
@@ -436,30 +936,20 @@ impl NCRoomInterface for NCRoom {
     ) -> Result<(), Box<dyn std::error::Error>> {
         if !self.messages.is_empty() {
             log::info!("Marking room {} as read", self.room_data.displayName);
+            let last_id = self
+                .messages
+                .values()
+                .next_back()
+                .ok_or("No last message")?
+                .get_id();
             let response_onceshot = {
                 requester
                     .lock()
                     .await
-                    .request_mark_chat_read(
-                        &self.room_data.token,
-                        self.messages
-                            .get(
-                                self.messages
-                                    .keys()
-                                    .sorted()
-                                    .last()
-                                    .expect("Failed to sort messages by its keys."),
-                            )
-                            .ok_or("No last message")?
-                            .get_id(),
-                    )
-                    .await
-                    .unwrap()
+                    .request_mark_chat_read(&self.room_data.token, last_id)
+                    .await?
             };
-            response_onceshot
-                .await
-                .expect("Failed for fetch chat participants")
-                .expect("Failed request");
+            response_onceshot.await??;
         }
         Ok(())
     }
@@ -471,7 +961,10 @@ impl NCRoomInterface for NCRoom {
     ) -> Result<(), Box<dyn std::error::Error>> {
         use std::cmp::Ordering;
         if let Some(room) = data_option {
-            if room.unreadMessages != self.room_data.unreadMessages {
+            // Refresh once the lobby opens, as no messages were fetched while waiting in it.
+            if room.unreadMessages != self.room_data.unreadMessages
+                || room.lobbyState != self.room_data.lobbyState
+            {
                 self.update(Some(room), requester).await?;
             }
         } else if let Some(last_internal_id) = self.get_last_room_level_message_id() {
@@ -486,96 +979,479 @@ impl NCRoomInterface for NCRoom {
                     self.update(data_option, requester).await?;
                 }
                 Ordering::Less => {
-                    log::debug!(
-                        "Message Id was older than message stored '{}'! Stored {} {} {} Upstream {}",
-                        self.to_string(),
-                        last_internal_id,
-                        self.messages
-                            .get(&last_internal_id)
-                            .unwrap()
-                            .data()
-                            .messageType,
-                        self.messages
-                            .get(&last_internal_id)
-                            .unwrap()
-                            .data()
-                            .systemMessage,
-                        message_id
-                    );
+                    if let Some(stored) = self.messages.get(&last_internal_id) {
+                        log::debug!(
+                            "Message Id was older than message stored '{}'! Stored {} {} {} Upstream {}",
+                            self,
+                            last_internal_id,
+                            stored.data().messageType,
+                            stored.data().systemMessage,
+                            message_id
+                        );
+                    }
                 }
                 Ordering::Equal => (),
             }
         }
+        self.last_synced_at = Utc::now().timestamp();
 
         Ok(())
     }
 
-    async fn fill_history<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+    async fn fetch_older<Requester: NCRequestInterface + 'static + std::marker::Sync>(
         &mut self,
+        count: i32,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let Some(first_id) = self.messages.keys().next().copied() else {
+            return Ok(0);
+        };
+        match self
+            .store
+            .load_before(&self.room_data.token, first_id, count.as_())
+        {
+            Ok(older) if !older.is_empty() => {
+                log::debug!(
+                    "Read {} older messages for {} from the store",
+                    older.len(),
+                    self.room_data.displayName
+                );
+                let before = self.messages.len();
+                for message in older {
+                    self.stored.insert(message.id, hash_message(&message));
+                    self.messages.insert(message.id, message.into());
+                }
+                return Ok(self.messages.len() - before);
+            }
+            Ok(_) => (),
+            Err(why) => log::warn!(
+                "Could not read older messages of {} from the store: {why}",
+                self.room_data.displayName
+            ),
+        }
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_chat_history(&self.room_data.token, count, first_id)
+                .await?
+        };
+        let response = response_onceshot.await??;
+        log::debug!(
+            "Fetched {} older messages for {}",
+            response.len(),
+            self.room_data.displayName
+        );
+        let before = self.messages.len();
+        for message in response {
+            self.messages.insert(message.id, message.into());
+        }
+        self.store_or_warn();
+        Ok(self.messages.len() - before)
+    }
+
+    async fn set_favorite<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &self,
+        favorite: bool,
         requester: Arc<tokio::sync::Mutex<Requester>>,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!("Setting favorite of {self} to {favorite}");
         let response_onceshot = {
             requester
                 .lock()
                 .await
-                .request_chat_update(&self.room_data.token, 200, 1)
+                .request_set_favorite(&self.room_data.token, favorite)
+                .await?
+        };
+        response_onceshot.await?.map_err(Into::into)
+    }
+
+    async fn set_muted<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &self,
+        muted: bool,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!("Setting muted of {self} to {muted}");
+        let level = if muted {
+            NOTIFICATION_LEVEL_NEVER
+        } else {
+            NOTIFICATION_LEVEL_DEFAULT
+        };
+        let response_onceshot = {
+            requester
+                .lock()
                 .await
-                .unwrap()
+                .request_set_notification_level(&self.room_data.token, level)
+                .await?
         };
-        let response = response_onceshot
-            .await
-            .expect("Failed for fetch chat update")
-            .expect("Failed request");
+        response_onceshot.await?.map_err(Into::into)
+    }
 
-        // Room is empty.
-        if response.is_empty() {
-            return Ok(());
-        }
+    async fn set_archived<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &self,
+        archived: bool,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!("Setting archived of {self} to {archived}");
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_set_archived(&self.room_data.token, archived)
+                .await?
+        };
+        response_onceshot.await?.map_err(Into::into)
+    }
 
-        let fetch_key = response
-            .first()
-            .expect("Failed to get last message of initial fetch")
-            .id;
+    async fn add_participant<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        user_id: &str,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!("Adding {user_id} to {}", self.room_data.displayName);
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_add_participant(&self.room_data.token, user_id)
+                .await?
+        };
+        response_onceshot.await??;
+        self.update_participants(requester).await?;
+        Ok(())
+    }
 
-        let last_entry = *(self
-            .messages
-            .last_key_value()
-            .expect("Failed to sort messages by its keys.")
-            .0);
+    async fn refresh_participants<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.update_participants(requester).await
+    }
 
-        log::debug!(
-            "Fetching Full history. Size stored {}, size to check {}, last {}, first {}",
-            self.messages.len(),
-            last_entry - fetch_key,
-            last_entry,
-            fetch_key
+    async fn remove_participant<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        attendee_id: i32,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!(
+            "Removing attendee {attendee_id} from {}",
+            self.room_data.displayName
         );
-        let mut running_key = fetch_key + 10_000;
-        let mut thread_handles = vec![];
-        for key in (fetch_key..=last_entry).step_by(10_000) {
-            log::debug!("Fetching thread {} to {} ", key, running_key);
-            let token = self.room_data.token.clone();
-            let cloned_requester = requester.clone();
-            thread_handles.push(tokio::spawn(async move {
-                NCRoom::fetch_message_subset(key, running_key, cloned_requester, &token).await
-            }));
-            running_key += 10_000;
-        }
-        log::debug!("Spawned all reads for fetching");
-        for handle in thread_handles {
-            let mut results = handle.await.expect("No Messages could be fetched.");
-            self.messages.append(&mut results);
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_remove_attendee(&self.room_data.token, attendee_id)
+                .await?
+        };
+        response_onceshot.await??;
+        self.update_participants(requester).await?;
+        Ok(())
+    }
+
+    async fn set_moderator<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        attendee_id: i32,
+        moderator: bool,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!(
+            "Setting moderator of attendee {attendee_id} in {} to {moderator}",
+            self.room_data.displayName
+        );
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_set_moderator(&self.room_data.token, attendee_id, moderator)
+                .await?
+        };
+        response_onceshot.await??;
+        self.update_participants(requester).await?;
+        Ok(())
+    }
+
+    async fn set_name<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        name: &str,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!("Renaming {} to {name}", self.room_data.displayName);
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_set_room_name(&self.room_data.token, name)
+                .await?
+        };
+        response_onceshot.await??;
+        self.room_data.name = name.to_string();
+        self.room_data.displayName = name.to_string();
+        Ok(())
+    }
+
+    async fn set_description<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        description: &str,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!("Setting description of {}", self.room_data.displayName);
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_set_room_description(&self.room_data.token, description)
+                .await?
+        };
+        response_onceshot.await??;
+        self.room_data.description = description.to_string();
+        Ok(())
+    }
+
+    async fn set_password<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        password: &str,
+        requester: Arc<Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!("Setting password of {}", self.room_data.displayName);
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_set_room_password(&self.room_data.token, password)
+                .await?
+        };
+        response_onceshot.await??;
+        self.room_data.hasPassword = !password.is_empty();
+        Ok(())
+    }
+
+    async fn set_mention_all_restricted<
+        Requester: NCRequestInterface + 'static + std::marker::Sync,
+    >(
+        &mut self,
+        restricted: bool,
+        requester: Arc<Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!(
+            "Setting @all mentions of {} to {}",
+            self.room_data.displayName,
+            if restricted { "moderators" } else { "everyone" }
+        );
+        let permissions = i32::from(restricted) * MENTION_PERMISSIONS_MODERATORS;
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_set_mention_permissions(&self.room_data.token, permissions)
+                .await?
+        };
+        response_onceshot.await??;
+        self.room_data.mentionPermissions = permissions;
+        Ok(())
+    }
+
+    async fn set_message_expiration<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        seconds: i32,
+        requester: Arc<Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!(
+            "Setting message expiration of {} to {seconds}s",
+            self.room_data.displayName
+        );
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_set_message_expiration(&self.room_data.token, seconds)
+                .await?
+        };
+        response_onceshot.await??;
+        self.room_data.messageExpiration = seconds;
+        Ok(())
+    }
+
+    async fn ban_participant<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        attendee_id: i32,
+        requester: Arc<tokio::sync::Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let participant = self
+            .participants
+            .iter()
+            .find(|participant| participant.attendeeId == attendee_id)
+            .ok_or("Participant not found in room")?;
+        log::info!(
+            "Banning {} from {}",
+            participant.actorId,
+            self.room_data.displayName
+        );
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_ban_attendee(
+                    &self.room_data.token,
+                    &participant.actorType,
+                    &participant.actorId,
+                )
+                .await?
+        };
+        response_onceshot.await??;
+        self.update_participants(requester).await?;
+        Ok(())
+    }
+
+    async fn send_reply<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &self,
+        message: String,
+        reply_to: i32,
+        requester: Arc<Mutex<Requester>>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        log::info!("Send Reply to {reply_to} {}", &message);
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_send_reply(message, &self.room_data.token, reply_to)
+                .await?
+        };
+        let response = response_onceshot.await?;
+        match response {
+            Ok(v) => Ok(v.message),
+            Err(why) => Err(why.into()),
         }
+    }
 
-        log::debug!(
-            "Updated Full history. Size {}, last {}, first {}",
-            self.messages.len(),
-            last_entry,
-            fetch_key
+    async fn edit_message<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        message_id: i32,
+        message: &str,
+        requester: Arc<Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!(
+            "Editing message {message_id} in {}",
+            self.room_data.displayName
         );
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_edit_message(&self.room_data.token, message_id, message)
+                .await?
+        };
+        response_onceshot.await??;
+        self.messages
+            .get_mut(&message_id)
+            .ok_or("Edited message not found in room")?
+            .set_message(message);
+        Ok(())
+    }
+
+    async fn delete_message<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        message_id: i32,
+        requester: Arc<Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!(
+            "Deleting message {message_id} in {}",
+            self.room_data.displayName
+        );
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_delete_message(&self.room_data.token, message_id)
+                .await?
+        };
+        response_onceshot.await??;
+        self.messages
+            .get_mut(&message_id)
+            .ok_or("Deleted message not found in room")?
+            .mark_deleted();
+        Ok(())
+    }
+
+    async fn react_to_message<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        message_id: i32,
+        reaction: &str,
+        requester: Arc<Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!("Reacting with {reaction} to message {message_id}");
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_react_to_message(&self.room_data.token, message_id, reaction)
+                .await?
+        };
+        response_onceshot.await??;
+        self.messages
+            .get_mut(&message_id)
+            .ok_or("Reacted message not found in room")?
+            .add_own_reaction(reaction);
+        Ok(())
+    }
 
+    async fn remove_reaction<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &mut self,
+        message_id: i32,
+        reaction: &str,
+        requester: Arc<Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!("Removing reaction {reaction} from message {message_id}");
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_delete_reaction(&self.room_data.token, message_id, reaction)
+                .await?
+        };
+        response_onceshot.await??;
+        self.messages
+            .get_mut(&message_id)
+            .ok_or("Reacted message not found in room")?
+            .remove_own_reaction(reaction);
         Ok(())
     }
+
+    async fn set_reminder<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &self,
+        message_id: i32,
+        timestamp: i64,
+        requester: Arc<Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!("Setting reminder for message {message_id} at {timestamp}");
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_set_reminder(&self.room_data.token, message_id, timestamp)
+                .await?
+        };
+        response_onceshot.await?.map_err(Into::into)
+    }
+
+    async fn pin_message<Requester: NCRequestInterface + 'static + std::marker::Sync>(
+        &self,
+        message_id: i32,
+        requester: Arc<Mutex<Requester>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::info!(
+            "Pinning message {message_id} in {}",
+            self.room_data.displayName
+        );
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_pin_message(&self.room_data.token, message_id)
+                .await?
+        };
+        response_onceshot.await?.map_err(Into::into)
+    }
 }
 
 impl Ord for NCRoom {
@@ -615,36 +1491,132 @@ impl std::ops::Deref for NCRoom {
 #[cfg(test)]
 mod tests {
     use super::*;
-    static BUTZ: &str = "Butz";
-    impl std::ops::Deref for MockNCRoomInterface {
-        type Target = str;
-        fn deref(&self) -> &Self::Target {
-            BUTZ
-        }
+    #[test]
+    fn participant_from_data() {
+        let mut data = NCReqDataParticipants::default();
+        data.actorType = "guests".to_string();
+        data.participantType = PARTICIPANT_TYPE_GUEST_MODERATOR;
+        data.inCall = 1;
+        let participant = Participant::from(&data);
+        assert!(!participant.is_user);
+        assert!(participant.in_call);
+        assert_eq!(participant.role, ParticipantRole::GuestModerator);
+        assert!(participant.role.is_moderator());
+        assert!(!participant.role.is_owner());
+
+        data.actorType = ACTOR_TYPE_USERS.to_string();
+        data.participantType = 42;
+        data.inCall = IN_CALL_DISCONNECTED;
+        let participant = Participant::from(&data);
+        assert!(participant.is_user);
+        assert!(!participant.in_call);
+        assert_eq!(participant.role, ParticipantRole::Unknown);
     }
-    impl Ord for MockNCRoomInterface {
-        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-            self.to_string().cmp(&other.to_string())
-        }
+
+    #[test]
+    fn participant_diff() {
+        let participant = |attendee_id: i32, name: &str| {
+            let mut participant = NCReqDataParticipants::default();
+            participant.attendeeId = attendee_id;
+            participant.displayName = name.to_string();
+            participant
+        };
+        let old = vec![participant(1, "Butz"), participant(2, "Bert")];
+        let new = vec![participant(2, "Bert"), participant(3, "Hundi")];
+
+        assert!(diff_participants(&old, &old, 100).is_empty());
+        assert_eq!(
+            diff_participants(&old, &new, 100),
+            vec![
+                ParticipantChange {
+                    timestamp: 100,
+                    display_name: "Hundi".to_string(),
+                    joined: true,
+                },
+                ParticipantChange {
+                    timestamp: 100,
+                    display_name: "Butz".to_string(),
+                    joined: false,
+                },
+            ]
+        );
     }
 
-    impl PartialOrd for MockNCRoomInterface {
-        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-            Some(self.to_string().cmp(&other.to_string()))
-        }
+    #[test]
+    fn added_by_system_messages() {
+        let message = |id: i32, actor: &str, system: &str, user: &str| {
+            let data: NCReqDataMessage = serde_json::from_value(serde_json::json!({
+                "id": id,
+                "token": "123",
+                "actorType": "users",
+                "actorId": actor,
+                "actorDisplayName": actor,
+                "timestamp": 0,
+                "systemMessage": system,
+                "messageType": "system",
+                "isReplyable": false,
+                "referenceId": "",
+                "message": "",
+                "messageParameters": {"user": {"type": "user", "id": user, "name": user}},
+                "expirationTimestamp": 0,
+                "reactions": {},
+                "markdown": false
+            }))
+            .unwrap();
+            (id, ChatMessage::from(data))
+        };
+        let mut messages = BTreeMap::from([message(1, "Bert", "conversation_created", "")]);
+        assert_eq!(added_by(&messages, "butz"), Some("Bert".to_string()));
+        assert_eq!(added_by(&messages, "Bert"), None);
+
+        messages.extend([
+            message(2, "Bert", "user_added", "other"),
+            message(3, "Ernie", "user_added", "butz"),
+        ]);
+        assert_eq!(added_by(&messages, "butz"), Some("Ernie".to_string()));
+        assert_eq!(added_by(&BTreeMap::new(), "butz"), None);
     }
 
-    impl PartialEq for MockNCRoomInterface {
-        fn eq(&self, other: &Self) -> bool {
-            self.to_string() == other.to_string()
-        }
+    #[test]
+    fn pushed_messages_update_room_data() {
+        let message = |id: i32, message_type: &str, timestamp: i64| NCReqDataMessage {
+            id,
+            messageType: message_type.to_string(),
+            timestamp,
+            ..Default::default()
+        };
+        let room_data = NCReqDataRoom {
+            displayName: "General".to_string(),
+            lastMessage: message(2, "comment", 100),
+            lastReadMessage: 2,
+            lastActivity: 100,
+            ..Default::default()
+        };
+        let mut room = NCRoom::from_data(room_data, MessageStore::in_memory().unwrap());
+
+        let pushed = vec![message(3, "comment", 200), message(4, "system", 300)];
+        assert_eq!(room.add_messages(pushed), Some(("General".to_string(), 2)));
+        assert_eq!(room.get_unread(), 1);
+        assert_eq!(room.get_last_activity(), 300);
+        assert_eq!(room.to_data().lastMessage.id, 4);
+
+        // Older messages, like chunks of the history, leave the room data alone.
+        assert_eq!(
+            room.add_messages(vec![message(1, "comment", 50)]),
+            Some(("General".to_string(), 1))
+        );
+        assert_eq!(room.get_unread(), 1);
+        assert_eq!(room.to_data().lastMessage.id, 4);
     }
 
-    impl Eq for MockNCRoomInterface {}
-    impl std::fmt::Display for MockNCRoomInterface {
-        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            let self_name = BUTZ.to_string();
-            write!(f, "{self_name}")
-        }
+    #[test]
+    fn initial_fetch_size() {
+        let sizes = FetchSizes::default();
+        let mut room_data = NCReqDataRoom::default();
+        assert_eq!(sizes.initial_for(&room_data), sizes.unopened);
+        room_data.lastReadMessage = 42;
+        assert_eq!(sizes.initial_for(&room_data), sizes.default);
+        room_data.isFavorite = true;
+        assert_eq!(sizes.initial_for(&room_data), sizes.favorite);
     }
 }
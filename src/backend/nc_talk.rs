@@ -1,15 +1,26 @@
 //! NC Talk Message Object
 //!
-//! [`NCTalk`] for the main implementation.
-//!
-//! [`NCBackend`] as the trait to enable testing.
+//! [`NCTalk`] for the main implementation of the [`ChatProvider`] trait.
 
 use crate::{
     backend::{
-        nc_request::{nc_requester::NCRequestInterface, NCReqDataRoom},
-        nc_room::NCRoomInterface,
+        capabilities::Capabilities,
+        chat_message::ChatMessage,
+        chat_provider::{BackendUpdate, ChatProvider, RoomUpdateError},
+        chat_room::ChatRoom,
+        chat_types::{ChatFeature, FoundUser, ListedRoom, OutOfOffice, UserStatus},
+        history_fetch::{HistoryFetch, HistoryProgress},
+        local_mutes::LocalMutes,
+        nc_request::{
+            nc_long_poll::{LongPoll, PushChannel, PushedMessages},
+            nc_requester::NCRequestInterface,
+            NCApiError, NCReqDataMessage, NCReqDataRoom,
+        },
+        nc_room::{FetchSizes, NCRoomInterface},
+        poll_schedule::{PollSchedule, PollTier},
+        request_metrics::MetricsSnapshot,
     },
-    config::Config,
+    config::{Config, Guest},
 };
 use async_trait::async_trait;
 use itertools::Itertools;
@@ -17,80 +28,42 @@ use std::{
     collections::HashMap,
     error::Error,
     fmt::Debug,
-    path::{Path, PathBuf},
+    path::PathBuf,
     sync::Arc,
+    time::{Duration, Instant},
 };
 use tokio::{sync::Mutex, task::JoinHandle};
 
 use super::{
-    nc_request::Token,
-    nc_room::{NCRoom, NCRoomTypes},
+    chat_types::RoomType, message_store::MessageStore, nc_request::Token, nc_room::NCRoom,
 };
 
-/// Public Trait for NC Talk Instance Object used for all interaction with the server.
-///
-/// This trait is needed due to the use of the [mockall](https://crates.io/crates/mockall) crate in testing.
-/// See [`NCTalk`] for more details on the functionality.
-#[async_trait]
-pub trait NCBackend: Debug + Send {
-    /// Type for Rooms used in Backend.
-    /// Since this ether can be a mock or a real room, it need to be named here.
-    type Room: NCRoomInterface;
-    /// Write all log files for this NC Instance to disk.
-    /// # Errors
-    /// Folder might not exists/be writable or not have space.
-    fn write_to_log(&mut self) -> Result<(), std::io::Error>;
-    /// Get a Room ref for a given Token.
-    fn get_room(&self, token: &Token) -> &Self::Room;
-    /// Get a list of tokens of rooms with unread messages.
-    fn get_unread_rooms(&self) -> Vec<Token>;
-    /// Get a list of tokens of favorite rooms.
-    fn get_favorite_rooms(&self) -> Vec<Token>;
-    /// Get a room token by its Displayname.
-    fn get_room_by_displayname(&self, name: &str) -> Token;
-    /// Get a list of direct messages rooms as token, displayname pairs.
-    fn get_dm_keys_display_name_mapping(&self) -> Vec<(Token, String)>;
-    /// Get a list of group messages rooms as token, displayname pairs.
-    fn get_group_keys_display_name_mapping(&self) -> Vec<(Token, String)>;
-    /// Get a list of all Room Token.
-    fn get_room_keys(&self) -> Vec<&'_ Token>;
-    /// Send a Message to the current selected room.
-    async fn send_message(
-        &mut self,
-        message: String,
-        token: &Token,
-    ) -> Result<Option<(String, usize)>, Box<dyn Error>>;
-    /// Select a Room by a given Token as the current Room.
-    async fn select_room(
-        &mut self,
-        token: &Token,
-    ) -> Result<Option<(String, usize)>, Box<dyn Error>>;
-    /// Check with the Server for all Rooms if updates happened.
-    /// ```force_update``` will force the currently stored Room data to be overwritten.
-    async fn update_rooms(&mut self, force_update: bool) -> Result<Vec<String>, Box<dyn Error>>;
-    /// Mark the room identified by the Token as read.
-    /// Does not need to be the current Room, but usually is.
-    async fn mark_current_room_as_read(
-        &self,
-        token: &Token,
-    ) -> Result<(), Box<dyn std::error::Error>>;
-    /// Mark all rooms as read, goes over list of unread rooms.
-    async fn mark_all_rooms_as_read(&self) -> Result<(), Box<dyn std::error::Error>>;
-    /// Fetch a rooms full history.
-    async fn fetch_room_history(&mut self, token: &Token) -> Result<(), Box<dyn Error>>;
-    /// trigger for all threads to be killed.
-    async fn shutdown(&self) -> Result<(), Box<dyn std::error::Error>>;
-}
-
 /// NC Talk instance reprensation for all interactions with Server.
 ///
 /// This struct stores all Rooms in a Hashmap and the API Wrapper.
 #[derive(Debug, Default)]
 pub struct NCTalk<Requester: NCRequestInterface + 'static + std::marker::Sync> {
     rooms: HashMap<Token, NCRoom>,
-    chat_data_path: PathBuf,
+    store: MessageStore,
     last_requested: i64,
     requester: Arc<Mutex<Requester>>,
+    /// Base url of the server, used to build links to rooms.
+    base_url: String,
+    /// Room shown in the UI, which is polled on every update.
+    active_room: Token,
+    poll_schedule: PollSchedule,
+    fetch_sizes: FetchSizes,
+    local_mutes: LocalMutes,
+    /// Long poll of the watched room, pushing its new messages as they are sent.
+    long_poll: Option<LongPoll>,
+    pushed_messages: PushChannel,
+    /// Full history of a room, fetched in the background.
+    history: Option<HistoryFetch>,
+    capabilities: Capabilities,
+    /// The only room synced when joined as a guest, rooms are not listed then.
+    guest_room: Option<Token>,
+    /// Set while the server cannot be reached, only the cached rooms are shown then.
+    offline: bool,
 }
 
 impl<Requester: NCRequestInterface + 'static + std::marker::Send> NCTalk<Requester> {
@@ -98,7 +71,8 @@ impl<Requester: NCRequestInterface + 'static + std::marker::Send> NCTalk<Request
         response: Vec<NCReqDataRoom>,
         raw_requester: Arc<Mutex<Requester>>,
         rooms: &mut HashMap<Token, NCRoom>,
-        chat_log_path: PathBuf,
+        store: &MessageStore,
+        fetch_sizes: FetchSizes,
     ) {
         let v: Vec<JoinHandle<(String, Option<NCRoom>)>> = response
             .into_iter()
@@ -106,13 +80,20 @@ impl<Requester: NCRequestInterface + 'static + std::marker::Send> NCTalk<Request
                 tokio::spawn(NCTalk::<Requester>::new_room(
                     child,
                     Arc::clone(&raw_requester),
-                    chat_log_path.clone(),
+                    store.clone(),
+                    fetch_sizes,
                 ))
             })
             .collect();
         log::debug!("Got {} initial threads", v.len());
         for jh in v {
-            let (name, room_option) = jh.await.unwrap();
+            let (name, room_option) = match jh.await {
+                Ok(result) => result,
+                Err(why) => {
+                    log::warn!("Adding a room failed: {why}");
+                    continue;
+                }
+            };
             if let Some(room) = room_option {
                 rooms.insert(name, room);
             } else {
@@ -123,9 +104,10 @@ impl<Requester: NCRequestInterface + 'static + std::marker::Send> NCTalk<Request
     async fn parse_files(
         mut data: HashMap<Token, NCReqDataRoom>,
         requester: Arc<Mutex<Requester>>,
-        chat_log_path: &Path,
+        store: &MessageStore,
         initial_message_ids: &mut HashMap<Token, &NCReqDataRoom>,
         rooms: &mut HashMap<Token, NCRoom>,
+        fetch_sizes: FetchSizes,
     ) -> Result<(), Box<dyn Error>> {
         let mut handles = HashMap::new();
         for (token, room) in &mut data {
@@ -134,28 +116,34 @@ impl<Requester: NCRequestInterface + 'static + std::marker::Send> NCTalk<Request
                 tokio::spawn(NCRoom::new::<Requester>(
                     room.clone(),
                     Arc::clone(&requester),
-                    chat_log_path.to_path_buf(),
+                    store.clone(),
+                    fetch_sizes.initial_for(room),
                 )),
             );
         }
         log::debug!("Got {} initial threads", handles.capacity());
         for (token, room_future) in &mut handles {
-            //we can safely unwrap here bc the json file on disk shall never be this broken.
-            let mut json_room = room_future.await?.unwrap();
-            if initial_message_ids.contains_key::<Token>(token) {
-                let message_id = initial_message_ids.get(token).unwrap().lastMessage.id;
-                json_room
+            let Some(mut json_room) = room_future.await? else {
+                log::warn!("Could not read the room {token} from the store.");
+                continue;
+            };
+            if let Some(room_data) = initial_message_ids.get(token) {
+                // A room failing to update is shown with the messages on disk, instead of failing all rooms.
+                if let Err(why) = json_room
                     .update_if_id_is_newer::<Requester>(
-                        message_id,
-                        Some((*initial_message_ids.get(token).unwrap()).clone()),
+                        room_data.lastMessage.id,
+                        Some((*room_data).clone()),
                         Arc::clone(&requester),
                     )
-                    .await?;
+                    .await
+                {
+                    log::warn!("Could not update {json_room}: {why}");
+                }
                 rooms.insert(token.clone(), json_room);
                 initial_message_ids.remove(token);
             } else {
                 log::warn!("Room was deleted upstream, failed to locate!");
-                //TODO: remove old chat log!!
+                //TODO: remove stored messages!!
             }
         }
         Ok(())
@@ -164,47 +152,57 @@ impl<Requester: NCRequestInterface + 'static + std::marker::Send> NCTalk<Request
     async fn new_room(
         packaged_child: NCReqDataRoom,
         requester_box: Arc<Mutex<Requester>>,
-        chat_log_path: PathBuf,
+        store: MessageStore,
+        fetch_sizes: FetchSizes,
     ) -> (Token, Option<NCRoom>) {
+        let fetch_size = fetch_sizes.initial_for(&packaged_child);
         (
             packaged_child.token.clone(),
-            NCRoom::new::<Requester>(packaged_child, requester_box, chat_log_path).await,
+            NCRoom::new::<Requester>(packaged_child, requester_box, store, fetch_size).await,
         )
     }
+    /// Tier of the room for the [`PollSchedule`], by how likely it sees activity.
+    fn poll_tier(&self, token: &Token) -> PollTier {
+        if *token == self.active_room {
+            PollTier::Active
+        } else if self.rooms[token].is_favorite() {
+            PollTier::Favorite
+        } else {
+            PollTier::Idle
+        }
+    }
+
     /// Create a new NC Talk Backend instance.
     ///
-    /// This will first try to read the chat history from the file system.
+    /// This will first try to read the chat history from the [`MessageStore`].
     /// Should this fail it will use the Requester to fetch data from Server.
-    /// # Panics
     ///
     /// # Errors
     /// Initial fetching of the Rooms from the backend may fail.
     /// Selecting a current Room might fail.
-    /// Reading data from a file might fail.
+    /// Opening the message store might fail.
+    #[allow(clippy::too_many_lines)]
     pub async fn new(
         raw_requester: Requester,
         config: &Config,
     ) -> Result<NCTalk<Requester>, Box<dyn Error>> {
-        let chat_log_path = config.get_server_data_dir();
-        let mut tmp_path_buf = chat_log_path.clone();
-        tmp_path_buf.push("Talk.json");
-        let path = tmp_path_buf.as_path();
+        let store = MessageStore::open(&config.get_server_data_dir())?;
         log::debug!("Fetching initial Rooms List");
 
         let requester = Arc::new(Mutex::new(raw_requester));
+        let fetch_sizes = FetchSizes::new(config);
+        let capabilities = NCTalk::fetch_capabilities(&requester).await;
+        if let Some(guest) = &config.guest {
+            return NCTalk::new_guest(requester, config, guest, capabilities, store).await;
+        }
 
-        let resp = {
-            requester
-                .lock()
-                .await
-                .request_rooms_initial()
-                .await
-                .expect("Initial fetching of rooms on startup failed.")
+        let (response, last_requested) = match NCTalk::fetch_rooms_initial(&requester).await {
+            Ok(rooms) => rooms,
+            Err(why) => {
+                log::warn!("Could not fetch the rooms, showing the cached ones offline: {why}");
+                return NCTalk::new_offline(requester, config, capabilities, store).await;
+            }
         };
-        let (response, last_requested) = resp
-            .await
-            .expect("Initial fetching of rooms failed.")
-            .expect("No rooms found");
         log::debug!("Parsing initial Rooms List");
 
         let mut initial_message_ids: HashMap<Token, &NCReqDataRoom> = response
@@ -214,22 +212,17 @@ impl<Requester: NCRequestInterface + 'static + std::marker::Send> NCTalk<Request
 
         let mut rooms = HashMap::<Token, NCRoom>::new();
 
-        log::debug!("Trying to read from disk.");
+        log::debug!("Trying to read from the store.");
 
-        if path.exists() {
-            if let Ok(data) = serde_json::from_str::<HashMap<String, NCReqDataRoom>>(
-                std::fs::read_to_string(path)?.as_str(),
-            ) {
-                let token_data = data
-                    .iter()
-                    .map(|(key, room_data)| (Token::from(key), room_data.clone()))
-                    .collect();
+        match store.load_rooms() {
+            Ok(data) if !data.is_empty() => {
                 NCTalk::parse_files(
-                    token_data,
+                    data,
                     Arc::clone(&requester),
-                    chat_log_path.as_path(),
+                    &store,
                     &mut initial_message_ids,
                     &mut rooms,
+                    fetch_sizes,
                 )
                 .await?;
                 if !initial_message_ids.is_empty() {
@@ -242,42 +235,45 @@ impl<Requester: NCRequestInterface + 'static + std::marker::Send> NCTalk<Request
                         remaining_room_data,
                         Arc::clone(&requester),
                         &mut rooms,
-                        chat_log_path.clone(),
+                        &store,
+                        fetch_sizes,
                     )
                     .await;
                     log::debug!(
-                        "New Room adds, missing in logs {}",
+                        "New Room adds, missing in the store {}",
                         initial_message_ids.len()
                     );
                 }
-                log::info!("Loaded Rooms from log files");
-            } else {
-                log::debug!("Failed to parse top level json, falling back to fetching");
+                log::info!("Loaded Rooms from the store");
+            }
+            result => {
+                if let Err(why) = result {
+                    log::warn!("Failed to read the stored rooms, falling back to fetching: {why}");
+                } else {
+                    log::debug!("No rooms stored, fetching logs from server.");
+                }
                 NCTalk::<Requester>::parse_response(
                     response,
                     requester.clone(),
                     &mut rooms,
-                    chat_log_path.clone(),
+                    &store,
+                    fetch_sizes,
                 )
                 .await;
             }
-        } else {
-            log::debug!("No Log files found in Path, fetching logs from server.");
-            NCTalk::<Requester>::parse_response(
-                response,
-                requester.clone(),
-                &mut rooms,
-                chat_log_path.clone(),
-            )
-            .await;
         }
 
-        let mut talk = NCTalk {
+        if rooms.is_empty() {
+            return Err("No rooms found.".into());
+        }
+        let mut talk = NCTalk::with_rooms(
             rooms,
-            chat_data_path: chat_log_path.clone(),
             last_requested,
             requester,
-        };
+            config,
+            capabilities,
+            store,
+        );
         log::info!("Entering default room {}", config.data.ui.default_room);
         talk.select_room(&talk.get_room_by_displayname(&Token::from(&config.data.ui.default_room)))
             .await?;
@@ -286,91 +282,328 @@ impl<Requester: NCRequestInterface + 'static + std::marker::Send> NCTalk<Request
 
         Ok(talk)
     }
-}
 
-#[async_trait]
-impl<Requester: NCRequestInterface + 'static + std::marker::Sync> NCBackend for NCTalk<Requester> {
-    type Room = NCRoom;
-    fn write_to_log(&mut self) -> Result<(), std::io::Error> {
-        use std::io::Write;
-
-        let mut data = HashMap::<Token, NCReqDataRoom>::new();
-        let mut tmp_path_buf = self.chat_data_path.clone();
-        tmp_path_buf.push("Talk.json");
-        let path = tmp_path_buf.as_path();
-        for (key, room) in &mut self.rooms {
-            data.insert(key.clone(), room.to_data());
-            room.write_to_log()?;
+    fn with_rooms(
+        rooms: HashMap<Token, NCRoom>,
+        last_requested: i64,
+        requester: Arc<Mutex<Requester>>,
+        config: &Config,
+        capabilities: Capabilities,
+        store: MessageStore,
+    ) -> NCTalk<Requester> {
+        NCTalk {
+            rooms,
+            local_mutes: LocalMutes::load(&config.get_server_data_dir()),
+            store,
+            last_requested,
+            requester,
+            base_url: config.data.general.url.trim_end_matches('/').to_string(),
+            active_room: Token::default(),
+            poll_schedule: PollSchedule::new(config),
+            fetch_sizes: FetchSizes::new(config),
+            long_poll: None,
+            pushed_messages: PushChannel::default(),
+            history: None,
+            capabilities,
+            guest_room: None,
+            offline: false,
+        }
+    }
+
+    /// Fetch all rooms of the user and the time they were fetched at.
+    async fn fetch_rooms_initial(
+        requester: &Arc<Mutex<Requester>>,
+    ) -> Result<(Vec<NCReqDataRoom>, i64), Box<dyn Error>> {
+        let response_onceshot = { requester.lock().await.request_rooms_initial().await? };
+        response_onceshot.await?.map_err(Into::into)
+    }
+
+    /// Show the rooms cached on disk without a connection to the server.
+    /// Only rooms with stored messages are shown, as the messages of others cannot be fetched.
+    async fn new_offline(
+        requester: Arc<Mutex<Requester>>,
+        config: &Config,
+        capabilities: Capabilities,
+        store: MessageStore,
+    ) -> Result<NCTalk<Requester>, Box<dyn Error>> {
+        let data = store.load_rooms()?;
+        if data.is_empty() {
+            return Err(format!(
+                "The server cannot be reached and there are no cached rooms in {}.",
+                config.get_server_data_dir().display()
+            )
+            .into());
+        }
+        let fetch_sizes = FetchSizes::new(config);
+        let mut rooms = HashMap::new();
+        for (token, room_data) in data {
+            if !store.has_messages(&token)? {
+                continue;
+            }
+            let fetch_size = fetch_sizes.initial_for(&room_data);
+            if let Some(room) =
+                NCRoom::new(room_data, Arc::clone(&requester), store.clone(), fetch_size).await
+            {
+                rooms.insert(token, room);
+            }
+        }
+        if rooms.is_empty() {
+            return Err("The server cannot be reached and no room has stored messages.".into());
+        }
+        let mut talk = NCTalk::with_rooms(rooms, 0, requester, config, capabilities, store);
+        talk.offline = true;
+        talk.active_room = talk.get_room_by_displayname(&config.data.ui.default_room);
+        log::info!("Offline with {} cached rooms", talk.rooms.len());
+        Ok(talk)
+    }
+
+    /// Try to reach the server again, updating the cached rooms if it answers.
+    /// Rooms failing to update are collected into a [`RoomUpdateError`], the others are updated anyway.
+    async fn reconnect(&mut self) -> Result<Vec<String>, Box<dyn Error>> {
+        let Ok((response, timestamp)) = NCTalk::fetch_rooms_initial(&self.requester).await else {
+            log::debug!("Server still unreachable, staying offline.");
+            return Ok(vec![]);
+        };
+        log::info!("Reached the server again, leaving offline mode.");
+        self.offline = false;
+        self.last_requested = timestamp;
+        let mut new_rooms = vec![];
+        let mut failed = vec![];
+        for room in response {
+            let name = room.displayName.clone();
+            if let Some(known) = self.rooms.get_mut(&room.token) {
+                if let Err(why) = known
+                    .update::<Requester>(Some(room), Arc::clone(&self.requester))
+                    .await
+                {
+                    log::warn!("Could not update {name}: {why}");
+                    failed.push((name, why.to_string()));
+                }
+            } else {
+                let fetch_size = self.fetch_sizes.initial_for(&room);
+                let token = room.token.clone();
+                if let Some(new_room) = NCRoom::new(
+                    room,
+                    Arc::clone(&self.requester),
+                    self.store.clone(),
+                    fetch_size,
+                )
+                .await
+                {
+                    self.rooms.insert(token, new_room);
+                    new_rooms.push(name);
+                } else {
+                    failed.push((name, "the room could not be created".to_string()));
+                }
+            }
+        }
+        let active_room = self.active_room.clone();
+        self.watch_room(&active_room).await;
+        if failed.is_empty() {
+            Ok(new_rooms)
+        } else {
+            Err(RoomUpdateError { failed, new_rooms }.into())
         }
-        // Open a file in write-only mode, returns `io::Result<File>`
-        let mut file = match std::fs::File::create(path) {
+    }
+
+    /// Join the public room of `guest` and only sync this room, which is all a guest can see.
+    async fn new_guest(
+        requester: Arc<Mutex<Requester>>,
+        config: &Config,
+        guest: &Guest,
+        capabilities: Capabilities,
+        store: MessageStore,
+    ) -> Result<NCTalk<Requester>, Box<dyn Error>> {
+        log::info!("Joining {} as guest", guest.token);
+        let response_onceshot = {
+            requester
+                .lock()
+                .await
+                .request_join_as_guest(&guest.token, guest.password.clone(), guest.name.clone())
+                .await?
+        };
+        let room_data = response_onceshot.await??;
+        let token = room_data.token.clone();
+        let fetch_size = FetchSizes::new(config).initial_for(&room_data);
+        let room = NCRoom::new(room_data, Arc::clone(&requester), store.clone(), fetch_size)
+            .await
+            .ok_or_else(|| format!("Failed to create the guest room {token}."))?;
+        let mut talk = NCTalk::with_rooms(
+            HashMap::from([(token.clone(), room)]),
+            0,
+            requester,
+            config,
+            capabilities,
+            store,
+        );
+        talk.guest_room = Some(token.clone());
+        talk.select_room(&token).await?;
+        Ok(talk)
+    }
+
+    /// Fetch the features the server supports, falling back to assuming all of them on failure.
+    async fn fetch_capabilities(requester: &Arc<Mutex<Requester>>) -> Capabilities {
+        let response = match requester.lock().await.request_capabilities().await {
+            Ok(response) => response.await,
             Err(why) => {
-                log::error!(
-                    "couldn't create top level log file {}: {}",
-                    tmp_path_buf
-                        .as_os_str()
-                        .to_str()
-                        .expect("Failed to convert"),
-                    why
-                );
-                return Err(why);
+                log::warn!("Could not request the server capabilities: {why}");
+                return Capabilities::default();
             }
-            Ok(file) => file,
         };
+        match response {
+            Ok(Ok(data)) => {
+                let capabilities = Capabilities::from(data);
+                log::info!(
+                    "Server runs Talk {}",
+                    capabilities.version().unwrap_or("of unknown version")
+                );
+                capabilities
+            }
+            Ok(Err(why)) => {
+                log::warn!("Could not fetch the server capabilities, assuming all features: {why}");
+                Capabilities::default()
+            }
+            Err(why) => {
+                log::warn!("Fetching the server capabilities was aborted: {why}");
+                Capabilities::default()
+            }
+        }
+    }
+
+    /// Add the pushed messages and all others already waiting in the channel.
+    fn add_pushed_messages(
+        &mut self,
+        mut pushed: Option<PushedMessages>,
+    ) -> Vec<(Token, Option<(String, usize)>)> {
+        let mut updated = vec![];
+        while let Some((token, messages)) = pushed {
+            if let Some(room) = self.rooms.get_mut(&token) {
+                let new_message = room.add_messages(messages);
+                updated.push((token, new_message));
+            }
+            pushed = self.pushed_messages.try_recv();
+        }
+        updated
+    }
 
-        if let Err(why) = file.write_all(serde_json::to_string(&data).unwrap().as_bytes()) {
-            log::error!(
-                "couldn't write top level log file to {}: {}",
-                tmp_path_buf
-                    .as_os_str()
-                    .to_str()
-                    .expect("Failed to convert"),
-                why
+    /// Add a chunk of the fetched history, `None` finishes the fetch.
+    fn add_history_chunk(
+        &mut self,
+        messages: Option<Vec<NCReqDataMessage>>,
+    ) -> Option<HistoryProgress> {
+        let history = self.history.as_ref()?;
+        let Some(messages) = messages else {
+            log::debug!(
+                "Fetched the full history of {}, {}",
+                history.token(),
+                history.progress()
             );
-            Err(why)
-        } else {
-            log::debug!("Wrote Logs to files! {:?} ", tmp_path_buf);
-            Ok(())
+            self.history = None;
+            return None;
+        };
+        let progress = history.progress();
+        if let Some(room) = self.rooms.get_mut(history.token()) {
+            // Old messages, which are not notified about.
+            room.add_messages(messages);
+        }
+        Some(progress)
+    }
+}
+
+#[async_trait]
+impl<Requester: NCRequestInterface + 'static + std::marker::Sync> ChatProvider
+    for NCTalk<Requester>
+{
+    type Room = NCRoom;
+    fn write_to_log(&mut self) -> Result<(), std::io::Error> {
+        for room in self.rooms.values_mut() {
+            room.write_to_log()?;
         }
+        let data: Vec<_> = self.rooms.values().map(NCRoom::to_data).collect();
+        if let Err(why) = self.store.save_rooms(data.iter()) {
+            log::error!("couldn't store the rooms: {why}");
+            return Err(std::io::Error::other(why.to_string()));
+        }
+        log::debug!("Stored {} rooms", data.len());
+        Ok(())
+    }
+
+    fn get_locally_muted_rooms(&self) -> Vec<Token> {
+        self.local_mutes.tokens()
+    }
+
+    fn is_locally_muted(&self, token: &Token) -> bool {
+        self.local_mutes.contains(token)
+    }
+
+    fn set_locally_muted(&mut self, token: &Token, muted: bool) -> Result<(), std::io::Error> {
+        log::info!(
+            "Locally {} room {token}",
+            if muted { "muting" } else { "unmuting" }
+        );
+        self.local_mutes.set(token, muted)
     }
 
     fn get_unread_rooms(&self) -> Vec<Token> {
         self.rooms
             .values()
-            .filter(|room| room.has_unread())
+            .filter(|room| room.has_unread() && !room.is_archived())
             .sorted_by(std::cmp::Ord::cmp)
-            .map(NCRoomInterface::to_token)
+            .map(ChatRoom::to_token)
             .collect::<Vec<Token>>()
     }
 
     fn get_favorite_rooms(&self) -> Vec<Token> {
         self.rooms
             .values()
-            .filter(|room| room.is_favorite())
+            .filter(|room| room.is_favorite() && !room.is_archived())
+            .sorted()
+            .map(ChatRoom::to_token)
+            .collect()
+    }
+
+    fn get_archived_rooms(&self) -> Vec<Token> {
+        self.rooms
+            .values()
+            .filter(|room| room.is_archived())
             .sorted()
-            .map(NCRoomInterface::to_token)
+            .map(ChatRoom::to_token)
             .collect()
     }
 
     fn get_room_by_displayname(&self, name: &str) -> Token {
-        for room in self.rooms.values() {
-            if room.to_string() == *name {
-                return room.to_token();
-            }
+        if let Some(room) = self.rooms.values().find(|room| room.to_string() == *name) {
+            return room.to_token();
         }
-        panic!("room doesnt exist {}", name);
+        let first = self
+            .rooms
+            .values()
+            .sorted()
+            .next()
+            .map(ChatRoom::to_token)
+            .unwrap_or_default();
+        log::warn!("No room named {name}, falling back to {first}.");
+        first
+    }
+
+    fn get_dm_room(&self, user_id: &str) -> Option<Token> {
+        self.rooms
+            .values()
+            .find(|room| *room.get_room_type() == RoomType::OneToOne && room.get_name() == user_id)
+            .map(ChatRoom::to_token)
     }
 
     fn get_dm_keys_display_name_mapping(&self) -> Vec<(Token, String)> {
         self.rooms
             .iter()
             .filter(|(_, room)| {
-                [
-                    NCRoomTypes::OneToOne,
-                    NCRoomTypes::NoteToSelf,
-                    NCRoomTypes::ChangeLog,
-                ]
-                .contains(room.get_room_type())
+                !room.is_archived()
+                    && [
+                        RoomType::OneToOne,
+                        RoomType::NoteToSelf,
+                        RoomType::ChangeLog,
+                    ]
+                    .contains(room.get_room_type())
             })
             .map(|(key, _)| (key.clone(), self.rooms[key].to_string()))
             .sorted_by(|(token_a, _), (token_b, _)| self.rooms[token_a].cmp(&self.rooms[token_b]))
@@ -379,9 +612,14 @@ impl<Requester: NCRequestInterface + 'static + std::marker::Sync> NCBackend for
 
     fn get_group_keys_display_name_mapping(&self) -> Vec<(Token, String)> {
         let mut mapping: Vec<(Token, String)> = Vec::new();
-        for (key, room) in &self.rooms {
+        for (key, room) in self.rooms.iter().filter(|(_, room)| {
+            !room.is_archived()
+                && room
+                    .get_parent_room()
+                    .is_none_or(|parent| !self.rooms.contains_key(&parent))
+        }) {
             match room.get_room_type() {
-                NCRoomTypes::Group | NCRoomTypes::Public => {
+                RoomType::Group | RoomType::Public => {
                     mapping.push((key.clone(), self.rooms[key].to_string()));
                 }
                 _ => {}
@@ -391,10 +629,42 @@ impl<Requester: NCRequestInterface + 'static + std::marker::Sync> NCBackend for
         mapping
     }
 
+    fn get_breakout_rooms(&self, token: &Token) -> Vec<(Token, String)> {
+        self.rooms
+            .values()
+            .filter(|room| room.get_parent_room().as_ref() == Some(token))
+            .sorted()
+            .map(|room| (room.to_token(), room.to_string()))
+            .collect()
+    }
+
     fn get_room_keys(&self) -> Vec<&Token> {
         self.rooms.keys().collect::<Vec<&Token>>()
     }
 
+    fn get_room_link(&self, token: &Token) -> String {
+        format!("{}/call/{token}", self.base_url)
+    }
+
+    fn resolve_message_link(&self, link: &str) -> Option<(Token, i32)> {
+        let path = link.strip_prefix(self.base_url.as_str())?;
+        let path = path.strip_prefix("/index.php").unwrap_or(path);
+        let (token, message_id) = path.strip_prefix("/call/")?.split_once("#message_")?;
+        let token = Token::from(token);
+        if !self.rooms.contains_key(&token) {
+            return None;
+        }
+        Some((token, message_id.parse().ok()?))
+    }
+
+    fn supports(&self, feature: ChatFeature) -> bool {
+        self.capabilities.supports(feature)
+    }
+
+    fn error_hint(&self, why: &(dyn Error + 'static)) -> Option<&'static str> {
+        why.downcast_ref::<NCApiError>().and_then(NCApiError::hint)
+    }
+
     async fn send_message(
         &mut self,
         message: String,
@@ -417,6 +687,10 @@ impl<Requester: NCRequestInterface + 'static + std::marker::Sync> NCBackend for
         token: &Token,
     ) -> Result<Option<(String, usize)>, Box<dyn Error>> {
         log::debug!("selected room {}", token);
+        self.active_room.clone_from(token);
+        if self.offline {
+            return Ok(None);
+        }
         self.rooms
             .get_mut(token)
             .ok_or_else(|| format!("Failed to get Room ref for room selection: {token}."))?
@@ -424,44 +698,95 @@ impl<Requester: NCRequestInterface + 'static + std::marker::Sync> NCBackend for
             .await
     }
 
+    async fn watch_room(&mut self, token: &Token) {
+        if self.offline {
+            return;
+        }
+        if self
+            .long_poll
+            .as_ref()
+            .is_some_and(|long_poll| long_poll.token() == token)
+        {
+            return;
+        }
+        // Dropping the long poll of the room watched before stops it.
+        self.long_poll = None;
+        let Some(last_message) = self
+            .rooms
+            .get(token)
+            .and_then(|room| room.get_messages().keys().next_back().copied())
+        else {
+            log::debug!("Not long polling {token}, as it has no messages yet.");
+            return;
+        };
+        self.long_poll = Some(self.requester.lock().await.start_long_poll(
+            token,
+            last_message,
+            self.pushed_messages.sender(),
+        ));
+    }
+
+    async fn next_update(&mut self) -> BackendUpdate {
+        let history = &mut self.history;
+        let fetched_history = async {
+            match history.as_mut() {
+                Some(history) => history.next().await,
+                None => std::future::pending().await,
+            }
+        };
+        // Nothing is awaited after receiving, so no messages are lost when cancelled.
+        tokio::select! {
+            pushed = self.pushed_messages.recv() => BackendUpdate::Pushed(self.add_pushed_messages(pushed)),
+            messages = fetched_history => BackendUpdate::History(self.add_history_chunk(messages)),
+        }
+    }
+
     async fn update_rooms(&mut self, force_update: bool) -> Result<Vec<String>, Box<dyn Error>> {
+        if let Some(token) = &self.guest_room {
+            self.rooms
+                .get_mut(token)
+                .ok_or("Failed to get the guest room for update.")?
+                .update::<Requester>(None, Arc::clone(&self.requester))
+                .await?;
+            return Ok(vec![]);
+        }
+        if self.offline {
+            return self.reconnect().await;
+        }
         let (response, timestamp) = if force_update {
             let resp = {
                 self.requester
                     .lock()
                     .await
                     .request_rooms_update(self.last_requested)
-                    .await
-                    .expect("Initial fetching of rooms on startup failed.")
+                    .await?
             };
-            resp.await
-                .expect("Initial fetching of rooms failed.")
-                .expect("No rooms found")
+            resp.await??
         } else {
-            let resp = {
-                self.requester
-                    .lock()
-                    .await
-                    .request_rooms_initial()
-                    .await
-                    .expect("Initial fetching of rooms on startup failed.")
-            };
-            resp.await
-                .expect("Initial fetching of rooms failed.")
-                .expect("No rooms found")
+            NCTalk::fetch_rooms_initial(&self.requester).await?
         };
         self.last_requested = timestamp;
+        let now = Instant::now();
         let mut new_room_token: Vec<String> = vec![];
+        let mut failed = vec![];
         for room in response {
             if self.rooms.contains_key(&room.token) {
+                let tier = self.poll_tier(&room.token);
+                if !force_update && !self.poll_schedule.is_due(&room.token, tier, now) {
+                    log::trace!("Skipping {:?} room {} until it is due.", tier, room.token);
+                    continue;
+                }
+                self.poll_schedule.mark_polled(&room.token, now);
                 let room_ref = self
                     .rooms
                     .get_mut(&room.token)
                     .ok_or("Failed to get Room ref for update.")?;
-                if force_update {
+                let name = room.displayName.clone();
+                let result = if force_update {
                     room_ref
                         .update::<Requester>(Some(room), Arc::clone(&self.requester))
-                        .await?;
+                        .await
+                        .map(|_| ())
                 } else {
                     room_ref
                         .update_if_id_is_newer::<Requester>(
@@ -469,19 +794,35 @@ impl<Requester: NCRequestInterface + 'static + std::marker::Sync> NCBackend for
                             Some(room),
                             Arc::clone(&self.requester),
                         )
-                        .await?;
+                        .await
+                };
+                if let Err(why) = result {
+                    log::warn!("Could not update {name}: {why}");
+                    failed.push((name, why.to_string()));
                 }
             } else {
-                new_room_token.push(room.displayName.clone());
-                self.rooms.insert(
-                    room.token.clone(),
-                    NCRoom::new(room, self.requester.clone(), self.chat_data_path.clone())
-                        .await
-                        .expect("Could not Create Room."),
-                );
+                let name = room.displayName.clone();
+                let token = room.token.clone();
+                let fetch_size = self.fetch_sizes.initial_for(&room);
+                if let Some(new_room) =
+                    NCRoom::new(room, self.requester.clone(), self.store.clone(), fetch_size).await
+                {
+                    self.rooms.insert(token, new_room);
+                    new_room_token.push(name);
+                } else {
+                    failed.push((name, "the room could not be created".to_string()));
+                }
             }
         }
-        Ok(new_room_token)
+        if failed.is_empty() {
+            Ok(new_room_token)
+        } else {
+            Err(RoomUpdateError {
+                failed,
+                new_rooms: new_room_token,
+            }
+            .into())
+        }
     }
 
     async fn mark_current_room_as_read(
@@ -504,84 +845,598 @@ impl<Requester: NCRequestInterface + 'static + std::marker::Sync> NCBackend for
     }
 
     async fn fetch_room_history(&mut self, token: &Token) -> Result<(), Box<dyn Error>> {
-        self.rooms
-            .get_mut(token.as_str())
-            .expect("Current Rooms seem to be missing.")
-            .fill_history(Arc::clone(&self.requester))
-            .await
-    }
-    async fn shutdown(&self) -> Result<(), Box<dyn std::error::Error>> {
-        self.requester.lock().await.shutdown().await
+        let last_message = self
+            .rooms
+            .get(token)
+            .ok_or_else(|| format!("Failed to get Room ref for fetching history: {token}."))?
+            .get_messages()
+            .keys()
+            .next_back()
+            .copied()
+            .unwrap_or_default();
+        // Dropping the fetch running before stops it.
+        self.history = Some(HistoryFetch::start(
+            Arc::clone(&self.requester),
+            token,
+            last_message,
+        ));
+        Ok(())
     }
-}
-
-#[cfg(test)]
-use crate::backend::nc_room::MockNCRoomInterface;
-#[cfg(test)]
-use mockall::{mock, predicate::*};
 
-#[cfg(test)]
-mock! {
-    #[derive(Debug)]
-    pub NCTalk{
+    fn get_history_progress(&self) -> Option<HistoryProgress> {
+        self.history.as_ref().map(HistoryFetch::progress)
     }
-    #[async_trait]
-    impl NCBackend for NCTalk{
-        type Room = MockNCRoomInterface;
-        fn write_to_log(&mut self) -> Result<(), std::io::Error>;
-        fn get_room(&self, token: &Token) -> &<MockNCTalk as NCBackend>::Room;
-        fn get_unread_rooms(&self) -> Vec<Token>;
-        fn get_favorite_rooms(&self) -> Vec<Token>;
-        fn get_room_by_displayname(&self, name: &str) -> Token;
-        fn get_dm_keys_display_name_mapping(&self) -> Vec<(Token, String)>;
-        fn get_group_keys_display_name_mapping(&self) -> Vec<(Token, String)>;
-        fn get_room_keys<'a>(&'a self) -> Vec<&'a Token>;
-        async fn send_message(& mut self, message: String, token: &Token) -> Result<Option<(String, usize)>, Box<dyn Error>>;
-        async fn select_room(&mut self, token: &Token) -> Result<Option<(String, usize)>, Box<dyn Error>>;
-        async fn update_rooms(& mut self, force_update: bool) -> Result<Vec<String>, Box<dyn Error>>;
-        async fn mark_current_room_as_read(&self, token: &Token) -> Result<(), Box<dyn std::error::Error>>;
-        async fn mark_all_rooms_as_read(&self) -> Result<(), Box<dyn std::error::Error>>;
-        async fn fetch_room_history(&mut self, token: &Token) -> Result<(), Box<dyn Error>>;
-        async fn shutdown(&self) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Unknown while the requester is locked for queuing a request, which only takes a moment.
+    fn get_rate_limit(&self) -> Option<Duration> {
+        self.requester.try_lock().ok()?.rate_limited_for()
     }
-}
 
-#[cfg(test)]
-static BUTZ: &str = "Butz";
+    fn is_offline(&self) -> bool {
+        self.offline
+    }
 
-#[cfg(test)]
-impl std::fmt::Display for MockNCTalk {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let self_name = BUTZ.to_string();
-        write!(f, "{self_name}")
+    /// Dumps are left for the next call while the requester is locked.
+    fn take_request_dumps(&mut self) -> Vec<PathBuf> {
+        self.requester
+            .try_lock()
+            .map(|requester| requester.take_request_dumps())
+            .unwrap_or_default()
     }
-}
 
-#[cfg(test)]
-mod tests {
+    /// `None` while the requester is locked.
+    fn get_metrics(&self) -> Option<MetricsSnapshot> {
+        self.requester
+            .try_lock()
+            .ok()
+            .map(|requester| requester.metrics())
+    }
 
-    use super::*;
-    use crate::{
-        backend::nc_request::{
-            nc_requester::MockNCRequest, NCReqDataMessage, NCReqDataParticipants, NCReqDataRoom,
-        },
-        config::init,
-    };
-    fn get_default_token() -> Token {
-        Token::from("123")
+    fn get_stored_messages(&self, token: &Token) -> Result<Vec<ChatMessage>, Box<dyn Error>> {
+        Ok(self
+            .store
+            .load_all(token)?
+            .into_iter()
+            .map(ChatMessage::from)
+            .collect())
     }
 
-    fn get_default_room() -> NCReqDataRoom {
-        NCReqDataRoom {
-            displayName: "General".to_string(),
-            token: get_default_token(),
-            roomtype: 2, // Group Chat
-            ..Default::default()
+    async fn fetch_older_messages(&mut self, token: &Token) -> Result<usize, Box<dyn Error>> {
+        if self.offline {
+            return Ok(0);
         }
+        self.rooms
+            .get_mut(token)
+            .ok_or_else(|| format!("Failed to get Room ref for fetching older messages: {token}."))?
+            .fetch_older(self.fetch_sizes.default, Arc::clone(&self.requester))
+            .await
     }
 
-    fn get_default_message() -> NCReqDataMessage {
-        NCReqDataMessage {
+    async fn set_room_favorite(&self, token: &Token, favorite: bool) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get(token)
+            .ok_or_else(|| format!("Failed to get Room ref for favorite: {token}."))?
+            .set_favorite(favorite, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn set_room_muted(&self, token: &Token, muted: bool) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get(token)
+            .ok_or_else(|| format!("Failed to get Room ref for muting: {token}."))?
+            .set_muted(muted, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn set_room_archived(&self, token: &Token, archived: bool) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get(token)
+            .ok_or_else(|| format!("Failed to get Room ref for archiving: {token}."))?
+            .set_archived(archived, Arc::clone(&self.requester))
+            .await
+    }
+    async fn search_users(&self, search: &str) -> Result<Vec<FoundUser>, Box<dyn Error>> {
+        let response_onceshot = {
+            self.requester
+                .lock()
+                .await
+                .request_autocomplete_users(search)
+                .await?
+        };
+        Ok(response_onceshot
+            .await??
+            .into_iter()
+            .map(FoundUser::from)
+            .collect())
+    }
+
+    async fn search_listed_rooms(&self, search: &str) -> Result<Vec<ListedRoom>, Box<dyn Error>> {
+        let response_onceshot = {
+            self.requester
+                .lock()
+                .await
+                .request_listed_rooms(search)
+                .await?
+        };
+        let rooms = response_onceshot.await??;
+        Ok(rooms
+            .into_iter()
+            .filter(|room| !self.rooms.contains_key(&room.token))
+            .map(ListedRoom::from)
+            .collect())
+    }
+
+    async fn set_read_status_private(&self, private: bool) -> Result<(), Box<dyn Error>> {
+        let response_onceshot = {
+            self.requester
+                .lock()
+                .await
+                .request_set_read_status_privacy(private)
+                .await?
+        };
+        response_onceshot.await?.map_err(Into::into)
+    }
+
+    async fn get_own_status(&self) -> Result<UserStatus, Box<dyn Error>> {
+        let response_onceshot = { self.requester.lock().await.request_user_status().await? };
+        Ok(response_onceshot.await??.into())
+    }
+
+    async fn get_out_of_office(
+        &self,
+        user_id: &str,
+    ) -> Result<Option<OutOfOffice>, Box<dyn Error>> {
+        let response_onceshot = {
+            self.requester
+                .lock()
+                .await
+                .request_out_of_office(user_id)
+                .await?
+        };
+        Ok(response_onceshot.await??.map(OutOfOffice::from))
+    }
+
+    async fn set_own_status(&self, status: &str) -> Result<(), Box<dyn Error>> {
+        let response_onceshot = {
+            self.requester
+                .lock()
+                .await
+                .request_set_user_status(status)
+                .await?
+        };
+        response_onceshot.await?.map_err(Into::into)
+    }
+
+    async fn set_own_status_message(
+        &self,
+        message: &str,
+        icon: Option<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        let response_onceshot = {
+            self.requester
+                .lock()
+                .await
+                .request_set_user_status_message(message, icon)
+                .await?
+        };
+        response_onceshot.await?.map_err(Into::into)
+    }
+
+    async fn share_file(
+        &self,
+        token: &Token,
+        name: &str,
+        data: Vec<u8>,
+    ) -> Result<(), Box<dyn Error>> {
+        let response_onceshot = {
+            self.requester
+                .lock()
+                .await
+                .request_share_file(token, name, data)
+                .await?
+        };
+        response_onceshot.await?.map_err(Into::into)
+    }
+
+    async fn join_room(&mut self, token: &Token) -> Result<(), Box<dyn Error>> {
+        let response_onceshot = { self.requester.lock().await.request_join_room(token).await? };
+        let room_data = response_onceshot.await??;
+        log::info!("Joined room {}", room_data.displayName);
+        if let Some(room) = self.rooms.get_mut(token) {
+            room.set_session_joined(room_data);
+            return Ok(());
+        }
+        let fetch_size = self.fetch_sizes.initial_for(&room_data);
+        let mut room = NCRoom::new(
+            room_data.clone(),
+            Arc::clone(&self.requester),
+            self.store.clone(),
+            fetch_size,
+        )
+        .await
+        .ok_or_else(|| format!("Failed to create joined room {token}."))?;
+        room.set_session_joined(room_data);
+        self.rooms.insert(token.clone(), room);
+        Ok(())
+    }
+
+    async fn leave_room(&mut self, token: &Token) -> Result<(), Box<dyn Error>> {
+        let Some(room) = self
+            .rooms
+            .get_mut(token)
+            .filter(|room| room.is_session_joined())
+        else {
+            return Ok(());
+        };
+        room.set_session_left();
+        let response_onceshot = {
+            self.requester
+                .lock()
+                .await
+                .request_leave_room(token)
+                .await?
+        };
+        response_onceshot.await?.map_err(Into::into)
+    }
+
+    async fn open_dm(&mut self, user_id: &str) -> Result<Token, Box<dyn Error>> {
+        if let Some(token) = self.get_dm_room(user_id) {
+            return Ok(token);
+        }
+        let response_onceshot = {
+            self.requester
+                .lock()
+                .await
+                .request_create_dm(user_id)
+                .await?
+        };
+        let room_data = response_onceshot.await??;
+        log::info!("Created the direct message room with {user_id}");
+        let token = room_data.token.clone();
+        let fetch_size = self.fetch_sizes.initial_for(&room_data);
+        let room = NCRoom::new(
+            room_data,
+            Arc::clone(&self.requester),
+            self.store.clone(),
+            fetch_size,
+        )
+        .await
+        .ok_or_else(|| format!("Failed to create the direct message room with {user_id}."))?;
+        self.rooms.insert(token.clone(), room);
+        Ok(token)
+    }
+
+    async fn add_participant(
+        &mut self,
+        token: &Token,
+        user_id: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get_mut(token)
+            .ok_or_else(|| format!("Failed to get Room ref for adding a participant: {token}."))?
+            .add_participant(user_id, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn refresh_participants(&mut self, token: &Token) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get_mut(token)
+            .ok_or_else(|| format!("Failed to get Room ref for refreshing participants: {token}."))?
+            .refresh_participants(Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn remove_participant(
+        &mut self,
+        token: &Token,
+        attendee_id: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get_mut(token)
+            .ok_or_else(|| format!("Failed to get Room ref for removing a participant: {token}."))?
+            .remove_participant(attendee_id, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn set_participant_moderator(
+        &mut self,
+        token: &Token,
+        attendee_id: i32,
+        moderator: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get_mut(token)
+            .ok_or_else(|| format!("Failed to get Room ref for setting a moderator: {token}."))?
+            .set_moderator(attendee_id, moderator, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn set_room_name(&mut self, token: &Token, name: &str) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get_mut(token)
+            .ok_or_else(|| format!("Failed to get Room ref for renaming: {token}."))?
+            .set_name(name, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn set_room_description(
+        &mut self,
+        token: &Token,
+        description: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get_mut(token)
+            .ok_or_else(|| format!("Failed to get Room ref for setting the description: {token}."))?
+            .set_description(description, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn ban_participant(
+        &mut self,
+        token: &Token,
+        attendee_id: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get_mut(token)
+            .ok_or_else(|| format!("Failed to get Room ref for banning a participant: {token}."))?
+            .ban_participant(attendee_id, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn send_reply(
+        &mut self,
+        message: String,
+        token: &Token,
+        reply_to: i32,
+    ) -> Result<Option<(String, usize)>, Box<dyn Error>> {
+        let room = self
+            .rooms
+            .get_mut(token)
+            .ok_or("Room not found when it should be there")?;
+        room.send_reply::<Requester>(message, reply_to, Arc::clone(&self.requester))
+            .await?;
+        room.update::<Requester>(None, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn edit_message(
+        &mut self,
+        token: &Token,
+        message_id: i32,
+        message: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get_mut(token)
+            .ok_or_else(|| format!("Failed to get Room ref for editing a message: {token}."))?
+            .edit_message(message_id, message, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn delete_message(
+        &mut self,
+        token: &Token,
+        message_id: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get_mut(token)
+            .ok_or_else(|| format!("Failed to get Room ref for deleting a message: {token}."))?
+            .delete_message(message_id, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn react_to_message(
+        &mut self,
+        token: &Token,
+        message_id: i32,
+        reaction: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get_mut(token)
+            .ok_or_else(|| format!("Failed to get Room ref for reacting: {token}."))?
+            .react_to_message(message_id, reaction, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn remove_reaction(
+        &mut self,
+        token: &Token,
+        message_id: i32,
+        reaction: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get_mut(token)
+            .ok_or_else(|| format!("Failed to get Room ref for removing a reaction: {token}."))?
+            .remove_reaction(message_id, reaction, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn set_message_reminder(
+        &self,
+        token: &Token,
+        message_id: i32,
+        timestamp: i64,
+    ) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get(token)
+            .ok_or_else(|| format!("Failed to get Room ref for setting a reminder: {token}."))?
+            .set_reminder(message_id, timestamp, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn pin_message(&self, token: &Token, message_id: i32) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get(token)
+            .ok_or_else(|| format!("Failed to get Room ref for pinning a message: {token}."))?
+            .pin_message(message_id, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn set_room_password(
+        &mut self,
+        token: &Token,
+        password: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get_mut(token)
+            .ok_or_else(|| format!("Failed to get Room ref for setting the password: {token}."))?
+            .set_password(password, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn set_mention_all_restricted(
+        &mut self,
+        token: &Token,
+        restricted: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get_mut(token)
+            .ok_or_else(|| {
+                format!("Failed to get Room ref for setting mention permissions: {token}.")
+            })?
+            .set_mention_all_restricted(restricted, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn set_message_expiration(
+        &mut self,
+        token: &Token,
+        seconds: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        self.rooms
+            .get_mut(token)
+            .ok_or_else(|| {
+                format!("Failed to get Room ref for setting the message expiration: {token}.")
+            })?
+            .set_message_expiration(seconds, Arc::clone(&self.requester))
+            .await
+    }
+
+    async fn shutdown(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.requester.lock().await.shutdown().await
+    }
+}
+
+#[cfg(test)]
+use crate::backend::chat_room::MockChatRoom;
+#[cfg(test)]
+use mockall::{mock, predicate::*};
+
+#[cfg(test)]
+mock! {
+    #[derive(Debug)]
+    pub NCTalk{
+    }
+    #[async_trait]
+    impl ChatProvider for NCTalk{
+        type Room = MockChatRoom;
+        fn write_to_log(&mut self) -> Result<(), std::io::Error>;
+        fn get_room(&self, token: &Token) -> &<MockNCTalk as ChatProvider>::Room;
+        fn get_unread_rooms(&self) -> Vec<Token>;
+        fn get_locally_muted_rooms(&self) -> Vec<Token>;
+        fn is_locally_muted(&self, token: &Token) -> bool;
+        fn set_locally_muted(&mut self, token: &Token, muted: bool) -> Result<(), std::io::Error>;
+        fn get_favorite_rooms(&self) -> Vec<Token>;
+        fn get_archived_rooms(&self) -> Vec<Token>;
+        fn get_room_by_displayname(&self, name: &str) -> Token;
+        fn get_dm_room(&self, user_id: &str) -> Option<Token>;
+        fn get_dm_keys_display_name_mapping(&self) -> Vec<(Token, String)>;
+        fn get_group_keys_display_name_mapping(&self) -> Vec<(Token, String)>;
+        fn get_breakout_rooms(&self, token: &Token) -> Vec<(Token, String)>;
+        fn get_room_keys<'a>(&'a self) -> Vec<&'a Token>;
+        fn get_room_link(&self, token: &Token) -> String;
+        fn resolve_message_link(&self, link: &str) -> Option<(Token, i32)>;
+        fn supports(&self, feature: ChatFeature) -> bool;
+        async fn send_message(& mut self, message: String, token: &Token) -> Result<Option<(String, usize)>, Box<dyn Error>>;
+        async fn select_room(&mut self, token: &Token) -> Result<Option<(String, usize)>, Box<dyn Error>>;
+        async fn watch_room(&mut self, token: &Token);
+        async fn next_update(&mut self) -> BackendUpdate;
+        async fn update_rooms(& mut self, force_update: bool) -> Result<Vec<String>, Box<dyn Error>>;
+        async fn mark_current_room_as_read(&self, token: &Token) -> Result<(), Box<dyn std::error::Error>>;
+        async fn mark_all_rooms_as_read(&self) -> Result<(), Box<dyn std::error::Error>>;
+        async fn fetch_room_history(&mut self, token: &Token) -> Result<(), Box<dyn Error>>;
+        fn get_history_progress(&self) -> Option<HistoryProgress>;
+        fn get_rate_limit(&self) -> Option<Duration>;
+        fn is_offline(&self) -> bool;
+        fn take_request_dumps(&mut self) -> Vec<PathBuf>;
+        fn get_metrics(&self) -> Option<MetricsSnapshot>;
+        fn get_stored_messages(&self, token: &Token) -> Result<Vec<ChatMessage>, Box<dyn Error>>;
+        async fn fetch_older_messages(&mut self, token: &Token) -> Result<usize, Box<dyn Error>>;
+        async fn set_room_favorite(&self, token: &Token, favorite: bool) -> Result<(), Box<dyn Error>>;
+        async fn set_room_muted(&self, token: &Token, muted: bool) -> Result<(), Box<dyn Error>>;
+        async fn set_room_archived(&self, token: &Token, archived: bool) -> Result<(), Box<dyn Error>>;
+        async fn search_users(&self, search: &str) -> Result<Vec<FoundUser>, Box<dyn Error>>;
+        async fn search_listed_rooms(&self, search: &str) -> Result<Vec<ListedRoom>, Box<dyn Error>>;
+        async fn join_room(&mut self, token: &Token) -> Result<(), Box<dyn Error>>;
+        async fn leave_room(&mut self, token: &Token) -> Result<(), Box<dyn Error>>;
+        async fn open_dm(&mut self, user_id: &str) -> Result<Token, Box<dyn Error>>;
+        async fn set_read_status_private(&self, private: bool) -> Result<(), Box<dyn Error>>;
+        async fn share_file(&self, token: &Token, name: &str, data: Vec<u8>) -> Result<(), Box<dyn Error>>;
+        async fn get_own_status(&self) -> Result<UserStatus, Box<dyn Error>>;
+        async fn get_out_of_office(&self, user_id: &str) -> Result<Option<OutOfOffice>, Box<dyn Error>>;
+        async fn set_own_status(&self, status: &str) -> Result<(), Box<dyn Error>>;
+        async fn set_own_status_message(
+            &self,
+            message: &str,
+            icon: Option<String>,
+        ) -> Result<(), Box<dyn Error>>;
+        async fn add_participant(&mut self, token: &Token, user_id: &str) -> Result<(), Box<dyn Error>>;
+        async fn refresh_participants(&mut self, token: &Token) -> Result<(), Box<dyn Error>>;
+        async fn remove_participant(&mut self, token: &Token, attendee_id: i32) -> Result<(), Box<dyn Error>>;
+        async fn set_participant_moderator(&mut self, token: &Token, attendee_id: i32, moderator: bool) -> Result<(), Box<dyn Error>>;
+        async fn ban_participant(&mut self, token: &Token, attendee_id: i32) -> Result<(), Box<dyn Error>>;
+        async fn set_room_name(&mut self, token: &Token, name: &str) -> Result<(), Box<dyn Error>>;
+        async fn set_room_description(&mut self, token: &Token, description: &str) -> Result<(), Box<dyn Error>>;
+        async fn send_reply(&mut self, message: String, token: &Token, reply_to: i32) -> Result<Option<(String, usize)>, Box<dyn Error>>;
+        async fn edit_message(&mut self, token: &Token, message_id: i32, message: &str) -> Result<(), Box<dyn Error>>;
+        async fn delete_message(&mut self, token: &Token, message_id: i32) -> Result<(), Box<dyn Error>>;
+        async fn react_to_message(&mut self, token: &Token, message_id: i32, reaction: &str) -> Result<(), Box<dyn Error>>;
+        async fn remove_reaction(&mut self, token: &Token, message_id: i32, reaction: &str) -> Result<(), Box<dyn Error>>;
+        async fn set_message_reminder(&self, token: &Token, message_id: i32, timestamp: i64) -> Result<(), Box<dyn Error>>;
+        async fn pin_message(&self, token: &Token, message_id: i32) -> Result<(), Box<dyn Error>>;
+        async fn set_room_password(&mut self, token: &Token, password: &str) -> Result<(), Box<dyn Error>>;
+        async fn set_mention_all_restricted(&mut self, token: &Token, restricted: bool) -> Result<(), Box<dyn Error>>;
+        async fn set_message_expiration(&mut self, token: &Token, seconds: i32) -> Result<(), Box<dyn Error>>;
+        async fn shutdown(&self) -> Result<(), Box<dyn std::error::Error>>;
+    }
+}
+
+#[cfg(test)]
+static BUTZ: &str = "Butz";
+
+#[cfg(test)]
+impl std::fmt::Display for MockNCTalk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let self_name = BUTZ.to_string();
+        write!(f, "{self_name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{
+        backend::nc_request::{
+            nc_requester::MockNCRequest, NCApiError, NCReqDataCapabilities, NCReqDataMessage,
+            NCReqDataParticipants, NCReqDataRoom,
+        },
+        config::init,
+    };
+    fn get_default_token() -> Token {
+        Token::from("123")
+    }
+
+    fn get_default_room() -> NCReqDataRoom {
+        NCReqDataRoom {
+            displayName: "General".to_string(),
+            token: get_default_token(),
+            roomtype: 2, // Group Chat
+            ..Default::default()
+        }
+    }
+
+    fn get_default_message() -> NCReqDataMessage {
+        NCReqDataMessage {
             messageType: "comment".to_string(),
             id: 1,
             ..Default::default()
@@ -589,6 +1444,15 @@ mod tests {
     }
 
     fn prep_backend_creation(mock_requester: &mut MockNCRequest) {
+        let (capabilities_tx, capabilities_rx) = tokio::sync::oneshot::channel();
+        capabilities_tx
+            .send(Ok(NCReqDataCapabilities::default()))
+            .expect("Sending Failed.");
+        mock_requester
+            .expect_request_capabilities()
+            .once()
+            .return_once(move || Ok(capabilities_rx));
+
         let (tx, rx) = tokio::sync::oneshot::channel();
         let (chat_tx, chat_rx) = tokio::sync::oneshot::channel();
         let (update_tx, update_rx) = tokio::sync::oneshot::channel();
@@ -601,221 +1465,841 @@ mod tests {
             .send(Ok(vec![get_default_message()]))
             .expect("Sending Failed.");
 
-        let update_message = NCReqDataMessage {
+        let update_message = NCReqDataMessage {
+            messageType: "comment".to_string(),
+            id: 2,
+            ..Default::default()
+        };
+        update_tx
+            .send(Ok(vec![update_message.clone()]))
+            .expect("Sending Failed.");
+
+        pat_tx
+            .send(Ok(vec![NCReqDataParticipants::default()]))
+            .expect("Sending Failed.");
+
+        mock_requester
+            .expect_request_rooms_initial()
+            .once()
+            .return_once(move || Ok(rx));
+        mock_requester
+            .expect_request_chat_initial()
+            .with(eq(get_default_token()), eq(20))
+            .return_once(move |_, _| Ok(chat_rx));
+
+        mock_requester
+            .expect_request_participants()
+            .times(1)
+            .return_once(move |_| Ok(pat_rx));
+        mock_requester
+            .expect_request_chat_update()
+            .with(eq(get_default_token()), eq(200), eq(1))
+            .return_once_st(move |_, _, _| Ok(update_rx));
+    }
+
+    #[tokio::test]
+    async fn create_backend() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+        let mut mock_requester = MockNCRequest::new();
+        prep_backend_creation(&mut mock_requester);
+        let backend = NCTalk::new(mock_requester, &config)
+            .await
+            .expect("Failed to create Backend");
+        assert_eq!(backend.rooms.len(), 1);
+    }
+    #[tokio::test]
+    async fn mark_room_as_read() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+        let (chat_tx, chat_rx) = tokio::sync::oneshot::channel();
+        chat_tx.send(Ok(())).expect("Sending Failed.");
+
+        let mut mock_requester = MockNCRequest::new();
+        prep_backend_creation(&mut mock_requester);
+        mock_requester
+            .expect_request_mark_chat_read()
+            .with(eq(get_default_token()), eq(2))
+            .return_once(move |_, _| Ok(chat_rx));
+
+        let backend = NCTalk::new(mock_requester, &config)
+            .await
+            .expect("Failed to create Backend");
+        assert!(backend
+            .mark_current_room_as_read(&get_default_token())
+            .await
+            .is_ok());
+    }
+    #[tokio::test]
+    async fn force_room_update() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+        let mut mock_requester = MockNCRequest::new();
+
+        prep_backend_creation(&mut mock_requester);
+
+        let (tx2, rx2) = tokio::sync::oneshot::channel();
+        let (chat_tx, chat_rx) = tokio::sync::oneshot::channel();
+
+        let new_room = NCReqDataRoom {
+            displayName: "General2".to_string(),
+            token: Token::from("3456"),
+            roomtype: 2, // Group Chat
+            ..Default::default()
+        };
+        tx2.send(Ok((vec![get_default_room(), new_room], 2)))
+            .expect("Sending Failed.");
+
+        chat_tx
+            .send(Ok(vec![get_default_message()]))
+            .expect("Sending Failed.");
+
+        mock_requester
+            .expect_request_rooms_initial()
+            .once()
+            .return_once(move || Ok(rx2));
+        mock_requester
+            .expect_request_chat_initial()
+            .with(eq(Token::from("3456")), eq(20))
+            .return_once(move |_, _| Ok(chat_rx));
+
+        let mut backend = NCTalk::new(mock_requester, &config)
+            .await
+            .expect("Failed to create Backend");
+        assert_eq!(backend.rooms.len(), 1);
+
+        assert!(backend.update_rooms(false).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn room_handling() {
+        let init = init("./test/").unwrap();
+        let config = init;
+
+        let mut mock_requester = MockNCRequest::new();
+
+        let (tx2, rx2) = tokio::sync::oneshot::channel();
+        let (pat2_tx, pat2_rx) = tokio::sync::oneshot::channel();
+        let (send_tx, send_rx) = tokio::sync::oneshot::channel();
+        let (chat_update_tx, chat_update_rx) = tokio::sync::oneshot::channel();
+
+        let post_send_message = NCReqDataMessage {
             messageType: "comment".to_string(),
-            id: 2,
+            id: 3,
             ..Default::default()
         };
-        update_tx
-            .send(Ok(vec![update_message.clone()]))
+
+        tx2.send(Ok((vec![get_default_room()], 1)))
+            .expect("Sending Failed.");
+
+        pat2_tx
+            .send(Ok(vec![NCReqDataParticipants::default()]))
+            .expect("Sending Failed.");
+
+        send_tx
+            .send(Ok(NCReqDataMessage::default()))
+            .expect("Sending Failed");
+
+        chat_update_tx
+            .send(Ok(vec![post_send_message.clone()]))
+            .expect("Failed to send");
+
+        prep_backend_creation(&mut mock_requester);
+
+        mock_requester
+            .expect_request_rooms_initial()
+            .once()
+            .return_once(move || Ok(rx2));
+
+        mock_requester
+            .expect_request_send_message()
+            .once()
+            .withf(|message: &String, token: &Token| message == "Test" && *token == "123")
+            .return_once(|_, _| Ok(send_rx));
+
+        mock_requester
+            .expect_request_chat_update()
+            .once()
+            .with(eq(get_default_token()), eq(200), eq(2))
+            .return_once(move |_, _, _| Ok(chat_update_rx));
+
+        mock_requester
+            .expect_request_participants()
+            .times(1)
+            .return_once(move |_| Ok(pat2_rx));
+
+        let mut backend = NCTalk::new(mock_requester, &config)
+            .await
+            .expect("Failed to create Backend");
+
+        assert!(backend
+            .send_message("Test".to_owned(), &Token::from("123"))
+            .await
+            .is_ok());
+
+        assert!(backend.update_rooms(false).await.is_ok());
+
+        assert_eq!(
+            backend.get_room(&"123".into()).to_token(),
+            Token::from("123")
+        );
+        assert_eq!(backend.get_unread_rooms().len(), 0);
+        assert_eq!(
+            backend.get_room_by_displayname("General"),
+            Token::from("123")
+        );
+        assert_eq!(backend.get_dm_keys_display_name_mapping(), vec![]);
+        assert_eq!(
+            backend.get_group_keys_display_name_mapping(),
+            vec![("123".into(), "General".to_string())]
+        );
+        assert_eq!(backend.get_room_keys(), vec![&Token::from("123")]);
+    }
+
+    #[tokio::test]
+    async fn set_room_flags() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut mock_requester = MockNCRequest::new();
+        prep_backend_creation(&mut mock_requester);
+
+        let (fav_tx, fav_rx) = tokio::sync::oneshot::channel();
+        let (notify_tx, notify_rx) = tokio::sync::oneshot::channel();
+        let (archive_tx, archive_rx) = tokio::sync::oneshot::channel();
+        fav_tx.send(Ok(())).expect("Sending Failed.");
+        notify_tx.send(Ok(())).expect("Sending Failed.");
+        archive_tx.send(Ok(())).expect("Sending Failed.");
+
+        mock_requester
+            .expect_request_set_favorite()
+            .with(eq("123"), eq(true))
+            .once()
+            .return_once(move |_, _| Ok(fav_rx));
+        mock_requester
+            .expect_request_set_notification_level()
+            .with(eq("123"), eq(3))
+            .once()
+            .return_once(move |_, _| Ok(notify_rx));
+        mock_requester
+            .expect_request_set_archived()
+            .with(eq("123"), eq(false))
+            .once()
+            .return_once(move |_, _| Ok(archive_rx));
+
+        let backend = NCTalk::new(mock_requester, &config)
+            .await
+            .expect("Failed to create Backend");
+
+        assert!(backend
+            .set_room_favorite(&get_default_token(), true)
+            .await
+            .is_ok());
+        assert!(backend
+            .set_room_muted(&get_default_token(), true)
+            .await
+            .is_ok());
+        assert!(backend
+            .set_room_archived(&get_default_token(), false)
+            .await
+            .is_ok());
+        assert!(backend
+            .set_room_favorite(&Token::from("unknown"), true)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn add_and_remove_participants() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut mock_requester = MockNCRequest::new();
+        prep_backend_creation(&mut mock_requester);
+
+        let (add_tx, add_rx) = tokio::sync::oneshot::channel();
+        let (remove_tx, remove_rx) = tokio::sync::oneshot::channel();
+        let (pat_tx, pat_rx) = tokio::sync::oneshot::channel();
+        let (refresh_tx, refresh_rx) = tokio::sync::oneshot::channel();
+        add_tx.send(Ok(())).expect("Sending Failed.");
+        remove_tx.send(Ok(())).expect("Sending Failed.");
+        pat_tx
+            .send(Ok(vec![NCReqDataParticipants::default()]))
+            .expect("Sending Failed.");
+        refresh_tx
+            .send(Ok(vec![NCReqDataParticipants::default()]))
+            .expect("Sending Failed.");
+
+        mock_requester
+            .expect_request_add_participant()
+            .with(eq("123"), eq("butz"))
+            .once()
+            .return_once(move |_, _| Ok(add_rx));
+        mock_requester
+            .expect_request_remove_attendee()
+            .with(eq("123"), eq(42))
+            .once()
+            .return_once(move |_, _| Ok(remove_rx));
+        mock_requester
+            .expect_request_participants()
+            .once()
+            .return_once(move |_| Ok(pat_rx));
+        mock_requester
+            .expect_request_participants()
+            .once()
+            .return_once(move |_| Ok(refresh_rx));
+
+        let mut backend = NCTalk::new(mock_requester, &config)
+            .await
+            .expect("Failed to create Backend");
+
+        assert!(backend
+            .add_participant(&get_default_token(), "butz")
+            .await
+            .is_ok());
+        assert!(backend
+            .remove_participant(&get_default_token(), 42)
+            .await
+            .is_ok());
+        assert!(backend
+            .add_participant(&Token::from("unknown"), "butz")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn moderate_participants() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut mock_requester = MockNCRequest::new();
+        prep_backend_creation(&mut mock_requester);
+
+        let (promote_tx, promote_rx) = tokio::sync::oneshot::channel();
+        let (ban_tx, ban_rx) = tokio::sync::oneshot::channel();
+        let (pat_tx, pat_rx) = tokio::sync::oneshot::channel();
+        let (refresh_tx, refresh_rx) = tokio::sync::oneshot::channel();
+        promote_tx.send(Ok(())).expect("Sending Failed.");
+        ban_tx.send(Ok(())).expect("Sending Failed.");
+        pat_tx
+            .send(Ok(vec![NCReqDataParticipants::default()]))
+            .expect("Sending Failed.");
+        refresh_tx
+            .send(Ok(vec![NCReqDataParticipants::default()]))
+            .expect("Sending Failed.");
+
+        mock_requester
+            .expect_request_set_moderator()
+            .with(eq("123"), eq(0), eq(true))
+            .once()
+            .return_once(move |_, _, _| Ok(promote_rx));
+        mock_requester
+            .expect_request_ban_attendee()
+            .with(eq("123"), eq(""), eq(""))
+            .once()
+            .return_once(move |_, _, _| Ok(ban_rx));
+        mock_requester
+            .expect_request_participants()
+            .once()
+            .return_once(move |_| Ok(pat_rx));
+        mock_requester
+            .expect_request_participants()
+            .once()
+            .return_once(move |_| Ok(refresh_rx));
+
+        let mut backend = NCTalk::new(mock_requester, &config)
+            .await
+            .expect("Failed to create Backend");
+
+        assert!(backend
+            .set_participant_moderator(&get_default_token(), 0, true)
+            .await
+            .is_ok());
+        assert!(backend
+            .ban_participant(&get_default_token(), 0)
+            .await
+            .is_ok());
+        assert!(backend
+            .ban_participant(&get_default_token(), 42)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn open_dm() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut mock_requester = MockNCRequest::new();
+        prep_backend_creation(&mut mock_requester);
+
+        let (create_tx, create_rx) = tokio::sync::oneshot::channel();
+        let (chat_tx, chat_rx) = tokio::sync::oneshot::channel();
+        create_tx
+            .send(Ok(NCReqDataRoom {
+                displayName: "Butz".to_string(),
+                name: "butz".to_string(),
+                token: Token::from("456"),
+                roomtype: 1, // One to one
+                ..Default::default()
+            }))
             .expect("Sending Failed.");
+        chat_tx.send(Ok(vec![])).expect("Sending Failed.");
+
+        mock_requester
+            .expect_request_create_dm()
+            .with(eq("butz"))
+            .once()
+            .return_once(move |_| Ok(create_rx));
+        mock_requester
+            .expect_request_chat_initial()
+            .withf(|token, _| token == "456")
+            .once()
+            .return_once(move |_, _| Ok(chat_rx));
+
+        let mut backend = NCTalk::new(mock_requester, &config)
+            .await
+            .expect("Failed to create Backend");
+
+        assert!(backend.get_dm_room("butz").is_none());
+        assert_eq!(backend.open_dm("butz").await.unwrap(), "456");
+        assert_eq!(backend.get_dm_room("butz"), Some(Token::from("456")));
+        // The room is known now, so it is not created again.
+        assert_eq!(backend.open_dm("butz").await.unwrap(), "456");
+    }
+
+    #[tokio::test]
+    async fn rename_room() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
 
-        pat_tx
-            .send(Ok(vec![NCReqDataParticipants::default()]))
-            .expect("Sending Failed.");
+        let mut mock_requester = MockNCRequest::new();
+        prep_backend_creation(&mut mock_requester);
+
+        let (name_tx, name_rx) = tokio::sync::oneshot::channel();
+        let (description_tx, description_rx) = tokio::sync::oneshot::channel();
+        name_tx.send(Ok(())).expect("Sending Failed.");
+        description_tx.send(Ok(())).expect("Sending Failed.");
 
         mock_requester
-            .expect_request_rooms_initial()
+            .expect_request_set_room_name()
+            .with(eq("123"), eq("Butzen"))
             .once()
-            .return_once(move || Ok(rx));
+            .return_once(move |_, _| Ok(name_rx));
         mock_requester
-            .expect_request_chat_initial()
-            .with(eq(get_default_token()), eq(200))
-            .return_once(move |_, _| Ok(chat_rx));
+            .expect_request_set_room_description()
+            .with(eq("123"), eq("All about Butz"))
+            .once()
+            .return_once(move |_, _| Ok(description_rx));
 
-        mock_requester
-            .expect_request_participants()
-            .times(1)
-            .return_once(move |_| Ok(pat_rx));
-        mock_requester
-            .expect_request_chat_update()
-            .with(eq(get_default_token()), eq(200), eq(1))
-            .return_once_st(move |_, _, _| Ok(update_rx));
+        let mut backend = NCTalk::new(mock_requester, &config)
+            .await
+            .expect("Failed to create Backend");
+
+        assert!(backend
+            .set_room_name(&get_default_token(), "Butzen")
+            .await
+            .is_ok());
+        assert!(backend
+            .set_room_description(&get_default_token(), "All about Butz")
+            .await
+            .is_ok());
+        let room = backend.get_room(&get_default_token());
+        assert_eq!(room.get_display_name(), "Butzen");
+        assert_eq!(room.get_description(), "All about Butz");
     }
 
     #[tokio::test]
-    async fn create_backend() {
+    async fn room_action_error_hint() {
         let dir = tempfile::tempdir().unwrap();
 
         std::env::set_var("HOME", dir.path().as_os_str());
         let config = init("./test/").unwrap();
+
         let mut mock_requester = MockNCRequest::new();
         prep_backend_creation(&mut mock_requester);
-        let backend = NCTalk::new(mock_requester, &config)
+
+        let (name_tx, name_rx) = tokio::sync::oneshot::channel();
+        let (join_tx, join_rx) = tokio::sync::oneshot::channel();
+        name_tx
+            .send(Err(NCApiError::NotFound("room 123".to_string())))
+            .expect("Sending Failed.");
+        join_tx
+            .send(Err(NCApiError::RateLimited))
+            .expect("Sending Failed.");
+
+        mock_requester
+            .expect_request_set_room_name()
+            .once()
+            .return_once(move |_, _| Ok(name_rx));
+        mock_requester
+            .expect_request_join_room()
+            .once()
+            .return_once(move |_| Ok(join_rx));
+
+        let mut backend = NCTalk::new(mock_requester, &config)
             .await
             .expect("Failed to create Backend");
-        assert_eq!(backend.rooms.len(), 1);
+
+        let why = backend
+            .set_room_name(&get_default_token(), "Butzen")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            backend.error_hint(why.as_ref()),
+            Some("The room may have been deleted or left.")
+        );
+        let why = backend.join_room(&get_default_token()).await.unwrap_err();
+        assert_eq!(
+            backend.error_hint(why.as_ref()),
+            Some("Requests are paused until the server accepts them.")
+        );
     }
+
     #[tokio::test]
-    async fn mark_room_as_read() {
+    async fn react_and_delete_message() {
         let dir = tempfile::tempdir().unwrap();
 
         std::env::set_var("HOME", dir.path().as_os_str());
         let config = init("./test/").unwrap();
-        let (chat_tx, chat_rx) = tokio::sync::oneshot::channel();
-        chat_tx.send(Ok(())).expect("Sending Failed.");
 
         let mut mock_requester = MockNCRequest::new();
         prep_backend_creation(&mut mock_requester);
+
+        let (react_tx, react_rx) = tokio::sync::oneshot::channel();
+        let (delete_tx, delete_rx) = tokio::sync::oneshot::channel();
+        react_tx.send(Ok(())).expect("Sending Failed.");
+        delete_tx.send(Ok(())).expect("Sending Failed.");
+
         mock_requester
-            .expect_request_mark_chat_read()
-            .with(eq(get_default_token()), eq(2))
-            .return_once(move |_, _| Ok(chat_rx));
+            .expect_request_react_to_message()
+            .with(eq("123"), eq(1), eq("👍"))
+            .once()
+            .return_once(move |_, _, _| Ok(react_rx));
+        mock_requester
+            .expect_request_delete_message()
+            .with(eq("123"), eq(1))
+            .once()
+            .return_once(move |_, _| Ok(delete_rx));
 
-        let backend = NCTalk::new(mock_requester, &config)
+        let mut backend = NCTalk::new(mock_requester, &config)
             .await
             .expect("Failed to create Backend");
+
         assert!(backend
-            .mark_current_room_as_read(&get_default_token())
+            .react_to_message(&get_default_token(), 1, "👍")
+            .await
+            .is_ok());
+        assert!(backend
+            .delete_message(&get_default_token(), 1)
             .await
             .is_ok());
     }
+
     #[tokio::test]
-    async fn force_room_update() {
+    async fn write_to_log() {
         let dir = tempfile::tempdir().unwrap();
 
         std::env::set_var("HOME", dir.path().as_os_str());
         let config = init("./test/").unwrap();
-        let mut mock_requester = MockNCRequest::new();
 
+        println!("Path is {}", config.get_data_dir().display());
+
+        let mut mock_requester = MockNCRequest::new();
         prep_backend_creation(&mut mock_requester);
 
-        let (tx2, rx2) = tokio::sync::oneshot::channel();
-        let (chat_tx, chat_rx) = tokio::sync::oneshot::channel();
+        let mut backend = NCTalk::new(mock_requester, &config)
+            .await
+            .expect("Failed to create Backend");
+        assert_eq!(backend.rooms.len(), 1);
+
+        backend.write_to_log().unwrap();
+        dir.close().unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_room_update() {
+        let dir = tempfile::tempdir().unwrap();
 
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+        let mut mock_requester = MockNCRequest::new();
+        prep_backend_creation(&mut mock_requester);
+
+        let (rooms_tx, rooms_rx) = tokio::sync::oneshot::channel();
+        let (update_tx, update_rx) = tokio::sync::oneshot::channel();
+        let unread_room = NCReqDataRoom {
+            unreadMessages: 1,
+            ..get_default_room()
+        };
         let new_room = NCReqDataRoom {
-            displayName: "General2".to_string(),
-            token: Token::from("3456"),
-            roomtype: 2, // Group Chat
-            ..Default::default()
+            token: "456".to_string(),
+            displayName: "Added".to_string(),
+            ..get_default_room()
         };
-        tx2.send(Ok((vec![get_default_room(), new_room], 2)))
+        rooms_tx
+            .send(Ok((vec![unread_room, new_room], 2)))
             .expect("Sending Failed.");
-
-        chat_tx
-            .send(Ok(vec![get_default_message()]))
+        update_tx
+            .send(Err(NCApiError::Status(
+                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+            )))
             .expect("Sending Failed.");
-
         mock_requester
             .expect_request_rooms_initial()
             .once()
-            .return_once(move || Ok(rx2));
+            .return_once(move || Ok(rooms_rx));
+        mock_requester
+            .expect_request_chat_update()
+            .with(eq(get_default_token()), eq(200), eq(2))
+            .once()
+            .return_once(move |_, _, _| Ok(update_rx));
         mock_requester
             .expect_request_chat_initial()
-            .with(eq(Token::from("3456")), eq(200))
-            .return_once(move |_, _| Ok(chat_rx));
+            .with(eq("456".to_string()), always())
+            .once()
+            .return_once(|_, _| Err(NCApiError::Network("unreachable".to_string())));
 
         let mut backend = NCTalk::new(mock_requester, &config)
             .await
             .expect("Failed to create Backend");
-        assert_eq!(backend.rooms.len(), 1);
-
-        assert!(backend.update_rooms(false).await.is_ok());
+        let why = backend.update_rooms(false).await.unwrap_err();
+        let update_error = why.downcast_ref::<RoomUpdateError>().unwrap();
+        assert_eq!(update_error.new_rooms, vec!["Added".to_string()]);
+        assert_eq!(
+            &update_error.failed,
+            &vec![(
+                "General".to_string(),
+                "The server answered 500 Internal Server Error".to_string()
+            )]
+        );
+        assert_eq!(
+            why.to_string(),
+            "Could not update General (The server answered 500 Internal Server Error)"
+        );
+        assert_eq!(backend.rooms.len(), 2);
     }
 
     #[tokio::test]
-    async fn room_handling() {
-        let init = init("./test/").unwrap();
-        let config = init;
-
-        let mut mock_requester = MockNCRequest::new();
+    async fn offline_with_cached_rooms() {
+        let dir = tempfile::tempdir().unwrap();
 
-        let (tx2, rx2) = tokio::sync::oneshot::channel();
-        let (pat2_tx, pat2_rx) = tokio::sync::oneshot::channel();
-        let (send_tx, send_rx) = tokio::sync::oneshot::channel();
-        let (chat_update_tx, chat_update_rx) = tokio::sync::oneshot::channel();
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
 
-        let post_send_message = NCReqDataMessage {
-            messageType: "comment".to_string(),
-            id: 3,
-            ..Default::default()
-        };
+        let mut mock_requester = MockNCRequest::new();
+        prep_backend_creation(&mut mock_requester);
+        let mut backend = NCTalk::new(mock_requester, &config)
+            .await
+            .expect("Failed to create Backend");
+        assert!(!backend.is_offline());
+        backend.write_to_log().unwrap();
 
-        tx2.send(Ok((vec![get_default_room()], 1)))
+        let mut mock_requester = MockNCRequest::new();
+        let (capabilities_tx, capabilities_rx) = tokio::sync::oneshot::channel();
+        capabilities_tx
+            .send(Ok(NCReqDataCapabilities::default()))
             .expect("Sending Failed.");
+        mock_requester
+            .expect_request_capabilities()
+            .once()
+            .return_once(move || Ok(capabilities_rx));
+        mock_requester
+            .expect_request_rooms_initial()
+            .times(2)
+            .returning(|| {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                tx.send(Err(NCApiError::Network("unreachable".to_string())))
+                    .expect("Sending Failed.");
+                Ok(rx)
+            });
 
-        pat2_tx
-            .send(Ok(vec![NCReqDataParticipants::default()]))
-            .expect("Sending Failed.");
+        let mut backend = NCTalk::new(mock_requester, &config)
+            .await
+            .expect("Failed to create offline Backend");
+        assert!(backend.is_offline());
+        assert_eq!(backend.get_room_keys(), vec![&get_default_token()]);
+        assert_eq!(
+            backend.get_room(&get_default_token()).get_messages().len(),
+            2
+        );
+        assert!(backend
+            .select_room(&get_default_token())
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            backend
+                .fetch_older_messages(&get_default_token())
+                .await
+                .unwrap(),
+            0
+        );
+        assert!(backend.update_rooms(false).await.unwrap().is_empty());
+        assert!(backend.is_offline());
+    }
 
-        send_tx
-            .send(Ok(NCReqDataMessage::default()))
-            .expect("Sending Failed");
+    #[tokio::test]
+    async fn reconnect_with_failed_room() {
+        let dir = tempfile::tempdir().unwrap();
 
-        chat_update_tx
-            .send(Ok(vec![post_send_message.clone()]))
-            .expect("Failed to send");
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
 
+        let mut mock_requester = MockNCRequest::new();
         prep_backend_creation(&mut mock_requester);
+        let mut backend = NCTalk::new(mock_requester, &config)
+            .await
+            .expect("Failed to create Backend");
+        backend.write_to_log().unwrap();
 
+        let mut mock_requester = MockNCRequest::new();
+        let mut seq = mockall::Sequence::new();
+        let (capabilities_tx, capabilities_rx) = tokio::sync::oneshot::channel();
+        capabilities_tx
+            .send(Ok(NCReqDataCapabilities::default()))
+            .expect("Sending Failed.");
+        mock_requester
+            .expect_request_capabilities()
+            .once()
+            .return_once(move || Ok(capabilities_rx));
         mock_requester
             .expect_request_rooms_initial()
             .once()
-            .return_once(move || Ok(rx2));
-
+            .in_sequence(&mut seq)
+            .returning(|| Err(NCApiError::Network("unreachable".to_string())));
+        let (rooms_tx, rooms_rx) = tokio::sync::oneshot::channel();
+        let (update_tx, update_rx) = tokio::sync::oneshot::channel();
+        rooms_tx
+            .send(Ok((
+                vec![
+                    NCReqDataRoom {
+                        unreadMessages: 1,
+                        lastMessage: NCReqDataMessage {
+                            id: 3,
+                            ..get_default_message()
+                        },
+                        ..get_default_room()
+                    },
+                    NCReqDataRoom {
+                        token: "456".to_string(),
+                        displayName: "Added".to_string(),
+                        ..get_default_room()
+                    },
+                ],
+                2,
+            )))
+            .expect("Sending Failed.");
+        update_tx
+            .send(Err(NCApiError::Status(
+                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+            )))
+            .expect("Sending Failed.");
         mock_requester
-            .expect_request_send_message()
+            .expect_request_rooms_initial()
             .once()
-            .withf(|message: &String, token: &Token| message == "Test" && *token == "123")
-            .return_once(|_, _| Ok(send_rx));
-
+            .in_sequence(&mut seq)
+            .return_once(move || Ok(rooms_rx));
         mock_requester
             .expect_request_chat_update()
-            .once()
             .with(eq(get_default_token()), eq(200), eq(2))
-            .return_once(move |_, _, _| Ok(chat_update_rx));
-
+            .once()
+            .return_once(move |_, _, _| Ok(update_rx));
         mock_requester
-            .expect_request_participants()
-            .times(1)
-            .return_once(move |_| Ok(pat2_rx));
+            .expect_request_chat_initial()
+            .with(eq("456".to_string()), always())
+            .once()
+            .return_once(|_, _| Err(NCApiError::Network("unreachable".to_string())));
+        mock_requester
+            .expect_start_long_poll()
+            .with(eq(get_default_token()), eq(2), always())
+            .once()
+            .returning(|token, _, _| LongPoll::idle(token));
 
         let mut backend = NCTalk::new(mock_requester, &config)
             .await
-            .expect("Failed to create Backend");
+            .expect("Failed to create offline Backend");
+        assert!(backend.is_offline());
+        let why = backend.update_rooms(false).await.unwrap_err();
+        let update_error = why.downcast_ref::<RoomUpdateError>().unwrap();
+        assert_eq!(update_error.new_rooms, vec!["Added".to_string()]);
+        assert_eq!(update_error.failed.len(), 1);
+        assert!(!backend.is_offline());
+        assert_eq!(backend.rooms.len(), 2);
+        assert!(backend.long_poll.is_some());
+    }
 
-        assert!(backend
-            .send_message("Test".to_owned(), &Token::from("123"))
-            .await
-            .is_ok());
+    #[tokio::test]
+    async fn offline_without_cache() {
+        let dir = tempfile::tempdir().unwrap();
 
-        assert!(backend.update_rooms(false).await.is_ok());
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
 
-        assert_eq!(
-            backend.get_room(&"123".into()).to_token(),
-            Token::from("123")
-        );
-        assert_eq!(backend.get_unread_rooms().len(), 0);
-        assert_eq!(
-            backend.get_room_by_displayname("General"),
-            Token::from("123")
-        );
-        assert_eq!(backend.get_dm_keys_display_name_mapping(), vec![]);
-        assert_eq!(
-            backend.get_group_keys_display_name_mapping(),
-            vec![("123".into(), "General".to_string())]
-        );
-        assert_eq!(backend.get_room_keys(), vec![&Token::from("123")]);
+        let mut mock_requester = MockNCRequest::new();
+        let (capabilities_tx, capabilities_rx) = tokio::sync::oneshot::channel();
+        capabilities_tx
+            .send(Ok(NCReqDataCapabilities::default()))
+            .expect("Sending Failed.");
+        mock_requester
+            .expect_request_capabilities()
+            .once()
+            .return_once(move || Ok(capabilities_rx));
+        mock_requester
+            .expect_request_rooms_initial()
+            .once()
+            .return_once(|| Err("unreachable".into()));
+
+        assert!(NCTalk::new(mock_requester, &config).await.is_err());
     }
 
     #[tokio::test]
-    async fn write_to_log() {
+    async fn resolve_message_link() {
         let dir = tempfile::tempdir().unwrap();
 
         std::env::set_var("HOME", dir.path().as_os_str());
         let config = init("./test/").unwrap();
-
-        println!("Path is {}", config.get_data_dir().display());
-
         let mut mock_requester = MockNCRequest::new();
         prep_backend_creation(&mut mock_requester);
-
-        let mut backend = NCTalk::new(mock_requester, &config)
+        let backend = NCTalk::new(mock_requester, &config)
             .await
             .expect("Failed to create Backend");
-        assert_eq!(backend.rooms.len(), 1);
 
-        backend.write_to_log().unwrap();
-        dir.close().unwrap();
+        assert_eq!(
+            backend.resolve_message_link("https://butz.com/call/123#message_42"),
+            Some((get_default_token(), 42))
+        );
+        assert_eq!(
+            backend.resolve_message_link("https://butz.com/index.php/call/123#message_7"),
+            Some((get_default_token(), 7))
+        );
+        assert_eq!(
+            backend.resolve_message_link("https://butz.com/call/123"),
+            None
+        );
+        assert_eq!(
+            backend.resolve_message_link("https://butz.com/call/456#message_42"),
+            None
+        );
+        assert_eq!(
+            backend.resolve_message_link("https://bert.com/call/123#message_42"),
+            None
+        );
     }
 }
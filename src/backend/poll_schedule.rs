@@ -0,0 +1,89 @@
+use crate::backend::Token;
+use crate::config::Config;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// How often a room is looked at when syncing, by how likely it sees activity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollTier {
+    /// The room shown in the UI, polled on every sync.
+    Active,
+    /// Favorite rooms, polled on a medium interval.
+    Favorite,
+    /// All other rooms, polled slowly.
+    Idle,
+}
+
+/// Scheduling layer of the sync, deciding which rooms are due for fetching new messages.
+///
+/// Cuts the request volume for accounts with many rooms,
+/// as only the active room is updated on every sync.
+#[derive(Debug, Default)]
+pub struct PollSchedule {
+    last_polled: HashMap<Token, Instant>,
+    favorite_interval: Duration,
+    idle_interval: Duration,
+}
+
+impl PollSchedule {
+    pub fn new(config: &Config) -> Self {
+        PollSchedule {
+            last_polled: HashMap::new(),
+            favorite_interval: Duration::from_secs(config.data.general.poll_favorite_seconds),
+            idle_interval: Duration::from_secs(config.data.general.poll_idle_seconds),
+        }
+    }
+
+    fn interval(&self, tier: PollTier) -> Duration {
+        match tier {
+            PollTier::Active => Duration::ZERO,
+            PollTier::Favorite => self.favorite_interval,
+            PollTier::Idle => self.idle_interval,
+        }
+    }
+
+    /// Check if the room should be polled, rooms never polled are always due.
+    pub fn is_due(&self, token: &Token, tier: PollTier, now: Instant) -> bool {
+        self.last_polled
+            .get(token)
+            .is_none_or(|last| now.duration_since(*last) >= self.interval(tier))
+    }
+
+    pub fn mark_polled(&mut self, token: &Token, now: Instant) {
+        self.last_polled.insert(token.clone(), now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::init;
+
+    #[test]
+    fn tiers() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut schedule = PollSchedule::new(&config);
+        let now = Instant::now();
+        let token = Token::from("123");
+        assert!(schedule.is_due(&token, PollTier::Idle, now));
+
+        schedule.mark_polled(&token, now);
+        assert!(schedule.is_due(&token, PollTier::Active, now));
+        assert!(!schedule.is_due(&token, PollTier::Favorite, now));
+
+        let later = now + Duration::from_secs(config.data.general.poll_favorite_seconds);
+        assert!(schedule.is_due(&token, PollTier::Favorite, later));
+        assert!(!schedule.is_due(&token, PollTier::Idle, later));
+        assert!(schedule.is_due(
+            &token,
+            PollTier::Idle,
+            now + Duration::from_secs(config.data.general.poll_idle_seconds)
+        ));
+    }
+}
@@ -0,0 +1,114 @@
+//! Dumps of server responses which could not be parsed, to look into what the server sent.
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// Dumps written since they were last taken, shared by all workers.
+#[derive(Debug, Clone, Default)]
+pub struct RequestDumps(Arc<Mutex<Vec<PathBuf>>>);
+
+impl RequestDumps {
+    fn push(&self, path: PathBuf) {
+        if let Ok(mut dumps) = self.0.lock() {
+            dumps.push(path);
+        }
+    }
+
+    /// Take the paths of the dumps written since the last call.
+    pub fn take(&self) -> Vec<PathBuf> {
+        self.0
+            .lock()
+            .map(|mut dumps| std::mem::take(&mut *dumps))
+            .unwrap_or_default()
+    }
+}
+
+/// Write the response `text` of `url` into a file of its own in `dir`, pretty printed if it is JSON.
+/// The file name starts with the time, so dumps sort from oldest to newest.
+pub fn write_dump(
+    dir: &Path,
+    url: &str,
+    text: &str,
+    dumps: &RequestDumps,
+) -> Result<PathBuf, Box<dyn Error>> {
+    std::fs::create_dir_all(dir)?;
+    let name: String = url
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '.' {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let path = dir.join(format!(
+        "{}_{name}.json",
+        chrono::Local::now().format("%Y%m%d-%H%M%S%.3f")
+    ));
+    std::fs::write(&path, pretty_json(text))?;
+    log::info!("Dumped the response of {url} to {}", path.display());
+    dumps.push(path.clone());
+    Ok(path)
+}
+
+/// Pretty print `text` if it is JSON, otherwise keep it as is.
+pub fn pretty_json(text: &str) -> String {
+    jzon::parse(text).map_or_else(|_| text.to_string(), |json| jzon::stringify_pretty(json, 2))
+}
+
+/// All dumps in `dir`, newest first.
+pub fn list_dumps(dir: &Path) -> Vec<PathBuf> {
+    let mut dumps: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect()
+        })
+        .unwrap_or_default();
+    dumps.sort_unstable_by(|a, b| b.cmp(a));
+    dumps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_list_and_take() {
+        let dir = tempfile::tempdir().unwrap();
+        let dumps = RequestDumps::default();
+        assert!(list_dumps(dir.path()).is_empty());
+
+        let first = write_dump(
+            dir.path(),
+            "https://cloud.example.org/ocs/v2.php/apps/spreed/api/v4/room",
+            "{\"ocs\":{\"data\":[1]}}",
+            &dumps,
+        )
+        .unwrap();
+        assert!(first
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .ends_with("_cloud.example.org_ocs_v2.php_apps_spreed_api_v4_room.json"));
+        assert_eq!(
+            std::fs::read_to_string(&first).unwrap(),
+            "{\n  \"ocs\": {\n    \"data\": [\n      1\n    ]\n  }\n}"
+        );
+
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let second = write_dump(dir.path(), "https://cloud/chat", "<html>", &dumps).unwrap();
+        assert_eq!(std::fs::read_to_string(&second).unwrap(), "<html>");
+
+        assert_eq!(list_dumps(dir.path()), vec![second.clone(), first.clone()]);
+        assert_eq!(dumps.take(), vec![first, second]);
+        assert!(dumps.take().is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+//! Counters of the requests to the server, to find out why the client is slow.
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    sync::{Arc, Mutex},
+};
+
+/// Kinds of requests which send a message when they succeed.
+const SENDING_REQUESTS: [&str; 2] = ["SendMessage", "SendReply"];
+
+/// How many requests of a kind were handled and how many of them failed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RequestCount {
+    pub total: u64,
+    pub failed: u64,
+}
+
+/// The counters at one point in time, see [`RequestMetrics::snapshot`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MetricsSnapshot {
+    /// Requests handled by the workers by their kind, like `FetchChatUpdate`.
+    pub requests: BTreeMap<String, RequestCount>,
+    /// Attempts repeated after a failed one.
+    pub retries: u64,
+    pub messages_sent: u64,
+    /// Messages fetched from the server, long polls included.
+    pub messages_received: u64,
+    /// Requests waiting for the dispatcher, followed by the ones waiting for each worker.
+    pub queued: Vec<usize>,
+}
+
+impl MetricsSnapshot {
+    pub fn total_requests(&self) -> u64 {
+        self.requests.values().map(|count| count.total).sum()
+    }
+
+    pub fn failed_requests(&self) -> u64 {
+        self.requests.values().map(|count| count.failed).sum()
+    }
+}
+
+/// Counters shared by the requester and all of its workers.
+#[derive(Debug, Clone, Default)]
+pub struct RequestMetrics(Arc<Mutex<MetricsSnapshot>>);
+
+impl RequestMetrics {
+    fn update(&self, change: impl FnOnce(&mut MetricsSnapshot)) {
+        if let Ok(mut metrics) = self.0.lock() {
+            change(&mut metrics);
+        }
+    }
+
+    /// Count a handled request of the kind, failed unless `ok`.
+    pub fn record_request(&self, kind: &str, ok: bool) {
+        self.update(|metrics| {
+            let count = metrics.requests.entry(kind.to_string()).or_default();
+            count.total += 1;
+            if !ok {
+                count.failed += 1;
+            } else if SENDING_REQUESTS.contains(&kind) {
+                metrics.messages_sent += 1;
+            }
+        });
+    }
+
+    pub fn record_retry(&self) {
+        self.update(|metrics| metrics.retries += 1);
+    }
+
+    pub fn record_received(&self, messages: usize) {
+        self.update(|metrics| {
+            metrics.messages_received += u64::try_from(messages).unwrap_or(u64::MAX);
+        });
+    }
+
+    /// Copy the counters, together with the current depths of the request queues.
+    pub fn snapshot(&self, queued: Vec<usize>) -> MetricsSnapshot {
+        let mut snapshot = self
+            .0
+            .lock()
+            .map(|metrics| metrics.clone())
+            .unwrap_or_default();
+        snapshot.queued = queued;
+        snapshot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count() {
+        let metrics = RequestMetrics::default();
+        let shared = metrics.clone();
+        shared.record_request("SendMessage", true);
+        shared.record_request("SendMessage", false);
+        shared.record_request("FetchChatUpdate", true);
+        shared.record_retry();
+        shared.record_received(3);
+        shared.record_received(2);
+
+        let snapshot = metrics.snapshot(vec![1, 0, 2]);
+        assert_eq!(
+            snapshot.requests["SendMessage"],
+            RequestCount {
+                total: 2,
+                failed: 1
+            }
+        );
+        assert_eq!(snapshot.total_requests(), 3);
+        assert_eq!(snapshot.failed_requests(), 1);
+        assert_eq!(snapshot.retries, 1);
+        assert_eq!(snapshot.messages_sent, 1);
+        assert_eq!(snapshot.messages_received, 5);
+        assert_eq!(snapshot.queued, [1, 0, 2]);
+        assert_eq!(
+            serde_json::to_value(&snapshot).unwrap()["requests"]["FetchChatUpdate"],
+            serde_json::json!({"total": 1, "failed": 0})
+        );
+    }
+}
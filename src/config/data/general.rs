@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use toml_example::TomlExample;
 
-#[derive(Serialize, Deserialize, Debug, Default, TomlExample)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, TomlExample)]
 pub struct General {
     /// `General.chat_server_name` is the name used for storage and displaying
     /// UPDATE THIS FIELD
@@ -19,15 +20,123 @@ pub struct General {
     pub user: String,
 
     /// `General.app_pw` generated by NC. See <https://butz.com/index.php/settings/user/security>
-    ///  UPDATE THIS FIELD
-    #[toml_example(default = "foobar-asdasd-asdsf")]
+    /// Leave empty to log in through the browser on the next start, which fills in `user` and `app_pw`.
+    #[toml_example(default = "")]
+    #[serde(default)]
     pub app_pw: String,
 
+    /// `General.app_pw_keyring` reads the app password from the system keyring instead of `app_pw`.
+    /// Store it there with `sechat-rs store-password`, logging in through the browser stores it there as well.
+    #[toml_example(default = false)]
+    #[serde(default)]
+    pub app_pw_keyring: bool,
+
     /// `General.log_to_file` should a log file be written into the apps data dir?
     #[toml_example(default = true)]
     pub log_to_file: bool,
 
-    /// `General.dump_failed_requests_to_file` should a log file be written into the apps data dir?
+    /// `General.log_level` up to which messages are logged: off, error, warn, info, debug or trace.
+    #[toml_example(default = "trace")]
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+
+    /// `General.log_modules` log levels for single modules, overriding `log_level` for them.
+    /// The modules are named like the targets in the log view, for example `sechat_rs::backend::nc_request`.
+    /// Left out of the example, set them in a `[general.log_modules]` section.
+    #[toml_example(skip)]
+    #[serde(default)]
+    pub log_modules: BTreeMap<String, String>,
+
+    /// `General.log_max_size_kb` size of `app.log` at which it is moved to `app.log.1`.
+    #[toml_example(default = 1024)]
+    #[serde(default = "default_log_max_size_kb")]
+    pub log_max_size_kb: u64,
+
+    /// `General.log_retention_count` how many moved log files are kept, 0 keeps none.
+    #[toml_example(default = 5)]
+    #[serde(default = "default_log_retention_count")]
+    pub log_retention_count: u32,
+
+    /// `General.dump_failed_requests_to_file` should unparsable responses be dumped into `failed_requests` in the apps data dir?
     #[toml_example(default = false)]
     pub dump_failed_requests_to_file: bool,
+
+    /// `General.read_status_private` keeps your read status private on the server, when set.
+    /// A private read status also hides the read status of others.
+    #[serde(default)]
+    pub read_status_private: Option<bool>,
+
+    /// `General.private_read_rooms` rooms by display name, which are only marked as read with 'm'.
+    #[serde(default)]
+    #[toml_example(default = [])]
+    pub private_read_rooms: Vec<String>,
+
+    /// `General.poll_favorite_seconds` how often favorite rooms are checked for new messages.
+    #[toml_example(default = 15)]
+    #[serde(default = "default_poll_favorite_seconds")]
+    pub poll_favorite_seconds: u64,
+
+    /// `General.poll_idle_seconds` how often all other rooms are checked for new messages.
+    /// The room currently shown is checked on every update.
+    #[toml_example(default = 60)]
+    #[serde(default = "default_poll_idle_seconds")]
+    pub poll_idle_seconds: u64,
+
+    /// `General.initial_fetch_size` how many messages are fetched for rooms missing in the cache.
+    /// Older messages are fetched on demand when scrolling past the first message.
+    #[toml_example(default = 100)]
+    #[serde(default = "default_initial_fetch_size")]
+    pub initial_fetch_size: i32,
+
+    /// `General.initial_fetch_size_favorite` initial message window for favorite rooms.
+    #[toml_example(default = 200)]
+    #[serde(default = "default_initial_fetch_size_favorite")]
+    pub initial_fetch_size_favorite: i32,
+
+    /// `General.initial_fetch_size_unopened` initial message window for rooms never read.
+    #[toml_example(default = 20)]
+    #[serde(default = "default_initial_fetch_size_unopened")]
+    pub initial_fetch_size_unopened: i32,
+
+    /// `General.autosave_seconds` how often rooms and messages are written to disk while running.
+    /// 0 only writes them on exit, which loses everything since the start on a crash.
+    #[toml_example(default = 300)]
+    #[serde(default = "default_autosave_seconds")]
+    pub autosave_seconds: u64,
+}
+
+fn default_log_level() -> String {
+    "trace".to_string()
+}
+
+fn default_log_max_size_kb() -> u64 {
+    1024
+}
+
+fn default_log_retention_count() -> u32 {
+    5
+}
+
+fn default_poll_favorite_seconds() -> u64 {
+    15
+}
+
+fn default_poll_idle_seconds() -> u64 {
+    60
+}
+
+fn default_initial_fetch_size() -> i32 {
+    100
+}
+
+fn default_initial_fetch_size_favorite() -> i32 {
+    200
+}
+
+fn default_initial_fetch_size_unopened() -> i32 {
+    20
+}
+
+fn default_autosave_seconds() -> u64 {
+    300
 }
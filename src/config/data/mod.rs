@@ -1,14 +1,22 @@
 mod general;
+mod network;
 mod notifications;
+mod server;
 mod ui;
 
-use general::General;
+pub use general::General;
+use network::Network;
 use notifications::Notifications;
+pub use notifications::RoomNotifications;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use toml_example::TomlExample;
 use ui::Ui;
 
-#[derive(Serialize, Deserialize, Debug, Default, TomlExample)]
+pub use server::Server;
+pub use ui::ChatSort;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, TomlExample)]
 pub struct ConfigOptions {
     #[toml_example(nesting)]
     pub general: General,
@@ -16,4 +24,57 @@ pub struct ConfigOptions {
     pub notifications: Notifications,
     #[toml_example(nesting)]
     pub ui: Ui,
+    #[toml_example(nesting)]
+    #[serde(default)]
+    pub network: Network,
+    /// Further accounts by name, each in a `[server.<name>]` section.
+    /// Left out of the example, as every section is connected to on start.
+    #[toml_example(skip)]
+    #[serde(default)]
+    pub server: BTreeMap<String, Server>,
+}
+
+impl ConfigOptions {
+    /// Check that no further account is named like the main one, as the accounts are told
+    /// apart and stored by their names.
+    pub fn check_account_names(&self) -> Result<(), String> {
+        let name = &self.general.chat_server_name;
+        if self.server.contains_key(name) {
+            return Err(format!(
+                "The section [server.{name}] is named like the chat_server_name of [general], rename it."
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_parses() {
+        let example = ConfigOptions::toml_example();
+        toml::from_str::<ConfigOptions>(&example)
+            .map_err(|why| format!("{why}\n{example}"))
+            .unwrap();
+    }
+
+    #[test]
+    fn network_defaults_without_section() {
+        let options =
+            toml::from_str::<ConfigOptions>(include_str!("../../../test/config.toml")).unwrap();
+        assert_eq!(options.network.retry_max_attempts, 3);
+        assert_eq!(options.network.retry_backoff_ms, 500);
+    }
+
+    #[test]
+    fn account_names() {
+        let mut options = ConfigOptions::default();
+        options.general.chat_server_name = "Work".to_string();
+        options.server.insert("Home".to_string(), Server::default());
+        assert!(options.check_account_names().is_ok());
+        options.server.insert("Work".to_string(), Server::default());
+        assert!(options.check_account_names().is_err());
+    }
 }
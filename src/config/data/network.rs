@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use toml_example::TomlExample;
+
+#[derive(Serialize, Deserialize, Debug, Clone, TomlExample)]
+pub struct Network {
+    /// `Network.proxy` all requests go through, e.g. `http://proxy:3128` or `socks5://localhost:1080`.
+    /// Leave empty to use the proxy of the environment variables, if any.
+    #[toml_example(default = "")]
+    #[serde(default)]
+    pub proxy: String,
+
+    /// `Network.ca_certificate` path of a PEM or DER certificate to trust in addition to the system ones,
+    /// for instances signed by an internal CA.
+    #[toml_example(default = "")]
+    #[serde(default)]
+    pub ca_certificate: String,
+
+    /// `Network.danger_accept_invalid_certs` accepts any certificate, including expired and self signed ones.
+    /// Anyone between you and the server can read your messages then, prefer `ca_certificate`.
+    #[toml_example(default = false)]
+    #[serde(default)]
+    pub danger_accept_invalid_certs: bool,
+
+    /// `Network.retry_max_attempts` how often a request is tried, before its failure is reported.
+    /// Requests failing with 429, 5xx, timeouts or connection errors are retried, 1 disables retries.
+    #[toml_example(default = 3)]
+    #[serde(default = "default_retry_max_attempts")]
+    pub retry_max_attempts: u32,
+
+    /// `Network.retry_backoff_ms` wait before the first retry, doubled for every further one.
+    #[toml_example(default = 500)]
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+}
+
+impl Default for Network {
+    /// Same as a `[network]` section without any keys, so configs without it keep retrying.
+    fn default() -> Self {
+        Self {
+            proxy: String::default(),
+            ca_certificate: String::default(),
+            danger_accept_invalid_certs: false,
+            retry_max_attempts: default_retry_max_attempts(),
+            retry_backoff_ms: default_retry_backoff_ms(),
+        }
+    }
+}
+
+fn default_retry_max_attempts() -> u32 {
+    3
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    500
+}
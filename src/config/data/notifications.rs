@@ -1,7 +1,22 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use toml_example::TomlExample;
 
-#[derive(Serialize, Deserialize, Debug, Default, TomlExample)]
+/// Which new messages of a room notify, overriding `mentions_only` for the room.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomNotifications {
+    /// Every new message.
+    #[default]
+    All,
+    /// Only mentions of the user.
+    Mentions,
+    /// No message at all.
+    None,
+}
+
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, TomlExample)]
 pub struct Notifications {
     /// `Notifications.timeout_ms` how long a notification shall be displayed.
     #[toml_example(default = 5000)]
@@ -10,4 +25,81 @@ pub struct Notifications {
     pub persistent: bool,
     #[toml_example(default = false)]
     pub silent: bool,
+
+    /// `Notifications.unread_summary` title of the popup for new messages in a room.
+    /// Placeholders are `{room}`, `{author}`, `{count}` and `{preview}` of the last message.
+    #[toml_example(default = "Unread: {room}")]
+    #[serde(default = "default_unread_summary")]
+    pub unread_summary: String,
+
+    /// `Notifications.unread_body` text of the popup for new messages in a room.
+    #[toml_example(default = "You have {count} new Messages in {room}")]
+    #[serde(default = "default_unread_body")]
+    pub unread_body: String,
+
+    /// `Notifications.new_room_summary` title of the popup for rooms you were added to.
+    /// Only `{room}` is filled in for new rooms.
+    #[toml_example(default = "New Room: {room}")]
+    #[serde(default = "default_new_room_summary")]
+    pub new_room_summary: String,
+
+    /// `Notifications.new_room_body` text of the popup for rooms you were added to.
+    #[toml_example(default = "You have been added to a new Room {room}")]
+    #[serde(default = "default_new_room_body")]
+    pub new_room_body: String,
+
+    /// `Notifications.mentions_only` only notify about mentions and direct messages.
+    #[serde(default)]
+    pub mentions_only: bool,
+
+    /// `Notifications.mute_keywords` new messages containing one of these, in any case, do not notify.
+    #[serde(default)]
+    #[toml_example(default = [])]
+    pub mute_keywords: Vec<String>,
+
+    /// `Notifications.quiet_hours` local time range without notifications, like `22:00-07:00`.
+    #[serde(default)]
+    pub quiet_hours: Option<String>,
+
+    /// `Notifications.summary_threshold` rooms with new messages in one update from which on a
+    /// single summary is shown instead of one notification per room, 0 never sums them up.
+    #[toml_example(default = 3)]
+    #[serde(default = "default_summary_threshold")]
+    pub summary_threshold: usize,
+
+    /// `Notifications.sound` played on new messages, a file or the name of a freedesktop sound
+    /// like `message-new-instant`. Independent of `silent`, which only affects the popup.
+    #[serde(default)]
+    pub sound: Option<String>,
+
+    /// `Notifications.sync_dnd_status` set the Nextcloud status to do not disturb while it is
+    /// toggled on in the app, the previous status is restored when toggled off.
+    #[serde(default)]
+    pub sync_dnd_status: bool,
+
+    /// `Notifications.rooms` overrides by the display name of the room, one of `all`, `mentions` or `none`.
+    /// Left out of the example, set them in a `[notifications.rooms]` section.
+    #[toml_example(skip)]
+    #[serde(default)]
+    pub rooms: BTreeMap<String, RoomNotifications>,
+}
+
+fn default_summary_threshold() -> usize {
+    3
+}
+
+fn default_unread_summary() -> String {
+    "Unread: {room}".to_string()
+}
+
+fn default_unread_body() -> String {
+    "You have {count} new Messages in {room}".to_string()
+}
+
+fn default_new_room_summary() -> String {
+    "New Room: {room}".to_string()
+}
+
+fn default_new_room_body() -> String {
+    "You have been added to a new Room {room}".to_string()
 }
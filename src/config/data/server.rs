@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+/// Further Nextcloud account, configured in a `[server.<name>]` section.
+/// The name takes the place of `General.chat_server_name`, all other settings are shared.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Server {
+    /// `Server.url` is the base url of the NC instance. Do not append any further parts.
+    pub url: String,
+
+    /// `Server.user` is the username. Usually not a email address.
+    pub user: String,
+
+    /// `Server.app_pw` generated by NC for this account.
+    #[serde(default)]
+    pub app_pw: String,
+
+    /// `Server.app_pw_keyring` reads the app password from the system keyring instead of `app_pw`.
+    #[serde(default)]
+    pub app_pw_keyring: bool,
+}
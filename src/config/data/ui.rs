@@ -1,7 +1,36 @@
 use serde::{Deserialize, Serialize};
+use strum_macros::Display;
 use toml_example::TomlExample;
 
-#[derive(Serialize, Deserialize, Debug, Default, TomlExample)]
+/// Order of the rooms in each section of the chat selector.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, Display)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatSort {
+    /// By display name.
+    #[default]
+    #[strum(to_string = "alphabetical")]
+    Alphabetical,
+    /// Most recent activity first.
+    #[strum(to_string = "last activity")]
+    LastActivity,
+    /// Rooms with unread messages first, then by display name.
+    #[strum(to_string = "unread first")]
+    UnreadFirst,
+}
+
+impl ChatSort {
+    /// The order after this one, used to cycle through all orders.
+    pub fn next(self) -> Self {
+        match self {
+            ChatSort::Alphabetical => ChatSort::LastActivity,
+            ChatSort::LastActivity => ChatSort::UnreadFirst,
+            ChatSort::UnreadFirst => ChatSort::Alphabetical,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, TomlExample)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Ui {
     /// The default room you want to see on startup.
     ///  UPDATE THIS FIELD
@@ -19,4 +48,104 @@ pub struct Ui {
     /// For available format options see <https://docs.rs/chrono/latest/chrono/format/strftime/index.html>
     #[toml_example(default = "%A %d %B %Y")]
     pub date_format: String,
+    /// Rooms by display name, in which messages are shown in a preview to confirm before sending.
+    #[serde(default)]
+    #[toml_example(default = [])]
+    pub confirm_send_rooms: Vec<String>,
+    /// Seconds a sent message is held back and can be undone with 'z', 0 sends right away.
+    #[serde(default)]
+    pub undo_send_seconds: u64,
+    /// Seconds without a sync of the current room, after which the chat is marked as stale.
+    #[toml_example(default = 120)]
+    #[serde(default = "default_stale_after_seconds")]
+    pub stale_after_seconds: i64,
+    /// Order of the rooms in the chat selector, one of `alphabetical`, `last_activity` or `unread_first`.
+    /// Press 'S' in the chat selector to switch it until the next start.
+    #[serde(default)]
+    #[toml_example(default = "alphabetical")]
+    pub chat_sort: ChatSort,
+    /// Guess the language of code blocks without a language tag, to highlight them in the zoomed message view.
+    #[serde(default)]
+    pub detect_code_language: bool,
+    /// Width of the user sidebar in percent, changed with '<' and '>' in reading mode.
+    #[toml_example(default = 20)]
+    #[serde(default = "default_sidebar_percent")]
+    pub sidebar_percent: u16,
+    /// Width of the name column of the chat, changed with '(' and ')' in reading mode.
+    #[toml_example(default = 20)]
+    #[serde(default = "default_name_width")]
+    pub name_width: u16,
+    /// Most lines the input grows to while writing, longer drafts scroll.
+    #[toml_example(default = 5)]
+    #[serde(default = "default_input_max_lines")]
+    pub input_max_lines: u16,
+    /// Send with Enter and start a new line with Shift+Enter or Alt+Enter.
+    /// If false, Enter starts a new line and Shift+Enter or Alt+Enter sends.
+    #[toml_example(default = true)]
+    #[serde(default = "default_enter_sends")]
+    pub enter_sends: bool,
+    /// Ask terminals supporting the kitty keyboard protocol to report all keys as escape codes,
+    /// telling apart combinations like Ctrl+Enter or Shift+Enter from Enter.
+    #[serde(default)]
+    pub report_all_keys: bool,
+    /// Hide the line at the bottom listing the most used keys of the current screen.
+    #[serde(default)]
+    pub hide_key_hints: bool,
+    /// Draw the names of users in the chat and the participant list in a color of their own.
+    /// The colors are kept in `UserStyles.json` in the data dir and can be changed there.
+    #[serde(default)]
+    pub colored_names: bool,
+    /// How often the participants of the open group room are fetched again while the user sidebar
+    /// is shown, to keep their online status current. 0 only fetches them when the room is updated.
+    #[toml_example(default = 60)]
+    #[serde(default = "default_presence_refresh_seconds")]
+    pub presence_refresh_seconds: u64,
+    /// How often all rooms are synced after two minutes without input or new messages, instead
+    /// of every 10 seconds. 0 keeps syncing every 10 seconds.
+    #[toml_example(default = 30)]
+    #[serde(default = "default_idle_fetch_seconds")]
+    pub idle_fetch_seconds: u64,
+    /// How often all rooms are synced while the terminal is not focused, for terminals reporting
+    /// focus changes. 0 keeps syncing every 10 seconds.
+    #[toml_example(default = 60)]
+    #[serde(default = "default_unfocused_fetch_seconds")]
+    pub unfocused_fetch_seconds: u64,
+    /// Hunspell dictionaries to check the spelling of the input with, none turn it off.
+    /// Either a language like `en_US` found in /usr/share/hunspell, or the path of the
+    /// `.aff` and `.dic` files without the extension.
+    #[serde(default)]
+    #[toml_example(default = [])]
+    pub spell_check_dictionaries: Vec<String>,
+}
+
+fn default_presence_refresh_seconds() -> u64 {
+    60
+}
+
+fn default_idle_fetch_seconds() -> u64 {
+    30
+}
+
+fn default_unfocused_fetch_seconds() -> u64 {
+    60
+}
+
+fn default_stale_after_seconds() -> i64 {
+    120
+}
+
+fn default_sidebar_percent() -> u16 {
+    20
+}
+
+fn default_name_width() -> u16 {
+    20
+}
+
+fn default_input_max_lines() -> u16 {
+    5
+}
+
+fn default_enter_sends() -> bool {
+    true
 }
@@ -0,0 +1,232 @@
+//! Logging into the log view of the app and, if enabled, a rotated `app.log`.
+use std::{
+    error::Error,
+    fs::File,
+    path::Path,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use log::{LevelFilter, Record};
+use log4rs::{
+    append::{
+        rolling_file::{
+            policy::compound::{
+                roll::fixed_window::FixedWindowRoller, trigger::size::SizeTrigger, CompoundPolicy,
+            },
+            RollingFileAppender,
+        },
+        Append,
+    },
+    config::{Appender, Logger, Root},
+    encode::pattern::PatternEncoder,
+};
+
+use tracing_subscriber::{
+    filter::Targets,
+    fmt::format::FmtSpan,
+    layer::{Layer, SubscriberExt},
+    util::SubscriberInitExt,
+};
+
+use super::data::General;
+
+const LOG_FILE: &str = "app.log";
+const LOG_PATTERN: &str = "{d(%Y-%m-%d %H:%M:%S)} {l:<5} {t}: {m}{n}";
+
+/// Warnings and errors logged since the log view was shown last.
+static UNSEEN_PROBLEMS: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of warnings and errors logged since [`mark_problems_seen`] was called.
+pub fn unseen_problems() -> usize {
+    UNSEEN_PROBLEMS.load(Ordering::Relaxed)
+}
+
+/// Reset the count of [`unseen_problems`], once the log view is shown.
+pub fn mark_problems_seen() {
+    UNSEEN_PROBLEMS.store(0, Ordering::Relaxed);
+}
+
+/// Passes every record on to `tui_logger`, which shows them in the log view.
+#[derive(Debug)]
+struct TuiAppender;
+
+impl Append for TuiAppender {
+    fn append(&self, record: &Record) -> anyhow::Result<()> {
+        if record.level() <= log::Level::Warn {
+            UNSEEN_PROBLEMS.fetch_add(1, Ordering::Relaxed);
+        }
+        tui_logger::Drain::new().log(record);
+        Ok(())
+    }
+
+    fn flush(&self) {}
+}
+
+/// Appender writing `app.log` in `dir`, which is moved to `app.log.1` once it is larger than
+/// `log_max_size_kb`, keeping `log_retention_count` of the older files.
+fn file_appender(dir: &Path, general: &General) -> Result<RollingFileAppender, Box<dyn Error>> {
+    let log_path = dir.join(LOG_FILE);
+    let roller = FixedWindowRoller::builder().base(1).build(
+        &format!("{}.{{}}", log_path.display()),
+        general.log_retention_count,
+    )?;
+    let policy = CompoundPolicy::new(
+        Box::new(SizeTrigger::new(general.log_max_size_kb.max(1) * 1024)),
+        Box::new(roller),
+    );
+    Ok(RollingFileAppender::builder()
+        .encoder(Box::new(PatternEncoder::new(LOG_PATTERN)))
+        .build(log_path, Box::new(policy))?)
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, Box<dyn Error>> {
+    LevelFilter::from_str(level).map_err(|_| format!("Unknown log level '{level}'.").into())
+}
+
+/// Build the logger configuration, with the file only if `log_to_file` is set.
+/// The levels of `log_modules` replace the quieter defaults of `reqwest::connect` and of the
+/// spans `tracing` passes on to the logger.
+fn build_config(dir: &Path, general: &General) -> Result<log4rs::Config, Box<dyn Error>> {
+    let mut modules = vec![
+        ("reqwest::connect", LevelFilter::Info),
+        ("tracing::span", LevelFilter::Info),
+    ];
+    for (module, level) in &general.log_modules {
+        modules.retain(|(other, _)| other != module);
+        modules.push((module.as_str(), parse_level(level)?));
+    }
+    let mut builder = log4rs::Config::builder()
+        .appender(Appender::builder().build("tui", Box::new(TuiAppender)))
+        .loggers(
+            modules
+                .into_iter()
+                .map(|(module, level)| Logger::builder().build(module, level)),
+        );
+    let mut root = Root::builder().appender("tui");
+    if general.log_to_file {
+        builder = builder
+            .appender(Appender::builder().build("file", Box::new(file_appender(dir, general)?)));
+        root = root.appender("file");
+    }
+    Ok(builder.build(root.build(parse_level(&general.log_level)?))?)
+}
+
+/// Install the logger for the whole app, logging into `dir` if enabled.
+pub fn init(dir: &Path, general: &General) -> Result<(), Box<dyn Error>> {
+    // Errors of the appenders can neither be logged nor printed over the TUI.
+    log4rs::config::init_config_with_err_handler(build_config(dir, general)?, Box::new(|_| {}))?;
+    // Only starts moving the records into the log view, as log4rs is already the logger.
+    let _ = tui_logger::init_logger(LevelFilter::Trace);
+    tui_logger::set_default_level(LevelFilter::Trace);
+    Ok(())
+}
+
+/// Write the spans of the app, like the ones of the requests to the server, to `path` once they
+/// close, with their fields and how long they took. Their events still reach the logger as well.
+pub fn init_trace_log(path: &Path) -> Result<(), Box<dyn Error>> {
+    let file = File::create(path)
+        .map_err(|why| format!("Could not create the trace log {}: {why}", path.display()))?;
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(Mutex::new(file))
+                .with_ansi(false)
+                .with_span_events(FmtSpan::CLOSE)
+                .with_filter(Targets::new().with_target("sechat_rs", tracing::Level::DEBUG)),
+        )
+        .try_init()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotate_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let general = General {
+            log_to_file: true,
+            log_max_size_kb: 1,
+            log_retention_count: 2,
+            ..Default::default()
+        };
+        let appender = file_appender(dir.path(), &general).unwrap();
+        let message = "x".repeat(100);
+        for _ in 0..50 {
+            appender
+                .append(
+                    &Record::builder()
+                        .args(format_args!("{message}"))
+                        .level(log::Level::Info)
+                        .target("test")
+                        .build(),
+                )
+                .unwrap();
+        }
+        appender.flush();
+
+        assert!(dir.path().join("app.log").exists());
+        assert!(dir.path().join("app.log.1").exists());
+        assert!(dir.path().join("app.log.2").exists());
+        assert!(!dir.path().join("app.log.3").exists());
+        assert!(std::fs::metadata(dir.path().join("app.log")).unwrap().len() <= 1024 + 200);
+    }
+
+    #[test]
+    fn count_problems() {
+        let before = unseen_problems();
+        for level in [log::Level::Error, log::Level::Warn, log::Level::Info] {
+            TuiAppender
+                .append(
+                    &Record::builder()
+                        .args(format_args!("x"))
+                        .level(level)
+                        .build(),
+                )
+                .unwrap();
+        }
+        assert!(unseen_problems() >= before + 2);
+    }
+
+    #[test]
+    fn levels() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut general = General {
+            log_level: "Warn".to_string(),
+            log_modules: [
+                ("reqwest::connect".to_string(), "off".to_string()),
+                ("sechat_rs::backend".to_string(), "trace".to_string()),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let config = build_config(dir.path(), &general).unwrap();
+        assert_eq!(config.root().level(), LevelFilter::Warn);
+        let loggers: Vec<_> = config
+            .loggers()
+            .iter()
+            .map(|logger| (logger.name(), logger.level()))
+            .collect();
+        assert_eq!(
+            loggers,
+            [
+                ("tracing::span", LevelFilter::Info),
+                ("reqwest::connect", LevelFilter::Off),
+                ("sechat_rs::backend", LevelFilter::Trace)
+            ]
+        );
+
+        general
+            .log_modules
+            .insert("x".to_string(), "loud".to_string());
+        assert_eq!(
+            build_config(dir.path(), &general).unwrap_err().to_string(),
+            "Unknown log level 'loud'."
+        );
+    }
+}
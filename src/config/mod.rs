@@ -1,19 +1,39 @@
 mod data;
+mod logging;
+mod secrets;
+
+pub use data::{ChatSort, RoomNotifications, Server};
+pub use logging::{mark_problems_seen, unseen_problems};
 mod theme;
 
 use data::ConfigOptions;
 use etcetera::{app_strategy::Xdg, choose_app_strategy, AppStrategy, AppStrategyArgs};
-use log::LevelFilter;
 use serde::de::DeserializeOwned;
 use std::{path::Path, path::PathBuf};
 use theme::{options::ColorPalette, Theme};
 use toml_example::TomlExample;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Config {
     pub data: ConfigOptions,
     pub theme: Theme,
     strategy: Xdg,
+    /// File the config was read from.
+    path: PathBuf,
+    /// Public room joined as a guest instead of logging in, see [`Config::for_guest`].
+    pub guest: Option<Guest>,
+    /// File the request metrics are written to as JSON, given by `--metrics-file`.
+    pub metrics_file: Option<PathBuf>,
+    /// File the spans of the requests to the server are written to, given by `--trace-log`.
+    pub trace_log: Option<PathBuf>,
+}
+
+/// Public room to join as a guest, with the password and name given on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Guest {
+    pub token: String,
+    pub password: Option<String>,
+    pub name: Option<String>,
 }
 
 pub fn check_config_exists_else_create_new<T: TomlExample>(
@@ -92,15 +112,50 @@ pub fn init(path_arg: &str) -> Result<Config, String> {
     check_config_exists_else_create_new::<ColorPalette>(&theme_path)?;
 
     let data = read_config_file::<ConfigOptions>(&config_path)?;
+    data.check_account_names()?;
     let theme_data = read_config_file::<ColorPalette>(&theme_path)?;
 
     let mut config = Config::default();
     config.set_config_data(data);
     config.set_theme(theme_data);
     config.set_strategy(strategy);
+    config.set_path(config_path);
     Ok(config)
 }
 
+/// Replace the line of `key` in the `[general]` table of the config text, adding it if missing.
+fn set_general_value(text: &str, key: &str, value: &str) -> Result<String, String> {
+    set_table_value(text, "general", key, value)
+}
+
+/// Replace the line of `key` in `table` of the config text, adding it if missing.
+fn set_table_value(
+    text: &str,
+    table: &str,
+    key: &str,
+    value: impl Into<toml::Value>,
+) -> Result<String, String> {
+    let new_line = format!("{key} = {}", value.into());
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let start = lines
+        .iter()
+        .position(|line| line.trim() == format!("[{table}]"))
+        .ok_or(format!("Config File has no [{table}] table."))?
+        + 1;
+    let end = lines[start..]
+        .iter()
+        .position(|line| line.trim_start().starts_with('['))
+        .map_or(lines.len(), |offset| start + offset);
+    match lines[start..end].iter().position(|line| {
+        line.split_once('=')
+            .is_some_and(|(line_key, _)| line_key.trim() == key)
+    }) {
+        Some(offset) => lines[start + offset] = new_line,
+        None => lines.insert(start, new_line),
+    }
+    Ok(lines.join("\n") + "\n")
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -112,6 +167,10 @@ impl Default for Config {
                 app_name: "sechat-rs".to_string(),
             })
             .expect("Could not create default strategy"),
+            path: PathBuf::new(),
+            guest: None,
+            metrics_file: None,
+            trace_log: None,
         }
     }
 }
@@ -126,9 +185,141 @@ impl Config {
     pub fn set_strategy(&mut self, strategy: Xdg) {
         self.strategy = strategy;
     }
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.path = path;
+    }
+
+    /// Write the login of the user into the config file, keeping its comments and other values.
+    /// With `app_pw_keyring` set the app password goes into the system keyring instead.
+    pub fn store_credentials(&mut self, user: &str, app_pw: &str) -> Result<(), String> {
+        let text = std::fs::read_to_string(&self.path)
+            .map_err(|why| format!("Failed to read Config File: {why}"))?;
+        let mut text = set_general_value(&text, "user", user)?;
+        if self.data.general.app_pw_keyring {
+            secrets::store_app_password(&self.data.general.url, user, app_pw)?;
+        } else {
+            text = set_general_value(&text, "app_pw", app_pw)?;
+            self.data.general.app_pw = app_pw.to_string();
+        }
+        std::fs::write(&self.path, text)
+            .map_err(|why| format!("Failed to write Config File: {why}"))?;
+        self.data.general.user = user.to_string();
+        Ok(())
+    }
+
+    /// Write the widths of the user sidebar and the name column into the config file.
+    pub fn store_layout(&mut self, sidebar_percent: u16, name_width: u16) -> Result<(), String> {
+        let text = std::fs::read_to_string(&self.path)
+            .map_err(|why| format!("Failed to read Config File: {why}"))?;
+        let text = set_table_value(&text, "ui", "sidebar_percent", i64::from(sidebar_percent))?;
+        let text = set_table_value(&text, "ui", "name_width", i64::from(name_width))?;
+        std::fs::write(&self.path, text)
+            .map_err(|why| format!("Failed to write Config File: {why}"))?;
+        self.data.ui.sidebar_percent = sidebar_percent;
+        self.data.ui.name_width = name_width;
+        Ok(())
+    }
+
+    /// The app password, read from the system keyring if `app_pw_keyring` is set.
+    pub fn get_app_password(&self) -> Result<String, String> {
+        let general = &self.data.general;
+        if general.app_pw_keyring {
+            secrets::read_app_password(&general.url, &general.user)
+        } else {
+            Ok(general.app_pw.clone())
+        }
+    }
+
+    /// Config of a further account, its server settings replacing those of the general section.
+    pub fn for_server(&self, name: &str, server: &Server) -> Config {
+        let mut config = self.clone();
+        let general = &mut config.data.general;
+        general.chat_server_name = name.to_string();
+        general.url.clone_from(&server.url);
+        general.user.clone_from(&server.user);
+        general.app_pw.clone_from(&server.app_pw);
+        general.app_pw_keyring = server.app_pw_keyring;
+        config
+    }
+
+    /// Config for joining the public room of `link` as a guest, without user and app password.
+    /// The room is stored apart from the accounts, by the host of the link.
+    pub fn for_guest(
+        &self,
+        link: &str,
+        password: Option<String>,
+        name: Option<String>,
+    ) -> Result<Config, String> {
+        let (base, token) = link
+            .split_once("/call/")
+            .ok_or_else(|| format!("{link} is no link to a room, like https://host/call/token"))?;
+        let token = token.split(['/', '?', '#']).next().unwrap_or_default();
+        let host = base
+            .split_once("://")
+            .map_or(base, |(_, rest)| rest)
+            .split('/')
+            .next()
+            .unwrap_or_default();
+        if token.is_empty() || host.is_empty() {
+            return Err(format!(
+                "{link} is no link to a room, like https://host/call/token"
+            ));
+        }
+        let mut config = self.clone();
+        let general = &mut config.data.general;
+        general.chat_server_name = format!("guest-{host}");
+        general.url = base.trim_end_matches("/index.php").to_string();
+        general.user = String::new();
+        general.app_pw = String::new();
+        general.app_pw_keyring = false;
+        config.data.server.clear();
+        config.guest = Some(Guest {
+            token: token.to_string(),
+            password,
+            name,
+        });
+        Ok(config)
+    }
+
+    /// Store the app password of the configured user in the system keyring.
+    pub fn store_app_password(&self, app_pw: &str) -> Result<(), String> {
+        secrets::store_app_password(&self.data.general.url, &self.data.general.user, app_pw)
+    }
+
+    /// Builder of HTTP clients, set up with the proxy and certificates of the network section.
+    pub fn http_client(&self) -> Result<reqwest::ClientBuilder, String> {
+        let network = &self.data.network;
+        let mut builder = reqwest::Client::builder();
+        if !network.proxy.is_empty() {
+            let proxy = reqwest::Proxy::all(&network.proxy)
+                .map_err(|why| format!("Invalid proxy {}: {why}", network.proxy))?;
+            builder = builder.proxy(proxy);
+        }
+        if !network.ca_certificate.is_empty() {
+            let read_error = |why: &dyn std::fmt::Display| {
+                format!(
+                    "Failed to read CA certificate {}: {why}",
+                    network.ca_certificate
+                )
+            };
+            let bytes = std::fs::read(&network.ca_certificate).map_err(|why| read_error(&why))?;
+            let certificate = reqwest::Certificate::from_pem(&bytes)
+                .or_else(|_| reqwest::Certificate::from_der(&bytes))
+                .map_err(|why| read_error(&why))?;
+            builder = builder.add_root_certificate(certificate);
+        }
+        if network.danger_accept_invalid_certs {
+            log::warn!(
+                "Accepting invalid certificates, the connection to the server is not verified."
+            );
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        Ok(builder)
+    }
+
     pub fn get_http_dump_dir(&self) -> Option<PathBuf> {
         if self.data.general.dump_failed_requests_to_file {
-            Some(self.get_data_dir())
+            Some(self.get_data_dir().join("failed_requests"))
         } else {
             None
         }
@@ -137,6 +328,20 @@ impl Config {
     pub fn get_data_dir(&self) -> PathBuf {
         self.strategy.data_dir()
     }
+
+    /// Directory exports are written to, `XDG_DOWNLOAD_DIR` or `~/Downloads` if present,
+    /// otherwise the data dir.
+    pub fn get_download_dir(&self) -> PathBuf {
+        std::env::var_os("XDG_DOWNLOAD_DIR")
+            .map(PathBuf::from)
+            .or_else(|| {
+                etcetera::home_dir()
+                    .ok()
+                    .map(|home| home.join("Downloads"))
+                    .filter(|dir| dir.is_dir())
+            })
+            .unwrap_or_else(|| self.get_data_dir())
+    }
     pub fn get_server_data_dir(&self) -> PathBuf {
         let path = self
             .strategy
@@ -156,19 +361,16 @@ impl Config {
         self.data.ui.use_paste
     }
 
-    pub fn config_logging(&self) {
-        // Set max_log_level to Trace
-        tui_logger::init_logger(log::LevelFilter::Trace).unwrap();
-
-        // Set default level for unknown targets to Trace
-        tui_logger::set_default_level(log::LevelFilter::Trace);
-        tui_logger::set_level_for_target("reqwest::connect", LevelFilter::Info);
-
-        if self.data.general.log_to_file {
-            let log_path = self.strategy.data_dir().join("app.log");
+    pub fn get_report_all_keys(&self) -> bool {
+        self.data.ui.report_all_keys
+    }
 
-            tui_logger::set_log_file(log_path.to_str().unwrap()).unwrap();
+    pub fn config_logging(&self) -> Result<(), Box<dyn std::error::Error>> {
+        logging::init(&self.strategy.data_dir(), &self.data.general)?;
+        if let Some(path) = &self.trace_log {
+            logging::init_trace_log(path)?;
         }
+        Ok(())
     }
 }
 
@@ -218,9 +420,30 @@ mod tests {
         assert!(config
             .get_http_dump_dir()
             .expect("Not Https Dump Dir found")
-            .ends_with(".local/share/sechat-rs"));
+            .ends_with(".local/share/sechat-rs/failed_requests"));
         assert!(config.get_enable_mouse());
         assert!(config.get_enable_paste());
+        assert_eq!(config.data.ui.confirm_send_rooms, vec!["Announcements"]);
+        assert_eq!(config.data.ui.undo_send_seconds, 0);
+        assert_eq!(config.data.ui.stale_after_seconds, 120);
+        assert_eq!(config.data.ui.chat_sort, ChatSort::Alphabetical);
+        assert_eq!(config.data.general.read_status_private, Some(false));
+        assert_eq!(config.data.general.private_read_rooms, vec!["Off Topic"]);
+        assert_eq!(config.data.general.poll_favorite_seconds, 15);
+        assert_eq!(config.data.general.poll_idle_seconds, 60);
+        assert_eq!(config.data.general.initial_fetch_size, 100);
+        assert_eq!(config.data.general.initial_fetch_size_favorite, 200);
+        assert_eq!(config.data.general.initial_fetch_size_unopened, 20);
+        assert_eq!(config.data.general.autosave_seconds, 300);
+        assert_eq!(config.data.general.log_level, "trace");
+        assert!(config.data.general.log_modules.is_empty());
+        assert_eq!(config.data.general.log_max_size_kb, 1024);
+        assert_eq!(config.data.general.log_retention_count, 5);
+        assert_eq!(config.data.notifications.unread_summary, "Unread: {room}");
+        assert_eq!(
+            config.data.notifications.new_room_body,
+            "You have been added to a new Room {room}"
+        );
     }
 
     #[test]
@@ -236,9 +459,128 @@ mod tests {
     }
 
     #[test]
-    fn init_logging() {
+    fn store_credentials() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        std::fs::copy("./test/config.toml", dir.path().join("config.toml")).unwrap();
+        std::fs::copy("./test/theme.toml", dir.path().join("theme.toml")).unwrap();
+        let mut config = init(dir.path().to_str().unwrap()).unwrap();
+
+        config.store_credentials("butz", "pass\"word").unwrap();
+        assert_eq!(config.data.general.app_pw, "pass\"word");
+
+        let config = init(dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(config.data.general.user, "butz");
+        assert_eq!(config.data.general.app_pw, "pass\"word");
+        assert_eq!(config.data.general.url, "https://butz.com/");
+        assert_eq!(config.data.ui.confirm_send_rooms, vec!["Announcements"]);
+
+        assert_eq!(
+            set_general_value("[general]\nurl = \"x\"\n[ui]\nuser = 1\n", "user", "butz"),
+            Ok("[general]\nuser = \"butz\"\nurl = \"x\"\n[ui]\nuser = 1\n".to_string())
+        );
+    }
+
+    #[test]
+    fn store_layout() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        std::fs::copy("./test/config.toml", dir.path().join("config.toml")).unwrap();
+        std::fs::copy("./test/theme.toml", dir.path().join("theme.toml")).unwrap();
+        let mut config = init(dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(config.data.ui.sidebar_percent, 20);
+        assert_eq!(config.data.ui.name_width, 20);
+
+        config.store_layout(35, 12).unwrap();
+        let config = init(dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(config.data.ui.sidebar_percent, 35);
+        assert_eq!(config.data.ui.name_width, 12);
+        assert_eq!(config.data.ui.default_room, "General");
+    }
+
+    #[test]
+    fn server_config() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+        let server = &config.data.server["Private"];
+        let private = config.for_server("Private", server);
+        assert_eq!(private.data.general.chat_server_name, "Private");
+        assert_eq!(private.data.general.url, "https://private.butz.com/");
+        assert_eq!(private.data.general.user, "private_user");
+        assert_eq!(private.get_app_password(), Ok("private-pw".to_string()));
+        assert_eq!(private.data.general.private_read_rooms, vec!["Off Topic"]);
+        assert!(private
+            .get_server_data_dir()
+            .ends_with(".local/share/sechat-rs/Private"));
+        assert_eq!(config.data.general.chat_server_name, "MyNCInstance");
+    }
+
+    #[test]
+    fn http_client() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let mut config = init("./test/").unwrap();
+        assert!(config.http_client().unwrap().build().is_ok());
+
+        config.data.network.proxy = "socks5://localhost:1080".to_string();
+        config.data.network.danger_accept_invalid_certs = true;
+        assert!(config.http_client().unwrap().build().is_ok());
+
+        config.data.network.proxy = "not a proxy".to_string();
+        assert!(config
+            .http_client()
+            .unwrap_err()
+            .starts_with("Invalid proxy not a proxy"));
+
+        config.data.network.proxy = String::new();
+        config.data.network.ca_certificate = dir.path().join("missing.pem").display().to_string();
+        assert!(config
+            .http_client()
+            .unwrap_err()
+            .starts_with("Failed to read CA certificate"));
+    }
+
+    #[test]
+    fn guest_link() {
         let conf = Config::default();
-        conf.config_logging();
+        let guest = conf
+            .for_guest(
+                "https://cloud.example.org/index.php/call/abc123#message_4",
+                Some("secret".to_string()),
+                None,
+            )
+            .unwrap();
+        assert_eq!(guest.data.general.url, "https://cloud.example.org");
+        assert_eq!(
+            guest.data.general.chat_server_name,
+            "guest-cloud.example.org"
+        );
+        assert_eq!(
+            guest.guest,
+            Some(Guest {
+                token: "abc123".to_string(),
+                password: Some("secret".to_string()),
+                name: None,
+            })
+        );
+        assert!(conf
+            .for_guest("https://cloud.example.org/apps/files", None, None)
+            .is_err());
+        assert!(conf
+            .for_guest("https://cloud.example.org/call/", None, None)
+            .is_err());
+    }
+
+    #[test]
+    fn init_logging() {
+        let mut conf = Config::default();
+        conf.data.general.log_level = "debug".to_string();
+        conf.config_logging().unwrap();
     }
 
     #[test]
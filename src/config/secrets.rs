@@ -0,0 +1,50 @@
+//! App passwords kept in the system keyring, instead of in plain text in the config file.
+use keyring::Entry;
+
+/// Service the app passwords are stored under in the keyring.
+const KEYRING_SERVICE: &str = "sechat-rs";
+
+/// Run `task` on a thread of its own, the secret service backend blocks on its own executor,
+/// which deadlocks when called from a tokio worker.
+fn on_own_thread<T: Send + 'static>(
+    task: impl FnOnce() -> keyring::Result<T> + Send + 'static,
+) -> Result<T, String> {
+    std::thread::spawn(task)
+        .join()
+        .map_err(|_| "Keyring access panicked.".to_string())?
+        .map_err(|why| format!("Keyring access failed: {why}"))
+}
+
+/// Keyring entry of the app password of `user` on the server at `url`.
+fn entry(url: &str, user: &str) -> keyring::Result<Entry> {
+    Entry::new(KEYRING_SERVICE, &format!("{user}@{url}"))
+}
+
+pub fn read_app_password(url: &str, user: &str) -> Result<String, String> {
+    let (url, user) = (url.to_string(), user.to_string());
+    on_own_thread(move || entry(&url, &user)?.get_password())
+}
+
+pub fn store_app_password(url: &str, user: &str, app_pw: &str) -> Result<(), String> {
+    let (url, user, app_pw) = (url.to_string(), user.to_string(), app_pw.to_string());
+    on_own_thread(move || entry(&url, &user)?.set_password(&app_pw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyring_access() {
+        keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+
+        assert_eq!(
+            store_app_password("https://butz.com/", "butz", "password"),
+            Ok(())
+        );
+        // every mock entry has a store of its own, so nothing is found by a later read
+        assert!(read_app_password("https://butz.com/", "butz")
+            .unwrap_err()
+            .starts_with("Keyring access failed: "));
+    }
+}
@@ -3,7 +3,7 @@ use ratatui::style::Stylize;
 
 pub mod options;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Theme {
     data: options::ColorPalette,
 }
@@ -71,6 +71,14 @@ impl Theme {
             .fg(self.data.popup_border)
             .bg(self.data.background)
     }
+
+    pub fn room_badge_styles(&self) -> Vec<Style> {
+        self.data
+            .room_badges
+            .iter()
+            .map(|color| Style::new().bold().fg(self.data.background).bg(*color))
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -129,6 +137,7 @@ mod tests {
             theme.popup_border_style(),
             Style::new().fg(Color::default()).bg(Color::default())
         );
+        assert!(theme.room_badge_styles().is_empty());
     }
 
     #[test]
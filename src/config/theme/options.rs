@@ -6,7 +6,7 @@ use toml_example::TomlExample;
 /// String, e.g. "white", see <https://docs.rs/ratatui/latest/ratatui/style/enum.Color.html>
 /// indexed, e.g. "10", see <https://en.wikipedia.org/wiki/ANSI_escape_code#8-bit>
 /// hex, e.g. "#a03f49", see <https://docs.rs/ratatui/latest/ratatui/style/enum.Color.html#method.deserialize>
-#[derive(Serialize, Deserialize, Debug, Default, TomlExample)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, TomlExample)]
 pub struct ColorPalette {
     /// Default Background
     #[toml_example(default = "#1f2335")]
@@ -67,4 +67,21 @@ pub struct ColorPalette {
     /// Borders for popup windows
     #[toml_example(default = "#ff757f")]
     pub popup_border: Color,
+
+    /// Backgrounds for the room badges, each room gets one picked by its name
+    #[serde(default = "default_room_badges")]
+    #[toml_example(default = ["#ff757f", "#ff9e64", "#ffc777", "#c3e88d", "#86e1fc", "#82aaff", "#c099ff"])]
+    pub room_badges: Vec<Color>,
+}
+
+fn default_room_badges() -> Vec<Color> {
+    [
+        Color::Red,
+        Color::Yellow,
+        Color::Green,
+        Color::Cyan,
+        Color::Blue,
+        Color::Magenta,
+    ]
+    .to_vec()
 }
@@ -0,0 +1,149 @@
+//! Headless mode, which only syncs the backend and sends desktop notifications.
+//!
+//! Meant for users keeping the chat itself in the browser, who still want to get pinged.
+use crate::backend::{chat_provider::RoomUpdateError, ChatProvider, ChatRoom, Token};
+use crate::config::Config;
+use crate::ui::notifications::NotifyWrapper;
+use std::{collections::HashMap, error::Error, time::Duration};
+
+/// How often the rooms are synced.
+const FETCH_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Unread counts of the rooms which already notified, so every ping only notifies once.
+#[derive(Debug, Default)]
+pub struct Pings {
+    notified: HashMap<Token, usize>,
+}
+
+impl Pings {
+    /// Collect the rooms with new mentions or direct messages since the last call,
+    /// as token and unread count. Locally muted rooms are left out.
+    pub fn collect(&mut self, backend: &impl ChatProvider) -> Vec<(Token, usize)> {
+        let unread = backend.get_unread_rooms();
+        self.notified.retain(|token, _| unread.contains(token));
+        unread
+            .into_iter()
+            .filter(|token| !backend.is_locally_muted(token))
+            .filter_map(|token| {
+                let room = backend.get_room(&token);
+                if !room.is_dm() && !room.has_unread_mention() {
+                    return None;
+                }
+                let count = room.get_unread();
+                let previous = self.notified.insert(token.clone(), count);
+                previous
+                    .is_none_or(|previous| count > previous)
+                    .then_some((token, count))
+            })
+            .collect()
+    }
+}
+
+/// Sync the rooms, returning the display names of the new ones.
+/// Failures are only logged, the next sync tries again. New rooms are still returned if only
+/// some rooms failed.
+async fn sync_rooms(backend: &mut impl ChatProvider) -> Vec<String> {
+    match backend.update_rooms(false).await {
+        Ok(new_rooms) => new_rooms,
+        Err(why) => {
+            log::warn!("Could not sync the rooms: {why}");
+            why.downcast_ref::<RoomUpdateError>()
+                .map(|partial| partial.new_rooms.clone())
+                .unwrap_or_default()
+        }
+    }
+}
+
+/// Sync the backend until Ctrl+C is pressed, notifying about mentions and direct messages.
+pub async fn run(mut backend: impl ChatProvider, config: &Config) -> Result<(), Box<dyn Error>> {
+    let mut notify = NotifyWrapper::new(config);
+    if !notify.is_available() {
+        log::warn!("Running without a notification service, no notifications will be shown.");
+    }
+    let mut pings = Pings::default();
+    let mut interval = tokio::time::interval(FETCH_INTERVAL);
+    log::info!("Running the notification daemon, stop it with Ctrl+C.");
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = interval.tick() => {
+                let new_rooms = sync_rooms(&mut backend).await;
+                let pinged = pings.collect(&backend);
+                notify.maybe_notify_new_rooms(new_rooms);
+                notify.start_batch();
+                for (token, unread) in pinged {
+                    let room = backend.get_room(&token);
+                    let room_name = room.get_display_name().to_string();
+                    let last_message = room.get_messages().values().next_back();
+                    // Only mentions and direct messages are collected, the rules may still keep them back.
+                    notify.maybe_notify_new_message(&token, Some((room_name, unread)), true, last_message);
+                }
+                notify.finish_batch();
+            }
+        }
+    }
+    log::info!("Shutting Down.");
+    backend.write_to_log()?;
+    backend.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{chat_room::MockChatRoom, nc_talk::MockNCTalk};
+    use mockall::predicate::eq;
+
+    #[test]
+    fn collect_pings() {
+        let mut mock_nc_backend = MockNCTalk::new();
+        let mut mock_room = MockChatRoom::new();
+        mock_room.expect_is_dm().return_const(true);
+        mock_room.expect_get_unread().return_const(2_usize);
+        mock_nc_backend
+            .expect_get_unread_rooms()
+            .return_const(vec![Token::from("1"), Token::from("2")]);
+        mock_nc_backend
+            .expect_is_locally_muted()
+            .with(eq(Token::from("1")))
+            .return_const(true);
+        mock_nc_backend
+            .expect_is_locally_muted()
+            .with(eq(Token::from("2")))
+            .return_const(false);
+        mock_nc_backend
+            .expect_get_room()
+            .with(eq(Token::from("2")))
+            .return_const(mock_room);
+
+        let mut pings = Pings::default();
+        assert_eq!(pings.collect(&mock_nc_backend), vec![(Token::from("2"), 2)]);
+        assert!(pings.collect(&mock_nc_backend).is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_sync() {
+        let mut mock_nc_backend = MockNCTalk::new();
+        let mut seq = mockall::Sequence::new();
+        mock_nc_backend
+            .expect_update_rooms()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|_| Err("Network error, unreachable".into()));
+        mock_nc_backend
+            .expect_update_rooms()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(|_| {
+                Err(Box::new(RoomUpdateError {
+                    failed: vec![("General".to_string(), "Not found".to_string())],
+                    new_rooms: vec!["Added".to_string()],
+                }))
+            });
+
+        assert!(sync_rooms(&mut mock_nc_backend).await.is_empty());
+        assert_eq!(
+            sync_rooms(&mut mock_nc_backend).await,
+            vec!["Added".to_string()]
+        );
+    }
+}
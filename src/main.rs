@@ -55,6 +55,9 @@
 //! With the config read the [`backend::nc_talk::NCTalk`] is created, which first tries to read old chat logs from disk, and the fetches updates from the server.
 //! Note: Currently gaps in the chat history are not filled automatically.
 //! The `NCTalk` instance is holding a list of all [`backend::nc_room::NCRoom`]s.
+//! The UI only talks to it through the [`backend::ChatProvider`] trait, so other chat providers can be added next to it.
+//! An experimental Matrix provider is available behind the ```matrix``` feature, started with ```--matrix```.
+//! It still translates Matrix rooms into NC Talk data and shows them through [`backend::nc_room::NCRoom`].
 //! [`backend::nc_request::nc_requester::NCRequest`] is the thread handler for the API Requests and `NCRequestWorker` does the actual request work to the API.
 //! Responses are parsed using [`serde`] and various structs in [`backend::nc_request`].
 //!
@@ -91,19 +94,274 @@
 mod backend;
 /// Config and Theme Module
 mod config;
+/// Headless Notification Mode
+mod daemon;
 // TUI and Event handling module
 mod ui;
 
-use clap::Parser;
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand};
+use std::io::Write;
 
 /// Argument struct for CLI Args. Using the [`clap`] crate.
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    /// Generate files for packagers or run headless instead of starting the client.
+    #[command(subcommand)]
+    command: Option<Command>,
     /// Path of the configuration File, if omitted will try default config paths.
     /// Default XDG based path is generally encouraged.
     #[arg(short, long, value_name = "PATH", default_value = "")]
     config_path: String,
+    /// Write counters of the requests to the server to this file as JSON, refreshed every few seconds.
+    #[arg(long, value_name = "PATH")]
+    metrics_file: Option<std::path::PathBuf>,
+    /// Write a span of every request to the server to this file, with its endpoint, room token,
+    /// status and duration, to find slow endpoints.
+    #[arg(long, value_name = "PATH")]
+    trace_log: Option<std::path::PathBuf>,
+    /// Connect to a Matrix homeserver instead of Nextcloud Talk. Experimental.
+    #[cfg(feature = "matrix")]
+    #[arg(long)]
+    matrix: bool,
+}
+
+/// Subcommands generating files from the [`Args`] definition or running without the TUI.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the completion script for a shell to stdout.
+    Completions {
+        /// Shell to generate the completions for.
+        shell: clap_complete::Shell,
+    },
+    /// Print the man page in roff format to stdout.
+    Manpage,
+    /// Only send desktop notifications for mentions and direct messages, without the TUI.
+    NotifyDaemon,
+    /// Store the app password of the configured user in the system keyring, read from stdin.
+    StorePassword,
+    /// Join a public conversation as a guest, without an account on its server.
+    /// Only this conversation is synced, the configured accounts are left out.
+    Guest {
+        /// Link to the conversation, like <https://cloud.example.org/call/token>.
+        link: String,
+        /// Ask for the password of the conversation on stdin, if it is protected by one.
+        #[arg(long)]
+        password: bool,
+        /// Name shown to the other participants instead of "Guest".
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Write the stored history of a room to a transcript in the download dir.
+    Export {
+        /// Display name or token of the room.
+        #[arg(long)]
+        room: String,
+        /// Format of the transcript.
+        #[arg(long, value_enum, default_value = "md")]
+        format: backend::export::ExportFormat,
+    },
+    /// Print the stored messages of a room to stdout as JSON lines, e.g. for jq.
+    Dump {
+        /// Display name or token of the room.
+        #[arg(long)]
+        room: String,
+        /// Only print the messages from this day on, like 2024-05-06.
+        #[arg(long)]
+        since: Option<chrono::NaiveDate>,
+    },
+    /// Send a message to a room without the TUI, e.g. from cron jobs or CI scripts.
+    #[command(group(ArgGroup::new("text").required(true).args(["message", "stdin"])))]
+    Send {
+        /// Display name or token of the room.
+        #[arg(long)]
+        room: String,
+        /// Text of the message.
+        #[arg(long)]
+        message: Option<String>,
+        /// Read the text of the message from stdin instead.
+        #[arg(long)]
+        stdin: bool,
+    },
+    /// Control the instance running with the same data dir, for scripts and key bindings.
+    Ctl {
+        /// What the instance is asked to do.
+        #[command(subcommand)]
+        request: ui::control::ControlRequest,
+    },
+}
+
+/// Write the completion script for `shell` to `out`.
+fn generate_completions(shell: clap_complete::Shell, out: &mut impl Write) {
+    let mut cli = Args::command();
+    let name = cli.get_name().to_string();
+    clap_complete::generate(shell, &mut cli, name, out);
+}
+
+/// Write the man page to `out`.
+fn generate_manpage(out: &mut impl Write) -> std::io::Result<()> {
+    clap_mangen::Man::new(Args::command()).render(out)
+}
+
+/// Log in through the browser with Login Flow v2 and store the app password in the config or keyring.
+async fn login(config: &mut config::Config) -> Result<(), Box<dyn std::error::Error>> {
+    let flow = backend::nc_request::nc_login_flow::LoginFlow::start(
+        &config.data.general.url,
+        config.http_client()?,
+    )
+    .await?;
+    println!("No app password configured, please log in at:");
+    println!("{}", flow.login_url());
+    if let Err(why) = ui::open_in_browser(flow.login_url()) {
+        println!("Could not open the browser: {why}");
+    }
+    println!("Waiting for the login to finish...");
+    let credentials = flow.wait_for_credentials().await?;
+    config.store_credentials(&credentials.loginName, &credentials.appPassword)?;
+    println!(
+        "Logged in as {}, the app password was stored.",
+        credentials.loginName
+    );
+    Ok(())
+}
+
+/// Read the password of the conversation from stdin, instead of taking it as an argument.
+fn read_guest_password(link: &str) -> Result<String, Box<dyn std::error::Error>> {
+    println!("Password of {link}:");
+    let mut password = String::new();
+    std::io::stdin().read_line(&mut password)?;
+    Ok(password.trim().to_string())
+}
+
+/// Read the app password from stdin and store it in the system keyring.
+fn store_password(config: &config::Config) -> Result<(), Box<dyn std::error::Error>> {
+    println!(
+        "App password of {} on {}:",
+        config.data.general.user, config.data.general.url
+    );
+    let mut app_pw = String::new();
+    std::io::stdin().read_line(&mut app_pw)?;
+    config.store_app_password(app_pw.trim())?;
+    println!("The app password was stored in the system keyring.");
+    if !config.data.general.app_pw_keyring {
+        println!("Set app_pw_keyring = true in the [general] section of the config to use it.");
+    }
+    Ok(())
+}
+
+/// Export the stored history of the room and print the path of the transcript.
+fn export_room(
+    config: &config::Config,
+    room: &str,
+    format: backend::export::ExportFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let store = backend::message_store::MessageStore::open(&config.get_server_data_dir())?;
+    let path =
+        backend::export::export_stored_room(&store, room, &config.get_download_dir(), format)?;
+    println!("Exported {room} to {}", path.display());
+    Ok(())
+}
+
+/// Print the stored messages of the room since the day as JSON lines to stdout.
+fn dump_room(
+    config: &config::Config,
+    room: &str,
+    since: Option<chrono::NaiveDate>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let store = backend::message_store::MessageStore::open(&config.get_server_data_dir())?;
+    backend::export::dump_stored_room(&store, room, since, &mut std::io::stdout().lock())?;
+    Ok(())
+}
+
+/// Run the subcommands which only need the config, `None` for all others.
+async fn run_without_backend(
+    config: &config::Config,
+    command: Option<&Command>,
+) -> Option<Result<(), Box<dyn std::error::Error>>> {
+    match command? {
+        Command::StorePassword => Some(store_password(config)),
+        Command::Export { room, format } => Some(export_room(config, room, *format)),
+        Command::Dump { room, since } => Some(dump_room(config, room, *since)),
+        Command::Ctl { request } => {
+            let path = ui::control::socket_path(&config.get_data_dir());
+            Some(ui::control::send_request(&path, request).await)
+        }
+        _ => None,
+    }
+}
+
+/// Room and text of the message to send by [`Command::Send`], the text given or read from stdin.
+fn outgoing_message(
+    command: Option<&Command>,
+) -> Result<Option<(String, String)>, Box<dyn std::error::Error>> {
+    let Some(Command::Send {
+        room,
+        message,
+        stdin,
+    }) = command
+    else {
+        return Ok(None);
+    };
+    let text = if *stdin {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        message.clone().unwrap_or_default()
+    };
+    let text = text.trim_end_matches('\n');
+    if text.trim().is_empty() {
+        return Err("The message is empty.".into());
+    }
+    Ok(Some((room.clone(), text.to_string())))
+}
+
+/// Send the message to the room, then leave without starting the TUI.
+async fn send_message(
+    mut backend: impl backend::ChatProvider,
+    room: &str,
+    message: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if backend.is_offline() {
+        return Err("The server cannot be reached.".into());
+    }
+    let token = backend::chat_provider::find_room(&backend, room)
+        .ok_or_else(|| format!("There is no room called {room}."))?;
+    backend.send_message(message, &token).await?;
+    backend.write_to_log()?;
+    backend.shutdown().await
+}
+
+/// Add a backend for each further account, skipping the ones which cannot be used.
+async fn add_accounts(
+    ui: &mut ui::app::App<
+        '_,
+        backend::nc_talk::NCTalk<backend::nc_request::nc_requester::NCRequest>,
+    >,
+    config: &config::Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for (name, server) in &config.data.server {
+        let account_config = config.for_server(name, server);
+        match account_config.get_app_password() {
+            Ok(app_pw) if !app_pw.is_empty() => (),
+            Ok(_) => {
+                log::warn!("No app password configured for the account {name}, skipping it.");
+                continue;
+            }
+            Err(why) => {
+                log::warn!("Could not read the app password of the account {name}: {why}");
+                continue;
+            }
+        }
+        let requester = backend::nc_request::nc_requester::NCRequest::new(&account_config);
+        let backend = match backend::nc_talk::NCTalk::new(requester, &account_config).await {
+            Ok(backend) => backend,
+            Err(why) => {
+                log::warn!("Could not connect the account {name}, skipping it: {why}");
+                continue;
+            }
+        };
+        ui.add_account(backend, &account_config)?;
+    }
+    Ok(())
 }
 
 /// Reads Console [`Args`] and [`config`].
@@ -113,9 +371,59 @@ struct Args {
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
+    match args.command {
+        Some(Command::Completions { shell }) => {
+            generate_completions(shell, &mut std::io::stdout());
+            return Ok(());
+        }
+        Some(Command::Manpage) => return Ok(generate_manpage(&mut std::io::stdout())?),
+        Some(
+            Command::NotifyDaemon
+            | Command::StorePassword
+            | Command::Guest { .. }
+            | Command::Export { .. }
+            | Command::Dump { .. }
+            | Command::Send { .. }
+            | Command::Ctl { .. },
+        )
+        | None => (),
+    }
+    let headless = matches!(args.command, Some(Command::NotifyDaemon));
+    let outgoing = outgoing_message(args.command.as_ref())?;
+
+    let mut config = config::init(&args.config_path)?;
+    config.metrics_file.clone_from(&args.metrics_file);
+    config.trace_log.clone_from(&args.trace_log);
+    if let Some(result) = run_without_backend(&config, args.command.as_ref()).await {
+        return result;
+    }
+    config.config_logging()?;
 
-    let config = config::init(&args.config_path)?;
-    config.config_logging();
+    if let Some(Command::Guest {
+        link,
+        password,
+        name,
+    }) = args.command
+    {
+        use backend::ChatProvider;
+
+        let password = if password {
+            Some(read_guest_password(&link)?)
+        } else {
+            None
+        };
+        let mut config = config.for_guest(&link, password, name)?;
+        let requester = backend::nc_request::nc_requester::NCRequest::new(&config);
+        let backend = backend::nc_talk::NCTalk::new(requester, &config).await?;
+        // The joined room is the only one, whichever default room is configured.
+        config.data.ui.default_room = backend
+            .get_room_keys()
+            .first()
+            .map(|token| backend.get_room(token).to_string())
+            .unwrap_or_default();
+        let mut ui: ui::app::App<'_, _> = ui::app::App::new(backend, &config);
+        return ui.run(&config).await;
+    }
 
     // check if crate has alpha suffix in version
     let pre = env!("CARGO_PKG_VERSION_PRE");
@@ -123,13 +431,68 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         log::warn!("Entering Sechat-rs, please be aware this is {pre} SW!");
     }
 
+    #[cfg(feature = "matrix")]
+    if args.matrix {
+        log::warn!("The Matrix provider is experimental, expect missing features!");
+        let backend = backend::matrix::MatrixTalk::new(&config).await?;
+        if let Some((room, message)) = outgoing {
+            return send_message(backend, &room, message).await;
+        }
+        if headless {
+            return daemon::run(backend, &config).await;
+        }
+        let mut ui: ui::app::App<'_, _> = ui::app::App::new(backend, &config);
+        return ui.run(&config).await;
+    }
+
+    let has_app_password = match config.get_app_password() {
+        Ok(app_pw) => !app_pw.is_empty(),
+        Err(why) => {
+            println!("Could not read the app password: {why}");
+            false
+        }
+    };
+    if !has_app_password {
+        login(&mut config).await?;
+    }
+
     // Create API Wrapper for NC Talk API.
     let requester = backend::nc_request::nc_requester::NCRequest::new(&config);
     // Create Backend
     let backend = backend::nc_talk::NCTalk::new(requester, &config).await?;
+    if let Some((room, message)) = outgoing {
+        return send_message(backend, &room, message).await;
+    }
+    if headless {
+        return daemon::run(backend, &config).await;
+    }
     // Create UI
     let mut ui: ui::app::App<'_, _> = ui::app::App::new(backend, &config);
 
+    add_accounts(&mut ui, &config).await?;
+
     // Enter loop and run UI.
     ui.run(&config).await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_files() {
+        Args::command().debug_assert();
+
+        let mut completions = vec![];
+        generate_completions(clap_complete::Shell::Bash, &mut completions);
+        assert!(String::from_utf8(completions)
+            .unwrap()
+            .contains("sechat-rs"));
+
+        let mut manpage = vec![];
+        generate_manpage(&mut manpage).unwrap();
+        assert!(String::from_utf8(manpage)
+            .unwrap()
+            .contains(".TH sechat-rs"));
+    }
+}
@@ -14,36 +14,130 @@
 //!
 //! The [``run``](crate::ui::app::App::run) method does the ui setup, through the [``init``] function,
 //! and then calls [``run_ui``](crate::ui::app::App::run_app) to execute the main loop.
-//! the main loop ether waits for a key event. Should now event ocure for 3 seconds a update from the remote server is fetched.
+//! the main loop ether waits for a key event. All rooms are synced with the server on the schedule of the
+//! [``IdleSyncSchedule``](crate::ui::idle_sync_schedule::IdleSyncSchedule), slower while the user is idle or the terminal unfocused.
+//! Messages held back in the [``outbox``](crate::ui::outbox) are dispatched once they are due.
 use crate::{
-    backend::{nc_request::Token, nc_room::NCRoomInterface, nc_talk::NCBackend},
-    config::Config,
-    ui::terminal_helpers::{init, install_hooks, restore},
+    backend::{
+        chat_message::ChatMessage,
+        chat_provider::{find_room, BackendUpdate, RoomUpdateError},
+        chat_types::RoomType,
+        export::{write_export, ExportFormat},
+        ChatProvider, ChatRoom, Token,
+    },
+    config::{mark_problems_seen, unseen_problems, Config},
+    ui::control::{socket_path, ControlCall, ControlRequest, ControlSocket},
+    ui::outbox::{Outbox, OutgoingMessage},
+    ui::terminal_helpers::{
+        copy_to_clipboard, edit_externally, init, install_hooks, open_in_browser, restore,
+        ExitSignals,
+    },
     ui::widget::{
-        chat_box::ChatBox, chat_selector::ChatSelector, help_box::HelpBox, input_box::InputBox,
-        title_bar::TitleBar, users::Users,
+        chat_box::ChatBox,
+        chat_selector::ChatSelector,
+        command_palette::{Command, CommandPalette},
+        date_jump::DateJump,
+        diagnostics::Diagnostics,
+        dump_browser::DumpBrowser,
+        help_box::HelpBox,
+        inbox::Inbox,
+        input_box::InputBox,
+        invite_box::InviteBox,
+        key_hints::KeyHints,
+        lobby_banner::LobbyBanner,
+        message_menu::{MessageAction, MessageMenu, QUICK_REACTIONS, REMINDER_HOURS},
+        message_search::MessageSearch,
+        message_view::MessageView,
+        participant_menu::{ParticipantAction, ParticipantMenu},
+        preview_banner::PreviewBanner,
+        quick_switcher::QuickSwitcher,
+        room_browser::RoomBrowser,
+        room_info::RoomInfo,
+        room_settings::RoomSettings,
+        spell_check::SpellSuggestions,
+        status_form::StatusForm,
+        title_bar::TitleBar,
+        toast::Toast,
+        users::Users,
     },
 };
+use chrono::{Local, NaiveDate, NaiveTime, Utc};
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Flex, Layout, Position},
+    layout::{Alignment, Constraint, Direction, Flex, Layout, Position, Rect},
     style::{Style, Stylize},
-    widgets::{Block, Clear, Paragraph},
+    text::Line,
+    widgets::{Block, Clear, Paragraph, Wrap},
     Frame, Terminal,
 };
+use std::{
+    convert::TryFrom,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 use strum_macros::Display;
 
-use tui_textarea::Input;
+use tui_textarea::{CursorMove, Input};
 
 use crossterm::event::{
-    poll, read, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseEventKind,
+    Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseEventKind,
 };
+use futures::StreamExt;
+use tokio::{sync::mpsc::UnboundedReceiver, time::MissedTickBehavior};
 use tui_textarea::Key;
 
-use super::{notifications::NotifyWrapper, widget::logger::LogBox};
+use super::{
+    idle_sync_schedule::{IdleSyncSchedule, FETCH_INTERVAL},
+    keys,
+    notifications::NotifyWrapper,
+    paste,
+    widget::logger::LogBox,
+};
+
+/// Lines the chat moves per step of the mouse wheel.
+const WHEEL_LINES: usize = 3;
+/// Longest time between the clicks of a double click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(500);
+/// Narrowest and widest user sidebar in percent.
+const SIDEBAR_PERCENTS: std::ops::RangeInclusive<u16> = 10..=50;
+/// Number of rooms remembered to go back to.
+const ROOM_HISTORY_LENGTH: usize = 50;
+
+/// Timer ticking every `period`, the first time after one period.
+/// Without a period the timer is never polled, the period it gets does not matter then.
+fn optional_timer(period: Option<Duration>) -> tokio::time::Interval {
+    let period = period.unwrap_or(FETCH_INTERVAL);
+    let mut timer = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    timer
+}
 
 enum ProcessEventResult {
     Continue,
     Exit,
+    /// Edit the draft in an external editor, which needs the terminal.
+    EditExternally,
+}
+
+/// Everything the main loop waits for, from the terminal, the backend and the timers.
+enum AppEvent {
+    Terminal(Event),
+    /// Messages pushed by the long poll or fetched as history.
+    Backend(BackendUpdate),
+    Fetch,
+    OutboxDue,
+    /// A toast has been shown long enough and is removed by the next draw.
+    ToastDue,
+    Autosave,
+    /// The participants of the open room are due to be fetched again for their status.
+    PresenceRefresh,
+    /// The process was asked to end by the named signal.
+    Signal(&'static str),
+    /// The notification about new messages in the room was activated.
+    OpenRoom(Token),
+    /// A notification to show in the TUI, as there is no notification service.
+    Banner(String),
+    /// A request from `sechat-rs ctl` on the control socket.
+    Control(ControlCall),
 }
 
 #[derive(PartialEq, Clone, Copy, Display)]
@@ -52,15 +146,67 @@ pub enum CurrentScreen {
     Opening,
     Editing,
     Logging,
+    Users,
+    Browsing,
+    Zoomed,
+    Dumps,
+    Inbox,
+}
+
+/// Actions which can be applied to all rooms marked in the [`ChatSelector`].
+#[derive(PartialEq, Clone, Copy, Display)]
+pub enum BulkAction {
+    MarkRead,
+    Mute,
+    Archive,
+    Favorite,
 }
 
 #[derive(PartialEq, Clone, Copy, Display)]
 pub enum Popup {
     Help,
     Exit,
+    Invite,
+    Search,
+    JumpToDate,
+    QuickSwitcher,
+    CommandPalette,
+    SpellSuggestions,
+    ParticipantActions,
+    ConfirmParticipantAction(ParticipantAction),
+    ConfirmSend,
+    RoomSettings,
+    Status,
+    MessageActions,
+    ConfirmDeleteMessage,
+    ConfirmShare,
+    RoomInfo,
+    Diagnostics,
+    Export,
+    Error,
+}
+
+/// What the input is composed for, set through the [`MessageMenu`].
+#[derive(PartialEq, Clone, Copy)]
+enum Compose {
+    Message,
+    Reply(i32),
+    Edit(i32),
+}
+
+/// Account of the general section or of a `[server.<name>]` section of the config.
+struct Account<Backend> {
+    name: String,
+    /// User id of the account, used to find own messages.
+    user: String,
+    /// Room shown when switching to the account.
+    room: Token,
+    /// Backend of the account, `None` while the account is shown and the [`App`] holds it.
+    backend: Option<Backend>,
 }
 
-pub struct App<'a, Backend: NCBackend> {
+#[allow(clippy::struct_excessive_bools)]
+pub struct App<'a, Backend: ChatProvider> {
     pub current_screen: CurrentScreen, // the current screen the user is looking at, and will later determine what is rendered.
     popup: Option<Popup>,
     backend: Backend,
@@ -68,20 +214,107 @@ pub struct App<'a, Backend: NCBackend> {
     chat: ChatBox<'a>,
     pub selector: ChatSelector<'a>,
     input: InputBox<'a>,
+    lobby_banner: LobbyBanner,
+    preview_banner: PreviewBanner,
     help: HelpBox,
+    /// Key hints at the bottom, `None` if hidden in the config.
+    key_hints: Option<KeyHints>,
     users: Users<'a>,
+    invite: InviteBox<'a>,
+    message_search: MessageSearch<'a>,
+    date_jump: DateJump<'a>,
+    quick_switcher: QuickSwitcher<'a>,
+    command_palette: CommandPalette<'a>,
+    spell_suggestions: SpellSuggestions,
+    participant_menu: ParticipantMenu,
+    room_settings: RoomSettings<'a>,
+    status_form: StatusForm<'a>,
+    room_browser: RoomBrowser<'a>,
+    dump_browser: DumpBrowser,
+    inbox: Inbox,
+    /// Dump of the last response which could not be parsed, opened by 'D'.
+    last_dump: Option<PathBuf>,
+    room_info: RoomInfo,
+    diagnostics: Diagnostics,
+    /// File the request metrics are written to on every fetch, see [`Config::metrics_file`].
+    metrics_file: Option<PathBuf>,
+    message_menu: MessageMenu,
+    message_view: MessageView,
+    message_menu_title: String,
     logging: LogBox,
+    toast: Toast,
+    /// Notifications shown in the top right corner when there is no notification service.
+    banners: Toast,
     user_sidebar_visible: bool,
+    /// Width of the user sidebar in percent.
+    sidebar_percent: u16,
     default_style: Style,
     popup_border_style: Style,
     current_room_token: Token,
+    /// Rooms opened before the current one, the last one most recently.
+    rooms_back: Vec<Token>,
+    /// Rooms left by going back, the last one most recently.
+    rooms_forward: Vec<Token>,
     notify: NotifyWrapper,
+    /// Tokens of the rooms whose notifications were activated, to open them.
+    notification_actions: UnboundedReceiver<Token>,
+    /// Notifications to show as [`Self::banners`].
+    notification_banners: UnboundedReceiver<String>,
+    /// Rooms with unread mentions, collected when the exit popup is opened.
+    exit_mention_rooms: Vec<Token>,
+    /// Text of the error popup, describing the last error which was not handled otherwise.
+    error_text: String,
+    /// Display names of rooms in which sending needs to be confirmed.
+    confirm_send_rooms: Vec<String>,
+    /// Image file named by the pasted text, shared once confirmed, and the text itself.
+    pasted_image: Option<(PathBuf, String)>,
+    /// Enter sends and Shift or Alt with Enter starts a new line, or the other way around.
+    enter_sends: bool,
+    /// The terminal tells Shift+Enter from Enter, otherwise Ctrl+j stands in for it.
+    keyboard_enhanced: bool,
+    /// Notifications are held back until toggled off again.
+    do_not_disturb: bool,
+    /// Set the Nextcloud status to do not disturb while [`Self::do_not_disturb`] is on.
+    sync_dnd_status: bool,
+    /// Status the user had before do not disturb set it, restored when toggled off.
+    status_before_dnd: Option<String>,
+    /// Display names of rooms which are not marked as read when sending.
+    private_read_rooms: Vec<String>,
+    outbox: Outbox,
+    /// User id of the logged in user, used to find own messages.
+    own_user: String,
+    compose: Compose,
+    /// Message text waiting for a target room to be forwarded to.
+    forward_message: Option<String>,
+    /// All accounts, the one shown is at `active_account`.
+    accounts: Vec<Account<Backend>>,
+    active_account: usize,
+    /// Directory room exports are written to.
+    download_dir: PathBuf,
+    /// How often rooms and messages are written to disk, `None` to only write them on exit.
+    autosave_interval: Option<Duration>,
+    /// How often the participants of the open group room are fetched, `None` to not refresh them.
+    presence_interval: Option<Duration>,
+    /// When all rooms are synced next.
+    sync_schedule: IdleSyncSchedule,
+    /// Whether the terminal has focus, `None` until it reports a focus change.
+    focused: Option<bool>,
+    /// Count typed in Reading mode before a motion, like "5j" in vim.
+    key_count: Option<usize>,
+    /// A first 'g' was typed, a second one selects the first message.
+    pending_g: bool,
+    /// Time and message of the last click into the chat, to detect double clicks.
+    last_click: Option<(Instant, Option<i32>)>,
+    /// Config of the app, changes of the layout are written back into its file.
+    config: Config,
 }
 
-impl<Backend: NCBackend> App<'_, Backend> {
+impl<Backend: ChatProvider> App<'_, Backend> {
     pub fn new(backend: Backend, config: &Config) -> Self {
         let init_room = backend.get_room_by_displayname(config.data.ui.default_room.as_str());
-        let notify = NotifyWrapper::new(config);
+        let mut notify = NotifyWrapper::new(config);
+        let notification_actions = notify.open_room_actions();
+        let notification_banners = notify.fallback_banners();
 
         Self {
             current_screen: CurrentScreen::Reading,
@@ -89,6 +322,8 @@ impl<Backend: NCBackend> App<'_, Backend> {
             title: TitleBar::new(CurrentScreen::Reading, config),
             selector: ChatSelector::new(&backend, config),
             input: InputBox::new("", config),
+            lobby_banner: LobbyBanner::new(config),
+            preview_banner: PreviewBanner::new(config),
             chat: {
                 let mut chat = ChatBox::new(config);
                 chat.update_messages(&backend, &init_room);
@@ -100,32 +335,190 @@ impl<Backend: NCBackend> App<'_, Backend> {
                 users.update(&backend, &init_room);
                 users
             },
+            invite: InviteBox::new(config),
+            message_search: MessageSearch::new(config),
+            date_jump: DateJump::new(config),
+            quick_switcher: QuickSwitcher::new(config),
+            command_palette: CommandPalette::new(config),
+            spell_suggestions: SpellSuggestions::new(config),
+            participant_menu: ParticipantMenu::new(config),
+            room_settings: RoomSettings::new(config),
+            status_form: StatusForm::new(config),
+            room_browser: RoomBrowser::new(config),
+            dump_browser: DumpBrowser::new(config),
+            inbox: Inbox::new(config),
+            last_dump: None,
+            room_info: RoomInfo::new(config),
+            diagnostics: Diagnostics::new(config),
+            metrics_file: config.metrics_file.clone(),
+            message_menu: MessageMenu::new(config),
+            message_view: MessageView::new(config),
+            message_menu_title: String::new(),
             logging: LogBox::new(config),
+            toast: Toast::new(config),
+            banners: Toast::banners(config),
             backend,
             help: HelpBox::new(config),
+            key_hints: (!config.data.ui.hide_key_hints).then(|| KeyHints::new(config)),
             user_sidebar_visible: config.data.ui.user_sidebar_default,
+            sidebar_percent: config
+                .data
+                .ui
+                .sidebar_percent
+                .clamp(*SIDEBAR_PERCENTS.start(), *SIDEBAR_PERCENTS.end()),
             default_style: config.theme.default_style(),
             popup_border_style: config.theme.popup_border_style(),
             current_room_token: init_room,
+            rooms_back: vec![],
+            rooms_forward: vec![],
             notify,
+            notification_actions,
+            notification_banners,
+            exit_mention_rooms: vec![],
+            error_text: String::new(),
+            confirm_send_rooms: config.data.ui.confirm_send_rooms.clone(),
+            pasted_image: None,
+            enter_sends: config.data.ui.enter_sends,
+            keyboard_enhanced: true,
+            do_not_disturb: false,
+            sync_dnd_status: config.data.notifications.sync_dnd_status,
+            status_before_dnd: None,
+            private_read_rooms: config.data.general.private_read_rooms.clone(),
+            outbox: Outbox::new(config),
+            own_user: config.data.general.user.clone(),
+            compose: Compose::Message,
+            forward_message: None,
+            accounts: vec![Account {
+                name: config.data.general.chat_server_name.clone(),
+                user: config.data.general.user.clone(),
+                room: Token::new(),
+                backend: None,
+            }],
+            active_account: 0,
+            download_dir: config.get_download_dir(),
+            autosave_interval: (config.data.general.autosave_seconds > 0)
+                .then(|| Duration::from_secs(config.data.general.autosave_seconds)),
+            presence_interval: (config.data.ui.presence_refresh_seconds > 0)
+                .then(|| Duration::from_secs(config.data.ui.presence_refresh_seconds)),
+            sync_schedule: IdleSyncSchedule::new(config, Instant::now()),
+            focused: None,
+            key_count: None,
+            pending_g: false,
+            last_click: None,
+            config: config.clone(),
         }
     }
 
+    /// Add the account of a `[server.<name>]` section, `config` being the one of that account.
+    pub fn add_account(
+        &mut self,
+        backend: Backend,
+        config: &Config,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // The default room may only exist on one of the servers, so fall back to any room.
+        let rooms = backend.get_room_keys();
+        let room = rooms
+            .iter()
+            .find(|token| backend.get_room(token).get_display_name() == config.data.ui.default_room)
+            .or_else(|| rooms.first())
+            .map(|token| (*token).clone())
+            .unwrap_or_default();
+        self.accounts.push(Account {
+            name: config.data.general.chat_server_name.clone(),
+            user: config.data.general.user.clone(),
+            room,
+            backend: Some(backend),
+        });
+        self.show_accounts();
+        self.selector.update(&self.backend)
+    }
+
+    /// List the accounts in the chat selector and title bar, if there is more than one.
+    fn show_accounts(&mut self) {
+        self.selector.set_accounts(
+            self.accounts
+                .iter()
+                .map(|account| account.name.clone())
+                .collect(),
+            self.active_account,
+        );
+        self.title.set_account(
+            (self.accounts.len() > 1).then(|| self.accounts[self.active_account].name.clone()),
+        );
+    }
+
+    /// Show the account at `index`, sending the held back messages of the current account first.
+    pub async fn switch_account(&mut self, index: usize) -> Result<(), Box<dyn std::error::Error>> {
+        if index == self.active_account || index >= self.accounts.len() {
+            return Ok(());
+        }
+        let pending = self.outbox.take_all();
+        self.dispatch_messages(pending).await?;
+        if self.accounts[index].backend.is_none() {
+            return Ok(());
+        }
+        self.leave_current_room().await;
+        let Some(backend) = self.accounts[index].backend.take() else {
+            return Ok(());
+        };
+        let previous = &mut self.accounts[self.active_account];
+        previous.backend = Some(std::mem::replace(&mut self.backend, backend));
+        previous.room = std::mem::take(&mut self.current_room_token);
+        self.active_account = index;
+        let account = &self.accounts[index];
+        log::info!("Switched to the account {}", account.name);
+        self.own_user.clone_from(&account.user);
+        let room = account.room.clone();
+        self.forward_message = None;
+        self.selector.marked.clear();
+        self.rooms_back.clear();
+        self.rooms_forward.clear();
+        self.show_accounts();
+        self.backend.update_rooms(false).await?;
+        self.refresh_own_status().await;
+        self.open_room(room).await
+    }
+
+    /// Switch to the account after the current one, starting over after the last.
+    async fn switch_to_next_account(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.switch_account((self.active_account + 1) % self.accounts.len())
+            .await
+    }
+
     pub async fn run(&mut self, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
         install_hooks(config)?;
 
-        let tui = init(config.get_enable_mouse(), config.get_enable_paste())
-            .expect("Could not Create TUI Backend.");
+        let (tui, keyboard_enhanced) = init(
+            config.get_enable_mouse(),
+            config.get_enable_paste(),
+            config.get_report_all_keys(),
+        )
+        .expect("Could not Create TUI Backend.");
+        self.set_keyboard_enhanced(keyboard_enhanced);
 
         log::debug!("crossterm setup done.");
 
+        if let Some(private) = config.data.general.read_status_private {
+            if let Err(why) = self.backend.set_read_status_private(private).await {
+                log::warn!("Could not set read status privacy: {why}");
+            }
+        }
+
+        self.refresh_own_status().await;
+
         // create app and run it
         self.run_app(tui).await?;
 
         log::info!("Shutting Down.");
 
+        self.leave_current_room().await;
         // Kill worker threads.
         self.backend.shutdown().await?;
+        for account in &mut self.accounts {
+            if let Some(backend) = account.backend.as_mut() {
+                backend.shutdown().await?;
+            }
+        }
 
         restore(config.get_enable_mouse(), config.get_enable_paste())?;
 
@@ -135,17 +528,35 @@ impl<Backend: NCBackend> App<'_, Backend> {
     pub fn ui(&mut self, f: &mut Frame) {
         let base_layout = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Length(2), Constraint::Min(1)])
+            .constraints([
+                Constraint::Length(2),
+                Constraint::Min(1),
+                Constraint::Length(u16::from(self.key_hints.is_some())),
+            ])
             .split(f.area());
 
         if self.current_screen == CurrentScreen::Opening {
             self.selector.render_area(f, base_layout[1]);
         } else if self.current_screen == CurrentScreen::Logging {
             self.logging.render_area(f, base_layout[1]);
+        } else if self.current_screen == CurrentScreen::Browsing {
+            self.room_browser.render_area(f, base_layout[1]);
+        } else if self.current_screen == CurrentScreen::Zoomed {
+            self.message_view.render_area(f, base_layout[1]);
+        } else if self.current_screen == CurrentScreen::Dumps {
+            self.dump_browser.render_area(f, base_layout[1]);
+        } else if self.current_screen == CurrentScreen::Inbox {
+            self.inbox.render_area(f, base_layout[1]);
         } else {
+            let room = self.backend.get_room(&self.current_room_token);
+            let input_height = if room.is_lobby_closed() || room.is_previewing() {
+                3
+            } else {
+                self.input.height()
+            };
             let main_layout = Layout::default()
                 .direction(Direction::Vertical)
-                .constraints([Constraint::Min(4), Constraint::Length(3)])
+                .constraints([Constraint::Min(4), Constraint::Length(input_height)])
                 .split(base_layout[1]);
 
             if self.user_sidebar_visible
@@ -153,13 +564,17 @@ impl<Backend: NCBackend> App<'_, Backend> {
             {
                 let chat_layout = Layout::default()
                     .direction(Direction::Horizontal)
-                    .constraints([Constraint::Percentage(80), Constraint::Percentage(20)])
+                    .constraints([
+                        Constraint::Percentage(100 - self.sidebar_percent),
+                        Constraint::Percentage(self.sidebar_percent),
+                    ])
                     .split(main_layout[0]);
                 self.chat.set_width_and_update_if_change(
                     chat_layout[0].width,
                     &self.backend,
                     &self.current_room_token,
                 );
+                self.chat.set_area(chat_layout[0]);
                 self.chat.render_area(f, chat_layout[0]);
                 self.users.render_area(f, chat_layout[1]);
             } else {
@@ -168,315 +583,2173 @@ impl<Backend: NCBackend> App<'_, Backend> {
                     &self.backend,
                     &self.current_room_token,
                 );
+                self.chat.set_area(main_layout[0]);
                 self.chat.render_area(f, main_layout[0]);
             };
 
-            self.input.render_area(f, main_layout[1]);
+            let room = self.backend.get_room(&self.current_room_token);
+            if room.is_lobby_closed() {
+                self.lobby_banner
+                    .render_area(f, main_layout[1], room.get_lobby_timer());
+            } else if room.is_previewing() {
+                self.preview_banner.render_area(f, main_layout[1]);
+            } else {
+                self.input.render_area(f, main_layout[1]);
+            }
         }
         self.title
             .update(self.current_screen, &self.backend, &self.current_room_token);
+        self.title.set_outbox_status(self.outbox.len());
+        if self.current_screen == CurrentScreen::Logging {
+            mark_problems_seen();
+        }
+        self.title.set_log_status(unseen_problems());
         self.title.render_area(f, base_layout[0]);
+        if let Some(key_hints) = &self.key_hints {
+            key_hints.render_area(f, base_layout[2], self.current_screen);
+        }
+        self.toast.expire(Instant::now());
+        self.toast.render_area(f, base_layout[1]);
+        self.banners.expire(Instant::now());
+        self.banners.render_area(f, base_layout[1]);
         if let Some(popup) = self.popup {
-            let (horizontal, vertical) = match popup {
-                Popup::Help => (Constraint::Length(130), Constraint::Length(14)),
-                Popup::Exit => (Constraint::Length(40), Constraint::Length(3)),
-            };
-            let [area] = Layout::horizontal([horizontal])
-                .flex(Flex::Center)
-                .areas(base_layout[1]);
-            let [area] = Layout::vertical([vertical]).flex(Flex::Center).areas(area);
-            f.render_widget(Clear, area);
-            match popup {
-                Popup::Help => self.help.render_area(f, area),
-                Popup::Exit => f.render_widget(
-                    Paragraph::new("To Quit Press 'y', to stay 'n'")
-                        .alignment(Alignment::Center)
-                        .style(self.default_style.bold())
-                        .block(
-                            Block::bordered()
-                                .title("Exit?")
-                                .border_style(self.popup_border_style),
-                        ),
-                    area,
-                ),
+            self.render_popup(f, popup, base_layout[1]);
+        }
+    }
+
+    /// Width and height of the popup.
+    fn popup_size(&self, popup: Popup) -> (Constraint, Constraint) {
+        match popup {
+            Popup::Help => (
+                Constraint::Length(130),
+                Constraint::Length(HelpBox::height()),
+            ),
+            Popup::Exit if self.exit_mention_rooms.is_empty() => {
+                (Constraint::Length(40), Constraint::Length(3))
             }
+            Popup::Exit => (Constraint::Length(60), Constraint::Length(4)),
+            Popup::Invite => (Constraint::Length(60), Constraint::Length(15)),
+            Popup::Search => (Constraint::Length(100), Constraint::Length(20)),
+            Popup::JumpToDate => (Constraint::Length(40), Constraint::Length(3)),
+            Popup::QuickSwitcher => (Constraint::Length(60), Constraint::Length(20)),
+            Popup::CommandPalette => (Constraint::Length(50), Constraint::Length(20)),
+            Popup::SpellSuggestions => (
+                Constraint::Length(40),
+                Constraint::Length(self.spell_suggestions.height()),
+            ),
+            Popup::ParticipantActions => (
+                Constraint::Length(50),
+                Constraint::Length(self.participant_menu.height()),
+            ),
+            Popup::ConfirmParticipantAction(_)
+            | Popup::ConfirmDeleteMessage
+            | Popup::ConfirmShare
+            | Popup::Export => (Constraint::Length(60), Constraint::Length(3)),
+            Popup::ConfirmSend => (
+                Constraint::Length(80),
+                Constraint::Length(self.send_preview_height()),
+            ),
+            Popup::RoomSettings => (
+                Constraint::Length(60),
+                Constraint::Length(self.room_settings.height()),
+            ),
+            Popup::Status => (Constraint::Length(60), Constraint::Length(6)),
+            Popup::MessageActions => (
+                Constraint::Length(30),
+                Constraint::Length(self.message_menu.height()),
+            ),
+            Popup::RoomInfo => (
+                Constraint::Length(60),
+                Constraint::Length(self.room_info.height()),
+            ),
+            Popup::Diagnostics => (
+                Constraint::Length(50),
+                Constraint::Length(self.diagnostics.height()),
+            ),
+            Popup::Error => (Constraint::Length(70), Constraint::Length(6)),
         }
     }
 
-    pub async fn mark_current_as_read(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.backend
-            .mark_current_room_as_read(&self.current_room_token)
-            .await?;
-        self.notify
-            .maybe_notify_new_rooms(self.backend.update_rooms(true).await?)?;
-        self.update_ui()?;
-        Ok(())
+    /// Render the popup centered into the given area.
+    fn render_popup(&self, f: &mut Frame, popup: Popup, base_area: Rect) {
+        let (horizontal, vertical) = self.popup_size(popup);
+        let [area] = Layout::horizontal([horizontal])
+            .flex(Flex::Center)
+            .areas(base_area);
+        let [area] = Layout::vertical([vertical]).flex(Flex::Center).areas(area);
+        f.render_widget(Clear, area);
+        match popup {
+            Popup::Help => self.help.render_area(f, area),
+            Popup::Exit => f.render_widget(
+                Paragraph::new(self.exit_text())
+                    .alignment(Alignment::Center)
+                    .style(self.default_style.bold())
+                    .block(
+                        Block::bordered()
+                            .title("Exit?")
+                            .border_style(self.popup_border_style),
+                    ),
+                area,
+            ),
+            Popup::Invite => self.invite.render_area(f, area),
+            Popup::Search => self.message_search.render_area(f, area),
+            Popup::JumpToDate => self.date_jump.render_area(f, area),
+            Popup::QuickSwitcher => self.quick_switcher.render_area(f, area),
+            Popup::CommandPalette => self.command_palette.render_area(f, area),
+            Popup::SpellSuggestions => self.spell_suggestions.render_area(f, area),
+            Popup::RoomSettings => self.room_settings.render_area(f, area),
+            Popup::Status => self.status_form.render_area(f, area),
+            Popup::RoomInfo => self.room_info.render_area(f, area),
+            Popup::Diagnostics => self.diagnostics.render_area(f, area),
+            Popup::MessageActions => {
+                self.message_menu
+                    .render_area(f, area, &self.message_menu_title);
+            }
+            Popup::Export => f.render_widget(
+                Paragraph::new("'m' markdown, 'h' HTML, 'j' JSON, 'n' cancel")
+                    .alignment(Alignment::Center)
+                    .style(self.default_style.bold())
+                    .block(
+                        Block::bordered()
+                            .title("Export the room history?")
+                            .border_style(self.popup_border_style),
+                    ),
+                area,
+            ),
+            Popup::ConfirmDeleteMessage => f.render_widget(
+                Paragraph::new("Delete the selected message? 'y' yes, 'n' no")
+                    .alignment(Alignment::Center)
+                    .style(self.default_style.bold())
+                    .block(
+                        Block::bordered()
+                            .title("Delete?")
+                            .border_style(self.popup_border_style),
+                    ),
+                area,
+            ),
+            Popup::ConfirmShare => self.render_confirm_share(f, area),
+            Popup::ParticipantActions => self.participant_menu.render_area(
+                f,
+                area,
+                self.users
+                    .selected_attendee()
+                    .map_or("", |(_, name)| name.as_str()),
+            ),
+            Popup::ConfirmSend => f.render_widget(
+                Paragraph::new(self.input.lines().join("\n"))
+                    .wrap(Wrap { trim: false })
+                    .style(self.default_style)
+                    .block(
+                        Block::bordered()
+                            .title(self.send_confirmation_title())
+                            .title_bottom("'y' send, 'n' keep editing")
+                            .border_style(self.popup_border_style),
+                    ),
+                area,
+            ),
+            Popup::Error => f.render_widget(
+                Paragraph::new(self.error_text.as_str())
+                    .wrap(Wrap { trim: true })
+                    .style(self.default_style)
+                    .block(
+                        Block::bordered()
+                            .title("Error")
+                            .title_bottom("Esc dismiss, 'L' logs")
+                            .border_style(self.popup_border_style),
+                    ),
+                area,
+            ),
+            Popup::ConfirmParticipantAction(action) => f.render_widget(
+                Paragraph::new(self.confirm_participant_text(action))
+                    .alignment(Alignment::Center)
+                    .style(self.default_style.bold())
+                    .block(
+                        Block::bordered()
+                            .title(format!("{action}?"))
+                            .border_style(self.popup_border_style),
+                    ),
+                area,
+            ),
+        }
     }
 
-    pub async fn mark_all_as_read(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.backend.mark_all_rooms_as_read().await?;
-        self.notify
-            .maybe_notify_new_rooms(self.backend.update_rooms(true).await?)?;
-        self.update_ui()?;
-        Ok(())
+    /// Ask whether to share the image file whose path was pasted.
+    fn render_confirm_share(&self, f: &mut Frame, area: Rect) {
+        let name = self
+            .pasted_image
+            .as_ref()
+            .map_or_else(String::new, |(path, _)| paste::file_name(path));
+        f.render_widget(
+            Paragraph::new("'y' share the file, 'n' paste the path")
+                .alignment(Alignment::Center)
+                .style(self.default_style.bold())
+                .block(
+                    Block::bordered()
+                        .title(format!("Share {name} in the room?"))
+                        .border_style(self.popup_border_style),
+                ),
+            area,
+        );
     }
 
-    fn update_ui(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.title
-            .update(self.current_screen, &self.backend, &self.current_room_token);
-        self.selector.update(&self.backend)?;
-        self.chat
-            .update_messages(&self.backend, &self.current_room_token);
-        self.users.update(&self.backend, &self.current_room_token);
-        Ok(())
+    /// Height of the send preview, fitting the wrapped message up to a maximum.
+    fn send_preview_height(&self) -> u16 {
+        let lines: usize = self
+            .input
+            .lines()
+            .iter()
+            .map(|line| line.chars().count() / 78 + 1)
+            .sum();
+        u16::try_from(lines + 2).unwrap_or(u16::MAX).min(20)
     }
 
-    pub async fn send_message(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        if self.input.is_empty() {
-            Ok(())
+    /// Check if the message in the input mentions everyone in the room.
+    fn input_mentions_all(&self) -> bool {
+        self.input.lines().iter().any(|line| {
+            line.split_whitespace().any(|word| {
+                matches!(
+                    word.trim_end_matches(|c: char| c.is_ascii_punctuation()),
+                    "@all" | "@here"
+                )
+            })
+        })
+    }
+
+    /// Check if the current room is configured to confirm messages before sending,
+    /// or if the message mentions everyone in the room.
+    fn needs_send_confirmation(&self) -> bool {
+        let name = self
+            .backend
+            .get_room(&self.current_room_token)
+            .get_display_name();
+        !self.input.is_empty()
+            && (self.input_mentions_all()
+                || self.confirm_send_rooms.iter().any(|room| room == name))
+    }
+
+    /// Title of the send confirmation, warning about mentions of everyone in the room.
+    fn send_confirmation_title(&self) -> String {
+        let room = self.backend.get_room(&self.current_room_token);
+        let name = room.get_display_name();
+        if !self.input_mentions_all() {
+            format!("Send to {name}?")
+        } else if room.is_mention_all_restricted() && !room.is_moderator() {
+            format!("Send to {name}? Only moderators may mention @all, nobody gets notified")
         } else {
-            self.notify.maybe_notify_new_message(
-                self.backend
-                    .send_message(self.input.lines().join("\n"), &self.current_room_token)
-                    .await?,
-            )?;
-            self.input.select_all();
-            self.input.cut();
-            self.input.select_all();
-            self.update_ui()?;
-            self.chat.select_last_message();
-            Ok(())
+            format!(
+                "Send to {name}? This notifies all {} participants",
+                room.get_users().len()
+            )
         }
     }
 
-    pub async fn select_room(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        if self.selector.state.selected().len() == if self.selector.searching { 1 } else { 2 } {
-            self.current_room_token.clone_from(
-                self.selector
-                    .state
-                    .selected()
-                    .last()
-                    .expect("no selection available"),
-            );
-            self.notify.maybe_notify_new_message(
-                self.backend.select_room(&self.current_room_token).await?,
-            )?;
-            self.selector.searching = false;
-            self.switch_screen(CurrentScreen::Reading);
-            self.update_ui()?;
-            self.chat.select_last_message();
-        } else {
-            self.selector.state.toggle_selected();
+    /// Check if the lobby of the current room keeps the user from writing.
+    fn is_current_lobby_closed(&self) -> bool {
+        self.backend
+            .get_room(&self.current_room_token)
+            .is_lobby_closed()
+    }
+
+    /// Join a session in the current room if it is only previewed, keeping the preview on failure.
+    async fn join_current_room_if_previewing(&mut self) {
+        if !self
+            .backend
+            .get_room(&self.current_room_token)
+            .is_previewing()
+        {
+            return;
+        }
+        if let Err(why) = self.backend.join_room(&self.current_room_token).await {
+            log::warn!("Failed to join room {}: {why}", self.current_room_token);
         }
-        Ok(())
     }
 
-    pub async fn fetch_updates(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.backend.update_rooms(false).await?;
-        self.update_ui()?;
-        Ok(())
+    /// Leave the session joined in the current room, so the server does not keep us in it.
+    async fn leave_current_room(&mut self) {
+        if self.current_room_token.is_empty() {
+            return;
+        }
+        if let Err(why) = self.backend.leave_room(&self.current_room_token).await {
+            log::warn!("Failed to leave room {}: {why}", self.current_room_token);
+        }
     }
 
-    pub async fn fetch_current_room_history(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.backend
-            .fetch_room_history(&self.current_room_token)
-            .await?;
-        self.chat.select_last_message();
-        Ok(())
+    /// Start writing in the current room, joining it first if it is only previewed.
+    async fn start_editing(&mut self) {
+        self.join_current_room_if_previewing().await;
+        if !self
+            .backend
+            .get_room(&self.current_room_token)
+            .is_previewing()
+        {
+            self.switch_screen(CurrentScreen::Editing);
+        }
     }
 
-    pub fn new_input_key(&mut self, key: Input) {
-        self.input.input(key);
+    /// Check if the current room is configured to only be marked as read explicitly.
+    fn is_private_read_room(&self) -> bool {
+        let name = self
+            .backend
+            .get_room(&self.current_room_token)
+            .get_display_name();
+        self.private_read_rooms.iter().any(|room| room == name)
     }
 
-    pub fn scroll_up(&mut self) {
-        self.chat.select_up();
+    fn confirm_participant_text(&self, action: ParticipantAction) -> String {
+        let verb = match action {
+            ParticipantAction::Ban => "Ban",
+            _ => "Remove",
+        };
+        self.users
+            .selected_attendee()
+            .map_or(String::new(), |(_, name)| {
+                format!("{verb} {name} from the room? 'y' yes, 'n' no")
+            })
     }
 
-    pub fn scroll_down(&mut self) {
-        self.chat.select_down();
+    /// Check if the current user may add or remove participants of the current room.
+    fn can_moderate_current_room(&self) -> bool {
+        let room = self.backend.get_room(&self.current_room_token);
+        if room.is_group() && room.is_moderator() {
+            true
+        } else {
+            log::warn!(
+                "Not allowed to manage participants of {}.",
+                room.get_display_name()
+            );
+            false
+        }
     }
 
-    pub fn toggle_user_sidebar(&mut self) {
-        self.user_sidebar_visible = !self.user_sidebar_visible;
+    /// Search the stored messages of the current room, or jump to the selected match if the search did not change.
+    async fn search_or_jump(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.message_search.needs_search() {
+            let messages = self
+                .backend
+                .get_room(&self.current_room_token)
+                .search_messages(&self.message_search.search_text())?;
+            self.message_search.set_results(&messages);
+        } else if let Some(message_id) = self.message_search.selected_message() {
+            self.popup = None;
+            self.jump_to_message(self.current_room_token.clone(), message_id)
+                .await?;
+        }
+        Ok(())
     }
 
-    pub fn click_at(&mut self, position: Position) -> Result<(), Box<dyn std::error::Error>> {
-        match self.current_screen {
-            CurrentScreen::Reading => self.chat.select_line(position)?,
-            CurrentScreen::Opening => {
-                self.selector.state.click_at(position);
+    /// Search users for the invite popup, or add the selected user if the search did not change.
+    pub async fn invite_selected_user(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.invite.needs_search() {
+            let users = self
+                .backend
+                .search_users(&self.invite.search_text())
+                .await?;
+            self.invite.set_results(users);
+        } else if let Some((user_id, _)) = self.invite.selected_user().cloned() {
+            self.popup = None;
+            if self.invite.direct_message {
+                return self.open_dm(&user_id).await;
             }
-            CurrentScreen::Editing | CurrentScreen::Logging => (),
+            self.backend
+                .add_participant(&self.current_room_token, &user_id)
+                .await?;
+            self.update_ui()?;
         }
         Ok(())
     }
 
-    pub fn write_log_files(&mut self) -> Result<(), std::io::Error> {
-        self.backend.write_to_log()
+    /// Search for a user to open the direct message with.
+    fn open_message_user(&mut self) {
+        self.invite.reset();
+        self.invite.direct_message = true;
+        self.popup = Some(Popup::Invite);
     }
 
-    async fn run_app<B: ratatui::prelude::Backend>(
-        &mut self,
-        mut terminal: Terminal<B>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        self.select_room().await?;
-        log::info!("Entering Main Loop");
-        loop {
-            terminal.draw(|f| self.ui(f))?;
+    /// Switch to the one to one room with the user, creating it if there is none yet.
+    async fn open_dm(&mut self, user_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let token = self.backend.open_dm(user_id).await?;
+        self.users.focused = false;
+        self.open_room(token).await?;
+        self.switch_screen(CurrentScreen::Reading);
+        Ok(())
+    }
 
-            // Event within timeout?
-            if poll(std::time::Duration::from_millis(3000))? {
-                match self.process_event(read()?).await {
-                    Ok(ProcessEventResult::Continue) => (),
-                    Ok(ProcessEventResult::Exit) => return Ok(()),
-                    Err(why) => return Err(why),
-                }
-            } else {
-                log::trace!("Looking for Updates on the server.");
-                // trigger a fetch from upstream for messages
-                self.fetch_updates().await?;
-            }
+    /// Open the call of the current room in the browser, copying its link if that fails.
+    fn join_current_call(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.backend.get_room(&self.current_room_token).has_call() {
+            return Ok(());
+        }
+        let link = self.backend.get_room_link(&self.current_room_token);
+        if let Err(why) = open_in_browser(&link) {
+            log::warn!("Could not open the call in the browser: {why}");
+            copy_to_clipboard(&link)?;
+            log::info!("Copied call link {link} to the clipboard.");
         }
+        Ok(())
     }
 
-    async fn process_event(
-        &mut self,
-        event: Event,
-    ) -> Result<ProcessEventResult, Box<dyn std::error::Error>> {
-        // It's guaranteed that `read` won't block, because `poll` returned
-        // `Ok(true)`.
-        match event {
-            Event::Key(key) => {
-                log::trace!("Processing key event {:?}", key);
-                if let Some(popup) = self.popup {
-                    match popup {
-                        Popup::Help => self.handle_key_in_help(key),
-                        Popup::Exit => {
-                            if let Some(value) = self.handle_key_in_exit(key) {
-                                return value;
-                            }
-                        }
-                    }
-                }
-                match self.current_screen {
-                    CurrentScreen::Reading => self.handle_key_in_reading(key).await?,
-                    CurrentScreen::Editing => {
-                        self.handle_key_in_editing(Input::from(event.clone()))
-                            .await?;
-                    }
-                    CurrentScreen::Opening => self.handle_key_in_opening(key).await?,
-                    CurrentScreen::Logging => self.handle_key_in_logging(key),
-                }
-            }
-            Event::Mouse(mouse) => match mouse.kind {
-                MouseEventKind::ScrollDown => self.scroll_down(),
-                MouseEventKind::ScrollUp => self.scroll_up(),
-                MouseEventKind::Down(_button) => {
-                    self.click_at(Position::new(mouse.column, mouse.row))?;
-                }
-                _ => (),
-            },
-            _ => {
-                log::warn!("Unknown Event {:?}", event);
-            }
+    /// Write the stored history of the current room into a file in the download dir,
+    /// the messages loaded so far if nothing is stored.
+    fn export_current_room(&mut self, format: ExportFormat) {
+        self.popup = None;
+        let room = self.backend.get_room(&self.current_room_token);
+        let stored = self
+            .backend
+            .get_stored_messages(&self.current_room_token)
+            .unwrap_or_else(|why| {
+                log::debug!("Could not read the stored messages: {why}");
+                vec![]
+            });
+        let messages = if stored.is_empty() {
+            room.get_messages().values().collect::<Vec<_>>()
+        } else {
+            stored.iter().collect()
+        };
+        match write_export(
+            &self.download_dir,
+            room.get_display_name(),
+            &messages,
+            format,
+        ) {
+            Ok(path) => log::info!(
+                "Exported {} messages of {} to {}",
+                messages.len(),
+                room.get_display_name(),
+                path.display()
+            ),
+            Err(why) => log::warn!("Could not export {}: {why}", room.get_display_name()),
         }
-        Ok(ProcessEventResult::Continue)
     }
 
-    async fn handle_key_in_opening(
-        &mut self,
-        key: KeyEvent,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        if self.selector.searching {
-            match key.code {
-                KeyCode::Down => _ = self.selector.state.key_down(),
-                KeyCode::Up => _ = self.selector.state.key_up(),
-                KeyCode::Enter => self.select_room().await?,
-                KeyCode::Esc => self.selector.searching = false,
-                _ => _ = self.selector.search_bar.input(key),
+    /// Open the counters of the requests to the server.
+    fn open_diagnostics(&mut self) {
+        self.diagnostics.update(self.backend.get_metrics().as_ref());
+        self.popup = Some(Popup::Diagnostics);
+    }
+
+    /// Refresh the open diagnostics and write the metrics file, if one was given.
+    fn record_metrics(&mut self) {
+        let metrics = self.backend.get_metrics();
+        if self.popup == Some(Popup::Diagnostics) {
+            self.diagnostics.update(metrics.as_ref());
+        }
+        if let (Some(path), Some(metrics)) = (&self.metrics_file, metrics) {
+            let written = serde_json::to_string_pretty(&metrics)
+                .map_err(|why| why.to_string())
+                .and_then(|json| std::fs::write(path, json).map_err(|why| why.to_string()));
+            if let Err(why) = written {
+                log::debug!("Could not write the metrics to {}: {why}", path.display());
             }
-        } else {
-            match key.code {
-                KeyCode::Esc => self.switch_screen(CurrentScreen::Reading),
-                KeyCode::Char('h') | KeyCode::Left => _ = self.selector.state.key_left(),
-                KeyCode::Char('j') | KeyCode::Down => _ = self.selector.state.key_down(),
-                KeyCode::Char('k') | KeyCode::Up => _ = self.selector.state.key_up(),
-                KeyCode::Char('l') | KeyCode::Right => _ = self.selector.state.key_right(),
-                KeyCode::Char('d') | KeyCode::PageDown => {
-                    _ = self.selector.state.select_relative(|current| {
-                        current.map_or(0, |current| current.saturating_add(9))
-                    });
-                }
-                KeyCode::Char('u') | KeyCode::PageUp => {
-                    _ = self.selector.state.select_relative(|current| {
-                        current.map_or(0, |current| current.saturating_sub(9))
-                    });
-                }
-                KeyCode::Char('/') => self.selector.searching = true,
-                KeyCode::Char('q') => self.popup = Some(Popup::Exit),
-                KeyCode::Char('?') => self.popup = Some(Popup::Help),
-                KeyCode::Char(' ') => _ = self.selector.state.toggle_selected(),
-                KeyCode::Enter => self.select_room().await?,
-                KeyCode::Home => _ = self.selector.state.select_first(),
-                KeyCode::End => _ = self.selector.state.select_last(),
-                _ => (),
-            };
         }
-        Ok(())
     }
 
-    async fn handle_key_in_editing(
-        &mut self,
-        key: Input,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        match key {
-            Input { key: Key::Esc, .. } => self.switch_screen(CurrentScreen::Reading),
-            Input {
-                key: Key::Enter,
-                shift: false,
-                ..
-            } => {
-                // SEND MEssage
-                self.switch_screen(CurrentScreen::Reading);
-                self.mark_current_as_read().await?;
-                self.send_message().await?;
-            }
-            _ => self.new_input_key(key),
-        };
+    /// Open the details of the current room.
+    fn open_room_info(&mut self) {
+        self.room_info.update(
+            self.backend.get_room(&self.current_room_token),
+            self.notify.is_available(),
+            Utc::now().timestamp(),
+        );
+        self.popup = Some(Popup::RoomInfo);
+    }
 
-        Ok(())
+    /// Open the room settings form for the current room.
+    fn open_room_settings(&mut self) {
+        let room = self.backend.get_room(&self.current_room_token);
+        self.room_settings.open(
+            room.get_display_name(),
+            room.get_description(),
+            room.is_mention_all_restricted(),
+            room.get_message_expiration(),
+        );
+        if room.is_public() {
+            self.room_settings.open_public(
+                self.backend.get_room_link(&self.current_room_token),
+                room.has_password(),
+            );
+        }
+        self.popup = Some(Popup::RoomSettings);
     }
 
-    fn handle_key_in_help(&mut self, key: KeyEvent) {
-        match key.code {
-            KeyCode::Char('q') => self.popup = Some(Popup::Exit),
-            KeyCode::Esc => self.popup = None,
-            KeyCode::Char('o') => {
-                self.popup = None;
-                self.switch_screen(CurrentScreen::Opening);
+    /// Send changed name and description of the room settings form to the server.
+    pub async fn save_room_settings(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.popup = None;
+        if let Some(name) = self.room_settings.changed_name() {
+            self.backend
+                .set_room_name(&self.current_room_token, &name)
+                .await?;
+        }
+        if let Some(description) = self.room_settings.changed_description() {
+            self.backend
+                .set_room_description(&self.current_room_token, &description)
+                .await?;
+        }
+        if let Some(password) = self.room_settings.changed_password() {
+            self.backend
+                .set_room_password(&self.current_room_token, &password)
+                .await?;
+        }
+        if let Some(restricted) = self.room_settings.changed_mention_all_restricted() {
+            self.backend
+                .set_mention_all_restricted(&self.current_room_token, restricted)
+                .await?;
+        }
+        if let Some(seconds) = self.room_settings.changed_message_expiration() {
+            self.backend
+                .set_message_expiration(&self.current_room_token, seconds)
+                .await?;
+        }
+        self.update_ui()
+    }
+
+    /// Fetch the status of the user and show it in the title bar.
+    async fn refresh_own_status(&mut self) {
+        if self.backend.is_offline() {
+            return;
+        }
+        match self.backend.get_own_status().await {
+            Ok(status) => self.title.set_own_status(Some(&status)),
+            Err(why) => {
+                log::debug!("Could not fetch the own status: {why}");
+                self.title.set_own_status(None);
             }
-            _ => (),
         }
     }
 
-    fn handle_key_in_logging(&mut self, key: KeyEvent) {
-        match key.code {
-            KeyCode::Char('q') => self.popup = Some(Popup::Exit),
-            KeyCode::Char('?') => self.popup = Some(Popup::Help),
-            KeyCode::Esc => self.switch_screen(CurrentScreen::Reading),
-            KeyCode::Char('o') => self.switch_screen(CurrentScreen::Opening),
-            _ => self.logging.handle_ui_event(key),
+    /// Open the form to set the status of the user, filled with the current one.
+    async fn open_status_form(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let status = self.backend.get_own_status().await?;
+        self.title.set_own_status(Some(&status));
+        self.status_form.open(&status);
+        self.popup = Some(Popup::Status);
+        Ok(())
+    }
+
+    /// Send the changed status and status message of the status form to the server.
+    async fn save_status(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.popup = None;
+        if let Some(status) = self.status_form.changed_status() {
+            self.backend.set_own_status(status).await?;
+            // Setting the status by hand replaces the one do not disturb would restore.
+            self.status_before_dnd = None;
+        }
+        if let Some((message, icon)) = self.status_form.changed_message() {
+            self.backend.set_own_status_message(&message, icon).await?;
         }
+        self.refresh_own_status().await;
+        Ok(())
     }
 
-    fn handle_key_in_exit(
+    /// Apply an action to the participant selected in the users sidebar.
+    pub async fn apply_participant_action(
         &mut self,
-        key: KeyEvent,
-    ) -> Option<Result<ProcessEventResult, Box<dyn std::error::Error>>> {
-        match key.code {
-            KeyCode::Char('?') => self.popup = Some(Popup::Help),
-            KeyCode::Char('y') => {
+        action: ParticipantAction,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.popup = None;
+        let Some((attendee_id, _)) = self.users.selected_attendee().cloned() else {
+            return Ok(());
+        };
+        let token = &self.current_room_token;
+        match action {
+            ParticipantAction::DirectMessage => {
+                let user_id = self
+                    .backend
+                    .get_room(token)
+                    .get_users()
+                    .iter()
+                    .find(|user| user.attendee_id == attendee_id)
+                    .map(|user| user.actor_id.clone());
+                if let Some(user_id) = user_id {
+                    self.open_dm(&user_id).await?;
+                }
+                return Ok(());
+            }
+            ParticipantAction::Promote => {
+                self.backend
+                    .set_participant_moderator(token, attendee_id, true)
+                    .await?;
+            }
+            ParticipantAction::Demote => {
+                self.backend
+                    .set_participant_moderator(token, attendee_id, false)
+                    .await?;
+            }
+            ParticipantAction::Remove => {
+                self.backend.remove_participant(token, attendee_id).await?;
+            }
+            ParticipantAction::Ban => self.backend.ban_participant(token, attendee_id).await?,
+        }
+        self.update_ui()
+    }
+
+    /// Get the message selected in the chat of the current room.
+    fn selected_message(&self) -> Option<&ChatMessage> {
+        let message_id = self.chat.selected_message_id()?;
+        self.backend
+            .get_room(&self.current_room_token)
+            .get_messages()
+            .values()
+            .find(|message| message.get_id() == message_id)
+    }
+
+    /// Show the message selected in the chat full screen, with its replies.
+    fn open_zoomed_message(&mut self) {
+        let Some(message) = self.selected_message().cloned() else {
+            return;
+        };
+        let messages = self
+            .backend
+            .get_room(&self.current_room_token)
+            .get_messages();
+        let parent = message
+            .get_parent_id()
+            .and_then(|parent_id| messages.get(&parent_id))
+            .cloned();
+        let replies = messages
+            .values()
+            .filter(|reply| reply.get_parent_id() == Some(message.get_id()))
+            .cloned()
+            .collect();
+        self.message_view.open(message, parent, replies);
+        self.switch_screen(CurrentScreen::Zoomed);
+    }
+
+    /// Open the action menu for the message selected in the chat.
+    fn open_message_menu(&mut self) {
+        let Some(message) = self.selected_message() else {
+            return;
+        };
+        let mut actions = MessageAction::available_for(
+            message,
+            &self.own_user,
+            self.backend
+                .get_room(&self.current_room_token)
+                .is_moderator(),
+            Utc::now().timestamp(),
+        );
+        if let Some((token, message_id)) = self.linked_message(message) {
+            actions.push(MessageAction::JumpTo(token, message_id));
+        }
+        actions.retain(|action| {
+            action
+                .required_feature()
+                .is_none_or(|feature| self.backend.supports(feature))
+        });
+        if !actions.is_empty() {
+            self.open_message_submenu("Message", actions);
+        }
+    }
+
+    /// Find the first permalink in the message which points to a message of a known room.
+    fn linked_message(&self, message: &ChatMessage) -> Option<(Token, i32)> {
+        message
+            .get_message()
+            .split_whitespace()
+            .map(|word| word.trim_matches(|c| matches!(c, '<' | '>' | '(' | ')' | '"' | '\'')))
+            .find_map(|word| self.backend.resolve_message_link(word))
+    }
+
+    /// Fetch older messages of the current room until `reached` holds for the oldest one,
+    /// or there are no older ones.
+    async fn fetch_older_until(&mut self, reached: impl Fn(&ChatMessage) -> bool) {
+        while self
+            .backend
+            .get_room(&self.current_room_token)
+            .get_messages()
+            .values()
+            .next()
+            .is_some_and(|first| !reached(first))
+        {
+            match self
+                .backend
+                .fetch_older_messages(&self.current_room_token)
+                .await
+            {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(why) => {
+                    log::warn!("Could not fetch older messages: {why}");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Select the first message of `date` in the current room, fetching older messages if needed.
+    async fn jump_to_date(&mut self, date: NaiveDate) -> Result<(), Box<dyn std::error::Error>> {
+        let start = date
+            .and_time(NaiveTime::MIN)
+            .and_local_timezone(Local)
+            .earliest()
+            .map_or(i64::MIN, |start| start.timestamp());
+        self.fetch_older_until(|first| first.get_timestamp() < start)
+            .await;
+        self.update_ui()?;
+        if !self.chat.select_date(date) {
+            log::info!("There are no messages since {date}.");
+        }
+        Ok(())
+    }
+
+    /// Select the first message of the day, or of the day before, fetching older messages
+    /// when the oldest day is reached.
+    async fn select_previous_day(&mut self) {
+        if self.chat.select_previous_day() {
+            return;
+        }
+        let Some(first_id) = self.chat.first_message_id() else {
+            return;
+        };
+        match self
+            .backend
+            .fetch_older_messages(&self.current_room_token)
+            .await
+        {
+            Ok(0) => (),
+            Ok(_) => {
+                self.chat
+                    .update_messages(&self.backend, &self.current_room_token);
+                self.chat.select_message(first_id);
+                self.chat.select_previous_day();
+            }
+            Err(why) => log::warn!("Could not fetch older messages: {why}"),
+        }
+    }
+
+    /// Open the room with the given Token and select the message, fetching older messages until it is loaded.
+    async fn jump_to_message(
+        &mut self,
+        token: Token,
+        message_id: i32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if token != self.current_room_token {
+            self.open_room(token).await?;
+        }
+        self.fetch_older_until(|first| first.get_id() <= message_id)
+            .await;
+        self.update_ui()?;
+        if !self
+            .backend
+            .get_room(&self.current_room_token)
+            .get_messages()
+            .contains_key(&message_id)
+        {
+            log::warn!("Linked message {message_id} could not be found.");
+        }
+        self.chat.select_message(message_id);
+        Ok(())
+    }
+
+    fn open_message_submenu(&mut self, title: &str, actions: Vec<MessageAction>) {
+        self.message_menu_title = title.to_string();
+        self.message_menu.open(actions);
+        self.popup = Some(Popup::MessageActions);
+    }
+
+    /// Apply an action of the message menu to the message selected in the chat.
+    pub async fn apply_message_action(
+        &mut self,
+        action: MessageAction,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.popup = None;
+        let Some((message_id, text)) = self
+            .selected_message()
+            .map(|message| (message.get_id(), message.get_message().to_string()))
+        else {
+            return Ok(());
+        };
+        let token = self.current_room_token.clone();
+        match action {
+            MessageAction::React => self.open_message_submenu(
+                "React",
+                QUICK_REACTIONS
+                    .iter()
+                    .filter_map(|shortcode| emojis::get_by_shortcode(shortcode))
+                    .map(|emoji| MessageAction::ReactWith(emoji.to_string()))
+                    .collect(),
+            ),
+            MessageAction::Remind => self.open_message_submenu(
+                "Remind me",
+                REMINDER_HOURS.map(MessageAction::RemindIn).to_vec(),
+            ),
+            MessageAction::ReactWith(reaction) => {
+                self.backend
+                    .react_to_message(&token, message_id, &reaction)
+                    .await?;
+            }
+            MessageAction::RemoveReaction(reaction) => {
+                self.backend
+                    .remove_reaction(&token, message_id, &reaction)
+                    .await?;
+            }
+            MessageAction::Reply => {
+                self.start_reply(message_id);
+                self.switch_screen(CurrentScreen::Editing);
+            }
+            MessageAction::Edit => {
+                self.clear_input();
+                self.input.insert_str(text);
+                self.input.set_title("Edit message");
+                self.compose = Compose::Edit(message_id);
+                self.switch_screen(CurrentScreen::Editing);
+            }
+            MessageAction::Delete => self.backend.delete_message(&token, message_id).await?,
+            MessageAction::Copy => copy_to_clipboard(&text)?,
+            MessageAction::Forward => {
+                self.forward_message = Some(text);
+                self.switch_screen(CurrentScreen::Opening);
+            }
+            MessageAction::RemindIn(hours) => {
+                self.backend
+                    .set_message_reminder(&token, message_id, Utc::now().timestamp() + hours * 3600)
+                    .await?;
+            }
+            MessageAction::Pin => self.backend.pin_message(&token, message_id).await?,
+            MessageAction::JumpTo(linked_room, linked_id) => {
+                self.jump_to_message(linked_room, linked_id).await?;
+            }
+        }
+        self.update_ui()
+    }
+
+    /// Compose the input as a reply to the message with the given id.
+    fn start_reply(&mut self, message_id: i32) {
+        let title = self
+            .selected_message()
+            .map_or("Reply".to_string(), |message| {
+                format!("Reply to {}", message.get_name())
+            });
+        self.input.set_title(&title);
+        self.compose = Compose::Reply(message_id);
+    }
+
+    /// Go back to composing a plain message.
+    fn reset_compose(&mut self) {
+        if let Compose::Edit(_) = self.compose {
+            self.clear_input();
+        }
+        self.compose = Compose::Message;
+        self.input.set_title("");
+    }
+
+    fn clear_input(&mut self) {
+        self.input.select_all();
+        self.input.cut();
+        self.input.select_all();
+    }
+
+    fn exit_text(&self) -> Vec<Line<'_>> {
+        let Some(first) = self.exit_mention_rooms.first() else {
+            return vec![Line::from("To Quit Press 'y', to stay 'n'")];
+        };
+        let rooms = if self.exit_mention_rooms.len() == 1 {
+            "1 room".to_string()
+        } else {
+            format!("{} rooms", self.exit_mention_rooms.len())
+        };
+        vec![
+            Line::from(format!("{rooms} with mentions unread, quit anyway?")),
+            Line::from(format!(
+                "'y' quit, 'n' stay, 'o' open {}",
+                self.backend.get_room(first).get_display_name()
+            )),
+        ]
+    }
+
+    /// Open the exit popup, collecting rooms with unread mentions for the summary.
+    fn open_exit_popup(&mut self) {
+        self.exit_mention_rooms = self
+            .backend
+            .get_unread_rooms()
+            .into_iter()
+            .filter(|token| self.backend.get_room(token).has_unread_mention())
+            .collect();
+        self.popup = Some(Popup::Exit);
+    }
+
+    pub async fn mark_current_as_read(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.backend
+            .mark_current_room_as_read(&self.current_room_token)
+            .await?;
+        let new_rooms = self.backend.update_rooms(true).await?;
+        self.announce_new_rooms(new_rooms);
+        self.update_ui()?;
+        Ok(())
+    }
+
+    pub async fn mark_all_as_read(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.backend.mark_all_rooms_as_read().await?;
+        let new_rooms = self.backend.update_rooms(true).await?;
+        self.announce_new_rooms(new_rooms);
+        self.update_ui()?;
+        Ok(())
+    }
+
+    fn update_ui(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let backend = &self.backend;
+        self.notify.set_muted_rooms(
+            backend
+                .get_room_keys()
+                .into_iter()
+                .filter(|token| backend.is_locally_muted(token))
+                .map(|token| backend.get_room(token).get_display_name().to_string()),
+        );
+        self.title
+            .update(self.current_screen, &self.backend, &self.current_room_token);
+        self.selector.update(&self.backend)?;
+        self.chat
+            .update_messages(&self.backend, &self.current_room_token);
+        self.users.update(&self.backend, &self.current_room_token);
+        Ok(())
+    }
+
+    /// Leave editing, mark the current room as read and send the input.
+    /// Rooms in `private_read_rooms` are only marked as read explicitly.
+    pub async fn submit_message(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.switch_screen(CurrentScreen::Reading);
+        if !self.is_private_read_room() {
+            self.mark_current_as_read().await?;
+        }
+        self.send_message().await
+    }
+
+    /// Send the input, or hold it back in the outbox if undo send is configured.
+    /// Edits of existing messages are applied right away.
+    pub async fn send_message(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.input.is_empty() {
+            Ok(())
+        } else {
+            let message = self.input.lines().join("\n");
+            match self.compose {
+                Compose::Edit(message_id) => {
+                    self.backend
+                        .edit_message(&self.current_room_token, message_id, &message)
+                        .await?;
+                }
+                Compose::Reply(message_id) => self.send_or_queue(message, Some(message_id)).await?,
+                Compose::Message => self.send_or_queue(message, None).await?,
+            }
+            self.clear_input();
+            self.reset_compose();
+            self.update_ui()?;
+            self.chat.select_last_message();
+            Ok(())
+        }
+    }
+
+    async fn send_or_queue(
+        &mut self,
+        message: String,
+        reply_to: Option<i32>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let token = self.current_room_token.clone();
+        if self.outbox.is_enabled() {
+            self.outbox.queue(token, message, reply_to, Instant::now());
+        } else {
+            let new_message = self.send_to_backend(message, &token, reply_to).await?;
+            self.notify_new_message(&token, new_message);
+        }
+        Ok(())
+    }
+
+    /// Send messages taken from the outbox to their rooms.
+    async fn dispatch_messages(
+        &mut self,
+        messages: Vec<OutgoingMessage>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+        for outgoing in messages {
+            let new_message = self
+                .send_to_backend(outgoing.message, &outgoing.token, outgoing.reply_to)
+                .await?;
+            self.notify_new_message(&outgoing.token, new_message);
+        }
+        self.update_ui()?;
+        self.chat.select_last_message();
+        Ok(())
+    }
+
+    async fn send_to_backend(
+        &mut self,
+        message: String,
+        token: &Token,
+        reply_to: Option<i32>,
+    ) -> Result<Option<(String, usize)>, Box<dyn std::error::Error>> {
+        match reply_to {
+            Some(message_id) => self.backend.send_reply(message, token, message_id).await,
+            None => self.backend.send_message(message, token).await,
+        }
+    }
+
+    /// Take the last held back message of the current room out of the outbox and back into the input.
+    pub fn undo_send(&mut self) {
+        let Some(outgoing) = self.outbox.undo_last(&self.current_room_token) else {
+            log::info!("No pending message to undo in {}.", self.current_room_token);
+            return;
+        };
+        if !self.input.is_empty() {
+            self.input.move_cursor(CursorMove::Bottom);
+            self.input.move_cursor(CursorMove::End);
+            self.input.insert_newline();
+        }
+        self.input.insert_str(outgoing.message);
+        if let Some(message_id) = outgoing.reply_to {
+            self.start_reply(message_id);
+        }
+        self.switch_screen(CurrentScreen::Editing);
+    }
+
+    /// Notify about new messages of a room, with its last message for the templates.
+    fn notify_new_message(&mut self, token: &Token, new_message: Option<(String, usize)>) {
+        let room = self.backend.get_room(token);
+        let last_message = new_message
+            .as_ref()
+            .and_then(|_| room.get_messages().values().next_back());
+        let mentioned = room.has_unread_mention() || room.is_dm();
+        self.notify.set_visible_room(self.visible_room());
+        self.notify
+            .maybe_notify_new_message(token, new_message, mentioned, last_message);
+    }
+
+    /// The current room, if the chat of it is shown in the focused terminal.
+    fn visible_room(&self) -> Option<Token> {
+        let chat_shown = matches!(
+            self.current_screen,
+            CurrentScreen::Reading | CurrentScreen::Editing
+        );
+        (self.focused == Some(true) && chat_shown).then(|| self.current_room_token.clone())
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        self.focused = Some(focused);
+        self.sync_schedule.set_focused(focused, Instant::now());
+    }
+
+    /// Open a room, remembering the current one to go back to.
+    pub async fn open_room(&mut self, token: Token) -> Result<(), Box<dyn std::error::Error>> {
+        if token != self.current_room_token && !self.current_room_token.is_empty() {
+            self.rooms_back.push(self.current_room_token.clone());
+            if self.rooms_back.len() > ROOM_HISTORY_LENGTH {
+                self.rooms_back.remove(0);
+            }
+            self.rooms_forward.clear();
+        }
+        self.enter_room(token).await
+    }
+
+    /// Go back to the room opened before the current one, or forward again, skipping rooms which
+    /// are gone by now.
+    async fn navigate_rooms(&mut self, back: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let (from, to) = if back {
+            (&mut self.rooms_back, &mut self.rooms_forward)
+        } else {
+            (&mut self.rooms_forward, &mut self.rooms_back)
+        };
+        while let Some(token) = from.pop() {
+            if self.backend.get_room_keys().contains(&&token) {
+                to.push(self.current_room_token.clone());
+                return self.enter_room(token).await;
+            }
+        }
+        log::info!(
+            "No room to go {} to.",
+            if back { "back" } else { "forward" }
+        );
+        Ok(())
+    }
+
+    /// Make the room identified by the token the current room and switch to reading it.
+    async fn enter_room(&mut self, token: Token) -> Result<(), Box<dyn std::error::Error>> {
+        self.selector.clear_new(&token);
+        if token != self.current_room_token {
+            self.leave_current_room().await;
+        }
+        self.current_room_token = token;
+        self.join_current_room_if_previewing().await;
+        let new_message = self.backend.select_room(&self.current_room_token).await?;
+        let token = self.current_room_token.clone();
+        self.notify_new_message(&token, new_message);
+        self.backend.watch_room(&self.current_room_token).await;
+        self.refresh_out_of_office().await;
+        self.switch_screen(CurrentScreen::Reading);
+        self.update_ui()?;
+        self.chat.select_last_message();
+        Ok(())
+    }
+
+    /// Look up if the other user of the current one to one room is out of office, for the title bar.
+    /// Failures are only logged, as the server may not offer absences.
+    async fn refresh_out_of_office(&mut self) {
+        let room = self.backend.get_room(&self.current_room_token);
+        let absence = if *room.get_room_type() == RoomType::OneToOne && !self.backend.is_offline() {
+            let user_id = room.get_name().to_string();
+            self.backend
+                .get_out_of_office(&user_id)
+                .await
+                .unwrap_or_else(|why| {
+                    log::debug!("Could not look up if {user_id} is out of office: {why}");
+                    None
+                })
+        } else {
+            None
+        };
+        self.title
+            .set_out_of_office(&self.current_room_token, absence.as_ref());
+    }
+
+    pub async fn select_room(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(index) = self.selector.selected_account() {
+            self.switch_account(index).await?;
+        } else if let Some(token) = self.selector.selected_room().cloned() {
+            self.selector.searching = false;
+            self.open_room(token).await?;
+            if let Some(message) = self.forward_message.take() {
+                self.reset_compose();
+                self.input.insert_str(message);
+                self.switch_screen(CurrentScreen::Editing);
+            }
+        } else {
+            self.selector.state.toggle_selected();
+        }
+        Ok(())
+    }
+
+    /// Room with unread messages following the current one, rooms mentioning the user first and
+    /// then by last activity, wrapping around at the end. Locally muted rooms are skipped.
+    fn next_unread_room(&self) -> Option<Token> {
+        let mut unread: Vec<Token> = self
+            .backend
+            .get_unread_rooms()
+            .into_iter()
+            .filter(|token| !self.backend.is_locally_muted(token))
+            .collect();
+        unread.sort_by_cached_key(|token| {
+            let room = self.backend.get_room(token);
+            (
+                !room.has_unread_mention(),
+                std::cmp::Reverse(room.get_last_activity()),
+            )
+        });
+        let next = unread
+            .iter()
+            .position(|token| *token == self.current_room_token)
+            .map_or(0, |index| index + 1);
+        unread
+            .into_iter()
+            .cycle()
+            .skip(next)
+            .take(1)
+            .find(|token| *token != self.current_room_token)
+    }
+
+    async fn open_next_unread_room(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        match self.next_unread_room() {
+            Some(token) => self.open_room(token).await?,
+            None => log::info!("No other room has unread messages."),
+        }
+        Ok(())
+    }
+
+    /// Run a command chosen in the [`CommandPalette`], like its key in reading mode would.
+    async fn run_command(&mut self, command: Command) -> Result<(), Box<dyn std::error::Error>> {
+        if command.needs_server() && self.backend.is_offline() {
+            log::warn!("'{command}' needs the server, which cannot be reached.");
+            return Ok(());
+        }
+        match command {
+            Command::MarkRead => self.mark_current_as_read().await?,
+            Command::MarkAllRead => self.mark_all_as_read().await?,
+            Command::FetchHistory => self.fetch_current_room_history().await?,
+            Command::NextUnread => self.open_next_unread_room().await?,
+            Command::SwitchRoom => self.open_quick_switcher(),
+            Command::OpenRooms => self.switch_screen(CurrentScreen::Opening),
+            Command::BrowseRooms => self.open_room_browser().await?,
+            Command::SearchMessages => {
+                self.message_search.reset();
+                self.popup = Some(Popup::Search);
+            }
+            Command::JumpToDate => {
+                self.date_jump.reset();
+                self.popup = Some(Popup::JumpToDate);
+            }
+            Command::ToggleSidebar => self.toggle_user_sidebar(),
+            Command::ToggleMute => self.toggle_locally_muted(&self.current_room_token.clone())?,
+            Command::ToggleArchive => self.toggle_current_room_archived().await?,
+            Command::DoNotDisturb => self.toggle_do_not_disturb().await,
+            Command::SetStatus => self.open_status_form().await?,
+            Command::MessageUser => self.open_message_user(),
+            Command::RoomInfo => self.open_room_info(),
+            Command::Diagnostics => self.open_diagnostics(),
+            Command::Export => self.popup = Some(Popup::Export),
+            Command::ShowLog => self.switch_screen(CurrentScreen::Logging),
+            Command::RequestDumps => self.open_dump_browser(),
+            Command::Inbox => self.open_inbox(),
+            Command::SwitchAccount => self.switch_to_next_account().await?,
+            Command::Help => self.popup = Some(Popup::Help),
+            Command::Quit => self.open_exit_popup(),
+        }
+        Ok(())
+    }
+
+    /// Open the quick switcher listing all rooms by name, from any screen.
+    fn open_quick_switcher(&mut self) {
+        let mut rooms: Vec<(Token, String)> = self
+            .backend
+            .get_room_keys()
+            .into_iter()
+            .map(|token| {
+                let name = self.backend.get_room(token).get_display_name().to_string();
+                (token.clone(), name)
+            })
+            .collect();
+        rooms.sort_by(|(_, name_a), (_, name_b)| name_a.cmp(name_b));
+        self.quick_switcher.reset(rooms);
+        self.popup = Some(Popup::QuickSwitcher);
+    }
+
+    /// Switch to the room browser, listing all listable rooms the user is not a member of.
+    pub async fn open_room_browser(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.room_browser.reset();
+        self.switch_screen(CurrentScreen::Browsing);
+        self.search_listed_rooms().await
+    }
+
+    async fn search_listed_rooms(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let rooms = self
+            .backend
+            .search_listed_rooms(&self.room_browser.search_text())
+            .await?;
+        self.room_browser.set_results(rooms);
+        Ok(())
+    }
+
+    /// Search listable rooms if the search changed, otherwise join and open the selected room.
+    pub async fn join_selected_room(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.room_browser.needs_search() {
+            self.search_listed_rooms().await?;
+        } else if let Some(token) = self.room_browser.selected_room().cloned() {
+            self.backend.join_room(&token).await?;
+            self.open_room(token).await?;
+        }
+        Ok(())
+    }
+
+    /// Apply a [`BulkAction`] to all rooms marked in the selector.
+    /// Toggling actions are switched on for all rooms, unless all of them already have it set.
+    pub async fn apply_bulk_action(
+        &mut self,
+        action: BulkAction,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let tokens = self.selector.take_marked();
+        if tokens.is_empty() {
+            return Ok(());
+        }
+        log::info!("Applying {} to {} rooms.", action, tokens.len());
+        // Toggle all rooms the same way, unless all of them already are in that state.
+        let enable = match action {
+            BulkAction::MarkRead => true,
+            BulkAction::Mute => !tokens
+                .iter()
+                .all(|token| self.backend.get_room(token).is_muted()),
+            BulkAction::Archive => !tokens
+                .iter()
+                .all(|token| self.backend.get_room(token).is_archived()),
+            BulkAction::Favorite => !tokens
+                .iter()
+                .all(|token| self.backend.get_room(token).is_favorite()),
+        };
+        // Keep going after a failed room, so one room does not hold back all others.
+        let mut failed = vec![];
+        for token in &tokens {
+            let result = match action {
+                BulkAction::MarkRead => self.backend.mark_current_room_as_read(token).await,
+                BulkAction::Mute => self.backend.set_room_muted(token, enable).await,
+                BulkAction::Archive => self.backend.set_room_archived(token, enable).await,
+                BulkAction::Favorite => self.backend.set_room_favorite(token, enable).await,
+            };
+            if let Err(why) = result {
+                let name = self.backend.get_room(token).get_display_name().to_string();
+                log::warn!("{action} failed for {name}: {why}");
+                failed.push(format!("{name} ({why})"));
+            }
+        }
+        let new_rooms = self.backend.update_rooms(true).await?;
+        self.announce_new_rooms(new_rooms);
+        self.update_ui()?;
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("{action} failed for {}", failed.join(", ")).into())
+        }
+    }
+
+    /// Mute the room only in this client, or unmute it if it already is muted.
+    pub fn toggle_locally_muted(
+        &mut self,
+        token: &Token,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let muted = !self.backend.is_locally_muted(token);
+        self.backend.set_locally_muted(token, muted)?;
+        self.update_ui()
+    }
+
+    /// Turn do not disturb on or off, syncing the Nextcloud status if configured.
+    async fn toggle_do_not_disturb(&mut self) {
+        self.do_not_disturb = !self.do_not_disturb;
+        self.notify.set_do_not_disturb(self.do_not_disturb);
+        let text = if self.do_not_disturb {
+            "Do not disturb is on, notifications are held back"
+        } else {
+            "Do not disturb is off"
+        };
+        log::info!("{text}");
+        self.toast.show(text.to_string(), Instant::now());
+        if self.sync_dnd_status {
+            if let Err(why) = self.sync_do_not_disturb().await {
+                log::warn!("Could not sync the status with do not disturb: {why}");
+            }
+            self.refresh_own_status().await;
+        }
+    }
+
+    /// Set the Nextcloud status to do not disturb, or back to the one it was before.
+    async fn sync_do_not_disturb(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.backend.is_offline() {
+            return Err("the server cannot be reached".into());
+        }
+        if self.do_not_disturb {
+            let current = self.backend.get_own_status().await?.status;
+            if current != "dnd" {
+                self.backend.set_own_status("dnd").await?;
+                // Only statuses which can be set are restored, offline users show as invisible.
+                let previous = match current.as_str() {
+                    "away" | "invisible" => current,
+                    "offline" => "invisible".to_string(),
+                    _ => "online".to_string(),
+                };
+                self.status_before_dnd = Some(previous);
+            }
+        } else if let Some(previous) = self.status_before_dnd.take() {
+            self.backend.set_own_status(&previous).await?;
+        }
+        Ok(())
+    }
+
+    /// Archive the current room, or unarchive it if it already is archived.
+    pub async fn toggle_current_room_archived(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let archive = !self
+            .backend
+            .get_room(&self.current_room_token)
+            .is_archived();
+        self.backend
+            .set_room_archived(&self.current_room_token, archive)
+            .await?;
+        let new_rooms = self.backend.update_rooms(true).await?;
+        self.announce_new_rooms(new_rooms);
+        self.update_ui()
+    }
+
+    /// Announce rooms the user was added to since the last update of the rooms.
+    /// They are highlighted in the selector until opened and a toast tells who added the user.
+    fn announce_new_rooms(&mut self, names: Vec<String>) {
+        let now = Instant::now();
+        for name in &names {
+            let Some(token) = self
+                .backend
+                .get_room_keys()
+                .into_iter()
+                .find(|token| self.backend.get_room(token).get_display_name() == name)
+                .cloned()
+            else {
+                continue;
+            };
+            let text = match self.backend.get_room(&token).get_added_by(&self.own_user) {
+                Some(who) => format!("{who} added you to {name}"),
+                None => format!("You were added to {name}"),
+            };
+            log::info!("{text}");
+            self.toast.show(text, now);
+            self.selector.mark_new(token);
+        }
+        self.notify.maybe_notify_new_rooms(names);
+    }
+
+    /// Tell about responses the backend could not parse, which can be looked into with 'D'.
+    fn announce_request_dumps(&mut self) {
+        let Some(dump) = self.backend.take_request_dumps().pop() else {
+            return;
+        };
+        self.toast.show(
+            "Could not read a response of the server, 'D' for details".to_string(),
+            Instant::now(),
+        );
+        self.last_dump = Some(dump);
+    }
+
+    /// Switch to the list of dumped responses, showing the one of the last toast if it is new.
+    fn open_dump_browser(&mut self) {
+        self.dump_browser.refresh();
+        if let Some(dump) = self.last_dump.take() {
+            if let Err(why) = self.dump_browser.open(dump) {
+                log::warn!("Could not open the dump: {why}");
+            }
+        }
+        self.switch_screen(CurrentScreen::Dumps);
+    }
+
+    /// Switch to the unread mentions and direct messages of all rooms.
+    fn open_inbox(&mut self) {
+        self.inbox.refresh(&self.backend, &self.own_user);
+        self.switch_screen(CurrentScreen::Inbox);
+    }
+
+    pub async fn fetch_updates(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        // Rooms which failed to update are reported after showing the updated ones.
+        let updated = self.backend.update_rooms(false).await;
+        if self.current_screen == CurrentScreen::Editing && self.is_current_lobby_closed() {
+            self.switch_screen(CurrentScreen::Reading);
+        }
+        self.update_ui()?;
+        match updated {
+            Ok(new_rooms) => {
+                self.announce_new_rooms(new_rooms);
+                Ok(())
+            }
+            Err(why) => {
+                if let Some(partial) = why.downcast_ref::<RoomUpdateError>() {
+                    self.announce_new_rooms(partial.new_rooms.clone());
+                }
+                Err(why)
+            }
+        }
+    }
+
+    /// Show the error in a popup to be dismissed, the app keeps running.
+    /// An error repeating the one shown last is only logged, so a room failing on every
+    /// update does not bring the popup back after dismissing it.
+    /// Errors of the API come with a hint on what to do about them.
+    fn show_error(&mut self, why: &(dyn std::error::Error + 'static)) {
+        log::error!("{why}");
+        let text = match self.backend.error_hint(why) {
+            Some(hint) => format!("{why}\n{hint}"),
+            None => why.to_string(),
+        };
+        if text == self.error_text && self.popup != Some(Popup::Error) {
+            return;
+        }
+        self.error_text = text;
+        self.popup = Some(Popup::Error);
+    }
+
+    /// Show the messages pushed by the long poll of the current room.
+    fn show_pushed_messages(
+        &mut self,
+        pushed: Vec<(Token, Option<(String, usize)>)>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if pushed.is_empty() {
+            return Ok(());
+        }
+        self.notify.start_batch();
+        for (token, new_message) in pushed {
+            self.notify_new_message(&token, new_message);
+        }
+        self.notify.finish_batch();
+        self.update_ui()
+    }
+
+    /// Start fetching the full history of the current room, which is added chunk by chunk.
+    pub async fn fetch_current_room_history(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.backend
+            .fetch_room_history(&self.current_room_token)
+            .await?;
+        self.update_ui()
+    }
+
+    pub fn new_input_key(&mut self, key: Input) {
+        let closes_shortcode = key.key == Key::Char(':');
+        self.input.input(key);
+        if closes_shortcode {
+            self.input.replace_shortcode();
+        }
+    }
+
+    pub fn scroll_up(&mut self) {
+        if self.current_screen == CurrentScreen::Zoomed {
+            self.message_view.scroll_up();
+        } else {
+            self.chat.select_up();
+        }
+    }
+
+    /// Scroll up, fetching older messages of the current room when the first row is selected.
+    async fn scroll_up_or_fetch_older(&mut self) {
+        if let (true, Some(first_id)) = (self.chat.is_at_top(), self.chat.first_message_id()) {
+            match self
+                .backend
+                .fetch_older_messages(&self.current_room_token)
+                .await
+            {
+                Ok(0) => (),
+                Ok(_) => {
+                    self.chat
+                        .update_messages(&self.backend, &self.current_room_token);
+                    self.chat.select_message(first_id);
+                }
+                Err(why) => log::warn!("Could not fetch older messages: {why}"),
+            }
+        }
+        self.scroll_up();
+    }
+
+    /// Scroll the view of the chat by the mouse wheel, or the zoomed message.
+    fn scroll_wheel(&mut self, up: bool) {
+        match (self.current_screen, up) {
+            (CurrentScreen::Zoomed, true) => self.message_view.scroll_up(),
+            (CurrentScreen::Zoomed, false) => self.message_view.scroll_down(),
+            (_, true) => self.chat.scroll_view_up(WHEEL_LINES),
+            (_, false) => self.chat.scroll_view_down(WHEEL_LINES),
+        }
+    }
+
+    /// Widen or narrow the user sidebar by `change` percent.
+    fn resize_sidebar(&mut self, change: i16) {
+        self.sidebar_percent = self
+            .sidebar_percent
+            .saturating_add_signed(change)
+            .clamp(*SIDEBAR_PERCENTS.start(), *SIDEBAR_PERCENTS.end());
+        self.store_layout();
+    }
+
+    /// Keep the widths of the sidebar and the name column for the next start.
+    fn store_layout(&mut self) {
+        if let Err(why) = self
+            .config
+            .store_layout(self.sidebar_percent, self.chat.name_width())
+        {
+            log::warn!("Could not store the layout in the config: {why}");
+        }
+    }
+
+    pub fn toggle_user_sidebar(&mut self) {
+        self.user_sidebar_visible = !self.user_sidebar_visible;
+    }
+
+    /// Select what was clicked, a second click on the same message opens its actions.
+    pub fn click_at(&mut self, position: Position) {
+        match self.current_screen {
+            CurrentScreen::Reading if self.popup.is_none() => {
+                if !self.chat.select_line(position) {
+                    return;
+                }
+                let clicked = self.chat.selected_message_id();
+                let now = Instant::now();
+                if let Some((at, message_id)) = self.last_click.take() {
+                    if clicked.is_some()
+                        && clicked == message_id
+                        && now.duration_since(at) <= DOUBLE_CLICK
+                    {
+                        self.open_message_menu();
+                        return;
+                    }
+                }
+                self.last_click = Some((now, clicked));
+            }
+            CurrentScreen::Opening => {
+                self.selector.state.click_at(position);
+            }
+            CurrentScreen::Reading
+            | CurrentScreen::Editing
+            | CurrentScreen::Logging
+            | CurrentScreen::Users
+            | CurrentScreen::Browsing
+            | CurrentScreen::Zoomed
+            | CurrentScreen::Dumps
+            | CurrentScreen::Inbox => (),
+        }
+    }
+
+    /// Write the rooms and messages of all accounts to disk.
+    pub fn write_log_files(&mut self) -> Result<(), std::io::Error> {
+        self.backend.write_to_log()?;
+        for account in &mut self.accounts {
+            if let Some(backend) = account.backend.as_mut() {
+                backend.write_to_log()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Send the messages held back by the outbox, as the app is about to exit.
+    async fn send_held_back(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.outbox.is_empty() {
+            log::info!(
+                "Sending {} held back messages before exiting.",
+                self.outbox.len()
+            );
+        }
+        let pending = self.outbox.take_all();
+        self.dispatch_messages(pending).await
+    }
+
+    /// Time until the next toast or banner has to disappear, `None` if none is shown.
+    fn time_to_next_toast_expiry(&self) -> Option<Duration> {
+        let now = Instant::now();
+        [
+            self.toast.time_to_next_expiry(now),
+            self.banners.time_to_next_expiry(now),
+        ]
+        .iter()
+        .flatten()
+        .min()
+        .copied()
+    }
+
+    /// Fetch the participants of the open group room again while they are shown, for their
+    /// current status. Failures are only logged, the next refresh may work again.
+    async fn refresh_presence(&mut self) {
+        let room = self.backend.get_room(&self.current_room_token);
+        if !self.user_sidebar_visible || !room.is_group() || self.backend.is_offline() {
+            return;
+        }
+        log::trace!(
+            "Refreshing the participants of {}.",
+            room.get_display_name()
+        );
+        if let Err(why) = self
+            .backend
+            .refresh_participants(&self.current_room_token)
+            .await
+        {
+            log::debug!("Could not refresh the participants: {why}");
+            return;
+        }
+        self.users.update(&self.backend, &self.current_room_token);
+    }
+
+    /// Wait for whatever comes first and handle it before drawing again.
+    ///
+    /// Only the long poll and the history fetch run in the background and report back through
+    /// [`ChatProvider::next_update`], the sync, sending and the actions of keys are awaited in
+    /// place and hold up the UI until the server answers.
+    async fn run_app<B: ratatui::prelude::Backend>(
+        &mut self,
+        mut terminal: Terminal<B>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.select_room().await?;
+        log::info!("Entering Main Loop");
+        let mut terminal_events = EventStream::new();
+        self.sync_schedule.fetched(Instant::now());
+        let mut autosave_timer = optional_timer(self.autosave_interval);
+        let mut presence_timer = optional_timer(self.presence_interval);
+        let mut exit_signals = ExitSignals::new()?;
+        let mut control = ControlSocket::bind(&socket_path(&self.config.get_data_dir()));
+        loop {
+            terminal.draw(|f| self.ui(f))?;
+
+            let outbox_due = self.outbox.time_to_next(Instant::now());
+            let toast_due = self.time_to_next_toast_expiry();
+            let event = tokio::select! {
+                event = terminal_events.next() => {
+                    AppEvent::Terminal(event.ok_or("Terminal event stream ended.")??)
+                }
+                update = self.backend.next_update() => AppEvent::Backend(update),
+                () = tokio::time::sleep(self.sync_schedule.time_to_next(Instant::now())) => AppEvent::Fetch,
+                () = tokio::time::sleep(outbox_due.unwrap_or_default()), if outbox_due.is_some() => {
+                    AppEvent::OutboxDue
+                }
+                () = tokio::time::sleep(toast_due.unwrap_or_default()), if toast_due.is_some() => {
+                    AppEvent::ToastDue
+                }
+                _ = autosave_timer.tick(), if self.autosave_interval.is_some() => AppEvent::Autosave,
+                _ = presence_timer.tick(), if self.presence_interval.is_some() => {
+                    AppEvent::PresenceRefresh
+                }
+                signal = exit_signals.recv() => AppEvent::Signal(signal),
+                Some(token) = self.notification_actions.recv() => AppEvent::OpenRoom(token),
+                Some(text) = self.notification_banners.recv() => AppEvent::Banner(text),
+                call = control.recv() => AppEvent::Control(call),
+            };
+            match event {
+                AppEvent::Terminal(event) => match self.process_event(event).await {
+                    Ok(ProcessEventResult::Continue) => (),
+                    Ok(ProcessEventResult::Exit) => return self.send_held_back().await,
+                    Ok(ProcessEventResult::EditExternally) => {
+                        // The event stream would take the keys typed into the editor.
+                        drop(terminal_events);
+                        self.edit_draft_externally();
+                        terminal_events = EventStream::new();
+                        terminal.clear()?;
+                    }
+                    Err(why) => self.show_error(&*why),
+                },
+                AppEvent::Backend(BackendUpdate::Pushed(pushed)) => {
+                    self.sync_schedule.active(Instant::now());
+                    if let Err(why) = self.show_pushed_messages(pushed) {
+                        self.show_error(&*why);
+                    }
+                }
+                AppEvent::Backend(BackendUpdate::History(_)) => {
+                    if let Err(why) = self.update_ui() {
+                        self.show_error(&*why);
+                    }
+                }
+                AppEvent::Fetch => {
+                    log::trace!("Looking for Updates on the server.");
+                    // trigger a fetch from upstream for messages
+                    self.sync_schedule.fetched(Instant::now());
+                    if let Err(why) = self.fetch_updates().await {
+                        self.show_error(&*why);
+                    }
+                    self.record_metrics();
+                }
+                AppEvent::OutboxDue => {
+                    let due = self.outbox.take_due(Instant::now());
+                    if let Err(why) = self.dispatch_messages(due).await {
+                        self.show_error(&*why);
+                    }
+                }
+                AppEvent::ToastDue => (),
+                AppEvent::Autosave => {
+                    log::debug!("Autosaving rooms and messages.");
+                    if let Err(why) = self.write_log_files() {
+                        log::warn!("Autosave failed: {why}");
+                    }
+                }
+                AppEvent::PresenceRefresh => self.refresh_presence().await,
+                AppEvent::OpenRoom(token) => {
+                    log::debug!("Opening {token} from its notification.");
+                    self.popup = None;
+                    if let Err(why) = self.open_room(token).await {
+                        self.show_error(&*why);
+                    }
+                }
+                AppEvent::Banner(text) => self.banners.show(text, Instant::now()),
+                AppEvent::Control(call) => self.answer_control_call(call).await,
+                AppEvent::Signal(signal) => {
+                    log::info!("Received {signal}, writing rooms and messages before exiting.");
+                    if let Err(why) = self.write_log_files() {
+                        log::warn!("Could not write rooms and messages: {why}");
+                    }
+                    return self.send_held_back().await;
+                }
+            }
+            self.announce_request_dumps();
+        }
+    }
+
+    /// Find the room by its display name or token, unlike the backend without a fallback.
+    fn find_room(&self, room: &str) -> Result<Token, Box<dyn std::error::Error>> {
+        find_room(&self.backend, room)
+            .ok_or_else(|| format!("There is no room called {room}.").into())
+    }
+
+    /// Carry out the request from the control socket and send back its outcome.
+    async fn answer_control_call(&mut self, call: ControlCall) {
+        log::debug!("Handling {:?} from the control socket.", call.request);
+        let result = self
+            .handle_control_request(call.request)
+            .await
+            .map_err(|why| why.to_string());
+        if let Err(why) = &result {
+            log::warn!("Control request failed: {why}");
+        }
+        _ = call.reply.send(result);
+    }
+
+    async fn handle_control_request(
+        &mut self,
+        request: ControlRequest,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match request {
+            ControlRequest::OpenRoom { room } => {
+                let token = self.find_room(&room)?;
+                self.popup = None;
+                self.open_room(token).await
+            }
+            ControlRequest::Send { room, message } => {
+                let token = self.find_room(&room)?;
+                let new_message = self.send_to_backend(message, &token, None).await?;
+                self.notify_new_message(&token, new_message);
+                self.update_ui()
+            }
+            ControlRequest::MarkAllRead => self.mark_all_as_read().await,
+        }
+    }
+
+    async fn process_event(
+        &mut self,
+        event: Event,
+    ) -> Result<ProcessEventResult, Box<dyn std::error::Error>> {
+        let event = match event {
+            Event::Key(key) => match keys::translate(key) {
+                Some(key) => Event::Key(key),
+                None => return Ok(ProcessEventResult::Continue),
+            },
+            event => event,
+        };
+        self.sync_schedule.active(Instant::now());
+        match event {
+            Event::Key(key) => {
+                log::trace!("Processing key event {:?}", key);
+                if let Some(popup) = self.popup {
+                    match popup {
+                        Popup::Help | Popup::RoomInfo | Popup::Diagnostics => {
+                            self.handle_key_in_help(key);
+                        }
+                        Popup::Exit => {
+                            if let Some(value) = self.handle_key_in_exit(key).await {
+                                return value;
+                            }
+                        }
+                        Popup::Invite => self.handle_key_in_invite(key).await?,
+                        Popup::Search => self.handle_key_in_search(key).await?,
+                        Popup::JumpToDate => self.handle_key_in_date_jump(key).await?,
+                        Popup::QuickSwitcher => self.handle_key_in_quick_switcher(key).await?,
+                        Popup::CommandPalette => self.handle_key_in_command_palette(key).await?,
+                        Popup::SpellSuggestions => self.handle_key_in_spell_suggestions(key),
+                        Popup::ParticipantActions => {
+                            self.handle_key_in_participant_actions(key).await?;
+                        }
+                        Popup::ConfirmParticipantAction(action) => {
+                            self.handle_key_in_confirm_participant(key, action).await?;
+                        }
+                        Popup::ConfirmSend => self.handle_key_in_confirm_send(key).await?,
+                        Popup::RoomSettings => self.handle_key_in_room_settings(key).await?,
+                        Popup::Status => self.handle_key_in_status(key).await?,
+                        Popup::MessageActions => self.handle_key_in_message_actions(key).await?,
+                        Popup::ConfirmDeleteMessage => {
+                            self.handle_key_in_confirm_delete_message(key).await?;
+                        }
+                        Popup::ConfirmShare => self.handle_key_in_confirm_share(key).await,
+                        Popup::Export => self.handle_key_in_export(key),
+                        Popup::Error => self.handle_key_in_error(key),
+                    }
+                    return Ok(ProcessEventResult::Continue);
+                }
+                if key.code == KeyCode::Char('k') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                    self.open_quick_switcher();
+                    return Ok(ProcessEventResult::Continue);
+                }
+                match self.current_screen {
+                    CurrentScreen::Reading => self.handle_key_in_reading(key).await?,
+                    CurrentScreen::Editing => {
+                        return self.handle_key_in_editing(Input::from(event.clone())).await;
+                    }
+                    CurrentScreen::Opening => self.handle_key_in_opening(key).await?,
+                    CurrentScreen::Logging => self.handle_key_in_logging(key),
+                    CurrentScreen::Users => self.handle_key_in_users(key).await?,
+                    CurrentScreen::Browsing => self.handle_key_in_browsing(key).await?,
+                    CurrentScreen::Zoomed => self.handle_key_in_zoomed(key),
+                    CurrentScreen::Dumps => self.handle_key_in_dumps(key)?,
+                    CurrentScreen::Inbox => self.handle_key_in_inbox(key).await?,
+                }
+            }
+            Event::Mouse(mouse) => match mouse.kind {
+                MouseEventKind::ScrollDown => self.scroll_wheel(false),
+                MouseEventKind::ScrollUp => self.scroll_wheel(true),
+                MouseEventKind::Down(_button) => {
+                    self.click_at(Position::new(mouse.column, mouse.row));
+                }
+                _ => (),
+            },
+            Event::FocusGained => self.set_focused(true),
+            Event::FocusLost => self.set_focused(false),
+            Event::Paste(text) => {
+                if self.popup.is_none() && self.current_screen == CurrentScreen::Editing {
+                    self.paste(text);
+                } else {
+                    log::debug!("Ignoring paste outside of the input.");
+                }
+            }
+            // The next draw fits the new size.
+            Event::Resize(..) => (),
+        }
+        Ok(ProcessEventResult::Continue)
+    }
+
+    /// Insert pasted text into the input, but offer to share pasted image files into the current room.
+    fn paste(&mut self, text: String) {
+        if let Some(path) = paste::pasted_image_path(&text) {
+            self.pasted_image = Some((path, text));
+            self.popup = Some(Popup::ConfirmShare);
+        } else if paste::is_binary(&text) {
+            log::warn!("Not pasting binary data, 'A-v' shares an image on the clipboard.");
+        } else {
+            self.input.insert_str(text);
+        }
+    }
+
+    /// Share the image on the clipboard into the current room.
+    async fn share_clipboard_image(&mut self) {
+        match tokio::task::block_in_place(paste::clipboard_image_png) {
+            Ok(data) => {
+                let name = format!("Clipboard {}.png", Local::now().format("%Y-%m-%d %H-%M-%S"));
+                self.share_file(&name, data).await;
+            }
+            Err(why) => log::warn!("Found no image on the clipboard: {why}"),
+        }
+    }
+
+    async fn share_file(&mut self, name: &str, data: Vec<u8>) {
+        if self.backend.is_offline() {
+            log::warn!("Sharing {name} needs the server, which cannot be reached.");
+            return;
+        }
+        match self
+            .backend
+            .share_file(&self.current_room_token, name, data)
+            .await
+        {
+            Ok(()) => {
+                log::info!("Shared {name}");
+                self.toast.show(format!("Shared {name}"), Instant::now());
+            }
+            Err(why) => log::warn!("Could not share {name}: {why}"),
+        }
+    }
+
+    async fn handle_key_in_opening(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.selector.searching {
+            match key.code {
+                KeyCode::Down => _ = self.selector.state.key_down(),
+                KeyCode::Up => _ = self.selector.state.key_up(),
+                KeyCode::Enter => self.select_room().await?,
+                KeyCode::Esc => self.selector.searching = false,
+                _ => _ = self.selector.search_bar.input(key),
+            }
+        } else {
+            match key.code {
+                KeyCode::Esc => {
+                    self.forward_message = None;
+                    self.switch_screen(CurrentScreen::Reading);
+                }
+                KeyCode::Char('h') | KeyCode::Left => _ = self.selector.state.key_left(),
+                KeyCode::Char('j') | KeyCode::Down => _ = self.selector.state.key_down(),
+                KeyCode::Char('k') | KeyCode::Up => _ = self.selector.state.key_up(),
+                KeyCode::Char('l') | KeyCode::Right => _ = self.selector.state.key_right(),
+                KeyCode::Char('d') | KeyCode::PageDown => {
+                    _ = self.selector.state.select_relative(|current| {
+                        current.map_or(0, |current| current.saturating_add(9))
+                    });
+                }
+                KeyCode::Char('u') | KeyCode::PageUp => {
+                    _ = self.selector.state.select_relative(|current| {
+                        current.map_or(0, |current| current.saturating_sub(9))
+                    });
+                }
+                KeyCode::Char('/') => self.selector.searching = true,
+                KeyCode::Char('S') => self.selector.cycle_sort(&self.backend)?,
+                KeyCode::Char('N') => {
+                    if let Some(token) = self.selector.selected_room().cloned() {
+                        self.toggle_locally_muted(&token)?;
+                    }
+                }
+                KeyCode::Char('q') => self.open_exit_popup(),
+                KeyCode::Char('?') => self.popup = Some(Popup::Help),
+                KeyCode::Char('r' | 'm' | 'a' | 'f' | 'b') if self.backend.is_offline() => {
+                    log::warn!("'{}' needs the server, which cannot be reached.", key.code);
+                }
+                KeyCode::Char(' ') => {
+                    if self.selector.toggle_mark_selected() {
+                        self.selector.update(&self.backend)?;
+                    } else {
+                        self.selector.state.toggle_selected();
+                    }
+                }
+                KeyCode::Char('r') => self.apply_bulk_action(BulkAction::MarkRead).await?,
+                KeyCode::Char('m') => self.apply_bulk_action(BulkAction::Mute).await?,
+                KeyCode::Char('a') => self.apply_bulk_action(BulkAction::Archive).await?,
+                KeyCode::Char('f') => self.apply_bulk_action(BulkAction::Favorite).await?,
+                KeyCode::Char('b') => self.open_room_browser().await?,
+                KeyCode::Char('W') => self.switch_to_next_account().await?,
+                KeyCode::Enter => self.select_room().await?,
+                KeyCode::Home => _ = self.selector.state.select_first(),
+                KeyCode::End => _ = self.selector.state.select_last(),
+                _ => (),
+            };
+        }
+        Ok(())
+    }
+
+    async fn handle_key_in_editing(
+        &mut self,
+        key: Input,
+    ) -> Result<ProcessEventResult, Box<dyn std::error::Error>> {
+        let key = match key {
+            Input {
+                key: Key::Char('j'),
+                ctrl: true,
+                ..
+            } if !self.keyboard_enhanced => Input {
+                key: Key::Enter,
+                alt: true,
+                ..Input::default()
+            },
+            key => key,
+        };
+        match key {
+            Input { key: Key::Esc, .. } => {
+                self.reset_compose();
+                self.switch_screen(CurrentScreen::Reading);
+            }
+            Input {
+                key: Key::Char('e'),
+                alt: true,
+                ..
+            } => return Ok(ProcessEventResult::EditExternally),
+            Input {
+                key: Key::Char('v'),
+                alt: true,
+                ..
+            } => self.share_clipboard_image().await,
+            Input {
+                key: Key::Char('s'),
+                alt: true,
+                ..
+            } => self.open_spell_suggestions(),
+            Input { key: Key::Tab, .. } if self.input.complete_emoji() => (),
+            // Alt and Ctrl work as Shift, which many terminals do not report together with Enter.
+            Input {
+                key: Key::Enter,
+                shift,
+                alt,
+                ctrl,
+            } if (shift || alt || ctrl) != self.enter_sends => {
+                if self.needs_send_confirmation() {
+                    self.popup = Some(Popup::ConfirmSend);
+                } else {
+                    self.submit_message().await?;
+                }
+            }
+            Input {
+                key: Key::Enter, ..
+            } => self.input.insert_newline(),
+            _ => self.new_input_key(key),
+        };
+
+        Ok(ProcessEventResult::Continue)
+    }
+
+    /// Switch to Ctrl+j for Shift+Enter, if the terminal cannot tell it from Enter.
+    fn set_keyboard_enhanced(&mut self, keyboard_enhanced: bool) {
+        self.keyboard_enhanced = keyboard_enhanced;
+        self.help.set_keyboard_enhanced(keyboard_enhanced);
+        if let Some(key_hints) = self.key_hints.as_mut() {
+            key_hints.set_keyboard_enhanced(keyboard_enhanced);
+        }
+    }
+
+    /// Offer the corrections of the misspelled word at the cursor.
+    fn open_spell_suggestions(&mut self) {
+        if let Some((word, suggestions)) = self.input.spelling_suggestions() {
+            self.spell_suggestions.reset(&word, suggestions);
+            self.popup = Some(Popup::SpellSuggestions);
+        } else {
+            log::info!("Found no misspelled word at the cursor.");
+        }
+    }
+
+    /// Replace the draft by what it was edited to in the external editor.
+    fn edit_draft_externally(&mut self) {
+        let draft = self.input.lines().join("\n");
+        let edited = tokio::task::block_in_place(|| {
+            edit_externally(
+                &draft,
+                self.config.get_enable_mouse(),
+                self.config.get_enable_paste(),
+                self.config.get_report_all_keys(),
+            )
+        });
+        match edited {
+            Ok(text) => {
+                self.clear_input();
+                self.input.insert_str(text);
+            }
+            Err(why) => log::warn!("Could not edit the message externally: {why}"),
+        }
+    }
+
+    fn handle_key_in_help(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('q') => self.open_exit_popup(),
+            KeyCode::Esc => self.popup = None,
+            KeyCode::Char('o') => {
+                self.popup = None;
+                self.switch_screen(CurrentScreen::Opening);
+            }
+            _ => (),
+        }
+    }
+
+    fn handle_key_in_zoomed(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('q') => self.open_exit_popup(),
+            KeyCode::Char('?') => self.popup = Some(Popup::Help),
+            KeyCode::Esc => self.switch_screen(CurrentScreen::Reading),
+            KeyCode::Char('j') | KeyCode::Down => self.message_view.scroll_down(),
+            KeyCode::Char('k') | KeyCode::Up => self.message_view.scroll_up(),
+            _ => (),
+        }
+    }
+
+    fn handle_key_in_dumps(&mut self, key: KeyEvent) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Char('q') => self.open_exit_popup(),
+            KeyCode::Char('?') => self.popup = Some(Popup::Help),
+            KeyCode::Esc if self.dump_browser.is_open() => self.dump_browser.close(),
+            KeyCode::Esc => self.switch_screen(CurrentScreen::Reading),
+            KeyCode::Enter => self.dump_browser.open_selected()?,
+            KeyCode::Char('d') | KeyCode::Delete => self.dump_browser.delete_selected()?,
+            KeyCode::Char('j') | KeyCode::Down => self.dump_browser.select_down(),
+            KeyCode::Char('k') | KeyCode::Up => self.dump_browser.select_up(),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    async fn handle_key_in_inbox(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Char('q') => self.open_exit_popup(),
+            KeyCode::Char('?') => self.popup = Some(Popup::Help),
+            KeyCode::Esc => self.switch_screen(CurrentScreen::Reading),
+            KeyCode::Enter => {
+                if let Some((token, message_id)) = self.inbox.selected() {
+                    self.jump_to_message(token, message_id).await?;
+                    self.switch_screen(CurrentScreen::Reading);
+                }
+            }
+            KeyCode::Char('j') | KeyCode::Down => self.inbox.select_down(),
+            KeyCode::Char('k') | KeyCode::Up => self.inbox.select_up(),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn handle_key_in_logging(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('q') => self.open_exit_popup(),
+            KeyCode::Char('?') => self.popup = Some(Popup::Help),
+            KeyCode::Esc => self.switch_screen(CurrentScreen::Reading),
+            KeyCode::Char('o') => self.switch_screen(CurrentScreen::Opening),
+            _ => self.logging.handle_ui_event(key),
+        }
+    }
+
+    async fn handle_key_in_exit(
+        &mut self,
+        key: KeyEvent,
+    ) -> Option<Result<ProcessEventResult, Box<dyn std::error::Error>>> {
+        match key.code {
+            KeyCode::Char('?') => self.popup = Some(Popup::Help),
+            KeyCode::Char('y') => {
                 if let Err(err) = self.write_log_files() {
                     log::warn!(
                         "Failure to store logs into log file ({}), ignoring for now.",
@@ -486,39 +2759,585 @@ impl<Backend: NCBackend> App<'_, Backend> {
                 return Some(Ok(ProcessEventResult::Exit));
             }
             KeyCode::Char('n') => self.popup = None,
+            KeyCode::Char('o') => {
+                if let Some(token) = self.exit_mention_rooms.first().cloned() {
+                    self.popup = None;
+                    if let Err(why) = self.open_room(token).await {
+                        return Some(Err(why));
+                    }
+                }
+            }
             _ => (),
         }
         None
     }
 
+    #[allow(clippy::too_many_lines)]
     async fn handle_key_in_reading(
         &mut self,
         key: KeyEvent,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        // The count and the first 'g' only apply to the next key pressed.
+        let (count, pending_g) = if key.kind == KeyEventKind::Press {
+            (self.key_count.take(), std::mem::take(&mut self.pending_g))
+        } else {
+            (None, false)
+        };
         match key.code {
             KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.popup = Some(Popup::Exit);
+                self.open_exit_popup();
+            }
+            KeyCode::Char(digit @ '0'..='9')
+                if key.kind == KeyEventKind::Press && (digit != '0' || count.is_some()) =>
+            {
+                let digit = digit.to_digit(10).map_or(0, |digit| digit as usize);
+                self.key_count = Some(count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+            }
+            KeyCode::Char('g') if key.kind == KeyEventKind::Press => {
+                if pending_g {
+                    self.chat.select_first();
+                } else {
+                    self.pending_g = true;
+                }
+            }
+            KeyCode::Char('G') => self.chat.select_last_message(),
+            KeyCode::Char('<') => self.resize_sidebar(5),
+            KeyCode::Char('>') => self.resize_sidebar(-5),
+            KeyCode::Char('(') => {
+                self.chat.change_name_width(-2);
+                self.store_layout();
+            }
+            KeyCode::Char(')') => {
+                self.chat.change_name_width(2);
+                self.store_layout();
+            }
+            KeyCode::Char('[') => self.select_previous_day().await,
+            KeyCode::Char(']') => {
+                self.chat.select_next_day();
+            }
+            KeyCode::Tab => self.open_next_unread_room().await?,
+            KeyCode::Char(':') => {
+                self.command_palette.reset();
+                self.popup = Some(Popup::CommandPalette);
+            }
+            KeyCode::Left if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.navigate_rooms(true).await?;
+            }
+            KeyCode::Right if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.navigate_rooms(false).await?;
+            }
+            KeyCode::Char('J') => {
+                self.date_jump.reset();
+                self.popup = Some(Popup::JumpToDate);
+            }
+            KeyCode::PageUp => self.chat.page_up(),
+            KeyCode::PageDown => self.chat.page_down(),
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                for _ in 0..count.unwrap_or(1) {
+                    self.chat.half_page_up();
+                }
+            }
+            KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                for _ in 0..count.unwrap_or(1) {
+                    self.chat.half_page_down();
+                }
+            }
+            KeyCode::Char('e' | 'i' | 'm' | 'M' | 'A' | 'a' | 's' | 'S' | 'P' | 'C' | 'f')
+                if self.backend.is_offline() =>
+            {
+                log::warn!("'{}' needs the server, which cannot be reached.", key.code);
+            }
+            KeyCode::Char('e' | 'i') if !self.is_current_lobby_closed() => {
+                self.start_editing().await;
             }
-            KeyCode::Char('e' | 'i') => self.switch_screen(CurrentScreen::Editing),
             KeyCode::Char('j') | KeyCode::Down if key.kind == KeyEventKind::Press => {
-                self.scroll_down();
+                self.chat.select_down_by(count.unwrap_or(1));
+            }
+            KeyCode::Char('k') | KeyCode::Up if key.kind == KeyEventKind::Press => {
+                self.chat.select_up_by(count.unwrap_or(1).saturating_sub(1));
+                self.scroll_up_or_fetch_older().await;
             }
-            KeyCode::Char('k') | KeyCode::Up if key.kind == KeyEventKind::Press => self.scroll_up(),
             KeyCode::Char('m') => self.mark_current_as_read().await?,
             KeyCode::Char('M') => self.mark_all_as_read().await?,
+            KeyCode::Char('A') => self.toggle_current_room_archived().await?,
+            KeyCode::Char('N') => self.toggle_locally_muted(&self.current_room_token.clone())?,
+            KeyCode::Char('B') => self.toggle_do_not_disturb().await,
+            KeyCode::Char('S') => self.open_status_form().await?,
+            KeyCode::Char('P') => self.open_message_user(),
+            KeyCode::Char('z') => self.open_zoomed_message(),
+            KeyCode::Char('Z') => self.undo_send(),
+            KeyCode::Char('D') => self.open_dump_browser(),
+            KeyCode::Char('U') => self.open_inbox(),
+            KeyCode::Char('W') => self.switch_to_next_account().await?,
+            KeyCode::Char('E') => self.popup = Some(Popup::Export),
+            KeyCode::Char('/') => {
+                self.message_search.reset();
+                self.popup = Some(Popup::Search);
+            }
+            KeyCode::Char('a') => self.open_message_menu(),
             KeyCode::Char('o') => self.switch_screen(CurrentScreen::Opening),
             KeyCode::Char('L') => self.switch_screen(CurrentScreen::Logging),
-            KeyCode::Char('q') => self.popup = Some(Popup::Exit),
+            KeyCode::Char('q') => self.open_exit_popup(),
             KeyCode::Char('?') => self.popup = Some(Popup::Help),
             KeyCode::Char('u') => self.toggle_user_sidebar(),
+            KeyCode::Char('s') if self.can_moderate_current_room() => self.open_room_settings(),
+            KeyCode::Char('I') => self.open_room_info(),
+            KeyCode::Char('H') => self.open_diagnostics(),
+            KeyCode::Char('C') => self.join_current_call()?,
+            KeyCode::Char('p') if self.backend.get_room(&self.current_room_token).is_group() => {
+                self.user_sidebar_visible = true;
+                self.users.focused = true;
+                self.switch_screen(CurrentScreen::Users);
+            }
             KeyCode::Char('f') => self.fetch_current_room_history().await?,
             _ => (),
         };
         Ok(())
     }
 
+    async fn handle_key_in_users(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Esc => {
+                self.users.focused = false;
+                self.switch_screen(CurrentScreen::Reading);
+            }
+            KeyCode::Char('j') | KeyCode::Down => self.users.select_down(),
+            KeyCode::Char('k') | KeyCode::Up => self.users.select_up(),
+            KeyCode::Char('a') if self.can_moderate_current_room() => {
+                self.invite.reset();
+                self.popup = Some(Popup::Invite);
+            }
+            KeyCode::Char('x') | KeyCode::Delete
+                if self.users.selected_attendee().is_some() && self.can_moderate_current_room() =>
+            {
+                self.popup = Some(Popup::ConfirmParticipantAction(ParticipantAction::Remove));
+            }
+            KeyCode::Enter => self.open_participant_profile().await?,
+            KeyCode::Char('q') => self.open_exit_popup(),
+            KeyCode::Char('?') => self.popup = Some(Popup::Help),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Show the profile of the participant selected in the users sidebar, with their status
+    /// fetched again, and the actions for them.
+    async fn open_participant_profile(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some((attendee_id, _)) = self.users.selected_attendee().cloned() else {
+            return Ok(());
+        };
+        if let Err(why) = self
+            .backend
+            .refresh_participants(&self.current_room_token)
+            .await
+        {
+            log::warn!("Could not refresh the participants, showing the last known status: {why}");
+        }
+        self.users.update(&self.backend, &self.current_room_token);
+        let room = self.backend.get_room(&self.current_room_token);
+        let Some(participant) = room
+            .get_users()
+            .iter()
+            .find(|user| user.attendee_id == attendee_id)
+        else {
+            return Ok(());
+        };
+        let is_self = participant.is_user && participant.actor_id == self.own_user;
+        self.participant_menu.open(
+            participant,
+            participant.is_user && !is_self,
+            room.is_group() && room.is_moderator() && !is_self,
+        );
+        self.popup = Some(Popup::ParticipantActions);
+        Ok(())
+    }
+
+    async fn handle_key_in_browsing(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Esc => self.switch_screen(CurrentScreen::Opening),
+            KeyCode::Enter => self.join_selected_room().await?,
+            KeyCode::Down => self.room_browser.select_down(),
+            KeyCode::Up => self.room_browser.select_up(),
+            _ => self.room_browser.input(key),
+        }
+        Ok(())
+    }
+
+    async fn handle_key_in_invite(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Esc => self.popup = None,
+            KeyCode::Enter => self.invite_selected_user().await?,
+            KeyCode::Down => self.invite.select_down(),
+            KeyCode::Up => self.invite.select_up(),
+            _ => self.invite.input(key),
+        }
+        Ok(())
+    }
+
+    async fn handle_key_in_search(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Esc => self.popup = None,
+            KeyCode::Enter => self.search_or_jump().await?,
+            KeyCode::Down => self.message_search.select_down(),
+            KeyCode::Up => self.message_search.select_up(),
+            _ => self.message_search.input(key),
+        }
+        Ok(())
+    }
+
+    async fn handle_key_in_date_jump(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Esc => self.popup = None,
+            KeyCode::Enter => {
+                if let Some(date) = self.date_jump.date() {
+                    self.popup = None;
+                    self.jump_to_date(date).await?;
+                }
+            }
+            _ => self.date_jump.input(key),
+        }
+        Ok(())
+    }
+
+    async fn handle_key_in_quick_switcher(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Esc => self.popup = None,
+            KeyCode::Enter => {
+                if let Some(token) = self.quick_switcher.selected_room().cloned() {
+                    self.popup = None;
+                    self.selector.searching = false;
+                    self.open_room(token).await?;
+                }
+            }
+            KeyCode::Down => self.quick_switcher.select_down(),
+            KeyCode::Up => self.quick_switcher.select_up(),
+            _ => self.quick_switcher.input(key),
+        }
+        Ok(())
+    }
+
+    fn handle_key_in_spell_suggestions(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => self.popup = None,
+            KeyCode::Enter => {
+                if let Some(correction) = self.spell_suggestions.selected() {
+                    self.input.correct_spelling(correction);
+                    self.popup = None;
+                }
+            }
+            KeyCode::Char('j') | KeyCode::Down => self.spell_suggestions.select_down(),
+            KeyCode::Char('k') | KeyCode::Up => self.spell_suggestions.select_up(),
+            _ => (),
+        }
+    }
+
+    async fn handle_key_in_command_palette(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Esc => self.popup = None,
+            KeyCode::Enter => {
+                if let Some(command) = self.command_palette.selected_command() {
+                    self.popup = None;
+                    self.run_command(command).await?;
+                }
+            }
+            KeyCode::Down => self.command_palette.select_down(),
+            KeyCode::Up => self.command_palette.select_up(),
+            _ => self.command_palette.input(key),
+        }
+        Ok(())
+    }
+
+    async fn handle_key_in_room_settings(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Esc => self.popup = None,
+            KeyCode::Tab | KeyCode::BackTab => self.room_settings.toggle_focus(),
+            KeyCode::Enter => self.save_room_settings().await?,
+            KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if let Some(link) = self.room_settings.link() {
+                    copy_to_clipboard(link)?;
+                    log::info!("Copied link {link} to the clipboard.");
+                }
+            }
+            KeyCode::Char('x') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.room_settings.clear_password();
+            }
+            KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.room_settings.toggle_mention_all_restricted();
+            }
+            KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.room_settings.cycle_message_expiration();
+            }
+            _ => self.room_settings.input(key),
+        }
+        Ok(())
+    }
+
+    async fn handle_key_in_status(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Esc => self.popup = None,
+            KeyCode::Tab | KeyCode::BackTab => self.status_form.toggle_focus(),
+            KeyCode::Up => self.status_form.cycle_status(false),
+            KeyCode::Down => self.status_form.cycle_status(true),
+            KeyCode::Enter => self.save_status().await?,
+            _ => self.status_form.input(key),
+        }
+        Ok(())
+    }
+
+    async fn handle_key_in_confirm_share(&mut self, key: KeyEvent) {
+        let Some((path, text)) = self.pasted_image.take() else {
+            self.popup = None;
+            return;
+        };
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                self.popup = None;
+                match std::fs::read(&path) {
+                    Ok(data) => self.share_file(&paste::file_name(&path), data).await,
+                    Err(why) => log::warn!("Could not read {}: {why}", path.display()),
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                self.popup = None;
+                self.input.insert_str(text);
+            }
+            _ => self.pasted_image = Some((path, text)),
+        }
+    }
+
+    async fn handle_key_in_confirm_send(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => {
+                self.popup = None;
+                self.submit_message().await?;
+            }
+            KeyCode::Char('n') | KeyCode::Esc => self.popup = None,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    async fn handle_key_in_participant_actions(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Esc => self.popup = None,
+            KeyCode::Char('j') | KeyCode::Down => self.participant_menu.select_down(),
+            KeyCode::Char('k') | KeyCode::Up => self.participant_menu.select_up(),
+            KeyCode::Enter => match self.participant_menu.selected() {
+                Some(action) if action.needs_confirmation() => {
+                    self.popup = Some(Popup::ConfirmParticipantAction(action));
+                }
+                Some(action) => self.apply_participant_action(action).await?,
+                None => self.popup = None,
+            },
+            _ => (),
+        }
+        Ok(())
+    }
+
+    async fn handle_key_in_message_actions(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Esc => self.popup = None,
+            KeyCode::Char('j') | KeyCode::Down => self.message_menu.select_down(),
+            KeyCode::Char('k') | KeyCode::Up => self.message_menu.select_up(),
+            KeyCode::Enter => match self.message_menu.selected() {
+                Some(action) if action.needs_confirmation() => {
+                    self.popup = Some(Popup::ConfirmDeleteMessage);
+                }
+                Some(action) => self.apply_message_action(action).await?,
+                None => self.popup = None,
+            },
+            _ => (),
+        }
+        Ok(())
+    }
+
+    async fn handle_key_in_confirm_delete_message(
+        &mut self,
+        key: KeyEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Char('y') => self.apply_message_action(MessageAction::Delete).await?,
+            KeyCode::Char('n') | KeyCode::Esc => self.popup = None,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn handle_key_in_error(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc | KeyCode::Enter => self.popup = None,
+            KeyCode::Char('L') => {
+                self.popup = None;
+                self.switch_screen(CurrentScreen::Logging);
+            }
+            KeyCode::Char('q') => self.open_exit_popup(),
+            _ => (),
+        }
+    }
+
+    fn handle_key_in_export(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('m') => self.export_current_room(ExportFormat::Markdown),
+            KeyCode::Char('h') => self.export_current_room(ExportFormat::Html),
+            KeyCode::Char('j') => self.export_current_room(ExportFormat::Json),
+            KeyCode::Char('n') | KeyCode::Esc => self.popup = None,
+            _ => (),
+        }
+    }
+
+    async fn handle_key_in_confirm_participant(
+        &mut self,
+        key: KeyEvent,
+        action: ParticipantAction,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Char('y') => self.apply_participant_action(action).await?,
+            KeyCode::Char('n') | KeyCode::Esc => self.popup = None,
+            _ => (),
+        }
+        Ok(())
+    }
+
     fn switch_screen(&mut self, next_screen: CurrentScreen) {
         log::info!("Switching from {} to {}.", self.current_screen, next_screen);
         self.current_screen = next_screen;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{chat_room::MockChatRoom, nc_talk::MockNCTalk};
+    use crate::config::init;
+    use mockall::predicate::eq;
+    use std::collections::BTreeMap;
+
+    /// Room which answers every question of the UI with an empty group room.
+    fn mock_room() -> MockChatRoom {
+        let mut room = MockChatRoom::new();
+        room.expect_get_messages().return_const(BTreeMap::new());
+        room.expect_get_users().return_const(vec![]);
+        room.expect_get_participant_changes().return_const(vec![]);
+        room.expect_get_room_type().return_const(RoomType::Group);
+        room.expect_get_display_name()
+            .return_const("General".to_string());
+        room.expect_get_name().return_const("General".to_string());
+        room.expect_get_description().return_const(String::new());
+        room.expect_is_previewing().return_const(false);
+        room.expect_is_lobby_closed().return_const(false);
+        room.expect_get_lobby_timer().return_const(0);
+        room.expect_has_call().return_const(false);
+        room.expect_get_users_in_call().return_const(0_usize);
+        room.expect_get_last_read().return_const(0);
+        room.expect_get_last_common_read().return_const(0);
+        room.expect_get_last_synced_at().return_const(0);
+        room.expect_get_unread().return_const(0_usize);
+        room.expect_has_unread().return_const(false);
+        room.expect_has_unread_mention().return_const(false);
+        room.expect_is_dm().return_const(false);
+        room.expect_is_group().return_const(true);
+        room.expect_is_public().return_const(false);
+        room.expect_is_favorite().return_const(false);
+        room.expect_is_muted().return_const(false);
+        room.expect_is_archived().return_const(false);
+        room.expect_is_moderator().return_const(false);
+        room.expect_get_parent_room().return_const(None);
+        room.expect_get_message_expiration().return_const(0);
+        room
+    }
+
+    /// Backend with the rooms `a` and `b`, in which only leaving rooms is checked.
+    fn mock_backend() -> MockNCTalk {
+        let rooms: &'static [Token] = Box::leak(Box::new([Token::from("a"), Token::from("b")]));
+        let mut backend = MockNCTalk::new();
+        backend
+            .expect_get_room_by_displayname()
+            .return_const(Token::from("a"));
+        backend.expect_get_room().return_const(mock_room());
+        backend
+            .expect_get_locally_muted_rooms()
+            .return_const(vec![]);
+        backend.expect_is_locally_muted().return_const(false);
+        backend.expect_get_unread_rooms().return_const(vec![]);
+        backend.expect_get_favorite_rooms().return_const(vec![]);
+        backend.expect_get_archived_rooms().return_const(vec![]);
+        backend
+            .expect_get_dm_keys_display_name_mapping()
+            .return_const(vec![]);
+        backend
+            .expect_get_group_keys_display_name_mapping()
+            .return_const(vec![]);
+        backend.expect_get_breakout_rooms().return_const(vec![]);
+        backend.expect_is_offline().return_const(false);
+        backend.expect_get_rate_limit().return_const(None);
+        backend.expect_get_history_progress().return_const(None);
+        backend
+            .expect_get_room_keys()
+            .returning(move || rooms.iter().collect());
+        backend.expect_select_room().returning(|_| Ok(None));
+        backend.expect_watch_room().return_const(());
+        backend
+    }
+
+    #[tokio::test]
+    async fn navigate_leaves_room() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut backend = mock_backend();
+        backend
+            .expect_leave_room()
+            .with(eq(Token::from("a")))
+            .once()
+            .returning(|_| Ok(()));
+        backend
+            .expect_leave_room()
+            .with(eq(Token::from("b")))
+            .once()
+            .returning(|_| Ok(()));
+
+        let mut app = App::new(backend, &config);
+        app.open_room(Token::from("b")).await.unwrap();
+        app.navigate_rooms(true).await.unwrap();
+        assert_eq!(app.current_room_token, "a");
+        assert_eq!(app.rooms_forward, vec![Token::from("b")]);
+    }
+}
@@ -0,0 +1,267 @@
+//! Control of a running instance over a unix socket in the data dir, used by `sechat-rs ctl`.
+//!
+//! A client writes one [`ControlRequest`] as a line of JSON and gets back one line of JSON with
+//! the [`ControlResult`], like `{"Ok":null}` or `{"Err":"There is no room called X."}`.
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+use tokio::{
+    sync::{
+        mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        oneshot,
+    },
+    task::JoinHandle,
+};
+
+/// Path of the control socket of the instance using the data dir.
+/// It is kept in a directory only the user can enter.
+pub fn socket_path(data_dir: &Path) -> PathBuf {
+    data_dir.join("control").join("control.sock")
+}
+
+/// What a running instance is asked to do.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, clap::Subcommand)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum ControlRequest {
+    /// Open the room with the display name or token.
+    OpenRoom {
+        /// Display name or token of the room.
+        room: String,
+    },
+    /// Send a message to the room with the display name or token.
+    Send {
+        /// Display name or token of the room.
+        #[arg(long)]
+        room: String,
+        /// Text of the message.
+        message: String,
+    },
+    /// Mark all rooms as read.
+    MarkAllRead,
+}
+
+/// Outcome of a request, the reason if it failed.
+pub type ControlResult = Result<(), String>;
+
+/// Request received on the socket, its outcome is sent back through `reply`.
+#[derive(Debug)]
+pub struct ControlCall {
+    pub request: ControlRequest,
+    pub reply: oneshot::Sender<ControlResult>,
+}
+
+/// Listener on the control socket passing the requests on to the app.
+/// The socket is removed again when this is dropped.
+pub struct ControlSocket {
+    calls: UnboundedReceiver<ControlCall>,
+    /// Path of the socket and the task accepting connections, `None` without a listener.
+    listener: Option<(PathBuf, JoinHandle<()>)>,
+}
+
+impl ControlSocket {
+    /// Listen on the socket at `path`, or not at all if another instance is listening there
+    /// already or binding fails. Only available on unix.
+    pub fn bind(path: &Path) -> Self {
+        let (sender, calls) = unbounded_channel();
+        cfg_if! {
+            if #[cfg(unix)] {
+                match listen(path) {
+                    Ok(listener) => {
+                        log::debug!("Listening for control requests on {}.", path.display());
+                        return ControlSocket {
+                            calls,
+                            listener: Some((path.to_path_buf(), tokio::spawn(accept(listener, sender)))),
+                        };
+                    }
+                    Err(why) => log::warn!("Not listening on {}: {why}", path.display()),
+                }
+            } else {
+                drop(sender);
+            }
+        }
+        ControlSocket {
+            calls,
+            listener: None,
+        }
+    }
+
+    /// Wait for the next request, never returning without a listener.
+    pub async fn recv(&mut self) -> ControlCall {
+        match self.calls.recv().await {
+            Some(call) => call,
+            None => std::future::pending().await,
+        }
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        if let Some((path, task)) = &self.listener {
+            task.abort();
+            if let Err(why) = std::fs::remove_file(path) {
+                log::debug!("Could not remove {}: {why}", path.display());
+            }
+        }
+    }
+}
+
+/// Bind the socket, replacing one left behind by an instance which did not exit cleanly.
+/// Other users must not control the client. The socket is created with the permissions of the
+/// umask, so its directory is closed to them before binding.
+#[cfg(unix)]
+fn listen(path: &Path) -> std::io::Result<UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    if let Some(dir) = path.parent() {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+    if std::os::unix::net::UnixStream::connect(path).is_ok() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AddrInUse,
+            "another instance is listening",
+        ));
+    }
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+#[cfg(unix)]
+async fn accept(listener: UnixListener, calls: UnboundedSender<ControlCall>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => _ = tokio::spawn(serve(stream, calls.clone())),
+            Err(why) => {
+                log::warn!("Stopped accepting control requests: {why}");
+                return;
+            }
+        }
+    }
+}
+
+/// Answer the single request of a connection.
+#[cfg(unix)]
+async fn serve(stream: UnixStream, calls: UnboundedSender<ControlCall>) {
+    let (read, mut write) = stream.into_split();
+    let mut line = String::new();
+    let result = match BufReader::new(read).read_line(&mut line).await {
+        Ok(_) => handle_line(&line, &calls).await,
+        Err(why) => Err(why.to_string()),
+    };
+    let reply = serde_json::to_string(&result).unwrap_or_default() + "\n";
+    if let Err(why) = write.write_all(reply.as_bytes()).await {
+        log::debug!("Could not answer a control request: {why}");
+    }
+}
+
+/// Pass the request in the line on to the app and wait for its outcome.
+#[cfg(unix)]
+async fn handle_line(line: &str, calls: &UnboundedSender<ControlCall>) -> ControlResult {
+    let request: ControlRequest =
+        serde_json::from_str(line).map_err(|why| format!("Invalid request: {why}"))?;
+    let (reply, result) = oneshot::channel();
+    calls
+        .send(ControlCall { request, reply })
+        .map_err(|_| "The client is exiting.".to_string())?;
+    result
+        .await
+        .unwrap_or_else(|_| Err("The client dropped the request.".to_string()))
+}
+
+/// Send the request to the instance listening at `path` and wait until it was handled.
+/// # Errors
+/// There may be no instance listening, or it could not handle the request.
+pub async fn send_request(
+    path: &Path,
+    request: &ControlRequest,
+) -> Result<(), Box<dyn std::error::Error>> {
+    cfg_if! {
+        if #[cfg(unix)] {
+            let stream = UnixStream::connect(path)
+                .await
+                .map_err(|why| format!("No running instance at {}: {why}", path.display()))?;
+            let (read, mut write) = stream.into_split();
+            write
+                .write_all((serde_json::to_string(request)? + "\n").as_bytes())
+                .await?;
+            let mut line = String::new();
+            BufReader::new(read).read_line(&mut line).await?;
+            serde_json::from_str::<ControlResult>(&line)?.map_err(Into::into)
+        } else {
+            let _ = (path, request);
+            Err("The control socket is only available on unix.".into())
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn request_and_reply() {
+        assert_eq!(
+            serde_json::to_string(&ControlRequest::MarkAllRead).unwrap(),
+            r#"{"command":"mark-all-read"}"#
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = socket_path(dir.path());
+        let mut socket = ControlSocket::bind(&path);
+        assert!(socket.listener.is_some());
+        assert!(ControlSocket::bind(&path).listener.is_none());
+        assert!(path.exists());
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(path.parent().unwrap()), 0o700);
+            assert_eq!(mode(&path), 0o600);
+        }
+
+        let request = ControlRequest::Send {
+            room: "General".to_string(),
+            message: "Hello".to_string(),
+        };
+        let client = tokio::spawn({
+            let (path, request) = (path.clone(), request.clone());
+            async move {
+                send_request(&path, &request)
+                    .await
+                    .map_err(|why| why.to_string())
+            }
+        });
+        let call = socket.recv().await;
+        assert_eq!(call.request, request);
+        call.reply.send(Err("No room".to_string())).unwrap();
+        assert_eq!(client.await.unwrap(), Err("No room".to_string()));
+
+        let client = tokio::spawn({
+            let path = path.clone();
+            async move {
+                send_request(&path, &ControlRequest::MarkAllRead)
+                    .await
+                    .is_ok()
+            }
+        });
+        socket.recv().await.reply.send(Ok(())).unwrap();
+        assert!(client.await.unwrap());
+
+        drop(socket);
+        assert!(!path.exists());
+        assert!(send_request(&path, &ControlRequest::MarkAllRead)
+            .await
+            .is_err());
+    }
+}
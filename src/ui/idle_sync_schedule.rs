@@ -0,0 +1,107 @@
+//! How often all rooms are synced with the server, slower while the user is away.
+//!
+//! The current room does not depend on this, new messages of it are long polled in between.
+use crate::config::Config;
+use std::time::{Duration, Instant};
+
+/// How often all rooms are synced while the user is active.
+pub const FETCH_INTERVAL: Duration = Duration::from_secs(10);
+/// Time without input or new messages after which the user counts as idle.
+const IDLE_AFTER: Duration = Duration::from_mins(2);
+
+/// Picks the time of the next sync, by whether the terminal is focused and the user is active.
+/// Terminals not reporting focus changes count as focused all the time.
+pub struct IdleSyncSchedule {
+    /// Interval while idle, from `idle_fetch_seconds`.
+    idle: Duration,
+    /// Interval while the terminal is not focused, from `unfocused_fetch_seconds`.
+    unfocused: Duration,
+    focused: bool,
+    last_activity: Instant,
+    last_fetch: Instant,
+}
+
+impl IdleSyncSchedule {
+    pub fn new(config: &Config, now: Instant) -> Self {
+        // Zero keeps the pace of an active user, slower settings never make it faster.
+        let interval = |seconds| Duration::from_secs(seconds).max(FETCH_INTERVAL);
+        IdleSyncSchedule {
+            idle: interval(config.data.ui.idle_fetch_seconds),
+            unfocused: interval(config.data.ui.unfocused_fetch_seconds),
+            focused: true,
+            last_activity: now,
+            last_fetch: now,
+        }
+    }
+
+    /// Time between two syncs at the moment.
+    pub fn interval(&self, now: Instant) -> Duration {
+        if !self.focused {
+            self.unfocused
+        } else if now.duration_since(self.last_activity) > IDLE_AFTER {
+            self.idle
+        } else {
+            FETCH_INTERVAL
+        }
+    }
+
+    /// Time until the next sync is due, zero if it is overdue, e.g. after focusing again.
+    pub fn time_to_next(&self, now: Instant) -> Duration {
+        (self.last_fetch + self.interval(now)).saturating_duration_since(now)
+    }
+
+    pub fn fetched(&mut self, now: Instant) {
+        self.last_fetch = now;
+    }
+
+    /// The user did something or new messages arrived, back to the pace of an active user.
+    pub fn active(&mut self, now: Instant) {
+        self.last_activity = now;
+    }
+
+    pub fn set_focused(&mut self, focused: bool, now: Instant) {
+        log::trace!("Terminal focused: {focused}");
+        self.focused = focused;
+        if focused {
+            self.active(now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::init;
+
+    use super::*;
+
+    #[test]
+    fn slower_while_away() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let mut config = init("./test/").unwrap();
+        config.data.ui.idle_fetch_seconds = 30;
+        config.data.ui.unfocused_fetch_seconds = 60;
+
+        let now = Instant::now();
+        let mut schedule = IdleSyncSchedule::new(&config, now);
+        assert_eq!(schedule.time_to_next(now), FETCH_INTERVAL);
+
+        let later = now + IDLE_AFTER + Duration::from_secs(1);
+        assert_eq!(schedule.interval(later), Duration::from_secs(30));
+        schedule.active(later);
+        assert_eq!(schedule.interval(later), FETCH_INTERVAL);
+        assert!(schedule.time_to_next(later).is_zero());
+
+        schedule.fetched(later);
+        schedule.set_focused(false, later);
+        assert_eq!(schedule.time_to_next(later), Duration::from_mins(1));
+        let back = later + Duration::from_secs(20);
+        schedule.set_focused(true, back);
+        assert!(schedule.time_to_next(back).is_zero());
+
+        config.data.ui.idle_fetch_seconds = 0;
+        let schedule = IdleSyncSchedule::new(&config, now);
+        assert_eq!(schedule.interval(later), FETCH_INTERVAL);
+    }
+}
@@ -0,0 +1,73 @@
+//! Key events as reported with all keys as escape codes, see [`translate`].
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers};
+
+/// Turn the key into what it types, as terminals reporting all keys as escape codes send
+/// the unshifted key with Shift or Caps Lock, unless they report the shifted key as well.
+/// Presses of only a modifier and releases are dropped.
+pub fn translate(key: KeyEvent) -> Option<KeyEvent> {
+    if matches!(key.code, KeyCode::Modifier(_)) || key.kind == KeyEventKind::Release {
+        return None;
+    }
+    let KeyCode::Char(c) = key.code else {
+        return Some(key);
+    };
+    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+    let caps_lock = key.state.contains(KeyEventState::CAPS_LOCK);
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(upper), None) if c.is_lowercase() && shift != caps_lock => Some(KeyEvent {
+            code: KeyCode::Char(upper),
+            modifiers: key.modifiers.difference(KeyModifiers::SHIFT),
+            ..key
+        }),
+        _ => Some(key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::ModifierKeyCode;
+
+    use super::*;
+
+    #[test]
+    fn translate_keys() {
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+
+        assert_eq!(
+            translate(key(KeyCode::Char('a'), KeyModifiers::SHIFT)),
+            Some(key(KeyCode::Char('A'), KeyModifiers::NONE))
+        );
+        assert_eq!(
+            translate(key(
+                KeyCode::Char('ü'),
+                KeyModifiers::SHIFT | KeyModifiers::ALT
+            )),
+            Some(key(KeyCode::Char('Ü'), KeyModifiers::ALT))
+        );
+        // Already the shifted key as reported by the terminal.
+        assert_eq!(
+            translate(key(KeyCode::Char('?'), KeyModifiers::NONE)),
+            Some(key(KeyCode::Char('?'), KeyModifiers::NONE))
+        );
+        assert_eq!(
+            translate(key(KeyCode::Enter, KeyModifiers::CONTROL)),
+            Some(key(KeyCode::Enter, KeyModifiers::CONTROL))
+        );
+
+        let mut caps_lock = key(KeyCode::Char('b'), KeyModifiers::NONE);
+        caps_lock.state = KeyEventState::CAPS_LOCK;
+        assert_eq!(translate(caps_lock).unwrap().code, KeyCode::Char('B'));
+        caps_lock.modifiers = KeyModifiers::SHIFT;
+        assert_eq!(translate(caps_lock).unwrap().code, KeyCode::Char('b'));
+
+        assert!(translate(key(
+            KeyCode::Modifier(ModifierKeyCode::LeftShift),
+            KeyModifiers::SHIFT
+        ))
+        .is_none());
+        let mut release = key(KeyCode::Char('x'), KeyModifiers::NONE);
+        release.kind = KeyEventKind::Release;
+        assert!(translate(release).is_none());
+    }
+}
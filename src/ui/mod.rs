@@ -1,5 +1,14 @@
 //! Sechat-rs Frontend based on [ratatui](https://docs.rs/ratatui/latest/ratatui/index.html). See [``app``] for more info.
 pub mod app;
+pub mod control;
+mod idle_sync_schedule;
+mod keys;
 pub mod notifications;
+mod outbox;
+mod paste;
+mod sound;
 mod terminal_helpers;
+mod user_styles;
 mod widget;
+
+pub use terminal_helpers::open_in_browser;
@@ -1,11 +1,84 @@
-use crate::config::Config;
+use crate::backend::{chat_message::ChatMessage, Token};
+use crate::config::{Config, RoomNotifications};
+use crate::ui::sound::Sound;
+use chrono::{Local, NaiveTime};
 use notify_rust::{Hint, Notification, Timeout};
+use std::collections::{BTreeMap, HashSet};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug, Clone, Default)]
 pub struct NotifyWrapper {
     app_name: String,
     timeout: Timeout,
     silent: bool,
+    /// Display names of rooms muted in this client, which never notify.
+    muted_rooms: HashSet<String>,
+    mentions_only: bool,
+    /// Lowercase keywords, messages containing one do not notify.
+    mute_keywords: Vec<String>,
+    /// Start and end of the local time without notifications, the end may be on the next day.
+    quiet_hours: Option<(NaiveTime, NaiveTime)>,
+    /// Which messages notify in the rooms, by display name.
+    room_rules: BTreeMap<String, RoomNotifications>,
+    unread_summary: String,
+    unread_body: String,
+    new_room_summary: String,
+    new_room_body: String,
+    /// Whether a notification service was found, cleared once showing a notification failed.
+    available: bool,
+    /// Gets the token of the room whose notification was activated, see [`Self::open_room_actions`].
+    open_room: Option<UnboundedSender<Token>>,
+    /// Gets summary and body of notifications while there is no notification service,
+    /// see [`Self::fallback_banners`].
+    fallback: Option<UnboundedSender<String>>,
+    /// Played on new messages, even without a notification service.
+    sound: Option<Sound>,
+    /// Toggled in the app, holds back all notifications while set.
+    do_not_disturb: bool,
+    /// Room shown in the focused terminal, its new messages are read right away.
+    visible_room: Option<Token>,
+    /// Rooms with new messages collected since [`Self::start_batch`], `None` while not batching.
+    batch: Option<Batch>,
+    /// Rooms with new messages in one batch from which on a single summary is shown, 0 never.
+    summary_threshold: usize,
+}
+
+/// Token, display name, number of new messages and the last message of the rooms in a batch.
+type Batch = Vec<(Token, String, usize, Option<ChatMessage>)>;
+
+/// Longest preview of the last message shown in a notification, in characters.
+const PREVIEW_LENGTH: usize = 80;
+
+/// Fill the `{room}`, `{author}`, `{count}` and `{preview}` placeholders of a notification template.
+fn fill_template(template: &str, room: &str, author: &str, count: usize, preview: &str) -> String {
+    template
+        .replace("{room}", room)
+        .replace("{author}", author)
+        .replace("{count}", &count.to_string())
+        .replace("{preview}", preview)
+}
+
+/// Read a time range like `22:00-07:00`.
+fn parse_quiet_hours(range: &str) -> Result<(NaiveTime, NaiveTime), String> {
+    let parse = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M");
+    range
+        .split_once('-')
+        .and_then(|(start, end)| Some((parse(start).ok()?, parse(end).ok()?)))
+        .ok_or_else(|| format!("'{range}' is no time range like 22:00-07:00"))
+}
+
+/// First line of a message, shortened to [`PREVIEW_LENGTH`].
+fn preview_text(message: &str) -> String {
+    let first_line = message.lines().next().unwrap_or_default();
+    if first_line.chars().count() > PREVIEW_LENGTH {
+        format!(
+            "{}…",
+            first_line.chars().take(PREVIEW_LENGTH).collect::<String>()
+        )
+    } else {
+        first_line.to_string()
+    }
 }
 
 impl NotifyWrapper {
@@ -18,19 +91,167 @@ impl NotifyWrapper {
                 Timeout::Milliseconds(config.data.notifications.timeout_ms)
             },
             silent: config.data.notifications.silent,
+            muted_rooms: HashSet::new(),
+            mentions_only: config.data.notifications.mentions_only,
+            mute_keywords: config
+                .data
+                .notifications
+                .mute_keywords
+                .iter()
+                .map(|keyword| keyword.to_lowercase())
+                .collect(),
+            quiet_hours: config
+                .data
+                .notifications
+                .quiet_hours
+                .as_deref()
+                .and_then(|range| {
+                    parse_quiet_hours(range)
+                        .map_err(|why| log::warn!("Ignoring the quiet hours: {why}"))
+                        .ok()
+                }),
+            room_rules: config.data.notifications.rooms.clone(),
+            unread_summary: config.data.notifications.unread_summary.clone(),
+            unread_body: config.data.notifications.unread_body.clone(),
+            new_room_summary: config.data.notifications.new_room_summary.clone(),
+            new_room_body: config.data.notifications.new_room_body.clone(),
+            available: NotifyWrapper::detect_service(),
+            open_room: None,
+            fallback: None,
+            sound: config
+                .data
+                .notifications
+                .sound
+                .as_deref()
+                .and_then(|sound| {
+                    let player = Sound::new(sound);
+                    if player.is_none() {
+                        log::warn!(
+                            "Found no player for the sound {sound}, new messages stay silent."
+                        );
+                    }
+                    player
+                }),
+            do_not_disturb: false,
+            visible_room: None,
+            batch: None,
+            summary_threshold: config.data.notifications.summary_threshold,
+        }
+    }
+
+    /// Let notifications about new messages open their room when activated, e.g. by a click.
+    /// The receiver gets the token of the room, where the notification service supports actions.
+    pub fn open_room_actions(&mut self) -> UnboundedReceiver<Token> {
+        let (sender, receiver) = unbounded_channel();
+        self.open_room = Some(sender);
+        receiver
+    }
+
+    /// Show notifications in the TUI while there is no notification service, e.g. over SSH.
+    /// The receiver gets the summary and body of each notification as two lines.
+    pub fn fallback_banners(&mut self) -> UnboundedReceiver<String> {
+        let (sender, receiver) = unbounded_channel();
+        self.fallback = Some(sender);
+        receiver
+    }
+
+    /// Check once if a notification service runs, so a missing one is only reported once.
+    /// Over SSH without a forwarded display they would show up on the remote desktop.
+    fn detect_service() -> bool {
+        let has_display =
+            std::env::var_os("DISPLAY").is_some() || std::env::var_os("WAYLAND_DISPLAY").is_some();
+        if std::env::var_os("SSH_CONNECTION").is_some() && !has_display {
+            log::info!("Running over SSH without a display, desktop notifications are disabled.");
+            return false;
+        }
+        cfg_if::cfg_if! {
+            if #[cfg(all(unix, not(target_os = "macos")))] {
+                match notify_rust::get_server_information() {
+                    Ok(info) => {
+                        log::debug!("Notifications are shown by {}", info.name);
+                        true
+                    }
+                    Err(why) => {
+                        log::warn!("No notification service found, notifications are disabled: {why}");
+                        false
+                    }
+                }
+            } else {
+                true
+            }
+        }
+    }
+
+    /// Check if notifications can be shown, they are disabled without a notification service.
+    pub fn is_available(&self) -> bool {
+        self.available
+    }
+
+    /// Show the notification, disabling all further ones if that fails.
+    /// Activating it sends the token of the `room` to the receiver of [`Self::open_room_actions`].
+    fn show(&mut self, notification: &mut Notification, room: Option<&Token>) {
+        if !self.available {
+            self.fall_back(notification);
+            return;
+        }
+        let open_room = room.cloned().zip(self.open_room.clone());
+        if open_room.is_some() {
+            notification.action("default", "Open");
+        }
+        match notification.show() {
+            Ok(handle) => {
+                cfg_if::cfg_if! {
+                    if #[cfg(all(unix, not(target_os = "macos")))] {
+                        if let Some((token, sender)) = open_room {
+                            // Blocks until the notification is activated or closed.
+                            std::thread::spawn(move || {
+                                handle.wait_for_action(|action| {
+                                    if action == "default" {
+                                        let _ = sender.send(token);
+                                    }
+                                });
+                            });
+                        }
+                    } else {
+                        let _ = (handle, open_room);
+                    }
+                }
+            }
+            Err(why) => {
+                log::warn!("Failed to show a notification, notifications are disabled: {why}");
+                self.available = false;
+                self.fall_back(notification);
+            }
         }
     }
 
+    /// Hand the notification to the receiver of [`Self::fallback_banners`], if there is one.
+    fn fall_back(&self, notification: &Notification) {
+        if let Some(fallback) = &self.fallback {
+            let _ = fallback.send(format!("{}\n{}", notification.summary, notification.body));
+        }
+    }
+
+    /// Notify about new messages in a room, `last_message` fills the `{author}` and `{preview}` placeholders.
     pub fn unread_message(
-        &self,
-        room_name: &String,
+        &mut self,
+        token: &Token,
+        room_name: &str,
         number_of_unread: usize,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+        last_message: Option<&ChatMessage>,
+    ) {
+        let (author, preview) = last_message.map_or((String::new(), String::new()), |message| {
+            (
+                message.get_name().to_string(),
+                preview_text(message.get_message()),
+            )
+        });
+        let fill = |template: &str| {
+            fill_template(template, room_name, &author, number_of_unread, &preview)
+        };
         let mut notification = Notification::new()
-            .summary(&format!("Unread: {room_name}"))
-            .body(&format!(
-                "You have {number_of_unread} new Messages in {room_name}"
-            ))
+            .summary(&fill(&self.unread_summary))
+            .body(&fill(&self.unread_body))
             .icon("dialog-information")
             .appname(&self.app_name)
             .to_owned();
@@ -42,14 +263,16 @@ impl NotifyWrapper {
             .timeout(self.timeout);
         notification.hint(Hint::SuppressSound(self.silent));
 
-        notification.show()?;
-        Ok(())
+        self.show(&mut notification, Some(token));
+        if let Some(sound) = &self.sound {
+            sound.play();
+        }
     }
 
-    pub fn new_room(&self, room_name: &String) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn new_room(&mut self, room_name: &str) {
         let mut notification = Notification::new()
-            .summary(&format!("New Room: {room_name}"))
-            .body(&format!("You have been added to a new Room {room_name}"))
+            .summary(&fill_template(&self.new_room_summary, room_name, "", 0, ""))
+            .body(&fill_template(&self.new_room_body, room_name, "", 0, ""))
             .icon("dialog-information")
             .appname(&self.app_name)
             .to_owned();
@@ -58,8 +281,7 @@ impl NotifyWrapper {
             .timeout(self.timeout); // this however is
         notification.hint(Hint::SuppressSound(self.silent));
 
-        notification.show()?;
-        Ok(())
+        self.show(&mut notification, None);
     }
 
     /// return `true` if notification is persistent (has infinite display timeout)
@@ -67,24 +289,148 @@ impl NotifyWrapper {
         self.timeout == Timeout::Never
     }
 
-    pub fn maybe_notify_new_message(
+    /// Replace the rooms muted in this client by their display names.
+    pub fn set_muted_rooms(&mut self, room_names: impl IntoIterator<Item = String>) {
+        self.muted_rooms = room_names.into_iter().collect();
+    }
+
+    /// Hold back all notifications while `on`, until turned off again.
+    pub fn set_do_not_disturb(&mut self, on: bool) {
+        self.do_not_disturb = on;
+    }
+
+    /// Set the room shown in the focused terminal, which does not notify, `None` while the
+    /// terminal is not focused or shows no room.
+    pub fn set_visible_room(&mut self, token: Option<Token>) {
+        self.visible_room = token;
+    }
+
+    /// Check the rules of the config for new messages in the room, `mentioned` if they mention
+    /// the user or are direct messages.
+    fn allows(
         &self,
+        room_name: &str,
+        mentioned: bool,
+        last_message: Option<&str>,
+        now: NaiveTime,
+    ) -> bool {
+        let rule = self
+            .room_rules
+            .get(room_name)
+            .copied()
+            .unwrap_or(if self.mentions_only {
+                RoomNotifications::Mentions
+            } else {
+                RoomNotifications::All
+            });
+        let quiet = self.quiet_hours.is_some_and(|(start, end)| {
+            if start <= end {
+                start <= now && now < end
+            } else {
+                start <= now || now < end
+            }
+        });
+        let muted_keyword = last_message.is_some_and(|message| {
+            let message = message.to_lowercase();
+            self.mute_keywords
+                .iter()
+                .any(|keyword| message.contains(keyword.as_str()))
+        });
+        match rule {
+            RoomNotifications::None => false,
+            RoomNotifications::Mentions if !mentioned => false,
+            _ => !quiet && !muted_keyword,
+        }
+    }
+
+    /// Notify about new messages in the room, unless it is muted or the rules of the config
+    /// keep them back. `mentioned` tells if they mention the user or are direct messages.
+    pub fn maybe_notify_new_message(
+        &mut self,
+        token: &Token,
         input: Option<(String, usize)>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        if let Some((displayname, size)) = input {
-            self.unread_message(&displayname, size)?;
+        mentioned: bool,
+        last_message: Option<&ChatMessage>,
+    ) {
+        match input {
+            Some((displayname, _)) if self.do_not_disturb => {
+                log::debug!("Not notifying about {displayname} while not to be disturbed");
+            }
+            Some((displayname, _)) if self.visible_room.as_ref() == Some(token) => {
+                log::debug!("Not notifying about {displayname}, it is shown already");
+            }
+            Some((displayname, _)) if self.muted_rooms.contains(&displayname) => {
+                log::debug!("Not notifying about locally muted room {displayname}");
+            }
+            Some((displayname, _))
+                if !self.allows(
+                    &displayname,
+                    mentioned,
+                    last_message.map(ChatMessage::get_message),
+                    Local::now().time(),
+                ) =>
+            {
+                log::debug!("Not notifying about {displayname} by the notification rules");
+            }
+            Some((displayname, size)) => {
+                if let Some(batch) = &mut self.batch {
+                    batch.push((token.clone(), displayname, size, last_message.cloned()));
+                } else {
+                    self.unread_message(token, &displayname, size, last_message);
+                }
+            }
+            None => (),
         }
-        Ok(())
     }
 
-    pub fn maybe_notify_new_rooms(
-        &self,
-        input: Vec<String>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    /// Collect the notifications about new messages until [`Self::finish_batch`], e.g. during
+    /// one update of the rooms.
+    pub fn start_batch(&mut self) {
+        self.batch.get_or_insert_with(Vec::new);
+    }
+
+    /// Notify about the new messages collected since [`Self::start_batch`], with a single
+    /// summary once enough rooms have them.
+    pub fn finish_batch(&mut self) {
+        let Some(batch) = self.batch.take() else {
+            return;
+        };
+        if self.summary_threshold == 0 || batch.len() < self.summary_threshold {
+            for (token, room_name, count, last_message) in batch {
+                self.unread_message(&token, &room_name, count, last_message.as_ref());
+            }
+            return;
+        }
+        let count: usize = batch.iter().map(|(_, _, count, _)| count).sum();
+        let room_names: Vec<&str> = batch.iter().map(|(_, name, _, _)| name.as_str()).collect();
+        let mut notification = Notification::new()
+            .summary(&format!("{count} new messages in {} rooms", batch.len()))
+            .body(&room_names.join(", "))
+            .icon("dialog-information")
+            .appname(&self.app_name)
+            .to_owned();
+        notification
+            .hint(Hint::Resident(self.is_persistent())) // this is not supported by all implementations
+            .timeout(self.timeout);
+        notification.hint(Hint::SuppressSound(self.silent));
+
+        self.show(&mut notification, None);
+        if let Some(sound) = &self.sound {
+            sound.play();
+        }
+    }
+
+    pub fn maybe_notify_new_rooms(&mut self, input: Vec<String>) {
+        if self.do_not_disturb {
+            log::debug!(
+                "Not notifying about {} new rooms while not to be disturbed",
+                input.len()
+            );
+            return;
+        }
         for displayname in input {
-            self.new_room(&displayname)?;
+            self.new_room(&displayname);
         }
-        Ok(())
     }
 }
 
@@ -93,7 +439,11 @@ mod tests {
 
     use crate::config::init;
 
-    use super::NotifyWrapper;
+    use super::{
+        fill_template, parse_quiet_hours, preview_text, NotifyWrapper, RoomNotifications,
+        PREVIEW_LENGTH,
+    };
+    use chrono::NaiveTime;
 
     /// We cannot test the actual notifications.
     #[test]
@@ -102,9 +452,112 @@ mod tests {
 
         std::env::set_var("HOME", dir.path().as_os_str());
         let config = init("./test/").unwrap();
-        let notify = NotifyWrapper::new(&config);
+        let mut notify = NotifyWrapper::new(&config);
+        let mut open_room = notify.open_room_actions();
+        let token = "123".to_string();
         assert!(!notify.is_persistent());
-        assert!(notify.maybe_notify_new_message(None).is_ok());
-        assert!(notify.maybe_notify_new_rooms(vec![]).is_ok());
+        notify.maybe_notify_new_message(&token, None, false, None);
+        notify.set_muted_rooms(vec!["Off Topic".to_string()]);
+        notify.maybe_notify_new_message(&token, Some(("Off Topic".to_string(), 3)), true, None);
+        notify.maybe_notify_new_rooms(vec![]);
+        notify.set_do_not_disturb(true);
+        notify.maybe_notify_new_message(&token, Some(("General".to_string(), 3)), true, None);
+        notify.maybe_notify_new_rooms(vec!["General".to_string()]);
+        notify.set_do_not_disturb(false);
+
+        // Without a notification service, nothing fails and notifications stay disabled.
+        notify.available = false;
+        notify.maybe_notify_new_message(&token, Some(("General".to_string(), 3)), false, None);
+        notify.maybe_notify_new_rooms(vec!["General".to_string()]);
+        assert!(!notify.is_available());
+        assert!(open_room.try_recv().is_err());
+
+        // Unless the TUI shows them instead.
+        let mut banners = notify.fallback_banners();
+        notify.maybe_notify_new_rooms(vec!["General".to_string()]);
+        assert_eq!(
+            banners.try_recv().unwrap(),
+            "New Room: General\nYou have been added to a new Room General"
+        );
+
+        // The room shown in the focused terminal is read already.
+        notify.set_visible_room(Some(token.clone()));
+        notify.maybe_notify_new_message(&token, Some(("General".to_string(), 3)), true, None);
+        assert!(banners.try_recv().is_err());
+        notify.set_visible_room(None);
+
+        // Enough rooms with new messages at once are summed up.
+        notify.start_batch();
+        for (room, count) in [("General", 2), ("Bots", 1), ("Random", 2)] {
+            notify.maybe_notify_new_message(&token, Some((room.to_string(), count)), false, None);
+        }
+        assert!(banners.try_recv().is_err());
+        notify.finish_batch();
+        assert_eq!(
+            banners.try_recv().unwrap(),
+            "5 new messages in 3 rooms\nGeneral, Bots, Random"
+        );
+        notify.start_batch();
+        notify.maybe_notify_new_message(&token, Some(("General".to_string(), 2)), false, None);
+        notify.finish_batch();
+        assert_eq!(
+            banners.try_recv().unwrap(),
+            "Unread: General\nYou have 2 new Messages in General"
+        );
+    }
+
+    #[test]
+    fn rules() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let mut config = init("./test/").unwrap();
+        config.data.notifications.mentions_only = true;
+        config.data.notifications.mute_keywords = vec!["Standup".to_string()];
+        config.data.notifications.quiet_hours = Some("22:00-07:00".to_string());
+        config.data.notifications.rooms = [
+            ("General".to_string(), RoomNotifications::All),
+            ("Bots".to_string(), RoomNotifications::None),
+        ]
+        .into();
+        let notify = NotifyWrapper::new(&config);
+        let time = |hour| NaiveTime::from_hms_opt(hour, 0, 0).unwrap();
+
+        assert!(notify.allows("General", false, Some("Hello"), time(12)));
+        assert!(!notify.allows("General", false, Some("standup now"), time(12)));
+        assert!(!notify.allows("General", false, Some("Hello"), time(23)));
+        assert!(!notify.allows("General", false, Some("Hello"), time(6)));
+        assert!(!notify.allows("Bots", true, None, time(12)));
+        assert!(!notify.allows("Off Topic", false, None, time(12)));
+        assert!(notify.allows("Off Topic", true, None, time(12)));
+
+        assert_eq!(
+            parse_quiet_hours("08:30 - 12:00"),
+            Ok((
+                NaiveTime::from_hms_opt(8, 30, 0).unwrap(),
+                NaiveTime::from_hms_opt(12, 0, 0).unwrap()
+            ))
+        );
+        assert!(parse_quiet_hours("night").is_err());
+    }
+
+    #[test]
+    fn templates() {
+        assert_eq!(
+            fill_template(
+                "{author} in {room} ({count}): {preview}",
+                "General",
+                "Butz",
+                2,
+                "Hello"
+            ),
+            "Butz in General (2): Hello"
+        );
+        assert_eq!(preview_text("first line\nsecond line"), "first line");
+        let long = "a".repeat(PREVIEW_LENGTH + 10);
+        assert_eq!(
+            preview_text(&long),
+            format!("{}…", "a".repeat(PREVIEW_LENGTH))
+        );
     }
 }
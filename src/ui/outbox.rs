@@ -0,0 +1,135 @@
+//! Client side queue delaying sent messages, so they can be taken back before reaching the server.
+use crate::backend::Token;
+use crate::config::Config;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// A message waiting in the [`Outbox`] until it is due.
+#[derive(Debug, PartialEq)]
+pub struct OutgoingMessage {
+    pub token: Token,
+    pub message: String,
+    /// Id of the message this one replies to.
+    pub reply_to: Option<i32>,
+    due: Instant,
+}
+
+/// Holds sent messages back for the configured `undo_send_seconds`.
+/// With a delay of zero messages are dispatched right away.
+pub struct Outbox {
+    delay: Duration,
+    pending: VecDeque<OutgoingMessage>,
+}
+
+impl Outbox {
+    pub fn new(config: &Config) -> Self {
+        Outbox {
+            delay: Duration::from_secs(config.data.ui.undo_send_seconds),
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.delay.is_zero()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Queue a message, it becomes due after the configured delay.
+    pub fn queue(&mut self, token: Token, message: String, reply_to: Option<i32>, now: Instant) {
+        log::debug!("Holding back message to {token} for {:?}", self.delay);
+        self.pending.push_back(OutgoingMessage {
+            token,
+            message,
+            reply_to,
+            due: now + self.delay,
+        });
+    }
+
+    /// Take back the last queued message for the given room.
+    pub fn undo_last(&mut self, token: &Token) -> Option<OutgoingMessage> {
+        let position = self
+            .pending
+            .iter()
+            .rposition(|outgoing| &outgoing.token == token)?;
+        self.pending.remove(position)
+    }
+
+    /// Time left until the next message is due, if any is queued.
+    pub fn time_to_next(&self, now: Instant) -> Option<Duration> {
+        self.pending
+            .front()
+            .map(|outgoing| outgoing.due.saturating_duration_since(now))
+    }
+
+    /// Take all messages which are due, in the order they were queued.
+    pub fn take_due(&mut self, now: Instant) -> Vec<OutgoingMessage> {
+        let due = self
+            .pending
+            .iter()
+            .take_while(|outgoing| outgoing.due <= now)
+            .count();
+        self.pending.drain(..due).collect()
+    }
+
+    /// Take all queued messages, e.g. when shutting down.
+    pub fn take_all(&mut self) -> Vec<OutgoingMessage> {
+        self.pending.drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::init;
+
+    use super::*;
+
+    #[test]
+    fn queue_undo_and_take_due() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let mut config = init("./test/").unwrap();
+        config.data.ui.undo_send_seconds = 5;
+
+        let mut outbox = Outbox::new(&config);
+        assert!(outbox.is_enabled());
+
+        let now = Instant::now();
+        outbox.queue(Token::from("123"), "first".to_string(), None, now);
+        outbox.queue(
+            Token::from("456"),
+            "other room".to_string(),
+            None,
+            now + Duration::from_secs(1),
+        );
+        outbox.queue(
+            Token::from("123"),
+            "second".to_string(),
+            Some(42),
+            now + Duration::from_secs(2),
+        );
+        assert_eq!(outbox.len(), 3);
+        assert_eq!(outbox.time_to_next(now), Some(Duration::from_secs(5)));
+
+        let undone = outbox.undo_last(&Token::from("123")).unwrap();
+        assert_eq!(undone.message, "second");
+        assert_eq!(undone.reply_to, Some(42));
+        assert!(outbox.undo_last(&Token::from("789")).is_none());
+
+        assert!(outbox.take_due(now).is_empty());
+        let due = outbox.take_due(now + Duration::from_secs(5));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].message, "first");
+        assert_eq!(outbox.take_all().len(), 1);
+        assert!(outbox.is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+//! Telling pasted images apart from pasted text, so they can be shared as files instead.
+use std::{
+    convert::TryFrom,
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "gif", "webp", "bmp", "svg"];
+
+/// Path of the image file if the pasted text is nothing else, like a file dropped on the terminal.
+pub fn pasted_image_path(text: &str) -> Option<PathBuf> {
+    let text = text.trim();
+    if text.is_empty() || text.contains('\n') {
+        return None;
+    }
+    let text = text
+        .strip_prefix("file://")
+        .unwrap_or(text)
+        .trim_matches(|c| c == '\'' || c == '"');
+    let path = PathBuf::from(text.replace("%20", " ").replace("\\ ", " "));
+    let is_image = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()));
+    (is_image && path.is_file()).then_some(path)
+}
+
+/// Check if the pasted data is binary, which would end up as garbage in the input.
+pub fn is_binary(text: &str) -> bool {
+    text.contains(char::REPLACEMENT_CHARACTER)
+        || text
+            .chars()
+            .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+}
+
+/// Name of the file to share the image at `path` as.
+pub fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || "Pasted image.png".to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+/// Get the image on the clipboard, encoded as PNG.
+pub fn clipboard_image_png() -> Result<Vec<u8>, Box<dyn Error>> {
+    let image = arboard::Clipboard::new()?.get_image()?;
+    let mut data = vec![];
+    let mut encoder = png::Encoder::new(
+        &mut data,
+        u32::try_from(image.width)?,
+        u32::try_from(image.height)?,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&image.bytes)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("my cat.PNG");
+        std::fs::write(&image, [0x89, b'P', b'N', b'G']).unwrap();
+        let text = dir.path().join("notes.txt");
+        std::fs::write(&text, "hello").unwrap();
+
+        let path = image.to_str().unwrap();
+        assert_eq!(pasted_image_path(path), Some(image.clone()));
+        assert_eq!(
+            pasted_image_path(&format!("'{path}'\n")),
+            Some(image.clone())
+        );
+        assert_eq!(
+            pasted_image_path(&format!("file://{}", path.replace(' ', "%20"))),
+            Some(image.clone())
+        );
+        assert_eq!(file_name(&image), "my cat.PNG");
+
+        assert!(pasted_image_path(text.to_str().unwrap()).is_none());
+        assert!(pasted_image_path(&format!("{path}\n{path}")).is_none());
+        assert!(pasted_image_path("/does/not/exist.png").is_none());
+        assert!(pasted_image_path("look at this").is_none());
+    }
+
+    #[test]
+    fn binary() {
+        assert!(!is_binary("Hello,\r\n\tWorld! 👋"));
+        assert!(is_binary("\u{89}PNG\r\n\u{1a}\n"));
+        assert!(is_binary("GIF89a\u{0}\u{1}"));
+        assert!(is_binary("\u{fffd}\u{fffd}"));
+    }
+}
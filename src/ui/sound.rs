@@ -0,0 +1,99 @@
+//! Playing a sound on new messages through a player of the system, see [`Sound`].
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// Players for sound files, the first one installed is used.
+const FILE_PLAYERS: [&str; 4] = ["pw-play", "paplay", "aplay", "afplay"];
+/// Player for sounds of the freedesktop sound theme, given by their name.
+const THEME_PLAYER: &str = "canberra-gtk-play";
+
+/// Find the program in the folders of `paths`, formatted like `PATH`.
+fn find_program(paths: &OsStr, name: &str) -> Option<PathBuf> {
+    std::env::split_paths(paths)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Check if the sound is a file, otherwise it is the name of a sound in the freedesktop theme,
+/// like `message-new-instant`.
+fn is_file(sound: &str) -> bool {
+    sound.contains('/') || Path::new(sound).extension().is_some()
+}
+
+/// Plays the sound configured for new messages, without waiting for it to end.
+#[derive(Debug, Clone, Default)]
+pub struct Sound {
+    /// Program and arguments playing the sound.
+    command: Vec<String>,
+}
+
+impl Sound {
+    /// Find a player for the sound file or name, `None` if none is installed.
+    pub fn new(sound: &str) -> Option<Self> {
+        Sound::find(sound, &std::env::var_os("PATH")?)
+    }
+
+    /// Find a player for the sound in the folders of `paths`.
+    fn find(sound: &str, paths: &OsStr) -> Option<Self> {
+        let command = if is_file(sound) {
+            let player = FILE_PLAYERS
+                .iter()
+                .find_map(|player| find_program(paths, player));
+            vec![player?.to_string_lossy().to_string(), sound.to_string()]
+        } else {
+            vec![
+                find_program(paths, THEME_PLAYER)?
+                    .to_string_lossy()
+                    .to_string(),
+                "-i".to_string(),
+                sound.to_string(),
+            ]
+        };
+        Some(Sound { command })
+    }
+
+    pub fn play(&self) {
+        let Some((program, args)) = self.command.split_first() else {
+            return;
+        };
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        match command.spawn() {
+            // Reaped in the background, so the player does not linger once done.
+            Ok(mut child) => _ = std::thread::spawn(move || child.wait()),
+            Err(why) => log::warn!("Could not play the sound with {program}: {why}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_and_names() {
+        assert!(is_file("/usr/share/sounds/freedesktop/stereo/message.oga"));
+        assert!(is_file("ping.wav"));
+        assert!(!is_file("message-new-instant"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let player = dir.path().join("paplay");
+        std::fs::write(&player, "").unwrap();
+        let paths = dir.path().as_os_str();
+        assert_eq!(find_program(paths, "paplay"), Some(player.clone()));
+        assert_eq!(
+            Sound::find("ping.wav", paths).unwrap().command,
+            [player.to_string_lossy().to_string(), "ping.wav".to_string()]
+        );
+        assert!(Sound::find("message-new-instant", paths).is_none());
+        // Without a player there is nothing to play.
+        Sound::default().play();
+    }
+}
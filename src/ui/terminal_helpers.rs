@@ -6,11 +6,15 @@ use color_eyre::{
 };
 use crossterm::{
     event::{
-        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
-        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+        DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        EnableFocusChange, EnableMouseCapture, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
     },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
 use ratatui::{prelude::CrosstermBackend, Terminal};
 use tracing::error;
@@ -76,21 +80,48 @@ fn install_eyre_hook(eyre_hook: EyreHook, config: &Config) -> color_eyre::Result
     Ok(())
 }
 
+/// Set up the terminal, also telling if it reports keys with [`KeyboardEnhancementFlags`].
 pub fn init(
     get_enable_mouse: bool,
     get_enable_paste: bool,
-) -> eyre::Result<Terminal<CrosstermBackend<std::io::Stdout>>> {
+    get_report_all_keys: bool,
+) -> eyre::Result<(Terminal<CrosstermBackend<std::io::Stdout>>, bool)> {
+    use std::io::stdout;
+
+    let keyboard_enhanced = enter_tui(get_enable_mouse, get_enable_paste, get_report_all_keys)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+    terminal.clear()?;
+    terminal.hide_cursor()?;
+    Ok((terminal, keyboard_enhanced))
+}
+
+/// Switch the terminal into the modes the TUI needs, undone by [`restore`].
+/// Returns if the terminal reports keys with [`KeyboardEnhancementFlags`], which tells
+/// e.g. Shift+Enter from Enter. With `get_report_all_keys` the terminal also reports printable
+/// keys as escape codes, with the shifted key, which [`super::keys::translate`] handles.
+fn enter_tui(
+    get_enable_mouse: bool,
+    get_enable_paste: bool,
+    get_report_all_keys: bool,
+) -> eyre::Result<bool> {
     use std::io::stdout;
 
     enable_raw_mode()?;
     execute!(stdout(), EnterAlternateScreen)?;
-    if execute!(
-        stdout(),
-        PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
-    )
-    .is_err()
-    {
-        log::warn!("Consider using a Terminal that supports KeyboardEnhancementFlags.");
+    // Only push the flags to terminals answering the query for them, others may report
+    // printable keys wrongly then, like Shift+'/' instead of '?'.
+    let keyboard_enhanced = supports_keyboard_enhancement().unwrap_or(false);
+    if keyboard_enhanced {
+        let mut flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES;
+        if get_report_all_keys {
+            flags |= KeyboardEnhancementFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES
+                | KeyboardEnhancementFlags::REPORT_ALTERNATE_KEYS;
+        }
+        execute!(stdout(), PushKeyboardEnhancementFlags(flags))?;
+    } else {
+        log::warn!(
+            "The terminal does not support KeyboardEnhancementFlags, use Ctrl+j instead of Shift+Enter."
+        );
     }
     if get_enable_mouse {
         execute!(stdout(), EnableMouseCapture)?;
@@ -98,15 +129,125 @@ pub fn init(
     if get_enable_paste {
         execute!(stdout(), EnableBracketedPaste)?;
     }
-    let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
-    terminal.clear()?;
-    terminal.hide_cursor()?;
-    Ok(terminal)
+    // Syncs slow down while unfocused, terminals not reporting focus changes ignore this.
+    execute!(stdout(), EnableFocusChange)?;
+    Ok(keyboard_enhanced)
+}
+
+/// Leave the TUI and edit `text` in `$VISUAL`, `$EDITOR` or `vi`, returning the saved text.
+/// The TUI is entered again afterwards, even if the editor failed.
+pub fn edit_externally(
+    text: &str,
+    get_enable_mouse: bool,
+    get_enable_paste: bool,
+    get_report_all_keys: bool,
+) -> eyre::Result<String> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let path = std::env::temp_dir().join(format!("sechat-draft-{}.md", std::process::id()));
+    std::fs::write(&path, text)?;
+
+    restore(get_enable_mouse, get_enable_paste)?;
+    // The editor may come with arguments, e.g. "code --wait".
+    let mut words = editor.split_whitespace();
+    let status = std::process::Command::new(words.next().unwrap_or("vi"))
+        .args(words)
+        .arg(&path)
+        .status();
+    enter_tui(get_enable_mouse, get_enable_paste, get_report_all_keys)?;
+
+    let edited = match status {
+        Ok(status) if status.success() => std::fs::read_to_string(&path),
+        Ok(status) => Err(std::io::Error::other(format!(
+            "{editor} exited with {status}"
+        ))),
+        Err(why) => Err(why),
+    };
+    let _ = std::fs::remove_file(&path);
+    let edited = edited?;
+    // Editors end the file with a newline, which is not part of the message.
+    Ok(edited.strip_suffix('\n').unwrap_or(&edited).to_string())
+}
+
+/// Copy the text to the system clipboard via the OSC 52 escape sequence of the terminal.
+pub fn copy_to_clipboard(text: &str) -> std::io::Result<()> {
+    use base64::prelude::*;
+    use std::io::{stdout, Write};
+
+    let mut out = stdout();
+    write!(out, "\x1b]52;c;{}\x07", BASE64_STANDARD.encode(text))?;
+    out.flush()
+}
+
+/// Signals asking the app to end, after which the chat logs should be written before exiting.
+/// Only `SIGTERM` and `SIGHUP` on unix, elsewhere no signal is ever received.
+pub struct ExitSignals {
+    #[cfg(unix)]
+    term: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    hup: tokio::signal::unix::Signal,
+}
+
+impl ExitSignals {
+    /// Handle the signals instead of being killed by them.
+    pub fn new() -> std::io::Result<Self> {
+        cfg_if! {
+            if #[cfg(unix)] {
+                use tokio::signal::unix::{signal, SignalKind};
+
+                Ok(ExitSignals {
+                    term: signal(SignalKind::terminate())?,
+                    hup: signal(SignalKind::hangup())?,
+                })
+            } else {
+                Ok(ExitSignals {})
+            }
+        }
+    }
+
+    /// Wait for the next signal, returning its name.
+    pub async fn recv(&mut self) -> &'static str {
+        cfg_if! {
+            if #[cfg(unix)] {
+                tokio::select! {
+                    _ = self.term.recv() => "SIGTERM",
+                    _ = self.hup.recv() => "SIGHUP",
+                }
+            } else {
+                std::future::pending().await
+            }
+        }
+    }
+}
+
+/// Open the url with the default browser of the system.
+pub fn open_in_browser(url: &str) -> std::io::Result<()> {
+    use std::process::{Command, Stdio};
+
+    cfg_if! {
+        if #[cfg(target_os = "macos")] {
+            let mut command = Command::new("open");
+        } else if #[cfg(target_os = "windows")] {
+            // Not `cmd /C start`, as cmd would split the url at every `&`.
+            let mut command = Command::new("rundll32");
+            command.arg("url.dll,FileProtocolHandler");
+        } else {
+            let mut command = Command::new("xdg-open");
+        }
+    }
+    command
+        .arg(url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
 }
 
 pub fn restore(get_enable_mouse: bool, get_enable_paste: bool) -> eyre::Result<()> {
     use std::io::stdout;
 
+    execute!(stdout(), DisableFocusChange)?;
     if get_enable_paste {
         execute!(stdout(), DisableBracketedPaste)?;
     }
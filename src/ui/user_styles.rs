@@ -0,0 +1,123 @@
+//! Colors of the names of users in the chat and the participant list, see [`UserStyles`].
+use ratatui::style::{Color, Style};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// Colors names are drawn in, readable on dark and light backgrounds.
+const PALETTE: [Color; 12] = [
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+];
+
+/// Pick the color of the user by a FNV-1a hash of the id, which unlike the hasher of the
+/// standard library is the same on every run and Rust version.
+fn hashed_color(user: &str) -> Color {
+    let hash = user.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+    PALETTE[hash as usize % PALETTE.len()]
+}
+
+/// Color of each user by their actor id, picked by a hash of the id when first seen.
+///
+/// Persisted as `UserStyles.json` in the data dir of the server, so colors can be changed by hand
+/// and stay the same if the palette changes.
+#[derive(Debug, Default)]
+pub struct UserStyles {
+    path: PathBuf,
+    colors: BTreeMap<String, Color>,
+}
+
+impl UserStyles {
+    /// Read the colors from the data dir, starting with none if there is no readable map.
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join("UserStyles.json");
+        UserStyles {
+            colors: UserStyles::read(&path),
+            path,
+        }
+    }
+
+    fn read(path: &Path) -> BTreeMap<String, Color> {
+        if !path.exists() {
+            return BTreeMap::new();
+        }
+        std::fs::read_to_string(path)
+            .map_err(|why| why.to_string())
+            .and_then(|data| serde_json::from_str(&data).map_err(|why| why.to_string()))
+            .unwrap_or_else(|why| {
+                log::warn!("Failed to read user colors from {}: {why}", path.display());
+                BTreeMap::new()
+            })
+    }
+
+    /// Get the style of the name of the user, writing the map to disk if the user is new.
+    pub fn style_for(&mut self, user: &str) -> Style {
+        if let Some(color) = self.colors.get(user) {
+            return Style::new().fg(*color);
+        }
+        let color = hashed_color(user);
+        self.colors.insert(user.to_string(), color);
+        if let Err(why) = self.save() {
+            log::warn!(
+                "Failed to write user colors to {}: {why}",
+                self.path.display()
+            );
+        }
+        Style::new().fg(color)
+    }
+
+    /// Write the map, keeping users added by others sharing the file, like the participant list.
+    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut colors = UserStyles::read(&self.path);
+        colors.extend(self.colors.clone());
+        std::fs::write(&self.path, serde_json::to_string_pretty(&colors)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persist() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut styles = UserStyles::load(dir.path());
+        let butz = styles.style_for("butz");
+        assert_eq!(butz, Style::new().fg(hashed_color("butz")));
+        assert_eq!(styles.style_for("butz"), butz);
+
+        let mut other = UserStyles::load(dir.path());
+        other.style_for("hans");
+        styles.style_for("bert");
+        let colors = UserStyles::read(&dir.path().join("UserStyles.json"));
+        assert_eq!(colors.keys().collect::<Vec<_>>(), ["bert", "butz", "hans"]);
+
+        std::fs::write(
+            dir.path().join("UserStyles.json"),
+            r##"{"butz": "#010203"}"##,
+        )
+        .unwrap();
+        assert_eq!(
+            UserStyles::load(dir.path()).style_for("butz"),
+            Style::new().fg(Color::Rgb(1, 2, 3))
+        );
+
+        std::fs::write(dir.path().join("UserStyles.json"), "butz").unwrap();
+        assert!(UserStyles::load(dir.path()).colors.is_empty());
+    }
+}
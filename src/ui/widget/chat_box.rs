@@ -1,36 +1,74 @@
-use crate::backend::nc_request::Token;
-use crate::backend::{nc_room::NCRoomInterface, nc_talk::NCBackend};
+use crate::backend::{
+    chat_message::ChatMessage, chat_types::ParticipantChange, ChatProvider, ChatRoom, Token,
+};
 use crate::config::Config;
-use chrono::{DateTime, Local, Utc};
+use crate::ui::{user_styles::UserStyles, widget::room_info::last_synced_text};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use itertools::Itertools;
 use ratatui::{
     prelude::*,
     widgets::{Block, Cell, HighlightSpacing, Row, Table, TableState},
 };
 use textwrap::Options;
 
-// this fits my name, so 20 it is :D
-const NAME_WIDTH: u16 = 20;
 const TIME_WIDTH: u16 = 5;
+/// Narrowest and widest name column.
+const NAME_WIDTHS: std::ops::RangeInclusive<u16> = 8..=40;
 
 #[derive(Default)]
 pub struct ChatBox<'a> {
     messages: Vec<Row<'a>>,
+    /// Id of the message each row belongs to, `None` for date and marker rows.
+    row_message_ids: Vec<Option<i32>>,
+    /// Lines taken by each row, to keep the selection in view.
+    row_heights: Vec<u16>,
+    /// Index and local day of each date row.
+    date_rows: Vec<(usize, NaiveDate)>,
     current_index: usize,
     width: u16,
+    name_width: u16,
+    /// Area the chat was drawn in last, empty before the first draw.
+    area: Rect,
     state: TableState,
     default_style: Style,
     default_highlight_style: Style,
     unread_message_style: Style,
     table_header_style: Style,
+    participant_change_style: Style,
+    read_receipt_style: Style,
     date_format: String,
+    /// User id of the logged in user, read receipts are shown on own messages.
+    own_user: String,
+    /// Read receipts are hidden when the own read status is private, as the server hides them too.
+    show_read_receipts: bool,
+    /// Unix timestamp of the last sync of the shown room.
+    last_synced_at: i64,
+    stale_after_seconds: i64,
+    /// Room the rows were built for, the selection is only kept across updates of the same room.
+    shown_room: Token,
+    /// Id of the newest message shown.
+    last_message_id: Option<i32>,
+    /// Messages which arrived below the selection while scrolled up.
+    new_below: usize,
+    /// Colors of the author names, `None` if names are not colored.
+    user_styles: Option<UserStyles>,
 }
 
 impl ChatBox<'_> {
     pub fn new(config: &Config) -> Self {
         ChatBox {
             messages: Vec::new(),
+            row_message_ids: Vec::new(),
+            row_heights: Vec::new(),
+            date_rows: Vec::new(),
             current_index: 0,
             width: 10,
+            name_width: config
+                .data
+                .ui
+                .name_width
+                .clamp(*NAME_WIDTHS.start(), *NAME_WIDTHS.end()),
+            area: Rect::default(),
             state: TableState::default().with_offset(0).with_selected(0),
             unread_message_style: config
                 .theme
@@ -39,37 +77,108 @@ impl ChatBox<'_> {
             default_style: config.theme.default_style(),
             default_highlight_style: config.theme.default_highlight_style(),
             table_header_style: config.theme.table_header_style(),
+            participant_change_style: config
+                .theme
+                .default_style()
+                .add_modifier(Modifier::DIM | Modifier::ITALIC),
+            read_receipt_style: config.theme.default_style().add_modifier(Modifier::DIM),
             date_format: config.data.ui.date_format.clone(),
+            own_user: config.data.general.user.clone(),
+            show_read_receipts: config.data.general.read_status_private != Some(true),
+            last_synced_at: 0,
+            stale_after_seconds: config.data.ui.stale_after_seconds,
+            shown_room: Token::new(),
+            last_message_id: None,
+            new_below: 0,
+            user_styles: config
+                .data
+                .ui
+                .colored_names
+                .then(|| UserStyles::load(&config.get_server_data_dir())),
         }
     }
 
     pub fn set_width_and_update_if_change(
         &mut self,
         width: u16,
-        backend: &impl NCBackend,
+        backend: &impl ChatProvider,
         current_room: &Token,
     ) {
-        let new_width = (width - TIME_WIDTH - 2 - NAME_WIDTH).max(10);
+        let new_width = width
+            .saturating_sub(TIME_WIDTH + 2 + self.name_width)
+            .max(10);
         if self.width != new_width {
             self.width = new_width;
             self.update_messages(backend, current_room);
         }
     }
 
-    pub fn update_messages(&mut self, backend: &impl NCBackend, current_room: &Token) {
-        use itertools::Itertools;
+    /// Rebuild the rows of the room. While scrolled up in the same room the selected message stays
+    /// where it is and newer messages are counted, at the bottom the selection follows new messages.
+    pub fn update_messages(&mut self, backend: &impl ChatProvider, current_room: &Token) {
+        let same_room = self.shown_room == *current_room;
+        let at_bottom = self.current_index + 1 >= self.messages.len();
+        let anchor = self.selected_message_id();
+        let previous_index = self.current_index;
+        let previous_last = self.last_message_id;
+
+        self.build_rows(backend, current_room);
+        self.last_message_id = self.row_message_ids.iter().flatten().max().copied();
+
+        if !same_room {
+            self.shown_room.clone_from(current_room);
+            self.new_below = 0;
+        } else if at_bottom && !self.messages.is_empty() {
+            self.select_last_message();
+        } else {
+            self.new_below += self
+                .row_message_ids
+                .iter()
+                .flatten()
+                .filter(|id| previous_last.is_some_and(|last| **id > last))
+                .unique()
+                .count();
+            let index = anchor
+                .and_then(|id| self.row_message_ids.iter().position(|row| *row == Some(id)))
+                .unwrap_or(previous_index)
+                .min(self.messages.len().saturating_sub(1));
+            // Move the viewport along with the selection, so rows added above it do not shift it.
+            let offset = self.state.offset() + index;
+            *self.state.offset_mut() = offset.saturating_sub(previous_index);
+            self.current_index = index;
+            self.state.select(Some(index));
+        }
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn build_rows(&mut self, backend: &impl ChatProvider, current_room: &Token) {
         use std::convert::TryInto;
 
         self.messages.clear();
+        self.row_message_ids.clear();
+        self.row_heights.clear();
+        self.date_rows.clear();
         let mut last_date = DateTime::<Utc>::MIN_UTC
             .format(&self.date_format)
             .to_string();
+        let mut participant_changes = backend
+            .get_room(current_room)
+            .get_participant_changes()
+            .iter()
+            .peekable();
+        let last_common_read = backend.get_room(current_room).get_last_common_read();
+        self.last_synced_at = backend.get_room(current_room).get_last_synced_at();
         for message_data in backend
             .get_room(current_room)
             .get_messages()
             .values()
             .filter(|mes| !mes.is_reaction() && !mes.is_edit_note() && !mes.is_comment_deleted())
         {
+            while let Some(change) = participant_changes
+                .next_if(|change| change.timestamp < message_data.get_timestamp())
+            {
+                self.push_participant_change(change);
+            }
             let date_str = message_data.get_date_str(&self.date_format);
             if date_str != last_date {
                 let mut date: Vec<Cell> = vec![
@@ -86,20 +195,36 @@ impl ChatBox<'_> {
                             .into(),
                     ];
                 }
+                self.date_rows.push((
+                    self.messages.len(),
+                    DateTime::<Local>::from(
+                        DateTime::<Utc>::from_timestamp(message_data.get_timestamp(), 0)
+                            .unwrap_or_default(),
+                    )
+                    .date_naive(),
+                ));
                 self.messages.push(Row::new(date));
+                self.row_message_ids.push(None);
+                self.row_heights.push(1);
                 last_date = date_str;
             }
 
+            let name_style = self
+                .user_styles
+                .as_mut()
+                .filter(|_| message_data.get_name() != "System")
+                .map_or_else(Style::new, |styles| {
+                    styles.style_for(message_data.get_actor_id())
+                });
             let name = textwrap::wrap(
                 message_data.get_name().to_string().as_str(),
-                Options::new(NAME_WIDTH.into()).break_words(true),
+                Options::new(self.name_width.into()).break_words(true),
             )
             .into_iter()
-            .map(std::borrow::Cow::into_owned)
-            .map(Line::from)
+            .map(|line| Line::styled(line.into_owned(), name_style))
             .collect_vec();
 
-            let message_string = message_data
+            let mut message_string = message_data
                 .get_message()
                 .split('\n')
                 .flat_map(|cell| {
@@ -110,6 +235,12 @@ impl ChatBox<'_> {
                         .collect_vec()
                 })
                 .collect_vec();
+            if let (Some(receipt), Some(last_line)) = (
+                self.read_receipt(message_data, last_common_read),
+                message_string.last_mut(),
+            ) {
+                last_line.push_span(receipt);
+            }
 
             let row_height: u16 = if message_string.len() > name.len() {
                 message_string.len().try_into().expect("message too long")
@@ -123,6 +254,8 @@ impl ChatBox<'_> {
             ];
 
             self.messages.push(Row::new(message).height(row_height));
+            self.row_message_ids.push(Some(message_data.get_id()));
+            self.row_heights.push(row_height);
 
             if message_data.has_reactions() {
                 let reaction: Vec<Cell> = vec![
@@ -131,6 +264,8 @@ impl ChatBox<'_> {
                     message_data.get_reactions_str().into(),
                 ];
                 self.messages.push(Row::new(reaction));
+                self.row_message_ids.push(Some(message_data.get_id()));
+                self.row_heights.push(1);
             }
             if backend.get_room(current_room).has_unread()
                 && backend.get_room(current_room).get_last_read() == message_data.get_id()
@@ -141,50 +276,307 @@ impl ChatBox<'_> {
                     Span::styled("+++ LAST READ +++", self.unread_message_style).into(),
                 ];
                 self.messages.push(Row::new(unread_marker));
+                self.row_message_ids.push(None);
+                self.row_heights.push(1);
             }
         }
+        for change in participant_changes {
+            self.push_participant_change(change);
+        }
+    }
+
+    /// Receipt appended to own messages, showing if everyone sharing their read status read it.
+    fn read_receipt(&self, message: &ChatMessage, last_common_read: i32) -> Option<Span<'static>> {
+        if !self.show_read_receipts || !message.is_from_user(&self.own_user) {
+            return None;
+        }
+        let receipt = if message.get_id() <= last_common_read {
+            " ✓✓"
+        } else {
+            " ✓"
+        };
+        Some(Span::styled(receipt, self.read_receipt_style))
+    }
+
+    /// Add a subtle line announcing a participant joining or leaving.
+    fn push_participant_change(&mut self, change: &ParticipantChange) {
+        let time: DateTime<Local> = DateTime::from(
+            DateTime::<Utc>::from_timestamp(change.timestamp, 0).unwrap_or_default(),
+        );
+        let text = format!(
+            "{} {}",
+            change.display_name,
+            if change.joined { "joined" } else { "left" }
+        );
+        self.messages.push(Row::new(vec![
+            Cell::from(time.format("%H:%M").to_string()),
+            "".into(),
+            Span::styled(text, self.participant_change_style).into(),
+        ]));
+        self.row_message_ids.push(None);
+        self.row_heights.push(1);
     }
 
     pub fn select_last_message(&mut self) {
         log::trace!("messages length: {}", self.messages.len());
-        self.current_index = self.messages.len().saturating_sub(1);
-        self.state.select(Some(self.current_index));
+        self.select_index(self.messages.len().saturating_sub(1));
+        self.new_below = 0;
+    }
+
+    pub fn select_first(&mut self) {
+        self.select_index(0);
+    }
+
+    /// Select the first row of the day of the selection, or of the day before when already there.
+    /// Returns if there was such a day.
+    pub fn select_previous_day(&mut self) -> bool {
+        let date_row = self
+            .date_rows
+            .iter()
+            .rev()
+            .find(|(index, _)| index + 1 < self.current_index);
+        self.select_below(date_row.map(|(index, _)| *index))
+    }
+
+    /// Select the first row of the next day, returns if there is one.
+    pub fn select_next_day(&mut self) -> bool {
+        let date_row = self
+            .date_rows
+            .iter()
+            .find(|(index, _)| index + 1 > self.current_index);
+        self.select_below(date_row.map(|(index, _)| *index))
+    }
+
+    /// Select the first row of `date` or of the next day with messages, returns if there was one.
+    pub fn select_date(&mut self, date: NaiveDate) -> bool {
+        let date_row = self.date_rows.iter().find(|(_, day)| *day >= date);
+        self.select_below(date_row.map(|(index, _)| *index))
+    }
+
+    /// Select the row below the date row `date_row`, the first of its day.
+    fn select_below(&mut self, date_row: Option<usize>) -> bool {
+        if let Some(index) = date_row {
+            self.select_index(index + 1);
+        }
+        date_row.is_some()
+    }
+
+    /// Messages which arrived below the selection while scrolled up.
+    pub fn new_below(&self) -> usize {
+        self.new_below
+    }
+
+    /// Check if the first row is selected, to fetch older messages when scrolling further up.
+    pub fn is_at_top(&self) -> bool {
+        self.current_index == 0
+    }
+
+    /// Get the id of the oldest message shown.
+    pub fn first_message_id(&self) -> Option<i32> {
+        self.row_message_ids.iter().flatten().next().copied()
+    }
+
+    /// Select the first row of the message with the given id, if it is shown.
+    pub fn select_message(&mut self, message_id: i32) {
+        if let Some(index) = self
+            .row_message_ids
+            .iter()
+            .position(|id| *id == Some(message_id))
+        {
+            self.select_index(index);
+        }
+    }
+
+    /// Get the id of the selected message, reaction rows belong to the message above them.
+    pub fn selected_message_id(&self) -> Option<i32> {
+        self.row_message_ids
+            .get(self.state.selected()?)
+            .copied()
+            .flatten()
+    }
+
+    /// Check if the shown room was not synced for longer than `stale_after_seconds`, e.g. after network loss.
+    pub fn is_stale(&self, now: i64) -> bool {
+        self.last_synced_at > 0 && now - self.last_synced_at > self.stale_after_seconds
     }
 
     pub fn render_area(&self, frame: &mut Frame, area: Rect) {
         frame.render_stateful_widget(self, area, &mut self.state.clone());
     }
 
+    /// Widen or narrow the name column by `change`.
+    /// The rows are rebuilt on the next [`ChatBox::set_width_and_update_if_change`].
+    pub fn change_name_width(&mut self, change: i16) {
+        self.name_width = self
+            .name_width
+            .saturating_add_signed(change)
+            .clamp(*NAME_WIDTHS.start(), *NAME_WIDTHS.end());
+        self.width = 0;
+    }
+
+    pub fn name_width(&self) -> u16 {
+        self.name_width
+    }
+
+    /// Remember the area of the chat, which page jumps, the offset and clicks depend on.
+    pub fn set_area(&mut self, area: Rect) {
+        if self.area != area {
+            self.area = area;
+            self.follow_selection();
+        }
+    }
+
     pub fn select_up(&mut self) {
-        self.current_index = self
-            .current_index
-            .saturating_sub(1)
-            .clamp(0, self.messages.len() - 1);
-        self.state.select(Some(self.current_index));
+        self.select_up_by(1);
     }
 
     pub fn select_down(&mut self) {
-        self.current_index = self
-            .current_index
-            .saturating_add(1)
-            .clamp(0, self.messages.len() - 1);
-        self.state.select(Some(self.current_index));
+        self.select_down_by(1);
+    }
+
+    pub fn select_up_by(&mut self, rows: usize) {
+        self.select_index(self.current_index.saturating_sub(rows));
+    }
+
+    pub fn select_down_by(&mut self, rows: usize) {
+        self.select_index(self.current_index.saturating_add(rows));
     }
-    pub fn select_line(&mut self, position: Position) -> Result<(), Box<dyn std::error::Error>> {
-        log::debug!(
-            "Got Position {:?} and selected {:?}",
-            position,
-            self.state.selected().ok_or("nothing selected")?
+
+    /// Move the view and the selection up by half the height of the chat, like `Ctrl+u` in vim.
+    pub fn half_page_up(&mut self) {
+        self.move_page_up(usize::from(self.visible_lines() / 2));
+    }
+
+    /// Move the view and the selection down by half the height of the chat, like `Ctrl+d` in vim.
+    pub fn half_page_down(&mut self) {
+        self.move_page_down(usize::from(self.visible_lines() / 2));
+    }
+
+    /// Move the view and the selection up by the height of the chat.
+    pub fn page_up(&mut self) {
+        self.move_page_up(usize::from(self.visible_lines()));
+    }
+
+    /// Move the view and the selection down by the height of the chat.
+    pub fn page_down(&mut self) {
+        self.move_page_down(usize::from(self.visible_lines()));
+    }
+
+    fn move_page_up(&mut self, lines: usize) {
+        let rows = rows_in_lines(self.row_heights[..self.current_index].iter().rev(), lines);
+        *self.state.offset_mut() = self.state.offset().saturating_sub(rows);
+        self.select_up_by(rows);
+    }
+
+    fn move_page_down(&mut self, lines: usize) {
+        let rows = rows_in_lines(self.row_heights.iter().skip(self.current_index + 1), lines);
+        *self.state.offset_mut() = self.state.offset() + rows;
+        self.select_down_by(rows);
+    }
+
+    /// Scroll the view up by `lines`, as by the mouse wheel.
+    /// The selection only moves when it would leave the view.
+    pub fn scroll_view_up(&mut self, lines: usize) {
+        let offset = self.state.offset();
+        let rows = rows_in_lines(self.row_heights[..offset].iter().rev(), lines);
+        *self.state.offset_mut() = offset.saturating_sub(rows);
+        if self.current_index > self.last_visible_row() {
+            self.select_index(self.last_visible_row());
+        }
+    }
+
+    /// Scroll the view down by `lines`, as by the mouse wheel.
+    /// The selection only moves when it would leave the view.
+    pub fn scroll_view_down(&mut self, lines: usize) {
+        let offset = self.state.offset();
+        let rows = rows_in_lines(self.row_heights.iter().skip(offset), lines);
+        *self.state.offset_mut() = (offset + rows).min(self.messages.len().saturating_sub(1));
+        if self.current_index < self.state.offset() {
+            self.select_index(self.state.offset());
+        }
+    }
+
+    /// Index of the last row shown completely from the current offset on.
+    fn last_visible_row(&self) -> usize {
+        let offset = self.state.offset();
+        let rows = rows_in_lines(
+            self.row_heights.iter().skip(offset),
+            usize::from(self.visible_lines()),
         );
+        (offset + rows - 1).min(self.messages.len().saturating_sub(1))
+    }
 
-        // let new_selection = state.selected().ok_or("nothing selected")?;
-        // self.current_index = position
-        //     .y
-        //     .clamp(0, (self.messages.len() - 1).try_into()?)
-        //     .try_into()?;
-        // Ok(())
-        todo!("commented code missing?");
+    /// Lines above the rows, the header and the stale data note.
+    fn lines_above_rows(&self) -> u16 {
+        1 + u16::from(self.is_stale(Utc::now().timestamp()))
     }
+
+    /// Lines available to rows, below the header and the stale data note.
+    fn visible_lines(&self) -> u16 {
+        self.area.height.saturating_sub(self.lines_above_rows())
+    }
+
+    fn select_index(&mut self, index: usize) {
+        self.current_index = index.min(self.messages.len().saturating_sub(1));
+        self.state.select(Some(self.current_index));
+        if self.current_index + 1 == self.messages.len() {
+            self.new_below = 0;
+        }
+        self.follow_selection();
+    }
+
+    /// Move the offset of the table just enough to show the selected row completely.
+    fn follow_selection(&mut self) {
+        if self.area.is_empty() {
+            return;
+        }
+        let visible = usize::from(self.visible_lines()).max(1);
+        let mut offset = self.state.offset().min(self.current_index);
+        while offset < self.current_index
+            && self.row_heights[offset..=self.current_index]
+                .iter()
+                .map(|height| usize::from(*height))
+                .sum::<usize>()
+                > visible
+        {
+            offset += 1;
+        }
+        *self.state.offset_mut() = offset;
+    }
+
+    /// Select the row shown at `position`, returns if there was one.
+    pub fn select_line(&mut self, position: Position) -> bool {
+        if !self.area.contains(position) || position.y < self.area.y + self.lines_above_rows() {
+            return false;
+        }
+        let line = usize::from(position.y - self.area.y - self.lines_above_rows());
+        let mut top = 0;
+        for (index, height) in self
+            .row_heights
+            .iter()
+            .enumerate()
+            .skip(self.state.offset())
+        {
+            top += usize::from(*height);
+            if line < top {
+                self.select_index(index);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Count the rows taking up to `lines`, but at least one.
+fn rows_in_lines<'h>(heights: impl Iterator<Item = &'h u16>, lines: usize) -> usize {
+    let mut taken = 0;
+    heights
+        .take_while(|height| {
+            taken += usize::from(**height);
+            taken <= lines
+        })
+        .count()
+        .max(1)
 }
 
 impl StatefulWidget for &ChatBox<'_> {
@@ -193,21 +585,48 @@ impl StatefulWidget for &ChatBox<'_> {
         // Columns widths are constrained in the same way as Layout...
         let widths = [
             Constraint::Length(TIME_WIDTH),
-            Constraint::Length(NAME_WIDTH),
+            Constraint::Length(self.name_width),
             Constraint::Min(10),
         ];
+        let block = if self.is_stale(Utc::now().timestamp()) {
+            Block::default().title(
+                Line::styled(
+                    format!(
+                        "stale data, last synced {}",
+                        last_synced_text(self.last_synced_at, Utc::now().timestamp())
+                    ),
+                    self.unread_message_style,
+                )
+                .right_aligned(),
+            )
+        } else {
+            Block::default()
+        };
         StatefulWidget::render(
             Table::new(self.messages.clone(), widths)
                 .column_spacing(1)
                 .style(self.default_style)
                 .header(Row::new(vec!["Time", "Name", "Message"]).style(self.table_header_style))
-                .block(Block::default())
+                .block(block)
                 .row_highlight_style(self.default_highlight_style)
                 .highlight_spacing(HighlightSpacing::Never),
             area,
             buf,
             state,
         );
+        if self.new_below > 0 && area.height > 1 {
+            let text = if self.new_below == 1 {
+                " 1 new message ↓ ".to_string()
+            } else {
+                format!(" {} new messages ↓ ", self.new_below)
+            };
+            Line::from(Span::styled(
+                text,
+                self.unread_message_style.add_modifier(Modifier::REVERSED),
+            ))
+            .right_aligned()
+            .render(Rect::new(area.x, area.bottom() - 1, area.width, 1), buf);
+        }
     }
 }
 
@@ -216,13 +635,11 @@ mod tests {
 
     use std::collections::BTreeMap;
 
-    use crate::backend::nc_message::NCMessage;
-    use crate::backend::nc_request::{NCReqDataMessage, NCReqDataParticipants};
-    use crate::backend::nc_room::MockNCRoomInterface;
+    use crate::backend::chat_room::MockChatRoom;
+    use crate::backend::nc_request::NCReqDataMessage;
     use crate::backend::nc_talk::MockNCTalk;
     use crate::config::init;
     use backend::TestBackend;
-    use chrono::{DateTime, Local, Utc};
 
     use super::*;
 
@@ -235,18 +652,20 @@ mod tests {
         let config = init("./test/").unwrap();
 
         let mut mock_nc_backend = MockNCTalk::new();
-        let mut mock_room = MockNCRoomInterface::new();
+        let mut mock_room = MockChatRoom::new();
         let timestamp_1 = DateTime::<Utc>::from_timestamp(2000, 0).unwrap();
-        let mock_message_1 = NCMessage::from(NCReqDataMessage {
+        let mock_message_1 = ChatMessage::from(NCReqDataMessage {
             id: 0,
             message: "Butz".to_string(),
             messageType: "comment".to_string(),
+            actorType: "users".to_string(),
+            actorId: "dummy_user".to_string(),
             actorDisplayName: "Hundi".to_string(),
             timestamp: timestamp_1.timestamp(),
             ..Default::default()
         });
         let timestamp_2 = DateTime::<Utc>::from_timestamp(200_000, 0).unwrap();
-        let mock_message_2 = NCMessage::from(NCReqDataMessage {
+        let mock_message_2 = ChatMessage::from(NCReqDataMessage {
             id: 1,
             message: "Bert".to_string(),
             messageType: "comment".to_string(),
@@ -260,16 +679,32 @@ mod tests {
         let mut terminal = Terminal::new(backend).unwrap();
         let mut chat_box = ChatBox::new(&config);
 
-        let mut dummy_user = NCReqDataParticipants::default();
-        dummy_user.displayName = "Butz".to_string();
         mock_room
             .expect_get_messages()
             .once()
             .return_const(message_tree);
+        let timestamp_change = DateTime::<Utc>::from_timestamp(100_000, 0).unwrap();
+        mock_room
+            .expect_get_participant_changes()
+            .once()
+            .return_const(vec![ParticipantChange {
+                timestamp: timestamp_change.timestamp(),
+                display_name: "Hundi".to_string(),
+                joined: false,
+            }]);
+        mock_room
+            .expect_get_last_common_read()
+            .once()
+            .return_const(0);
+        let last_synced_at = Utc::now().timestamp();
+        mock_room
+            .expect_get_last_synced_at()
+            .once()
+            .return_const(last_synced_at);
         mock_room.expect_has_unread().times(2).return_const(false);
         mock_nc_backend
             .expect_get_room()
-            .times(3)
+            .times(6)
             .return_const(mock_room);
 
         terminal
@@ -303,14 +738,14 @@ mod tests {
         let mut expected = Buffer::with_lines([
             "Time  Name                 Message      ",
             "                           Thursday 01 J",
-            "01:33 Hundi                Butz         ",
+            "01:33 Hundi                Butz ✓✓      ",
+            "04:46                      Hundi left   ",
             "                           Saturday 03 J",
             "08:33 Stinko               Bert         ",
             "                                        ",
             "                                        ",
             "                                        ",
             "                                        ",
-            "                                        ",
         ]);
         expected.set_style(Rect::new(0, 0, 40, 10), config.theme.default_style());
         expected.set_style(Rect::new(0, 0, 40, 1), config.theme.table_header_style());
@@ -326,7 +761,18 @@ mod tests {
                 .add_modifier(Modifier::BOLD),
         );
         expected.set_style(
-            Rect::new(27, 3, 13, 1),
+            Rect::new(31, 2, 3, 1),
+            config.theme.default_style().add_modifier(Modifier::DIM),
+        );
+        expected.set_style(
+            Rect::new(27, 3, 10, 1),
+            config
+                .theme
+                .default_style()
+                .add_modifier(Modifier::DIM | Modifier::ITALIC),
+        );
+        expected.set_style(
+            Rect::new(27, 4, 13, 1),
             config
                 .theme
                 .unread_message_style()
@@ -342,7 +788,15 @@ mod tests {
         );
         expected.set_string(
             0,
-            4,
+            3,
+            DateTime::<Local>::from(timestamp_change)
+                .format("%H:%M")
+                .to_string(),
+            config.theme.default_style(),
+        );
+        expected.set_string(
+            0,
+            5,
             DateTime::<Local>::from(timestamp_2)
                 .format("%H:%M")
                 .to_string(),
@@ -350,5 +804,208 @@ mod tests {
         );
 
         terminal.backend().assert_buffer(&expected);
+
+        assert!(chat_box.is_stale(last_synced_at + 121));
+        assert!(!chat_box.is_stale(last_synced_at + 120));
+        assert_eq!(chat_box.selected_message_id(), None);
+        assert!(chat_box.is_at_top());
+        assert_eq!(chat_box.first_message_id(), Some(0));
+        chat_box.select_last_message();
+        assert_eq!(chat_box.selected_message_id(), Some(1));
+        chat_box.select_message(0);
+        assert_eq!(chat_box.selected_message_id(), Some(0));
+    }
+
+    fn backend_with_messages(ids: std::ops::RangeInclusive<i32>) -> MockNCTalk {
+        backend_with_timestamps(ids.map(|id| (id, 0)))
+    }
+
+    fn backend_with_timestamps(messages: impl Iterator<Item = (i32, i64)>) -> MockNCTalk {
+        let messages: BTreeMap<i32, ChatMessage> = messages
+            .map(|(id, timestamp)| {
+                (
+                    id,
+                    ChatMessage::from(NCReqDataMessage {
+                        id,
+                        timestamp,
+                        message: format!("Message {id}"),
+                        messageType: "comment".to_string(),
+                        actorDisplayName: "Butz".to_string(),
+                        ..Default::default()
+                    }),
+                )
+            })
+            .collect();
+        let mut mock_room = MockChatRoom::new();
+        mock_room.expect_get_messages().return_const(messages);
+        mock_room
+            .expect_get_participant_changes()
+            .return_const(vec![]);
+        mock_room.expect_get_last_common_read().return_const(0);
+        mock_room.expect_get_last_synced_at().return_const(0);
+        mock_room.expect_has_unread().return_const(false);
+        let mut mock_nc_backend = MockNCTalk::new();
+        mock_nc_backend.expect_get_room().return_const(mock_room);
+        mock_nc_backend
+    }
+
+    #[test]
+    fn keep_selection_on_update() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+        let token = "123".to_string();
+
+        let mut chat_box = ChatBox::new(&config);
+        chat_box.update_messages(&backend_with_messages(1..=3), &token);
+        chat_box.select_last_message();
+        assert_eq!(chat_box.selected_message_id(), Some(3));
+
+        // at the bottom the selection follows new messages
+        chat_box.update_messages(&backend_with_messages(1..=4), &token);
+        assert_eq!(chat_box.selected_message_id(), Some(4));
+
+        chat_box.select_up();
+        chat_box.select_up();
+        chat_box.update_messages(&backend_with_messages(1..=6), &token);
+        assert_eq!(chat_box.selected_message_id(), Some(2));
+        assert_eq!(chat_box.new_below(), 2);
+
+        let backend = TestBackend::new(40, 4);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| chat_box.render_area(frame, Rect::new(0, 0, 40, 4)))
+            .unwrap();
+        let last_line: String = (0..40)
+            .map(|x| terminal.backend().buffer()[(x, 3)].symbol())
+            .collect();
+        assert!(last_line.ends_with(" 2 new messages ↓ "));
+
+        while chat_box.new_below() > 0 {
+            chat_box.select_down();
+        }
+        assert_eq!(chat_box.selected_message_id(), Some(6));
+
+        // another room starts over
+        chat_box.select_up();
+        chat_box.update_messages(&backend_with_messages(1..=8), &"456".to_string());
+        assert_eq!(chat_box.new_below(), 0);
+    }
+
+    #[test]
+    fn motions_follow_offset() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+        let token = "123".to_string();
+
+        // a date row and 20 messages of one line each, 5 lines below the header
+        let mut chat_box = ChatBox::new(&config);
+        chat_box.update_messages(&backend_with_messages(1..=20), &token);
+        chat_box.set_area(Rect::new(0, 0, 40, 6));
+
+        chat_box.select_last_message();
+        assert_eq!(chat_box.selected_message_id(), Some(20));
+        assert_eq!(chat_box.state.offset(), 16);
+
+        chat_box.select_first();
+        assert!(chat_box.is_at_top());
+        assert_eq!(chat_box.state.offset(), 0);
+
+        chat_box.select_down_by(7);
+        assert_eq!(chat_box.selected_message_id(), Some(7));
+        assert_eq!(chat_box.state.offset(), 3);
+
+        chat_box.half_page_down();
+        assert_eq!(chat_box.selected_message_id(), Some(9));
+        assert_eq!(chat_box.state.offset(), 5);
+
+        chat_box.half_page_up();
+        assert_eq!(chat_box.selected_message_id(), Some(7));
+        assert_eq!(chat_box.state.offset(), 3);
+
+        chat_box.page_down();
+        assert_eq!(chat_box.selected_message_id(), Some(12));
+        assert_eq!(chat_box.state.offset(), 8);
+
+        // the wheel moves the view and pulls the selection along only at its edges
+        chat_box.scroll_view_down(3);
+        assert_eq!(chat_box.state.offset(), 11);
+        assert_eq!(chat_box.selected_message_id(), Some(12));
+        chat_box.scroll_view_down(3);
+        assert_eq!(chat_box.state.offset(), 14);
+        assert_eq!(chat_box.selected_message_id(), Some(14));
+        chat_box.scroll_view_up(3);
+        assert_eq!(chat_box.state.offset(), 11);
+        assert_eq!(chat_box.selected_message_id(), Some(14));
+        chat_box.scroll_view_up(6);
+        assert_eq!(chat_box.state.offset(), 5);
+        assert_eq!(chat_box.selected_message_id(), Some(9));
+
+        chat_box.page_up();
+        assert_eq!(chat_box.selected_message_id(), Some(4));
+        assert_eq!(chat_box.state.offset(), 0);
+
+        chat_box.select_up_by(100);
+        assert_eq!(chat_box.state.offset(), 0);
+
+        // clicks below the header select the row, the date row first
+        assert!(!chat_box.select_line(Position::new(3, 0)));
+        assert!(chat_box.select_line(Position::new(3, 3)));
+        assert_eq!(chat_box.selected_message_id(), Some(2));
+        assert!(!chat_box.select_line(Position::new(3, 6)));
+        chat_box.select_down_by(100);
+        assert_eq!(chat_box.selected_message_id(), Some(20));
+        assert_eq!(chat_box.state.offset(), 16);
+    }
+
+    #[test]
+    fn jump_between_days() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+        let day = |date: NaiveDate| {
+            date.and_hms_opt(12, 0, 0)
+                .unwrap()
+                .and_local_timezone(Local)
+                .unwrap()
+                .timestamp()
+        };
+        let first = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let second = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+
+        // rows: date, 1, 2, date, 3, 4
+        let mut chat_box = ChatBox::new(&config);
+        chat_box.update_messages(
+            &backend_with_timestamps(
+                vec![
+                    (1, day(first)),
+                    (2, day(first)),
+                    (3, day(second)),
+                    (4, day(second)),
+                ]
+                .into_iter(),
+            ),
+            &"123".to_string(),
+        );
+        chat_box.select_last_message();
+
+        assert!(chat_box.select_previous_day());
+        assert_eq!(chat_box.selected_message_id(), Some(3));
+        assert!(chat_box.select_previous_day());
+        assert_eq!(chat_box.selected_message_id(), Some(1));
+        assert!(!chat_box.select_previous_day());
+        assert!(chat_box.select_next_day());
+        assert_eq!(chat_box.selected_message_id(), Some(3));
+        assert!(!chat_box.select_next_day());
+
+        assert!(chat_box.select_date(NaiveDate::from_ymd_opt(2024, 3, 2).unwrap()));
+        assert_eq!(chat_box.selected_message_id(), Some(3));
+        assert!(chat_box.select_date(NaiveDate::from_ymd_opt(2020, 1, 1).unwrap()));
+        assert_eq!(chat_box.selected_message_id(), Some(1));
+        assert!(!chat_box.select_date(NaiveDate::from_ymd_opt(2024, 3, 6).unwrap()));
     }
 }
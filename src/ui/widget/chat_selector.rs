@@ -1,4 +1,7 @@
-use std::error::Error;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+};
 
 use itertools::Itertools;
 use ratatui::{
@@ -10,150 +13,291 @@ use ratatui::{
 use tui_textarea::TextArea;
 use tui_tree_widget::{Tree, TreeItem, TreeState};
 
-use crate::backend::nc_talk::NCBackend;
-use crate::backend::{nc_request::Token, nc_room::NCRoomInterface};
-use crate::config::Config;
+use crate::backend::{ChatProvider, ChatRoom, Token};
+use crate::config::{ChatSort, Config};
+use crate::ui::widget::room_badge::RoomBadge;
+
+/// Identifier of the section listing the accounts.
+const ACCOUNTS_SECTION: &str = "accounts";
 
 pub struct ChatSelector<'a> {
     pub state: TreeState<String>,
     items: Vec<TreeItem<'a, String>>,
     search_items: Vec<(Token, String)>,
+    /// Rooms marked for bulk actions.
+    pub marked: BTreeSet<Token>,
+    /// Rooms muted in this client, rendered dimmed.
+    muted: BTreeSet<Token>,
+    /// Rooms joined during this session and not yet opened.
+    new_rooms: BTreeSet<Token>,
+    /// Rooms with unread messages mentioning the user.
+    mentioned: BTreeSet<Token>,
+    /// Number of unread messages of the rooms having any.
+    unread_counts: BTreeMap<Token, usize>,
     pub search_bar: TextArea<'a>,
     pub searching: bool,
+    /// Order of the rooms in each section.
+    pub sort: ChatSort,
+    /// Names of all accounts, listed in their own section if there is more than one.
+    accounts: Vec<String>,
+    active_account: usize,
     default_style: Style,
     default_highlight_style: Style,
+    muted_style: Style,
+    new_style: Style,
+    mention_style: Style,
+    badge: RoomBadge,
+}
+
+/// Drop rooms listed again, which the tree would refuse as duplicate identifiers.
+fn without_duplicates(rooms: Vec<(Token, String)>) -> Vec<(Token, String)> {
+    let mut seen = BTreeSet::new();
+    rooms
+        .into_iter()
+        .filter(|(token, display_name)| {
+            let first = seen.insert(token.clone());
+            if !first {
+                log::warn!("Skipping {display_name} ({token}) listed twice in the chat selector.");
+            }
+            first
+        })
+        .collect_vec()
 }
 
 impl ChatSelector<'_> {
-    pub fn new(backend: &impl NCBackend, config: &Config) -> Self {
-        Self {
+    pub fn new(backend: &impl ChatProvider, config: &Config) -> Self {
+        let mut selector = Self {
             state: TreeState::default(),
-            items: vec![
-                TreeItem::new::<String>(
-                    "unread".to_string(),
-                    "Unread Chats".to_string(),
-                    backend
-                        .get_unread_rooms()
-                        .iter()
-                        .map(|token| {
-                            TreeItem::new_leaf::<String>(
-                                token.to_string(),
-                                backend.get_room(token).get_display_name().into(),
-                            )
-                        })
-                        .collect_vec(),
-                )
-                .expect("unread duplicate"),
-                TreeItem::new::<String>(
-                    "favorites".to_string(),
-                    "Favorite Chats".to_string(),
-                    backend
-                        .get_favorite_rooms()
-                        .iter()
-                        .map(|token| {
-                            TreeItem::new_leaf::<String>(
-                                token.to_string(),
-                                backend.get_room(token).get_display_name().into(),
-                            )
-                        })
-                        .collect_vec(),
-                )
-                .expect("favorite room name duplicate"),
-                TreeItem::new::<String>(
-                    "direct".to_string(),
-                    "DMs".to_string(),
-                    backend
-                        .get_dm_keys_display_name_mapping()
-                        .iter()
-                        .map(|(token, display_name)| {
-                            TreeItem::new_leaf::<String>(token.to_string(), display_name.clone())
-                        })
-                        .collect_vec(),
-                )
-                .expect("DM name duplicate"),
-                TreeItem::new::<String>(
-                    "group".to_string(),
-                    "Group".to_string(),
-                    backend
-                        .get_group_keys_display_name_mapping()
-                        .iter()
-                        .map(|(token, display_name)| {
-                            TreeItem::new_leaf::<String>(token.to_string(), display_name.clone())
-                        })
-                        .collect_vec(),
-                )
-                .expect("Group name duplicate"),
-            ],
-            search_items: backend
-                .get_room_keys()
-                .iter()
-                .map(|&token| {
-                    (
-                        token.to_string(),
-                        backend.get_room(token).get_display_name().into(),
-                    )
-                })
-                .collect_vec(),
+            items: vec![],
+            search_items: vec![],
+            marked: BTreeSet::new(),
+            muted: BTreeSet::new(),
+            new_rooms: BTreeSet::new(),
+            mentioned: BTreeSet::new(),
+            unread_counts: BTreeMap::new(),
             searching: false,
             search_bar: TextArea::new(vec![String::new()]),
+            sort: config.data.ui.chat_sort,
+            accounts: vec![],
+            active_account: 0,
             default_style: config.theme.default_style(),
             default_highlight_style: config.theme.default_highlight_style(),
+            muted_style: config.theme.default_style().add_modifier(Modifier::DIM),
+            new_style: config.theme.unread_message_style(),
+            mention_style: config
+                .theme
+                .title_important_style()
+                .add_modifier(Modifier::BOLD),
+            badge: RoomBadge::new(config),
+        };
+        if let Err(why) = selector.update(backend) {
+            log::warn!("Could not list the rooms in the chat selector: {why}");
+        }
+        selector
+    }
+
+    /// Badge, name and unread count of a room, marked if selected for a bulk action, dimmed if muted
+    /// in this client, highlighted as new if joined during this session and marked with '@' if the
+    /// user was mentioned.
+    fn item_text<'b>(&self, token: &Token, display_name: &str) -> Line<'b> {
+        let text = if self.marked.contains(token) {
+            format!("[x] {display_name}")
+        } else {
+            display_name.to_string()
+        };
+        let text = if self.muted.contains(token) {
+            Span::styled(text, self.muted_style)
+        } else {
+            Span::raw(text)
+        };
+        let mut spans = vec![self.badge.span(display_name), Span::raw(" "), text];
+        if let Some(count) = self.unread_counts.get(token) {
+            spans.push(Span::styled(format!(" ({count})"), self.new_style));
+        }
+        if self.new_rooms.contains(token) {
+            spans.push(Span::styled(" new", self.new_style));
+        }
+        if self.mentioned.contains(token) {
+            spans.push(Span::styled(" @", self.mention_style));
+        }
+        Line::from(spans)
+    }
+
+    /// Highlight a room as new, until it is opened.
+    /// The items are only rebuilt by the next [`ChatSelector::update`].
+    pub fn mark_new(&mut self, token: Token) {
+        self.new_rooms.insert(token);
+    }
+
+    /// Remove the new highlight of a room, returns true if it was highlighted.
+    pub fn clear_new(&mut self, token: &Token) -> bool {
+        self.new_rooms.remove(token)
+    }
+
+    fn leaf<'b>(&self, token: &Token, display_name: &str) -> TreeItem<'b, String> {
+        TreeItem::new_leaf(token.clone(), self.item_text(token, display_name))
+    }
+
+    /// Item of a group room, listing its breakout rooms below it.
+    fn group_item<'b>(
+        &self,
+        backend: &impl ChatProvider,
+        token: &Token,
+        display_name: &str,
+    ) -> Result<TreeItem<'b, String>, Box<dyn Error>> {
+        let breakout_rooms = backend.get_breakout_rooms(token);
+        if breakout_rooms.is_empty() {
+            return Ok(self.leaf(token, display_name));
+        }
+        Ok(TreeItem::new(
+            token.clone(),
+            self.item_text(token, display_name),
+            without_duplicates(breakout_rooms)
+                .iter()
+                .map(|(token, display_name)| self.leaf(token, display_name))
+                .collect_vec(),
+        )?)
+    }
+
+    /// Set the accounts listed in the accounts section, shown on the next update.
+    pub fn set_accounts(&mut self, accounts: Vec<String>, active_account: usize) {
+        self.accounts = accounts;
+        self.active_account = active_account;
+    }
+
+    /// Item of the accounts section, marking the account shown.
+    fn accounts_item<'b>(&self) -> Result<TreeItem<'b, String>, Box<dyn Error>> {
+        Ok(TreeItem::new::<String>(
+            ACCOUNTS_SECTION.to_string(),
+            "Accounts".to_string(),
+            self.accounts
+                .iter()
+                .enumerate()
+                .map(|(index, name)| {
+                    let text = if index == self.active_account {
+                        format!("{name} (active)")
+                    } else {
+                        name.clone()
+                    };
+                    TreeItem::new_leaf(name.clone(), text)
+                })
+                .collect_vec(),
+        )?)
+    }
+
+    /// Order rooms by the current [`ChatSort`], the backend already lists them alphabetically.
+    /// A room listed twice is skipped, as the tree only allows it once per section.
+    fn sorted(
+        &self,
+        backend: &impl ChatProvider,
+        rooms: Vec<(Token, String)>,
+    ) -> Vec<(Token, String)> {
+        let mut rooms = without_duplicates(rooms);
+        match self.sort {
+            ChatSort::Alphabetical => (),
+            ChatSort::LastActivity => rooms.sort_by_cached_key(|(token, _)| {
+                std::cmp::Reverse(backend.get_room(token).get_last_activity())
+            }),
+            ChatSort::UnreadFirst => {
+                rooms.sort_by_cached_key(|(token, _)| !backend.get_room(token).has_unread());
+            }
+        }
+        rooms
+    }
+
+    /// Header of a section, showing how many of its rooms have unread messages.
+    fn section_header(name: &str, rooms: &[(Token, String)], unread: &[Token]) -> String {
+        let new = rooms
+            .iter()
+            .filter(|(token, _)| unread.contains(token))
+            .count();
+        if new == 0 {
+            name.to_string()
+        } else {
+            format!("{name} ({new} new)")
         }
     }
 
-    pub fn update(&mut self, backend: &impl NCBackend) -> Result<(), Box<dyn Error>> {
+    pub fn update(&mut self, backend: &impl ChatProvider) -> Result<(), Box<dyn Error>> {
+        self.muted = backend.get_locally_muted_rooms().into_iter().collect();
+        let with_names = |tokens: Vec<Token>| {
+            tokens
+                .into_iter()
+                .map(|token| {
+                    let display_name = backend.get_room(&token).get_display_name().to_string();
+                    (token, display_name)
+                })
+                .collect_vec()
+        };
+        let unread = backend.get_unread_rooms();
+        self.mentioned.clear();
+        self.unread_counts.clear();
+        let unread_rooms = unread
+            .iter()
+            .map(|token| {
+                let room = backend.get_room(token);
+                if room.has_unread_mention() {
+                    self.mentioned.insert(token.clone());
+                }
+                if room.get_unread() > 0 {
+                    self.unread_counts.insert(token.clone(), room.get_unread());
+                }
+                (token.clone(), room.get_display_name().to_string())
+            })
+            .collect_vec();
+        let unread_items = self
+            .sorted(backend, unread_rooms)
+            .iter()
+            .map(|(token, display_name)| self.leaf(token, display_name))
+            .collect_vec();
+        let favorites = self.sorted(backend, with_names(backend.get_favorite_rooms()));
+        let dms = self.sorted(backend, backend.get_dm_keys_display_name_mapping());
+        let groups = self.sorted(backend, backend.get_group_keys_display_name_mapping());
+        let archived = self.sorted(backend, with_names(backend.get_archived_rooms()));
+
+        let unread_header = if unread.is_empty() {
+            "Unread Chats".to_string()
+        } else {
+            format!("Unread Chats ({})", unread.len())
+        };
+
         self.items = vec![
-            TreeItem::new::<String>(
-                "unread".to_string(),
-                "Unread Chats".to_string(),
-                backend
-                    .get_unread_rooms()
-                    .iter()
-                    .map(|token| {
-                        TreeItem::new_leaf::<String>(
-                            token.to_string(),
-                            backend.get_room(token).get_display_name().into(),
-                        )
-                    })
-                    .collect_vec(),
-            )?,
+            TreeItem::new::<String>("unread".to_string(), unread_header, unread_items)?,
             TreeItem::new::<String>(
                 "favorites".to_string(),
-                "Favorite Chats".to_string(),
-                backend
-                    .get_favorite_rooms()
+                Self::section_header("Favorite Chats", &favorites, &unread),
+                favorites
                     .iter()
-                    .map(|token| {
-                        TreeItem::new_leaf::<String>(
-                            token.to_string(),
-                            backend.get_room(token).get_display_name().into(),
-                        )
-                    })
+                    .map(|(token, display_name)| self.leaf(token, display_name))
                     .collect_vec(),
             )?,
             TreeItem::new::<String>(
                 "direct".to_string(),
-                "DMs".to_string(),
-                backend
-                    .get_dm_keys_display_name_mapping()
-                    .iter()
-                    .map(|(token, display_name)| {
-                        TreeItem::new_leaf::<String>(token.to_string(), display_name.clone())
-                    })
+                Self::section_header("DMs", &dms, &unread),
+                dms.iter()
+                    .map(|(token, display_name)| self.leaf(token, display_name))
                     .collect_vec(),
             )?,
             TreeItem::new::<String>(
                 "group".to_string(),
-                "Group".to_string(),
-                backend
-                    .get_group_keys_display_name_mapping()
+                Self::section_header("Group", &groups, &unread),
+                groups
+                    .iter()
+                    .map(|(token, display_name)| self.group_item(backend, token, display_name))
+                    .collect::<Result<Vec<_>, _>>()?,
+            )?,
+            TreeItem::new::<String>(
+                "archived".to_string(),
+                "Archived".to_string(),
+                archived
                     .iter()
-                    .map(|(token, display_name)| {
-                        TreeItem::new_leaf::<String>(token.to_string(), display_name.clone())
-                    })
+                    .map(|(token, display_name)| self.leaf(token, display_name))
                     .collect_vec(),
             )?,
         ];
+        if self.accounts.len() > 1 {
+            self.items.push(self.accounts_item()?);
+        }
         self.search_items = backend
             .get_room_keys()
             .iter()
@@ -167,6 +311,53 @@ impl ChatSelector<'_> {
         Ok(())
     }
 
+    /// Switch to the next [`ChatSort`] and sort the rooms by it.
+    pub fn cycle_sort(&mut self, backend: &impl ChatProvider) -> Result<(), Box<dyn Error>> {
+        self.sort = self.sort.next();
+        log::info!("Sorting the chat selector by {}", self.sort);
+        self.update(backend)
+    }
+
+    /// Get the token of the currently selected room, if a room and not a section is selected.
+    /// Breakout rooms are nested one level deeper below their parent room.
+    pub fn selected_room(&self) -> Option<&Token> {
+        let depth = self.state.selected().len();
+        if self.selected_account().is_some() {
+            None
+        } else if (self.searching && depth == 1) || (!self.searching && depth >= 2) {
+            self.state.selected().last()
+        } else {
+            None
+        }
+    }
+
+    /// Get the index of the currently selected account in the accounts section.
+    pub fn selected_account(&self) -> Option<usize> {
+        match self.state.selected() {
+            [section, name] if !self.searching && section == ACCOUNTS_SECTION => {
+                self.accounts.iter().position(|account| account == name)
+            }
+            _ => None,
+        }
+    }
+
+    /// Mark or unmark the currently selected room for a bulk action.
+    /// Returns false if no room is selected.
+    pub fn toggle_mark_selected(&mut self) -> bool {
+        let Some(token) = self.selected_room().cloned() else {
+            return false;
+        };
+        if !self.marked.remove(&token) {
+            self.marked.insert(token);
+        }
+        true
+    }
+
+    /// Take all marked rooms, leaving no room marked.
+    pub fn take_marked(&mut self) -> Vec<Token> {
+        std::mem::take(&mut self.marked).into_iter().collect()
+    }
+
     pub fn render_area(&mut self, frame: &mut Frame, area: Rect) {
         let items = if self.searching {
             self.search_bar.set_placeholder_text(String::new());
@@ -218,7 +409,13 @@ impl ChatSelector<'_> {
         let layout = Layout::vertical([Constraint::Min(4), Constraint::Length(3)]).split(area);
         let widget = Tree::new(items)
             .expect("all item identifiers are unique")
-            .block(Block::bordered().title("Chat Section"))
+            .block(
+                Block::bordered().title(if self.sort == ChatSort::Alphabetical {
+                    "Chat Section".to_string()
+                } else {
+                    format!("Chat Section (sorted: {})", self.sort)
+                }),
+            )
             .experimental_scrollbar(Some(
                 Scrollbar::new(ScrollbarOrientation::VerticalRight)
                     .begin_symbol(None)
@@ -236,9 +433,9 @@ impl ChatSelector<'_> {
 #[cfg(test)]
 mod tests {
 
-    use crate::backend::nc_request::{NCReqDataParticipants, Token};
-    use crate::backend::nc_room::MockNCRoomInterface;
+    use crate::backend::chat_room::MockChatRoom;
     use crate::backend::nc_talk::MockNCTalk;
+    use crate::backend::Token;
     use crate::config::init;
     use backend::TestBackend;
     use mockall::predicate::eq;
@@ -246,11 +443,33 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn skip_duplicate_rooms() {
+        let rooms = vec![
+            (Token::from("a"), "Alpha".to_string()),
+            (Token::from("b"), "Beta".to_string()),
+            (Token::from("a"), "Alpha".to_string()),
+        ];
+        assert_eq!(
+            without_duplicates(rooms),
+            vec![
+                (Token::from("a"), "Alpha".to_string()),
+                (Token::from("b"), "Beta".to_string()),
+            ]
+        );
+    }
+
     fn setup_mocks(
         seq: &mut Sequence,
         mock_nc_backend: &mut MockNCTalk,
-        mut mock_room: MockNCRoomInterface,
+        mut mock_room: MockChatRoom,
     ) {
+        mock_nc_backend
+            .expect_get_locally_muted_rooms()
+            .once()
+            .in_sequence(seq)
+            .return_const(vec![]);
+
         mock_nc_backend
             .expect_get_unread_rooms()
             .once()
@@ -275,12 +494,24 @@ mod tests {
             .in_sequence(seq)
             .return_const(vec![]);
 
+        mock_nc_backend
+            .expect_get_archived_rooms()
+            .once()
+            .in_sequence(seq)
+            .return_const(vec![]);
+
         mock_nc_backend
             .expect_get_room_keys()
             .once()
             .in_sequence(seq)
             .return_const(vec![]);
 
+        mock_nc_backend
+            .expect_get_locally_muted_rooms()
+            .once()
+            .in_sequence(seq)
+            .return_const(vec![Token::from("0")]);
+
         mock_nc_backend
             .expect_get_unread_rooms()
             .once()
@@ -291,6 +522,8 @@ mod tests {
             .expect_get_display_name()
             .once()
             .return_const("General".to_string());
+        mock_room.expect_has_unread_mention().return_const(true);
+        mock_room.expect_get_unread().return_const(12_usize);
 
         mock_nc_backend
             .expect_get_room()
@@ -317,6 +550,19 @@ mod tests {
             .in_sequence(seq)
             .return_const(vec![(Token::from("Bert"), "2".to_string())]);
 
+        mock_nc_backend
+            .expect_get_archived_rooms()
+            .once()
+            .in_sequence(seq)
+            .return_const(vec![]);
+
+        mock_nc_backend
+            .expect_get_breakout_rooms()
+            .with(eq(Token::from("Bert")))
+            .once()
+            .in_sequence(seq)
+            .return_const(vec![(Token::from("Hundi"), "2.1".to_string())]);
+
         mock_nc_backend
             .expect_get_room_keys()
             .once()
@@ -324,6 +570,31 @@ mod tests {
             .return_const(vec![]);
     }
 
+    /// Backend without any rooms, for tests which do not care about the listed rooms.
+    fn empty_backend() -> MockNCTalk {
+        let mut mock_nc_backend = MockNCTalk::new();
+        mock_nc_backend
+            .expect_get_locally_muted_rooms()
+            .return_const(vec![]);
+        mock_nc_backend
+            .expect_get_unread_rooms()
+            .return_const(vec![]);
+        mock_nc_backend
+            .expect_get_favorite_rooms()
+            .return_const(vec![]);
+        mock_nc_backend
+            .expect_get_dm_keys_display_name_mapping()
+            .return_const(vec![]);
+        mock_nc_backend
+            .expect_get_group_keys_display_name_mapping()
+            .return_const(vec![]);
+        mock_nc_backend
+            .expect_get_archived_rooms()
+            .return_const(vec![]);
+        mock_nc_backend.expect_get_room_keys().return_const(vec![]);
+        mock_nc_backend
+    }
+
     #[test]
     fn render() {
         let dir = tempfile::tempdir().unwrap();
@@ -333,19 +604,16 @@ mod tests {
         let mut seq = Sequence::new();
 
         let mut mock_nc_backend = MockNCTalk::new();
-        let mock_room = MockNCRoomInterface::new();
-        let backend = TestBackend::new(40, 10);
+        let mock_room = MockChatRoom::new();
+        let backend = TestBackend::new(40, 11);
         let mut terminal = Terminal::new(backend).unwrap();
 
         setup_mocks(&mut seq, &mut mock_nc_backend, mock_room);
 
         let mut chat_selector_box = ChatSelector::new(&mock_nc_backend, &config);
 
-        let mut dummy_user = NCReqDataParticipants::default();
-        dummy_user.displayName = "Butz".to_string();
-
         terminal
-            .draw(|frame| chat_selector_box.render_area(frame, Rect::new(0, 0, 40, 10)))
+            .draw(|frame| chat_selector_box.render_area(frame, Rect::new(0, 0, 40, 11)))
             .unwrap();
 
         let mut expected = Buffer::with_lines([
@@ -354,13 +622,14 @@ mod tests {
             "│  Favorite Chats                      │",
             "│  DMs                                 │",
             "│  Group                               │",
+            "│  Archived                            │",
             "│                                      │",
             "└──────────────────────────────────────┘",
             "┌──────────────────────────────────────┐",
             "│ Type '/' to start searching          │",
             "└──────────────────────────────────────┘",
         ]);
-        expected.set_style(Rect::new(0, 0, 40, 10), config.theme.default_style());
+        expected.set_style(Rect::new(0, 0, 40, 11), config.theme.default_style());
 
         terminal.backend().assert_buffer(&expected);
 
@@ -370,27 +639,176 @@ mod tests {
         chat_selector_box.state.key_right();
 
         terminal
-            .draw(|frame| chat_selector_box.render_area(frame, Rect::new(0, 0, 40, 10)))
+            .draw(|frame| chat_selector_box.render_area(frame, Rect::new(0, 0, 40, 11)))
             .unwrap();
 
         let mut expected = Buffer::with_lines([
             "┌Chat Section──────────────────────────┐",
-            "│>> ▼ Unread Chats                     │",
-            "│       General                        │",
+            "│>> ▼ Unread Chats (1)                 │",
+            "│       GE General (12) @              │",
             "│     Favorite Chats                   │",
             "│   ▶ DMs                              │",
             "│   ▶ Group                            │",
+            "│     Archived                         │",
             "└──────────────────────────────────────┘",
             "┌──────────────────────────────────────┐",
             "│ Type '/' to start searching          │",
             "└──────────────────────────────────────┘",
         ]);
-        expected.set_style(Rect::new(0, 0, 40, 10), config.theme.default_style());
+        expected.set_style(Rect::new(0, 0, 40, 11), config.theme.default_style());
         expected.set_style(
             Rect::new(1, 1, 38, 1),
             config.theme.default_highlight_style().bold(),
         );
+        expected.set_style(
+            Rect::new(8, 2, 2, 1),
+            RoomBadge::new(&config).span("General").style,
+        );
+        expected.set_style(
+            Rect::new(11, 2, 7, 1),
+            config.theme.default_style().add_modifier(Modifier::DIM),
+        );
+        expected.set_style(Rect::new(18, 2, 5, 1), config.theme.unread_message_style());
+        expected.set_style(
+            Rect::new(23, 2, 2, 1),
+            config
+                .theme
+                .title_important_style()
+                .add_modifier(Modifier::BOLD),
+        );
 
         terminal.backend().assert_buffer(&expected);
+
+        chat_selector_box.state.select(vec![
+            "group".to_string(),
+            "Bert".to_string(),
+            "Hundi".to_string(),
+        ]);
+        assert_eq!(
+            chat_selector_box.selected_room(),
+            Some(&Token::from("Hundi"))
+        );
+    }
+
+    #[test]
+    fn sort() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut mock_nc_backend = empty_backend();
+        for (token, last_activity, unread) in [("a", 10, false), ("b", 30, false), ("c", 20, true)]
+        {
+            let mut mock_room = MockChatRoom::new();
+            mock_room
+                .expect_get_last_activity()
+                .return_const(last_activity);
+            mock_room.expect_has_unread().return_const(unread);
+            mock_nc_backend
+                .expect_get_room()
+                .with(eq(Token::from(token)))
+                .return_const(mock_room);
+        }
+
+        let mut chat_selector_box = ChatSelector::new(&mock_nc_backend, &config);
+        let rooms = ["a", "b", "c"]
+            .map(|token| (Token::from(token), token.to_uppercase()))
+            .to_vec();
+        let order = |selector: &ChatSelector| {
+            selector
+                .sorted(&mock_nc_backend, rooms.clone())
+                .into_iter()
+                .map(|(token, _)| token)
+                .collect_vec()
+        };
+
+        assert_eq!(order(&chat_selector_box), ["a", "b", "c"]);
+        chat_selector_box.cycle_sort(&mock_nc_backend).unwrap();
+        assert_eq!(chat_selector_box.sort, ChatSort::LastActivity);
+        assert_eq!(order(&chat_selector_box), ["b", "c", "a"]);
+        chat_selector_box.cycle_sort(&mock_nc_backend).unwrap();
+        assert_eq!(order(&chat_selector_box), ["c", "a", "b"]);
+        chat_selector_box.cycle_sort(&mock_nc_backend).unwrap();
+        assert_eq!(chat_selector_box.sort, ChatSort::Alphabetical);
+    }
+
+    #[test]
+    fn accounts() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mock_nc_backend = empty_backend();
+
+        let mut chat_selector_box = ChatSelector::new(&mock_nc_backend, &config);
+        chat_selector_box.set_accounts(vec!["Work".to_string(), "Home".to_string()], 0);
+        chat_selector_box.update(&mock_nc_backend).unwrap();
+
+        let backend = TestBackend::new(40, 13);
+        let mut terminal = Terminal::new(backend).unwrap();
+        chat_selector_box.state.open(vec!["accounts".to_string()]);
+        chat_selector_box
+            .state
+            .select(vec!["accounts".to_string(), "Home".to_string()]);
+        terminal
+            .draw(|frame| chat_selector_box.render_area(frame, Rect::new(0, 0, 40, 13)))
+            .unwrap();
+        let text = (1..9)
+            .map(|y| {
+                (1..39)
+                    .map(|x| terminal.backend().buffer()[(x, y)].symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect_vec();
+        assert_eq!(
+            text,
+            [
+                "     Unread Chats",
+                "     Favorite Chats",
+                "     DMs",
+                "     Group",
+                "     Archived",
+                "   ▼ Accounts",
+                "       Work (active)",
+                ">>     Home",
+            ]
+        );
+        assert_eq!(chat_selector_box.selected_account(), Some(1));
+        assert_eq!(chat_selector_box.selected_room(), None);
+
+        chat_selector_box.set_accounts(vec!["Work".to_string()], 0);
+        chat_selector_box.update(&mock_nc_backend).unwrap();
+        assert_eq!(chat_selector_box.selected_account(), None);
+    }
+
+    #[test]
+    fn new_rooms() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mock_nc_backend = empty_backend();
+
+        let mut chat_selector_box = ChatSelector::new(&mock_nc_backend, &config);
+
+        let token = Token::from("123");
+        let text = |selector: &ChatSelector| {
+            selector
+                .item_text(&token, "General")
+                .spans
+                .iter()
+                .map(|span| span.content.to_string())
+                .collect::<String>()
+        };
+        chat_selector_box.mark_new(token.clone());
+        assert!(text(&chat_selector_box).ends_with("General new"));
+        assert!(chat_selector_box.clear_new(&token));
+        assert!(!chat_selector_box.clear_new(&token));
+        assert!(text(&chat_selector_box).ends_with("General"));
     }
 }
@@ -0,0 +1,294 @@
+//! Lightweight highlighting of code blocks, guessing the language of blocks without a tag.
+use itertools::Itertools;
+use ratatui::prelude::*;
+use strum_macros::Display;
+
+/// Languages the code blocks are highlighted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum Language {
+    #[strum(to_string = "rust")]
+    Rust,
+    #[strum(to_string = "python")]
+    Python,
+    #[strum(to_string = "shell")]
+    Shell,
+    #[strum(to_string = "javascript")]
+    JavaScript,
+    #[strum(to_string = "json")]
+    Json,
+}
+
+const LANGUAGES: [Language; 5] = [
+    Language::Rust,
+    Language::Python,
+    Language::Shell,
+    Language::JavaScript,
+    Language::Json,
+];
+
+/// Markers needed to trust a guessed language.
+const MIN_DETECTION_SCORE: usize = 2;
+
+impl Language {
+    /// Language of the tag after the opening fence of a code block.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag.trim().to_lowercase().as_str() {
+            "rust" | "rs" => Some(Language::Rust),
+            "python" | "py" => Some(Language::Python),
+            "sh" | "bash" | "shell" | "zsh" | "console" => Some(Language::Shell),
+            "javascript" | "js" | "typescript" | "ts" => Some(Language::JavaScript),
+            "json" => Some(Language::Json),
+            _ => None,
+        }
+    }
+
+    /// Guess the language of a code block by counting typical markers.
+    /// Returns `None` when no language is clearly ahead of the others.
+    pub fn detect(code: &str) -> Option<Self> {
+        let scores = LANGUAGES
+            .iter()
+            .map(|language| (*language, language.score(code)))
+            .sorted_by_key(|(_, score)| std::cmp::Reverse(*score))
+            .collect_vec();
+        match scores.as_slice() {
+            [(language, best), (_, second), ..]
+                if *best >= MIN_DETECTION_SCORE && best > second =>
+            {
+                Some(*language)
+            }
+            _ => None,
+        }
+    }
+
+    fn score(self, code: &str) -> usize {
+        let trimmed = code.trim();
+        match self {
+            // JSON has no markers of its own, the whole block has to look like an object or list.
+            Language::Json => {
+                let delimited = (trimmed.starts_with('{') && trimmed.ends_with('}'))
+                    || (trimmed.starts_with('[') && trimmed.ends_with(']'));
+                if delimited && trimmed.contains("\":") && !trimmed.contains(';') {
+                    MIN_DETECTION_SCORE + 1
+                } else {
+                    0
+                }
+            }
+            _ => self
+                .markers()
+                .iter()
+                .filter(|marker| code.contains(*marker))
+                .count(),
+        }
+    }
+
+    fn markers(self) -> &'static [&'static str] {
+        match self {
+            Language::Rust => &[
+                "fn ", "let mut ", "impl ", "::", "-> ", "use std", "println!", "&self", "#[",
+                "match ", "pub ",
+            ],
+            Language::Python => &[
+                "def ", "import ", "self.", "elif ", "print(", "None", "__init__", "):\n", "from ",
+            ],
+            Language::Shell => &[
+                "#!/bin/", "echo ", "sudo ", "$(", "export ", "| grep", "cd ", "\nfi", "apt ",
+            ],
+            Language::JavaScript => &[
+                "function",
+                "const ",
+                "=>",
+                "console.",
+                "===",
+                "require(",
+                "document.",
+                "var ",
+            ],
+            Language::Json => &[],
+        }
+    }
+
+    fn keywords(self) -> &'static [&'static str] {
+        match self {
+            Language::Rust => &[
+                "as", "async", "await", "break", "const", "continue", "crate", "else", "enum",
+                "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+                "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+                "trait", "true", "type", "unsafe", "use", "where", "while",
+            ],
+            Language::Python => &[
+                "and", "as", "async", "await", "break", "class", "continue", "def", "del", "elif",
+                "else", "except", "False", "finally", "for", "from", "if", "import", "in", "is",
+                "lambda", "None", "not", "or", "pass", "raise", "return", "True", "try", "while",
+                "with", "yield",
+            ],
+            Language::Shell => &[
+                "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function",
+                "if", "in", "local", "return", "then", "until", "while",
+            ],
+            Language::JavaScript => &[
+                "async",
+                "await",
+                "break",
+                "case",
+                "class",
+                "const",
+                "continue",
+                "else",
+                "export",
+                "false",
+                "for",
+                "function",
+                "if",
+                "import",
+                "let",
+                "new",
+                "null",
+                "return",
+                "switch",
+                "this",
+                "throw",
+                "true",
+                "try",
+                "typeof",
+                "undefined",
+                "var",
+                "while",
+            ],
+            Language::Json => &["true", "false", "null"],
+        }
+    }
+
+    fn line_comment(self) -> Option<&'static str> {
+        match self {
+            Language::Rust | Language::JavaScript => Some("//"),
+            Language::Python | Language::Shell => Some("#"),
+            Language::Json => None,
+        }
+    }
+
+    /// Quotes starting strings, single quotes are lifetimes in Rust.
+    fn quotes(self) -> &'static [char] {
+        match self {
+            Language::Rust | Language::Json => &['"'],
+            Language::Python | Language::Shell | Language::JavaScript => &['"', '\'', '`'],
+        }
+    }
+}
+
+/// Styles of the highlighted parts of code, derived from the style of code blocks.
+#[derive(Debug, Clone, Copy)]
+pub struct CodeStyles {
+    pub code: Style,
+    pub keyword: Style,
+    pub string: Style,
+    pub comment: Style,
+}
+
+impl CodeStyles {
+    pub fn new(code: Style) -> Self {
+        CodeStyles {
+            code,
+            keyword: code.add_modifier(Modifier::BOLD),
+            string: code.add_modifier(Modifier::ITALIC),
+            comment: code.add_modifier(Modifier::DIM),
+        }
+    }
+}
+
+/// Split a line of code into spans of keywords, strings, comments and the rest.
+pub fn highlight_line(line: &str, language: Language, styles: CodeStyles) -> Vec<Span<'static>> {
+    let mut spans: Vec<Span<'static>> = vec![];
+    let mut push = |text: &str, style: Style| match spans.last_mut() {
+        Some(last) if last.style == style => last.content.to_mut().push_str(text),
+        _ => spans.push(Span::styled(text.to_string(), style)),
+    };
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        if language
+            .line_comment()
+            .is_some_and(|comment| rest.starts_with(comment))
+        {
+            push(rest, styles.comment);
+            break;
+        }
+        let length = if language.quotes().contains(&c) {
+            let mut escaped = false;
+            let end = rest[1..]
+                .char_indices()
+                .find(|(_, next)| {
+                    let closing = !escaped && *next == c;
+                    escaped = !escaped && *next == '\\';
+                    closing
+                })
+                .map_or(rest.len(), |(index, _)| index + 2);
+            push(&rest[..end], styles.string);
+            end
+        } else if c.is_alphanumeric() || c == '_' {
+            let end = rest
+                .find(|next: char| !(next.is_alphanumeric() || next == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            if language.keywords().contains(&word) {
+                push(word, styles.keyword);
+            } else {
+                push(word, styles.code);
+            }
+            end
+        } else {
+            push(&rest[..c.len_utf8()], styles.code);
+            c.len_utf8()
+        };
+        rest = &rest[length..];
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect() {
+        assert_eq!(Language::from_tag("RS"), Some(Language::Rust));
+        assert_eq!(Language::from_tag("go"), None);
+        assert_eq!(
+            Language::detect("fn main() {\n    println!(\"hi\");\n}"),
+            Some(Language::Rust)
+        );
+        assert_eq!(
+            Language::detect("def run(self):\n    print(self.name)"),
+            Some(Language::Python)
+        );
+        assert_eq!(
+            Language::detect("#!/bin/bash\necho $(whoami)"),
+            Some(Language::Shell)
+        );
+        assert_eq!(
+            Language::detect("{\"name\": \"sechat\", \"tags\": [1, 2]}"),
+            Some(Language::Json)
+        );
+        assert_eq!(Language::detect("just some text"), None);
+    }
+
+    #[test]
+    fn highlight() {
+        let styles = CodeStyles::new(Style::new().reversed());
+        assert_eq!(
+            highlight_line("let s = \"a \\\" b\"; // done", Language::Rust, styles),
+            vec![
+                Span::styled("let", styles.keyword),
+                Span::styled(" s = ", styles.code),
+                Span::styled("\"a \\\" b\"", styles.string),
+                Span::styled("; ", styles.code),
+                Span::styled("// done", styles.comment),
+            ]
+        );
+        assert_eq!(
+            highlight_line("letter 'a", Language::Python, styles),
+            vec![
+                Span::styled("letter ", styles.code),
+                Span::styled("'a", styles.string),
+            ]
+        );
+    }
+}
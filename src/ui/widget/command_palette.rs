@@ -0,0 +1,275 @@
+use crate::config::Config;
+use crate::ui::widget::quick_switcher::fuzzy_rank;
+use crossterm::event::KeyEvent;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, HighlightSpacing, Row, Table, TableState},
+};
+use strum_macros::Display;
+use tui_textarea::TextArea;
+
+/// Actions of the app which can be run from the [`CommandPalette`].
+#[derive(PartialEq, Clone, Copy, Debug, Display)]
+pub enum Command {
+    #[strum(to_string = "mark read")]
+    MarkRead,
+    #[strum(to_string = "mark all read")]
+    MarkAllRead,
+    #[strum(to_string = "fetch history")]
+    FetchHistory,
+    #[strum(to_string = "next unread")]
+    NextUnread,
+    #[strum(to_string = "inbox")]
+    Inbox,
+    #[strum(to_string = "switch room")]
+    SwitchRoom,
+    #[strum(to_string = "open rooms")]
+    OpenRooms,
+    #[strum(to_string = "browse rooms")]
+    BrowseRooms,
+    #[strum(to_string = "search messages")]
+    SearchMessages,
+    #[strum(to_string = "jump to date")]
+    JumpToDate,
+    #[strum(to_string = "toggle sidebar")]
+    ToggleSidebar,
+    #[strum(to_string = "toggle mute")]
+    ToggleMute,
+    #[strum(to_string = "toggle archive")]
+    ToggleArchive,
+    #[strum(to_string = "toggle do not disturb")]
+    DoNotDisturb,
+    #[strum(to_string = "set status")]
+    SetStatus,
+    #[strum(to_string = "message user")]
+    MessageUser,
+    #[strum(to_string = "room info")]
+    RoomInfo,
+    #[strum(to_string = "diagnostics")]
+    Diagnostics,
+    #[strum(to_string = "export")]
+    Export,
+    #[strum(to_string = "show log")]
+    ShowLog,
+    #[strum(to_string = "request dumps")]
+    RequestDumps,
+    #[strum(to_string = "switch account")]
+    SwitchAccount,
+    #[strum(to_string = "help")]
+    Help,
+    #[strum(to_string = "quit")]
+    Quit,
+}
+
+impl Command {
+    const ALL: [Command; 24] = [
+        Command::MarkRead,
+        Command::MarkAllRead,
+        Command::FetchHistory,
+        Command::NextUnread,
+        Command::Inbox,
+        Command::SwitchRoom,
+        Command::OpenRooms,
+        Command::BrowseRooms,
+        Command::SearchMessages,
+        Command::JumpToDate,
+        Command::ToggleSidebar,
+        Command::ToggleMute,
+        Command::ToggleArchive,
+        Command::DoNotDisturb,
+        Command::SetStatus,
+        Command::MessageUser,
+        Command::RoomInfo,
+        Command::Diagnostics,
+        Command::Export,
+        Command::ShowLog,
+        Command::RequestDumps,
+        Command::SwitchAccount,
+        Command::Help,
+        Command::Quit,
+    ];
+
+    /// Key running the command in reading mode.
+    fn key(self) -> &'static str {
+        match self {
+            Command::MarkRead => "m",
+            Command::MarkAllRead => "M",
+            Command::FetchHistory => "f",
+            Command::NextUnread => "Tab",
+            Command::Inbox => "U",
+            Command::SwitchRoom => "C-k",
+            Command::OpenRooms => "o",
+            Command::BrowseRooms => "o b",
+            Command::SearchMessages => "/",
+            Command::JumpToDate => "J",
+            Command::ToggleSidebar => "u",
+            Command::ToggleMute => "N",
+            Command::ToggleArchive => "A",
+            Command::DoNotDisturb => "B",
+            Command::SetStatus => "S",
+            Command::MessageUser => "P",
+            Command::RoomInfo => "I",
+            Command::Diagnostics => "H",
+            Command::Export => "E",
+            Command::ShowLog => "L",
+            Command::RequestDumps => "D",
+            Command::SwitchAccount => "W",
+            Command::Help => "?",
+            Command::Quit => "q",
+        }
+    }
+
+    /// Check if the command needs the server to be reachable.
+    pub fn needs_server(self) -> bool {
+        matches!(
+            self,
+            Command::MarkRead
+                | Command::MarkAllRead
+                | Command::FetchHistory
+                | Command::BrowseRooms
+                | Command::ToggleArchive
+                | Command::SetStatus
+                | Command::MessageUser
+        )
+    }
+}
+
+/// Popup listing the [`Command`]s matching the typed text, the best matches first.
+pub struct CommandPalette<'a> {
+    input: TextArea<'a>,
+    /// Indices into [`Command::ALL`] of the matches, best first.
+    results: Vec<usize>,
+    matcher: SkimMatcherV2,
+    state: TableState,
+    default_style: Style,
+    default_highlight_style: Style,
+    popup_border_style: Style,
+    key_style: Style,
+}
+
+impl CommandPalette<'_> {
+    pub fn new(config: &Config) -> Self {
+        let mut input = TextArea::new(vec![String::new()]);
+        input.set_placeholder_text("Type a command");
+        input.set_placeholder_style(config.theme.default_style());
+        input.set_style(config.theme.default_highlight_style());
+        input.set_block(Block::bordered().border_style(config.theme.default_style()));
+        let mut palette = CommandPalette {
+            input,
+            results: vec![],
+            matcher: SkimMatcherV2::default(),
+            state: TableState::default(),
+            default_style: config.theme.default_style(),
+            default_highlight_style: config.theme.default_highlight_style(),
+            popup_border_style: config.theme.popup_border_style(),
+            key_style: config.theme.table_header_style(),
+        };
+        palette.rank();
+        palette
+    }
+
+    /// Clear the input and list all commands, for reopening the popup.
+    pub fn reset(&mut self) {
+        self.input.select_all();
+        self.input.cut();
+        self.rank();
+    }
+
+    pub fn input(&mut self, key: KeyEvent) {
+        if self.input.input(key) {
+            self.rank();
+        }
+    }
+
+    fn rank(&mut self) {
+        let names: Vec<String> = Command::ALL.iter().map(ToString::to_string).collect();
+        self.results = fuzzy_rank(
+            &self.matcher,
+            names.iter().map(String::as_str),
+            &self.input.lines().join(""),
+        );
+        self.state = TableState::default().with_selected((!self.results.is_empty()).then_some(0));
+    }
+
+    pub fn select_up(&mut self) {
+        self.state.select_previous();
+    }
+
+    pub fn select_down(&mut self) {
+        if self.state.selected().unwrap_or(0) + 1 < self.results.len() {
+            self.state.select_next();
+        }
+    }
+
+    pub fn selected_command(&self) -> Option<Command> {
+        let index = self.results.get(self.state.selected()?)?;
+        Some(Command::ALL[*index])
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered()
+            .title("Commands")
+            .title_bottom("Enter run, Esc close")
+            .border_style(self.popup_border_style)
+            .style(self.default_style);
+        let [input_area, results_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(block.inner(area));
+        frame.render_widget(block, area);
+        frame.render_widget(&self.input, input_area);
+        frame.render_stateful_widget(
+            Table::new(
+                self.results.iter().map(|index| {
+                    let command = Command::ALL[*index];
+                    Row::new([
+                        Text::raw(command.to_string()),
+                        Text::styled(command.key(), self.key_style),
+                    ])
+                }),
+                [Constraint::Min(10), Constraint::Length(4)],
+            )
+            .column_spacing(1)
+            .style(self.default_style)
+            .row_highlight_style(self.default_highlight_style)
+            .highlight_spacing(HighlightSpacing::Never),
+            results_area,
+            &mut self.state.clone(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::init;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    use super::*;
+
+    #[test]
+    fn complete_commands() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut palette = CommandPalette::new(&config);
+        assert_eq!(palette.results.len(), Command::ALL.len());
+        assert_eq!(palette.selected_command(), Some(Command::MarkRead));
+
+        for key in "mar".chars() {
+            palette.input(KeyEvent::new(KeyCode::Char(key), KeyModifiers::NONE));
+        }
+        assert_eq!(palette.selected_command(), Some(Command::MarkRead));
+        palette.select_down();
+        assert_eq!(palette.selected_command(), Some(Command::MarkAllRead));
+
+        palette.reset();
+        for key in "tglsid".chars() {
+            palette.input(KeyEvent::new(KeyCode::Char(key), KeyModifiers::NONE));
+        }
+        assert_eq!(palette.selected_command(), Some(Command::ToggleSidebar));
+
+        palette.input(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+        assert!(palette.selected_command().is_none());
+    }
+}
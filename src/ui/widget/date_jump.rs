@@ -0,0 +1,100 @@
+use crate::config::Config;
+use chrono::{Local, NaiveDate};
+use crossterm::event::KeyEvent;
+use ratatui::{prelude::*, widgets::Block};
+use tui_textarea::TextArea;
+
+/// Popup asking for the day to jump to in the current room.
+pub struct DateJump<'a> {
+    input: TextArea<'a>,
+    /// Why the last text could not be read as a date.
+    error: Option<String>,
+    popup_border_style: Style,
+    error_style: Style,
+}
+
+impl DateJump<'_> {
+    pub fn new(config: &Config) -> Self {
+        let mut input = TextArea::new(vec![String::new()]);
+        input.set_placeholder_text("YYYY-MM-DD, today or yesterday");
+        input.set_placeholder_style(config.theme.default_style());
+        input.set_style(config.theme.default_highlight_style());
+        input.set_cursor_line_style(Style::default());
+        DateJump {
+            input,
+            error: None,
+            popup_border_style: config.theme.popup_border_style(),
+            error_style: config.theme.title_important_style(),
+        }
+    }
+
+    /// Clear the input and the error, for reopening the popup.
+    pub fn reset(&mut self) {
+        self.input.select_all();
+        self.input.cut();
+        self.error = None;
+    }
+
+    pub fn input(&mut self, key: KeyEvent) {
+        self.input.input(key);
+        self.error = None;
+    }
+
+    /// Read the day entered, keeping an error to show if it is none.
+    pub fn date(&mut self) -> Option<NaiveDate> {
+        let text = self.input.lines().join("");
+        let today = Local::now().date_naive();
+        let date = match text.trim() {
+            "today" => Some(today),
+            "yesterday" => today.pred_opt(),
+            text => NaiveDate::parse_from_str(text, "%Y-%m-%d").ok(),
+        };
+        if date.is_none() {
+            self.error = Some(format!("'{text}' is no date"));
+        }
+        date
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect) {
+        let bottom = match &self.error {
+            Some(error) => Line::styled(error.as_str(), self.error_style),
+            None => Line::from("Enter jump, Esc cancel"),
+        };
+        let block = Block::bordered()
+            .title("Jump to date")
+            .title_bottom(bottom)
+            .border_style(self.popup_border_style);
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        frame.render_widget(&self.input, inner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::init;
+
+    use super::*;
+
+    #[test]
+    fn parse_dates() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut jump = DateJump::new(&config);
+        jump.input.insert_str("2024-02-29");
+        assert_eq!(jump.date(), NaiveDate::from_ymd_opt(2024, 2, 29));
+        assert!(jump.error.is_none());
+
+        jump.reset();
+        jump.input.insert_str("today");
+        assert_eq!(jump.date(), Some(Local::now().date_naive()));
+
+        jump.reset();
+        jump.input.insert_str("2023-02-29");
+        assert!(jump.date().is_none());
+        assert_eq!(jump.error.as_deref(), Some("'2023-02-29' is no date"));
+    }
+}
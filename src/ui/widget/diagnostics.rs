@@ -0,0 +1,137 @@
+use crate::backend::request_metrics::MetricsSnapshot;
+use crate::config::Config;
+use itertools::Itertools;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Cell, Row, Table},
+};
+use std::convert::TryFrom;
+
+/// Most kinds of requests listed, the most frequent ones are kept.
+const MAX_KINDS: usize = 10;
+
+/// Popup with the counters of the requests to the server, to see why the client is slow.
+pub struct Diagnostics {
+    rows: Vec<(String, String)>,
+    default_style: Style,
+    popup_border_style: Style,
+    table_header_style: Style,
+}
+
+impl Diagnostics {
+    pub fn new(config: &Config) -> Self {
+        Diagnostics {
+            rows: vec![],
+            default_style: config.theme.default_style(),
+            popup_border_style: config.theme.popup_border_style(),
+            table_header_style: config.theme.table_header_style(),
+        }
+    }
+
+    /// Show the counters, `None` if the chat provider keeps none right now.
+    pub fn update(&mut self, metrics: Option<&MetricsSnapshot>) {
+        let Some(metrics) = metrics else {
+            self.rows = vec![("Requests".to_string(), "not available".to_string())];
+            return;
+        };
+        self.rows = vec![
+            (
+                "Requests".to_string(),
+                format!(
+                    "{} ({} failed)",
+                    metrics.total_requests(),
+                    metrics.failed_requests()
+                ),
+            ),
+            ("Retries".to_string(), metrics.retries.to_string()),
+            ("Sent".to_string(), metrics.messages_sent.to_string()),
+            (
+                "Received".to_string(),
+                metrics.messages_received.to_string(),
+            ),
+            (
+                "Queued".to_string(),
+                match metrics.queued.split_first() {
+                    Some((dispatcher, workers)) => format!(
+                        "{dispatcher}, workers {}",
+                        workers.iter().map(ToString::to_string).join(" ")
+                    ),
+                    None => "0".to_string(),
+                },
+            ),
+        ];
+        self.rows.extend(
+            metrics
+                .requests
+                .iter()
+                .sorted_by_key(|(_, count)| std::cmp::Reverse(count.total))
+                .take(MAX_KINDS)
+                .map(|(kind, count)| {
+                    (
+                        format!("  {kind}"),
+                        format!("{} ({} failed)", count.total, count.failed),
+                    )
+                }),
+        );
+    }
+
+    pub fn height(&self) -> u16 {
+        u16::try_from(self.rows.len()).unwrap_or(u16::MAX) + 2
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(
+            Table::new(
+                self.rows.iter().map(|(key, value)| {
+                    Row::new([
+                        Cell::from(key.as_str()).style(self.table_header_style),
+                        Cell::from(value.as_str()),
+                    ])
+                }),
+                [Constraint::Length(24), Constraint::Min(10)],
+            )
+            .style(self.default_style)
+            .block(
+                Block::bordered()
+                    .title("Diagnostics")
+                    .title_bottom("Esc close")
+                    .border_style(self.popup_border_style),
+            ),
+            area,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::request_metrics::RequestMetrics;
+    use crate::config::init;
+
+    use super::*;
+
+    #[test]
+    fn rows() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let metrics = RequestMetrics::default();
+        metrics.record_request("SendMessage", true);
+        metrics.record_request("FetchChatUpdate", true);
+        metrics.record_request("FetchChatUpdate", false);
+        let mut diagnostics = Diagnostics::new(&config);
+        diagnostics.update(Some(&metrics.snapshot(vec![4, 0, 1])));
+        assert_eq!(diagnostics.rows[0].1, "3 (1 failed)");
+        assert_eq!(diagnostics.rows[2].1, "1");
+        assert_eq!(diagnostics.rows[4].1, "4, workers 0 1");
+        assert_eq!(
+            diagnostics.rows[5],
+            ("  FetchChatUpdate".to_string(), "2 (1 failed)".to_string())
+        );
+        assert_eq!(diagnostics.height(), 9);
+
+        diagnostics.update(None);
+        assert_eq!(diagnostics.height(), 3);
+    }
+}
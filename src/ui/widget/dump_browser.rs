@@ -0,0 +1,209 @@
+use crate::backend::request_dump::{list_dumps, pretty_json};
+use crate::config::Config;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, HighlightSpacing, Paragraph, Row, Table, TableState},
+};
+use std::path::PathBuf;
+
+/// Screen to look through the dumps of responses which could not be parsed.
+pub struct DumpBrowser {
+    /// Directory of the dumps, `None` if dumping is switched off.
+    dir: Option<PathBuf>,
+    dumps: Vec<PathBuf>,
+    state: TableState,
+    /// Dump shown and its pretty printed lines.
+    opened: Option<(PathBuf, Vec<String>)>,
+    scroll: u16,
+    default_style: Style,
+    default_highlight_style: Style,
+    table_header_style: Style,
+}
+
+impl DumpBrowser {
+    pub fn new(config: &Config) -> Self {
+        DumpBrowser {
+            dir: config.get_http_dump_dir(),
+            dumps: vec![],
+            state: TableState::default(),
+            opened: None,
+            scroll: 0,
+            default_style: config.theme.default_style(),
+            default_highlight_style: config.theme.default_highlight_style(),
+            table_header_style: config.theme.table_header_style(),
+        }
+    }
+
+    /// List the dumps again, newest first, selecting the newest.
+    pub fn refresh(&mut self) {
+        self.dumps = self.dir.as_deref().map(list_dumps).unwrap_or_default();
+        self.opened = None;
+        self.state = TableState::default().with_selected((!self.dumps.is_empty()).then_some(0));
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.opened.is_some()
+    }
+
+    /// Show the dump at `path` pretty printed.
+    pub fn open(&mut self, path: PathBuf) -> Result<(), std::io::Error> {
+        let text = pretty_json(&std::fs::read_to_string(&path)?);
+        if let Some(index) = self.dumps.iter().position(|dump| *dump == path) {
+            self.state.select(Some(index));
+        }
+        self.opened = Some((path, text.lines().map(str::to_string).collect()));
+        self.scroll = 0;
+        Ok(())
+    }
+
+    pub fn open_selected(&mut self) -> Result<(), std::io::Error> {
+        match self.selected_dump().cloned() {
+            Some(path) => self.open(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Go back from the shown dump to the list.
+    pub fn close(&mut self) {
+        self.opened = None;
+    }
+
+    /// Delete the selected dump, or the shown one, and list the remaining ones.
+    pub fn delete_selected(&mut self) -> Result<(), std::io::Error> {
+        let Some(path) = self.selected_dump().cloned() else {
+            return Ok(());
+        };
+        std::fs::remove_file(&path)?;
+        log::info!("Deleted the dump {}", path.display());
+        let index = self.state.selected().unwrap_or_default();
+        self.refresh();
+        if !self.dumps.is_empty() {
+            self.state.select(Some(index.min(self.dumps.len() - 1)));
+        }
+        Ok(())
+    }
+
+    pub fn selected_dump(&self) -> Option<&PathBuf> {
+        self.dumps.get(self.state.selected()?)
+    }
+
+    pub fn select_up(&mut self) {
+        if self.is_open() {
+            self.scroll = self.scroll.saturating_sub(1);
+        } else {
+            self.state.select_previous();
+        }
+    }
+
+    pub fn select_down(&mut self) {
+        if let Some((_, lines)) = &self.opened {
+            if usize::from(self.scroll) + 1 < lines.len() {
+                self.scroll += 1;
+            }
+        } else if self.state.selected().unwrap_or(0) + 1 < self.dumps.len() {
+            self.state.select_next();
+        }
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect) {
+        if let Some((path, lines)) = &self.opened {
+            frame.render_widget(
+                Paragraph::new(
+                    lines
+                        .iter()
+                        .map(|line| Line::from(line.as_str()))
+                        .collect::<Vec<_>>(),
+                )
+                .style(self.default_style)
+                .scroll((self.scroll, 0))
+                .block(
+                    Block::bordered()
+                        .title(file_name(path))
+                        .title_bottom("Esc back, 'd' delete"),
+                ),
+                area,
+            );
+            return;
+        }
+        let title = if self.dir.is_some() {
+            format!("Failed Requests ({})", self.dumps.len())
+        } else {
+            "Failed Requests (set dump_failed_requests_to_file to collect them)".to_string()
+        };
+        frame.render_stateful_widget(
+            Table::new(
+                self.dumps.iter().map(|path| {
+                    let size = std::fs::metadata(path).map_or(0, |meta| meta.len());
+                    Row::new([file_name(path), format!("{size} B")])
+                }),
+                [Constraint::Min(10), Constraint::Length(12)],
+            )
+            .column_spacing(1)
+            .style(self.default_style)
+            .header(Row::new(["Dump", "Size"]).style(self.table_header_style))
+            .block(
+                Block::bordered()
+                    .title(title)
+                    .title_bottom("Enter open, 'd' delete, Esc back"),
+            )
+            .row_highlight_style(self.default_highlight_style)
+            .highlight_spacing(HighlightSpacing::Never),
+            area,
+            &mut self.state.clone(),
+        );
+    }
+}
+
+fn file_name(path: &std::path::Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::request_dump::{write_dump, RequestDumps};
+    use crate::config::init;
+
+    use super::*;
+
+    #[test]
+    fn open_and_delete() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+        let dump_dir = config.get_http_dump_dir().unwrap();
+
+        let mut browser = DumpBrowser::new(&config);
+        browser.refresh();
+        assert!(browser.selected_dump().is_none());
+        browser.open_selected().unwrap();
+        assert!(!browser.is_open());
+
+        let dumps = RequestDumps::default();
+        let first = write_dump(&dump_dir, "https://cloud/room", "{\"a\":1}", &dumps).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let second = write_dump(&dump_dir, "https://cloud/chat", "{\"b\":2}", &dumps).unwrap();
+
+        browser.refresh();
+        assert_eq!(browser.selected_dump(), Some(&second));
+        browser.select_down();
+        browser.select_down();
+        assert_eq!(browser.selected_dump(), Some(&first));
+
+        browser.open(second.clone()).unwrap();
+        assert!(browser.is_open());
+        assert_eq!(browser.selected_dump(), Some(&second));
+        assert_eq!(browser.opened.as_ref().unwrap().1.len(), 3);
+        browser.select_down();
+        browser.select_down();
+        browser.select_down();
+        assert_eq!(browser.scroll, 2);
+
+        browser.delete_selected().unwrap();
+        assert!(!second.exists());
+        assert!(!browser.is_open());
+        assert_eq!(browser.selected_dump(), Some(&first));
+    }
+}
@@ -10,6 +10,8 @@ pub struct HelpBox {
     default_highlight: Style,
     table_header: Style,
     popup_border: Style,
+    /// The terminal tells Shift+Enter from Enter, otherwise Ctrl+j stands in for it.
+    keyboard_enhanced: bool,
 }
 
 impl HelpBox {
@@ -19,54 +21,303 @@ impl HelpBox {
             default_highlight: config.theme.default_highlight_style(),
             table_header: config.theme.table_header_style(),
             popup_border: config.theme.popup_border_style(),
+            keyboard_enhanced: true,
         }
     }
+
+    pub fn set_keyboard_enhanced(&mut self, keyboard_enhanced: bool) {
+        self.keyboard_enhanced = keyboard_enhanced;
+    }
+
     pub fn render_area(&self, frame: &mut Frame, area: Rect) {
         frame.render_widget(self, area);
     }
+
+    /// Height needed to show all rows, including header, padding and borders.
+    pub fn height() -> u16 {
+        use std::convert::TryFrom;
+
+        u16::try_from(Self::rows(true).len()).unwrap_or(u16::MAX - 5) + 5
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn rows<'a>(keyboard_enhanced: bool) -> Vec<Row<'a>> {
+        vec![
+            Row::new(["q", "quit", "enter the quit screen."]),
+            Row::new(["o", "open", "enter the chat selection screen."]),
+            Row::new([
+                "u",
+                "users sidebar",
+                "Toggle whether the users are shown in a chat sidebar. Available in reading mode.",
+            ]),
+            Row::new([
+                "f",
+                "fetch history",
+                "Force a full history fetch of the current chat. Might take some time!",
+            ]),
+            Row::new(["?", "help", "enter this help screen."]),
+            Row::new([
+                "m",
+                "mark as read",
+                "mark current chat as read, when in reading mode.",
+            ]),
+            Row::new([
+                "M",
+                "mark all as read",
+                "mark all chats as read, when in reading mode.",
+            ]),
+            Row::new([
+                "(e|i)",
+                "edit",
+                "enter the editing screen, when in reading mode.",
+            ]),
+            Row::new([
+                "(u|d)",
+                "jump scroll",
+                "scroll up or down the lists in bigger intervals when in opening mode.",
+            ]),
+            Row::new([
+                "ESC",
+                "leave Mode",
+                "leave help, opening, or editing mode to return to reading mode",
+            ]),
+            Row::new([
+                "Enter",
+                "send/select",
+                "Send Message (new line if enter_sends is off), when in edit mode. Select chat when in opening mode.",
+            ]),
+            Row::new([
+                "space",
+                "mark room",
+                "Mark the selected room for a bulk action, when in opening mode.",
+            ]),
+            Row::new([
+                "r",
+                "bulk read",
+                "Mark all marked rooms as read, when in opening mode.",
+            ]),
+            Row::new([
+                "m",
+                "bulk mute",
+                "Toggle notifications of all marked rooms, when in opening mode.",
+            ]),
+            Row::new([
+                "a",
+                "bulk archive",
+                "Toggle archiving of all marked rooms, when in opening mode.",
+            ]),
+            Row::new([
+                "f",
+                "bulk favorite",
+                "Toggle favorite of all marked rooms, when in opening mode.",
+            ]),
+            Row::new([
+                "Z",
+                "undo send",
+                "Take the last pending message back into the input, when undo_send_seconds is set.",
+            ]),
+            Row::new([
+                "a",
+                "message actions",
+                "React to, reply to, edit, delete, copy, forward, pin or get reminded of the selected message.",
+            ]),
+            Row::new([
+                "A",
+                "archive",
+                "Toggle archiving of the current room, when in reading mode.",
+            ]),
+            Row::new([
+                "b",
+                "browse",
+                "Browse and join listable rooms, when in the chat selection screen.",
+            ]),
+            Row::new([
+                "C",
+                "join call",
+                "Open the running call of the current room in the browser, when in reading mode.",
+            ]),
+            Row::new([
+                "I",
+                "room info",
+                "Show details of the current room and when it was last synced, when in reading mode.",
+            ]),
+            Row::new([
+                "H",
+                "diagnostics",
+                "Show counters of the requests to the server, failures, retries and queued requests, when in reading mode.",
+            ]),
+            Row::new([
+                "N",
+                "local mute",
+                "Toggle muting the current or selected room only in this client, without notifications.",
+            ]),
+            Row::new([
+                "B",
+                "do not disturb",
+                "Toggle holding back all notifications, optionally setting the Nextcloud status.",
+            ]),
+            Row::new([
+                "S",
+                "set status",
+                "Set your status and status message, shown at the right of the title bar.",
+            ]),
+            Row::new([
+                "P",
+                "message user",
+                "Search for a user and open the direct message with them, creating it if needed.",
+            ]),
+            Row::new([
+                "S",
+                "sort rooms",
+                "Sort the rooms alphabetically, by last activity or unread first, when in opening mode.",
+            ]),
+            Row::new([
+                "z",
+                "zoom message",
+                "Show the selected message full screen with its reactions and replies, Esc to leave.",
+            ]),
+            Row::new([
+                "D",
+                "failed requests",
+                "Browse, open and delete the dumped responses which could not be read, when in reading mode.",
+            ]),
+            Row::new([
+                "W",
+                "switch account",
+                "Show the next account configured in a [server.<name>] section, when in reading or opening mode.",
+            ]),
+            Row::new([
+                "(gg|G)",
+                "first/last",
+                "select the first or last message, when in reading mode.",
+            ]),
+            Row::new([
+                "C-(u|d)",
+                "half page",
+                "move up or down by half the chat, when in reading mode. A count like 5j repeats j/k.",
+            ]),
+            Row::new([
+                "PgUp/PgDn",
+                "page",
+                "move up or down by the height of the chat, when in reading mode.",
+            ]),
+            Row::new([
+                "<|>",
+                "sidebar width",
+                "widen or narrow the user sidebar, ( and ) the name column. Kept in the config.",
+            ]),
+            Row::new([
+                "[|]",
+                "day jump",
+                "select the first message of the previous or next day, J jumps to a date.",
+            ]),
+            Row::new([
+                "L",
+                "log",
+                "show the log of the app, the title bar counts warnings and errors not seen there yet.",
+            ]),
+            Row::new([
+                "Tab",
+                "next unread",
+                "Open the next room with unread messages, mentions first, when in reading mode.",
+            ]),
+            Row::new([
+                "U",
+                "inbox",
+                "List unread mentions and direct messages of all rooms, Enter jumps to the message.",
+            ]),
+            Row::new([
+                "A-(←|→)",
+                "room history",
+                "Go back to the previously opened room or forward again, when in reading mode.",
+            ]),
+            Row::new([
+                "Tab",
+                "emoji",
+                "Complete the :shortcode: being typed to its emoji, when in editing mode.",
+            ]),
+            Row::new([
+                "A-e",
+                "external editor",
+                "Edit the message in $VISUAL or $EDITOR, when in editing mode.",
+            ]),
+            Row::new([
+                "A-v",
+                "share image",
+                "Share the image on the clipboard in the room, when in editing mode.",
+            ]),
+            if keyboard_enhanced {
+                Row::new([
+                    "A-Enter",
+                    "new line/send",
+                    "Start a new line like Shift+Enter (send if enter_sends is off), when in edit mode.",
+                ])
+            } else {
+                Row::new([
+                    "C-j",
+                    "new line/send",
+                    "Start a new line like Alt+Enter (send if enter_sends is off), when in edit mode. This terminal cannot tell Shift+Enter from Enter.",
+                ])
+            },
+            Row::new([
+                "A-s",
+                "spelling",
+                "Offer corrections of the misspelled word at the cursor, when in editing mode.",
+            ]),
+            Row::new([
+                ":",
+                "commands",
+                "Find and run a command by typing parts of its name, when in reading mode.",
+            ]),
+            Row::new([
+                "C-k",
+                "quick switch",
+                "Find a room by typing parts of its name and open it with Enter, from any screen.",
+            ]),
+            Row::new([
+                "/",
+                "search messages",
+                "Search the stored messages of the room, Enter on a match jumps to it, when in reading mode.",
+            ]),
+            Row::new([
+                "E",
+                "export room",
+                "Write the loaded messages of the room as markdown or JSON into the downloads dir.",
+            ]),
+            Row::new([
+                "s",
+                "room settings",
+                "Rename a group chat, edit its description or the password and link of public rooms.",
+            ]),
+            Row::new([
+                "p",
+                "participants",
+                "Focus the participant list of a group chat, when in reading mode.",
+            ]),
+            Row::new([
+                "a",
+                "invite",
+                "Search for a user to add to the room, when focusing participants.",
+            ]),
+            Row::new([
+                "(x|Del)",
+                "remove",
+                "Remove the selected participant from the room, when focusing participants.",
+            ]),
+            Row::new([
+                "Enter",
+                "profile",
+                "Show the status of the selected participant, open a direct message with them, promote, demote, remove or ban them, when focusing participants.",
+            ]),
+        ]
+    }
 }
 
 impl Widget for &HelpBox {
     fn render(self, area: Rect, buf: &mut Buffer) {
         Widget::render(
             Table::new(
-                vec![
-                    Row::new(["q", "quit", "enter the quit screen."]),
-                    Row::new(["o", "open", "enter the chat selection screen."]),
-                    Row::new(["u", "users sidebar", "Toggle whether the users are shown in a chat sidebar. Available in reading mode."]),
-                    Row::new(["f", "fetch history", "Force a full history fetch of the current chat. Might take some time!"]),
-                    Row::new(["?", "help", "enter this help screen."]),
-                    Row::new([
-                        "m",
-                        "mark as read",
-                        "mark current chat as read, when in reading mode.",
-                    ]),
-                    Row::new([
-                      "M",
-                      "mark all as read",
-                      "mark all chats as read, when in reading mode.",
-                  ]),
-                    Row::new([
-                        "(e|i)",
-                        "edit",
-                        "enter the editing screen, when in reading mode.",
-                    ]),
-                    Row::new([
-                        "(u|d)",
-                        "jump scroll",
-                        "scroll up or down the lists in bigger intervals when in opening mode.",
-                    ]),
-                    Row::new([
-                        "ESC",
-                        "leave Mode",
-                        "leave help, opening, or editing mode to return to reading mode",
-                    ]),
-                    Row::new([
-                        "Enter",
-                        "send/select",
-                        "Send Message, when in edit mode. Select chat when in opening mode.",
-                    ]),
-                ],
+                HelpBox::rows(self.keyboard_enhanced),
                 [
                     Constraint::Length(5),
                     Constraint::Length(20),
@@ -76,7 +327,12 @@ impl Widget for &HelpBox {
             .column_spacing(1)
             .style(self.default)
             .header(Row::new(vec!["Key", "Name", "Behavior"]).style(self.table_header))
-            .block(Block::bordered().title("Help").border_style(self.popup_border).padding(Padding::proportional(1)))
+            .block(
+                Block::bordered()
+                    .title("Help")
+                    .border_style(self.popup_border)
+                    .padding(Padding::proportional(1)),
+            )
             .row_highlight_style(self.default_highlight)
             .highlight_spacing(HighlightSpacing::Never),
             area,
@@ -88,7 +344,6 @@ impl Widget for &HelpBox {
 #[cfg(test)]
 mod tests {
 
-    use crate::backend::nc_request::NCReqDataParticipants;
     use crate::config::init;
     use backend::TestBackend;
 
@@ -105,9 +360,6 @@ mod tests {
         let mut terminal = Terminal::new(backend).unwrap();
         let help_box = HelpBox::new(&config);
 
-        let mut dummy_user = NCReqDataParticipants::default();
-        dummy_user.displayName = "Butz".to_string();
-
         terminal
             .draw(|frame| help_box.render_area(frame, Rect::new(0, 0, 46, 15)))
             .unwrap();
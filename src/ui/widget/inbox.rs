@@ -0,0 +1,231 @@
+use crate::backend::{chat_message::ChatMessage, ChatProvider, ChatRoom, Token};
+use crate::config::Config;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, HighlightSpacing, Row, Table, TableState},
+};
+
+/// Most messages listed, the most recent ones are kept.
+const MAX_ENTRIES: usize = 200;
+
+/// Unread message in the inbox and the room it is in.
+struct Entry {
+    token: Token,
+    message_id: i32,
+    timestamp: i64,
+    date: String,
+    room: String,
+    author: String,
+    text: String,
+}
+
+/// Screen listing the unread messages of all rooms which mention the user or are direct
+/// messages, the most recent first, to jump into their room.
+pub struct Inbox {
+    entries: Vec<Entry>,
+    state: TableState,
+    date_format: String,
+    default_style: Style,
+    default_highlight_style: Style,
+    table_header_style: Style,
+}
+
+/// Check if the message of another user is worth looking at, in a direct message or mentioning the user.
+fn is_for_user(message: &ChatMessage, is_dm: bool, user_id: &str) -> bool {
+    message.is_comment() && !message.is_from_user(user_id) && (is_dm || message.mentions(user_id))
+}
+
+impl Inbox {
+    pub fn new(config: &Config) -> Self {
+        Inbox {
+            entries: vec![],
+            state: TableState::default(),
+            date_format: format!("{} %H:%M", config.data.ui.date_format),
+            default_style: config.theme.default_style(),
+            default_highlight_style: config.theme.default_highlight_style(),
+            table_header_style: config.theme.table_header_style(),
+        }
+    }
+
+    /// Collect the unread messages of all rooms again, selecting the most recent one.
+    pub fn refresh(&mut self, backend: &impl ChatProvider, user_id: &str) {
+        let mut entries = vec![];
+        for token in backend.get_room_keys() {
+            let room = backend.get_room(token);
+            if !room.has_unread() || backend.is_locally_muted(token) {
+                continue;
+            }
+            let last_read = room.get_last_read();
+            entries.extend(
+                room.get_messages()
+                    .range(last_read + 1..)
+                    .map(|(_, message)| message)
+                    .filter(|message| is_for_user(message, room.is_dm(), user_id))
+                    .map(|message| Entry {
+                        token: token.clone(),
+                        message_id: message.get_id(),
+                        timestamp: message.get_timestamp(),
+                        date: message.get_date_str(&self.date_format),
+                        room: room.get_display_name().to_string(),
+                        author: message.get_name().to_string(),
+                        text: message.get_message().replace('\n', " "),
+                    }),
+            );
+        }
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.timestamp));
+        entries.truncate(MAX_ENTRIES);
+        self.entries = entries;
+        self.state = TableState::default().with_selected((!self.entries.is_empty()).then_some(0));
+    }
+
+    pub fn select_up(&mut self) {
+        self.state.select_previous();
+    }
+
+    pub fn select_down(&mut self) {
+        if self.state.selected().unwrap_or(0) + 1 < self.entries.len() {
+            self.state.select_next();
+        }
+    }
+
+    /// Get the room and id of the selected message.
+    pub fn selected(&self) -> Option<(Token, i32)> {
+        self.entries
+            .get(self.state.selected()?)
+            .map(|entry| (entry.token.clone(), entry.message_id))
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect) {
+        frame.render_stateful_widget(
+            Table::new(
+                self.entries.iter().map(|entry| {
+                    Row::new([
+                        entry.date.as_str(),
+                        entry.room.as_str(),
+                        entry.author.as_str(),
+                        entry.text.as_str(),
+                    ])
+                }),
+                [
+                    Constraint::Length(18),
+                    Constraint::Length(20),
+                    Constraint::Length(16),
+                    Constraint::Min(10),
+                ],
+            )
+            .column_spacing(1)
+            .style(self.default_style)
+            .header(Row::new(["Date", "Room", "Author", "Message"]).style(self.table_header_style))
+            .block(
+                Block::bordered()
+                    .title(format!(
+                        "Inbox, mentions and direct messages ({})",
+                        self.entries.len()
+                    ))
+                    .title_bottom("Enter jump to message, Esc back"),
+            )
+            .row_highlight_style(self.default_highlight_style)
+            .highlight_spacing(HighlightSpacing::Never),
+            area,
+            &mut self.state.clone(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{
+        chat_room::MockChatRoom, nc_request::NCReqDataMessage, nc_talk::MockNCTalk,
+    };
+    use crate::config::init;
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn message(id: i32, author: &str, text: &str, mentioned: Option<&str>) -> ChatMessage {
+        let parameters = mentioned.map_or(
+            serde_json::json!([]),
+            |user| serde_json::json!({"mention-user1": {"type": "user", "id": user, "name": user}}),
+        );
+        let data: NCReqDataMessage = serde_json::from_value(serde_json::json!({
+            "id": id, "token": "", "actorType": "users", "actorId": author,
+            "actorDisplayName": author, "timestamp": 1000 + i64::from(id),
+            "systemMessage": "", "messageType": "comment", "isReplyable": true,
+            "referenceId": "", "message": text, "messageParameters": parameters,
+            "expirationTimestamp": 0, "reactions": {}, "markdown": true
+        }))
+        .unwrap();
+        ChatMessage::from(data)
+    }
+
+    fn room(name: &str, is_dm: bool, messages: Vec<ChatMessage>) -> MockChatRoom {
+        let mut room = MockChatRoom::new();
+        room.expect_has_unread().return_const(true);
+        room.expect_get_last_read().return_const(1);
+        room.expect_is_dm().return_const(is_dm);
+        room.expect_get_display_name()
+            .return_const(name.to_string());
+        room.expect_get_messages().return_const(
+            messages
+                .into_iter()
+                .map(|message| (message.get_id(), message))
+                .collect::<BTreeMap<_, _>>(),
+        );
+        room
+    }
+
+    #[test]
+    fn collect_mentions_and_dms() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let general = room(
+            "General",
+            false,
+            vec![
+                message(1, "Bert", "@me read already", Some("me")),
+                message(2, "Bert", "hello @me", Some("me")),
+                message(5, "Bert", "just chatting", None),
+                message(6, "Hans", "@other", Some("other")),
+            ],
+        );
+        let dm = room(
+            "Hans",
+            true,
+            vec![
+                message(3, "Hans", "are you there?", None),
+                message(4, "me", "yes", None),
+            ],
+        );
+        let mut backend = MockNCTalk::new();
+        let tokens: Vec<&'static Token> = ["general", "dm"]
+            .iter()
+            .map(|token| &*Box::leak(Box::new(token.to_string())))
+            .collect();
+        backend.expect_get_room_keys().return_const(tokens);
+        backend.expect_is_locally_muted().return_const(false);
+        backend
+            .expect_get_room()
+            .withf(|token| token == "general")
+            .return_const(general);
+        backend
+            .expect_get_room()
+            .withf(|token| token == "dm")
+            .return_const(dm);
+
+        let mut inbox = Inbox::new(&config);
+        inbox.refresh(&backend, "me");
+        let texts: Vec<&str> = inbox
+            .entries
+            .iter()
+            .map(|entry| entry.text.as_str())
+            .collect();
+        assert_eq!(texts, ["are you there?", "hello @me"]);
+        assert_eq!(inbox.selected(), Some(("dm".to_string(), 3)));
+        inbox.select_down();
+        inbox.select_down();
+        assert_eq!(inbox.selected(), Some(("general".to_string(), 2)));
+    }
+}
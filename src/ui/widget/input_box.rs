@@ -1,13 +1,28 @@
 use crate::config::Config;
+use crate::ui::widget::spell_check::{words, SpellChecker};
 use ratatui::{
     prelude::*,
     widgets::{Block, Borders},
 };
-use tui_textarea::TextArea;
+use std::convert::TryFrom;
+use tui_textarea::{CursorMove, TextArea};
+
+/// Fewest lines of text the input shows, below its top border.
+const MIN_LINES: u16 = 2;
+/// Shortest start of a `:shortcode:` for which emojis are offered.
+const MIN_SHORTCODE_PREFIX: usize = 2;
+/// Most emojis offered for a shortcode at once.
+const MAX_COMPLETIONS: usize = 5;
 
 #[derive(Default)]
 pub struct InputBox<'a> {
     textarea: TextArea<'a>,
+    default_style: Style,
+    /// Most lines of text shown, longer drafts scroll.
+    max_lines: u16,
+    spell_checker: Option<SpellChecker>,
+    /// Text the misspelled words were last looked for in.
+    spell_checked: Vec<String>,
 }
 
 impl InputBox<'_> {
@@ -18,11 +33,202 @@ impl InputBox<'_> {
                 .borders(Borders::TOP)
                 .style(config.theme.default_style()),
         );
-        InputBox { textarea }
+        textarea.set_search_style(config.theme.title_important_style().underlined());
+        let spell_checker = SpellChecker::new(config).unwrap_or_else(|why| {
+            log::warn!("Not checking the spelling: {why}");
+            None
+        });
+        InputBox {
+            textarea,
+            default_style: config.theme.default_style(),
+            max_lines: config.data.ui.input_max_lines.max(MIN_LINES),
+            spell_checker,
+            spell_checked: vec![],
+        }
+    }
+
+    /// Rows needed to show the draft including the border, growing with it up to the max lines.
+    pub fn height(&self) -> u16 {
+        let lines = u16::try_from(self.textarea.lines().len()).unwrap_or(u16::MAX);
+        lines.min(self.max_lines).max(MIN_LINES) + 1
+    }
+
+    /// Show a title on the top border, e.g. while replying to or editing a message.
+    pub fn set_title(&mut self, title: &str) {
+        self.textarea.set_block(
+            Block::default()
+                .borders(Borders::TOP)
+                .title(title.to_string())
+                .style(self.default_style),
+        );
+    }
+
+    /// Start of a `:shortcode:` right before the cursor, without the colon.
+    fn shortcode_prefix(&self) -> Option<&str> {
+        let (row, column) = self.textarea.cursor();
+        let line = self.textarea.lines().get(row)?;
+        let before = &line[..line
+            .char_indices()
+            .nth(column)
+            .map_or(line.len(), |(i, _)| i)];
+        let start = before.rfind(':')?;
+        let prefix = &before[start + 1..];
+        let word_start = before[..start]
+            .chars()
+            .last()
+            .is_none_or(char::is_whitespace);
+        (word_start
+            && prefix.len() >= MIN_SHORTCODE_PREFIX
+            && prefix
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_+-".contains(c)))
+        .then_some(prefix)
+    }
+
+    /// Emojis with a shortcode starting like the one being typed, the shortest shortcodes first.
+    pub fn emoji_completions(&self) -> Vec<(&'static str, &'static str)> {
+        let Some(prefix) = self.shortcode_prefix() else {
+            return vec![];
+        };
+        let mut completions: Vec<_> = emojis::iter()
+            .flat_map(|emoji| {
+                emoji
+                    .shortcodes()
+                    .filter(|shortcode| shortcode.starts_with(prefix))
+                    .map(move |shortcode| (shortcode, emoji.as_str()))
+            })
+            .collect();
+        completions.sort_by_key(|(shortcode, _)| shortcode.len());
+        completions.truncate(MAX_COMPLETIONS);
+        completions
+    }
+
+    /// Replace the typed start of a shortcode by the emoji of the first completion.
+    pub fn complete_emoji(&mut self) -> bool {
+        let Some((shortcode, emoji)) = self.emoji_completions().first().copied() else {
+            return false;
+        };
+        let typed = self
+            .shortcode_prefix()
+            .map_or(0, |prefix| prefix.chars().count());
+        self.replace_before_cursor(typed + 1, emoji);
+        log::trace!("Completed :{shortcode}: to {emoji}");
+        true
+    }
+
+    /// Replace a complete `:shortcode:` right before the cursor by its emoji.
+    pub fn replace_shortcode(&mut self) {
+        let (row, column) = self.textarea.cursor();
+        let Some(line) = self.textarea.lines().get(row) else {
+            return;
+        };
+        let before: String = line.chars().take(column).collect();
+        let Some(rest) = before.strip_suffix(':') else {
+            return;
+        };
+        let Some(start) = rest.rfind(':') else {
+            return;
+        };
+        if !rest[..start].chars().last().is_none_or(char::is_whitespace) {
+            return;
+        }
+        if let Some(emoji) = emojis::get_by_shortcode(&rest[start + 1..]) {
+            let length = rest[start..].chars().count() + 1;
+            self.replace_before_cursor(length, emoji.as_str());
+        }
     }
 
-    pub fn render_area(&self, frame: &mut Frame, area: Rect) {
+    /// Highlight the words of the draft the dictionaries do not know, if the draft changed.
+    fn check_spelling(&mut self) {
+        let Some(checker) = &self.spell_checker else {
+            return;
+        };
+        if self.spell_checked == self.textarea.lines() {
+            return;
+        }
+        let misspelled = checker.misspelled(self.textarea.lines());
+        // Words only consist of letters and apostrophes, which need no escaping.
+        let pattern = if misspelled.is_empty() {
+            String::new()
+        } else {
+            format!(r"\b(?:{})\b", misspelled.join("|"))
+        };
+        if let Err(why) = self.textarea.set_search_pattern(pattern) {
+            log::warn!("Could not highlight misspelled words: {why}");
+        }
+        self.spell_checked = self.textarea.lines().to_vec();
+    }
+
+    /// Misspelled word at the cursor or the last one before it in the line, with its column.
+    fn misspelled_at_cursor(&self) -> Option<(usize, &str)> {
+        let checker = self.spell_checker.as_ref()?;
+        let (row, column) = self.textarea.cursor();
+        words(self.textarea.lines().get(row)?)
+            .into_iter()
+            .rev()
+            .find(|(start, word)| *start <= column && !checker.check(word))
+    }
+
+    /// Misspelled word at the cursor with the corrections for it, see [`Self::misspelled_at_cursor`].
+    pub fn spelling_suggestions(&self) -> Option<(String, Vec<String>)> {
+        let checker = self.spell_checker.as_ref()?;
+        let (_, word) = self.misspelled_at_cursor()?;
+        Some((word.to_string(), checker.suggest(word)))
+    }
+
+    /// Replace the misspelled word at the cursor by the correction, keeping the cursor in place.
+    pub fn correct_spelling(&mut self, correction: &str) {
+        let Some((start, word)) = self.misspelled_at_cursor() else {
+            return;
+        };
+        let length = word.chars().count();
+        let (row, column) = self.textarea.cursor();
+        let end = start + length;
+        let jump = |column: usize| {
+            CursorMove::Jump(
+                u16::try_from(row).unwrap_or(u16::MAX),
+                u16::try_from(column).unwrap_or(u16::MAX),
+            )
+        };
+        self.textarea.move_cursor(jump(end));
+        self.replace_before_cursor(length, correction);
+        if column >= end {
+            self.textarea
+                .move_cursor(jump(column - length + correction.chars().count()));
+        }
+    }
+
+    fn replace_before_cursor(&mut self, chars: usize, text: &str) {
+        for _ in 0..chars {
+            self.textarea.delete_char();
+        }
+        self.textarea.insert_str(text);
+    }
+
+    /// Render the draft with misspelled words highlighted, and the emojis offered for a shortcode
+    /// or the line of the cursor on the right of the border if the draft does not fit.
+    pub fn render_area(&mut self, frame: &mut Frame, area: Rect) {
+        self.check_spelling();
         frame.render_widget(&self.textarea, area);
+        let completions = self.emoji_completions();
+        let lines = self.textarea.lines().len();
+        if !completions.is_empty() {
+            let offered = completions
+                .iter()
+                .map(|(shortcode, emoji)| format!("{emoji} :{shortcode}:"))
+                .collect::<Vec<_>>()
+                .join("  ");
+            frame.render_widget(
+                Line::styled(format!(" Tab {offered} "), self.default_style).right_aligned(),
+                Rect { height: 1, ..area },
+            );
+        } else if lines + 1 > usize::from(area.height) {
+            let position = format!(" {}/{lines} ", self.textarea.cursor().0 + 1);
+            frame.render_widget(
+                Line::styled(position, self.default_style).right_aligned(),
+                Rect { height: 1, ..area },
+            );
+        }
     }
 }
 
@@ -43,7 +249,6 @@ impl std::ops::DerefMut for InputBox<'_> {
 #[cfg(test)]
 mod tests {
 
-    use crate::backend::nc_request::NCReqDataParticipants;
     use crate::config::init;
     use backend::TestBackend;
 
@@ -60,9 +265,6 @@ mod tests {
         let mut terminal = Terminal::new(backend).unwrap();
         let mut input_box = InputBox::new("test", &config);
 
-        let mut dummy_user = NCReqDataParticipants::default();
-        dummy_user.displayName = "Butz".to_string();
-
         terminal
             .draw(|frame| input_box.render_area(frame, Rect::new(0, 0, 30, 3)))
             .unwrap();
@@ -112,4 +314,90 @@ mod tests {
 
         terminal.backend().assert_buffer(&expected);
     }
+
+    #[test]
+    fn grow_and_scroll() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut input_box = InputBox::new("", &config);
+        assert_eq!(input_box.height(), 3);
+        input_box.insert_str("1\n2\n3");
+        assert_eq!(input_box.height(), 4);
+        input_box.insert_str("\n4\n5\n6\n7");
+        assert_eq!(input_box.height(), 6);
+
+        let backend = TestBackend::new(20, 6);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| input_box.render_area(frame, Rect::new(0, 0, 20, 6)))
+            .unwrap();
+        let top: String = (0..20)
+            .map(|x| terminal.backend().buffer()[(x, 0)].symbol())
+            .collect();
+        assert_eq!(top, "─────────────── 7/7 ");
+    }
+
+    #[test]
+    fn emoji_shortcodes() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut input_box = InputBox::new("", &config);
+        input_box.insert_str("nice :t");
+        assert!(input_box.emoji_completions().is_empty());
+        input_box.insert_str("hu");
+        let completions = input_box.emoji_completions();
+        assert!(completions.contains(&("thumbsup", "👍")));
+        assert!(completions.len() <= MAX_COMPLETIONS);
+
+        input_box.select_all();
+        input_box.cut();
+        input_box.insert_str("well :tad");
+        assert!(input_box.complete_emoji());
+        assert_eq!(input_box.lines(), ["well 🎉"]);
+        assert!(!input_box.complete_emoji());
+
+        input_box.insert_str(" 10:30 :+1:");
+        input_box.replace_shortcode();
+        assert_eq!(input_box.lines(), ["well 🎉 10:30 👍"]);
+        input_box.insert_str(" a:+1:");
+        input_box.replace_shortcode();
+        assert_eq!(input_box.lines(), ["well 🎉 10:30 👍 a:+1:"]);
+    }
+
+    #[test]
+    fn spelling() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let mut config = init("./test/").unwrap();
+        config.data.ui.spell_check_dictionaries = vec!["./test/spelling".to_string()];
+
+        let mut input_box = InputBox::new("", &config);
+        input_box.insert_str("helo world");
+        let (word, suggestions) = input_box.spelling_suggestions().unwrap();
+        assert_eq!(word, "helo");
+        assert!(suggestions.contains(&"hello".to_string()));
+
+        input_box.correct_spelling("hello");
+        assert_eq!(input_box.lines(), ["hello world"]);
+        assert_eq!(input_box.cursor(), (0, 11));
+        assert!(input_box.spelling_suggestions().is_none());
+
+        let backend = TestBackend::new(20, 3);
+        let mut terminal = Terminal::new(backend).unwrap();
+        input_box.insert_str(" wrld");
+        terminal
+            .draw(|frame| input_box.render_area(frame, Rect::new(0, 0, 20, 3)))
+            .unwrap();
+        let highlighted = config.theme.title_important_style().underlined();
+        let buffer = terminal.backend().buffer();
+        assert_eq!(Some(buffer[(12, 1)].bg), highlighted.bg);
+        assert_ne!(Some(buffer[(6, 1)].bg), highlighted.bg);
+    }
 }
@@ -0,0 +1,159 @@
+use crate::backend::chat_types::FoundUser;
+use crate::config::Config;
+use crossterm::event::KeyEvent;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, HighlightSpacing, Row, Table, TableState},
+};
+use tui_textarea::TextArea;
+
+/// Popup to search for a user and invite them into the current room, or message them.
+pub struct InviteBox<'a> {
+    /// Whether the selected user is messaged instead of invited, until the next reset.
+    pub direct_message: bool,
+    search_bar: TextArea<'a>,
+    /// User id and label of all search results.
+    results: Vec<(String, String)>,
+    last_search: String,
+    state: TableState,
+    default_style: Style,
+    default_highlight_style: Style,
+    popup_border_style: Style,
+}
+
+impl InviteBox<'_> {
+    pub fn new(config: &Config) -> Self {
+        let mut search_bar = TextArea::new(vec![String::new()]);
+        search_bar.set_placeholder_text("Type a name and press Enter to search");
+        search_bar.set_placeholder_style(config.theme.default_style());
+        search_bar.set_style(config.theme.default_highlight_style());
+        search_bar.set_block(Block::bordered().border_style(config.theme.default_style()));
+        InviteBox {
+            direct_message: false,
+            search_bar,
+            results: vec![],
+            last_search: String::new(),
+            state: TableState::default(),
+            default_style: config.theme.default_style(),
+            default_highlight_style: config.theme.default_highlight_style(),
+            popup_border_style: config.theme.popup_border_style(),
+        }
+    }
+
+    /// Clear search and results, for reopening the popup.
+    pub fn reset(&mut self) {
+        self.search_bar.select_all();
+        self.search_bar.cut();
+        self.results.clear();
+        self.last_search.clear();
+        self.state = TableState::default();
+        self.direct_message = false;
+    }
+
+    pub fn input(&mut self, key: KeyEvent) {
+        self.search_bar.input(key);
+    }
+
+    pub fn search_text(&self) -> String {
+        self.search_bar.lines().join("")
+    }
+
+    /// Check if the search text changed since the last results were set.
+    pub fn needs_search(&self) -> bool {
+        self.results.is_empty() || self.search_text() != self.last_search
+    }
+
+    pub fn set_results(&mut self, users: Vec<FoundUser>) {
+        self.last_search = self.search_text();
+        self.results = users
+            .into_iter()
+            .map(|user| (user.id, user.display_name))
+            .collect();
+        self.state = TableState::default().with_selected(0);
+    }
+
+    pub fn select_up(&mut self) {
+        self.state.select_previous();
+    }
+
+    pub fn select_down(&mut self) {
+        if self.state.selected().unwrap_or(0) + 1 < self.results.len() {
+            self.state.select_next();
+        }
+    }
+
+    /// Get user id and label of the selected search result.
+    pub fn selected_user(&self) -> Option<&(String, String)> {
+        self.results.get(self.state.selected()?)
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered()
+            .title(if self.direct_message {
+                "Message User"
+            } else {
+                "Invite User"
+            })
+            .border_style(self.popup_border_style)
+            .style(self.default_style);
+        let [search_area, results_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(block.inner(area));
+        frame.render_widget(block, area);
+        frame.render_widget(&self.search_bar, search_area);
+        frame.render_stateful_widget(
+            Table::new(
+                self.results
+                    .iter()
+                    .map(|(id, label)| Row::new([label.clone(), id.clone()])),
+                [Constraint::Percentage(60), Constraint::Percentage(40)],
+            )
+            .style(self.default_style)
+            .row_highlight_style(self.default_highlight_style)
+            .highlight_spacing(HighlightSpacing::Never),
+            results_area,
+            &mut self.state.clone(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::init;
+
+    use super::*;
+
+    #[test]
+    fn search_and_select() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut invite = InviteBox::new(&config);
+        assert!(invite.needs_search());
+        assert!(invite.selected_user().is_none());
+
+        invite.search_bar.insert_str("bu");
+        let butz = FoundUser {
+            id: "butz".to_string(),
+            display_name: "Butz".to_string(),
+        };
+        let bert = FoundUser {
+            id: "bert".to_string(),
+            display_name: "Bert".to_string(),
+        };
+        invite.set_results(vec![butz, bert]);
+        assert!(!invite.needs_search());
+        assert_eq!(invite.selected_user().unwrap().0, "butz");
+        invite.select_down();
+        invite.select_down();
+        assert_eq!(invite.selected_user().unwrap().0, "bert");
+
+        invite.search_bar.insert_char('r');
+        assert!(invite.needs_search());
+
+        invite.reset();
+        assert!(invite.search_text().is_empty());
+        assert!(invite.selected_user().is_none());
+    }
+}
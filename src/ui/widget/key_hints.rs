@@ -0,0 +1,167 @@
+use crate::config::Config;
+use crate::ui::app::CurrentScreen;
+use ratatui::{prelude::*, widgets::Paragraph};
+
+/// Line at the bottom listing the most used keys of the current screen, '?' shows all of them.
+pub struct KeyHints {
+    default_style: Style,
+    key_style: Style,
+    enter_sends: bool,
+    /// The terminal tells Shift+Enter from Enter, otherwise Ctrl+j stands in for it.
+    keyboard_enhanced: bool,
+}
+
+impl KeyHints {
+    pub fn new(config: &Config) -> Self {
+        KeyHints {
+            default_style: config.theme.default_style(),
+            key_style: config.theme.table_header_style(),
+            enter_sends: config.data.ui.enter_sends,
+            keyboard_enhanced: true,
+        }
+    }
+
+    pub fn set_keyboard_enhanced(&mut self, keyboard_enhanced: bool) {
+        self.keyboard_enhanced = keyboard_enhanced;
+    }
+
+    /// Keys and what they do on `screen`, the most relevant first.
+    fn hints(&self, screen: CurrentScreen) -> &'static [(&'static str, &'static str)] {
+        match screen {
+            CurrentScreen::Reading => &[
+                ("e", "write"),
+                ("j/k", "select"),
+                ("a", "actions"),
+                ("o", "rooms"),
+                ("Tab", "next unread"),
+                ("C-k", "switch"),
+                ("/", "search"),
+                (":", "commands"),
+                ("?", "help"),
+                ("q", "quit"),
+            ],
+            CurrentScreen::Editing if !self.enter_sends => &[
+                ("A-Enter", "send"),
+                ("Enter", "new line"),
+                ("Esc", "stop writing"),
+                ("A-e", "editor"),
+                ("A-v", "share image"),
+                ("C-k", "switch"),
+            ],
+            CurrentScreen::Editing => &[
+                ("Enter", "send"),
+                ("A-Enter", "new line"),
+                ("Esc", "stop writing"),
+                ("A-e", "editor"),
+                ("A-v", "share image"),
+                ("C-k", "switch"),
+            ],
+            CurrentScreen::Opening => &[
+                ("Enter", "open"),
+                ("j/k", "select"),
+                ("/", "search"),
+                ("Space", "mark"),
+                ("S", "sort"),
+                ("b", "browse"),
+                ("Esc", "back"),
+                ("?", "help"),
+            ],
+            CurrentScreen::Users => &[
+                ("j/k", "select"),
+                ("Enter", "profile"),
+                ("a", "invite"),
+                ("Esc", "back"),
+            ],
+            CurrentScreen::Browsing => {
+                &[("Enter", "search/join"), ("↑/↓", "select"), ("Esc", "back")]
+            }
+            CurrentScreen::Dumps => &[
+                ("Enter", "open"),
+                ("d", "delete"),
+                ("j/k", "select"),
+                ("Esc", "back"),
+            ],
+            CurrentScreen::Inbox => &[
+                ("Enter", "jump"),
+                ("j/k", "select"),
+                ("Esc", "back"),
+                ("?", "help"),
+            ],
+            CurrentScreen::Zoomed => &[("j/k", "scroll"), ("Esc", "back"), ("?", "help")],
+            CurrentScreen::Logging => &[("o", "rooms"), ("Esc", "back"), ("?", "help")],
+        }
+    }
+
+    fn line(&self, screen: CurrentScreen) -> Line<'static> {
+        let mut spans = vec![];
+        for (key, action) in self.hints(screen) {
+            if !spans.is_empty() {
+                spans.push(Span::raw("  "));
+            }
+            let key = if *key == "A-Enter" && !self.keyboard_enhanced {
+                "C-j"
+            } else {
+                key
+            };
+            spans.push(Span::styled(key, self.key_style));
+            spans.push(Span::raw(format!(" {action}")));
+        }
+        Line::from(spans)
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect, screen: CurrentScreen) {
+        frame.render_widget(
+            Paragraph::new(self.line(screen)).style(self.default_style),
+            area,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::init;
+    use backend::TestBackend;
+
+    use super::*;
+
+    #[test]
+    fn render() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let hints = KeyHints::new(&config);
+        let backend = TestBackend::new(40, 1);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| {
+                hints.render_area(frame, Rect::new(0, 0, 40, 1), CurrentScreen::Logging);
+            })
+            .unwrap();
+
+        let mut expected = Buffer::with_lines(["o rooms  Esc back  ? help               "]);
+        expected.set_style(Rect::new(0, 0, 40, 1), config.theme.default_style());
+        expected.set_style(Rect::new(0, 0, 1, 1), config.theme.table_header_style());
+        expected.set_style(Rect::new(9, 0, 3, 1), config.theme.table_header_style());
+        expected.set_style(Rect::new(19, 0, 1, 1), config.theme.table_header_style());
+
+        terminal.backend().assert_buffer(&expected);
+    }
+
+    #[test]
+    fn enter_hints() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let mut config = init("./test/").unwrap();
+
+        let first = |hints: &KeyHints| hints.line(CurrentScreen::Editing).spans[0].content.clone();
+        assert_eq!(first(&KeyHints::new(&config)), "Enter");
+        config.data.ui.enter_sends = false;
+        let mut hints = KeyHints::new(&config);
+        assert_eq!(first(&hints), "A-Enter");
+        hints.set_keyboard_enhanced(false);
+        assert_eq!(first(&hints), "C-j");
+    }
+}
@@ -0,0 +1,81 @@
+use crate::config::Config;
+use chrono::{DateTime, Local, Utc};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph},
+};
+
+/// Banner shown instead of the input, while the lobby of the room keeps non moderators out.
+pub struct LobbyBanner {
+    style: Style,
+}
+
+impl LobbyBanner {
+    pub fn new(config: &Config) -> Self {
+        LobbyBanner {
+            style: config.theme.unread_message_style(),
+        }
+    }
+
+    /// Text of the banner, `lobby_timer` is the unix timestamp the lobby opens at, 0 if unknown.
+    fn text(lobby_timer: i32) -> String {
+        match DateTime::<Utc>::from_timestamp(lobby_timer.into(), 0) {
+            Some(time) if lobby_timer > 0 => format!(
+                "Waiting for the host to open the lobby, opens at {}",
+                DateTime::<Local>::from(time).format("%H:%M")
+            ),
+            _ => "Waiting for the host to open the lobby".to_string(),
+        }
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect, lobby_timer: i32) {
+        frame.render_widget(
+            Paragraph::new(LobbyBanner::text(lobby_timer))
+                .style(self.style)
+                .alignment(Alignment::Center)
+                .block(Block::default().borders(Borders::TOP)),
+            area,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::init;
+    use backend::TestBackend;
+
+    use super::*;
+
+    #[test]
+    fn render() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let banner = LobbyBanner::new(&config);
+        let backend = TestBackend::new(40, 3);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| banner.render_area(frame, Rect::new(0, 0, 40, 3), 0))
+            .unwrap();
+
+        let mut expected = Buffer::with_lines([
+            "────────────────────────────────────────",
+            " Waiting for the host to open the lobby ",
+            "                                        ",
+        ]);
+        expected.set_style(Rect::new(0, 0, 40, 3), config.theme.unread_message_style());
+
+        terminal.backend().assert_buffer(&expected);
+
+        let time = DateTime::<Local>::from(DateTime::<Utc>::from_timestamp(3600, 0).unwrap());
+        assert_eq!(
+            LobbyBanner::text(3600),
+            format!(
+                "Waiting for the host to open the lobby, opens at {}",
+                time.format("%H:%M")
+            )
+        );
+    }
+}
@@ -55,8 +55,7 @@ impl LogBox {
         frame.render_widget(logger, log_area);
         if area.width > 40 {
             let help_text = Text::from(vec![
-                "s: Cancel Scroll | Tab: Switch state | ↑/↓: Select target | f: Focus target"
-                    .into(),
+                "s: Cancel Scroll | ↑/↓: Select target | f: Focus target".into(),
                 "←/→: Display level | +/-: Filter level | Space: Toggle hidden targets".into(),
                 "h: Hide target selector | PageUp/Down: Scroll | Esc: Exit this screen".into(),
             ])
@@ -0,0 +1,318 @@
+use crate::backend::{chat_message::ChatMessage, chat_types::ChatFeature, Token};
+use crate::config::Config;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, HighlightSpacing, List, ListState},
+};
+use std::{convert::TryFrom, fmt};
+
+/// Shortcodes of the reactions offered after choosing [`MessageAction::React`].
+pub const QUICK_REACTIONS: [&str; 6] = ["+1", "heart", "joy", "open_mouth", "cry", "tada"];
+/// Hours from now offered after choosing [`MessageAction::Remind`].
+pub const REMINDER_HOURS: [i64; 4] = [1, 3, 24, 168];
+/// Talk only allows editing messages younger than a day.
+const EDIT_MAX_AGE: i64 = 24 * 60 * 60;
+/// Talk only allows deleting messages younger than six hours.
+const DELETE_MAX_AGE: i64 = 6 * 60 * 60;
+
+/// Context actions on a single message of the current room.
+#[derive(PartialEq, Clone, Debug)]
+pub enum MessageAction {
+    React,
+    ReactWith(String),
+    RemoveReaction(String),
+    Reply,
+    Edit,
+    Delete,
+    Copy,
+    Forward,
+    Remind,
+    RemindIn(i64),
+    Pin,
+    /// Open the room and message a permalink in the message points to.
+    JumpTo(Token, i32),
+}
+
+impl fmt::Display for MessageAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MessageAction::React => write!(f, "React"),
+            MessageAction::ReactWith(reaction) => {
+                match emojis::get(reaction).and_then(emojis::Emoji::shortcode) {
+                    Some(shortcode) => write!(f, "{reaction} :{shortcode}:"),
+                    None => write!(f, "{reaction}"),
+                }
+            }
+            MessageAction::RemoveReaction(reaction) => write!(f, "Remove reaction {reaction}"),
+            MessageAction::Reply => write!(f, "Reply"),
+            MessageAction::Edit => write!(f, "Edit"),
+            MessageAction::Delete => write!(f, "Delete"),
+            MessageAction::Copy => write!(f, "Copy text"),
+            MessageAction::Forward => write!(f, "Forward"),
+            MessageAction::Remind => write!(f, "Remind me"),
+            MessageAction::RemindIn(1) => write!(f, "In 1 hour"),
+            MessageAction::RemindIn(24) => write!(f, "Tomorrow"),
+            MessageAction::RemindIn(168) => write!(f, "Next week"),
+            MessageAction::RemindIn(hours) => write!(f, "In {hours} hours"),
+            MessageAction::Pin => write!(f, "Pin"),
+            MessageAction::JumpTo(..) => write!(f, "Jump to linked message"),
+        }
+    }
+}
+
+impl MessageAction {
+    /// Collect the actions the current user may apply to the message.
+    /// `now` is the current unix timestamp, edits and deletes are limited by the age of the message.
+    pub fn available_for(
+        message: &ChatMessage,
+        own_user: &str,
+        is_moderator: bool,
+        now: i64,
+    ) -> Vec<MessageAction> {
+        if !message.is_comment() {
+            return vec![];
+        }
+        let own = message.is_from_user(own_user);
+        let age = now - message.get_timestamp();
+
+        let mut actions = vec![MessageAction::React];
+        actions.extend(
+            message
+                .get_own_reactions()
+                .iter()
+                .cloned()
+                .map(MessageAction::RemoveReaction),
+        );
+        if message.is_replyable() {
+            actions.push(MessageAction::Reply);
+        }
+        if own && age < EDIT_MAX_AGE {
+            actions.push(MessageAction::Edit);
+        }
+        if (own || is_moderator) && age < DELETE_MAX_AGE {
+            actions.push(MessageAction::Delete);
+        }
+        actions.extend([
+            MessageAction::Copy,
+            MessageAction::Forward,
+            MessageAction::Remind,
+        ]);
+        if is_moderator {
+            actions.push(MessageAction::Pin);
+        }
+        actions
+    }
+
+    /// Optional server feature the action needs.
+    pub fn required_feature(&self) -> Option<ChatFeature> {
+        match self {
+            MessageAction::React
+            | MessageAction::ReactWith(_)
+            | MessageAction::RemoveReaction(_) => Some(ChatFeature::Reactions),
+            MessageAction::Edit => Some(ChatFeature::EditMessages),
+            MessageAction::Remind | MessageAction::RemindIn(_) => Some(ChatFeature::Reminders),
+            MessageAction::Pin => Some(ChatFeature::PinnedMessages),
+            _ => None,
+        }
+    }
+
+    /// Check if the action should be confirmed before applying it.
+    pub fn needs_confirmation(&self) -> bool {
+        *self == MessageAction::Delete
+    }
+}
+
+/// Context popup listing the [`MessageAction`]s for the selected message.
+pub struct MessageMenu {
+    actions: Vec<MessageAction>,
+    state: ListState,
+    default_style: Style,
+    default_highlight_style: Style,
+    popup_border_style: Style,
+}
+
+impl MessageMenu {
+    pub fn new(config: &Config) -> Self {
+        MessageMenu {
+            actions: vec![],
+            state: ListState::default(),
+            default_style: config.theme.default_style(),
+            default_highlight_style: config.theme.default_highlight_style(),
+            popup_border_style: config.theme.popup_border_style(),
+        }
+    }
+
+    /// Fill the menu with the given actions and select the first one.
+    pub fn open(&mut self, actions: Vec<MessageAction>) {
+        self.actions = actions;
+        self.state = ListState::default().with_selected(Some(0));
+    }
+
+    pub fn height(&self) -> u16 {
+        u16::try_from(self.actions.len()).unwrap_or(u16::MAX) + 2
+    }
+
+    pub fn select_up(&mut self) {
+        self.state.select_previous();
+    }
+
+    pub fn select_down(&mut self) {
+        if self.state.selected().unwrap_or(0) + 1 < self.actions.len() {
+            self.state.select_next();
+        }
+    }
+
+    pub fn selected(&self) -> Option<MessageAction> {
+        self.actions.get(self.state.selected()?).cloned()
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect, title: &str) {
+        frame.render_stateful_widget(
+            List::new(self.actions.iter().map(ToString::to_string))
+                .style(self.default_style)
+                .highlight_style(self.default_highlight_style)
+                .highlight_spacing(HighlightSpacing::Never)
+                .block(
+                    Block::bordered()
+                        .title(title)
+                        .border_style(self.popup_border_style),
+                ),
+            area,
+            &mut self.state.clone(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::nc_request::NCReqDataMessage;
+    use crate::config::init;
+    use backend::TestBackend;
+
+    use super::*;
+
+    #[test]
+    fn available_actions() {
+        let mut data = NCReqDataMessage {
+            messageType: "comment".to_string(),
+            actorType: "users".to_string(),
+            actorId: "butz".to_string(),
+            isReplyable: true,
+            timestamp: 1000,
+            ..Default::default()
+        };
+        data.reactionsSelf = vec!["👍".to_string()];
+        let message = ChatMessage::from(data);
+
+        assert_eq!(
+            MessageAction::available_for(&message, "butz", false, 1000),
+            vec![
+                MessageAction::React,
+                MessageAction::RemoveReaction("👍".to_string()),
+                MessageAction::Reply,
+                MessageAction::Edit,
+                MessageAction::Delete,
+                MessageAction::Copy,
+                MessageAction::Forward,
+                MessageAction::Remind,
+            ]
+        );
+        assert_eq!(
+            MessageAction::available_for(&message, "bert", true, 1000 + DELETE_MAX_AGE),
+            vec![
+                MessageAction::React,
+                MessageAction::RemoveReaction("👍".to_string()),
+                MessageAction::Reply,
+                MessageAction::Copy,
+                MessageAction::Forward,
+                MessageAction::Remind,
+                MessageAction::Pin,
+            ]
+        );
+
+        let system = ChatMessage::from(NCReqDataMessage {
+            messageType: "system".to_string(),
+            ..Default::default()
+        });
+        assert!(MessageAction::available_for(&system, "butz", true, 0).is_empty());
+    }
+
+    #[test]
+    fn required_features() {
+        assert_eq!(
+            MessageAction::ReactWith("👍".to_string()).required_feature(),
+            Some(ChatFeature::Reactions)
+        );
+        assert_eq!(
+            MessageAction::Edit.required_feature(),
+            Some(ChatFeature::EditMessages)
+        );
+        assert_eq!(
+            MessageAction::RemindIn(1).required_feature(),
+            Some(ChatFeature::Reminders)
+        );
+        assert_eq!(
+            MessageAction::Pin.required_feature(),
+            Some(ChatFeature::PinnedMessages)
+        );
+        assert_eq!(MessageAction::Copy.required_feature(), None);
+    }
+
+    #[test]
+    fn reaction_names() {
+        assert_eq!(
+            QUICK_REACTIONS
+                .iter()
+                .filter_map(|shortcode| emojis::get_by_shortcode(shortcode))
+                .map(|emoji| MessageAction::ReactWith(emoji.to_string()).to_string())
+                .collect::<Vec<_>>(),
+            [
+                "👍 :+1:",
+                "❤️ :heart:",
+                "😂 :joy:",
+                "😮 :open_mouth:",
+                "😢 :cry:",
+                "🎉 :tada:"
+            ]
+        );
+        assert_eq!(MessageAction::ReactWith("x".to_string()).to_string(), "x");
+    }
+
+    #[test]
+    fn render_and_select() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut menu = MessageMenu::new(&config);
+        menu.open(REMINDER_HOURS.map(MessageAction::RemindIn).to_vec());
+        assert_eq!(menu.height(), 6);
+        menu.select_down();
+        assert_eq!(menu.selected(), Some(MessageAction::RemindIn(3)));
+        menu.select_up();
+
+        let backend = TestBackend::new(20, 6);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| menu.render_area(frame, Rect::new(0, 0, 20, 6), "Remind me"))
+            .unwrap();
+
+        let mut expected = Buffer::with_lines([
+            "┌Remind me─────────┐",
+            "│In 1 hour         │",
+            "│In 3 hours        │",
+            "│Tomorrow          │",
+            "│Next week         │",
+            "└──────────────────┘",
+        ]);
+        expected.set_style(Rect::new(0, 0, 20, 6), config.theme.popup_border_style());
+        expected.set_style(Rect::new(1, 1, 18, 4), config.theme.default_style());
+        expected.set_style(
+            Rect::new(1, 1, 18, 1),
+            config.theme.default_highlight_style(),
+        );
+
+        terminal.backend().assert_buffer(&expected);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::backend::chat_message::ChatMessage;
+use crate::config::Config;
+use crossterm::event::KeyEvent;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, HighlightSpacing, Row, Table, TableState},
+};
+use tui_textarea::TextArea;
+
+/// Popup to search the stored messages of the current room and jump to a match.
+pub struct MessageSearch<'a> {
+    search_bar: TextArea<'a>,
+    /// Message id, date, author and text of all matches.
+    results: Vec<(i32, String, String, String)>,
+    last_search: Option<String>,
+    state: TableState,
+    date_format: String,
+    default_style: Style,
+    default_highlight_style: Style,
+    popup_border_style: Style,
+}
+
+impl MessageSearch<'_> {
+    pub fn new(config: &Config) -> Self {
+        let mut search_bar = TextArea::new(vec![String::new()]);
+        search_bar.set_placeholder_text("Type a text and press Enter to search");
+        search_bar.set_placeholder_style(config.theme.default_style());
+        search_bar.set_style(config.theme.default_highlight_style());
+        search_bar.set_block(Block::bordered().border_style(config.theme.default_style()));
+        MessageSearch {
+            search_bar,
+            results: vec![],
+            last_search: None,
+            state: TableState::default(),
+            date_format: format!("{} %H:%M", config.data.ui.date_format),
+            default_style: config.theme.default_style(),
+            default_highlight_style: config.theme.default_highlight_style(),
+            popup_border_style: config.theme.popup_border_style(),
+        }
+    }
+
+    /// Clear search and results, for reopening the popup.
+    pub fn reset(&mut self) {
+        self.search_bar.select_all();
+        self.search_bar.cut();
+        self.results.clear();
+        self.last_search = None;
+        self.state = TableState::default();
+    }
+
+    pub fn input(&mut self, key: KeyEvent) {
+        self.search_bar.input(key);
+    }
+
+    pub fn search_text(&self) -> String {
+        self.search_bar.lines().join("")
+    }
+
+    /// Check if the search text changed since the last results were set.
+    pub fn needs_search(&self) -> bool {
+        self.last_search.as_ref() != Some(&self.search_text())
+    }
+
+    pub fn set_results(&mut self, messages: &[ChatMessage]) {
+        self.last_search = Some(self.search_text());
+        self.results = messages
+            .iter()
+            .map(|message| {
+                (
+                    message.get_id(),
+                    message.get_date_str(&self.date_format),
+                    message.get_name().to_string(),
+                    message.get_message().replace('\n', " "),
+                )
+            })
+            .collect();
+        self.state = TableState::default().with_selected((!self.results.is_empty()).then_some(0));
+    }
+
+    pub fn select_up(&mut self) {
+        self.state.select_previous();
+    }
+
+    pub fn select_down(&mut self) {
+        if self.state.selected().unwrap_or(0) + 1 < self.results.len() {
+            self.state.select_next();
+        }
+    }
+
+    /// Get the id of the selected message.
+    pub fn selected_message(&self) -> Option<i32> {
+        self.results
+            .get(self.state.selected()?)
+            .map(|result| result.0)
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect) {
+        let title = match &self.last_search {
+            Some(_) => format!("Search Messages ({} found)", self.results.len()),
+            None => "Search Messages".to_string(),
+        };
+        let block = Block::bordered()
+            .title(title)
+            .title_bottom("Enter search or jump, Esc close")
+            .border_style(self.popup_border_style)
+            .style(self.default_style);
+        let [search_area, results_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(block.inner(area));
+        frame.render_widget(block, area);
+        frame.render_widget(&self.search_bar, search_area);
+        frame.render_stateful_widget(
+            Table::new(
+                self.results.iter().map(|(_, date, name, text)| {
+                    Row::new([date.clone(), name.clone(), text.clone()])
+                }),
+                [
+                    Constraint::Length(18),
+                    Constraint::Length(16),
+                    Constraint::Min(10),
+                ],
+            )
+            .column_spacing(1)
+            .style(self.default_style)
+            .row_highlight_style(self.default_highlight_style)
+            .highlight_spacing(HighlightSpacing::Never),
+            results_area,
+            &mut self.state.clone(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::nc_request::NCReqDataMessage;
+    use crate::config::init;
+
+    use super::*;
+
+    #[test]
+    fn search_and_select() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut search = MessageSearch::new(&config);
+        assert!(search.needs_search());
+        assert!(search.selected_message().is_none());
+
+        search.search_bar.insert_str("hello");
+        let messages: Vec<ChatMessage> = vec![7, 3]
+            .into_iter()
+            .map(|id| {
+                ChatMessage::from(NCReqDataMessage {
+                    id,
+                    message: "hello\nthere".to_string(),
+                    messageType: "comment".to_string(),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        search.set_results(&messages);
+        assert!(!search.needs_search());
+        assert_eq!(search.results[0].3, "hello there");
+        assert_eq!(search.selected_message(), Some(7));
+        search.select_down();
+        search.select_down();
+        assert_eq!(search.selected_message(), Some(3));
+
+        search.set_results(&[]);
+        assert!(!search.needs_search());
+        assert!(search.selected_message().is_none());
+
+        search.reset();
+        assert!(search.needs_search());
+        assert!(search.search_text().is_empty());
+    }
+}
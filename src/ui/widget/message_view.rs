@@ -0,0 +1,420 @@
+use super::code_highlight::{highlight_line, CodeStyles, Language};
+use crate::backend::chat_message::ChatMessage;
+use crate::config::Config;
+use itertools::Itertools;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Paragraph},
+};
+use std::convert::TryFrom;
+
+/// Full screen view of a single message, for reading long messages comfortably.
+pub struct MessageView {
+    message: ChatMessage,
+    parent: Option<ChatMessage>,
+    replies: Vec<ChatMessage>,
+    date_format: String,
+    detect_code_language: bool,
+    scroll: u16,
+    /// Lines of the last render, scrolling stops at the last one.
+    line_count: u16,
+    default_style: Style,
+    code_style: Style,
+    heading_style: Style,
+    quote_style: Style,
+    table_header_style: Style,
+}
+
+impl MessageView {
+    pub fn new(config: &Config) -> Self {
+        MessageView {
+            message: ChatMessage::default(),
+            parent: None,
+            replies: vec![],
+            date_format: config.data.ui.date_format.clone(),
+            detect_code_language: config.data.ui.detect_code_language,
+            scroll: 0,
+            line_count: 0,
+            default_style: config.theme.default_style(),
+            code_style: config
+                .theme
+                .default_style()
+                .add_modifier(Modifier::REVERSED),
+            heading_style: config
+                .theme
+                .default_style()
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            quote_style: config.theme.default_style().add_modifier(Modifier::ITALIC),
+            table_header_style: config.theme.table_header_style(),
+        }
+    }
+
+    /// Show the message, with the message it replies to and the replies to it.
+    pub fn open(
+        &mut self,
+        message: ChatMessage,
+        parent: Option<ChatMessage>,
+        replies: Vec<ChatMessage>,
+    ) {
+        self.message = message;
+        self.parent = parent;
+        self.replies = replies;
+        self.scroll = 0;
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.scroll = self.scroll.saturating_sub(1);
+    }
+
+    pub fn scroll_down(&mut self) {
+        if self.scroll + 1 < self.line_count {
+            self.scroll += 1;
+        }
+    }
+
+    /// Collect all lines of the view, wrapped to `width`.
+    fn lines(&self, width: usize) -> Vec<Line<'static>> {
+        let mut lines = vec![];
+        if let Some(parent) = &self.parent {
+            lines.push(Line::styled(
+                format!("In reply to {}:", parent.get_name()),
+                self.table_header_style,
+            ));
+            lines.extend(
+                textwrap::wrap(parent.get_message(), width.saturating_sub(2).max(1))
+                    .into_iter()
+                    .map(|line| Line::styled(format!("│ {line}"), self.quote_style)),
+            );
+            lines.push(Line::default());
+        }
+        lines.extend(markdown_lines(
+            self.message.get_message(),
+            width,
+            MarkdownStyles {
+                code: self.code_style,
+                heading: self.heading_style,
+                quote: self.quote_style,
+            },
+            self.detect_code_language,
+        ));
+        let reactions = self.message.get_reactions();
+        if !reactions.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::styled("Reactions", self.table_header_style));
+            lines.push(Line::from(
+                reactions
+                    .iter()
+                    .map(|(reaction, count)| format!("{reaction} {count}"))
+                    .collect::<Vec<_>>()
+                    .join("  "),
+            ));
+        }
+        if !self.replies.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::styled(
+                format!("Replies ({})", self.replies.len()),
+                self.table_header_style,
+            ));
+            for reply in &self.replies {
+                let text = format!(
+                    "{} {}: {}",
+                    reply.get_time_str(),
+                    reply.get_name(),
+                    reply.get_message().replace('\n', " ")
+                );
+                lines.extend(
+                    textwrap::wrap(&text, width)
+                        .into_iter()
+                        .map(|line| Line::from(line.into_owned())),
+                );
+            }
+        }
+        lines
+    }
+
+    pub fn render_area(&mut self, frame: &mut Frame, area: Rect) {
+        let lines = self.lines(area.width.saturating_sub(2).max(1).into());
+        self.line_count = u16::try_from(lines.len()).unwrap_or(u16::MAX);
+        self.scroll = self.scroll.min(self.line_count.saturating_sub(1));
+        let title = format!(
+            "{} {} {} (Esc to leave)",
+            self.message.get_name(),
+            self.message.get_date_str(&self.date_format),
+            self.message.get_time_str()
+        );
+        frame.render_widget(
+            Paragraph::new(lines)
+                .style(self.default_style)
+                .scroll((self.scroll, 0))
+                .block(Block::bordered().title(title)),
+            area,
+        );
+    }
+}
+
+/// Styles of the markdown elements rendered by [`markdown_lines`].
+#[derive(Clone, Copy)]
+struct MarkdownStyles {
+    code: Style,
+    heading: Style,
+    quote: Style,
+}
+
+/// Render the markdown of a message into lines wrapped to `width`.
+/// Only headings, quotes, lists, code blocks and inline emphasis and code are styled.
+/// Code blocks without a language tag get one guessed, if `detect_language` is set.
+fn markdown_lines(
+    text: &str,
+    width: usize,
+    styles: MarkdownStyles,
+    detect_language: bool,
+) -> Vec<Line<'static>> {
+    let mut lines = vec![];
+    let mut raw_lines = text.split('\n');
+    while let Some(raw) = raw_lines.next() {
+        if let Some(tag) = raw.trim_start().strip_prefix("```") {
+            let code = raw_lines
+                .by_ref()
+                .take_while(|line| !line.trim_start().starts_with("```"))
+                .collect_vec();
+            lines.extend(code_block_lines(
+                tag,
+                &code,
+                width,
+                CodeStyles::new(styles.code),
+                detect_language,
+            ));
+            continue;
+        }
+        let heading = raw.trim_start_matches('#');
+        let (first_prefix, prefix, rest, style) =
+            if raw.starts_with('#') && heading.starts_with(' ') {
+                ("", "", heading.trim_start(), styles.heading)
+            } else if let Some(quote) = raw.strip_prefix('>') {
+                ("│ ", "│ ", quote.trim_start(), styles.quote)
+            } else if let Some(item) = raw.strip_prefix("- ").or_else(|| raw.strip_prefix("* ")) {
+                ("• ", "  ", item, Style::default())
+            } else {
+                ("", "", raw, Style::default())
+            };
+        let wrapped = textwrap::wrap(rest, width.saturating_sub(prefix.chars().count()).max(1));
+        if wrapped.is_empty() {
+            lines.push(Line::default());
+        }
+        for (index, line) in wrapped.iter().enumerate() {
+            let lead = if index == 0 { first_prefix } else { prefix };
+            let mut spans = vec![Span::raw(lead)];
+            spans.extend(inline_spans(line, styles.code));
+            lines.push(Line::from(spans).style(style));
+        }
+    }
+    lines
+}
+
+/// Render a fenced code block, headed by its language if it is tagged or detected.
+fn code_block_lines(
+    tag: &str,
+    code: &[&str],
+    width: usize,
+    styles: CodeStyles,
+    detect_language: bool,
+) -> Vec<Line<'static>> {
+    let tag = tag.trim();
+    let (language, header) = if tag.is_empty() {
+        let language = detect_language
+            .then(|| Language::detect(&code.join("\n")))
+            .flatten();
+        (
+            language,
+            language.map(|language| format!("{language} (detected)")),
+        )
+    } else {
+        (Language::from_tag(tag), Some(tag.to_string()))
+    };
+    let mut lines = vec![];
+    if let Some(header) = header {
+        lines.push(Line::styled(format!("── {header}"), styles.keyword));
+    }
+    for raw in code {
+        lines.extend(
+            textwrap::wrap(raw, width)
+                .into_iter()
+                .map(|line| match language {
+                    Some(language) => Line::from(highlight_line(&line, language, styles)),
+                    None => Line::styled(line.into_owned(), styles.code),
+                }),
+        );
+    }
+    lines
+}
+
+/// Split a line at `**`, `*` and backtick markers into styled spans.
+/// Markers without a closing one on the same line are kept as text.
+fn inline_spans(text: &str, code_style: Style) -> Vec<Span<'static>> {
+    let mut spans = vec![];
+    let mut current = String::new();
+    let mut style = Style::default();
+    let mut open: Vec<&str> = vec![];
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let marker = if rest.starts_with('`') {
+            Some("`")
+        } else if open.last() == Some(&"`") {
+            None
+        } else if rest.starts_with("**") {
+            Some("**")
+        } else if rest.starts_with('*') {
+            Some("*")
+        } else {
+            None
+        };
+        match marker {
+            Some(marker)
+                if open.last() == Some(&marker) || rest[marker.len()..].contains(marker) =>
+            {
+                if !current.is_empty() {
+                    spans.push(Span::styled(std::mem::take(&mut current), style));
+                }
+                if open.last() == Some(&marker) {
+                    open.pop();
+                } else {
+                    open.push(marker);
+                }
+                style = open
+                    .iter()
+                    .fold(Style::default(), |style, marker| match *marker {
+                        "`" => style.patch(code_style),
+                        "**" => style.add_modifier(Modifier::BOLD),
+                        _ => style.add_modifier(Modifier::ITALIC),
+                    });
+                rest = &rest[marker.len()..];
+            }
+            _ => {
+                current.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    if !current.is_empty() {
+        spans.push(Span::styled(current, style));
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::nc_request::NCReqDataMessage;
+    use crate::config::init;
+    use backend::TestBackend;
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn inline_markdown() {
+        let code = Style::new().reversed();
+        assert_eq!(
+            inline_spans("a **bold** and *it* `c*de` 2 * 3", code),
+            vec![
+                Span::raw("a "),
+                Span::styled("bold", Style::new().bold()),
+                Span::raw(" and "),
+                Span::styled("it", Style::new().italic()),
+                Span::raw(" "),
+                Span::styled("c*de", code),
+                Span::raw(" 2 * 3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn code_blocks() {
+        let styles = MarkdownStyles {
+            code: Style::new().reversed(),
+            heading: Style::new().bold(),
+            quote: Style::new().italic(),
+        };
+        let text = "```\nfn main() {\n    println!();\n}\n```\n```go\nfunc main() {}\n```";
+        let lines = markdown_lines(text, 40, styles, true);
+        assert_eq!(
+            lines[0],
+            Line::styled("── rust (detected)", styles.code.bold())
+        );
+        assert_eq!(lines[1].spans[0], Span::styled("fn", styles.code.bold()));
+        assert_eq!(lines[4], Line::styled("── go", styles.code.bold()));
+        assert_eq!(lines[5], Line::styled("func main() {}", styles.code));
+        assert_eq!(lines.len(), 6);
+
+        let lines = markdown_lines(text, 40, styles, false);
+        assert_eq!(lines[0], Line::styled("fn main() {", styles.code));
+        assert_eq!(lines.len(), 5);
+    }
+
+    #[test]
+    fn render() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let message = ChatMessage::from(NCReqDataMessage {
+            messageType: "comment".to_string(),
+            actorDisplayName: "Butz".to_string(),
+            message: "# Title\nSome long text to wrap\n- item\n```\nlet a;\n```".to_string(),
+            reactions: HashMap::from([("👍".to_string(), 2)]),
+            ..Default::default()
+        });
+        let reply = ChatMessage::from(NCReqDataMessage {
+            messageType: "comment".to_string(),
+            actorDisplayName: "Bert".to_string(),
+            message: "ok".to_string(),
+            ..Default::default()
+        });
+        let mut view = MessageView::new(&config);
+        view.open(message, None, vec![reply.clone()]);
+
+        let backend = TestBackend::new(30, 14);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| view.render_area(frame, Rect::new(0, 0, 30, 14)))
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let text = (1..13)
+            .map(|y| {
+                (1..29)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            text,
+            [
+                "Title".to_string(),
+                "Some long text to wrap".to_string(),
+                "• item".to_string(),
+                "let a;".to_string(),
+                String::new(),
+                "Reactions".to_string(),
+                // the emoji is two cells wide
+                "👍  2".to_string(),
+                String::new(),
+                "Replies (1)".to_string(),
+                format!("{} Bert: ok", reply.get_time_str()),
+                String::new(),
+                String::new(),
+            ]
+        );
+        assert_eq!(
+            buffer[(1, 1)].style().add_modifier,
+            Modifier::BOLD | Modifier::UNDERLINED
+        );
+
+        view.scroll_down();
+        view.scroll_down();
+        view.scroll_up();
+        assert_eq!(view.scroll, 1);
+    }
+}
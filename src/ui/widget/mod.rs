@@ -1,7 +1,29 @@
 pub mod chat_box;
 pub mod chat_selector;
+pub mod code_highlight;
+pub mod command_palette;
+pub mod date_jump;
+pub mod diagnostics;
+pub mod dump_browser;
 pub mod help_box;
+pub mod inbox;
 pub mod input_box;
+pub mod invite_box;
+pub mod key_hints;
+pub mod lobby_banner;
 pub mod logger;
+pub mod message_menu;
+pub mod message_search;
+pub mod message_view;
+pub mod participant_menu;
+pub mod preview_banner;
+pub mod quick_switcher;
+pub mod room_badge;
+pub mod room_browser;
+pub mod room_info;
+pub mod room_settings;
+pub mod spell_check;
+pub mod status_form;
 pub mod title_bar;
+pub mod toast;
 pub mod users;
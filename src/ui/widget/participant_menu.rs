@@ -0,0 +1,249 @@
+use crate::backend::chat_types::Participant;
+use crate::config::Config;
+use chrono::{DateTime, Local};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, HighlightSpacing, List, ListState, Paragraph},
+};
+use std::convert::TryFrom;
+use strum_macros::Display;
+
+/// Actions on a participant of the current room, all but opening a direct message need a moderator.
+#[derive(PartialEq, Clone, Copy, Debug, Display)]
+pub enum ParticipantAction {
+    #[strum(to_string = "Open direct message")]
+    DirectMessage,
+    #[strum(to_string = "Promote to moderator")]
+    Promote,
+    #[strum(to_string = "Demote to user")]
+    Demote,
+    #[strum(to_string = "Remove from room")]
+    Remove,
+    #[strum(to_string = "Ban from room")]
+    Ban,
+}
+
+impl ParticipantAction {
+    /// Check if the action should be confirmed before applying it.
+    pub fn needs_confirmation(self) -> bool {
+        matches!(self, ParticipantAction::Remove | ParticipantAction::Ban)
+    }
+}
+
+/// Name of the online status of a participant.
+fn status_name(status: &str) -> &str {
+    match status {
+        "online" => "Online",
+        "away" => "Away",
+        "dnd" => "Do not disturb",
+        "offline" => "Offline",
+        "invisible" => "Invisible",
+        unknown => unknown,
+    }
+}
+
+/// Profile popup of the selected participant with their status, listing the
+/// [`ParticipantAction`]s for them below.
+pub struct ParticipantMenu {
+    details: Vec<String>,
+    actions: Vec<ParticipantAction>,
+    state: ListState,
+    default_style: Style,
+    default_highlight_style: Style,
+    popup_border_style: Style,
+}
+
+impl ParticipantMenu {
+    pub fn new(config: &Config) -> Self {
+        ParticipantMenu {
+            details: vec![],
+            actions: vec![],
+            state: ListState::default(),
+            default_style: config.theme.default_style(),
+            default_highlight_style: config.theme.default_highlight_style(),
+            popup_border_style: config.theme.popup_border_style(),
+        }
+    }
+
+    /// Fill the popup for a participant. A direct message is offered if they can be messaged,
+    /// moderators can promote or demote depending on their role, remove or ban them, unless
+    /// they own the room.
+    pub fn open(&mut self, participant: &Participant, can_message: bool, can_moderate: bool) {
+        let mut status = participant
+            .status
+            .as_deref()
+            .map_or("Unknown", status_name)
+            .to_string();
+        if let Some(icon) = participant
+            .status_icon
+            .as_deref()
+            .filter(|icon| !icon.is_empty())
+        {
+            status = format!("{icon} {status}");
+        }
+        if let Some(message) = participant
+            .status_message
+            .as_deref()
+            .filter(|message| !message.is_empty())
+        {
+            status = format!("{status}, {message}");
+        }
+        self.details = vec![
+            format!("Id: {}", participant.actor_id),
+            format!("Role: {}", participant.role),
+            format!("Status: {status}"),
+        ];
+        if let Some(clear_at) = participant
+            .status_clear_at
+            .and_then(|clear_at| DateTime::from_timestamp(clear_at, 0))
+        {
+            self.details.push(format!(
+                "Until: {}",
+                DateTime::<Local>::from(clear_at).format("%Y-%m-%d %H:%M")
+            ));
+        }
+        if participant.in_call {
+            self.details.push("In the call".to_string());
+        }
+
+        self.actions = vec![];
+        if can_message {
+            self.actions.push(ParticipantAction::DirectMessage);
+        }
+        if can_moderate && !participant.role.is_owner() {
+            self.actions.push(if participant.role.is_moderator() {
+                ParticipantAction::Demote
+            } else {
+                ParticipantAction::Promote
+            });
+            self.actions.push(ParticipantAction::Remove);
+            self.actions.push(ParticipantAction::Ban);
+        }
+        self.state = ListState::default().with_selected((!self.actions.is_empty()).then_some(0));
+    }
+
+    pub fn height(&self) -> u16 {
+        // The actions are set apart from the details by an empty line.
+        let separator = usize::from(!self.actions.is_empty());
+        u16::try_from(self.details.len() + separator + self.actions.len()).unwrap_or(u16::MAX) + 2
+    }
+
+    pub fn select_up(&mut self) {
+        self.state.select_previous();
+    }
+
+    pub fn select_down(&mut self) {
+        if self.state.selected().unwrap_or(0) + 1 < self.actions.len() {
+            self.state.select_next();
+        }
+    }
+
+    pub fn selected(&self) -> Option<ParticipantAction> {
+        self.actions.get(self.state.selected()?).copied()
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect, participant: &str) {
+        let block = Block::bordered()
+            .title(participant)
+            .border_style(self.popup_border_style)
+            .style(self.default_style);
+        let details_height = u16::try_from(self.details.len()).unwrap_or(u16::MAX);
+        let [details_area, _, actions_area] = Layout::vertical([
+            Constraint::Length(details_height),
+            Constraint::Length(1),
+            Constraint::Fill(1),
+        ])
+        .areas(block.inner(area));
+        frame.render_widget(block, area);
+        frame.render_widget(Paragraph::new(self.details.join("\n")), details_area);
+        frame.render_stateful_widget(
+            List::new(self.actions.iter().map(ToString::to_string))
+                .highlight_style(self.default_highlight_style)
+                .highlight_spacing(HighlightSpacing::Never),
+            actions_area,
+            &mut self.state.clone(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::init;
+    use backend::TestBackend;
+
+    use super::*;
+    use crate::backend::chat_types::ParticipantRole;
+
+    #[test]
+    fn render_and_select() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut participant = Participant {
+            actor_id: "butz".to_string(),
+            is_user: true,
+            role: ParticipantRole::User,
+            status: Some("away".to_string()),
+            status_icon: Some("🍕".to_string()),
+            status_message: Some("Lunch".to_string()),
+            ..Default::default()
+        };
+
+        let mut menu = ParticipantMenu::new(&config);
+        menu.open(&participant, false, false);
+        assert_eq!(menu.details[2], "Status: 🍕 Away, Lunch");
+        assert_eq!(menu.height(), 5);
+        assert_eq!(menu.selected(), None);
+
+        menu.open(&participant, true, true);
+        assert_eq!(menu.height(), 10);
+        assert_eq!(menu.selected(), Some(ParticipantAction::DirectMessage));
+        menu.select_down();
+        assert_eq!(menu.selected(), Some(ParticipantAction::Promote));
+        menu.select_down();
+        menu.select_down();
+        menu.select_down();
+        assert_eq!(menu.selected(), Some(ParticipantAction::Ban));
+        assert!(menu.selected().unwrap().needs_confirmation());
+
+        participant.role = ParticipantRole::Moderator;
+        participant.status_icon = None;
+        menu.open(&participant, false, true);
+        assert_eq!(menu.selected(), Some(ParticipantAction::Demote));
+
+        participant.role = ParticipantRole::Owner;
+        menu.open(&participant, true, true);
+        assert_eq!(menu.actions, [ParticipantAction::DirectMessage]);
+        participant.role = ParticipantRole::Moderator;
+        menu.open(&participant, false, true);
+
+        let backend = TestBackend::new(30, 9);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| menu.render_area(frame, Rect::new(0, 0, 30, 9), "Butz"))
+            .unwrap();
+
+        let mut expected = Buffer::with_lines([
+            "┌Butz────────────────────────┐",
+            "│Id: butz                    │",
+            "│Role: Moderator             │",
+            "│Status: Away, Lunch         │",
+            "│                            │",
+            "│Demote to user              │",
+            "│Remove from room            │",
+            "│Ban from room               │",
+            "└────────────────────────────┘",
+        ]);
+        expected.set_style(Rect::new(0, 0, 30, 9), config.theme.default_style());
+        expected.set_style(Rect::new(0, 0, 30, 9), config.theme.popup_border_style());
+        expected.set_style(Rect::new(1, 1, 28, 7), config.theme.default_style());
+        expected.set_style(
+            Rect::new(1, 5, 28, 1),
+            config.theme.default_highlight_style(),
+        );
+
+        terminal.backend().assert_buffer(&expected);
+    }
+}
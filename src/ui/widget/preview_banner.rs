@@ -0,0 +1,60 @@
+use crate::config::Config;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph},
+};
+
+/// Banner shown instead of the input, while the user only previews the room without a joined session.
+pub struct PreviewBanner {
+    style: Style,
+}
+
+impl PreviewBanner {
+    pub fn new(config: &Config) -> Self {
+        PreviewBanner {
+            style: config.theme.unread_message_style(),
+        }
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(
+            Paragraph::new("You are previewing this conversation, press 'e' to join")
+                .style(self.style)
+                .alignment(Alignment::Center)
+                .block(Block::default().borders(Borders::TOP)),
+            area,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::init;
+    use backend::TestBackend;
+
+    use super::*;
+
+    #[test]
+    fn render() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let banner = PreviewBanner::new(&config);
+        let backend = TestBackend::new(60, 3);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| banner.render_area(frame, Rect::new(0, 0, 60, 3)))
+            .unwrap();
+
+        let mut expected = Buffer::with_lines([
+            "────────────────────────────────────────────────────────────",
+            "   You are previewing this conversation, press 'e' to join  ",
+            "                                                            ",
+        ]);
+        expected.set_style(Rect::new(0, 0, 60, 3), config.theme.unread_message_style());
+
+        terminal.backend().assert_buffer(&expected);
+    }
+}
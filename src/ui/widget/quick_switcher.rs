@@ -0,0 +1,170 @@
+use crate::backend::Token;
+use crate::config::Config;
+use crossterm::event::KeyEvent;
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, HighlightSpacing, Row, Table, TableState},
+};
+use tui_textarea::TextArea;
+
+/// Indices of the `names` matching `pattern`, the best match first and equal ones in their order.
+pub fn fuzzy_rank<'n>(
+    matcher: &SkimMatcherV2,
+    names: impl Iterator<Item = &'n str>,
+    pattern: &str,
+) -> Vec<usize> {
+    let mut scored: Vec<(i64, usize)> = names
+        .enumerate()
+        .filter_map(|(index, name)| {
+            matcher
+                .fuzzy_match(name, pattern.trim())
+                .map(|score| (score, index))
+        })
+        .collect();
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().map(|(_, index)| index).collect()
+}
+
+/// Popup to find a room by typing parts of its name, the best matches listed first.
+pub struct QuickSwitcher<'a> {
+    search_bar: TextArea<'a>,
+    /// Token and display name of all rooms.
+    rooms: Vec<(Token, String)>,
+    /// Indices into `rooms` of the matches, best first.
+    results: Vec<usize>,
+    matcher: SkimMatcherV2,
+    state: TableState,
+    default_style: Style,
+    default_highlight_style: Style,
+    popup_border_style: Style,
+}
+
+impl QuickSwitcher<'_> {
+    pub fn new(config: &Config) -> Self {
+        let mut search_bar = TextArea::new(vec![String::new()]);
+        search_bar.set_placeholder_text("Type a part of the room name");
+        search_bar.set_placeholder_style(config.theme.default_style());
+        search_bar.set_style(config.theme.default_highlight_style());
+        search_bar.set_block(Block::bordered().border_style(config.theme.default_style()));
+        QuickSwitcher {
+            search_bar,
+            rooms: vec![],
+            results: vec![],
+            matcher: SkimMatcherV2::default(),
+            state: TableState::default(),
+            default_style: config.theme.default_style(),
+            default_highlight_style: config.theme.default_highlight_style(),
+            popup_border_style: config.theme.popup_border_style(),
+        }
+    }
+
+    /// Clear the search and list all `rooms`, for reopening the popup.
+    pub fn reset(&mut self, rooms: Vec<(Token, String)>) {
+        self.search_bar.select_all();
+        self.search_bar.cut();
+        self.rooms = rooms;
+        self.rank();
+    }
+
+    pub fn input(&mut self, key: KeyEvent) {
+        if self.search_bar.input(key) {
+            self.rank();
+        }
+    }
+
+    fn rank(&mut self) {
+        let pattern = self.search_bar.lines().join("");
+        self.results = fuzzy_rank(
+            &self.matcher,
+            self.rooms.iter().map(|(_, name)| name.as_str()),
+            &pattern,
+        );
+        self.state = TableState::default().with_selected((!self.results.is_empty()).then_some(0));
+    }
+
+    pub fn select_up(&mut self) {
+        self.state.select_previous();
+    }
+
+    pub fn select_down(&mut self) {
+        if self.state.selected().unwrap_or(0) + 1 < self.results.len() {
+            self.state.select_next();
+        }
+    }
+
+    /// Get the token of the selected room.
+    pub fn selected_room(&self) -> Option<&Token> {
+        let index = self.results.get(self.state.selected()?)?;
+        Some(&self.rooms[*index].0)
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered()
+            .title(format!("Switch Room ({} found)", self.results.len()))
+            .title_bottom("Enter switch, Esc close")
+            .border_style(self.popup_border_style)
+            .style(self.default_style);
+        let [search_area, results_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(block.inner(area));
+        frame.render_widget(block, area);
+        frame.render_widget(&self.search_bar, search_area);
+        frame.render_stateful_widget(
+            Table::new(
+                self.results
+                    .iter()
+                    .map(|index| Row::new([self.rooms[*index].1.clone()])),
+                [Constraint::Min(10)],
+            )
+            .style(self.default_style)
+            .row_highlight_style(self.default_highlight_style)
+            .highlight_spacing(HighlightSpacing::Never),
+            results_area,
+            &mut self.state.clone(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::init;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    use super::*;
+
+    #[test]
+    fn rank_and_select() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut switcher = QuickSwitcher::new(&config);
+        switcher.reset(
+            vec!["Butz", "General", "Gardening Team", "Bert"]
+                .into_iter()
+                .map(|name| (name.to_lowercase(), name.to_string()))
+                .collect(),
+        );
+        assert_eq!(switcher.results.len(), 4);
+        assert_eq!(switcher.selected_room(), Some(&"butz".to_string()));
+
+        for key in "gen".chars() {
+            switcher.input(KeyEvent::new(KeyCode::Char(key), KeyModifiers::NONE));
+        }
+        assert_eq!(switcher.results.len(), 2);
+        assert_eq!(switcher.selected_room(), Some(&"general".to_string()));
+        switcher.select_down();
+        switcher.select_down();
+        assert_eq!(
+            switcher.selected_room(),
+            Some(&"gardening team".to_string())
+        );
+
+        switcher.input(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+        assert!(switcher.selected_room().is_none());
+
+        switcher.reset(vec![]);
+        assert!(switcher.selected_room().is_none());
+    }
+}
@@ -0,0 +1,78 @@
+use crate::config::Config;
+use ratatui::prelude::*;
+
+/// Colored two letter badge of a room, so rooms are easier to tell apart when scanning a list.
+pub struct RoomBadge {
+    styles: Vec<Style>,
+    default_style: Style,
+}
+
+impl RoomBadge {
+    pub fn new(config: &Config) -> Self {
+        RoomBadge {
+            styles: config.theme.room_badge_styles(),
+            default_style: config.theme.default_style().reversed(),
+        }
+    }
+
+    /// Two letter initialism of a room name, the first letters of the first two words
+    /// or the first two letters of a single word.
+    pub fn initials(name: &str) -> String {
+        let words = name
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>();
+        let initials: String = match words.as_slice() {
+            [] => "?".to_string(),
+            [word] => word.chars().take(2).collect(),
+            [first, second, ..] => first
+                .chars()
+                .take(1)
+                .chain(second.chars().take(1))
+                .collect(),
+        };
+        format!("{:<2}", initials.to_uppercase())
+    }
+
+    /// Style of the badge, picked by a hash of the name, so it stays the same between runs.
+    fn style(&self, name: &str) -> Style {
+        if self.styles.is_empty() {
+            return self.default_style;
+        }
+        let hash = name.bytes().fold(0_usize, |hash, byte| {
+            hash.wrapping_mul(31).wrapping_add(byte.into())
+        });
+        self.styles[hash % self.styles.len()]
+    }
+
+    pub fn span<'a>(&self, name: &str) -> Span<'a> {
+        Span::styled(RoomBadge::initials(name), self.style(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::init;
+
+    use super::*;
+
+    #[test]
+    fn badge() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        assert_eq!(RoomBadge::initials("General"), "GE");
+        assert_eq!(RoomBadge::initials("Off Topic"), "OT");
+        assert_eq!(RoomBadge::initials("#dev-ops"), "DO");
+        assert_eq!(RoomBadge::initials("X"), "X ");
+        assert_eq!(RoomBadge::initials("🎉"), "? ");
+
+        let badge = RoomBadge::new(&config);
+        let span = badge.span("General");
+        assert_eq!(span.content, "GE");
+        assert_eq!(span.style, badge.span("General").style);
+        assert!(config.theme.room_badge_styles().contains(&span.style));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::backend::{chat_types::ListedRoom, Token};
+use crate::config::Config;
+use crossterm::event::KeyEvent;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, HighlightSpacing, Row, Table, TableState},
+};
+use tui_textarea::TextArea;
+
+/// Screen to search listable rooms on the server and join them.
+pub struct RoomBrowser<'a> {
+    search_bar: TextArea<'a>,
+    /// Token, display name and description of all rooms found.
+    results: Vec<(Token, String, String)>,
+    last_search: Option<String>,
+    state: TableState,
+    default_style: Style,
+    default_highlight_style: Style,
+    table_header_style: Style,
+}
+
+impl RoomBrowser<'_> {
+    pub fn new(config: &Config) -> Self {
+        let mut search_bar = TextArea::new(vec![String::new()]);
+        search_bar.set_placeholder_text("Type a name and press Enter to search");
+        search_bar.set_placeholder_style(config.theme.default_style());
+        search_bar.set_style(config.theme.default_highlight_style());
+        search_bar.set_block(
+            Block::bordered()
+                .title("Public Rooms")
+                .border_style(config.theme.default_style()),
+        );
+        RoomBrowser {
+            search_bar,
+            results: vec![],
+            last_search: None,
+            state: TableState::default(),
+            default_style: config.theme.default_style(),
+            default_highlight_style: config.theme.default_highlight_style(),
+            table_header_style: config.theme.table_header_style(),
+        }
+    }
+
+    /// Clear search and results, for reopening the screen.
+    pub fn reset(&mut self) {
+        self.search_bar.select_all();
+        self.search_bar.cut();
+        self.results.clear();
+        self.last_search = None;
+        self.state = TableState::default();
+    }
+
+    pub fn input(&mut self, key: KeyEvent) {
+        self.search_bar.input(key);
+    }
+
+    pub fn search_text(&self) -> String {
+        self.search_bar.lines().join("")
+    }
+
+    /// Check if the search text changed since the last results were set.
+    pub fn needs_search(&self) -> bool {
+        self.last_search.as_deref() != Some(self.search_text().as_str())
+    }
+
+    pub fn set_results(&mut self, rooms: Vec<ListedRoom>) {
+        self.last_search = Some(self.search_text());
+        self.results = rooms
+            .into_iter()
+            .map(|room| (room.token, room.display_name, room.description))
+            .collect();
+        self.state = TableState::default().with_selected(0);
+    }
+
+    pub fn select_up(&mut self) {
+        self.state.select_previous();
+    }
+
+    pub fn select_down(&mut self) {
+        if self.state.selected().unwrap_or(0) + 1 < self.results.len() {
+            self.state.select_next();
+        }
+    }
+
+    /// Get the token of the selected room.
+    pub fn selected_room(&self) -> Option<&Token> {
+        self.results
+            .get(self.state.selected()?)
+            .map(|(token, _, _)| token)
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect) {
+        let [search_area, results_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(area);
+        frame.render_widget(&self.search_bar, search_area);
+        frame.render_stateful_widget(
+            Table::new(
+                self.results.iter().map(|(_, name, description)| {
+                    Row::new([name.clone(), description.replace('\n', " ")])
+                }),
+                [Constraint::Percentage(30), Constraint::Percentage(70)],
+            )
+            .column_spacing(1)
+            .style(self.default_style)
+            .header(Row::new(["Name", "Description"]).style(self.table_header_style))
+            .row_highlight_style(self.default_highlight_style)
+            .highlight_spacing(HighlightSpacing::Never),
+            results_area,
+            &mut self.state.clone(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::init;
+    use backend::TestBackend;
+
+    use super::*;
+
+    #[test]
+    fn search_select_and_render() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut browser = RoomBrowser::new(&config);
+        assert!(browser.needs_search());
+        assert!(browser.selected_room().is_none());
+
+        browser.set_results(vec![
+            ListedRoom {
+                token: "123".to_string(),
+                display_name: "Butz".to_string(),
+                description: "All about Butz".to_string(),
+            },
+            ListedRoom {
+                token: "456".to_string(),
+                display_name: "Bert".to_string(),
+                ..Default::default()
+            },
+        ]);
+        assert!(!browser.needs_search());
+        assert_eq!(browser.selected_room(), Some(&"123".to_string()));
+        browser.select_down();
+        browser.select_down();
+        assert_eq!(browser.selected_room(), Some(&"456".to_string()));
+        browser.select_up();
+
+        let backend = TestBackend::new(30, 6);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| browser.render_area(frame, Rect::new(0, 0, 30, 6)))
+            .unwrap();
+
+        let mut expected = Buffer::with_lines([
+            "┌Public Rooms────────────────┐",
+            "│ Type a name and press Enter│",
+            "└────────────────────────────┘",
+            "Name      Description         ",
+            "Butz      All about Butz      ",
+            "Bert                          ",
+        ]);
+        expected.set_style(Rect::new(0, 0, 30, 3), config.theme.default_style());
+        expected.set_style(
+            Rect::new(1, 1, 1, 1),
+            config.theme.default_style().reversed(),
+        );
+        expected.set_style(Rect::new(0, 3, 30, 3), config.theme.default_style());
+        expected.set_style(Rect::new(0, 3, 30, 1), config.theme.table_header_style());
+        expected.set_style(
+            Rect::new(0, 4, 30, 1),
+            config.theme.default_highlight_style(),
+        );
+
+        terminal.backend().assert_buffer(&expected);
+
+        browser.input(KeyEvent::from(crossterm::event::KeyCode::Char('b')));
+        assert!(browser.needs_search());
+
+        browser.reset();
+        assert!(browser.search_text().is_empty());
+        assert!(browser.selected_room().is_none());
+    }
+}
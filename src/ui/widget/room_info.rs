@@ -0,0 +1,177 @@
+use crate::backend::ChatRoom;
+use crate::config::Config;
+use crate::ui::widget::room_settings::expiration_text;
+use chrono::{DateTime, Local, Utc};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Cell, Row, Table},
+};
+use std::convert::TryFrom;
+
+/// Popup with details on the current room, including when it was last synced with the server.
+pub struct RoomInfo {
+    rows: Vec<(&'static str, String)>,
+    title: String,
+    default_style: Style,
+    popup_border_style: Style,
+    table_header_style: Style,
+}
+
+/// Describe when a room was last synced, `now` and `last_synced_at` are unix timestamps.
+pub fn last_synced_text(last_synced_at: i64, now: i64) -> String {
+    match DateTime::<Utc>::from_timestamp(last_synced_at, 0) {
+        Some(time) if last_synced_at > 0 => format!(
+            "{} ({}s ago)",
+            DateTime::<Local>::from(time).format("%H:%M:%S"),
+            (now - last_synced_at).max(0)
+        ),
+        _ => "never".to_string(),
+    }
+}
+
+impl RoomInfo {
+    pub fn new(config: &Config) -> Self {
+        RoomInfo {
+            rows: vec![],
+            title: String::new(),
+            default_style: config.theme.default_style(),
+            popup_border_style: config.theme.popup_border_style(),
+            table_header_style: config.theme.table_header_style(),
+        }
+    }
+
+    /// Collect the details of the room, `now` is the current unix timestamp.
+    /// `notifications_available` is false without a notification service.
+    pub fn update(&mut self, room: &impl ChatRoom, notifications_available: bool, now: i64) {
+        self.title = room.get_display_name().to_string();
+        self.rows = vec![
+            ("Type", room.get_room_type().to_string()),
+            ("Description", room.get_description().replace('\n', " ")),
+            ("Participants", room.get_users().len().to_string()),
+            ("Unread", room.get_unread().to_string()),
+            (
+                "Mention @all",
+                if room.is_mention_all_restricted() {
+                    "moderators only".to_string()
+                } else {
+                    "everyone".to_string()
+                },
+            ),
+            (
+                "Expire after",
+                expiration_text(room.get_message_expiration()),
+            ),
+            (
+                "Notifications",
+                if notifications_available {
+                    "desktop".to_string()
+                } else {
+                    "no notification service".to_string()
+                },
+            ),
+            (
+                "Last synced",
+                last_synced_text(room.get_last_synced_at(), now),
+            ),
+        ];
+    }
+
+    pub fn height(&self) -> u16 {
+        u16::try_from(self.rows.len()).unwrap_or(u16::MAX) + 2
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(
+            Table::new(
+                self.rows.iter().map(|(key, value)| {
+                    Row::new([
+                        Cell::from(*key).style(self.table_header_style),
+                        Cell::from(value.clone()),
+                    ])
+                }),
+                [Constraint::Length(13), Constraint::Min(10)],
+            )
+            .style(self.default_style)
+            .block(
+                Block::bordered()
+                    .title(self.title.as_str())
+                    .border_style(self.popup_border_style),
+            ),
+            area,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::{chat_room::MockChatRoom, chat_types::RoomType};
+    use crate::config::init;
+    use backend::TestBackend;
+
+    use super::*;
+
+    #[test]
+    fn render() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut mock_room = MockChatRoom::new();
+        mock_room
+            .expect_get_display_name()
+            .return_const("General".to_string());
+        mock_room
+            .expect_get_room_type()
+            .return_const(RoomType::Group);
+        mock_room
+            .expect_get_description()
+            .return_const("All about\nButz".to_string());
+        mock_room.expect_get_users().return_const(vec![]);
+        mock_room.expect_get_unread().return_const(3_usize);
+        mock_room
+            .expect_is_mention_all_restricted()
+            .return_const(true);
+        mock_room
+            .expect_get_message_expiration()
+            .return_const(86_400);
+        mock_room.expect_get_last_synced_at().return_const(0);
+
+        let mut info = RoomInfo::new(&config);
+        info.update(&mock_room, false, 1000);
+        assert_eq!(info.height(), 10);
+
+        let backend = TestBackend::new(30, 10);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| info.render_area(frame, Rect::new(0, 0, 30, 10)))
+            .unwrap();
+
+        let mut expected = Buffer::with_lines([
+            "┌General─────────────────────┐",
+            "│Type          Group         │",
+            "│Description   All about Butz│",
+            "│Participants  0             │",
+            "│Unread        3             │",
+            "│Mention @all  moderators onl│",
+            "│Expire after  1 day         │",
+            "│Notifications no notificatio│",
+            "│Last synced   never         │",
+            "└────────────────────────────┘",
+        ]);
+        expected.set_style(Rect::new(0, 0, 30, 10), config.theme.popup_border_style());
+        expected.set_style(Rect::new(1, 1, 28, 8), config.theme.default_style());
+        expected.set_style(Rect::new(1, 1, 13, 8), config.theme.table_header_style());
+
+        terminal.backend().assert_buffer(&expected);
+
+        assert_eq!(
+            last_synced_text(940, 1000),
+            format!(
+                "{} (60s ago)",
+                DateTime::<Local>::from(DateTime::<Utc>::from_timestamp(940, 0).unwrap())
+                    .format("%H:%M:%S")
+            )
+        );
+    }
+}
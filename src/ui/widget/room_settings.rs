@@ -0,0 +1,396 @@
+use crate::config::Config;
+use crossterm::event::KeyEvent;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Paragraph},
+};
+use tui_textarea::TextArea;
+
+/// Message expiration choices of the Talk web UI in seconds, 0 keeps messages.
+pub const MESSAGE_EXPIRATIONS: [i32; 6] = [0, 3600, 28_800, 86_400, 604_800, 2_419_200];
+
+/// Describe after how long messages expire, in the largest whole unit.
+pub fn expiration_text(seconds: i32) -> String {
+    let (count, unit) = match seconds {
+        0 => return "off".to_string(),
+        s if s % 604_800 == 0 => (s / 604_800, "week"),
+        s if s % 86_400 == 0 => (s / 86_400, "day"),
+        s if s % 3600 == 0 => (s / 3600, "hour"),
+        s if s % 60 == 0 => (s / 60, "minute"),
+        s => (s, "second"),
+    };
+    if count == 1 {
+        format!("1 {unit}")
+    } else {
+        format!("{count} {unit}s")
+    }
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum Field {
+    Name,
+    Description,
+    Password,
+}
+
+/// Password and share link of a public room.
+struct PublicSettings<'a> {
+    link: String,
+    has_password: bool,
+    password: TextArea<'a>,
+    clear_password: bool,
+}
+
+/// Popup form to rename the current room, edit its description, who may mention @all
+/// and after how long messages expire.
+/// For public rooms it also manages the password and shows the share link.
+pub struct RoomSettings<'a> {
+    name: TextArea<'a>,
+    description: TextArea<'a>,
+    public: Option<PublicSettings<'a>>,
+    /// Name and description when the form was opened.
+    original: (String, String),
+    /// If @all mentions were restricted to moderators when the form was opened and now.
+    mention_all_restricted: (bool, bool),
+    /// Message expiration in seconds when the form was opened and now.
+    message_expiration: (i32, i32),
+    focus: Field,
+    default_style: Style,
+    default_highlight_style: Style,
+    popup_border_style: Style,
+}
+
+impl RoomSettings<'_> {
+    pub fn new(config: &Config) -> Self {
+        RoomSettings {
+            name: TextArea::default(),
+            description: TextArea::default(),
+            public: None,
+            original: (String::new(), String::new()),
+            mention_all_restricted: (false, false),
+            message_expiration: (0, 0),
+            focus: Field::Name,
+            default_style: config.theme.default_style(),
+            default_highlight_style: config.theme.default_highlight_style(),
+            popup_border_style: config.theme.popup_border_style(),
+        }
+    }
+
+    /// Fill the form with the current values of the room.
+    pub fn open(
+        &mut self,
+        name: &str,
+        description: &str,
+        mention_all_restricted: bool,
+        message_expiration: i32,
+    ) {
+        self.name = TextArea::new(vec![name.to_string()]);
+        self.name.move_cursor(tui_textarea::CursorMove::End);
+        self.description = TextArea::new(vec![description.replace('\n', " ")]);
+        self.description.move_cursor(tui_textarea::CursorMove::End);
+        self.original = (name.to_string(), description.to_string());
+        self.mention_all_restricted = (mention_all_restricted, mention_all_restricted);
+        self.message_expiration = (message_expiration, message_expiration);
+        self.public = None;
+        self.focus = Field::Name;
+        self.update_blocks();
+    }
+
+    /// Add the password field and share link of a public room to the form.
+    pub fn open_public(&mut self, link: String, has_password: bool) {
+        let mut password = TextArea::default();
+        password.set_mask_char('•');
+        self.public = Some(PublicSettings {
+            link,
+            has_password,
+            password,
+            clear_password: false,
+        });
+        self.update_blocks();
+    }
+
+    pub fn height(&self) -> u16 {
+        if self.public.is_some() {
+            14
+        } else {
+            10
+        }
+    }
+
+    pub fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Field::Name => Field::Description,
+            Field::Description if self.public.is_some() => Field::Password,
+            Field::Description | Field::Password => Field::Name,
+        };
+        self.update_blocks();
+    }
+
+    fn field_style(&self, field: Field) -> Style {
+        if self.focus == field {
+            self.default_highlight_style
+        } else {
+            self.default_style
+        }
+    }
+
+    fn update_blocks(&mut self) {
+        let name_style = self.field_style(Field::Name);
+        let description_style = self.field_style(Field::Description);
+        let password_style = self.field_style(Field::Password);
+        self.name
+            .set_block(Block::bordered().title("Name").border_style(name_style));
+        self.description.set_block(
+            Block::bordered()
+                .title("Description")
+                .border_style(description_style),
+        );
+        if let Some(public) = &mut self.public {
+            let title = if public.clear_password {
+                "Password (removed on save)"
+            } else if public.has_password {
+                "Password (set, Ctrl+x removes)"
+            } else {
+                "Password (none)"
+            };
+            public
+                .password
+                .set_block(Block::bordered().title(title).border_style(password_style));
+        }
+    }
+
+    pub fn input(&mut self, key: KeyEvent) {
+        match self.focus {
+            Field::Name => _ = self.name.input(key),
+            Field::Description => _ = self.description.input(key),
+            Field::Password => {
+                if let Some(public) = &mut self.public {
+                    public.clear_password = false;
+                    public.password.input(key);
+                }
+                self.update_blocks();
+            }
+        }
+    }
+
+    /// Switch between restricting @all mentions to moderators and allowing them for everyone.
+    pub fn toggle_mention_all_restricted(&mut self) {
+        self.mention_all_restricted.1 = !self.mention_all_restricted.1;
+    }
+
+    /// Get if @all mentions should be restricted to moderators, if it was changed.
+    pub fn changed_mention_all_restricted(&self) -> Option<bool> {
+        let (original, current) = self.mention_all_restricted;
+        (original != current).then_some(current)
+    }
+
+    /// Switch to the next message expiration choice, starting over after the longest.
+    /// Expirations set elsewhere, which are no choice here, continue with the first longer one.
+    pub fn cycle_message_expiration(&mut self) {
+        let current = self.message_expiration.1;
+        self.message_expiration.1 = MESSAGE_EXPIRATIONS
+            .iter()
+            .copied()
+            .find(|seconds| *seconds > current)
+            .unwrap_or(MESSAGE_EXPIRATIONS[0]);
+    }
+
+    /// Get the new message expiration in seconds, if it was changed.
+    pub fn changed_message_expiration(&self) -> Option<i32> {
+        let (original, current) = self.message_expiration;
+        (original != current).then_some(current)
+    }
+
+    /// Mark the password of a public room to be removed on save.
+    pub fn clear_password(&mut self) {
+        if let Some(public) = &mut self.public {
+            public.password = TextArea::default();
+            public.password.set_mask_char('•');
+            public.clear_password = public.has_password;
+            self.update_blocks();
+        }
+    }
+
+    /// Get the share link of a public room.
+    pub fn link(&self) -> Option<&str> {
+        self.public.as_ref().map(|public| public.link.as_str())
+    }
+
+    /// Get the new password, empty if the password is to be removed.
+    pub fn changed_password(&self) -> Option<String> {
+        let public = self.public.as_ref()?;
+        let password = public.password.lines().join("");
+        if !password.is_empty() {
+            Some(password)
+        } else if public.clear_password {
+            Some(String::new())
+        } else {
+            None
+        }
+    }
+
+    /// Get the new name, if it was changed and is not empty.
+    pub fn changed_name(&self) -> Option<String> {
+        let name = self.name.lines().join("").trim().to_string();
+        (!name.is_empty() && name != self.original.0).then_some(name)
+    }
+
+    /// Get the new description, if it was changed.
+    pub fn changed_description(&self) -> Option<String> {
+        let description = self.description.lines().join("").trim().to_string();
+        (description != self.original.1).then_some(description)
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered()
+            .title("Room Settings")
+            .title_bottom("Tab switch field, Enter save, Esc cancel")
+            .border_style(self.popup_border_style)
+            .style(self.default_style);
+        let [name_area, description_area, mention_area, expiration_area, password_area, link_area] =
+            Layout::vertical([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(3),
+                Constraint::Length(1),
+            ])
+            .areas(block.inner(area));
+        frame.render_widget(block, area);
+        frame.render_widget(&self.name, name_area);
+        frame.render_widget(&self.description, description_area);
+        frame.render_widget(
+            Paragraph::new(format!(
+                "Ctrl+t toggles who may mention @all: {}",
+                if self.mention_all_restricted.1 {
+                    "moderators"
+                } else {
+                    "everyone"
+                }
+            )),
+            mention_area,
+        );
+        frame.render_widget(
+            Paragraph::new(format!(
+                "Ctrl+o changes when messages expire: {}",
+                expiration_text(self.message_expiration.1)
+            )),
+            expiration_area,
+        );
+        if let Some(public) = &self.public {
+            frame.render_widget(&public.password, password_area);
+            frame.render_widget(
+                Paragraph::new(format!("Ctrl+l copies {}", public.link)),
+                link_area,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::init;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    use super::*;
+
+    #[test]
+    fn edit_fields() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut settings = RoomSettings::new(&config);
+        settings.open("General", "Talk about things", false, 0);
+        assert_eq!(settings.changed_name(), None);
+        assert_eq!(settings.changed_description(), None);
+
+        settings.input(KeyEvent::new(KeyCode::Char('!'), KeyModifiers::NONE));
+        assert_eq!(settings.changed_name(), Some("General!".to_string()));
+        assert_eq!(settings.changed_description(), None);
+
+        settings.toggle_focus();
+        settings.input(KeyEvent::new(KeyCode::Char('?'), KeyModifiers::NONE));
+        assert_eq!(
+            settings.changed_description(),
+            Some("Talk about things?".to_string())
+        );
+
+        settings.toggle_focus();
+        for _ in 0.."General!".len() {
+            settings.input(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        }
+        assert_eq!(settings.changed_name(), None);
+        assert_eq!(settings.changed_password(), None);
+
+        assert_eq!(settings.changed_mention_all_restricted(), None);
+        settings.toggle_mention_all_restricted();
+        assert_eq!(settings.changed_mention_all_restricted(), Some(true));
+        settings.toggle_mention_all_restricted();
+        assert_eq!(settings.changed_mention_all_restricted(), None);
+    }
+
+    #[test]
+    fn message_expiration() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut settings = RoomSettings::new(&config);
+        settings.open("General", "", false, 28_800);
+        assert_eq!(settings.changed_message_expiration(), None);
+
+        settings.cycle_message_expiration();
+        assert_eq!(settings.changed_message_expiration(), Some(86_400));
+        for _ in 0..4 {
+            settings.cycle_message_expiration();
+        }
+        assert_eq!(settings.changed_message_expiration(), Some(3600));
+        settings.cycle_message_expiration();
+        assert_eq!(settings.changed_message_expiration(), None);
+
+        settings.open("General", "", false, 7200);
+        settings.cycle_message_expiration();
+        assert_eq!(settings.changed_message_expiration(), Some(28_800));
+
+        assert_eq!(expiration_text(0), "off");
+        assert_eq!(expiration_text(3600), "1 hour");
+        assert_eq!(expiration_text(28_800), "8 hours");
+        assert_eq!(expiration_text(2_419_200), "4 weeks");
+        assert_eq!(expiration_text(90), "90 seconds");
+    }
+
+    #[test]
+    fn public_room_password() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut settings = RoomSettings::new(&config);
+        settings.open("General", "", true, 0);
+        assert_eq!(settings.height(), 10);
+        assert_eq!(settings.link(), None);
+
+        settings.open_public("https://butz.com/call/123".to_string(), true);
+        assert_eq!(settings.height(), 14);
+        assert_eq!(settings.link(), Some("https://butz.com/call/123"));
+        assert_eq!(settings.changed_password(), None);
+
+        settings.toggle_focus();
+        settings.toggle_focus();
+        settings.input(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+        assert_eq!(settings.changed_password(), Some("x".to_string()));
+        assert_eq!(settings.changed_name(), None);
+
+        settings.clear_password();
+        assert_eq!(settings.changed_password(), Some(String::new()));
+
+        settings.toggle_focus();
+        settings.input(KeyEvent::new(KeyCode::Char('!'), KeyModifiers::NONE));
+        assert_eq!(settings.changed_name(), Some("General!".to_string()));
+    }
+}
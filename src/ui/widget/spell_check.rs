@@ -0,0 +1,253 @@
+use crate::config::Config;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, HighlightSpacing, Row, Table, TableState},
+};
+use spellbook::Dictionary;
+use std::{convert::TryFrom, error::Error, path::PathBuf};
+
+/// Folders searched for dictionaries given by their language only, like `en_US`.
+const DICTIONARY_DIRS: [&str; 3] = [
+    "/usr/share/hunspell",
+    "/usr/share/myspell",
+    "/usr/share/myspell/dicts",
+];
+/// Most suggestions offered for a misspelled word.
+const MAX_SUGGESTIONS: usize = 10;
+
+/// Words of the line worth checking with the column of their first char.
+/// Mentions, links, shortcodes, code and words with digits are left out.
+pub fn words(line: &str) -> Vec<(usize, &str)> {
+    let mut words = vec![];
+    let mut column = 0;
+    for token in line.split(' ') {
+        let skip = token.starts_with('@')
+            || token.starts_with(':')
+            || token.contains("://")
+            || token.contains('`');
+        if !skip {
+            let mut start = None;
+            for (offset, (i, c)) in token
+                .char_indices()
+                .chain(std::iter::once((token.len(), ' ')))
+                .enumerate()
+            {
+                match (start, c.is_alphanumeric() || c == '\'') {
+                    (None, true) => start = Some((offset, i)),
+                    (Some((start_column, start_index)), false) => {
+                        let word = &token[start_index..i];
+                        let trimmed = word.trim_start_matches('\'');
+                        let skipped = word.len() - trimmed.len();
+                        let trimmed = trimmed.trim_end_matches('\'');
+                        if !trimmed.is_empty() && !trimmed.chars().any(char::is_numeric) {
+                            words.push((column + start_column + skipped, trimmed));
+                        }
+                        start = None;
+                    }
+                    _ => (),
+                }
+            }
+        }
+        column += token.chars().count() + 1;
+    }
+    words
+}
+
+/// Checks words against hunspell dictionaries, a word is fine if one of them knows it.
+pub struct SpellChecker {
+    dictionaries: Vec<Dictionary>,
+}
+
+impl SpellChecker {
+    /// Load the dictionaries configured, none turn the spell checking off.
+    pub fn new(config: &Config) -> Result<Option<Self>, Box<dyn Error>> {
+        let names = &config.data.ui.spell_check_dictionaries;
+        if names.is_empty() {
+            return Ok(None);
+        }
+        let dictionaries = names
+            .iter()
+            .map(|name| SpellChecker::load(name))
+            .collect::<Result<_, _>>()?;
+        Ok(Some(SpellChecker { dictionaries }))
+    }
+
+    /// Load the `.aff` and `.dic` files at the path, or of the language in the system folders.
+    fn load(name: &str) -> Result<Dictionary, Box<dyn Error>> {
+        let base = if name.contains('/') {
+            PathBuf::from(name)
+        } else {
+            DICTIONARY_DIRS
+                .iter()
+                .map(|dir| PathBuf::from(dir).join(name))
+                .find(|path| path.with_extension("dic").is_file())
+                .ok_or_else(|| format!("Found no dictionary for '{name}'."))?
+        };
+        let read = |extension| {
+            let path = base.with_extension(extension);
+            std::fs::read_to_string(&path)
+                .map_err(|why| format!("Could not read {}: {why}", path.display()))
+        };
+        Ok(Dictionary::new(&read("aff")?, &read("dic")?)
+            .map_err(|why| format!("Could not load the dictionary '{name}': {why}"))?)
+    }
+
+    pub fn check(&self, word: &str) -> bool {
+        self.dictionaries
+            .iter()
+            .any(|dictionary| dictionary.check(word))
+    }
+
+    /// Words of the text none of the dictionaries know, each once.
+    pub fn misspelled<'t>(&self, lines: &'t [String]) -> Vec<&'t str> {
+        let mut misspelled: Vec<&str> = lines
+            .iter()
+            .flat_map(|line| words(line))
+            .map(|(_, word)| word)
+            .filter(|word| !self.check(word))
+            .collect();
+        misspelled.sort_unstable();
+        misspelled.dedup();
+        misspelled
+    }
+
+    /// Corrections of the word offered by the dictionaries, in their order.
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        let mut suggestions = vec![];
+        for dictionary in &self.dictionaries {
+            let mut found = vec![];
+            dictionary.suggest(word, &mut found);
+            for suggestion in found {
+                if !suggestions.contains(&suggestion) {
+                    suggestions.push(suggestion);
+                }
+            }
+        }
+        suggestions.truncate(MAX_SUGGESTIONS);
+        suggestions
+    }
+}
+
+/// Popup offering the corrections of a misspelled word in the input.
+pub struct SpellSuggestions {
+    word: String,
+    suggestions: Vec<String>,
+    state: TableState,
+    default_style: Style,
+    default_highlight_style: Style,
+    popup_border_style: Style,
+}
+
+impl SpellSuggestions {
+    pub fn new(config: &Config) -> Self {
+        SpellSuggestions {
+            word: String::new(),
+            suggestions: vec![],
+            state: TableState::default(),
+            default_style: config.theme.default_style(),
+            default_highlight_style: config.theme.default_highlight_style(),
+            popup_border_style: config.theme.popup_border_style(),
+        }
+    }
+
+    /// Offer the suggestions for the word, for reopening the popup.
+    pub fn reset(&mut self, word: &str, suggestions: Vec<String>) {
+        self.word = word.to_string();
+        self.state = TableState::default().with_selected((!suggestions.is_empty()).then_some(0));
+        self.suggestions = suggestions;
+    }
+
+    pub fn height(&self) -> u16 {
+        u16::try_from(self.suggestions.len().max(1)).unwrap_or(u16::MAX) + 2
+    }
+
+    pub fn select_up(&mut self) {
+        self.state.select_previous();
+    }
+
+    pub fn select_down(&mut self) {
+        if self.state.selected().unwrap_or(0) + 1 < self.suggestions.len() {
+            self.state.select_next();
+        }
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        self.suggestions
+            .get(self.state.selected()?)
+            .map(String::as_str)
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect) {
+        let rows = if self.suggestions.is_empty() {
+            vec![Row::new(["No suggestions"])]
+        } else {
+            self.suggestions
+                .iter()
+                .map(|suggestion| Row::new([suggestion.as_str()]))
+                .collect()
+        };
+        frame.render_stateful_widget(
+            Table::new(rows, [Constraint::Min(10)])
+                .block(
+                    Block::bordered()
+                        .title(format!("Spelling of '{}'", self.word))
+                        .title_bottom("Enter replace, Esc close")
+                        .border_style(self.popup_border_style),
+                )
+                .style(self.default_style)
+                .row_highlight_style(self.default_highlight_style)
+                .highlight_spacing(HighlightSpacing::Never),
+            area,
+            &mut self.state.clone(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::init;
+
+    use super::*;
+
+    #[test]
+    fn split_words() {
+        assert_eq!(
+            words("Hi @butz, it's 2nd: see https://x.org or :tada: 'quoted' don't"),
+            [
+                (0, "Hi"),
+                (10, "it's"),
+                (20, "see"),
+                (38, "or"),
+                (49, "quoted"),
+                (57, "don't")
+            ]
+        );
+        assert_eq!(words("über-cool"), [(0, "über"), (5, "cool")]);
+        assert!(words("").is_empty());
+    }
+
+    #[test]
+    fn check_and_suggest() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let mut config = init("./test/").unwrap();
+        assert!(SpellChecker::new(&config).unwrap().is_none());
+
+        config.data.ui.spell_check_dictionaries = vec!["./test/spelling".to_string()];
+        let checker = SpellChecker::new(&config).unwrap().unwrap();
+        assert!(checker.check("hello"));
+        assert!(checker.check("Hello"));
+        assert_eq!(
+            checker.misspelled(&["helo world".to_string(), "wrld helo".to_string()]),
+            ["helo", "wrld"]
+        );
+        assert_eq!(
+            checker.suggest("helo").first().map(String::as_str),
+            Some("hello")
+        );
+
+        config.data.ui.spell_check_dictionaries = vec!["xx_XX".to_string()];
+        assert!(SpellChecker::new(&config).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use crate::backend::chat_types::UserStatus;
+use crate::config::Config;
+use crossterm::event::KeyEvent;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Paragraph},
+};
+use tui_textarea::TextArea;
+
+/// Statuses the user can set by their name in the API, with their label.
+const STATUSES: [(&str, &str); 4] = [
+    ("online", "Online"),
+    ("away", "Away"),
+    ("dnd", "Do not disturb"),
+    ("invisible", "Invisible"),
+];
+
+#[derive(PartialEq, Clone, Copy)]
+enum Field {
+    Icon,
+    Message,
+}
+
+/// Popup form to set the status of the user and the custom status message with its emoji.
+pub struct StatusForm<'a> {
+    icon: TextArea<'a>,
+    message: TextArea<'a>,
+    /// Index into [`STATUSES`] when the form was opened and now.
+    status: (usize, usize),
+    /// Emoji and message when the form was opened.
+    original: (String, String),
+    focus: Field,
+    default_style: Style,
+    default_highlight_style: Style,
+    popup_border_style: Style,
+}
+
+impl StatusForm<'_> {
+    pub fn new(config: &Config) -> Self {
+        StatusForm {
+            icon: TextArea::default(),
+            message: TextArea::default(),
+            status: (0, 0),
+            original: (String::new(), String::new()),
+            focus: Field::Message,
+            default_style: config.theme.default_style(),
+            default_highlight_style: config.theme.default_highlight_style(),
+            popup_border_style: config.theme.popup_border_style(),
+        }
+    }
+
+    /// Fill the form with the current status of the user.
+    pub fn open(&mut self, current: &UserStatus) {
+        // Invisible users see themselves as offline.
+        let status = match current.status.as_str() {
+            "offline" => "invisible",
+            status => status,
+        };
+        let index = STATUSES
+            .iter()
+            .position(|(name, _)| *name == status)
+            .unwrap_or(0);
+        let icon = current.icon.clone().unwrap_or_default();
+        let message = current.message.clone().unwrap_or_default();
+        self.icon = TextArea::new(vec![icon.clone()]);
+        self.icon.move_cursor(tui_textarea::CursorMove::End);
+        self.message = TextArea::new(vec![message.clone()]);
+        self.message.move_cursor(tui_textarea::CursorMove::End);
+        self.status = (index, index);
+        self.original = (icon, message);
+        self.focus = Field::Message;
+        self.update_blocks();
+    }
+
+    pub fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Field::Icon => Field::Message,
+            Field::Message => Field::Icon,
+        };
+        self.update_blocks();
+    }
+
+    fn update_blocks(&mut self) {
+        let (icon_style, message_style) = if self.focus == Field::Icon {
+            (self.default_highlight_style, self.default_style)
+        } else {
+            (self.default_style, self.default_highlight_style)
+        };
+        self.icon
+            .set_block(Block::bordered().title("Emoji").border_style(icon_style));
+        self.message.set_block(
+            Block::bordered()
+                .title("Message")
+                .border_style(message_style),
+        );
+    }
+
+    pub fn input(&mut self, key: KeyEvent) {
+        match self.focus {
+            Field::Icon => _ = self.icon.input(key),
+            Field::Message => _ = self.message.input(key),
+        }
+    }
+
+    /// Switch to the next status, or the previous one if not `forward`.
+    pub fn cycle_status(&mut self, forward: bool) {
+        let current = self.status.1;
+        self.status.1 = if forward {
+            (current + 1) % STATUSES.len()
+        } else {
+            (current + STATUSES.len() - 1) % STATUSES.len()
+        };
+    }
+
+    /// Get the name of the new status, if it was changed.
+    pub fn changed_status(&self) -> Option<&'static str> {
+        let (original, current) = self.status;
+        (original != current).then_some(STATUSES[current].0)
+    }
+
+    /// Get the new message and emoji, if one of them was changed, both empty clear them.
+    /// The emoji can also be given by its shortcode, like `:palm_tree:`.
+    pub fn changed_message(&self) -> Option<(String, Option<String>)> {
+        let icon = self.icon.lines().join("").trim().to_string();
+        let message = self.message.lines().join("").trim().to_string();
+        if (&icon, &message) == (&self.original.0, &self.original.1) {
+            return None;
+        }
+        let icon = icon
+            .strip_prefix(':')
+            .and_then(|shortcode| shortcode.strip_suffix(':'))
+            .and_then(emojis::get_by_shortcode)
+            .map_or(icon.clone(), |emoji| emoji.as_str().to_string());
+        Some((message, (!icon.is_empty()).then_some(icon)))
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered()
+            .title("Status")
+            .title_bottom("Tab switch field, ↑/↓ status, Enter save, Esc cancel")
+            .border_style(self.popup_border_style)
+            .style(self.default_style);
+        let [status_area, text_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Length(3)])
+                .areas(block.inner(area));
+        let [icon_area, message_area] =
+            Layout::horizontal([Constraint::Length(16), Constraint::Fill(1)]).areas(text_area);
+        frame.render_widget(block, area);
+        frame.render_widget(
+            Paragraph::new(format!(
+                "↑/↓ changes the status: {}",
+                STATUSES[self.status.1].1
+            )),
+            status_area,
+        );
+        frame.render_widget(&self.icon, icon_area);
+        frame.render_widget(&self.message, message_area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::init;
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    use super::*;
+
+    #[test]
+    fn edit_status() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let current = UserStatus {
+            status: "away".to_string(),
+            message: Some("Lunch".to_string()),
+            icon: Some("🍕".to_string()),
+        };
+        let mut form = StatusForm::new(&config);
+        form.open(&current);
+        assert_eq!(form.changed_status(), None);
+        assert_eq!(form.changed_message(), None);
+
+        form.cycle_status(true);
+        assert_eq!(form.changed_status(), Some("dnd"));
+        form.cycle_status(false);
+        form.cycle_status(false);
+        assert_eq!(form.changed_status(), Some("online"));
+        form.cycle_status(false);
+        assert_eq!(form.changed_status(), Some("invisible"));
+
+        form.input(KeyEvent::new(KeyCode::Char('!'), KeyModifiers::NONE));
+        assert_eq!(
+            form.changed_message(),
+            Some(("Lunch!".to_string(), Some("🍕".to_string())))
+        );
+
+        form.toggle_focus();
+        form.input(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        for key in ":palm_tree:".chars() {
+            form.input(KeyEvent::new(KeyCode::Char(key), KeyModifiers::NONE));
+        }
+        assert_eq!(
+            form.changed_message(),
+            Some(("Lunch!".to_string(), Some("🌴".to_string())))
+        );
+
+        form.open(&UserStatus::default());
+        assert_eq!(form.changed_message(), None);
+        form.toggle_focus();
+        form.input(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+        assert_eq!(
+            form.changed_message(),
+            Some((String::new(), Some("x".to_string())))
+        );
+    }
+}
@@ -1,8 +1,13 @@
-use crate::backend::nc_request::Token;
-use crate::backend::nc_room::NCRoomInterface;
+use crate::backend::{
+    chat_types::{OutOfOffice, UserStatus},
+    ChatProvider, ChatRoom, Token,
+};
 use crate::config::Config;
-use crate::{backend::nc_talk::NCBackend, ui::app::CurrentScreen};
+use crate::ui::app::CurrentScreen;
+use crate::ui::widget::{room_badge::RoomBadge, room_settings::expiration_text};
 
+use chrono::{DateTime, Local};
+use itertools::Itertools;
 use num_traits::AsPrimitive as _;
 use ratatui::{
     prelude::*,
@@ -19,9 +24,21 @@ pub struct TitleBar<'a> {
     mode: String,
     unread: usize,
     unread_rooms: Text<'a>,
+    outbox_status: Option<String>,
+    /// Warnings and errors not seen in the log view yet.
+    log_status: Option<String>,
+    /// Name of the account shown, if there are several.
+    account: Option<String>,
+    /// Status of the user with the status message, shown at the right.
+    own_status: Option<Span<'static>>,
+    /// Direct message room and the note about the other user being out of office.
+    out_of_office: Option<(Token, String)>,
+    date_format: String,
+    call_style: Style,
     title_important_style: Style,
     title_style: Style,
     default_style: Style,
+    badge: RoomBadge,
 }
 
 impl TitleBar<'_> {
@@ -35,16 +52,24 @@ impl TitleBar<'_> {
             mode: initial_state.to_string(),
             unread: 0,
             unread_rooms: Text::raw(""),
+            outbox_status: None,
+            log_status: None,
+            account: None,
+            own_status: None,
+            out_of_office: None,
+            date_format: config.data.ui.date_format.clone(),
+            call_style: config.theme.title_important_style().bold(),
             title_important_style: config.theme.title_important_style().rapid_blink(),
             title_style: config.theme.title_status_style(),
             default_style: config.theme.default_style(),
+            badge: RoomBadge::new(config),
         }
     }
 
     pub fn update(
         &mut self,
         screen: CurrentScreen,
-        backend: &impl NCBackend,
+        backend: &impl ChatProvider,
         current_room: &Token,
     ) {
         self.mode = screen.to_string();
@@ -56,12 +81,12 @@ impl TitleBar<'_> {
             let user = room
                 .get_users()
                 .iter()
-                .find(|user| user.displayName == room.get_display_name());
+                .find(|user| user.display_name == room.get_display_name());
             if user.is_none() {
                 log::error!("Could not find user associated with this DM");
             }
             status = user.and_then(|user| user.status.clone());
-            status_text = user.and_then(|user| match (&user.statusIcon, &user.statusMessage) {
+            status_text = user.and_then(|user| match (&user.status_icon, &user.status_message) {
                 (None, None) => None,
                 (None, Some(msg)) => Some(msg.to_string()),
                 (Some(icon), None) => Some(icon.to_string()),
@@ -69,12 +94,19 @@ impl TitleBar<'_> {
             });
         }
         self.unread = room.get_unread();
+        // Rooms mentioning the user come first, marked with '@'.
         let unread_array: Vec<String> = backend
             .get_unread_rooms()
             .iter()
-            .map(|token| {
-                let room = backend.get_room(token);
-                format!("{room}: {}", room.get_unread())
+            .filter(|token| !backend.is_locally_muted(token))
+            .map(|token| backend.get_room(token))
+            .sorted_by_key(|room| !room.has_unread_mention())
+            .map(|room| {
+                if room.has_unread_mention() {
+                    format!("@{room}: {}", room.get_unread())
+                } else {
+                    format!("{room}: {}", room.get_unread())
+                }
             })
             .collect();
         self.unread_rooms = if unread_array.is_empty() {
@@ -88,22 +120,19 @@ impl TitleBar<'_> {
         } else {
             "Current: ".to_string()
         };
-        let room_style = if let Some(status) = &status {
-            match status.as_str() {
-                "away" => self.user_away_style,
-                "offline" => self.user_offline_style,
-                "dnd" => self.user_dnd_style,
-                "online" => self.user_online_style,
-                unknown => {
-                    log::debug!("Unknown Status {unknown}");
-                    self.default_style
-                }
-            }
-        } else {
-            self.title_style
-        };
+        let room_style = status
+            .as_deref()
+            .map_or(self.title_style, |status| self.status_style(status));
         let mut title_spans = vec![
+            Span::styled(
+                self.account
+                    .as_ref()
+                    .map_or(String::new(), |account| format!("{account} | ")),
+                self.title_style,
+            ),
             Span::styled(header, self.title_style),
+            self.badge.span(room_name),
+            Span::styled(" ", self.title_style),
             Span::styled(room_name.to_owned(), room_style),
         ];
 
@@ -111,9 +140,131 @@ impl TitleBar<'_> {
             let status_text = format!(" ({status_text})");
             title_spans.push(Span::styled(status_text, self.title_style));
         }
+        if let Some((_, note)) = self
+            .out_of_office
+            .as_ref()
+            .filter(|(token, _)| room.is_dm() && token == current_room)
+        {
+            title_spans.push(Span::styled(format!(" {note}"), self.user_away_style));
+        }
+        if room.get_message_expiration() > 0 {
+            title_spans.push(Span::styled(
+                format!(
+                    " ⏳ messages expire after {}",
+                    expiration_text(room.get_message_expiration())
+                ),
+                self.title_style,
+            ));
+        }
+        if room.has_call() {
+            title_spans.push(Span::styled(
+                format!(
+                    " 📞 call in progress ({} in call), 'C' to join",
+                    room.get_users_in_call()
+                ),
+                self.call_style,
+            ));
+        }
+        title_spans.extend(self.background_spans(backend));
         self.title = Line::from(title_spans);
     }
 
+    fn status_style(&self, status: &str) -> Style {
+        match status {
+            "away" => self.user_away_style,
+            "offline" | "invisible" => self.user_offline_style,
+            "dnd" => self.user_dnd_style,
+            "online" => self.user_online_style,
+            unknown => {
+                log::debug!("Unknown Status {unknown}");
+                self.default_style
+            }
+        }
+    }
+
+    /// Show the status of the user and the status message, `None` if it is unknown.
+    pub fn set_own_status(&mut self, status: Option<&UserStatus>) {
+        self.own_status = status.map(|status| {
+            let text = [status.icon.as_deref(), status.message.as_deref()]
+                .iter()
+                .flatten()
+                .filter(|part| !part.is_empty())
+                .join(" ");
+            let text = if text.is_empty() {
+                format!("● {} ", status.status)
+            } else {
+                format!("● {} ({text}) ", status.status)
+            };
+            Span::styled(text, self.status_style(&status.status))
+        });
+    }
+
+    /// Note that the other user of the direct message room is out of office, `None` if they are not.
+    pub fn set_out_of_office(&mut self, token: &Token, absence: Option<&OutOfOffice>) {
+        self.out_of_office = absence.map(|absence| {
+            let until = DateTime::from_timestamp(absence.end_date, 0)
+                .map(|end| {
+                    DateTime::<Local>::from(end)
+                        .format(&self.date_format)
+                        .to_string()
+                })
+                .unwrap_or_default();
+            let note = if absence.short_message.is_empty() {
+                format!("🏖 OOO until {until}")
+            } else {
+                format!("🏖 OOO until {until}: {}", absence.short_message)
+            };
+            (token.clone(), note)
+        });
+    }
+
+    /// Spans for what the backend is busy with in the background.
+    fn background_spans(&self, backend: &impl ChatProvider) -> Vec<Span<'static>> {
+        let mut spans = vec![];
+        if backend.is_offline() {
+            spans.push(Span::styled(
+                " ⚡ offline, showing cached messages, reconnecting…",
+                self.call_style,
+            ));
+        }
+        if let Some(progress) = backend.get_history_progress() {
+            spans.push(Span::styled(
+                format!(" ⟳ fetching history ({progress})"),
+                self.title_style,
+            ));
+        }
+        if let Some(wait) = backend.get_rate_limit() {
+            spans.push(Span::styled(
+                format!(" ⏸ rate limited, retrying in {}s", wait.as_secs().max(1)),
+                self.call_style,
+            ));
+        }
+        spans
+    }
+
+    /// Show how many sent messages are held back and can still be undone.
+    pub fn set_outbox_status(&mut self, pending: usize) {
+        self.outbox_status = match pending {
+            0 => None,
+            1 => Some("1 message pending, 'Z' to undo ".to_string()),
+            _ => Some(format!("{pending} messages pending, 'Z' to undo ")),
+        };
+    }
+
+    /// Show how many warnings and errors were logged since the log view was shown.
+    pub fn set_log_status(&mut self, problems: usize) {
+        self.log_status = match problems {
+            0 => None,
+            1 => Some("⚠ 1 problem, 'L' to show ".to_string()),
+            _ => Some(format!("⚠ {problems} problems, 'L' to show ")),
+        };
+    }
+
+    /// Show the name of the account in front of the room, `None` with a single account.
+    pub fn set_account(&mut self, account: Option<String>) {
+        self.account = account;
+    }
+
     pub fn render_area(&self, frame: &mut Frame, area: Rect) {
         frame.render_widget(self, area);
     }
@@ -138,19 +289,45 @@ impl Widget for &TitleBar<'_> {
             .block(title_block)
             .render(title_layout[0], buf);
 
+        let unread_area = if let Some(outbox_status) = &self.outbox_status {
+            let [outbox_area, unread_area] = Layout::horizontal([
+                Constraint::Length(outbox_status.len().as_()),
+                Constraint::Fill(1),
+            ])
+            .areas(title_layout[1]);
+            Paragraph::new(Text::styled(outbox_status.clone(), self.title_style))
+                .block(
+                    Block::default()
+                        .borders(Borders::BOTTOM)
+                        .style(self.default_style),
+                )
+                .render(outbox_area, buf);
+            unread_area
+        } else {
+            title_layout[1]
+        };
+
         let unread_block = Block::default()
             .borders(Borders::BOTTOM)
             .style(self.default_style);
 
         Paragraph::new(self.unread_rooms.clone())
             .block(unread_block)
-            .render(title_layout[1], buf);
+            .render(unread_area, buf);
 
         let mode_block = Block::default()
             .borders(Borders::BOTTOM)
             .style(self.default_style);
 
-        Paragraph::new(Text::styled(self.mode.clone(), self.title_style))
+        let mut mode = Line::default();
+        if let Some(log_status) = &self.log_status {
+            mode.push_span(Span::styled(log_status.clone(), self.call_style));
+        }
+        if let Some(own_status) = &self.own_status {
+            mode.push_span(own_status.clone());
+        }
+        mode.push_span(Span::styled(self.mode.clone(), self.title_style));
+        Paragraph::new(mode)
             .block(mode_block)
             .alignment(Alignment::Right)
             .render(title_layout[2], buf);
@@ -161,10 +338,12 @@ impl Widget for &TitleBar<'_> {
 mod tests {
 
     use crate::backend::{
-        nc_request::NCReqDataParticipants, nc_room::MockNCRoomInterface, nc_talk::MockNCTalk,
+        chat_room::MockChatRoom, chat_types::Participant, history_fetch::HistoryProgress,
+        nc_talk::MockNCTalk,
     };
     use crate::config::init;
     use backend::TestBackend;
+    use mockall::predicate::eq;
 
     use super::*;
 
@@ -179,14 +358,18 @@ mod tests {
         let backend = TestBackend::new(60, 3);
         let mut terminal = Terminal::new(backend).unwrap();
 
-        let mut mock_room = MockNCRoomInterface::new();
-        let mut dummy_user = NCReqDataParticipants::default();
-        dummy_user.displayName = "Butz".to_string();
-        dummy_user.status = Some("online".to_string());
-        dummy_user.statusMessage = Some("having fun".to_string());
+        let mut mock_room = MockChatRoom::new();
+        let dummy_user = Participant {
+            display_name: "Butz".to_string(),
+            status: Some("online".to_string()),
+            status_message: Some("having fun".to_string()),
+            ..Default::default()
+        };
         mock_room.expect_get_users().return_const(vec![dummy_user]);
         mock_room.expect_get_unread().return_const(42_usize);
         mock_room.expect_is_dm().return_const(true);
+        mock_room.expect_has_call().return_const(false);
+        mock_room.expect_get_message_expiration().return_const(0);
         mock_room
             .expect_get_display_name()
             .return_const("Butz".to_string());
@@ -198,6 +381,11 @@ mod tests {
             .expect_get_room()
             .once()
             .return_const(mock_room);
+        mock_nc_backend
+            .expect_get_history_progress()
+            .return_const(None);
+        mock_nc_backend.expect_get_rate_limit().return_const(None);
+        mock_nc_backend.expect_is_offline().return_const(false);
 
         let mut bar = TitleBar::new(CurrentScreen::Reading, &config);
         bar.update(CurrentScreen::Reading, &mock_nc_backend, &"123".to_string());
@@ -207,18 +395,211 @@ mod tests {
             .unwrap();
 
         let mut expected = Buffer::with_lines([
-            "Current(42): Butz (having fun)                       Reading",
+            "Current(42): BU Butz (having fun)                    Reading",
             "                                                            ",
             "────────────────────────────────────────────────────────────",
         ]);
         expected.set_style(Rect::new(0, 0, 60, 3), config.theme.default_style());
 
         expected.set_style(Rect::new(0, 0, 13, 1), config.theme.title_status_style());
-        expected.set_style(Rect::new(13, 0, 4, 1), config.theme.user_online_style());
-        expected.set_style(Rect::new(17, 0, 13, 1), config.theme.title_status_style());
+        expected.set_style(
+            Rect::new(13, 0, 2, 1),
+            RoomBadge::new(&config).span("Butz").style,
+        );
+        expected.set_style(Rect::new(15, 0, 1, 1), config.theme.title_status_style());
+        expected.set_style(Rect::new(16, 0, 4, 1), config.theme.user_online_style());
+        expected.set_style(Rect::new(20, 0, 13, 1), config.theme.title_status_style());
 
         expected.set_style(Rect::new(53, 0, 7, 1), config.theme.title_status_style());
 
         terminal.backend().assert_buffer(&expected);
     }
+
+    #[test]
+    fn call_banner() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut mock_nc_backend = MockNCTalk::new();
+        let mut mock_room = MockChatRoom::new();
+        mock_room.expect_get_unread().return_const(0_usize);
+        mock_room.expect_is_dm().return_const(false);
+        mock_room.expect_has_call().return_const(true);
+        mock_room.expect_get_users_in_call().return_const(2_usize);
+        mock_room.expect_get_message_expiration().return_const(3600);
+        mock_room
+            .expect_get_display_name()
+            .return_const("General".to_string());
+        mock_nc_backend
+            .expect_get_unread_rooms()
+            .once()
+            .return_const(vec!["456".to_string()]);
+        mock_nc_backend
+            .expect_is_locally_muted()
+            .with(eq("456".to_string()))
+            .once()
+            .return_const(true);
+        mock_nc_backend
+            .expect_get_room()
+            .once()
+            .return_const(mock_room);
+        mock_nc_backend
+            .expect_get_history_progress()
+            .return_const(None);
+        mock_nc_backend.expect_get_rate_limit().return_const(None);
+        mock_nc_backend.expect_is_offline().return_const(false);
+
+        let mut bar = TitleBar::new(CurrentScreen::Reading, &config);
+        bar.update(CurrentScreen::Reading, &mock_nc_backend, &"123".to_string());
+
+        assert_eq!(
+            bar.title.to_string(),
+            "Current: GE General ⏳ messages expire after 1 hour 📞 call in progress (2 in call), 'C' to join"
+        );
+        assert_eq!(
+            bar.title.spans.last().unwrap().style,
+            config.theme.title_important_style().bold()
+        );
+        assert_eq!(bar.unread_rooms, Text::raw(""));
+    }
+
+    #[test]
+    fn out_of_office() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let mut config = init("./test/").unwrap();
+        config.data.ui.date_format = "%Y-%m-%d".to_string();
+
+        let mut mock_nc_backend = MockNCTalk::new();
+        let mut mock_room = MockChatRoom::new();
+        mock_room.expect_get_users().return_const(vec![]);
+        mock_room.expect_get_unread().return_const(0_usize);
+        mock_room.expect_is_dm().return_const(true);
+        mock_room.expect_has_call().return_const(false);
+        mock_room.expect_get_message_expiration().return_const(0);
+        mock_room
+            .expect_get_display_name()
+            .return_const("Butz".to_string());
+        mock_nc_backend
+            .expect_get_unread_rooms()
+            .return_const(vec![]);
+        mock_nc_backend.expect_get_room().return_const(mock_room);
+        mock_nc_backend
+            .expect_get_history_progress()
+            .return_const(None);
+        mock_nc_backend.expect_get_rate_limit().return_const(None);
+        mock_nc_backend.expect_is_offline().return_const(false);
+
+        let absence = OutOfOffice {
+            end_date: chrono::Local::now().timestamp(),
+            short_message: "Vacation".to_string(),
+        };
+        let today = chrono::Local::now().format("%Y-%m-%d");
+        let mut bar = TitleBar::new(CurrentScreen::Reading, &config);
+        bar.set_out_of_office(&"123".to_string(), Some(&absence));
+        bar.update(CurrentScreen::Reading, &mock_nc_backend, &"123".to_string());
+        assert_eq!(
+            bar.title.to_string(),
+            format!("Current: BU Butz 🏖 OOO until {today}: Vacation")
+        );
+
+        // Only shown in the room it was fetched for.
+        bar.update(CurrentScreen::Reading, &mock_nc_backend, &"456".to_string());
+        assert_eq!(bar.title.to_string(), "Current: BU Butz");
+    }
+
+    #[test]
+    fn history_progress() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut mock_nc_backend = MockNCTalk::new();
+        let mut mock_room = MockChatRoom::new();
+        mock_room.expect_get_unread().return_const(0_usize);
+        mock_room.expect_is_dm().return_const(false);
+        mock_room.expect_has_call().return_const(false);
+        mock_room.expect_get_message_expiration().return_const(0);
+        mock_room
+            .expect_get_display_name()
+            .return_const("General".to_string());
+        mock_nc_backend
+            .expect_get_unread_rooms()
+            .once()
+            .return_const(vec![]);
+        mock_nc_backend
+            .expect_get_room()
+            .once()
+            .return_const(mock_room);
+        mock_nc_backend
+            .expect_get_history_progress()
+            .once()
+            .return_const(Some(HistoryProgress {
+                messages: 400,
+                ranges_done: 1,
+                ranges: 3,
+            }));
+        mock_nc_backend
+            .expect_get_rate_limit()
+            .once()
+            .return_const(Some(std::time::Duration::from_millis(4500)));
+        mock_nc_backend
+            .expect_is_offline()
+            .once()
+            .return_const(true);
+
+        let mut bar = TitleBar::new(CurrentScreen::Reading, &config);
+        bar.update(CurrentScreen::Reading, &mock_nc_backend, &"123".to_string());
+
+        assert_eq!(
+            bar.title.to_string(),
+            "Current: GE General ⚡ offline, showing cached messages, reconnecting… ⟳ fetching history (400 messages, 1/3 ranges) ⏸ rate limited, retrying in 4s"
+        );
+    }
+
+    #[test]
+    fn mentions_first() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let mut mock_nc_backend = MockNCTalk::new();
+        for (token, unread, mention) in [("123", 0_usize, false), ("a", 1, false), ("b", 2, true)] {
+            let mut mock_room = MockChatRoom::new();
+            mock_room.expect_get_unread().return_const(unread);
+            mock_room.expect_has_unread_mention().return_const(mention);
+            mock_room.expect_is_dm().return_const(false);
+            mock_room.expect_has_call().return_const(false);
+            mock_room.expect_get_message_expiration().return_const(0);
+            mock_room
+                .expect_get_display_name()
+                .return_const("General".to_string());
+            mock_nc_backend
+                .expect_get_room()
+                .with(eq(Token::from(token)))
+                .return_const(mock_room);
+        }
+        mock_nc_backend
+            .expect_get_unread_rooms()
+            .once()
+            .return_const(vec![Token::from("a"), Token::from("b")]);
+        mock_nc_backend
+            .expect_is_locally_muted()
+            .return_const(false);
+        mock_nc_backend
+            .expect_get_history_progress()
+            .return_const(None);
+        mock_nc_backend.expect_get_rate_limit().return_const(None);
+        mock_nc_backend.expect_is_offline().return_const(false);
+
+        let mut bar = TitleBar::new(CurrentScreen::Reading, &config);
+        bar.update(CurrentScreen::Reading, &mock_nc_backend, &"123".to_string());
+
+        assert_eq!(bar.unread_rooms.to_string(), "UNREAD: @Butz: 2, Butz: 1");
+    }
 }
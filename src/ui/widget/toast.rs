@@ -0,0 +1,196 @@
+use std::{
+    collections::VecDeque,
+    convert::TryFrom,
+    time::{Duration, Instant},
+};
+
+use crate::config::Config;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// Time a toast stays on screen.
+pub const TOAST_DURATION: Duration = Duration::from_secs(8);
+/// Most notification banners shown at once, further ones wait for them to expire.
+const MAX_BANNERS: usize = 3;
+
+/// Short lived note in the bottom right corner, over whatever is rendered there.
+/// Lines of a text are shown as separate lines of the toast.
+pub struct Toast {
+    texts: Vec<(String, Instant)>,
+    /// Texts waiting for a shown one to expire.
+    queue: VecDeque<String>,
+    max_shown: usize,
+    at_top: bool,
+    style: Style,
+}
+
+impl Toast {
+    pub fn new(config: &Config) -> Self {
+        Toast {
+            texts: vec![],
+            queue: VecDeque::new(),
+            max_shown: usize::MAX,
+            at_top: false,
+            style: config.theme.unread_message_style(),
+        }
+    }
+
+    /// Banners in the top right corner standing in for desktop notifications, at most
+    /// [`MAX_BANNERS`] at once.
+    pub fn banners(config: &Config) -> Self {
+        Toast {
+            max_shown: MAX_BANNERS,
+            at_top: true,
+            ..Toast::new(config)
+        }
+    }
+
+    /// Show `text` for [`TOAST_DURATION`] from `now`, below the toasts still shown.
+    /// Once as many toasts as allowed are shown it waits for one of them to expire.
+    pub fn show(&mut self, text: String, now: Instant) {
+        if self.texts.len() < self.max_shown {
+            self.texts.push((text, now + TOAST_DURATION));
+        } else {
+            self.queue.push_back(text);
+        }
+    }
+
+    /// Drop all toasts shown for long enough, showing waiting ones in their place.
+    pub fn expire(&mut self, now: Instant) {
+        self.texts.retain(|(_, until)| *until > now);
+        while self.texts.len() < self.max_shown {
+            let Some(text) = self.queue.pop_front() else {
+                break;
+            };
+            self.texts.push((text, now + TOAST_DURATION));
+        }
+    }
+
+    /// Time until the next toast has to disappear, `None` if none is shown.
+    pub fn time_to_next_expiry(&self, now: Instant) -> Option<Duration> {
+        self.texts
+            .iter()
+            .map(|(_, until)| until.saturating_duration_since(now))
+            .min()
+    }
+
+    pub fn render_area(&self, frame: &mut Frame, area: Rect) {
+        if self.texts.is_empty() {
+            return;
+        }
+        let lines: Vec<Line> = self
+            .texts
+            .iter()
+            .flat_map(|(text, _)| text.lines())
+            .map(Line::from)
+            .collect();
+        let width = lines
+            .iter()
+            .map(Line::width)
+            .max()
+            .unwrap_or_default()
+            .saturating_add(2);
+        let width = u16::try_from(width).unwrap_or(u16::MAX).min(area.width);
+        let height = u16::try_from(lines.len() + 2)
+            .unwrap_or(u16::MAX)
+            .min(area.height);
+        let top = if self.at_top {
+            area.top()
+        } else {
+            area.bottom() - height
+        };
+        let toast_area = Rect::new(area.right() - width, top, width, height);
+        frame.render_widget(Clear, toast_area);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .style(self.style)
+                .block(Block::default().borders(Borders::ALL)),
+            toast_area,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::init;
+    use backend::TestBackend;
+
+    use super::*;
+
+    #[test]
+    fn render() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let now = Instant::now();
+        let mut toast = Toast::new(&config);
+        assert_eq!(toast.time_to_next_expiry(now), None);
+        toast.show("Bert added you to General".to_string(), now);
+        assert_eq!(toast.time_to_next_expiry(now), Some(TOAST_DURATION));
+
+        let backend = TestBackend::new(40, 5);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| toast.render_area(frame, Rect::new(0, 0, 40, 5)))
+            .unwrap();
+
+        let mut expected = Buffer::with_lines([
+            "                                        ",
+            "                                        ",
+            "             ┌─────────────────────────┐",
+            "             │Bert added you to General│",
+            "             └─────────────────────────┘",
+        ]);
+        expected.set_style(Rect::new(13, 2, 27, 3), config.theme.unread_message_style());
+
+        terminal.backend().assert_buffer(&expected);
+
+        toast.expire(now + TOAST_DURATION);
+        assert_eq!(toast.time_to_next_expiry(now), None);
+    }
+
+    #[test]
+    fn banners() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let now = Instant::now();
+        let mut banners = Toast::banners(&config);
+        for room in ["General", "Bots", "Off Topic", "Random"] {
+            banners.show(format!("Unread: {room}\n2 new"), now);
+        }
+        assert_eq!(banners.texts.len(), MAX_BANNERS);
+        assert_eq!(banners.queue.len(), 1);
+
+        let later = now + TOAST_DURATION;
+        banners.expire(later);
+        assert_eq!(
+            banners.texts,
+            [("Unread: Random\n2 new".to_string(), later + TOAST_DURATION)]
+        );
+        assert!(banners.queue.is_empty());
+
+        let backend = TestBackend::new(20, 5);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| banners.render_area(frame, Rect::new(0, 0, 20, 5)))
+            .unwrap();
+
+        let mut expected = Buffer::with_lines([
+            "    ┌──────────────┐",
+            "    │Unread: Random│",
+            "    │2 new         │",
+            "    └──────────────┘",
+            "                    ",
+        ]);
+        expected.set_style(Rect::new(4, 0, 16, 4), config.theme.unread_message_style());
+
+        terminal.backend().assert_buffer(&expected);
+    }
+}
@@ -6,46 +6,79 @@ use ratatui::{
 };
 use style::Styled;
 
-use crate::backend::{nc_request::Token, nc_room::NCRoomInterface, nc_talk::NCBackend};
+use crate::backend::{ChatProvider, ChatRoom, Token};
 use crate::config::Config;
+use crate::ui::user_styles::UserStyles;
 
 pub struct Users<'a> {
     user_list: Vec<Row<'a>>,
+    /// Room the list was last updated for, the selection is kept while it stays the same.
+    room: Token,
+    /// Attendee id and display name of each row in `user_list`.
+    attendees: Vec<(i32, String)>,
     state: TableState,
+    /// Whether the sidebar has the keyboard focus.
+    pub focused: bool,
     default_style: Style,
+    default_highlight_style: Style,
     user_away_style: Style,
     user_dnd_style: Style,
     user_online_style: Style,
     user_offline_style: Style,
     table_header_style: Style,
+    /// Colors of the names, the status is then shown by a dot in front of them.
+    user_styles: Option<UserStyles>,
 }
 
 impl Users<'_> {
     pub fn new(config: &Config) -> Self {
         Users {
             user_list: vec![],
+            room: Token::new(),
+            attendees: vec![],
             state: TableState::default().with_offset(0).with_selected(0),
+            focused: false,
             default_style: config.theme.default_style(),
+            default_highlight_style: config.theme.default_highlight_style(),
             user_away_style: config.theme.user_away_style(),
             user_dnd_style: config.theme.user_dnd_style(),
             user_online_style: config.theme.user_online_style(),
             user_offline_style: config.theme.user_offline_style(),
             table_header_style: config.theme.table_header_style(),
+            user_styles: config
+                .data
+                .ui
+                .colored_names
+                .then(|| UserStyles::load(&config.get_server_data_dir())),
         }
     }
     pub fn render_area(&self, frame: &mut Frame, area: Rect) {
         frame.render_stateful_widget(self, area, &mut self.state.clone());
     }
-    pub fn update(&mut self, backend: &impl NCBackend, current_room: &Token) {
-        self.user_list = backend
+    /// Rebuild the list, keeping the selected participant if the room did not change.
+    pub fn update(&mut self, backend: &impl ChatProvider, current_room: &Token) {
+        let same_room = self.room == *current_room;
+        let previous_attendee = self
+            .selected_attendee()
+            .map(|(attendee_id, _)| *attendee_id);
+        let previous_index = self.state.selected().unwrap_or(0);
+        let users = backend
             .get_room(current_room)
             .get_users()
             .iter()
-            .sorted_by(|user1, user2| user1.displayName.cmp(&user2.displayName))
+            .sorted_by(|user1, user2| user1.display_name.cmp(&user2.display_name))
+            .collect_vec();
+        self.attendees = users
+            .iter()
+            .map(|user| (user.attendee_id, user.display_name.clone()))
+            .collect();
+        self.user_list = users
+            .into_iter()
             .map(|user| {
-                Row::new([{
-                    if let Some(status) = &user.status {
-                        Cell::new(user.displayName.to_string()).set_style(match status.as_str() {
+                let status_style =
+                    user.status
+                        .as_deref()
+                        .map_or(self.default_style, |status| match status {
                             "away" => self.user_away_style,
                             "offline" => self.user_offline_style,
                             "dnd" => self.user_dnd_style,
@@ -54,15 +87,47 @@ impl Users<'_> {
                                 log::debug!("Unknown Status {unknown}");
                                 self.default_style
                             }
-                        })
-                    } else {
-                        Cell::new(user.displayName.to_string()).style(self.default_style)
-                    }
-                }])
+                        });
+                let cell = if let Some(styles) = self.user_styles.as_mut() {
+                    Cell::new(Line::from(vec![
+                        Span::styled("● ", status_style),
+                        Span::styled(user.display_name.clone(), styles.style_for(&user.actor_id)),
+                    ]))
+                } else {
+                    Cell::new(user.display_name.clone()).set_style(status_style)
+                };
+                Row::new([cell])
             })
             .collect();
 
-        self.state = TableState::default().with_offset(0).with_selected(0);
+        if same_room {
+            let index = previous_attendee
+                .and_then(|previous| {
+                    self.attendees
+                        .iter()
+                        .position(|(attendee_id, _)| *attendee_id == previous)
+                })
+                .unwrap_or_else(|| previous_index.min(self.attendees.len().saturating_sub(1)));
+            self.state.select(Some(index));
+        } else {
+            self.room.clone_from(current_room);
+            self.state = TableState::default().with_offset(0).with_selected(0);
+        }
+    }
+
+    pub fn select_up(&mut self) {
+        self.state.select_previous();
+    }
+
+    pub fn select_down(&mut self) {
+        if self.state.selected().unwrap_or(0) + 1 < self.attendees.len() {
+            self.state.select_next();
+        }
+    }
+
+    /// Get attendee id and display name of the selected user.
+    pub fn selected_attendee(&self) -> Option<&(i32, String)> {
+        self.attendees.get(self.state.selected()?)
     }
 }
 
@@ -75,7 +140,11 @@ impl StatefulWidget for &Users<'_> {
                 .style(self.default_style)
                 .header(Row::new(vec!["Users"]).style(self.table_header_style))
                 .block(Block::default())
-                .row_highlight_style(Style::new().bold())
+                .row_highlight_style(if self.focused {
+                    self.default_highlight_style
+                } else {
+                    Style::new().bold()
+                })
                 .highlight_spacing(HighlightSpacing::Never)
                 .highlight_symbol("")
                 .block(Block::new().borders(Borders::LEFT)),
@@ -89,9 +158,7 @@ impl StatefulWidget for &Users<'_> {
 #[cfg(test)]
 mod tests {
 
-    use crate::backend::{
-        nc_request::NCReqDataParticipants, nc_room::MockNCRoomInterface, nc_talk::MockNCTalk,
-    };
+    use crate::backend::{chat_room::MockChatRoom, chat_types::Participant, nc_talk::MockNCTalk};
     use crate::config::init;
     use backend::TestBackend;
 
@@ -109,9 +176,11 @@ mod tests {
         let mut terminal = Terminal::new(backend).unwrap();
         let mut users = Users::new(&config);
 
-        let mut mock_room = MockNCRoomInterface::new();
-        let mut dummy_user = NCReqDataParticipants::default();
-        dummy_user.displayName = "Butz".to_string();
+        let mut mock_room = MockChatRoom::new();
+        let dummy_user = Participant {
+            display_name: "Butz".to_string(),
+            ..Default::default()
+        };
         mock_room.expect_get_users().return_const(vec![dummy_user]);
         mock_nc_backend
             .expect_get_room()
@@ -149,4 +218,51 @@ mod tests {
 
         terminal.backend().assert_buffer(&expected);
     }
+
+    #[test]
+    fn keep_selection() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::env::set_var("HOME", dir.path().as_os_str());
+        let config = init("./test/").unwrap();
+
+        let participant = |attendee_id: i32, name: &str| Participant {
+            attendee_id,
+            display_name: name.to_string(),
+            ..Default::default()
+        };
+        let mut mock_nc_backend = MockNCTalk::new();
+        let mut first_room = MockChatRoom::new();
+        first_room
+            .expect_get_users()
+            .return_const(vec![participant(1, "Bert"), participant(2, "Butz")]);
+        let mut second_room = MockChatRoom::new();
+        second_room.expect_get_users().return_const(vec![
+            participant(3, "Anna"),
+            participant(1, "Bert"),
+            participant(2, "Butz"),
+        ]);
+        let mut sequence = mockall::Sequence::new();
+        mock_nc_backend
+            .expect_get_room()
+            .once()
+            .in_sequence(&mut sequence)
+            .return_const(first_room);
+        mock_nc_backend
+            .expect_get_room()
+            .times(2)
+            .in_sequence(&mut sequence)
+            .return_const(second_room);
+
+        let mut users = Users::new(&config);
+        users.update(&mock_nc_backend, &"123".to_string());
+        users.select_down();
+        assert_eq!(users.selected_attendee().unwrap().0, 2);
+
+        users.update(&mock_nc_backend, &"123".to_string());
+        assert_eq!(users.selected_attendee().unwrap().0, 2);
+
+        users.update(&mock_nc_backend, &"456".to_string());
+        assert_eq!(users.selected_attendee().unwrap().0, 3);
+    }
 }